@@ -1,87 +1,393 @@
-use similar::{ChangeTag, TextDiff};
+use similar::{ChangeTag, DiffOp, TextDiff};
+use std::io::{IsTerminal, Write};
 use std::path::Path;
 
+/// ANSI colors for deleted and inserted lines, hunk headers and file headings
+const DELETE_COLOR: &str = "31";
+const INSERT_COLOR: &str = "32";
+const HUNK_COLOR: &str = "36";
+const HEADING_COLOR: &str = "1";
+
+/// Width assumed when the terminal's can't be found, e.g. when piped
+const DEFAULT_WIDTH: usize = 120;
+
+/// Narrowest a side-by-side column gets, however narrow the terminal
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// Marks a line cut short to fit its column
+const TRUNCATION_MARKER: char = '…';
+
+/// How diffs are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffOptions {
+    /// Color deleted lines red and inserted ones green
+    pub color: bool,
+    /// Unchanged lines shown around each change
+    pub context: usize,
+    /// Columns a side-by-side diff may take
+    pub width: usize,
+}
+
+impl DiffOptions {
+    /// Options for a diff printed to stdout: as wide as the terminal, and
+    /// colored if `color` is set and stdout is a terminal
+    pub fn for_stdout(color: bool, context: usize) -> Self {
+        let width = crossterm::terminal::size()
+            .map(|(columns, _)| columns as usize)
+            .ok()
+            .filter(|&columns| columns > 0)
+            .unwrap_or(DEFAULT_WIDTH);
+        Self {
+            color: color && std::io::stdout().is_terminal(),
+            context,
+            width,
+        }
+    }
+
+    /// Wraps `text` in `color` if coloring is on
+    fn paint(&self, color: &str, text: &str) -> String {
+        if self.color {
+            format!("\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
 /// Prints a unified diff format showing the differences between old and new content
-pub fn print_unified_diff(file_path: &Path, old_content: &str, new_content: &str) {
+pub fn print_unified_diff(
+    file_path: &Path,
+    old_content: &str,
+    new_content: &str,
+    options: &DiffOptions,
+) {
+    // Where println! would panic on a closed stdout, the diff just ends
+    let _ = write_unified_diff(
+        &mut std::io::stdout().lock(),
+        file_path,
+        old_content,
+        new_content,
+        options,
+    );
+}
+
+/// Writes a unified diff of old and new content to `out`
+pub fn write_unified_diff(
+    out: &mut impl Write,
+    file_path: &Path,
+    old_content: &str,
+    new_content: &str,
+    options: &DiffOptions,
+) -> std::io::Result<()> {
     let diff = TextDiff::from_lines(old_content, new_content);
 
-    println!("--- {}", file_path.display());
-    println!("+++ {}", file_path.display());
+    let heading = |prefix: &str| {
+        options.paint(
+            HEADING_COLOR,
+            &format!("{} {}", prefix, file_path.display()),
+        )
+    };
+    writeln!(out, "{}", heading("---"))?;
+    writeln!(out, "{}", heading("+++"))?;
 
-    for group in diff.grouped_ops(3) {
+    for group in diff.grouped_ops(options.context) {
+        let (old_start, new_start) = (group[0].old_range().start, group[0].new_range().start);
         let (mut old_count, mut new_count) = (0, 0);
-        let first_op = &group[0];
-        let old_start = match first_op {
-            similar::DiffOp::Delete { old_index, .. }
-            | similar::DiffOp::Replace { old_index, .. }
-            | similar::DiffOp::Equal { old_index, .. } => *old_index,
-            similar::DiffOp::Insert { .. } => 0,
-        };
-        let new_start = match first_op {
-            similar::DiffOp::Insert { new_index, .. }
-            | similar::DiffOp::Replace { new_index, .. }
-            | similar::DiffOp::Equal { new_index, .. } => *new_index,
-            similar::DiffOp::Delete { .. } => 0,
-        };
-
-        for op in group.iter() {
-            match op {
-                similar::DiffOp::Delete { old_len, .. } => old_count += old_len,
-                similar::DiffOp::Insert { new_len, .. } => new_count += new_len,
-                similar::DiffOp::Replace {
-                    old_len, new_len, ..
-                } => {
-                    old_count += old_len;
-                    new_count += new_len;
-                }
-                similar::DiffOp::Equal { len, .. } => {
-                    old_count += len;
-                    new_count += len;
-                }
-            }
+        for op in &group {
+            old_count += op.old_range().len();
+            new_count += op.new_range().len();
         }
 
-        // Print hunk header
-        println!(
+        // Print hunk header. Ranges are 1-based, except that an empty one
+        // names the line before it, as in `diff -U0`
+        let line = |start: usize, count: usize| if count == 0 { start } else { start + 1 };
+        let header = format!(
             "@@ -{},{} +{},{} @@",
-            old_start + 1,
+            line(old_start, old_count),
             old_count,
-            new_start + 1,
+            line(new_start, new_count),
             new_count
         );
+        writeln!(out, "{}", options.paint(HUNK_COLOR, &header))?;
 
         // Print each line with a prefix, using iter_changes for line-based diffs
         for op in group {
             for change in diff.iter_changes(&op) {
-                match change.tag() {
-                    ChangeTag::Delete => print!("-{}", change.value()),
-                    ChangeTag::Insert => print!("+{}", change.value()),
-                    ChangeTag::Equal => print!(" {}", change.value()),
+                let (prefix, color) = match change.tag() {
+                    ChangeTag::Delete => ('-', Some(DELETE_COLOR)),
+                    ChangeTag::Insert => ('+', Some(INSERT_COLOR)),
+                    ChangeTag::Equal => (' ', None),
+                };
+                let value = change.value();
+                let (text, newline) = match value.strip_suffix('\n') {
+                    Some(text) => (text, "\n"),
+                    None => (value, ""),
+                };
+                let line = format!("{}{}", prefix, text);
+                match color {
+                    Some(color) => write!(out, "{}{}", options.paint(color, &line), newline)?,
+                    None => write!(out, "{}{}", line, newline)?,
                 }
             }
         }
     }
+    Ok(())
 }
 
-/// Prints a side-by-side diff showing only the changed lines
-pub fn print_side_by_side_diff(file_path: &Path, old_content: &str, new_content: &str) {
-    println!("In file: {}", file_path.display());
-    println!("(Side-by-side diff: only showing changed lines)\n");
+/// Prints a side-by-side diff of the changed lines and the context around them
+pub fn print_side_by_side_diff(
+    file_path: &Path,
+    old_content: &str,
+    new_content: &str,
+    options: &DiffOptions,
+) {
+    let _ = write_side_by_side_diff(
+        &mut std::io::stdout().lock(),
+        file_path,
+        old_content,
+        new_content,
+        options,
+    );
+}
 
+/// One side of a side-by-side row: a line number and the line, with `-` or
+/// `+` if it was deleted or inserted
+struct Cell<'a> {
+    number: usize,
+    text: &'a str,
+    tag: ChangeTag,
+}
+
+/// Writes a side-by-side diff of old and new content to `out`, old lines on
+/// the left and new ones on the right, each with its line number. The two
+/// columns share `options.width`, and lines too long for theirs are cut
+/// short with a marker.
+pub fn write_side_by_side_diff(
+    out: &mut impl Write,
+    file_path: &Path,
+    old_content: &str,
+    new_content: &str,
+    options: &DiffOptions,
+) -> std::io::Result<()> {
+    let diff = TextDiff::from_lines(old_content, new_content);
     let old_lines: Vec<&str> = old_content.lines().collect();
     let new_lines: Vec<&str> = new_content.lines().collect();
-    let max_len = old_lines.len().max(new_lines.len());
-
-    for i in 0..max_len {
-        let old_line = old_lines.get(i).unwrap_or(&"");
-        let new_line = new_lines.get(i).unwrap_or(&"");
-
-        if old_line != new_line {
-            let line_num = i + 1; // 1-based line numbering
-            println!("Line {}:", line_num);
-            println!("  OLD: {}", old_line);
-            println!("  NEW: {}", new_line);
-            println!();
+
+    // Each side is "<number> <mark> <text>", with " │ " between them
+    let digits = old_lines
+        .len()
+        .max(new_lines.len())
+        .max(1)
+        .to_string()
+        .len();
+    let column = (options.width.saturating_sub(2 * (digits + 3) + 3) / 2).max(MIN_COLUMN_WIDTH);
+
+    let cell = |cell: Option<Cell>, pad: bool| -> String {
+        let Some(cell) = cell else {
+            return if pad {
+                " ".repeat(digits + 3 + column)
+            } else {
+                String::new()
+            };
+        };
+        let (mark, color) = match cell.tag {
+            ChangeTag::Delete => ('-', Some(DELETE_COLOR)),
+            ChangeTag::Insert => ('+', Some(INSERT_COLOR)),
+            ChangeTag::Equal => (' ', None),
+        };
+        let text = fit(cell.text, column);
+        let text = if pad {
+            format!("{:<width$}", text, width = column)
+        } else {
+            text
+        };
+        let line = format!("{:>digits$} {} {}", cell.number, mark, text);
+        match color {
+            Some(color) => options.paint(color, &line),
+            None => line,
+        }
+    };
+
+    writeln!(
+        out,
+        "{}",
+        options.paint(HEADING_COLOR, &file_path.display().to_string())
+    )?;
+    for (i, group) in diff.grouped_ops(options.context).iter().enumerate() {
+        if i > 0 {
+            writeln!(out, "{:>digits$}", "...")?;
         }
+        for op in group {
+            let (old_range, new_range) = (op.old_range(), op.new_range());
+            let rows = match op {
+                DiffOp::Equal { .. } | DiffOp::Replace { .. } => {
+                    old_range.len().max(new_range.len())
+                }
+                DiffOp::Delete { .. } => old_range.len(),
+                DiffOp::Insert { .. } => new_range.len(),
+            };
+            let (old_tag, new_tag) = match op {
+                DiffOp::Equal { .. } => (ChangeTag::Equal, ChangeTag::Equal),
+                _ => (ChangeTag::Delete, ChangeTag::Insert),
+            };
+            for row in 0..rows {
+                let old = old_range
+                    .clone()
+                    .nth(row)
+                    .filter(|_| !matches!(op, DiffOp::Insert { .. }))
+                    .map(|index| Cell {
+                        number: index + 1,
+                        text: old_lines.get(index).copied().unwrap_or_default(),
+                        tag: old_tag,
+                    });
+                let new = new_range
+                    .clone()
+                    .nth(row)
+                    .filter(|_| !matches!(op, DiffOp::Delete { .. }))
+                    .map(|index| Cell {
+                        number: index + 1,
+                        text: new_lines.get(index).copied().unwrap_or_default(),
+                        tag: new_tag,
+                    });
+                let right = cell(new, false);
+                let row = format!("{} │ {}", cell(old, true), right);
+                writeln!(out, "{}", row.trim_end())?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `text` with tabs expanded, cut short with a marker if it is wider than
+/// `width` characters
+fn fit(text: &str, width: usize) -> String {
+    let text = text.trim_end_matches('\r').replace('\t', "    ");
+    if text.chars().count() <= width {
+        return text;
+    }
+    let mut fitted: String = text.chars().take(width.saturating_sub(1)).collect();
+    fitted.push(TRUNCATION_MARKER);
+    fitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OLD: &str = "fn main() {\n    let old_name = 1;\n    keep();\n    keep();\n    keep();\n    println!(\"{}\", old_name);\n}\n";
+    const NEW: &str = "fn main() {\n    let new_name = 1;\n    keep();\n    keep();\n    keep();\n    println!(\"{}\", new_name);\n}\n";
+
+    fn options(color: bool, context: usize, width: usize) -> DiffOptions {
+        DiffOptions {
+            color,
+            context,
+            width,
+        }
+    }
+
+    fn unified(old: &str, new: &str, options: DiffOptions) -> String {
+        let mut out = Vec::new();
+        write_unified_diff(&mut out, Path::new("src/main.rs"), old, new, &options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    fn side_by_side(old: &str, new: &str, options: DiffOptions) -> String {
+        let mut out = Vec::new();
+        write_side_by_side_diff(&mut out, Path::new("src/main.rs"), old, new, &options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_unified_diff_context() {
+        assert_eq!(
+            unified(OLD, NEW, options(false, 1, 80)),
+            "--- src/main.rs\n\
+             +++ src/main.rs\n\
+             @@ -1,3 +1,3 @@\n \
+             fn main() {\n\
+             -    let old_name = 1;\n\
+             +    let new_name = 1;\n     \
+             keep();\n\
+             @@ -5,3 +5,3 @@\n     \
+             keep();\n\
+             -    println!(\"{}\", old_name);\n\
+             +    println!(\"{}\", new_name);\n \
+             }\n"
+        );
+
+        // Enough context joins the two hunks
+        let joined = unified(OLD, NEW, options(false, 3, 80));
+        assert_eq!(joined.matches("@@").count(), 2);
+        assert!(joined.contains("@@ -1,7 +1,7 @@\n"));
+    }
+
+    #[test]
+    fn test_unified_diff_insertion_without_context() {
+        assert_eq!(
+            unified("a\nb\nc\n", "a\nb\nx\nc\n", options(false, 0, 80)),
+            "--- src/main.rs\n\
+             +++ src/main.rs\n\
+             @@ -2,0 +3,1 @@\n\
+             +x\n"
+        );
+        assert_eq!(
+            unified("a\nb\nc\n", "a\nc\n", options(false, 0, 80)),
+            "--- src/main.rs\n\
+             +++ src/main.rs\n\
+             @@ -2,1 +1,0 @@\n\
+             -b\n"
+        );
+    }
+
+    #[test]
+    fn test_unified_diff_colors() {
+        let diff = unified("a\nb\n", "a\nc\n", options(true, 0, 80));
+        assert_eq!(
+            diff,
+            "\x1b[1m--- src/main.rs\x1b[0m\n\
+             \x1b[1m+++ src/main.rs\x1b[0m\n\
+             \x1b[36m@@ -2,1 +2,1 @@\x1b[0m\n\
+             \x1b[31m-b\x1b[0m\n\
+             \x1b[32m+c\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_side_by_side_diff() {
+        assert_eq!(
+            side_by_side(OLD, NEW, options(false, 1, 60)),
+            "src/main.rs\n\
+             1   fn main() {              │ 1   fn main() {\n\
+             2 -     let old_name = 1;    │ 2 +     let new_name = 1;\n\
+             3       keep();              │ 3       keep();\n\
+             ...\n\
+             5       keep();              │ 5       keep();\n\
+             6 -     println!(\"{}\", old_… │ 6 +     println!(\"{}\", new_…\n\
+             7   }                        │ 7   }\n"
+        );
+    }
+
+    #[test]
+    fn test_side_by_side_uneven_changes() {
+        let diff = side_by_side("a\nb\nc\n", "a\nx\ny\nc\n", options(false, 0, 30));
+        assert_eq!(
+            diff,
+            concat!(
+                "src/main.rs\n",
+                "2 - b         │ 2 + x\n",
+                "              │ 3 + y\n",
+            )
+        );
+
+        // Colors leave the layout alone
+        let colored = side_by_side("a\n", "b\n", options(true, 0, 30));
+        assert!(colored.contains("\x1b[31m1 - a        \x1b[0m │ \x1b[32m1 + b\x1b[0m"));
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("short", 8), "short");
+        assert_eq!(fit("much too long", 8), "much to…");
+        assert_eq!(fit("\tx\r", 8), "    x");
     }
 }
@@ -1,18 +1,36 @@
-use std::io::Write;
+use std::io::{IsTerminal, Read, Write};
 use std::num::NonZeroUsize;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
 
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
 use rustscout::{
-    cache::ChangeDetectionStrategy,
-    config::{EncodingMode, SearchConfig},
-    errors::SearchError,
+    cache::{CacheFormat, ChangeDetectionStrategy},
+    config::{
+        load_profile, parse_size, parse_time, EncodingMode, OutputMode, PartialSearchConfig,
+        Schedule, SearchConfig, SearchConfigBuilder, SearchOverrides,
+    },
+    encoding::TextEncoding,
+    errors::{PatternError, SearchError},
+    filters::{FileTypes, IncludeGlobs},
     replace::{
-        FileReplacementPlan, ReplacementConfig, ReplacementPattern, ReplacementSet,
-        ReplacementTask, UndoInfo,
+        approve_replacements, read_backup, rename_paths, replace_in_text, DiffHunk,
+        FileReplacementPlan, HunkSelection, PrunePolicy, ReplacementConfig, ReplacementOverrides,
+        ReplacementPattern, ReplacementSet, ReplacementTask, UndoInfo,
+    },
+    results::{
+        report::{write_report, ReportFormat},
+        SearchStats,
     },
-    search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode},
-    Match,
+    search::matcher::{
+        per_pattern_values, CaseMode, HyphenMode, PatternDefinition, PatternMatcher,
+        WordBoundaryMode,
+    },
+    search::{CancellationToken, ProgressHook, SearchProgress},
+    workspace::{detect_workspace_root, is_workspace_path},
+    SearchResultType,
 };
 use tracing_subscriber::{self, EnvFilter};
 
@@ -28,7 +46,7 @@ type Result<T> = std::result::Result<T, SearchError>;
 Global Options:
   -h, --help        Print this help message
   -V, --version     Print the version of RustScout
-  -v, --verbosity <LEVEL>
+      --verbosity <LEVEL>
                     Set the global log level (error|warn|info|debug|trace)
                     (Defaults to 'info')
 
@@ -62,7 +80,7 @@ Examples:
   rustscout-cli workspace init --dir /my_project")]
 struct Cli {
     /// Set the global log level (error|warn|info|debug|trace)
-    #[arg(short = 'v', long = "verbosity", global = true, default_value = "info")]
+    #[arg(long = "verbosity", global = true, default_value = "info")]
     verbosity: String,
 
     #[command(subcommand)]
@@ -99,6 +117,7 @@ fn setup_logging(level: &str) -> Result<()> {
 
     tracing_subscriber::fmt()
         .with_env_filter(filter)
+        .with_writer(std::io::stderr)
         .try_init()
         .map_err(|e| SearchError::config_error(format!("Failed to initialize logging: {}", e)))?;
 
@@ -108,7 +127,16 @@ fn setup_logging(level: &str) -> Result<()> {
 #[derive(Subcommand, Debug)]
 enum ReplaceCommands {
     /// Perform a search/replace operation
-    Do(ReplaceDo),
+    Do(Box<ReplaceDo>),
+
+    /// Rename files and directories whose names match a pattern
+    Rename(ReplaceRename),
+
+    /// List recorded replacement operations that can be undone
+    List(ReplaceList),
+
+    /// Delete old undo operations and the backups only they need
+    Prune(ReplacePrune),
 
     /// Undo or partially revert a previous replacement operation
     Undo(ReplaceUndo),
@@ -163,6 +191,30 @@ struct CliSearchConfig {
     #[arg(hide = true)]
     legacy_patterns: Vec<String>,
 
+    /// Reads additional patterns from a file, one per line.
+    /// Blank lines and lines starting with # are ignored. Patterns are literal
+    /// unless -r true is also given, in which case each line is compiled as a regex
+    /// and an invalid one is reported with its line number.
+    /// Example: -f deprecated_apis.txt -p "legacy_call"
+    #[arg(
+        short = 'f',
+        long = "pattern-file",
+        value_name = "PATH",
+        help_heading = "Core Pattern Options"
+    )]
+    pattern_file: Option<PathBuf>,
+
+    /// Runs a named search from the `profiles:` section of the workspace's
+    /// .rustscout/config.yaml: its patterns, extensions and ignore patterns.
+    /// Patterns given with -p are searched for as well.
+    /// Example: --profile todo-audit -p "XXX"
+    #[arg(
+        long = "profile",
+        value_name = "NAME",
+        help_heading = "Core Pattern Options"
+    )]
+    profile: Option<String>,
+
     /// For the most recently specified --pattern, treat it as a regular expression (if true).
     /// Example:
     ///   rustscout-cli search -p "fn (\w+)\(\)" -r true
@@ -171,16 +223,18 @@ struct CliSearchConfig {
     #[arg(short = 'r', long = "regex", action = clap::ArgAction::Append, help_heading = "Core Pattern Options")]
     is_regex: Vec<bool>,
 
-    /// Word boundary mode for pattern matching
+    /// Word boundary mode (strict|partial|none, default none) for the most recently
+    /// specified --pattern. Patterns without their own -b inherit the last one given.
+    /// Example: -p foo -b strict -p bar -b none
     ///
-    ///   Tip: Use -w, --word-boundary as a shorthand for --boundary-mode strict
+    ///   Tip: Use -w, --word-boundary as a shorthand for --boundary-mode strict on every pattern
     #[arg(
         short = 'b',
         long = "boundary-mode",
-        default_value = "none",
+        action = clap::ArgAction::Append,
         help_heading = "Core Pattern Options"
     )]
-    boundary_mode: String,
+    boundary_mode: Vec<String>,
 
     /// Shorthand for --boundary-mode strict
     #[arg(
@@ -191,26 +245,82 @@ struct CliSearchConfig {
     )]
     word_boundary: bool,
 
-    /// Determines how hyphens are treated in word boundaries:
+    /// Determines how hyphens are treated in word boundaries, per pattern like -b:
     /// - boundary: Hyphens are considered separate boundaries
     /// - joining (default): Hyphens are treated as word characters, bridging word parts
     #[arg(
         short = 'y',
         long = "hyphen-mode",
-        default_value = "joining",
+        action = clap::ArgAction::Append,
         help_heading = "Core Pattern Options"
     )]
-    hyphen_mode: String,
+    hyphen_mode: Vec<String>,
 
-    /// Specifies the root directory to search in.
-    /// Default: Current directory (.)
+    /// Match all patterns case-insensitively.
+    /// Takes precedence over --smart-case when both are given.
     #[arg(
-        short = 'd',
-        long = "root",
-        default_value = ".",
-        help_heading = "File/Directory Options"
+        short = 'i',
+        long = "ignore-case",
+        help_heading = "Core Pattern Options"
     )]
-    root: PathBuf,
+    ignore_case: bool,
+
+    /// Case-insensitive if the pattern is all lowercase, case-sensitive otherwise.
+    /// Example: -p todo matches "TODO" and "todo", while -p TODO only matches "TODO".
+    /// For regex patterns only literal characters count; escapes like \W are ignored.
+    #[arg(long = "smart-case", help_heading = "Core Pattern Options")]
+    smart_case: bool,
+
+    /// Let regex patterns match across line boundaries.
+    /// In this mode `.` also matches newlines and ^/$ match at every line.
+    /// Example: -U -r true -p "fn \w+\(\)\s*\{\s*\}" finds empty functions spanning lines.
+    #[arg(short = 'U', long = "multiline", help_heading = "Core Pattern Options")]
+    multiline: bool,
+
+    /// Only match lines that consist entirely of the pattern (like grep -x).
+    /// Example: -X -p unsafe finds lines containing just `unsafe`.
+    #[arg(
+        short = 'X',
+        long = "line-regexp",
+        help_heading = "Core Pattern Options"
+    )]
+    line_regexp: bool,
+
+    /// With --line-regexp, ignore leading and trailing spaces and tabs on each line.
+    #[arg(
+        long = "trim-line",
+        requires = "line_regexp",
+        help_heading = "Core Pattern Options"
+    )]
+    trim_line: bool,
+
+    /// Report overlapping occurrences too, e.g. -p aa finds 3 matches in "aaaa".
+    #[arg(long = "overlapping", help_heading = "Core Pattern Options")]
+    overlapping: bool,
+
+    /// Match literal patterns approximately, allowing up to N character edits
+    /// (substitutions, insertions, deletions). Example: --fuzzy 1 -p separate finds "seperate".
+    #[arg(
+        long = "fuzzy",
+        value_name = "N",
+        help_heading = "Core Pattern Options"
+    )]
+    fuzzy: Option<u8>,
+
+    /// Print lines that do not match any pattern (like grep -v).
+    /// Each non-matching line counts as one match in the statistics.
+    #[arg(
+        short = 'v',
+        long = "invert-match",
+        help_heading = "Core Pattern Options"
+    )]
+    invert_match: bool,
+
+    /// Specifies the root directory to search in. Can be given multiple times;
+    /// files under overlapping roots are searched once. Use - to search stdin.
+    /// Default: Current directory (.), or stdin if it is piped
+    #[arg(short = 'd', long = "root", help_heading = "File/Directory Options")]
+    root: Vec<PathBuf>,
 
     /// Comma-separated list of file extensions to include.
     /// Example: -x rs,go,js
@@ -221,40 +331,442 @@ struct CliSearchConfig {
     )]
     extensions: Option<String>,
 
+    /// Comma-separated list of file extensions to skip. Extensions may
+    /// contain dots; files without an extension are never skipped by this.
+    /// Example: --exclude-ext min.js,lock
+    #[arg(
+        long = "exclude-ext",
+        value_name = "EXTENSIONS",
+        value_delimiter = ',',
+        help_heading = "File/Directory Options"
+    )]
+    exclude_ext: Option<Vec<String>>,
+
+    /// Only searches files matching this glob; can be given multiple times.
+    /// Globs with a slash match the path relative to the root, others just
+    /// the file name. Applied before -x and -g.
+    /// Example: --include-glob "src/**/*.rs"
+    #[arg(
+        long = "include-glob",
+        value_name = "GLOB",
+        help_heading = "File/Directory Options"
+    )]
+    include_glob: Vec<String>,
+
+    /// Only searches files of this type (e.g. rust, js, markdown); can be
+    /// given multiple times. Adds to -x: with both, files with a listed
+    /// extension or of a listed type are searched.
+    /// Example: -t rust -t toml
+    #[arg(
+        short = 't',
+        long = "type",
+        value_name = "NAME",
+        help_heading = "File/Directory Options"
+    )]
+    types: Vec<String>,
+
+    /// Skips files of this type; can be given multiple times
+    #[arg(
+        long = "type-not",
+        value_name = "NAME",
+        help_heading = "File/Directory Options"
+    )]
+    type_not: Vec<String>,
+
+    /// Defines a file type for this search as NAME:GLOB; adding to an
+    /// existing type extends it. Types can also be defined under `types:` in
+    /// .rustscout/config.yaml.
+    /// Example: --type-add 'proto:*.proto' -t proto
+    #[arg(
+        long = "type-add",
+        value_name = "NAME:GLOB",
+        help_heading = "File/Directory Options"
+    )]
+    type_add: Vec<String>,
+
+    /// Doesn't look at the #! line of extensionless files to match them
+    /// against -x and --type (e.g. a `deploy` script run by python is
+    /// searched with -t python by default)
+    #[arg(long = "no-sniff-shebang", help_heading = "File/Directory Options")]
+    no_sniff_shebang: bool,
+
+    /// Searches a file reached through several hard links (or bind mounts)
+    /// once per path instead of only once
+    #[arg(long = "no-dedupe-hardlinks", help_heading = "File/Directory Options")]
+    no_dedupe_hardlinks: bool,
+
+    /// Lists the known file types and their globs, then exits
+    #[arg(long = "type-list", help_heading = "File/Directory Options")]
+    type_list: bool,
+
     /// Defines ignore patterns (in glob format) for files or directories.
     /// Example: -g "**/node_modules/**" to skip node modules.
     #[arg(short = 'g', long = "ignore", help_heading = "File/Directory Options")]
     ignore: Vec<String>,
 
+    /// Also searches hidden files and directories (e.g. .github/, .env).
+    /// .git directories are still skipped unless --no-ignore is given.
+    #[arg(long = "hidden", help_heading = "File/Directory Options")]
+    hidden: bool,
+
+    /// Doesn't respect .gitignore/.ignore/.rustscoutignore files, and searches
+    /// inside .git directories when combined with --hidden. -g patterns still apply.
+    #[arg(long = "no-ignore", help_heading = "File/Directory Options")]
+    no_ignore: bool,
+
+    /// Doesn't respect .rustscoutignore files, but still .gitignore
+    #[arg(long = "no-rustscoutignore", help_heading = "File/Directory Options")]
+    no_rustscoutignore: bool,
+
+    /// Follows symbolic links. Link cycles are skipped, and a file reachable
+    /// through several links is reported once.
+    #[arg(short = 'L', long = "follow", help_heading = "File/Directory Options")]
+    follow: bool,
+
+    /// Skips files larger than this size. Accepts K, M and G suffixes.
+    /// Example: --max-filesize 10M
+    #[arg(
+        long = "max-filesize",
+        value_parser = parse_size,
+        help_heading = "File/Directory Options"
+    )]
+    max_filesize: Option<u64>,
+
+    /// Skips files that look minified, such as bundled *.min.js with one huge
+    /// line: lines in their first 64 KiB average over 1000 bytes. Files given
+    /// directly with -d are still searched.
+    #[arg(long = "skip-minified", help_heading = "File/Directory Options")]
+    skip_minified: bool,
+
+    /// Also searches the files inside .zip, .tar and .tar.gz archives,
+    /// reporting them as artifact.zip!/logs/app.log. -x, --type and ignore
+    /// patterns apply to member names, --max-filesize to each member.
+    /// Needs a build with the archives feature.
+    #[arg(long = "search-archives", help_heading = "File/Directory Options")]
+    search_archives: bool,
+
+    /// Only searches files modified more recently than this: an age such as
+    /// 2d, 6h or 30min, or a date such as 2024-01-01.
+    /// Example: --newer-than 2d
+    #[arg(
+        long = "newer-than",
+        value_name = "AGE|DATE",
+        value_parser = parse_time_arg,
+        help_heading = "File/Directory Options"
+    )]
+    newer_than: Option<SystemTime>,
+
+    /// Only searches files last modified before this age or date.
+    /// Example: --older-than 2024-01-01
+    #[arg(
+        long = "older-than",
+        value_name = "AGE|DATE",
+        value_parser = parse_time_arg,
+        help_heading = "File/Directory Options"
+    )]
+    older_than: Option<SystemTime>,
+
+    /// Only searches files of at least this size. Accepts K, M and G suffixes.
+    /// Example: --min-size 1k
+    #[arg(
+        long = "min-size",
+        value_parser = parse_size,
+        help_heading = "File/Directory Options"
+    )]
+    min_size: Option<u64>,
+
+    /// Only searches files of at most this size, counting them as filtered
+    /// rather than too large (see --max-filesize).
+    /// Example: --max-size 5M
+    #[arg(
+        long = "max-size",
+        value_parser = parse_size,
+        help_heading = "File/Directory Options"
+    )]
+    max_size: Option<u64>,
+
+    /// Only reports matches on lines added or changed since this git ref, and
+    /// only searches the files it touched. Compares the working tree,
+    /// including uncommitted changes; untracked files are not searched.
+    /// Example: --diff-base origin/main
+    #[arg(
+        long = "diff-base",
+        value_name = "REF",
+        help_heading = "File/Directory Options"
+    )]
+    diff_base: Option<String>,
+
+    /// Descends at most this many directory levels below the root.
+    /// Example: --max-depth 1 searches only the root's own files
+    #[arg(long = "max-depth", help_heading = "File/Directory Options")]
+    max_depth: Option<usize>,
+
+    /// Searches only the files listed in FILE, one per line, instead of
+    /// walking the root. Use - to read the list from stdin.
+    /// Listed files still honour .gitignore and .rustscoutignore (see --no-ignore).
+    /// Example: git diff --name-only | rustscout-cli search -p TODO --files-from -
+    #[arg(long = "files-from", help_heading = "File/Directory Options")]
+    files_from: Option<String>,
+
+    /// Paths in the --files-from list are separated by NUL bytes instead of newlines
+    #[arg(short = '0', long = "null", help_heading = "File/Directory Options")]
+    null_separated: bool,
+
+    /// Fails on listed paths that don't exist instead of warning and skipping them
+    #[arg(long = "strict-paths", help_heading = "File/Directory Options")]
+    strict_paths: bool,
+
     /// Number of context lines before each match (default: 0)
     #[arg(
         short = 'B',
         long = "context-before",
-        default_value = "0",
         help_heading = "Match Output & Context"
     )]
-    context_before: usize,
+    context_before: Option<usize>,
 
     /// Number of context lines after each match (default: 0)
     /// When used together (like -B 2 -A 2), you get a small snippet of lines around each match—helpful for code review.
     #[arg(
         short = 'A',
         long = "context-after",
-        default_value = "0",
         help_heading = "Match Output & Context"
     )]
-    context_after: usize,
+    context_after: Option<usize>,
+
+    /// Number of context lines both before and after each match, like -B N -A N.
+    /// (-C stays the short form of --cache-path.)
+    #[arg(
+        long = "context",
+        value_name = "N",
+        conflicts_with_all = ["context_before", "context_after"],
+        help_heading = "Match Output & Context"
+    )]
+    context: Option<usize>,
 
     /// Show only statistics, not the actual matches.
     /// Perfect for counting how many files or lines matched without spamming the terminal.
     #[arg(short = 's', long = "stats", help_heading = "Match Output & Context")]
     stats: bool,
 
+    /// Print nothing and stop at the first match; only the exit status reports
+    /// whether anything matched. Overrides --stats and --format.
+    #[arg(short = 'q', long = "quiet", help_heading = "Match Output & Context")]
+    quiet: bool,
+
+    /// Show a progress bar on stderr while searching (also shown with --stats).
+    /// Only drawn when stderr is a terminal.
+    #[arg(long = "progress", help_heading = "Match Output & Context")]
+    progress: bool,
+
+    /// Output format: text (default), json (one JSON object per file with
+    /// matches, then a summary object), sarif (SARIF 2.1.0 for code scanning),
+    /// or csv/tsv (one row per match)
+    #[arg(
+        long = "format",
+        default_value = "text",
+        value_parser = ["text", "json", "sarif", "csv", "tsv"],
+        help_heading = "Match Output & Context"
+    )]
+    format: String,
+
+    /// Print a summary table instead of matches: "dirs" rolls match counts up
+    /// to directories (see --summary-depth), "files" ranks files by matches.
+    /// Works with --format text or json.
+    #[arg(
+        long = "summary",
+        value_name = "KIND",
+        value_parser = ["dirs", "files"],
+        help_heading = "Match Output & Context"
+    )]
+    summary: Option<String>,
+
+    /// Directory depth for --summary dirs, counted below the search root
+    /// (1 = the root's top-level directories)
+    #[arg(
+        long = "summary-depth",
+        value_name = "N",
+        default_value = "1",
+        help_heading = "Match Output & Context"
+    )]
+    summary_depth: usize,
+
+    /// Only show the first N rows of --summary
+    #[arg(
+        long = "top",
+        value_name = "N",
+        help_heading = "Match Output & Context"
+    )]
+    top: Option<usize>,
+
+    /// Write a shareable report of the matches to FILE instead of printing them
+    #[arg(
+        long = "report",
+        value_name = "FILE",
+        help_heading = "Match Output & Context"
+    )]
+    report: Option<PathBuf>,
+
+    /// Format of --report: markdown (default) or html
+    #[arg(
+        long = "report-format",
+        default_value = "markdown",
+        value_parser = ["markdown", "html"],
+        help_heading = "Match Output & Context"
+    )]
+    report_format: String,
+
+    /// Omit the header row from csv/tsv output
+    #[arg(long = "no-header", help_heading = "Match Output & Context")]
+    no_header: bool,
+
+    /// Write json, sarif, csv or tsv output to FILE instead of stdout
+    #[arg(
+        long = "output",
+        value_name = "FILE",
+        help_heading = "Match Output & Context"
+    )]
+    output: Option<PathBuf>,
+
+    /// Stop reporting matches in a file after NUM hits.
+    /// Example: -m 3 shows at most three matches per file.
+    #[arg(
+        short = 'm',
+        long = "max-count",
+        value_name = "NUM",
+        help_heading = "Match Output & Context"
+    )]
+    max_count: Option<usize>,
+
+    /// Only print the paths of files that contain a match, one per line.
+    /// Stops scanning each file at its first match.
+    #[arg(
+        short = 'l',
+        long = "files-with-matches",
+        conflicts_with_all = ["files_without_match", "count"],
+        help_heading = "Match Output & Context"
+    )]
+    files_with_matches: bool,
+
+    /// Only print the paths of files that contain no match, one per line.
+    #[arg(
+        long = "files-without-match",
+        conflicts_with = "count",
+        help_heading = "Match Output & Context"
+    )]
+    files_without_match: bool,
+
+    /// Print the number of matches per file as path:count.
+    #[arg(short = 'c', long = "count", help_heading = "Match Output & Context")]
+    count: bool,
+
+    /// Print the 1-based column of the first match on each line (file:line:col:).
+    /// Columns count characters, not bytes.
+    #[arg(long = "column", help_heading = "Match Output & Context")]
+    column: bool,
+
+    /// Print only the matched text, one match per line (file:line:col:match).
+    /// --replace-output takes precedence when both are given.
+    #[arg(
+        short = 'o',
+        long = "only-matching",
+        help_heading = "Match Output & Context"
+    )]
+    only_matching: bool,
+
+    /// Shorten lines longer than NUM bytes to a window around the first match,
+    /// noting how many bytes were left out. 0 disables the limit.
+    #[arg(
+        long = "max-columns",
+        value_name = "NUM",
+        default_value = "4096",
+        help_heading = "Match Output & Context"
+    )]
+    max_columns: usize,
+
+    /// Print only a match count for lines longer than --max-columns instead of
+    /// a shortened preview
+    #[arg(long = "max-columns-preview", help_heading = "Match Output & Context")]
+    max_columns_preview: bool,
+
+    /// Print the file path once above its matches instead of on every line.
+    /// This is the default when stdout is a terminal.
+    #[arg(
+        long = "heading",
+        overrides_with = "no_heading",
+        help_heading = "Match Output & Context"
+    )]
+    heading: bool,
+
+    /// Prefix every line with the file path, even on a terminal
+    #[arg(
+        long = "no-heading",
+        overrides_with = "heading",
+        help_heading = "Match Output & Context"
+    )]
+    no_heading: bool,
+
+    /// Don't prefix output lines with the file path
+    #[arg(long = "no-filename", help_heading = "Match Output & Context")]
+    no_filename: bool,
+
+    /// Don't prefix output lines with the line number
+    #[arg(long = "no-line-number", help_heading = "Match Output & Context")]
+    no_line_number: bool,
+
+    /// Prefix each matching line with the pattern(s) that matched it.
+    /// Patterns are labelled by name when one is set in a config file, otherwise by their text.
+    /// With --stats, prints the number of matches per pattern.
+    #[arg(long = "show-pattern", help_heading = "Match Output & Context")]
+    show_pattern: bool,
+
+    /// Print each match formatted through TEMPLATE instead of the whole line.
+    /// Use $1 / ${1} for numbered groups, ${name} for named groups, $0 for the
+    /// whole match and $$ for a literal $. Literal patterns only have $0.
+    #[arg(
+        long = "replace-output",
+        value_name = "TEMPLATE",
+        help_heading = "Match Output & Context"
+    )]
+    replace_output: Option<String>,
+
+    /// Stop the whole search once NUM matches have been found.
+    #[arg(
+        long = "max-total",
+        value_name = "NUM",
+        help_heading = "Match Output & Context"
+    )]
+    max_total: Option<usize>,
+
     /// Number of threads to use for parallel searching.
     /// Defaults to the number of CPU cores.
     #[arg(short = 'j', long = "threads", help_heading = "Performance & Caching")]
     threads: Option<NonZeroUsize>,
 
+    /// Order in which files are handed to the threads: largest-first starts
+    /// big files early so they don't finish last on one core; discovery gives
+    /// each thread an equal run of files in path order. Output is in path
+    /// order either way. With --max-total and -j 1, discovery reports the
+    /// first files in path order; with more threads, which files make the
+    /// cut varies between runs.
+    #[arg(
+        long = "schedule",
+        value_name = "ORDER",
+        value_enum,
+        help_heading = "Performance & Caching"
+    )]
+    schedule: Option<ScheduleArg>,
+
+    /// Keeps match details for at most about this much memory; files found
+    /// past it are only counted. Accepts K, M and G suffixes.
+    /// Example: --memory-budget 512M
+    #[arg(
+        long = "memory-budget",
+        value_name = "SIZE",
+        value_parser = parse_size,
+        help_heading = "Performance & Caching"
+    )]
+    memory_budget: Option<u64>,
+
     /// Enable incremental search using a local cache of file checksums or Git metadata. This speeds up repeated searches.
     /// Combine with -C, -S, -M, -Z for advanced tuning.
     #[arg(
@@ -264,6 +776,15 @@ struct CliSearchConfig {
     )]
     incremental: bool,
 
+    /// Searches without the cache, even if the workspace config enables
+    /// incremental search
+    #[arg(
+        long = "no-incremental",
+        conflicts_with = "incremental",
+        help_heading = "Performance & Caching"
+    )]
+    no_incremental: bool,
+
     /// Specifies the path to the cache file (default: .rustscout-cache.json)
     #[arg(
         short = 'C',
@@ -279,10 +800,9 @@ struct CliSearchConfig {
     #[arg(
         short = 'S',
         long = "cache-strategy",
-        default_value = "auto",
         help_heading = "Performance & Caching"
     )]
-    cache_strategy: String,
+    cache_strategy: Option<String>,
 
     /// Limits the cache to <MB> megabytes. Use 0 for unlimited.
     #[arg(
@@ -300,16 +820,32 @@ struct CliSearchConfig {
     )]
     compress_cache: bool,
 
+    /// Stores the incremental cache uncompressed, even if the config asks
+    /// for compression
+    #[arg(
+        long = "no-compress-cache",
+        conflicts_with = "compress_cache",
+        help_heading = "Performance & Caching"
+    )]
+    no_compress_cache: bool,
+
+    /// How the incremental cache is stored: json (default) stays readable,
+    /// binary loads and saves faster on large trees and needs rustscout built
+    /// with the binary-cache feature. Either is read back.
+    #[arg(
+        long = "cache-format",
+        value_name = "FORMAT",
+        value_enum,
+        help_heading = "Performance & Caching"
+    )]
+    cache_format: Option<CacheFormatArg>,
+
     /// Controls how to handle invalid UTF-8 sequences:
     /// - failfast (default): Abort on invalid sequences
     /// - lossy: Replace invalid bytes with placeholders, continuing the search
-    #[arg(
-        short = 'E',
-        long = "encoding",
-        default_value = "failfast",
-        help_heading = "Miscellaneous"
-    )]
-    encoding: String,
+    /// - detect: Decode UTF-16 files with a byte-order mark, and non-UTF-8 files as Latin-1
+    #[arg(short = 'E', long = "encoding", help_heading = "Miscellaneous")]
+    encoding: Option<String>,
 
     /// Disables colored output. Handy for scripts or logs that don't support ANSI colors.
     #[arg(short = 'N', long = "no-color", help_heading = "Miscellaneous")]
@@ -330,26 +866,35 @@ Examples:
   # Regex replacement with capture groups
   rustscout-cli replace do -x --pattern 'fn (\\w+)\\(\\)' --replacement 'fn new_$1()' src/**/*.rs
 
+  # Several replacements in one pass
+  rustscout-cli replace do -p oldAPI -r newAPI -p legacyFunc -r modernFunc src/
+
   # Preview with side-by-side diffs
   rustscout-cli replace do -p HTTP -r HTTPS -n --diff-format side-by-side /var/www
 
   # Interactive approval with backups
   rustscout-cli replace do --pattern temp --replacement permanent --interactive --backup .")]
 struct ReplaceDo {
-    /// Text or pattern to search for
-    #[arg(short = 'p', long = "pattern", required = true, value_name = "PATTERN")]
+    /// Text or pattern to search for. Repeat with -r to replace several patterns in one
+    /// pass; patterns and replacements pair up by position
+    #[arg(
+        short = 'p',
+        long = "pattern",
+        required_unless_present = "config",
+        value_name = "PATTERN"
+    )]
     #[arg(help_heading = "Required Options")]
-    pattern: String,
+    pattern: Vec<String>,
 
-    /// Text to replace matches with
+    /// Text to replace matches of the pattern in the same position with
     #[arg(
         short = 'r',
         long = "replacement",
-        required = true,
+        required_unless_present = "config",
         value_name = "REPLACEMENT"
     )]
     #[arg(help_heading = "Required Options")]
-    replacement: String,
+    replacement: Vec<String>,
 
     /// Treat pattern as a regular expression
     #[arg(short = 'x', long = "regex")]
@@ -384,11 +929,48 @@ struct ReplaceDo {
     #[arg(help_heading = "General Options")]
     hyphen_mode: String,
 
+    /// Let a regex pattern match across line boundaries (`.` also matches newlines)
+    #[arg(short = 'U', long = "multiline")]
+    #[arg(help_heading = "General Options")]
+    multiline: bool,
+
+    /// Only replace lines that consist entirely of the pattern
+    #[arg(short = 'X', long = "line-regexp")]
+    #[arg(help_heading = "General Options")]
+    line_regexp: bool,
+
+    /// With --line-regexp, ignore leading and trailing spaces and tabs on each line
+    #[arg(long = "trim-line", requires = "line_regexp")]
+    #[arg(help_heading = "General Options")]
+    trim_line: bool,
+
+    /// Match the pattern in any case and give each replacement the case of the text it
+    /// replaces (color -> colour, Color -> Colour, COLOR -> COLOUR). Decided per match
+    #[arg(long = "preserve-case")]
+    #[arg(help_heading = "General Options")]
+    preserve_case: bool,
+
+    /// Replace at most N matches in each file, the first ones
+    #[arg(long = "max-per-file", value_name = "N")]
+    #[arg(help_heading = "General Options")]
+    max_per_file: Option<usize>,
+
+    /// Replace only the first match on each line
+    #[arg(long = "first-per-line")]
+    #[arg(help_heading = "General Options")]
+    first_per_line: bool,
+
     /// Load advanced configuration from a YAML/JSON file (e.g., multiple patterns, filtering rules)
     #[arg(short = 'c', long = "config", value_name = "FILE")]
     #[arg(help_heading = "General Options")]
     config: Option<PathBuf>,
 
+    /// Saves the configuration in effect, patterns and flags included, for reuse with -c.
+    /// Written as JSON if FILE ends in .json and as YAML otherwise
+    #[arg(long = "save-config", value_name = "FILE")]
+    #[arg(help_heading = "General Options")]
+    save_config: Option<PathBuf>,
+
     /// Shows what would be changed without modifying files. Great for previews
     #[arg(short = 'n', long = "dry-run")]
     #[arg(help_heading = "General Options")]
@@ -404,12 +986,42 @@ struct ReplaceDo {
     #[arg(help_heading = "General Options")]
     diff_format: String,
 
+    /// Unchanged lines shown around each change in the diffs
+    #[arg(long = "diff-context", default_value_t = 3, value_name = "N")]
+    #[arg(help_heading = "General Options")]
+    diff_context: usize,
+
+    /// Disables colored diffs. Diffs are only colored on a terminal anyway
+    #[arg(short = 'N', long = "no-color")]
+    #[arg(help_heading = "General Options")]
+    no_color: bool,
+
+    /// Output format: text (diffs, the default) or json (a report listing each file's
+    /// replacement count, bytes changed, backup and hunks, in place of the diffs)
+    #[arg(
+        long = "format",
+        default_value = "text",
+        value_parser = ["text", "json"],
+        value_name = "FORMAT"
+    )]
+    #[arg(help_heading = "General Options")]
+    format: String,
+
     /// Number of threads to use (default: CPU cores)
     #[arg(short = 'j', long = "threads", value_name = "N")]
     #[arg(help_heading = "General Options")]
     threads: Option<NonZeroUsize>,
 
-    /// Opens an interactive "approve or skip" TUI for each match. Perfect for selectively replacing matches in large codebases
+    /// How to read files (failfast|detect), failfast by default. With detect, UTF-16 files with
+    /// a byte-order mark and Latin-1 files are searched decoded and written back in their
+    /// original encoding
+    #[arg(short = 'E', long = "encoding", value_name = "MODE")]
+    #[arg(help_heading = "General Options")]
+    encoding: Option<String>,
+
+    /// Asks before each replacement, showing its line before and after. Answer y(es), n(o),
+    /// a(ll remaining), f (skip the rest of the file) or q(uit); only approved matches are
+    /// replaced and backed up
     #[arg(short = 'i', long = "interactive")]
     #[arg(help_heading = "Advanced Options")]
     interactive: bool,
@@ -419,32 +1031,196 @@ struct ReplaceDo {
     #[arg(help_heading = "Advanced Options")]
     backup: bool,
 
-    /// Keeps file permissions and timestamps intact after replacement
+    /// Makes no backups, even if the config file asks for them
+    #[arg(long = "no-backup", conflicts_with = "backup")]
+    #[arg(help_heading = "Advanced Options")]
+    no_backup: bool,
+
+    /// Writes backups to this directory instead of .rustscout/backups, creating it if needed.
+    /// Implies -B
+    #[arg(long = "backup-dir", value_name = "PATH", conflicts_with = "no_backup")]
+    #[arg(help_heading = "Advanced Options")]
+    backup_dir: Option<PathBuf>,
+
+    /// Keeps file permissions, access and modification times, and on Unix the owner (when
+    /// allowed) intact after replacement
     #[arg(short = 'm', long = "preserve-metadata")]
     #[arg(help_heading = "Advanced Options")]
     preserve_metadata: bool,
 
-    /// Additional filters or globs for included files. Handy if you specify large directories but only want certain file types
+    /// Doesn't restore file permissions, even if the config file asks for it
+    #[arg(long = "no-preserve-metadata", conflicts_with = "preserve_metadata")]
+    #[arg(help_heading = "Advanced Options")]
+    no_preserve_metadata: bool,
+
+    /// Only replace in files matching one of these comma-separated globs (e.g. "*.rs,docs/*.md").
+    /// Handy if you specify large directories but only want certain file types
     #[arg(short = 'f', long = "file-filter", value_name = "PATTERNS")]
     #[arg(help_heading = "Advanced Options")]
     file_filter: Option<String>,
 
+    /// Skips files or folders matching these globs when searching directories, on top of
+    /// .gitignore files and the workspace config's ignore patterns (e.g. "**/generated/**")
+    #[arg(short = 'g', long = "ignore", value_name = "GLOB")]
+    #[arg(help_heading = "Advanced Options")]
+    ignore: Vec<String>,
+
+    /// Reads text from stdin and writes it to stdout with the replacements made, like sed.
+    /// No files are touched, backed up or recorded for undo. With -n, the diff goes to stderr
+    /// and nothing is written to stdout
+    #[arg(long = "stdin", conflicts_with_all = ["paths", "interactive"])]
+    #[arg(help_heading = "Advanced Options")]
+    stdin: bool,
+
     /// One or more files, directories, or globs to process
-    #[arg(required = true, value_name = "PATHS")]
+    #[arg(required_unless_present = "stdin", value_name = "PATHS")]
     #[arg(help_heading = "Arguments")]
     paths: Vec<PathBuf>,
 }
 
-/// Revert all or part of a previous replacement operation. Supports listing hunks, partial revert, and interactive hunk selection.
+/// Rename files and directories whose names match a pattern, deepest first, with undo support.
 #[derive(Parser, Debug)]
-#[command(about = "Undo or partially revert a previous replacement operation")]
+#[command(about = "Rename files and directories whose names match a pattern")]
 #[command(
-    long_about = "Revert all or part of a previous replacement operation. Supports listing hunks, partial revert, and interactive hunk selection."
+    long_about = "Rename files and directories whose names match a pattern. Only names change, never contents. Hidden and ignored paths are skipped, nothing is renamed if a target already exists, and every rename is recorded so replace undo can reverse it."
 )]
 #[command(after_help = "\
 Examples:
-  # Full revert
-  rustscout-cli replace undo 1672834872
+  # Preview renaming a module's files
+  rustscout-cli replace rename -p old_service -r billing -n src/
+
+  # Regex rename with capture groups
+  rustscout-cli replace rename -x -p '^test_(\\w+)\\.rs$' -r '${1}_test.rs' tests/")]
+struct ReplaceRename {
+    /// Text or pattern to look for in names. Repeat with -r to apply several in one pass
+    #[arg(short = 'p', long = "pattern", required = true, value_name = "PATTERN")]
+    #[arg(help_heading = "Required Options")]
+    pattern: Vec<String>,
+
+    /// Text to replace matches of the pattern in the same position with
+    #[arg(
+        short = 'r',
+        long = "replacement",
+        required = true,
+        value_name = "REPLACEMENT"
+    )]
+    #[arg(help_heading = "Required Options")]
+    replacement: Vec<String>,
+
+    /// Treat pattern as a regular expression
+    #[arg(short = 'x', long = "regex")]
+    #[arg(help_heading = "General Options")]
+    is_regex: bool,
+
+    /// Only match whole words within names
+    #[arg(short = 'w', long = "word-boundary")]
+    #[arg(help_heading = "General Options")]
+    word_boundary: bool,
+
+    /// Shows the renames as old → new without making them
+    #[arg(short = 'n', long = "dry-run")]
+    #[arg(help_heading = "General Options")]
+    dry_run: bool,
+
+    /// Files to rename, or directories whose contents to rename
+    #[arg(required = true, value_name = "PATHS")]
+    #[arg(help_heading = "Arguments")]
+    paths: Vec<PathBuf>,
+}
+
+/// List the replacement operations recorded for undo, oldest first.
+#[derive(Parser, Debug)]
+#[command(about = "List recorded replacement operations that can be undone")]
+#[command(after_help = "\
+Examples:
+  # IDs, times and descriptions
+  rustscout-cli replace list
+
+  # With each operation's files and hunks
+  rustscout-cli replace list --verbose
+
+  # For scripts
+  rustscout-cli replace list --format json")]
+struct ReplaceList {
+    /// Also lists each operation's files, with their hunks, backups or renames
+    #[arg(long = "verbose")]
+    #[arg(help_heading = "Options")]
+    verbose: bool,
+
+    /// Output format: text (the default) or json (an array of operations)
+    #[arg(
+        long = "format",
+        default_value = "text",
+        value_parser = ["text", "json"],
+        value_name = "FORMAT"
+    )]
+    #[arg(help_heading = "Options")]
+    format: String,
+
+    /// Override the default .rustscout/undo path where backup data is stored
+    #[arg(long = "undo-dir", default_value = ".rustscout/undo")]
+    #[arg(value_name = "UNDO_DIR")]
+    #[arg(help_heading = "Options")]
+    undo_dir: PathBuf,
+}
+
+/// Delete undo operations beyond a retention limit, with the backups no other operation needs.
+#[derive(Parser, Debug)]
+#[command(about = "Delete old undo operations and the backups only they need")]
+#[command(after_help = "\
+Examples:
+  # Keep the 10 newest operations
+  rustscout-cli replace prune --keep-last 10
+
+  # See what a month's retention would remove
+  rustscout-cli replace prune --older-than 30d -n
+
+  # Cap the backups at 500 MiB, dropping the oldest first
+  rustscout-cli replace prune --max-total-size 500M")]
+#[command(group(
+    clap::ArgGroup::new("limits")
+        .required(true)
+        .multiple(true)
+        .args(["keep_last", "older_than", "max_total_size"])
+))]
+struct ReplacePrune {
+    /// Keeps only the N newest operations
+    #[arg(long = "keep-last", value_name = "N")]
+    #[arg(help_heading = "Options")]
+    keep_last: Option<usize>,
+
+    /// Removes operations older than this age, such as 30d or 12h
+    #[arg(long = "older-than", value_name = "AGE", value_parser = humantime::parse_duration)]
+    #[arg(help_heading = "Options")]
+    older_than: Option<std::time::Duration>,
+
+    /// Removes the oldest operations until the backups take at most this much (e.g. 500M)
+    #[arg(long = "max-total-size", value_name = "SIZE", value_parser = parse_size)]
+    #[arg(help_heading = "Options")]
+    max_total_size: Option<u64>,
+
+    /// Lists what would be removed without deleting anything
+    #[arg(short = 'n', long = "dry-run")]
+    #[arg(help_heading = "Options")]
+    dry_run: bool,
+
+    /// Override the default .rustscout/undo path where backup data is stored
+    #[arg(long = "undo-dir", default_value = ".rustscout/undo")]
+    #[arg(value_name = "UNDO_DIR")]
+    #[arg(help_heading = "Options")]
+    undo_dir: PathBuf,
+}
+
+/// Revert all or part of a previous replacement operation. Supports listing hunks, partial revert, and interactive hunk selection.
+#[derive(Parser, Debug)]
+#[command(about = "Undo or partially revert a previous replacement operation")]
+#[command(
+    long_about = "Revert all or part of a previous replacement operation. Supports listing hunks, partial revert, and interactive hunk selection."
+)]
+#[command(after_help = "\
+Examples:
+  # Full revert
+  rustscout-cli replace undo 1672834872
 
   # List hunks
   rustscout-cli replace undo 1672834872 --list-hunks
@@ -468,11 +1244,19 @@ struct ReplaceUndo {
     #[arg(help_heading = "Options")]
     list_hunks: bool,
 
-    /// Revert only these hunk indices (comma-separated). If omitted, reverts all hunks
+    /// Revert only these hunks: global indices as listed by --list-hunks (`0,1,3`), or
+    /// indices within the files matching a glob (`src/config.rs:0,2`). Repeatable
     #[arg(short = 'u', long = "hunks", value_name = "HUNKS")]
     #[arg(conflicts_with = "interactive")]
     #[arg(help_heading = "Options")]
-    hunks: Option<String>,
+    hunks: Vec<String>,
+
+    /// Revert every hunk in the files matching these globs (comma-separated). Combines
+    /// with --hunks
+    #[arg(long = "files", value_name = "GLOB", value_delimiter = ',')]
+    #[arg(conflicts_with = "interactive")]
+    #[arg(help_heading = "Options")]
+    files: Vec<String>,
 
     /// Shows the content that would be restored without changing files
     #[arg(short = 'p', long = "preview")]
@@ -481,11 +1265,12 @@ struct ReplaceUndo {
 
     /// Interactive "approve or skip" flow for each hunk, letting you partially revert
     #[arg(short = 'i', long = "interactive")]
-    #[arg(conflicts_with = "hunks")]
+    #[arg(conflicts_with_all = ["hunks", "files"])]
     #[arg(help_heading = "Options")]
     interactive: bool,
 
-    /// Skip all confirmations. Use with caution
+    /// Skip all confirmations, and undo even files edited after the operation (losing those
+    /// edits) or from backups that fail their checksum. Use with caution
     #[arg(short = 'f', long = "force", alias = "yes")]
     #[arg(help_heading = "Options")]
     force: bool,
@@ -559,6 +1344,16 @@ struct InteractiveSearchArgs {
     #[arg(hide = true)]
     legacy_patterns: Vec<String>,
 
+    /// Reads additional patterns from a file, one per line (blank lines and # comments are ignored).
+    /// Patterns are literal unless -r true is also given.
+    #[arg(
+        short = 'f',
+        long = "pattern-file",
+        value_name = "PATH",
+        help_heading = "Core Pattern Options"
+    )]
+    pattern_file: Option<PathBuf>,
+
     /// Toggles regex interpretation for the most recently added pattern.
     /// Default: false (treat pattern as literal)
     /// Example:
@@ -567,19 +1362,20 @@ struct InteractiveSearchArgs {
     #[arg(short = 'r', long = "regex", action = clap::ArgAction::Append, help_heading = "Core Pattern Options")]
     is_regex: Vec<bool>,
 
-    /// Controls word boundary matching:
+    /// Controls word boundary matching for the most recently added pattern:
     /// - strict: Only match entire words
     /// - partial: Loose boundary handling
     /// - none (default): No boundary constraint
     ///
+    /// Patterns without their own -b inherit the last one given.
     ///   Shorthand: -w, --word-boundary = --boundary-mode strict
     #[arg(
         short = 'b',
         long = "boundary-mode",
-        default_value = "none",
+        action = clap::ArgAction::Append,
         help_heading = "Core Pattern Options"
     )]
-    boundary_mode: String,
+    boundary_mode: Vec<String>,
 
     /// Shorthand for --boundary-mode strict
     #[arg(
@@ -590,15 +1386,28 @@ struct InteractiveSearchArgs {
     )]
     word_boundary: bool,
 
-    /// Defines how hyphens are treated in boundary detection (boundary or joining).
+    /// Defines how hyphens are treated in boundary detection (boundary or joining),
+    /// per pattern like -b.
     /// Default: joining (hyphens considered part of a word).
     #[arg(
         short = 'y',
         long = "hyphen-mode",
-        default_value = "joining",
+        action = clap::ArgAction::Append,
         help_heading = "Core Pattern Options"
     )]
-    hyphen_mode: String,
+    hyphen_mode: Vec<String>,
+
+    /// Match all patterns case-insensitively (overrides --smart-case).
+    #[arg(
+        short = 'i',
+        long = "ignore-case",
+        help_heading = "Core Pattern Options"
+    )]
+    ignore_case: bool,
+
+    /// Case-insensitive unless the pattern contains an uppercase character.
+    #[arg(long = "smart-case", help_heading = "Core Pattern Options")]
+    smart_case: bool,
 
     /// Specifies the root directory to search.
     /// Default: . (current directory)
@@ -679,6 +1488,7 @@ struct InteractiveSearchArgs {
     /// Specifies how to handle invalid UTF-8:
     /// - failfast (default)
     /// - lossy (replace invalid sequences)
+    /// - detect (decode UTF-16 with a byte-order mark, and Latin-1)
     #[arg(
         short = 'E',
         long = "encoding",
@@ -729,7 +1539,12 @@ Example Workflows:
 
 4. Force Overwrite
    rustscout-cli workspace init -F
-   Replaces any existing .rustscout folder and config.")]
+   Replaces any existing .rustscout folder and config.
+
+5. Search Defaults
+   rustscout-cli workspace init --format yaml --with-config
+   Also writes .rustscout/config.yaml; settings filled in there become defaults
+   for every search in the workspace, and command-line flags override them.")]
 struct WorkspaceInit {
     /// The directory in which to initialize the workspace.
     /// Default: current directory (.)
@@ -758,6 +1573,12 @@ struct WorkspaceInit {
     /// Use with caution—this discards any old config in .rustscout.
     #[arg(short = 'F', long = "force", help_heading = "Options")]
     force: bool,
+
+    /// Also write .rustscout/config.yaml (or config.json) listing the search
+    /// defaults a workspace can set: ignore patterns, extensions, encoding,
+    /// threads, incremental caching and context lines.
+    #[arg(long = "with-config", help_heading = "Options")]
+    with_config: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -793,36 +1614,72 @@ struct WorkspaceInfo {
     dir: Option<PathBuf>,
 }
 
+/// Values of --schedule
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum ScheduleArg {
+    LargestFirst,
+    Discovery,
+}
+
+impl From<ScheduleArg> for Schedule {
+    fn from(arg: ScheduleArg) -> Self {
+        match arg {
+            ScheduleArg::LargestFirst => Schedule::LargestFirst,
+            ScheduleArg::Discovery => Schedule::Discovery,
+        }
+    }
+}
+
+/// Values of --cache-format
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum CacheFormatArg {
+    Json,
+    Binary,
+}
+
+impl From<CacheFormatArg> for CacheFormat {
+    fn from(arg: CacheFormatArg) -> Self {
+        match arg {
+            CacheFormatArg::Json => CacheFormat::Json,
+            CacheFormatArg::Binary => CacheFormat::Binary,
+        }
+    }
+}
+
 mod diff_utils;
-use diff_utils::{print_side_by_side_diff, print_unified_diff};
+mod printer;
+use diff_utils::{print_side_by_side_diff, print_unified_diff, write_unified_diff, DiffOptions};
+use printer::{PrintOptions, PrintStyle, Printer};
 
 /// Runs an interactive wizard in the terminal to pick hunks. Returns the set of chosen hunk indices.
-fn interactive_select_hunks(info: &UndoInfo) -> Result<Vec<usize>> {
-    let mut global_idx = 0;
-    let mut mapping = Vec::new(); // (global_idx, file_idx, hunk_idx)
+/// Shows the hunks of an operation grouped by file and asks which to
+/// revert: hunks by their global index, whole files by `f<N>`
+fn interactive_select_hunks(info: &UndoInfo) -> Result<HunkSelection> {
+    // (file_idx, hunk_idx) for each global index
+    let mapping: Vec<(usize, usize)> = info
+        .file_diffs
+        .iter()
+        .enumerate()
+        .flat_map(|(f_idx, file_diff)| (0..file_diff.hunks.len()).map(move |h_idx| (f_idx, h_idx)))
+        .collect();
     let mut choices = Vec::new();
 
-    // First pass: show hunks and build mapping
+    // First pass: show hunks grouped by file
     println!("\nOperation {} ({})", info.timestamp, info.description);
+    let mut global_idx = 0;
     for (f_idx, file_diff) in info.file_diffs.iter().enumerate() {
-        println!("\nFile: {}", file_diff.file_path.display());
+        println!(
+            "\n[f{}] File: {} ({} hunks)",
+            f_idx + 1,
+            file_diff.file_path.display(),
+            file_diff.hunks.len()
+        );
         for (h_idx, hunk) in file_diff.hunks.iter().enumerate() {
-            mapping.push((global_idx, f_idx, h_idx));
-
-            // Show hunk header with clearer line range format
-            let range_text = if hunk.original_line_count == 1 {
-                format!("line {}", hunk.original_start_line)
-            } else {
-                format!(
-                    "lines {}–{}",
-                    hunk.original_start_line,
-                    hunk.original_start_line + hunk.original_line_count - 1
-                )
-            };
-
             println!(
                 "  [ ] Hunk {} (Global index {}): {}",
-                h_idx, global_idx, range_text
+                h_idx,
+                global_idx,
+                hunk_range_text(hunk)
             );
 
             // Show hunk content with line numbers
@@ -840,7 +1697,7 @@ fn interactive_select_hunks(info: &UndoInfo) -> Result<Vec<usize>> {
         }
     }
 
-    println!("\nEnter hunk indexes to revert (comma-separated), or press Enter to revert all. Type 'q' to cancel.\n> ");
+    println!("\nEnter global hunk indexes to revert (comma-separated), f<N> to revert a whole file, or press Enter to revert all. Type 'q' to cancel.\n> ");
     std::io::stdout().flush()?;
     let mut input = String::new();
     std::io::stdin().read_line(&mut input)?;
@@ -852,16 +1709,25 @@ fn interactive_select_hunks(info: &UndoInfo) -> Result<Vec<usize>> {
 
     if input.is_empty() {
         // Revert all hunks
-        choices.extend(0..global_idx);
+        choices.extend(0..mapping.len());
     } else {
         // Parse user input
         for part in input.split(',') {
-            match part.trim().parse::<usize>() {
-                Ok(idx) if idx < global_idx => {
+            let part = part.trim();
+            let file = part
+                .strip_prefix(['f', 'F'])
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| (1..=info.file_diffs.len()).contains(n));
+            if let Some(file) = file {
+                choices.extend((0..mapping.len()).filter(|&idx| mapping[idx].0 == file - 1));
+                continue;
+            }
+            match part.parse::<usize>() {
+                Ok(idx) if idx < mapping.len() => {
                     choices.push(idx);
                 }
                 _ => {
-                    println!("Warning: invalid hunk index '{}' ignored", part.trim());
+                    println!("Warning: invalid hunk index '{}' ignored", part);
                 }
             }
         }
@@ -875,25 +1741,14 @@ fn interactive_select_hunks(info: &UndoInfo) -> Result<Vec<usize>> {
     if !choices.is_empty() {
         println!("\nSelected hunks to revert:");
         for &idx in &choices {
-            if let Some(&(_, f_idx, h_idx)) = mapping.iter().find(|&&(g, _, _)| g == idx) {
-                let file_diff = &info.file_diffs[f_idx];
-                let hunk = &file_diff.hunks[h_idx];
-                let range_text = if hunk.original_line_count == 1 {
-                    format!("line {}", hunk.original_start_line)
-                } else {
-                    format!(
-                        "lines {}–{}",
-                        hunk.original_start_line,
-                        hunk.original_start_line + hunk.original_line_count - 1
-                    )
-                };
-                println!(
-                    "  File: {}, Hunk {} ({})",
-                    file_diff.file_path.display(),
-                    h_idx,
-                    range_text
-                );
-            }
+            let (f_idx, h_idx) = mapping[idx];
+            let file_diff = &info.file_diffs[f_idx];
+            println!(
+                "  File: {}, Hunk {} ({})",
+                file_diff.file_path.display(),
+                h_idx,
+                hunk_range_text(&file_diff.hunks[h_idx])
+            );
         }
 
         // Final confirmation
@@ -906,22 +1761,62 @@ fn interactive_select_hunks(info: &UndoInfo) -> Result<Vec<usize>> {
         }
     }
 
-    Ok(choices)
+    Ok(HunkSelection {
+        hunks: choices,
+        ..HunkSelection::default()
+    })
+}
+
+/// The lines a hunk replaced, like `line 3` or `lines 3–5`
+fn hunk_range_text(hunk: &DiffHunk) -> String {
+    if hunk.original_line_count == 1 {
+        format!("line {}", hunk.original_start_line)
+    } else {
+        format!(
+            "lines {}–{}",
+            hunk.original_start_line,
+            hunk.original_start_line + hunk.original_line_count - 1
+        )
+    }
 }
 
-fn main() -> Result<()> {
+fn main() -> ExitCode {
     run()
 }
 
-fn run() -> Result<()> {
+/// Runs the CLI and maps the outcome to an exit status.
+///
+/// Like grep, `search` exits 0 when something matched, 1 when nothing did and
+/// 2 on error. The other subcommands exit 0 on success and 1 on error.
+fn run() -> ExitCode {
     let cli = Cli::parse();
+    let is_search = matches!(cli.command, Commands::Search(_));
+
+    match execute(cli) {
+        Ok(Some(result)) if result.total_matches == 0 => ExitCode::from(1),
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::from(if is_search { 2 } else { 1 })
+        }
+    }
+}
 
-    // Set up logging based on verbosity
-    setup_logging(&cli.verbosity)?;
+/// Dispatches to the subcommand, returning the search result for `search`
+fn execute(cli: Cli) -> Result<Option<SearchResultType>> {
+    // Quiet searches keep stderr free of everything but errors
+    let log_level = match &cli.command {
+        Commands::Search(args) if args.quiet => "error",
+        _ => cli.verbosity.as_str(),
+    };
+    setup_logging(log_level)?;
 
     match cli.command {
+        Commands::Search(args) if args.type_list => {
+            print_type_list(&args)?;
+        }
         Commands::Search(args) => {
-            handle_search(*args, &cli.verbosity)?;
+            return handle_search(*args, &cli.verbosity).map(Some);
         }
         Commands::Replace { command } => {
             handle_replace(command, &cli.verbosity)?;
@@ -933,383 +1828,1365 @@ fn run() -> Result<()> {
             handle_workspace(command)?;
         }
     }
-    Ok(())
+    Ok(None)
 }
 
-fn handle_search(args: CliSearchConfig, verbosity: &str) -> Result<()> {
-    let mut pattern_defs = Vec::new();
-
-    // Convert CLI patterns to pattern definitions
-    for (i, pattern) in args
-        .patterns
+/// Prints every invalid pattern at once, with a caret under the position of a
+/// regex syntax error, and fails if there were any. Warnings are left to the
+/// search itself to log.
+fn check_patterns(pattern_defs: &[PatternDefinition]) -> Result<()> {
+    let errors: Vec<(usize, PatternError)> = pattern_defs
         .iter()
-        .chain(args.legacy_patterns.iter())
         .enumerate()
-    {
-        let boundary_mode = if args.word_boundary {
-            WordBoundaryMode::WholeWords
-        } else {
-            match args.boundary_mode.as_str() {
-                "strict" => WordBoundaryMode::WholeWords,
-                "partial" => WordBoundaryMode::Partial,
-                "none" => WordBoundaryMode::None,
-                _ => {
-                    return Err(SearchError::config_error(format!(
-                        "Invalid boundary mode '{}'. Valid values are: strict, partial, none",
-                        args.boundary_mode
-                    )))
-                }
-            }
-        };
-
-        pattern_defs.push(PatternDefinition {
-            text: pattern.clone(),
-            is_regex: i < args.is_regex.len() && args.is_regex[i],
-            boundary_mode,
-            hyphen_mode: match args.hyphen_mode.as_str() {
-                "boundary" => HyphenMode::Boundary,
-                "joining" => HyphenMode::Joining,
-                _ => {
-                    return Err(SearchError::config_error(
-                        "Invalid hyphen mode. Valid values are: boundary, joining",
-                    ))
-                }
-            },
-        });
+        .filter_map(|(index, def)| Some((index, def.validate().err()?)))
+        .filter(|(_, error)| !error.is_warning())
+        .collect();
+    if errors.is_empty() {
+        return Ok(());
     }
 
-    let file_extensions = args.extensions.as_ref().map(|e| {
-        e.split(',')
-            .map(|s| s.trim().to_string())
-            .collect::<Vec<_>>()
-    });
-
-    let cache_strategy = match args.cache_strategy.as_str() {
-        "git" => ChangeDetectionStrategy::GitStatus,
-        "signature" => ChangeDetectionStrategy::FileSignature,
-        _ => ChangeDetectionStrategy::Auto,
-    };
+    for (index, error) in &errors {
+        eprintln!("error: pattern {}: {}", index + 1, error);
+        if let PatternError::InvalidRegex {
+            pattern,
+            position: Some(position),
+            ..
+        } = error
+        {
+            eprintln!("    {}", pattern);
+            eprintln!("    {}^", " ".repeat(*position));
+        }
+    }
+    Err(SearchError::invalid_pattern(format!(
+        "{} of {} pattern(s) are invalid",
+        errors.len(),
+        pattern_defs.len()
+    )))
+}
 
-    let encoding_mode = match args.encoding.to_lowercase().as_str() {
-        "lossy" => EncodingMode::Lossy,
-        _ => EncodingMode::FailFast,
+/// Builds the file types for --type/--type-not, on top of the built-in types,
+/// those defined in the workspace config and those from --type-add
+fn file_types_from_args(
+    args: &CliSearchConfig,
+    workspace_defaults: Option<&PartialSearchConfig>,
+) -> Result<FileTypes> {
+    let mut file_types = match workspace_defaults {
+        Some(defaults) => defaults.file_types()?,
+        None => FileTypes::new(),
     };
+    for definition in &args.type_add {
+        file_types.add(definition)?;
+    }
+    for name in &args.types {
+        file_types.select(name)?;
+    }
+    for name in &args.type_not {
+        file_types.negate(name)?;
+    }
+    Ok(file_types)
+}
 
-    let search_config = SearchConfig {
-        pattern_definitions: pattern_defs,
-        root_path: args.root,
-        file_extensions,
-        ignore_patterns: args.ignore,
-        stats_only: args.stats,
-        thread_count: args
-            .threads
-            .unwrap_or_else(|| NonZeroUsize::new(4).unwrap()),
-        log_level: verbosity.to_string(),
-        context_before: args.context_before,
-        context_after: args.context_after,
-        incremental: args.incremental,
-        cache_path: args.cache_path,
-        cache_strategy,
-        max_cache_size: args.max_cache_size.map(|size| size * 1024 * 1024),
-        use_compression: args.compress_cache,
-        encoding_mode,
-    };
+/// Prints every known file type with its globs, for --type-list
+fn print_type_list(args: &CliSearchConfig) -> Result<()> {
+    let root = args
+        .root
+        .first()
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("."));
+    let workspace_defaults = SearchConfig::load_workspace_defaults(&root)?;
+    let file_types = file_types_from_args(args, workspace_defaults.as_ref())?;
+    for (name, globs) in file_types.definitions() {
+        println!("{}: {}", name, globs.join(", "));
+    }
+    Ok(())
+}
 
-    let result = rustscout::search::search(&search_config)?;
+/// Parses a --newer-than/--older-than value relative to now
+fn parse_time_arg(text: &str) -> Result<SystemTime> {
+    parse_time(text, SystemTime::now())
+}
 
-    if args.stats {
-        println!(
-            "{} matches across {} files",
-            result.total_matches, result.files_with_matches
-        );
-        return Ok(());
+/// Checks that files can be created in `dir` by creating and removing a
+/// test directory in it
+fn check_writable(dir: &Path) -> Result<()> {
+    let test_path = dir.join(".rustscout_write_test");
+    match std::fs::create_dir(&test_path) {
+        Ok(_) => {
+            std::fs::remove_dir(&test_path).ok(); // Cleanup
+            Ok(())
+        }
+        Err(e) => Err(SearchError::config_error(format!(
+            "Directory '{}' is not writable: {}",
+            dir.display(),
+            e
+        ))),
     }
+}
 
-    // Print matches in ripgrep style
-    for file_result in &result.file_results {
-        let file_content = std::fs::read_to_string(&file_result.path)?;
-        let all_lines: Vec<&str> = file_content.lines().collect();
+/// Resolves the directory given to `--backup-dir`, creating it and checking
+/// that it is writable. A dry run writes no backups, so it only checks that
+/// the path isn't something other than a directory.
+fn prepare_backup_dir(dir: &Path, dry_run: bool) -> Result<PathBuf> {
+    if dir.exists() && !dir.is_dir() {
+        return Err(SearchError::config_error(format!(
+            "Backup directory '{}' is not a directory",
+            dir.display()
+        )));
+    }
+    if dry_run {
+        return Ok(std::path::absolute(dir)?);
+    }
 
-        // Track which lines we've printed to avoid duplicates when showing context
-        let mut printed_lines = std::collections::HashSet::new();
+    std::fs::create_dir_all(dir).map_err(|e| {
+        SearchError::config_error(format!(
+            "Cannot create backup directory '{}': {}",
+            dir.display(),
+            e
+        ))
+    })?;
+    let dir = dir.canonicalize()?;
+    check_writable(&dir)?;
+    Ok(dir)
+}
 
-        // Group matches by their line number
-        let mut line_to_matches: std::collections::HashMap<usize, Vec<&Match>> =
-            std::collections::HashMap::new();
-        for m in &file_result.matches {
-            line_to_matches.entry(m.line_number).or_default().push(m);
-        }
+/// An option set by a flag and cleared by its --no- counterpart, or `None`
+/// if neither was given
+fn flag_pair(on: bool, off: bool) -> Option<bool> {
+    match (on, off) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
 
-        // Get sorted line numbers
-        let mut line_numbers: Vec<_> = line_to_matches.keys().copied().collect();
-        line_numbers.sort();
+/// Parses the -E/--encoding value; unknown values fall back to failfast
+fn encoding_mode_from_flag(encoding: &str) -> EncodingMode {
+    match encoding.to_lowercase().as_str() {
+        "lossy" => EncodingMode::Lossy,
+        "detect" => EncodingMode::Detect,
+        _ => EncodingMode::FailFast,
+    }
+}
 
-        // Process lines in order
-        for line_num in line_numbers {
-            if line_num == 0 || line_num > all_lines.len() {
-                continue;
+/// Reads a --files-from list from a file, or from stdin if `source` is "-".
+/// Entries are separated by newlines, or by NUL bytes if `nul_separated` is set;
+/// empty entries are skipped.
+fn read_path_list(source: &str, nul_separated: bool) -> Result<Vec<PathBuf>> {
+    let mut bytes = Vec::new();
+    if source == "-" {
+        std::io::stdin().read_to_end(&mut bytes)?;
+    } else {
+        bytes = std::fs::read(source)?;
+    }
+    let text = String::from_utf8_lossy(&bytes);
+
+    let separator = if nul_separated { '\0' } else { '\n' };
+    Ok(text
+        .split(separator)
+        .map(|entry| {
+            if nul_separated {
+                entry
+            } else {
+                entry.trim_end_matches('\r')
             }
+        })
+        .filter(|entry| !entry.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
 
-            // Print context before if not already printed
-            for ctx_line_num in (line_num.saturating_sub(args.context_before))..line_num {
-                if ctx_line_num > 0 && printed_lines.insert(ctx_line_num) {
-                    println!(
-                        "{}:{}-{}",
-                        file_result.path.display(),
-                        ctx_line_num,
-                        all_lines[ctx_line_num - 1]
-                    );
-                }
-            }
+/// Progress bar for searches, drawn on stderr
+struct SearchProgressBar(ProgressBar);
 
-            // Print the matching line with all matches highlighted
-            if printed_lines.insert(line_num) {
-                let line = all_lines[line_num - 1];
-                let matches_in_line = &line_to_matches[&line_num];
+impl SearchProgress for SearchProgressBar {
+    fn on_files_discovered(&self, count: usize) {
+        self.0.set_length(count as u64);
+        if count == 0 {
+            self.0.finish_and_clear();
+        }
+    }
 
-                // Sort matches by their start position
-                let mut sorted = matches_in_line.clone();
-                sorted.sort_by_key(|m| m.start);
+    // Interactive search takes over the terminal as soon as the search ends,
+    // so the bar clears itself after the last file
+    fn on_file_done(&self, _path: &Path, _matches: usize) {
+        self.0.inc(1);
+        if Some(self.0.position()) == self.0.length() {
+            self.0.finish_and_clear();
+        }
+    }
+}
 
-                let mut highlighted_line = String::new();
-                let mut last_offset = 0;
+/// Builds a search progress bar, or `None` if stderr isn't a terminal
+fn progress_bar() -> Option<ProgressBar> {
+    if !std::io::stderr().is_terminal() {
+        return None;
+    }
+    let bar = ProgressBar::new(0);
+    bar.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} files")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+    Some(bar)
+}
 
-                for m in sorted {
-                    // Add non-highlighted prefix
-                    highlighted_line.push_str(&line[last_offset..m.start]);
+/// Wraps a progress bar as a search progress hook
+fn progress_hook(bar: &Option<ProgressBar>) -> Option<ProgressHook> {
+    bar.clone()
+        .map(|bar| ProgressHook::new(SearchProgressBar(bar)))
+}
 
-                    // Add the highlighted match
-                    if args.no_color {
-                        highlighted_line.push_str(&line[m.start..m.end]);
-                    } else {
-                        highlighted_line
-                            .push_str(&format!("\x1b[1;31m{}\x1b[0m", &line[m.start..m.end]));
-                    }
+/// Returns a token that the first Ctrl-C triggers, so the search can stop
+/// cleanly and print partial results; a second Ctrl-C exits immediately
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let token = CancellationToken::new();
+    let handler_token = token.clone();
+    let installed = ctrlc::set_handler(move || {
+        if handler_token.is_cancelled() {
+            std::process::exit(130);
+        }
+        handler_token.cancel();
+    });
+    if let Err(e) = installed {
+        eprintln!("Warning: Ctrl-C won't stop the search cleanly: {}", e);
+    }
+    token
+}
 
-                    last_offset = m.end;
-                }
+/// Whether stdin is a pipe or redirected file, as opposed to a terminal or
+/// /dev/null
+fn stdin_is_piped() -> bool {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        std::fs::metadata("/dev/stdin").is_ok_and(|metadata| {
+            let file_type = metadata.file_type();
+            file_type.is_fifo() || file_type.is_file() || file_type.is_socket()
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        !std::io::stdin().is_terminal()
+    }
+}
 
-                // Add any remaining non-highlighted suffix
-                highlighted_line.push_str(&line[last_offset..]);
+/// Opens --output, or stdout when it isn't given
+fn output_writer(output: Option<&Path>) -> Result<Box<dyn Write>> {
+    Ok(match output {
+        Some(path) => Box::new(std::io::BufWriter::new(std::fs::File::create(path)?)),
+        None => Box::new(std::io::stdout().lock()),
+    })
+}
 
-                println!(
-                    "{}:{}:{}",
-                    file_result.path.display(),
-                    line_num,
-                    highlighted_line
-                );
+/// Prints the --summary table, or one JSON object per row with --format json
+fn print_summary(
+    writer: &mut impl Write,
+    result: &SearchResultType,
+    kind: &str,
+    roots: &[PathBuf],
+    depth: usize,
+    limit: usize,
+    json: bool,
+) -> Result<()> {
+    if kind == "dirs" {
+        let rows = result.summarize_by_directory_in(roots, depth);
+        let rows = &rows[..rows.len().min(limit)];
+        if json {
+            for row in rows {
+                serde_json::to_writer(&mut *writer, row)?;
+                writeln!(writer)?;
             }
-
-            // Print context after if not already printed
-            let end_ctx = (line_num + args.context_after).min(all_lines.len());
-            for ctx_line_num in (line_num + 1)..=end_ctx {
-                if printed_lines.insert(ctx_line_num) {
-                    println!(
-                        "{}:{}-{}",
-                        file_result.path.display(),
-                        ctx_line_num,
-                        all_lines[ctx_line_num - 1]
-                    );
-                }
+            return Ok(());
+        }
+        let width = rows
+            .iter()
+            .map(|row| row.path.display().to_string().len())
+            .chain(["DIRECTORY".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            writer,
+            "{:<width$}  {:>7}  {:>7}",
+            "DIRECTORY", "FILES", "MATCHES"
+        )?;
+        for row in rows {
+            writeln!(
+                writer,
+                "{:<width$}  {:>7}  {:>7}",
+                row.path.display().to_string(),
+                row.files_with_matches,
+                row.total_matches
+            )?;
+        }
+    } else {
+        let rows = result.top_files(limit);
+        if json {
+            for (path, total_matches) in rows {
+                let row = serde_json::json!({
+                    "path": path.to_string_lossy(),
+                    "total_matches": total_matches,
+                });
+                serde_json::to_writer(&mut *writer, &row)?;
+                writeln!(writer)?;
             }
+            return Ok(());
+        }
+        let width = rows
+            .iter()
+            .map(|(path, _)| path.display().to_string().len())
+            .chain(["FILE".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(writer, "{:<width$}  {:>7}", "FILE", "MATCHES")?;
+        for (path, total_matches) in rows {
+            writeln!(
+                writer,
+                "{:<width$}  {:>7}",
+                path.display().to_string(),
+                total_matches
+            )?;
         }
     }
-
-    println!(
-        "\n{} matches across {} files",
-        result.total_matches, result.files_with_matches
-    );
     Ok(())
 }
 
-fn handle_replace(command: ReplaceCommands, verbosity: &str) -> Result<()> {
-    match command {
+/// Prints the --stats breakdown of where the search spent its time
+fn print_search_stats(stats: &SearchStats) {
+    println!(
+        "Searched {} files ({}) in {:.1?} ({}/s)",
+        stats.files_scanned,
+        format_bytes(stats.bytes_searched as f64),
+        stats.elapsed,
+        format_bytes(stats.throughput())
+    );
+    println!(
+        "Skipped: {} ignored, {} binary, {} too large, {} filtered by time or size, {} minified, {} duplicate",
+        stats.files_skipped_ignored,
+        stats.files_skipped_binary,
+        stats.files_skipped_too_large,
+        stats.files_skipped_filtered,
+        stats.files_skipped_minified,
+        stats.files_skipped_duplicate
+    );
+    if stats.cache_hits + stats.cache_misses > 0 {
+        println!(
+            "Phases: walk {:.1?}, match {:.1?} (slowest file {:.1?}), cache save {:.1?}",
+            stats.walk_time, stats.match_time, stats.slowest_file_time, stats.cache_save_time
+        );
+        println!(
+            "Cache: {} hits, {} misses",
+            stats.cache_hits, stats.cache_misses
+        );
+    } else {
+        println!(
+            "Phases: walk {:.1?}, match {:.1?} (slowest file {:.1?})",
+            stats.walk_time, stats.match_time, stats.slowest_file_time
+        );
+    }
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 MiB`
+fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", value as u64, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// Label for a pattern in output: its name if it has one, otherwise its text
+fn pattern_label(config: &SearchConfig, pattern_index: usize) -> &str {
+    config
+        .pattern_definitions
+        .get(pattern_index)
+        .map(|def| def.name.as_deref().unwrap_or(&def.text))
+        .unwrap_or_default()
+}
+
+fn handle_search(mut args: CliSearchConfig, verbosity: &str) -> Result<SearchResultType> {
+    // A lone "-", as the root or a trailing argument, searches stdin; so does
+    // piped input when no root or file list is given
+    let dash_given =
+        args.root == [PathBuf::from("-")] || args.legacy_patterns.iter().any(|p| p == "-");
+    args.legacy_patterns.retain(|p| p != "-");
+    let read_stdin =
+        dash_given || (args.root.is_empty() && args.files_from.is_none() && stdin_is_piped());
+    let root_paths = if args.root.is_empty() || read_stdin {
+        vec![PathBuf::from(".")]
+    } else {
+        std::mem::take(&mut args.root)
+    };
+
+    let mut pattern_defs = Vec::new();
+    let case_mode = CaseMode::from_flags(args.ignore_case, args.smart_case);
+
+    // -b and -y apply per pattern, like -r; the pattern file counts as one more pattern
+    let pattern_count =
+        args.patterns.len() + args.legacy_patterns.len() + usize::from(args.pattern_file.is_some());
+    let boundary_modes = if args.word_boundary {
+        vec![WordBoundaryMode::WholeWords; pattern_count]
+    } else {
+        let modes = args
+            .boundary_mode
+            .iter()
+            .map(|mode| mode.parse())
+            .collect::<Result<Vec<WordBoundaryMode>>>()?;
+        per_pattern_values(
+            &modes,
+            pattern_count,
+            WordBoundaryMode::None,
+            "--boundary-mode",
+        )?
+    };
+    let hyphen_modes = {
+        let modes = args
+            .hyphen_mode
+            .iter()
+            .map(|mode| mode.parse())
+            .collect::<Result<Vec<HyphenMode>>>()?;
+        per_pattern_values(
+            &modes,
+            pattern_count,
+            HyphenMode::default(),
+            "--hyphen-mode",
+        )?
+    };
+
+    // Convert CLI patterns to pattern definitions
+    for (i, pattern) in args
+        .patterns
+        .iter()
+        .chain(args.legacy_patterns.iter())
+        .enumerate()
+    {
+        pattern_defs.push(PatternDefinition {
+            text: pattern.clone(),
+            is_regex: i < args.is_regex.len() && args.is_regex[i],
+            boundary_mode: boundary_modes[i],
+            hyphen_mode: hyphen_modes[i],
+            case_mode,
+            multiline: args.multiline,
+            match_whole_line: args.line_regexp,
+            trim_whole_line: args.trim_line,
+            allow_overlapping: args.overlapping,
+            fuzzy_distance: args.fuzzy,
+            name: None,
+        });
+    }
+
+    // Append patterns loaded from --pattern-file
+    if let Some(pattern_file) = &args.pattern_file {
+        let is_regex = args
+            .is_regex
+            .get(pattern_count - 1)
+            .copied()
+            .unwrap_or(false);
+        let boundary_mode = boundary_modes[pattern_count - 1];
+        for mut def in PatternDefinition::load_from_file(pattern_file, is_regex, boundary_mode)? {
+            def.hyphen_mode = hyphen_modes[pattern_count - 1];
+            def.case_mode = case_mode;
+            def.multiline = args.multiline;
+            def.match_whole_line = args.line_regexp;
+            def.trim_whole_line = args.trim_line;
+            def.allow_overlapping = args.overlapping;
+            def.fuzzy_distance = args.fuzzy;
+            pattern_defs.push(def);
+        }
+    }
+
+    check_patterns(&pattern_defs)?;
+
+    if args.summary.is_some() && !["text", "json"].contains(&args.format.as_str()) {
+        return Err(SearchError::config_error(
+            "--summary supports --format text or json",
+        ));
+    }
+    if args.output.is_some() && args.format == "text" && args.summary.is_none() {
+        return Err(SearchError::config_error(
+            "--output requires a machine-readable --format (json, sarif, csv or tsv)",
+        ));
+    }
+
+    let file_extensions = args.extensions.as_ref().map(|e| {
+        e.split(',')
+            .map(|s| s.trim().to_string())
+            .collect::<Vec<_>>()
+    });
+
+    let cache_strategy = args
+        .cache_strategy
+        .as_deref()
+        .map(|strategy| match strategy {
+            "git" => ChangeDetectionStrategy::GitStatus,
+            "signature" => ChangeDetectionStrategy::FileSignature,
+            _ => ChangeDetectionStrategy::Auto,
+        });
+
+    let output_mode = if args.files_with_matches {
+        Some(OutputMode::FilesWithMatches)
+    } else if args.files_without_match {
+        Some(OutputMode::FilesWithoutMatch)
+    } else if args.count {
+        Some(OutputMode::CountPerFile)
+    } else {
+        None
+    };
+
+    let explicit_paths = args
+        .files_from
+        .as_deref()
+        .map(|source| read_path_list(source, args.null_separated))
+        .transpose()?;
+
+    // Defaults (or a profile) from the workspace's .rustscout/config.yaml
+    // are applied below, with flags on top
+    let workspace_start = root_paths[0].clone();
+    let workspace_defaults = SearchConfig::load_workspace_defaults(&workspace_start)?;
+    let file_types = file_types_from_args(&args, workspace_defaults.as_ref())?;
+
+    let bar = (!args.quiet && (args.progress || args.stats))
+        .then(progress_bar)
+        .flatten();
+    // Only flags that were given are Some, so each of them wins over the
+    // workspace config, even when it sets an option back to its default
+    let overrides = SearchOverrides {
+        pattern_definitions: pattern_defs,
+        root_paths: Some(root_paths),
+        file_extensions,
+        exclude_extensions: args.exclude_ext,
+        include_globs: args.include_glob,
+        sniff_shebang: args.no_sniff_shebang.then_some(false),
+        // Includes the types defined in the workspace config
+        file_types: Some(file_types),
+        ignore_patterns: args.ignore,
+        stats_only: args.stats.then_some(true),
+        thread_count: args.threads,
+        log_level: Some(verbosity.to_string()),
+        context_before: args.context.or(args.context_before),
+        context_after: args.context.or(args.context_after),
+        incremental: flag_pair(args.incremental, args.no_incremental),
+        cache_path: args.cache_path,
+        cache_strategy,
+        max_cache_size: args.max_cache_size.map(|size| size * 1024 * 1024),
+        use_compression: flag_pair(args.compress_cache, args.no_compress_cache),
+        cache_format: args.cache_format.map(CacheFormat::from),
+        encoding_mode: args.encoding.as_deref().map(encoding_mode_from_flag),
+        invert_match: args.invert_match.then_some(true),
+        max_count_per_file: args.max_count,
+        // Quiet mode only needs to know whether anything matches
+        max_total_matches: if args.quiet { Some(1) } else { args.max_total },
+        memory_budget: args
+            .memory_budget
+            .map(|bytes| usize::try_from(bytes).unwrap_or(usize::MAX)),
+        output_mode,
+        capture_groups: args.replace_output.is_some().then_some(true),
+        include_hidden: args.hidden.then_some(true),
+        no_ignore: args.no_ignore.then_some(true),
+        no_rustscoutignore: args.no_rustscoutignore.then_some(true),
+        follow_symlinks: args.follow.then_some(true),
+        max_file_size: args.max_filesize,
+        max_depth: args.max_depth,
+        skip_minified: args.skip_minified.then_some(true),
+        modified_after: args.newer_than,
+        modified_before: args.older_than,
+        size_range: (args.min_size.is_some() || args.max_size.is_some()).then(|| {
+            (
+                args.min_size.unwrap_or(0),
+                args.max_size.unwrap_or(u64::MAX),
+            )
+        }),
+        diff_base: args.diff_base,
+        search_archives: args.search_archives.then_some(true),
+        dedupe_hardlinks: args.no_dedupe_hardlinks.then_some(false),
+        schedule: args.schedule.map(Schedule::from),
+        explicit_paths,
+        strict_paths: args.strict_paths.then_some(true),
+        stdin: read_stdin.then_some(true),
+        cancellation: Some(cancel_on_ctrl_c()),
+        progress: progress_hook(&bar),
+        ..SearchOverrides::default()
+    };
+    let mut search_config = if let Some(profile) = &args.profile {
+        let mut config = load_profile(&workspace_start, profile)?;
+        config.extend_with_cli(overrides);
+        config
+    } else {
+        workspace_defaults
+            .unwrap_or_default()
+            .merge_with_cli(overrides)
+    };
+    if search_config.incremental && search_config.cache_path.is_none() {
+        search_config.cache_path = Some(search_config.default_cache_path());
+    }
+    // A profile supplies its own patterns, so their absence is reported below
+    let search_config = SearchConfigBuilder::from(search_config)
+        .allow_empty_patterns()
+        .build()?;
+    if search_config.pattern_definitions.is_empty() {
+        return Err(SearchError::config_error(
+            "No search patterns given; use -p/--pattern, -f/--pattern-file or --profile",
+        ));
+    }
+
+    let result = if read_stdin {
+        rustscout::search::search_reader(&search_config, std::io::stdin().lock())?
+    } else {
+        rustscout::search::search(&search_config)?
+    };
+    if let Some(bar) = &bar {
+        bar.finish_and_clear();
+    }
+
+    if args.quiet {
+        return Ok(result);
+    }
+
+    if let Some(report_path) = &args.report {
+        let format = if args.report_format == "html" {
+            ReportFormat::Html
+        } else {
+            ReportFormat::Markdown
+        };
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(report_path)?);
+        write_report(&result, &search_config, format, &mut writer)?;
+        writer.flush()?;
+        println!(
+            "Wrote report of {} matches across {} files to {}",
+            result.total_matches,
+            result.files_with_matches,
+            report_path.display()
+        );
+        return Ok(result);
+    }
+
+    if let Some(kind) = &args.summary {
+        let mut writer = output_writer(args.output.as_deref())?;
+        print_summary(
+            &mut writer,
+            &result,
+            kind,
+            &search_config.root_paths,
+            args.summary_depth,
+            args.top.unwrap_or(usize::MAX),
+            args.format == "json",
+        )?;
+        writer.flush()?;
+        return Ok(result);
+    }
+
+    if args.format != "text" {
+        let mut writer = output_writer(args.output.as_deref())?;
+        match args.format.as_str() {
+            "sarif" => rustscout::results::sarif::write_sarif(
+                &result,
+                &search_config.pattern_definitions,
+                &mut writer,
+            )?,
+            "csv" | "tsv" => {
+                let delimiter = if args.format == "csv" { b',' } else { b'\t' };
+                if args.no_header {
+                    rustscout::results::export::write_csv_records(&result, &mut writer, delimiter)?;
+                } else {
+                    rustscout::results::export::write_csv(&result, &mut writer, delimiter)?;
+                }
+            }
+            _ => rustscout::results::to_json_lines(&result, &mut writer)?,
+        }
+        writer.flush()?;
+        return Ok(result);
+    }
+
+    let mut summary_note = String::new();
+    if result.limit_reached {
+        summary_note.push_str(" (stopped at --max-total)");
+    }
+    if result.results_truncated {
+        summary_note.push_str(" (some files only counted, over --memory-budget)");
+    }
+    if result.cancelled {
+        summary_note.push_str(" (cancelled, results are partial)");
+    }
+    if result.stats.files_skipped_too_large > 0 {
+        summary_note.push_str(&format!(
+            " ({} files skipped by --max-filesize)",
+            result.stats.files_skipped_too_large
+        ));
+    }
+
+    if args.stats {
+        println!(
+            "{} matches across {} files{}",
+            result.total_matches, result.files_with_matches, summary_note
+        );
+        if args.show_pattern {
+            for (pattern_index, count) in result.pattern_counts() {
+                println!(
+                    "  {}: {}",
+                    pattern_label(&search_config, pattern_index),
+                    count
+                );
+            }
+        }
+        print_search_stats(&result.stats);
+        return Ok(result);
+    }
+
+    // Path-only modes print one plain line per file, suitable for piping
+    match search_config.output_mode {
+        OutputMode::FilesWithMatches => {
+            for file_result in &result.file_results {
+                println!("{}", file_result.path.display());
+            }
+            return Ok(result);
+        }
+        OutputMode::FilesWithoutMatch => {
+            for file_result in result
+                .file_results
+                .iter()
+                .filter(|fr| fr.matches.is_empty())
+            {
+                println!("{}", file_result.path.display());
+            }
+            return Ok(result);
+        }
+        OutputMode::CountPerFile => {
+            for file_result in &result.file_results {
+                println!(
+                    "{}:{}",
+                    file_result.path.display(),
+                    file_result.matches.len()
+                );
+            }
+            return Ok(result);
+        }
+        OutputMode::Full => {}
+    }
+
+    // --replace-output wins over --only-matching when both are given
+    let style = if let Some(template) = &args.replace_output {
+        PrintStyle::Template(template.clone())
+    } else if args.only_matching {
+        PrintStyle::OnlyMatching
+    } else {
+        PrintStyle::Lines
+    };
+    let pattern_labels = args.show_pattern.then(|| {
+        (0..search_config.pattern_definitions.len())
+            .map(|i| pattern_label(&search_config, i).to_string())
+            .collect()
+    });
+    let heading = if args.heading {
+        true
+    } else if args.no_heading {
+        false
+    } else {
+        std::io::stdout().is_terminal()
+    };
+    let mut printer = Printer::new(
+        std::io::stdout().lock(),
+        PrintOptions {
+            style,
+            heading,
+            color: !args.no_color,
+            filename: !args.no_filename,
+            line_number: !args.no_line_number,
+            column: args.column,
+            context: search_config.context_before > 0 || search_config.context_after > 0,
+            pattern_labels,
+            max_columns: (args.max_columns > 0).then_some(args.max_columns),
+            omit_long_lines: args.max_columns_preview,
+        },
+    );
+    for file_result in &result.file_results {
+        printer.print_file(file_result)?;
+    }
+    printer.into_inner().flush()?;
+
+    println!(
+        "\n{} matches across {} files{}",
+        result.total_matches, result.files_with_matches, summary_note
+    );
+    Ok(result)
+}
+
+fn handle_replace(command: ReplaceCommands, verbosity: &str) -> Result<()> {
+    match command {
         ReplaceCommands::Do(do_command) => {
             // Load config file if provided
-            let mut repl_config = if let Some(config_path) = do_command.config {
-                ReplacementConfig::load_from(&config_path)?
+            let mut repl_config = if let Some(config_path) = &do_command.config {
+                ReplacementConfig::load_from(config_path)?
             } else {
                 ReplacementConfig {
-                    patterns: vec![],
-                    backup_enabled: true,
-                    dry_run: do_command.dry_run,
-                    backup_dir: None,
-                    preserve_metadata: true,
-                    undo_dir: PathBuf::from(".rustscout").join("undo"),
+                    backup_enabled: false,
+                    preserve_metadata: false,
+                    ..ReplacementConfig::default()
                 }
             };
 
+            // A backup directory is checked before anything is searched, and
+            // is only created when backups will actually be written
+            let backup_dir = match &do_command.backup_dir {
+                Some(dir) => Some(prepare_backup_dir(dir, do_command.dry_run)?),
+                None => None,
+            };
+
+            // Flags given on the command line win over the config file
+            repl_config.merge_with_cli(ReplacementOverrides {
+                backup_enabled: flag_pair(
+                    do_command.backup || backup_dir.is_some(),
+                    do_command.no_backup,
+                ),
+                backup_dir,
+                dry_run: do_command.dry_run.then_some(true),
+                preserve_metadata: flag_pair(
+                    do_command.preserve_metadata,
+                    do_command.no_preserve_metadata,
+                ),
+                encoding_mode: do_command.encoding.as_deref().map(encoding_mode_from_flag),
+                thread_count: do_command.threads,
+                max_replacements_per_file: do_command.max_per_file,
+                first_match_per_line: do_command.first_per_line.then_some(true),
+                ..ReplacementOverrides::default()
+            });
+            let encoding_mode = repl_config.encoding_mode;
+
+            // -f takes comma-separated globs, like "*.rs,docs/*.md"
+            let include_globs: Vec<String> = do_command
+                .file_filter
+                .as_deref()
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|glob| !glob.is_empty())
+                .map(String::from)
+                .collect();
+
             let target_paths = if do_command.paths.is_empty() {
                 vec![PathBuf::from(".")] // Default to current directory if no paths provided
             } else {
                 do_command.paths
             };
 
-            // Create pattern definition
+            // Patterns from the command line follow any from the config file
+            if do_command.pattern.len() != do_command.replacement.len() {
+                return Err(SearchError::config_error(format!(
+                    "Each --pattern needs its own --replacement (got {} patterns and {} replacements)",
+                    do_command.pattern.len(),
+                    do_command.replacement.len()
+                )));
+            }
             let boundary_mode = if do_command.word_boundary {
                 WordBoundaryMode::WholeWords
             } else {
-                match do_command.boundary_mode.as_str() {
-                    "strict" => WordBoundaryMode::WholeWords,
-                    "partial" => WordBoundaryMode::Partial,
-                    "none" => WordBoundaryMode::None,
-                    _ => {
-                        return Err(SearchError::config_error(format!(
-                            "Invalid boundary mode '{}'. Valid values are: strict, partial, none",
-                            do_command.boundary_mode
-                        )))
-                    }
-                }
-            };
-
-            let pattern_def = PatternDefinition {
-                text: do_command.pattern.clone(),
-                is_regex: do_command.is_regex,
-                boundary_mode,
-                hyphen_mode: match do_command.hyphen_mode.as_str() {
-                    "boundary" => HyphenMode::Boundary,
-                    "joining" => HyphenMode::Joining,
-                    _ => {
-                        return Err(SearchError::config_error(
-                            "Invalid hyphen mode. Valid values are: boundary, joining",
-                        ))
-                    }
-                },
-            };
-
-            let replacement_pattern = ReplacementPattern {
-                definition: pattern_def.clone(),
-                replacement_text: do_command.replacement.clone(),
+                do_command.boundary_mode.parse()?
             };
+            let hyphen_mode = do_command.hyphen_mode.parse()?;
+            for (pattern, replacement) in do_command.pattern.iter().zip(&do_command.replacement) {
+                let definition = PatternDefinition {
+                    text: pattern.clone(),
+                    is_regex: do_command.is_regex,
+                    boundary_mode,
+                    hyphen_mode,
+                    case_mode: if do_command.preserve_case {
+                        CaseMode::Insensitive
+                    } else {
+                        CaseMode::default()
+                    },
+                    multiline: do_command.multiline,
+                    match_whole_line: do_command.line_regexp,
+                    trim_whole_line: do_command.trim_line,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
+                };
+                repl_config.patterns.push(ReplacementPattern {
+                    definition,
+                    replacement_text: replacement.clone(),
+                    preserve_case: do_command.preserve_case,
+                });
+            }
+            if repl_config.patterns.is_empty() {
+                return Err(SearchError::config_error(
+                    "Nothing to replace: give --pattern and --replacement or a config file with patterns",
+                ));
+            }
+            if let Some(path) = &do_command.save_config {
+                repl_config.save_to(path)?;
+            }
 
-            // Add pattern to config
-            repl_config.patterns.push(replacement_pattern);
+            // As a filter, the text goes from stdin to stdout without plans
+            if do_command.stdin {
+                let mut input = String::new();
+                std::io::stdin().read_to_string(&mut input)?;
+                let (output, _) = replace_in_text(&repl_config.patterns, &input)?;
+                if repl_config.dry_run {
+                    let options = DiffOptions {
+                        color: !do_command.no_color && std::io::stderr().is_terminal(),
+                        ..DiffOptions::for_stdout(false, do_command.diff_context)
+                    };
+                    write_unified_diff(
+                        &mut std::io::stderr().lock(),
+                        Path::new("<stdin>"),
+                        &input,
+                        &output,
+                        &options,
+                    )?;
+                } else {
+                    std::io::stdout().lock().write_all(output.as_bytes())?;
+                }
+                return Ok(());
+            }
+            let pattern_defs: Vec<PatternDefinition> = repl_config
+                .patterns
+                .iter()
+                .map(|pattern| pattern.definition.clone())
+                .collect();
+            // Matches carry the index of the pattern they came from
+            let replacement_for =
+                |pattern_index: usize| repl_config.patterns[pattern_index].replacement_text.clone();
 
             // Create replacement set
             let mut replacement_set = ReplacementSet::new(repl_config.clone());
 
+            // Directories are searched with the same ignores as a search: the
+            // workspace config's patterns and -g, plus .gitignore files
+            let workspace_defaults = SearchConfig::load_workspace_defaults(&target_paths[0])?;
+            let ignore_patterns = workspace_defaults
+                .map(|defaults| defaults.ignore_patterns)
+                .unwrap_or_default()
+                .into_iter()
+                .chain(do_command.ignore.iter().cloned());
+
             // First, find all matches using the search functionality
-            let search_config = SearchConfig {
-                pattern_definitions: vec![pattern_def],
-                root_path: PathBuf::from("."),
-                file_extensions: None,
-                ignore_patterns: vec![],
-                stats_only: false,
-                thread_count: do_command
-                    .threads
-                    .unwrap_or_else(|| NonZeroUsize::new(4).unwrap()),
-                log_level: verbosity.to_string(),
-                context_before: 0,
-                context_after: 0,
-                incremental: false,
-                cache_path: None,
-                cache_strategy: ChangeDetectionStrategy::FileSignature,
-                max_cache_size: None,
-                use_compression: false,
-                encoding_mode: EncodingMode::FailFast,
+            let search_config = SearchConfig::builder()
+                .pattern_definitions(pattern_defs.clone())
+                .threads(
+                    do_command
+                        .threads
+                        .unwrap_or_else(SearchConfig::default_thread_count),
+                )
+                .log_level(verbosity)
+                .cache_strategy(ChangeDetectionStrategy::FileSignature)
+                .encoding_mode(encoding_mode)
+                .include_globs(include_globs.clone())
+                .ignore_patterns(ignore_patterns)
+                .build()?;
+            let file_filter = IncludeGlobs::new(&include_globs)?;
+
+            // Files named outright are matched here directly, as is every file
+            // when a pattern spans lines, so ranges come straight from the matcher
+            let matcher = PatternMatcher::from_definitions(pattern_defs.clone())?;
+            let multiline = pattern_defs.iter().any(|definition| definition.multiline);
+            let plan_from_matcher = |file: &Path| -> Result<Option<FileReplacementPlan>> {
+                let content = if encoding_mode == EncodingMode::Detect {
+                    let bytes = std::fs::read(file)?;
+                    TextEncoding::detect(&bytes).decode(&bytes).into_owned()
+                } else {
+                    std::fs::read_to_string(file)?
+                };
+                let found = matcher.find_matches(&content);
+                if found.is_empty() {
+                    return Ok(None);
+                }
+                let mut plan = FileReplacementPlan::new(file.to_path_buf())?;
+                for m in found {
+                    // A match running to the end of a CRLF line (e.g. `.*`)
+                    // leaves its \r alone
+                    let end = if m.end > m.start
+                        && content[..m.end].ends_with('\r')
+                        && content[m.end..].starts_with('\n')
+                    {
+                        m.end - 1
+                    } else {
+                        m.end
+                    };
+                    plan.add_replacement(ReplacementTask::new(
+                        file.to_path_buf(),
+                        (m.start, end),
+                        replacement_for(m.pattern_index),
+                        m.pattern_index,
+                        repl_config.clone(),
+                    ))?;
+                }
+                Ok(Some(plan))
             };
 
-            // Process each target path
             for path in target_paths {
+                // Undo information and backups are never rewritten
+                if is_workspace_path(&path) {
+                    eprintln!("Warning: skipping {}: it is workspace data", path.display());
+                    continue;
+                }
                 if path.is_file() {
-                    let mut plan = FileReplacementPlan::new(path.clone())?;
-                    // Search for matches in this file
-                    let search_result = rustscout::search::search(&SearchConfig {
-                        root_path: path.clone(),
-                        ..search_config.clone()
-                    })?;
-
-                    // Create a replacement task for each match
-                    if let Some(file_result) = search_result.file_results.first() {
-                        let content = std::fs::read_to_string(&path)?;
-
-                        // Create a map of line number to byte offset
-                        let mut line_offsets = vec![0];
-                        for (i, c) in content.char_indices() {
-                            if c == '\n' {
-                                line_offsets.push(i + 1);
-                            }
-                        }
-                        line_offsets.push(content.len());
-
-                        for m in &file_result.matches {
-                            // Convert line-relative positions to absolute file positions
-                            let line_offset = line_offsets[m.line_number - 1];
-                            let abs_start = line_offset + m.start;
-                            let abs_end = line_offset + m.end;
-
-                            let task = ReplacementTask::new(
-                                path.clone(),
-                                (abs_start, abs_end),
-                                do_command.replacement.clone(),
-                                0,
-                                repl_config.clone(),
-                            );
-                            plan.add_replacement(task)?;
+                    // Files named outright still have to pass --file-filter
+                    let parent = path.parent().unwrap_or(Path::new(""));
+                    if file_filter.matches(&path, parent) {
+                        if let Some(plan) = plan_from_matcher(&path)? {
+                            replacement_set.add_plan(plan);
                         }
-                        replacement_set.add_plan(plan);
                     }
-                } else if path.is_dir() {
-                    // Search for matches in all files in the directory
-                    let search_result = rustscout::search::search(&SearchConfig {
-                        root_path: path.clone(),
-                        ..search_config.clone()
-                    })?;
-
-                    // Create plans for each file with matches
-                    for file_result in &search_result.file_results {
-                        let mut plan = FileReplacementPlan::new(file_result.path.clone())?;
-                        for m in &file_result.matches {
-                            let task = ReplacementTask::new(
-                                file_result.path.clone(),
-                                (m.start, m.end),
-                                do_command.replacement.clone(),
-                                0,
-                                repl_config.clone(),
-                            );
-                            plan.add_replacement(task)?;
+                    continue;
+                }
+
+                // One search per root finds the files with matches
+                let search_result = rustscout::search::search(&SearchConfig {
+                    root_paths: vec![path.clone()],
+                    ..search_config.clone()
+                })?;
+                for file_result in &search_result.file_results {
+                    if is_workspace_path(&file_result.path) {
+                        continue;
+                    }
+                    if multiline {
+                        if let Some(plan) = plan_from_matcher(&file_result.path)? {
+                            replacement_set.add_plan(plan);
                         }
-                        replacement_set.add_plan(plan);
+                        continue;
                     }
+                    replacement_set.add_plan(FileReplacementPlan::from_matches(
+                        file_result.path.clone(),
+                        &file_result.matches,
+                        &repl_config,
+                    )?);
                 }
             }
 
-            // Execute replacements
-            if do_command.dry_run {
+            let json = do_command.format == "json";
+
+            // Only the approved matches are replaced, backed up and recorded
+            if do_command.interactive {
+                // Keep stdout for the report when it is JSON
+                let mut prompts: Box<dyn Write> = if json {
+                    Box::new(std::io::stderr())
+                } else {
+                    Box::new(std::io::stdout().lock())
+                };
+                let approved = approve_replacements(
+                    &mut replacement_set.plans,
+                    std::io::stdin().lock(),
+                    &mut prompts,
+                )?;
+                writeln!(prompts, "{} replacements approved", approved)?;
+            }
+
+            // A dry run reports without writing anything
+            if json {
+                let report = replacement_set.execute()?;
+                let report =
+                    serde_json::to_string_pretty(&report).map_err(SearchError::JsonError)?;
+                println!("{}", report);
+                return Ok(());
+            }
+
+            if repl_config.dry_run {
                 println!("Dry run - no changes will be made");
             }
 
             // Always show the preview
+            let options = DiffOptions::for_stdout(!do_command.no_color, do_command.diff_context);
             for plan in &replacement_set.plans {
                 let (old_content, new_content) = plan.preview_old_new()?;
                 match do_command.diff_format.as_str() {
-                    "unified" => print_unified_diff(&plan.file_path, &old_content, &new_content),
-                    "side-by-side" => {
-                        print_side_by_side_diff(&plan.file_path, &old_content, &new_content)
-                    }
-                    _ => print_unified_diff(&plan.file_path, &old_content, &new_content),
+                    "side-by-side" => print_side_by_side_diff(
+                        &plan.file_path,
+                        &old_content,
+                        &new_content,
+                        &options,
+                    ),
+                    _ => print_unified_diff(&plan.file_path, &old_content, &new_content, &options),
                 }
             }
+            let skipped: usize = replacement_set.plans.iter().map(|plan| plan.skipped).sum();
+            if skipped > 0 {
+                println!(
+                    "{} matches left alone (--max-per-file/--first-per-line)",
+                    skipped
+                );
+            }
 
             // Apply changes if not a dry run
-            if !do_command.dry_run {
-                let _backups = replacement_set.apply_with_progress()?;
-                println!("Replacements applied successfully.");
+            if !repl_config.dry_run {
+                let report = replacement_set.execute()?;
+                let backup_dir = report
+                    .files
+                    .iter()
+                    .find_map(|file| file.backup_path.as_deref()?.parent());
+                match backup_dir {
+                    Some(dir) => println!(
+                        "Replacements applied successfully. Backups are in {}",
+                        dir.display()
+                    ),
+                    None => println!("Replacements applied successfully."),
+                }
             }
 
             Ok(())
         }
+        ReplaceCommands::Rename(rename_command) => handle_rename(rename_command),
+        ReplaceCommands::List(list_command) => handle_list(&list_command),
+        ReplaceCommands::Prune(prune_command) => handle_prune(&prune_command),
         ReplaceCommands::Undo(undo_command) => handle_undo(&undo_command),
     }
 }
 
+fn handle_prune(prune_command: &ReplacePrune) -> Result<()> {
+    let config = ReplacementConfig {
+        undo_dir: prune_command.undo_dir.clone(),
+        dry_run: prune_command.dry_run,
+        ..Default::default()
+    };
+    let policy = PrunePolicy {
+        keep_last: prune_command.keep_last,
+        older_than: prune_command.older_than,
+        max_total_size: prune_command.max_total_size,
+    };
+    let report = ReplacementSet::prune_undo(&config, &policy)?;
+
+    if report.removed.is_empty() {
+        println!("Nothing to prune.");
+        return Ok(());
+    }
+    let verb = if report.dry_run {
+        "Would remove"
+    } else {
+        "Removed"
+    };
+    for operation in &report.removed {
+        println!("{} {}  =>  {}", verb, operation.id, operation.description);
+    }
+    for backup in &report.backup_files {
+        println!("  {}", backup.display());
+    }
+    println!(
+        "{} {} operations and {} backups, {}",
+        verb,
+        report.removed.len(),
+        report.backup_files.len(),
+        format_bytes(report.bytes_freed as f64)
+    );
+    Ok(())
+}
+
+fn handle_list(list_command: &ReplaceList) -> Result<()> {
+    let config = ReplacementConfig {
+        undo_dir: list_command.undo_dir.clone(),
+        ..Default::default()
+    };
+    let mut operations = ReplacementSet::list_undo_operations_verbose(&config)?;
+    if !list_command.verbose {
+        for operation in &mut operations {
+            operation.files.clear();
+            operation.patterns.clear();
+            operation.config = None;
+        }
+    }
+
+    if list_command.format == "json" {
+        let json = serde_json::to_string_pretty(&operations).map_err(SearchError::JsonError)?;
+        println!("{}", json);
+        return Ok(());
+    }
+    if operations.is_empty() {
+        println!(
+            "No undo operations recorded in {}",
+            config.undo_dir.display()
+        );
+        return Ok(());
+    }
+
+    for operation in &operations {
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(operation.id);
+        println!(
+            "ID: {}  {}  =>  {}",
+            operation.id,
+            humantime::format_rfc3339_seconds(time),
+            operation.description
+        );
+        println!(
+            "  {} files, {}, {}",
+            operation.file_count,
+            format_bytes(operation.total_size as f64),
+            if operation.partial_revert {
+                "partial revert available"
+            } else {
+                "full revert only"
+            }
+        );
+        for line in operation_details(&operation.patterns, operation.config.as_ref()) {
+            println!("  {}", line);
+        }
+        for (file_idx, file) in operation.files.iter().enumerate() {
+            if let Some(renamed_to) = &file.renamed_to {
+                println!(
+                    "  Renamed: {} → {}",
+                    file.path.display(),
+                    renamed_to.display()
+                );
+                continue;
+            }
+            match (&file.backup, file.hunks.is_empty()) {
+                (Some(backup), true) => {
+                    println!("  File: {} -> {}", file.path.display(), backup.display())
+                }
+                _ => println!("  File #{}: {}", file_idx + 1, file.path.display()),
+            }
+            for (hunk_idx, h) in file.hunks.iter().enumerate() {
+                println!(
+                    "    Hunk {}: lines {}-{} replaced with lines {}-{}",
+                    hunk_idx + 1,
+                    h.original_start_line,
+                    h.original_start_line + h.original_line_count - 1,
+                    h.new_start_line,
+                    h.new_start_line + h.new_line_count - 1
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Describes the patterns and settings an operation ran with, one line each.
+/// Operations recorded before they were kept get no lines.
+fn operation_details(
+    patterns: &[ReplacementPattern],
+    config: Option<&ReplacementConfig>,
+) -> Vec<String> {
+    let mut lines: Vec<String> = patterns
+        .iter()
+        .map(|pattern| format!("Pattern: {}", describe_pattern(pattern)))
+        .collect();
+    if let Some(config) = config {
+        let mut settings = vec![
+            if config.backup_enabled {
+                "backups".to_string()
+            } else {
+                "no backups".to_string()
+            },
+            format!("encoding {:?}", config.encoding_mode),
+        ];
+        if config.compress_backups {
+            settings.push("compressed backups".to_string());
+        }
+        if config.preserve_metadata {
+            settings.push("preserve metadata".to_string());
+        }
+        if let Some(max) = config.max_replacements_per_file {
+            settings.push(format!("at most {} per file", max));
+        }
+        if config.first_match_per_line {
+            settings.push("first match per line".to_string());
+        }
+        if config.no_rollback {
+            settings.push("no rollback".to_string());
+        }
+        lines.push(format!("Settings: {}", settings.join(", ")));
+    }
+    lines
+}
+
+/// A pattern as `'text' -> 'replacement'`, followed by the options that
+/// differ from the defaults
+fn describe_pattern(pattern: &ReplacementPattern) -> String {
+    let definition = &pattern.definition;
+    let mut options = Vec::new();
+    if definition.is_regex {
+        options.push("regex".to_string());
+    }
+    if definition.boundary_mode != WordBoundaryMode::None {
+        options.push(format!("boundary {:?}", definition.boundary_mode));
+    }
+    if definition.case_mode != CaseMode::Sensitive {
+        options.push(format!("case {:?}", definition.case_mode));
+    }
+    if definition.multiline {
+        options.push("multiline".to_string());
+    }
+    if definition.match_whole_line {
+        options.push("whole line".to_string());
+    }
+    if pattern.preserve_case {
+        options.push("preserve case".to_string());
+    }
+
+    let text = format!("'{}' -> '{}'", definition.text, pattern.replacement_text);
+    if options.is_empty() {
+        text
+    } else {
+        format!("{} ({})", text, options.join(", "))
+    }
+}
+
+fn handle_rename(rename_command: ReplaceRename) -> Result<()> {
+    if rename_command.pattern.len() != rename_command.replacement.len() {
+        return Err(SearchError::config_error(format!(
+            "Each --pattern needs its own --replacement (got {} patterns and {} replacements)",
+            rename_command.pattern.len(),
+            rename_command.replacement.len()
+        )));
+    }
+    let boundary_mode = if rename_command.word_boundary {
+        WordBoundaryMode::WholeWords
+    } else {
+        WordBoundaryMode::None
+    };
+    let config = ReplacementConfig {
+        patterns: rename_command
+            .pattern
+            .iter()
+            .zip(&rename_command.replacement)
+            .map(|(pattern, replacement)| ReplacementPattern {
+                definition: PatternDefinition::new(
+                    pattern.clone(),
+                    rename_command.is_regex,
+                    boundary_mode,
+                ),
+                replacement_text: replacement.clone(),
+                preserve_case: false,
+            })
+            .collect(),
+        dry_run: rename_command.dry_run,
+        ..ReplacementConfig::default()
+    };
+
+    if config.dry_run {
+        println!("Dry run - no changes will be made");
+    }
+    let renames = rename_paths(&config, &rename_command.paths)?;
+    for (from, to) in &renames {
+        println!("{} → {}", from.display(), to.display());
+    }
+    if renames.is_empty() {
+        println!("No names match.");
+    } else if !config.dry_run {
+        println!("Renamed {} paths.", renames.len());
+    }
+    Ok(())
+}
+
 fn handle_undo(undo_command: &ReplaceUndo) -> Result<()> {
     // Check for conflicting flags
-    if undo_command.interactive && undo_command.hunks.is_some() {
+    if undo_command.interactive
+        && (!undo_command.hunks.is_empty() || !undo_command.files.is_empty())
+    {
         return Err(SearchError::config_error(
-            "Cannot use --interactive and --hunks together. Please use one or the other.",
+            "Cannot use --interactive with --hunks or --files. Please use one or the other.",
         ));
     }
+    let mut selection = HunkSelection {
+        files: undo_command.files.clone(),
+        ..HunkSelection::default()
+    };
+    for hunks in &undo_command.hunks {
+        selection.add_hunks(hunks)?;
+    }
 
     let config = ReplacementConfig {
         undo_dir: undo_command.undo_dir.clone(),
@@ -1322,31 +3199,39 @@ fn handle_undo(undo_command: &ReplaceUndo) -> Result<()> {
         .map_err(|e| SearchError::config_error(format!("Invalid undo ID: {}", e)))?;
 
     // Load the undo info first to check if it exists and has diffs
-    let info_path = config.undo_dir.join(format!("{}.json", id));
-    let content = std::fs::read_to_string(&info_path)
-        .map_err(|e| SearchError::config_error(format!("Failed to read undo info: {}", e)))?;
-    let info: UndoInfo = serde_json::from_str(&content)
-        .map_err(|e| SearchError::config_error(format!("Failed to parse undo info: {}", e)))?;
+    let info = UndoInfo::load(&config.undo_dir.join(format!("{}.json", id)))?;
 
     // If there are no diffs, we can only do a full revert
     if info.file_diffs.is_empty() {
-        if undo_command.hunks.is_some() || undo_command.list_hunks || undo_command.interactive {
+        if !selection.is_empty() || undo_command.list_hunks || undo_command.interactive {
             return Err(SearchError::config_error(
                 "This undo operation only supports full-file backups; partial revert is not possible.",
             ));
         }
         if undo_command.preview {
             println!("Preview of full file revert for operation {}:", id);
+            for (original, renamed) in info.renames.iter().rev() {
+                println!("{} → {}", renamed.display(), original.rel_path.display());
+            }
+            let workspace_root = detect_workspace_root(&config.undo_dir)?;
             for (original, backup) in &info.backups {
-                let backup_path = backup.get_abs_path()?;
-                let original_path = original.get_abs_path()?;
-                let backup_content = std::fs::read_to_string(&backup_path)?;
+                let backup_path = info.resolve_backup(backup, &workspace_root);
+                let original_path = original.resolve_in(&workspace_root);
+                let backup_content =
+                    String::from_utf8_lossy(&read_backup(&backup_path)?).into_owned();
                 let current_content = std::fs::read_to_string(&original_path)?;
-                print_unified_diff(&original_path, &current_content, &backup_content);
+                print_unified_diff(
+                    &original_path,
+                    &current_content,
+                    &backup_content,
+                    &DiffOptions::for_stdout(true, 3),
+                );
             }
             return Ok(());
         }
-        ReplacementSet::undo_by_id(id, &config)?;
+        undo_with_progress(info.backups.len(), |progress| {
+            ReplacementSet::undo_by_id_with_progress(id, &config, undo_command.force, progress)
+        })?;
         println!("Successfully restored files from backup {}", id);
         return Ok(());
     }
@@ -1354,19 +3239,20 @@ fn handle_undo(undo_command: &ReplaceUndo) -> Result<()> {
     // Handle --list-hunks
     if undo_command.list_hunks {
         println!("Operation {} ({})", id, info.description);
+        for line in operation_details(&info.patterns, info.config.as_ref()) {
+            println!("{}", line);
+        }
+        let mut global_idx = 0;
         for file_diff in &info.file_diffs {
             println!("\nFile: {}", file_diff.file_path.display());
             for (i, hunk) in file_diff.hunks.iter().enumerate() {
-                let range_text = if hunk.original_line_count == 1 {
-                    format!("line {}", hunk.original_start_line)
-                } else {
-                    format!(
-                        "lines {}–{}",
-                        hunk.original_start_line,
-                        hunk.original_start_line + hunk.original_line_count - 1
-                    )
-                };
-                println!("  [Hunk {}] {}", i, range_text);
+                println!(
+                    "  [Hunk {}] {} (global index {})",
+                    i,
+                    hunk_range_text(hunk),
+                    global_idx
+                );
+                global_idx += 1;
 
                 // Show a preview of the hunk content if --preview is also used
                 if undo_command.preview {
@@ -1388,52 +3274,54 @@ fn handle_undo(undo_command: &ReplaceUndo) -> Result<()> {
 
     // Handle preview of specific hunks
     if undo_command.preview {
-        // Parse hunk indices if provided
-        let hunk_indices: Vec<usize> = if let Some(hunks_str) = &undo_command.hunks {
-            hunks_str
-                .split(',')
-                .map(|s| {
-                    s.trim().parse::<usize>().map_err(|_| {
-                        SearchError::config_error(format!("Invalid hunk index: {}", s.trim()))
-                    })
-                })
-                .collect::<Result<Vec<_>>>()?
+        // If no hunks are selected, preview all of them
+        let selected = if selection.is_empty() {
+            info.file_diffs
+                .iter()
+                .map(|d| (0..d.hunks.len()).collect())
+                .collect()
         } else {
-            // If no hunks specified, use all hunks
-            (0..info.file_diffs.iter().map(|d| d.hunks.len()).sum()).collect()
+            selection.resolve(&info)?
         };
 
-        for file_diff in &info.file_diffs {
+        for (file_diff, hunk_indices) in info.file_diffs.iter().zip(&selected) {
+            if hunk_indices.is_empty() {
+                continue;
+            }
             let file_path = file_diff.file_path.get_abs_path()?;
             let current_content = std::fs::read_to_string(&file_path)?;
             let mut preview_content = current_content.clone();
 
             // Apply selected hunks
-            for &idx in &hunk_indices {
-                if let Some(hunk) = file_diff.hunks.get(idx) {
-                    // Apply hunk changes to preview_content
-                    let lines: Vec<&str> = preview_content.lines().collect();
-                    let mut new_lines = Vec::new();
-
-                    // Copy lines before the hunk
-                    new_lines.extend(lines.iter().take(hunk.new_start_line - 1).cloned());
-
-                    // Add the original lines from the hunk
-                    new_lines.extend(hunk.original_lines.iter().map(|s| s.as_str()));
-
-                    // Copy remaining lines
-                    new_lines.extend(
-                        lines
-                            .iter()
-                            .skip(hunk.new_start_line - 1 + hunk.new_line_count)
-                            .cloned(),
-                    );
-
-                    preview_content = new_lines.join("\n");
-                }
+            for &idx in hunk_indices {
+                let hunk = &file_diff.hunks[idx];
+                // Apply hunk changes to preview_content
+                let lines: Vec<&str> = preview_content.lines().collect();
+                let mut new_lines = Vec::new();
+
+                // Copy lines before the hunk
+                new_lines.extend(lines.iter().take(hunk.new_start_line - 1).cloned());
+
+                // Add the original lines from the hunk
+                new_lines.extend(hunk.original_lines.iter().map(|s| s.as_str()));
+
+                // Copy remaining lines
+                new_lines.extend(
+                    lines
+                        .iter()
+                        .skip(hunk.new_start_line - 1 + hunk.new_line_count)
+                        .cloned(),
+                );
+
+                preview_content = new_lines.join("\n");
             }
 
-            print_unified_diff(&file_path, &current_content, &preview_content);
+            print_unified_diff(
+                &file_path,
+                &current_content,
+                &preview_content,
+                &DiffOptions::for_stdout(true, 3),
+            );
         }
         return Ok(());
     }
@@ -1441,12 +3329,12 @@ fn handle_undo(undo_command: &ReplaceUndo) -> Result<()> {
     // Handle --interactive
     if undo_command.interactive {
         match interactive_select_hunks(&info) {
-            Ok(hunk_indices) => {
-                if hunk_indices.is_empty() {
+            Ok(selection) => {
+                if selection.is_empty() {
                     println!("No hunks selected. Operation cancelled.");
                     return Ok(());
                 }
-                ReplacementSet::undo_partial_by_id(id, &config, &hunk_indices)?;
+                revert_selection(id, &config, &info, &selection, undo_command.force)?;
                 println!("Successfully reverted selected hunks.");
                 return Ok(());
             }
@@ -1457,20 +3345,10 @@ fn handle_undo(undo_command: &ReplaceUndo) -> Result<()> {
         }
     }
 
-    // Parse hunk indices if provided
-    let hunk_indices = if let Some(hunks_str) = &undo_command.hunks {
-        hunks_str
-            .split(',')
-            .map(|s| {
-                s.trim().parse::<usize>().map_err(|_| {
-                    SearchError::config_error(format!("Invalid hunk index: {}", s.trim()))
-                })
-            })
-            .collect::<Result<Vec<_>>>()?
-    } else {
-        // If no hunks specified, use all hunks
-        (0..info.file_diffs.iter().map(|d| d.hunks.len()).sum()).collect()
-    };
+    // Check the selection before asking
+    if !selection.is_empty() {
+        selection.resolve(&info)?;
+    }
 
     // Confirm unless --force is used
     if !undo_command.force {
@@ -1484,17 +3362,67 @@ fn handle_undo(undo_command: &ReplaceUndo) -> Result<()> {
         }
     }
 
+    // If no hunks are selected, revert all of them
+    if selection.is_empty() {
+        selection.hunks = (0..info.file_diffs.iter().map(|d| d.hunks.len()).sum()).collect();
+    }
+
     // Perform the actual revert
-    if hunk_indices.is_empty() {
-        ReplacementSet::undo_by_id(id, &config)?;
+    if selection.is_empty() {
+        undo_with_progress(info.backups.len(), |progress| {
+            ReplacementSet::undo_by_id_with_progress(id, &config, undo_command.force, progress)
+        })?;
     } else {
-        ReplacementSet::undo_partial_by_id(id, &config, &hunk_indices)?;
+        revert_selection(id, &config, &info, &selection, undo_command.force)?;
     }
 
     println!("Successfully reverted changes.");
     Ok(())
 }
 
+/// Reverts the hunks `selection` picks, with a progress bar over their files
+fn revert_selection(
+    id: u64,
+    config: &ReplacementConfig,
+    info: &UndoInfo,
+    selection: &HunkSelection,
+    force: bool,
+) -> Result<()> {
+    let files = selection
+        .resolve(info)?
+        .iter()
+        .filter(|hunks| !hunks.is_empty())
+        .count();
+    undo_with_progress(files, |progress| {
+        ReplacementSet::undo_selection_by_id(id, config, selection, force, progress)
+    })
+}
+
+/// Runs an undo over `files` files with a progress bar, if stderr is a
+/// terminal
+fn undo_with_progress(
+    files: usize,
+    undo: impl FnOnce(Option<&(dyn Fn(&Path) + Sync)>) -> Result<()>,
+) -> Result<()> {
+    let bar = progress_bar();
+    let tick = |_: &Path| {
+        if let Some(bar) = &bar {
+            bar.inc(1);
+        }
+    };
+    let result = match &bar {
+        Some(bar) => {
+            bar.set_length(files as u64);
+            undo(Some(&tick))
+        }
+        None => undo(None),
+    };
+    if let Some(bar) = &bar {
+        bar.finish_and_clear();
+    }
+    result
+}
+
 /// Handle workspace-related commands
 fn handle_workspace(cmd: WorkspaceCommands) -> Result<()> {
     match cmd {
@@ -1533,20 +3461,7 @@ fn handle_workspace(cmd: WorkspaceCommands) -> Result<()> {
                 )));
             }
 
-            // Check write permissions with a test directory
-            let test_path = abs_dir.join(".rustscout_write_test");
-            match std::fs::create_dir(&test_path) {
-                Ok(_) => {
-                    std::fs::remove_dir(&test_path).ok(); // Cleanup
-                }
-                Err(e) => {
-                    return Err(SearchError::config_error(format!(
-                        "Directory '{}' is not writable: {}",
-                        abs_dir.display(),
-                        e
-                    )));
-                }
-            }
+            check_writable(&abs_dir)?;
 
             // Check for existing workspace
             let rustscout_dir = abs_dir.join(".rustscout");
@@ -1579,6 +3494,10 @@ fn handle_workspace(cmd: WorkspaceCommands) -> Result<()> {
                     })
                     .display()
             );
+            if args.with_config {
+                let config_path = rustscout::workspace::init_search_config(&abs_dir, &format)?;
+                println!("  Search defaults: {}", config_path.display());
+            }
 
             Ok(())
         }
@@ -1606,10 +3525,13 @@ fn handle_interactive_search(args: InteractiveSearchArgs, verbosity: &str) -> Re
     let lib_args = rustscout::search::interactive_search::InteractiveSearchArgs {
         patterns: args.patterns,
         legacy_patterns: args.legacy_patterns,
+        pattern_file: args.pattern_file,
         is_regex: args.is_regex,
         boundary_mode: args.boundary_mode,
         word_boundary: args.word_boundary,
         hyphen_mode: args.hyphen_mode,
+        ignore_case: args.ignore_case,
+        smart_case: args.smart_case,
         root: args.root,
         extensions: args.extensions,
         ignore: args.ignore,
@@ -1624,8 +3546,10 @@ fn handle_interactive_search(args: InteractiveSearchArgs, verbosity: &str) -> Re
     };
 
     // Convert args to search config with the global verbosity
-    let config =
+    let mut config =
         rustscout::search::interactive_search::convert_args_to_config(&lib_args, verbosity)?;
+    let bar = progress_bar();
+    config.progress = progress_hook(&bar);
 
     rustscout::search::interactive_search::run_interactive_search(&lib_args, &config)?;
     Ok(())
@@ -0,0 +1,606 @@
+use rustscout::{FileResult, Match};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+
+/// ANSI colors used to highlight matches, cycled by pattern index
+const MATCH_COLORS: [&str; 6] = ["1;31", "1;32", "1;33", "1;34", "1;35", "1;36"];
+
+/// ANSI color for file headings
+const HEADING_COLOR: &str = "1;35";
+
+/// What to print for each match
+#[derive(Debug, Clone)]
+pub enum PrintStyle {
+    /// The whole line with matches highlighted, plus any context lines
+    Lines,
+    /// Only the matched text, one record per match (`-o`)
+    OnlyMatching,
+    /// Each match expanded through a `--replace-output` template
+    Template(String),
+}
+
+/// Settings that control how a [`Printer`] lays out matches
+#[derive(Debug, Clone)]
+pub struct PrintOptions {
+    pub style: PrintStyle,
+    /// Print the path once above each file's matches instead of on every line
+    pub heading: bool,
+    pub color: bool,
+    /// Include the file path (as a heading or line prefix)
+    pub filename: bool,
+    pub line_number: bool,
+    /// Include the column of the first match on each line
+    pub column: bool,
+    /// Context lines were requested, so separate non-contiguous blocks with `--`
+    pub context: bool,
+    /// Labels to show for each pattern index, set with --show-pattern
+    pub pattern_labels: Option<Vec<String>>,
+    /// Lines longer than this many bytes are shortened around the first match
+    pub max_columns: Option<usize>,
+    /// Replace over-long lines with a match count instead of a shortened preview
+    pub omit_long_lines: bool,
+}
+
+/// A line to print for a file: either a match line with the matches on it,
+/// or a context line
+enum Line<'a> {
+    Match(&'a str, Vec<&'a Match>),
+    Context(&'a str),
+}
+
+/// Writes search results in ripgrep style.
+///
+/// Everything printed comes from the `Match` values themselves (`line_content`
+/// plus `context_before`/`context_after`), so files are never read again.
+pub struct Printer<W: Write> {
+    writer: W,
+    options: PrintOptions,
+    files_printed: usize,
+}
+
+impl<W: Write> Printer<W> {
+    pub fn new(writer: W, options: PrintOptions) -> Self {
+        Self {
+            writer,
+            options,
+            files_printed: 0,
+        }
+    }
+
+    /// Prints all matches of one file; files without matches print nothing
+    pub fn print_file(&mut self, file_result: &FileResult) -> io::Result<()> {
+        if file_result.matches.is_empty() {
+            return Ok(());
+        }
+
+        if self.files_printed > 0 {
+            if self.options.heading {
+                writeln!(self.writer)?;
+            } else if self.options.context && matches!(self.options.style, PrintStyle::Lines) {
+                writeln!(self.writer, "--")?;
+            }
+        }
+        self.files_printed += 1;
+
+        if self.options.heading && self.options.filename {
+            if self.options.color {
+                writeln!(
+                    self.writer,
+                    "\x1b[{}m{}\x1b[0m",
+                    HEADING_COLOR,
+                    file_result.path.display()
+                )?;
+            } else {
+                writeln!(self.writer, "{}", file_result.path.display())?;
+            }
+        }
+
+        let mut matches: Vec<&Match> = file_result.matches.iter().collect();
+        matches.sort_by_key(|m| (m.line_number, m.start));
+
+        match &self.options.style {
+            PrintStyle::Lines => self.print_lines(&file_result.path, &matches),
+            PrintStyle::OnlyMatching => {
+                for m in matches {
+                    let text = m.line_content.get(m.start..m.end).unwrap_or_default();
+                    let prefix = self.prefix(&file_result.path, m.line_number, ':');
+                    let text = self.highlight(m.pattern_index, text);
+                    writeln!(self.writer, "{}{}:{}", prefix, m.column_start, text)?;
+                }
+                Ok(())
+            }
+            PrintStyle::Template(template) => {
+                for m in matches {
+                    let mut prefix = self.prefix(&file_result.path, m.line_number, ':');
+                    if self.options.column {
+                        prefix.push_str(&format!("{}:", m.column_start));
+                    }
+                    writeln!(self.writer, "{}{}", prefix, m.expand_template(template))?;
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns the underlying writer
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn print_lines(&mut self, path: &Path, matches: &[&Match]) -> io::Result<()> {
+        // Merge match and context lines by line number; a line that matched
+        // is never printed as context
+        let mut lines: BTreeMap<usize, Line> = BTreeMap::new();
+        for m in matches {
+            for (line_number, text) in m.context_before.iter().chain(&m.context_after) {
+                lines
+                    .entry(*line_number)
+                    .or_insert(Line::Context(text.as_str()));
+            }
+            match lines.get_mut(&m.line_number) {
+                Some(Line::Match(_, on_line)) => on_line.push(m),
+                _ => {
+                    lines.insert(m.line_number, Line::Match(&m.line_content, vec![m]));
+                }
+            }
+        }
+
+        let blocks = context_blocks(
+            matches
+                .iter()
+                .map(|m| (m.line_number, m.context_before.len(), m.context_after.len())),
+        );
+        for (i, block) in blocks.into_iter().enumerate() {
+            if self.options.context && i > 0 {
+                writeln!(self.writer, "--")?;
+            }
+            for (&line_number, line) in lines.range(block) {
+                self.print_line(path, line_number, line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn print_line(&mut self, path: &Path, line_number: usize, line: &Line) -> io::Result<()> {
+        match line {
+            Line::Context(text) => {
+                let prefix = self.prefix(path, line_number, '-');
+                writeln!(self.writer, "{}{}", prefix, self.render_line(text, &[]))
+            }
+            Line::Match(text, on_line) => {
+                let mut prefix = self.prefix(path, line_number, ':');
+                if self.options.column {
+                    prefix.push_str(&format!("{}:", on_line[0].column_start));
+                }
+                if let Some(labels) = self.pattern_labels(on_line) {
+                    prefix.push_str(&format!("[{}] ", labels));
+                }
+                writeln!(self.writer, "{}{}", prefix, self.render_line(text, on_line))
+            }
+        }
+    }
+
+    /// Builds the `path:line:` prefix for a match line (`separator` ':') or
+    /// the `path:line-` prefix for a context line (`separator` '-'). The path
+    /// is left out when it was already printed as a heading.
+    fn prefix(&self, path: &Path, line_number: usize, separator: char) -> String {
+        let mut parts = Vec::with_capacity(2);
+        if self.options.filename && !self.options.heading {
+            parts.push(path.display().to_string());
+        }
+        if self.options.line_number {
+            parts.push(line_number.to_string());
+        }
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!("{}{}", parts.join(":"), separator)
+        }
+    }
+
+    fn highlight(&self, pattern_index: usize, text: &str) -> String {
+        if self.options.color {
+            let color = MATCH_COLORS[pattern_index % MATCH_COLORS.len()];
+            format!("\x1b[{}m{}\x1b[0m", color, text)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// Highlights the matches on a line, shortening it first if it is longer
+    /// than --max-columns; `matches` is sorted by start
+    fn render_line(&self, line: &str, matches: &[&Match]) -> String {
+        let Some(max_columns) = self.options.max_columns.filter(|&max| line.len() > max) else {
+            return self.highlight_line(line, 0..line.len(), matches);
+        };
+        if self.options.omit_long_lines {
+            return if matches.is_empty() {
+                "[Omitted long context line]".to_string()
+            } else {
+                format!("[Omitted long line with {} matches]", matches.len())
+            };
+        }
+
+        let focus = matches.first().map_or(0..0, |m| m.start..m.end);
+        let window = truncation_window(line, focus, max_columns);
+        let omitted = line.len() - window.len();
+        let mut rendered = String::new();
+        if window.start > 0 {
+            rendered.push_str("...");
+        }
+        rendered.push_str(&self.highlight_line(line, window.clone(), matches));
+        if window.end < line.len() {
+            rendered.push_str("...");
+        }
+        rendered.push_str(&format!(" [... {} bytes omitted]", omitted));
+        rendered
+    }
+
+    /// Highlights every match within the `window` byte range of a line
+    fn highlight_line(&self, line: &str, window: Range<usize>, matches: &[&Match]) -> String {
+        let mut highlighted = String::new();
+        let mut last_offset = window.start;
+        for m in matches {
+            // Matches from different patterns may overlap; only highlight
+            // the part not already covered, and only inside the window
+            let (start, end) = (m.start.max(last_offset), m.end.min(window.end));
+            if end <= start {
+                continue;
+            }
+            let (Some(before), Some(matched)) =
+                (line.get(last_offset..start), line.get(start..end))
+            else {
+                continue;
+            };
+            highlighted.push_str(before);
+            highlighted.push_str(&self.highlight(m.pattern_index, matched));
+            last_offset = end;
+        }
+        highlighted.push_str(line.get(last_offset..window.end).unwrap_or_default());
+        highlighted
+    }
+
+    /// Comma-separated labels of the distinct patterns matching on a line
+    fn pattern_labels(&self, matches: &[&Match]) -> Option<String> {
+        let labels = self.options.pattern_labels.as_ref()?;
+        let mut shown: Vec<&str> = Vec::new();
+        for m in matches {
+            let label = labels.get(m.pattern_index).map_or("", String::as_str);
+            if !shown.contains(&label) {
+                shown.push(label);
+            }
+        }
+        Some(shown.join(","))
+    }
+}
+
+/// Groups matches into the blocks of lines printed together, like grep.
+///
+/// Each item is a match's line number with the number of context lines it
+/// has before and after it. Spans that overlap or touch merge into one
+/// block, so blocks are only ever separated by lines that aren't printed.
+/// Line numbers start at 1; context can't reach past the start of the file.
+pub fn context_blocks(
+    spans: impl IntoIterator<Item = (usize, usize, usize)>,
+) -> Vec<RangeInclusive<usize>> {
+    let mut spans: Vec<RangeInclusive<usize>> = spans
+        .into_iter()
+        .map(|(line_number, before, after)| {
+            line_number.saturating_sub(before).max(1)..=line_number + after
+        })
+        .collect();
+    spans.sort_by_key(|span| *span.start());
+
+    let mut blocks: Vec<RangeInclusive<usize>> = Vec::new();
+    for span in spans {
+        match blocks.last_mut() {
+            Some(last) if *span.start() <= last.end() + 1 => {
+                *last = *last.start()..=*last.end().max(span.end());
+            }
+            _ => blocks.push(span),
+        }
+    }
+    blocks
+}
+
+/// Picks the byte range of `line` to show when it is longer than
+/// `max_columns` bytes.
+///
+/// The window is centered on `focus` (usually the first match) and shifted
+/// back inside the line at either end. A match longer than the window keeps
+/// its start visible. Both ends fall on character boundaries, so the window
+/// may be a few bytes narrower than `max_columns` but never splits a UTF-8
+/// sequence.
+pub fn truncation_window(line: &str, focus: Range<usize>, max_columns: usize) -> Range<usize> {
+    if line.len() <= max_columns {
+        return 0..line.len();
+    }
+
+    let focus_len = focus.end.saturating_sub(focus.start);
+    let mut start = focus
+        .start
+        .saturating_sub(max_columns.saturating_sub(focus_len) / 2);
+    let mut end = start + max_columns;
+    if end > line.len() {
+        end = line.len();
+        start = end - max_columns;
+    }
+
+    while !line.is_char_boundary(start) {
+        start += 1;
+    }
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+    start..end.max(start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn options(style: PrintStyle, heading: bool) -> PrintOptions {
+        PrintOptions {
+            style,
+            heading,
+            color: false,
+            filename: true,
+            line_number: true,
+            column: false,
+            context: true,
+            pattern_labels: None,
+            max_columns: None,
+            omit_long_lines: false,
+        }
+    }
+
+    fn match_at(line_number: usize, line: &str, start: usize, end: usize) -> Match {
+        Match {
+            line_number,
+            line_content: line.to_string(),
+            start,
+            end,
+            column_start: start + 1,
+            column_end: end + 1,
+            byte_offset: 0,
+            context_before: vec![(line_number - 1, format!("before {}", line_number))],
+            context_after: vec![(line_number + 1, format!("after {}", line_number))],
+            pattern_index: 0,
+            pattern_name: None,
+            captures: None,
+        }
+    }
+
+    fn sample_files() -> Vec<FileResult> {
+        vec![
+            FileResult {
+                path: PathBuf::from("src/a.rs"),
+                matches: vec![
+                    match_at(2, "let x = 1;", 4, 5),
+                    match_at(3, "let x = 2;", 4, 5),
+                    match_at(10, "x + x", 0, 1),
+                    match_at(10, "x + x", 4, 5),
+                ],
+                line_ending: None,
+            },
+            FileResult {
+                path: PathBuf::from("src/b.rs"),
+                matches: vec![match_at(5, "x", 0, 1)],
+                line_ending: None,
+            },
+        ]
+    }
+
+    fn print_all(options: PrintOptions) -> String {
+        let mut printer = Printer::new(Vec::new(), options);
+        for file_result in sample_files() {
+            printer.print_file(&file_result).unwrap();
+        }
+        String::from_utf8(printer.into_inner()).unwrap()
+    }
+
+    #[test]
+    fn test_heading_groups_lines_under_path() {
+        assert_eq!(
+            print_all(options(PrintStyle::Lines, true)),
+            "src/a.rs\n\
+             1-before 2\n\
+             2:let x = 1;\n\
+             3:let x = 2;\n\
+             4-after 3\n\
+             --\n\
+             9-before 10\n\
+             10:x + x\n\
+             11-after 10\n\
+             \n\
+             src/b.rs\n\
+             4-before 5\n\
+             5:x\n\
+             6-after 5\n"
+        );
+    }
+
+    #[test]
+    fn test_no_heading_prefixes_every_line() {
+        let output = print_all(options(PrintStyle::Lines, false));
+        let lines: Vec<&str> = output.lines().collect();
+        assert_eq!(lines[0], "src/a.rs:1-before 2");
+        assert_eq!(lines[1], "src/a.rs:2:let x = 1;");
+        assert_eq!(lines[8], "--");
+        assert_eq!(lines[9], "src/b.rs:4-before 5");
+    }
+
+    #[test]
+    fn test_only_matching_prints_each_match() {
+        let mut opts = options(PrintStyle::OnlyMatching, false);
+        opts.line_number = false;
+        let output = print_all(opts);
+        assert_eq!(
+            output,
+            "src/a.rs:5:x\nsrc/a.rs:5:x\nsrc/a.rs:1:x\nsrc/a.rs:5:x\nsrc/b.rs:1:x\n"
+        );
+    }
+
+    #[test]
+    fn test_color_highlights_each_match() {
+        let mut opts = options(PrintStyle::Lines, true);
+        opts.color = true;
+        opts.context = false;
+        opts.pattern_labels = Some(vec!["x".to_string()]);
+        let mut printer = Printer::new(Vec::new(), opts);
+        printer.print_file(&sample_files()[0]).unwrap();
+        let output = String::from_utf8(printer.into_inner()).unwrap();
+
+        assert!(output.starts_with("\x1b[1;35msrc/a.rs\x1b[0m\n"));
+        assert!(output.contains("10:[x] \x1b[1;31mx\x1b[0m + \x1b[1;31mx\x1b[0m\n"));
+        // Without --context there are no block separators
+        assert!(!output.contains("--"));
+    }
+
+    #[test]
+    fn test_prints_files_deleted_after_search() -> rustscout::SearchResult<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("kept.txt"), "needle one\n")?;
+        std::fs::write(dir.path().join("gone.txt"), "needle two\n")?;
+
+        let mut config = rustscout::SearchConfig::new_with_pattern(
+            "needle".to_string(),
+            false,
+            rustscout::WordBoundaryMode::None,
+        );
+        config.root_paths = vec![dir.path().to_path_buf()];
+        let mut result = rustscout::search(&config)?;
+        result.file_results.sort_by(|a, b| a.path.cmp(&b.path));
+
+        // Printing must not depend on the files still being there
+        std::fs::remove_file(dir.path().join("gone.txt"))?;
+
+        let mut opts = options(PrintStyle::Lines, true);
+        opts.context = false;
+        let mut printer = Printer::new(Vec::new(), opts);
+        for file_result in &result.file_results {
+            printer.print_file(file_result)?;
+        }
+        let output = String::from_utf8(printer.into_inner()).unwrap();
+
+        assert!(output.contains("1:needle two\n"));
+        assert!(output.contains("1:needle one\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_blocks_merge_overlapping_and_adjacent() {
+        // Overlapping: 3..=7 and 5..=9
+        assert_eq!(context_blocks([(5, 2, 2), (7, 2, 2)]), vec![3..=9]);
+        // Adjacent: 3..=7 and 8..=12 print as one block without a separator
+        assert_eq!(context_blocks([(5, 2, 2), (10, 2, 2)]), vec![3..=12]);
+        // One unprinted line (8) between them keeps the blocks apart
+        assert_eq!(context_blocks([(5, 2, 2), (11, 2, 2)]), vec![3..=7, 9..=13]);
+        // A span inside another doesn't shrink it
+        assert_eq!(context_blocks([(10, 5, 5), (11, 0, 0)]), vec![5..=15]);
+        // Input order doesn't matter
+        assert_eq!(
+            context_blocks([(20, 0, 0), (2, 1, 1), (3, 1, 1)]),
+            vec![1..=4, 20..=20]
+        );
+        assert!(context_blocks([]).is_empty());
+    }
+
+    #[test]
+    fn test_context_blocks_at_file_boundaries() {
+        // Context can't start before line 1
+        assert_eq!(context_blocks([(1, 3, 1), (2, 3, 0)]), vec![1..=2]);
+        // Near the end of the file matches carry fewer context lines, so
+        // the block ends at the last line that exists
+        assert_eq!(context_blocks([(9, 2, 1), (10, 2, 0)]), vec![7..=10]);
+    }
+
+    #[test]
+    fn test_overlapping_context_prints_once() {
+        let line = |n: usize| (n, format!("line {}", n));
+        let mut m1 = match_at(3, "x", 0, 1);
+        m1.context_before = vec![line(1), line(2)];
+        m1.context_after = vec![line(4), line(5)];
+        let mut m2 = match_at(5, "x", 0, 1);
+        m2.context_before = vec![line(3), line(4)];
+        m2.context_after = vec![line(6), line(7)];
+        let mut m3 = match_at(10, "x", 0, 1);
+        m3.context_before = vec![line(8), line(9)];
+        m3.context_after = vec![];
+
+        let mut printer = Printer::new(Vec::new(), options(PrintStyle::Lines, true));
+        printer
+            .print_file(&FileResult {
+                path: PathBuf::from("a.rs"),
+                matches: vec![m1, m2, m3],
+                line_ending: None,
+            })
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(printer.into_inner()).unwrap(),
+            "a.rs\n1-line 1\n2-line 2\n3:x\n4-line 4\n5:x\n6-line 6\n7-line 7\n\
+             8-line 8\n9-line 9\n10:x\n"
+        );
+    }
+
+    #[test]
+    fn test_truncation_window_centers_on_focus() {
+        let line = "a".repeat(100);
+        assert_eq!(truncation_window(&line, 50..52, 10), 46..56);
+        // Clamped at either end of the line
+        assert_eq!(truncation_window(&line, 0..2, 10), 0..10);
+        assert_eq!(truncation_window(&line, 98..100, 10), 90..100);
+        // A focus wider than the window keeps its start
+        assert_eq!(truncation_window(&line, 20..60, 10), 20..30);
+        // Short lines are left alone
+        assert_eq!(truncation_window("short", 0..1, 10), 0..5);
+    }
+
+    #[test]
+    fn test_truncation_window_respects_char_boundaries() {
+        // Each 'é' is two bytes, each '世' three
+        let line = format!("{}needle{}", "é".repeat(20), "世".repeat(20));
+        let focus = 40..46;
+        for max_columns in 6..30 {
+            let window = truncation_window(&line, focus.clone(), max_columns);
+            assert!(line.is_char_boundary(window.start));
+            assert!(line.is_char_boundary(window.end));
+            assert!(window.len() <= max_columns);
+            assert!(window.start <= focus.start && window.end >= focus.end);
+        }
+    }
+
+    #[test]
+    fn test_long_lines_are_truncated_or_omitted() {
+        let line = format!("{}needle{}", "x".repeat(50), "y".repeat(50));
+        let file_result = FileResult {
+            path: PathBuf::from("min.js"),
+            matches: vec![Match {
+                context_before: Vec::new(),
+                context_after: Vec::new(),
+                ..match_at(1, &line, 50, 56)
+            }],
+            line_ending: None,
+        };
+
+        let mut opts = options(PrintStyle::Lines, true);
+        opts.max_columns = Some(16);
+        let mut printer = Printer::new(Vec::new(), opts.clone());
+        printer.print_file(&file_result).unwrap();
+        assert_eq!(
+            String::from_utf8(printer.into_inner()).unwrap(),
+            "min.js\n1:...xxxxxneedleyyyyy... [... 90 bytes omitted]\n"
+        );
+
+        opts.omit_long_lines = true;
+        let mut printer = Printer::new(Vec::new(), opts);
+        printer.print_file(&file_result).unwrap();
+        assert_eq!(
+            String::from_utf8(printer.into_inner()).unwrap(),
+            "min.js\n1:[Omitted long line with 1 matches]\n"
+        );
+    }
+}
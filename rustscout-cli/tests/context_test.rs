@@ -0,0 +1,34 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::{tempdir, TempDir};
+
+fn search(dir: &TempDir, args: &[&str]) -> Result<Command> {
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["search", "-d", dir.path().to_str().unwrap()])
+        .args(args);
+    Ok(cmd)
+}
+
+#[test]
+fn test_context_flag_merges_blocks() -> Result<()> {
+    let dir = tempdir()?;
+    let lines: Vec<String> = (1..=12).map(|i| format!("line {}", i)).collect();
+    let mut content = lines.join("\n");
+    content = content
+        .replace("line 3", "TODO 3")
+        .replace("line 5", "TODO 5");
+    content = content.replace("line 11", "TODO 11");
+    std::fs::write(dir.path().join("notes.txt"), content + "\n")?;
+
+    search(&dir, &["-p", "TODO", "--context", "1", "--no-heading"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::function(|out: &str| {
+            out.matches("--\n").count() == 1 && out.matches("line 4").count() == 1
+        }));
+    search(&dir, &["-p", "TODO", "--context", "1", "-B", "2"])?
+        .assert()
+        .code(2);
+    Ok(())
+}
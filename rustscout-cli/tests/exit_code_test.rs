@@ -0,0 +1,112 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::{tempdir, TempDir};
+
+fn search_dir() -> Result<TempDir> {
+    let temp_dir = tempdir()?;
+    std::fs::write(temp_dir.path().join("notes.txt"), "TODO: write docs\n")?;
+    Ok(temp_dir)
+}
+
+fn search(dir: &TempDir, args: &[&str]) -> Result<Command> {
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["search", "-d", dir.path().to_str().unwrap()])
+        .args(args);
+    Ok(cmd)
+}
+
+#[test]
+fn test_search_exit_codes() -> Result<()> {
+    let dir = search_dir()?;
+
+    search(&dir, &["-p", "TODO"])?.assert().code(0);
+    search(&dir, &["-p", "FIXME"])?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("0 matches across 0 files"));
+    search(&dir, &["-p", "(unclosed", "-r", "true"])?
+        .assert()
+        .code(2);
+    Ok(())
+}
+
+#[test]
+fn test_quiet_prints_nothing() -> Result<()> {
+    let dir = search_dir()?;
+
+    search(&dir, &["-p", "TODO", "-q"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::is_empty());
+    search(&dir, &["-p", "FIXME", "-q"])?
+        .assert()
+        .code(1)
+        .stdout(predicate::str::is_empty());
+
+    // --stats still prints on its own, but not with --quiet
+    search(&dir, &["-p", "TODO", "--stats"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("1 matches across 1 files"));
+    search(&dir, &["-p", "TODO", "--stats", "-q"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::is_empty());
+    Ok(())
+}
+
+#[test]
+fn test_replace_keeps_exit_status() -> Result<()> {
+    let dir = search_dir()?;
+
+    // A replacement with nothing to replace still succeeds
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["replace", "do", "-p", "FIXME", "-r", "DONE", "-n"])
+        .arg(dir.path().join("notes.txt"));
+    cmd.assert().code(0);
+    Ok(())
+}
+
+#[test]
+fn test_pattern_file_takes_its_own_regex_flag() -> Result<()> {
+    let dir = tempdir()?;
+    std::fs::write(dir.path().join("notes.txt"), "TODO: write\ndocs\n")?;
+    let patterns = tempdir()?;
+    let pattern_file = patterns.path().join("patterns.txt");
+    std::fs::write(&pattern_file, "write.docs\n")?;
+    let pattern_file = pattern_file.to_str().unwrap();
+
+    // As a regex the dot spans the line break; -r true after -p leaves the file literal
+    search(&dir, &["--multiline", "-f", pattern_file, "-r", "true"])?
+        .assert()
+        .code(0);
+    search(
+        &dir,
+        &[
+            "--multiline",
+            "-p",
+            "FIX+ME",
+            "-r",
+            "true",
+            "-f",
+            pattern_file,
+        ],
+    )?
+    .assert()
+    .code(1);
+    Ok(())
+}
+
+#[test]
+fn test_invert_match_short_flag() -> Result<()> {
+    let dir = search_dir()?;
+
+    search(&dir, &["-p", "FIXME", "-v"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("TODO: write docs"));
+    search(&dir, &["-p", "TODO", "-v"])?.assert().code(1);
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::{tempdir, TempDir};
+
+fn search_dir() -> Result<TempDir> {
+    let temp_dir = tempdir()?;
+    for name in [
+        "main.rs",
+        "bindings.rs.in",
+        "app.tsx",
+        "README.md",
+        "Cargo.toml",
+    ] {
+        std::fs::write(temp_dir.path().join(name), "TODO\n")?;
+    }
+    Ok(temp_dir)
+}
+
+fn search(dir: &TempDir, args: &[&str]) -> Result<Command> {
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["search", "-d", dir.path().to_str().unwrap(), "-p", "TODO"])
+        .arg("--files-with-matches")
+        .args(args);
+    Ok(cmd)
+}
+
+#[test]
+fn test_type_selects_files() -> Result<()> {
+    let dir = search_dir()?;
+
+    search(&dir, &["-t", "rust"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("main.rs"))
+        .stdout(predicate::str::contains("bindings.rs.in"))
+        .stdout(predicate::str::contains("app.tsx").not());
+    // -x and --type add up
+    search(&dir, &["-t", "rust", "-x", "toml"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("Cargo.toml"))
+        .stdout(predicate::str::contains("main.rs"))
+        .stdout(predicate::str::contains("README.md").not());
+    search(&dir, &["--type-not", "markdown", "--type-not", "ts"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("README.md").not())
+        .stdout(predicate::str::contains("app.tsx").not())
+        .stdout(predicate::str::contains("Cargo.toml"));
+    search(&dir, &["-t", "cobol"])?
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("Unknown file type 'cobol'"));
+    Ok(())
+}
+
+#[test]
+fn test_custom_types() -> Result<()> {
+    let dir = search_dir()?;
+    std::fs::write(dir.path().join("api.proto"), "TODO\n")?;
+    std::fs::write(dir.path().join("data.ron"), "TODO\n")?;
+
+    search(&dir, &["--type-add", "proto:*.proto", "-t", "proto"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("api.proto"))
+        .stdout(predicate::str::contains("main.rs").not());
+
+    // Types from the workspace config
+    std::fs::create_dir_all(dir.path().join(".rustscout"))?;
+    std::fs::write(
+        dir.path().join(".rustscout/config.yaml"),
+        "types:\n  ron: ['*.ron']\n",
+    )?;
+    search(&dir, &["-t", "ron"])?
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("data.ron"))
+        .stdout(predicate::str::contains("api.proto").not());
+
+    Command::cargo_bin("rustscout-cli")?
+        .args(["search", "-d", dir.path().to_str().unwrap(), "--type-list"])
+        .args(["--type-add", "proto:*.proto"])
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("rust: *.rs, *.rs.in\n"))
+        .stdout(predicate::str::contains("proto: *.proto\n"))
+        .stdout(predicate::str::contains("ron: *.ron\n"));
+    Ok(())
+}
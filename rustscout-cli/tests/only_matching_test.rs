@@ -0,0 +1,74 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use tempfile::{tempdir, TempDir};
+
+fn versions_dir() -> Result<TempDir> {
+    let temp_dir = tempdir()?;
+    std::fs::write(
+        temp_dir.path().join("versions.txt"),
+        "version 1.2.3 and 4.5.6\nnone here\nv7.8.9\n",
+    )?;
+    Ok(temp_dir)
+}
+
+fn search(dir: &TempDir, args: &[&str]) -> Result<Command> {
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(dir.path())
+        .args(["search", "-d", ".", "-N", "-r", "true"])
+        .args(["-p", r"\d+\.\d+\.\d+"])
+        .args(args);
+    Ok(cmd)
+}
+
+const SUMMARY: &str = "\n3 matches across 1 files\n";
+
+#[test]
+fn test_only_matching_prints_each_match() -> Result<()> {
+    let dir = versions_dir()?;
+
+    search(&dir, &["-o"])?.assert().code(0).stdout(format!(
+        "./versions.txt:1:9:1.2.3\n./versions.txt:1:19:4.5.6\n./versions.txt:3:2:7.8.9\n{}",
+        SUMMARY
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_no_filename() -> Result<()> {
+    let dir = versions_dir()?;
+
+    search(&dir, &["-o", "--no-filename"])?
+        .assert()
+        .code(0)
+        .stdout(format!("1:9:1.2.3\n1:19:4.5.6\n3:2:7.8.9\n{}", SUMMARY));
+    search(&dir, &["--no-filename"])?
+        .assert()
+        .code(0)
+        .stdout(format!("1:version 1.2.3 and 4.5.6\n3:v7.8.9\n{}", SUMMARY));
+    Ok(())
+}
+
+#[test]
+fn test_no_line_number() -> Result<()> {
+    let dir = versions_dir()?;
+
+    search(&dir, &["-o", "--no-line-number"])?
+        .assert()
+        .code(0)
+        .stdout(format!(
+            "./versions.txt:9:1.2.3\n./versions.txt:19:4.5.6\n./versions.txt:2:7.8.9\n{}",
+            SUMMARY
+        ));
+    search(&dir, &["--no-line-number"])?
+        .assert()
+        .code(0)
+        .stdout(format!(
+            "./versions.txt:version 1.2.3 and 4.5.6\n./versions.txt:v7.8.9\n{}",
+            SUMMARY
+        ));
+    search(&dir, &["--no-filename", "--no-line-number"])?
+        .assert()
+        .code(0)
+        .stdout(format!("version 1.2.3 and 4.5.6\nv7.8.9\n{}", SUMMARY));
+    Ok(())
+}
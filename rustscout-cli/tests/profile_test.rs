@@ -0,0 +1,104 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+const CONFIG: &str = r#"
+profiles:
+  todo-audit:
+    patterns:
+      - text: TODO
+      - text: FIXME
+    file_extensions: [rs]
+"#;
+
+#[test]
+fn test_search_with_profile() -> Result<()> {
+    let dir = tempdir()?;
+    std::fs::create_dir_all(dir.path().join(".rustscout"))?;
+    std::fs::write(dir.path().join(".rustscout/config.yaml"), CONFIG)?;
+    std::fs::create_dir_all(dir.path().join("src"))?;
+    std::fs::write(
+        dir.path().join("src/lib.rs"),
+        "// TODO: one\n// FIXME: two\n// XXX: three\n",
+    )?;
+    std::fs::write(dir.path().join("notes.txt"), "TODO: not an .rs file\n")?;
+    let root = dir.path().join("src");
+    let root = root.to_str().unwrap();
+
+    Command::cargo_bin("rustscout-cli")?
+        .args(["search", "-d", root, "--profile", "todo-audit", "--stats"])
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("2 matches across 1 files"));
+
+    // Extra -p patterns are searched for too
+    Command::cargo_bin("rustscout-cli")?
+        .args(["search", "-d", root, "--profile", "todo-audit"])
+        .args(["-p", "XXX", "--stats"])
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("3 matches across 1 files"));
+
+    Command::cargo_bin("rustscout-cli")?
+        .args(["search", "-d", root, "--profile", "weekly"])
+        .assert()
+        .code(2)
+        .stderr(predicate::str::contains("available profiles: todo-audit"));
+    Ok(())
+}
+
+#[test]
+fn test_flags_override_workspace_defaults() -> Result<()> {
+    let dir = tempdir()?;
+    std::fs::create_dir_all(dir.path().join(".rustscout"))?;
+    std::fs::write(
+        dir.path().join(".rustscout/config.yaml"),
+        "context_after: 2\n",
+    )?;
+    std::fs::write(dir.path().join("notes.txt"), "a\nTODO\nb\nc\n")?;
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(dir.path())
+        .args(["search", "-N", "-d", ".", "-p", "TODO"])
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("./notes.txt:4-c"));
+
+    // -A 0 wins over the configured context even though it is the default
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(dir.path())
+        .args(["search", "-N", "-d", ".", "-p", "TODO", "-A", "0"])
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("./notes.txt:2:TODO"))
+        .stdout(predicate::str::contains("./notes.txt:3-b").not());
+    Ok(())
+}
+
+#[test]
+fn test_no_incremental_overrides_workspace_defaults() -> Result<()> {
+    let dir = tempdir()?;
+    std::fs::create_dir_all(dir.path().join(".rustscout"))?;
+    std::fs::write(
+        dir.path().join(".rustscout/config.yaml"),
+        "incremental: true\ncache_path: search-cache.json\n",
+    )?;
+    std::fs::write(dir.path().join("notes.txt"), "TODO\n")?;
+    let cache = dir.path().join("search-cache.json");
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(dir.path())
+        .args(["search", "-N", "-d", ".", "-p", "TODO", "--no-incremental"])
+        .assert()
+        .code(0);
+    assert!(!cache.exists());
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(dir.path())
+        .args(["search", "-N", "-d", ".", "-p", "TODO"])
+        .assert()
+        .code(0);
+    assert!(cache.exists());
+    Ok(())
+}
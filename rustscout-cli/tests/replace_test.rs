@@ -0,0 +1,811 @@
+use anyhow::Result;
+use assert_cmd::Command;
+use predicates::prelude::*;
+use tempfile::tempdir;
+
+#[test]
+fn test_preserve_case_dry_run() -> Result<()> {
+    let dir = tempdir()?;
+    let file = dir.path().join("colors.txt");
+    std::fs::write(&file, "color Color COLOR colorMap\n")?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args([
+        "replace",
+        "do",
+        "-p",
+        "color",
+        "-r",
+        "colour",
+        "--preserve-case",
+        "-n",
+    ])
+    .arg(&file);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("+colour Colour COLOUR colourMap"));
+    assert_eq!(
+        std::fs::read_to_string(&file)?,
+        "color Color COLOR colorMap\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_several_patterns_in_one_pass() -> Result<()> {
+    let dir = tempdir()?;
+    let file = dir.path().join("api.rs");
+    std::fs::write(&file, "oldApi(v1);\nlegacy(v22);\noldApi(legacy);\n")?;
+    let config = dir.path().join("replace.yaml");
+    std::fs::write(
+        &config,
+        r#"patterns:
+  - definition:
+      text: 'v(\d+)'
+      is_regex: true
+    replacement_text: 'version_$1'
+backup_enabled: false
+dry_run: false
+backup_dir: null
+preserve_metadata: false
+undo_dir: .rustscout/undo
+"#,
+    )?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["replace", "do", "-c"])
+        .arg(&config)
+        .args([
+            "-p", "oldApi", "-r", "newApi", "-p", "legacy", "-r", "modern",
+        ])
+        .arg(&file);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(&file)?,
+        "newApi(version_1);\nmodern(version_22);\nnewApi(modern);\n"
+    );
+
+    // Patterns and replacements must pair up
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["replace", "do", "-p", "a", "-p", "b", "-r", "c", "-n"])
+        .arg(&file);
+    cmd.assert().failure().stderr(predicate::str::contains(
+        "got 2 patterns and 1 replacements",
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_overlapping_patterns_are_rejected() -> Result<()> {
+    let dir = tempdir()?;
+    let file = dir.path().join("names.txt");
+    std::fs::write(&file, "first\nfoobar\n")?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args([
+        "replace", "do", "-p", "foo", "-r", "x", "-p", "oba", "-r", "y", "-n",
+    ])
+    .arg(&file);
+    cmd.assert().failure().stderr(
+        predicate::str::contains("names.txt:2")
+            .and(predicate::str::contains("pattern 'foo' (#1)"))
+            .and(predicate::str::contains("pattern 'oba' (#2)")),
+    );
+    assert_eq!(std::fs::read_to_string(&file)?, "first\nfoobar\n");
+    Ok(())
+}
+
+#[test]
+fn test_interactive_replace_previews_approved_only() -> Result<()> {
+    let dir = tempdir()?;
+    let file = dir.path().join("notes.txt");
+    std::fs::write(&file, "old a\nold b\n")?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["replace", "do", "-p", "old", "-r", "new", "-i", "-n"])
+        .arg(&file)
+        .write_stdin("n\ny\n");
+    cmd.assert().success().stdout(
+        predicate::str::contains("1 replacements approved")
+            .and(predicate::str::contains("-old b\n+new b"))
+            .and(predicate::str::contains("-old a").not()),
+    );
+    Ok(())
+}
+
+#[test]
+fn test_backup_and_file_filter_flags() -> Result<()> {
+    let dir = tempdir()?;
+    let notes = dir.path().join("notes.txt");
+    let code = dir.path().join("main.rs");
+    std::fs::write(&notes, "old notes\n")?;
+    std::fs::write(&code, "// old code\n")?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(dir.path()).args([
+        "replace", "do", "-p", "old", "-r", "new", "-f", "*.txt", ".",
+    ]);
+    cmd.assert().success();
+
+    assert_eq!(std::fs::read_to_string(&notes)?, "new notes\n");
+    assert_eq!(std::fs::read_to_string(&code)?, "// old code\n");
+    // No -B, so nothing was backed up
+    assert!(!dir.path().join(".rustscout").exists());
+
+    // A file named outright is filtered too
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["replace", "do", "-p", "old", "-r", "new", "-f", "*.txt"])
+        .arg(&code);
+    cmd.assert().success();
+    assert_eq!(std::fs::read_to_string(&code)?, "// old code\n");
+    Ok(())
+}
+
+#[test]
+fn test_backup_dir_flag() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::create_dir(root.join(".rustscout"))?;
+    std::fs::write(root.join("notes.txt"), "old notes\n")?;
+    let backups = tempdir()?;
+    let backup_dir = backups.path().join("rustscout");
+
+    // A dry run creates nothing
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args([
+            "replace",
+            "do",
+            "-p",
+            "old",
+            "-r",
+            "new",
+            "-n",
+            "--backup-dir",
+        ])
+        .arg(&backup_dir)
+        .arg("notes.txt")
+        .assert()
+        .success();
+    assert!(!backup_dir.exists());
+
+    // --backup-dir implies -B, and the message says where the backups went
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "do", "-p", "old", "-r", "new", "--backup-dir"])
+        .arg(&backup_dir)
+        .arg("notes.txt")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Backups are in {}",
+            backup_dir.canonicalize()?.display()
+        )));
+    assert_eq!(
+        std::fs::read_to_string(root.join("notes.txt"))?,
+        "new notes\n"
+    );
+    assert_eq!(std::fs::read_dir(&backup_dir)?.count(), 1);
+    assert!(!root.join(".rustscout/backups").exists());
+
+    let id = std::fs::read_dir(root.join(".rustscout/undo"))?
+        .next()
+        .unwrap()?
+        .path()
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(root.join("notes.txt"))?,
+        "old notes\n"
+    );
+
+    // A file where the directory should be is refused up front
+    let file = backups.path().join("taken");
+    std::fs::write(&file, "")?;
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "do", "-p", "old", "-r", "new", "--backup-dir"])
+        .arg(&file)
+        .arg("notes.txt")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("is not a directory"));
+    assert_eq!(
+        std::fs::read_to_string(root.join("notes.txt"))?,
+        "old notes\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_replace_from_another_directory() -> Result<()> {
+    let workdir = tempdir()?;
+    let target = tempdir()?;
+    let nested = target.path().join("src");
+    std::fs::create_dir(&nested)?;
+    let in_dir = nested.join("lib.rs");
+    let named = target.path().join("notes.txt");
+    std::fs::write(&in_dir, "fn first() {}\nfn old() {}\n")?;
+    std::fs::write(&named, "old\nold again\n")?;
+
+    // A directory and a file, neither under the working directory
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(workdir.path())
+        .args(["replace", "do", "-p", "old", "-r", "new"])
+        .arg(&nested)
+        .arg(&named);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(&in_dir)?,
+        "fn first() {}\nfn new() {}\n"
+    );
+    assert_eq!(std::fs::read_to_string(&named)?, "new\nnew again\n");
+
+    // A relative path resolves against the working directory
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(target.path())
+        .args(["replace", "do", "-p", "new", "-r", "newer", "src"]);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(&in_dir)?,
+        "fn first() {}\nfn newer() {}\n"
+    );
+    assert!(!workdir.path().join(".rustscout").exists());
+    Ok(())
+}
+
+#[test]
+fn test_json_report() -> Result<()> {
+    let dir = tempdir()?;
+    let file = dir.path().join("notes.txt");
+    std::fs::write(&file, "old a\nkeep\nold b\n")?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args([
+        "replace", "do", "-p", "old", "-r", "new", "-n", "--format", "json",
+    ])
+    .arg(&file);
+    let output = cmd.assert().success().get_output().stdout.clone();
+    let report: serde_json::Value = serde_json::from_slice(&output)?;
+    assert_eq!(report["dry_run"], true);
+    assert_eq!(report["files"][0]["replacements"], 2);
+    assert_eq!(report["files"][0]["hunks"][1]["new_lines"][0], "new b");
+    assert_eq!(std::fs::read_to_string(&file)?, "old a\nkeep\nold b\n");
+    Ok(())
+}
+
+#[test]
+fn test_max_per_file() -> Result<()> {
+    let dir = tempdir()?;
+    let file = dir.path().join("years.txt");
+    std::fs::write(&file, "2023\n2023 2023\n2023\n2023\n")?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args([
+        "replace",
+        "do",
+        "-p",
+        "2023",
+        "-r",
+        "2024",
+        "--max-per-file",
+        "2",
+    ])
+    .arg(&file);
+    cmd.assert()
+        .success()
+        .stdout(predicate::str::contains("3 matches left alone"));
+    assert_eq!(
+        std::fs::read_to_string(&file)?,
+        "2024\n2024 2023\n2023\n2023\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_ignored_files_are_left_alone() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    for sub in [".git", "src", "generated", "vendor", ".rustscout/undo"] {
+        std::fs::create_dir_all(root.join(sub))?;
+    }
+    std::fs::write(root.join(".gitignore"), "generated/\n")?;
+    let files = [
+        ("src/lib.rs", true),
+        ("generated/out.rs", false),
+        ("vendor/dep.rs", false),
+        (".git/HEAD", false),
+        (".rustscout/undo/1.json", false),
+    ];
+    for (name, _) in files {
+        std::fs::write(root.join(name), "old_api();\n")?;
+    }
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(root)
+        .args(["replace", "do", "-p", "old_api", "-r", "new_api", "-g"])
+        .args(["**/vendor/**", "."]);
+    cmd.assert().success();
+    for (name, replaced) in files {
+        let expected = if replaced {
+            "new_api();\n"
+        } else {
+            "old_api();\n"
+        };
+        assert_eq!(
+            std::fs::read_to_string(root.join(name))?,
+            expected,
+            "{}",
+            name
+        );
+    }
+
+    // Naming workspace data outright doesn't reach it either
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(root)
+        .args(["replace", "do", "-p", "old_api", "-r", "new_api"])
+        .arg(".rustscout/undo/1.json");
+    cmd.assert()
+        .success()
+        .stderr(predicate::str::contains("skipping .rustscout/undo/1.json"));
+    assert_eq!(
+        std::fs::read_to_string(root.join(".rustscout/undo/1.json"))?,
+        "old_api();\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_no_backup_overrides_config_file() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::create_dir(root.join(".rustscout"))?;
+    std::fs::write(root.join("notes.txt"), "old notes\n")?;
+    std::fs::write(
+        root.join("replace.yaml"),
+        "patterns: []\nbackup_enabled: true\ndry_run: false\nbackup_dir: null\npreserve_metadata: true\nundo_dir: .rustscout/undo\n",
+    )?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(root).args([
+        "replace",
+        "do",
+        "-c",
+        "replace.yaml",
+        "-p",
+        "old",
+        "-r",
+        "new",
+        "--no-backup",
+        "notes.txt",
+    ]);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(root.join("notes.txt"))?,
+        "new notes\n"
+    );
+    assert!(!root.join(".rustscout/backups").exists());
+
+    // Without the flag, the config file's backups are made
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(root).args([
+        "replace",
+        "do",
+        "-c",
+        "replace.yaml",
+        "-p",
+        "new",
+        "-r",
+        "newer",
+        "notes.txt",
+    ]);
+    cmd.assert().success();
+    assert!(root.join(".rustscout/backups").read_dir()?.next().is_some());
+    Ok(())
+}
+
+#[test]
+fn test_save_config_for_reuse() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::write(root.join("a.txt"), "old_api old_api\n")?;
+    std::fs::write(root.join("b.txt"), "old_api old_api\n")?;
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(root).args([
+        "replace",
+        "do",
+        "-p",
+        "old_api",
+        "-r",
+        "new_api",
+        "--max-per-file",
+        "1",
+        "--save-config",
+        "replace.json",
+        "a.txt",
+    ]);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(root.join("a.txt"))?,
+        "new_api old_api\n"
+    );
+
+    // The saved JSON runs the same replacement, limit included
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.current_dir(root)
+        .args(["replace", "do", "-c", "replace.json", "b.txt"]);
+    cmd.assert().success();
+    assert_eq!(
+        std::fs::read_to_string(root.join("b.txt"))?,
+        "new_api old_api\n"
+    );
+    Ok(())
+}
+
+#[test]
+fn test_stdin_filter() -> Result<()> {
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["replace", "do", "-p", "cat", "-r", "dog", "-w", "--stdin"])
+        .write_stdin("cat concat\ncat\n");
+    cmd.assert().success().stdout("dog concat\ndog\n");
+
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args([
+        "replace", "do", "-x", "-p", r"v(\d+)", "-r", "v$1.0", "--stdin",
+    ])
+    .write_stdin("v1 v22\r\n");
+    cmd.assert().success().stdout("v1.0 v22.0\r\n");
+
+    // A dry run only shows the diff, on stderr
+    let mut cmd = Command::cargo_bin("rustscout-cli")?;
+    cmd.args(["replace", "do", "-p", "cat", "-r", "dog", "--stdin", "-n"])
+        .write_stdin("cat\nkeep\n");
+    cmd.assert()
+        .success()
+        .stdout("")
+        .stderr(predicate::str::contains("-cat\n+dog\n"));
+    Ok(())
+}
+
+#[test]
+fn test_rename_and_undo() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::create_dir_all(root.join(".rustscout/undo"))?;
+    std::fs::create_dir_all(root.join("src/old_service"))?;
+    std::fs::write(root.join("src/old_service/old_service_api.rs"), "api")?;
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args([
+            "replace",
+            "rename",
+            "-p",
+            "old_service",
+            "-r",
+            "billing",
+            "-n",
+            "src",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "src/old_service/old_service_api.rs → src/old_service/billing_api.rs",
+        ))
+        .stdout(predicate::str::contains("src/old_service → src/billing"));
+    assert!(root.join("src/old_service/old_service_api.rs").exists());
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args([
+            "replace",
+            "rename",
+            "-p",
+            "old_service",
+            "-r",
+            "billing",
+            "src",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Renamed 2 paths."));
+    assert_eq!(
+        std::fs::read_to_string(root.join("src/billing/billing_api.rs"))?,
+        "api"
+    );
+
+    let id = std::fs::read_dir(root.join(".rustscout/undo"))?
+        .next()
+        .unwrap()?
+        .path()
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id])
+        .assert()
+        .success();
+    assert!(root.join("src/old_service/old_service_api.rs").exists());
+    assert!(!root.join("src/billing").exists());
+    Ok(())
+}
+
+#[test]
+fn test_list_undo_history() -> Result<()> {
+    let dir = tempdir()?;
+    let undo_dir = dir.path().join(".rustscout/undo");
+    std::fs::create_dir_all(&undo_dir)?;
+    std::fs::write(
+        undo_dir.join("1700000200.json"),
+        r#"{"timestamp":1700000200,"description":"Replace 'b' with 'c'","backups":[],"total_size":2048,"file_count":2,"dry_run":false}"#,
+    )?;
+    std::fs::write(
+        undo_dir.join("1700000100.json"),
+        r#"{"timestamp":1700000100,"description":"Replace 'a' with 'b'","backups":[],"total_size":0,"file_count":1,"dry_run":false,
+            "file_diffs":[{"file_path":{"rel_path":"src/lib.rs","abs_path":null},"hunks":[{"original_start_line":3,"new_start_line":3,"original_line_count":1,"new_line_count":1,"original_lines":["a"],"new_lines":["b"]}]}]}"#,
+    )?;
+
+    let output = Command::cargo_bin("rustscout-cli")?
+        .current_dir(dir.path())
+        .args(["replace", "list", "--verbose"])
+        .output()?;
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout)?;
+    let first = stdout.find("ID: 1700000100  2023-11-14T22:15:00Z  =>  Replace 'a' with 'b'");
+    let second = stdout.find("ID: 1700000200  2023-11-14T22:16:40Z  =>  Replace 'b' with 'c'");
+    assert!(
+        first.is_some() && second.is_some() && first < second,
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("  1 files, 0 B, partial revert available"));
+    assert!(stdout.contains("  2 files, 2.0 KiB, full revert only"));
+    assert!(stdout.contains("    Hunk 1: lines 3-3 replaced with lines 3-3"));
+
+    Command::cargo_bin("rustscout-cli")?
+        .args(["replace", "list", "--format", "json", "--undo-dir"])
+        .arg(&undo_dir)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(r#""id": 1700000100"#))
+        .stdout(predicate::str::contains(r#""partial_revert": false"#))
+        .stdout(predicate::str::contains("src/lib.rs").not());
+    Ok(())
+}
+
+#[test]
+fn test_undo_history_shows_patterns_and_settings() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::create_dir_all(root.join(".rustscout"))?;
+    std::fs::write(root.join("notes.txt"), "old notes, old notes\n")?;
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args([
+            "replace",
+            "do",
+            "-p",
+            "old",
+            "-r",
+            "new",
+            "-w",
+            "--first-per-line",
+            "-B",
+            "notes.txt",
+        ])
+        .assert()
+        .success();
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "list", "--verbose"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "  Pattern: 'old' -> 'new' (boundary WholeWords)",
+        ))
+        .stdout(predicate::str::contains(
+            "  Settings: backups, encoding FailFast, first match per line",
+        ));
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Pattern:").not());
+
+    let id = std::fs::read_dir(root.join(".rustscout/undo"))?
+        .next()
+        .unwrap()?
+        .path()
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id, "--list-hunks"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Pattern: 'old' -> 'new' (boundary WholeWords)",
+        ));
+    Ok(())
+}
+
+#[test]
+fn test_undo_single_file_of_operation() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::create_dir_all(root.join(".rustscout"))?;
+    std::fs::create_dir_all(root.join("src"))?;
+    std::fs::write(root.join("src/config.rs"), "old\nkeep\nkeep\nkeep\nold\n")?;
+    std::fs::write(root.join("src/main.rs"), "old\n")?;
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "do", "-p", "old", "-r", "new", "-B", "src"])
+        .assert()
+        .success();
+    let id = std::fs::read_dir(root.join(".rustscout/undo"))?
+        .next()
+        .unwrap()?
+        .path()
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+
+    // Every hunk of one file, leaving the other alone
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id, "--files", "config.rs"])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert_eq!(
+        std::fs::read_to_string(root.join("src/config.rs"))?,
+        "old\nkeep\nkeep\nkeep\nold\n"
+    );
+    assert_eq!(std::fs::read_to_string(root.join("src/main.rs"))?, "new\n");
+
+    // Hunks numbered within a file
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id, "--hunks", "src/main.rs:0"])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(root.join("src/main.rs"))?, "old\n");
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id, "--files", "lib.rs"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("matches 'lib.rs'"));
+    Ok(())
+}
+
+#[test]
+fn test_prune_keep_last() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::create_dir_all(root.join(".rustscout/undo"))?;
+    std::fs::create_dir_all(root.join(".rustscout/backups"))?;
+    for id in [1700000100, 1700000200, 1700000300] {
+        let backup = format!(".rustscout/backups/{}_lib.rs", id);
+        std::fs::write(root.join(&backup), "fn old() {}\n")?;
+        std::fs::write(
+            root.join(format!(".rustscout/undo/{}.json", id)),
+            format!(
+                r#"{{"timestamp":{id},"description":"Operation {id}","backups":[[{{"rel_path":"lib.rs","abs_path":null}},{{"rel_path":"{backup}","abs_path":null}}]],"total_size":12,"file_count":1,"dry_run":false}}"#
+            ),
+        )?;
+    }
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "prune", "--keep-last", "1", "-n"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "Would remove 1700000100  =>  Operation 1700000100",
+        ))
+        .stdout(predicate::str::contains(
+            "Would remove 2 operations and 2 backups, 24 B",
+        ));
+    assert!(root.join(".rustscout/undo/1700000100.json").exists());
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "prune", "--keep-last", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed 2 operations"));
+    for (id, kept) in [(1700000100, false), (1700000200, false), (1700000300, true)] {
+        assert_eq!(
+            root.join(format!(".rustscout/undo/{}.json", id)).exists(),
+            kept
+        );
+        assert_eq!(
+            root.join(format!(".rustscout/backups/{}_lib.rs", id))
+                .exists(),
+            kept
+        );
+    }
+
+    // A limit is required
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "prune"])
+        .assert()
+        .failure();
+    Ok(())
+}
+
+#[test]
+fn test_undo_refuses_later_edits_without_force() -> Result<()> {
+    let dir = tempdir()?;
+    let root = dir.path();
+    std::fs::create_dir_all(root.join(".rustscout"))?;
+    let file = root.join("notes.txt");
+    std::fs::write(&file, "old notes\n")?;
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "do", "-p", "old", "-r", "new", "-B", "notes.txt"])
+        .assert()
+        .success();
+    let id = std::fs::read_dir(root.join(".rustscout/undo"))?
+        .next()
+        .unwrap()?
+        .path()
+        .file_stem()
+        .unwrap()
+        .to_string_lossy()
+        .into_owned();
+    // Edits elsewhere survive an undo, which follows the changed lines
+    std::fs::write(&file, "header\nnew notes\n")?;
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id])
+        .write_stdin("y\n")
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&file)?, "header\nold notes\n");
+
+    // Once the changed line itself is edited, only --force reverts it
+    std::fs::write(&file, "rewritten notes\n")?;
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("edited after the operation"))
+        .stderr(predicate::str::contains("notes.txt"));
+    assert_eq!(std::fs::read_to_string(&file)?, "rewritten notes\n");
+
+    Command::cargo_bin("rustscout-cli")?
+        .current_dir(root)
+        .args(["replace", "undo", &id, "--force"])
+        .assert()
+        .success();
+    assert_eq!(std::fs::read_to_string(&file)?, "old notes\n");
+    Ok(())
+}
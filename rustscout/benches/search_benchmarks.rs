@@ -2,10 +2,11 @@
 
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use rustscout::{
-    cache::{ChangeDetectionStrategy, IncrementalCache},
-    config::SearchConfig,
+    cache::{CacheFormat, ChangeDetectionStrategy, IncrementalCache},
+    config::{Schedule, SearchConfig},
+    filters::FileTypes,
     search,
-    search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode},
+    search::matcher::{CaseMode, HyphenMode, PatternDefinition, WordBoundaryMode},
 };
 use std::{fs::File, io::Write, num::NonZeroUsize};
 use tempfile::tempdir;
@@ -36,8 +37,15 @@ fn create_base_config(dir: &tempfile::TempDir) -> SearchConfig {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -51,6 +59,37 @@ fn create_base_config(dir: &tempfile::TempDir) -> SearchConfig {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: rustscout::config::EncodingMode::default(),
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: rustscout::config::OutputMode::default(),
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     }
 }
 
@@ -58,7 +97,7 @@ fn bench_repeated_pattern(c: &mut Criterion) -> std::io::Result<()> {
     let dir = tempdir().unwrap();
     create_test_files(&dir, 1, 10)?;
 
-    let patterns = vec![
+    let patterns = [
         "TODO",
         r"TODO:.*\d+",
         r"FIXME:.*bug.*line \d+",
@@ -76,6 +115,13 @@ fn bench_repeated_pattern(c: &mut Criterion) -> std::io::Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }];
 
         group.bench_function(format!("pattern_{}", i), |b| {
@@ -92,6 +138,40 @@ fn bench_repeated_pattern(c: &mut Criterion) -> std::io::Result<()> {
     Ok(())
 }
 
+fn bench_many_literals(c: &mut Criterion) -> std::io::Result<()> {
+    let dir = tempdir().unwrap();
+    create_test_files(&dir, 10, 200)?;
+
+    let mut group = c.benchmark_group("Many Literals");
+    group.sample_size(20);
+    group.warm_up_time(std::time::Duration::from_secs(1));
+
+    // Dozens of literals share one automaton instead of one scan each
+    for count in [1, 10, 60] {
+        let mut config = create_base_config(&dir);
+        config.pattern_definitions = (0..count)
+            .map(|i| PatternDefinition::new(format!("task{}", i), false, WordBoundaryMode::None))
+            .chain(std::iter::once(PatternDefinition::new(
+                "TODO".to_string(),
+                false,
+                WordBoundaryMode::None,
+            )))
+            .collect();
+
+        group.bench_function(format!("literals_{}", count + 1), |b| {
+            b.iter_with_setup(
+                || config.clone(),
+                |cfg| {
+                    black_box(search(&cfg).unwrap());
+                },
+            );
+        });
+    }
+
+    group.finish();
+    Ok(())
+}
+
 fn bench_file_scaling(c: &mut Criterion) -> std::io::Result<()> {
     let dir = tempdir().unwrap();
     let file_counts = vec![1, 10, 100, 1000];
@@ -393,7 +473,7 @@ fn bench_change_detection(c: &mut Criterion) -> std::io::Result<()> {
 criterion_group! {
     name = benches;
     config = Criterion::default();
-    targets = bench_repeated_pattern, bench_file_scaling,
+    targets = bench_repeated_pattern, bench_many_literals, bench_file_scaling,
               bench_incremental_search, bench_cache_operations,
               bench_change_detection
 }
@@ -0,0 +1,119 @@
+use fs2::FileExt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::errors::{SearchError, SearchResult};
+
+/// How long to wait for another run to release the cache
+pub(crate) const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a contended lock is retried
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The lock file guarding a cache: `<cache>.lock`, next to it. The cache
+/// itself can't be locked, as saving renames a new file over it.
+pub fn lock_path(cache_path: &Path) -> PathBuf {
+    let mut name = cache_path.as_os_str().to_owned();
+    name.push(".lock");
+    PathBuf::from(name)
+}
+
+/// An advisory lock on a cache, released when dropped.
+///
+/// Readers share the lock; a writer holds it alone and records its PID in
+/// the lock file, so a lock that is held for long can be traced to its run.
+/// The lock file is left in place afterwards; the operating system drops the
+/// lock itself when its holder exits.
+#[derive(Debug)]
+pub(crate) struct CacheLock {
+    file: File,
+}
+
+impl CacheLock {
+    /// Takes a shared lock for reading the cache at `cache_path`
+    pub(crate) fn shared(cache_path: &Path, timeout: Duration) -> SearchResult<Self> {
+        Self::acquire(&lock_path(cache_path), false, timeout)
+    }
+
+    /// Takes the exclusive lock for writing the cache at `cache_path`
+    pub(crate) fn exclusive(cache_path: &Path, timeout: Duration) -> SearchResult<Self> {
+        Self::acquire(&lock_path(cache_path), true, timeout)
+    }
+
+    /// Waits up to `timeout` for the lock
+    fn acquire(path: &Path, exclusive: bool, timeout: Duration) -> SearchResult<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .map_err(SearchError::IoError)?;
+        let deadline = Instant::now() + timeout;
+        loop {
+            let locked = if exclusive {
+                FileExt::try_lock_exclusive(&file)
+            } else {
+                FileExt::try_lock_shared(&file)
+            };
+            match locked {
+                Ok(()) => break,
+                Err(e) if e.raw_os_error() == fs2::lock_contended_error().raw_os_error() => {
+                    if Instant::now() >= deadline {
+                        return Err(SearchError::CacheError(format!(
+                            "timed out waiting for the cache lock {}",
+                            path.display()
+                        )));
+                    }
+                    std::thread::sleep(POLL_INTERVAL);
+                }
+                Err(e) => return Err(SearchError::IoError(e)),
+            }
+        }
+
+        if exclusive {
+            file.set_len(0).map_err(SearchError::IoError)?;
+            write!(&file, "{}", std::process::id()).map_err(SearchError::IoError)?;
+        }
+        Ok(Self { file })
+    }
+}
+
+impl Drop for CacheLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_writers_exclude_each_other() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let cache_path = dir.path().join("cache.json");
+        let short = Duration::from_millis(50);
+
+        let writer = CacheLock::exclusive(&cache_path, short)?;
+        assert_eq!(
+            std::fs::read_to_string(lock_path(&cache_path))?,
+            std::process::id().to_string()
+        );
+        assert!(matches!(
+            CacheLock::exclusive(&cache_path, short),
+            Err(SearchError::CacheError(_))
+        ));
+        assert!(CacheLock::shared(&cache_path, short).is_err());
+        drop(writer);
+
+        let reader = CacheLock::shared(&cache_path, short)?;
+        let _other_reader = CacheLock::shared(&cache_path, short)?;
+        assert!(CacheLock::exclusive(&cache_path, short).is_err());
+        drop(reader);
+        Ok(())
+    }
+}
@@ -1,38 +1,113 @@
 mod detector;
+mod lock;
 
 pub use detector::{
     create_detector, ChangeDetectionStrategy, ChangeDetector, ChangeStatus, FileChangeInfo,
     FileSignatureDetector, GitStatusDetector,
 };
+pub use lock::lock_path;
 
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::time::SystemTime;
+use tracing::warn;
 
+use crate::config::SearchConfig;
 use crate::errors::{SearchError, SearchResult};
 use crate::results::Match;
+use lock::{CacheLock, LOCK_TIMEOUT};
 
-#[derive(Debug, Serialize, Deserialize, Default)]
+/// The first bytes of every gzip stream
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+
+/// Written before the contents of a binary cache
+const BINARY_MAGIC: &[u8] = b"RSCACHE\x01";
+
+/// How the incremental cache is stored on disk. Either format is read back
+/// whatever the current setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum CacheFormat {
+    /// JSON, readable for debugging (default)
+    #[default]
+    Json,
+    /// A compact binary encoding that loads and saves much faster on large
+    /// trees. Needs the `binary-cache` feature.
+    Binary,
+}
+
+/// How [`IncrementalCache::save_with`] writes the cache. The default is
+/// uncompressed JSON, as written by [`IncrementalCache::save_to`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CacheSaveOptions {
+    /// The format to write
+    pub format: CacheFormat,
+    /// Whether to gzip the encoded cache
+    pub compress: bool,
+}
+
+/// How many pattern sets' results are kept for each file
+pub const MAX_CACHED_PATTERN_SETS: usize = 4;
+
+/// Identifies the patterns, context settings and other settings deciding a
+/// file's matches (skipping minified files, decoding) that a search ran with,
+/// so cached matches are only reused by searches that would find the same ones.
+///
+/// The patterns are hashed in order rather than sorted: each match records
+/// the index of the pattern that found it.
+pub fn pattern_fingerprint(config: &SearchConfig) -> String {
+    let mut hasher = Sha256::new();
+    for definition in &config.pattern_definitions {
+        // Serializing covers every boundary, case and regex setting
+        hasher.update(serde_json::to_vec(definition).unwrap_or_default());
+        hasher.update([0]);
+    }
+    hasher.update(format!(
+        "{}:{}:{}:{:?}",
+        config.context_before, config.context_after, config.skip_minified, config.encoding_mode
+    ));
+    format!("{:x}", hasher.finalize())
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, PartialEq)]
 pub struct IncrementalCache {
     /// Maps absolute file paths to their cache entries
     pub files: HashMap<PathBuf, FileCacheEntry>,
     /// Metadata about the cache itself
     pub metadata: CacheMetadata,
+    /// Files whose entries this run removed, which a merged save mustn't
+    /// take back from the cache on disk
+    #[serde(skip)]
+    removed: HashSet<PathBuf>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct FileCacheEntry {
     /// File signature used to detect changes
     pub signature: FileSignature,
-    /// Cached search results, if any
-    pub search_results: Option<Vec<Match>>,
+    /// Cached matches for each pattern set the file was searched with, most
+    /// recently stored first
+    #[serde(default)]
+    pub results: Vec<CachedMatches>,
     /// When this entry was last accessed
     pub last_accessed: SystemTime,
     /// Number of times this entry has been accessed
     pub access_count: u64,
 }
 
+/// The matches one pattern set found in a file
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
+pub struct CachedMatches {
+    /// [`pattern_fingerprint`] of the search that found the matches
+    pub pattern_fingerprint: String,
+    pub matches: Vec<Match>,
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct FileSignature {
     pub mtime: SystemTime,
@@ -40,7 +115,7 @@ pub struct FileSignature {
     pub hash: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, PartialEq)]
 pub struct CacheMetadata {
     /// RustScout version that created this cache
     pub version: String,
@@ -48,10 +123,23 @@ pub struct CacheMetadata {
     pub last_search_timestamp: SystemTime,
     /// Cache hit rate (successful reuse of cached results)
     pub hit_rate: f64,
-    /// Compression ratio when compression is enabled
+    /// Compressed size as a fraction of the JSON size, when the cache was
+    /// loaded or saved compressed
     pub compression_ratio: Option<f64>,
     /// Files that change frequently
     pub frequently_changed: Vec<PathBuf>,
+    /// Whether the cached results come from an inverted search
+    #[serde(default)]
+    pub invert_match: bool,
+    /// Per-file match limit the cached results were produced with
+    #[serde(default)]
+    pub max_count_per_file: Option<usize>,
+    /// Whether the cached results include capture group spans
+    #[serde(default)]
+    pub capture_groups: bool,
+    /// [`pattern_fingerprint`] of the last search that used the cache
+    #[serde(default)]
+    pub pattern_fingerprint: String,
 }
 
 impl Default for CacheMetadata {
@@ -62,6 +150,10 @@ impl Default for CacheMetadata {
             hit_rate: 0.0,
             compression_ratio: None,
             frequently_changed: Vec::new(),
+            invert_match: false,
+            max_count_per_file: None,
+            capture_groups: false,
+            pattern_fingerprint: String::new(),
         }
     }
 }
@@ -77,48 +169,159 @@ impl IncrementalCache {
                 hit_rate: 0.0,
                 compression_ratio: None,
                 frequently_changed: Vec::new(),
+                invert_match: false,
+                max_count_per_file: None,
+                capture_groups: false,
+                pattern_fingerprint: String::new(),
             },
+            removed: HashSet::new(),
         }
     }
 
-    /// Loads a cache from disk
+    /// Loads a cache from disk, in either format and compressed or not. A
+    /// cache that can't be read is replaced by an empty one.
+    ///
+    /// Reading shares the cache's lock with other readers, so a save by
+    /// another run is never seen half done. If that run holds the lock past
+    /// the wait, the search goes on without the cache.
     pub fn load_from(path: &Path) -> SearchResult<Self> {
         if !path.exists() {
             return Ok(Self::new());
         }
 
-        let data = match std::fs::read(path) {
-            Ok(data) => data,
-            Err(_) => return Ok(Self::new()),
+        let _lock = match CacheLock::shared(path, LOCK_TIMEOUT) {
+            Ok(lock) => Some(lock),
+            Err(SearchError::CacheError(e)) => {
+                warn!("{}, searching without the cache", e);
+                return Ok(Self::new());
+            }
+            // A read-only cache directory has no room for the lock file
+            Err(_) => None,
         };
+        Ok(Self::read_from(path).unwrap_or_default())
+    }
 
-        match serde_json::from_slice(&data) {
-            Ok(cache) => Ok(cache),
-            Err(_) => {
-                // Cache is corrupted, return a new one
-                Ok(Self::new())
-            }
-        }
+    /// Reads and decodes the cache file, without locking it
+    fn read_from(path: &Path) -> Option<Self> {
+        let data = std::fs::read(path).ok()?;
+
+        // Decide by the content rather than the current setting, so turning
+        // compression on or off between runs keeps the cache
+        let compressed_len = data.len();
+        let compressed = data.starts_with(&GZIP_MAGIC);
+        let data = if compressed {
+            let mut json = Vec::new();
+            GzDecoder::new(data.as_slice())
+                .read_to_end(&mut json)
+                .ok()?;
+            json
+        } else {
+            data
+        };
+
+        // A corrupted cache decodes to nothing
+        let mut cache = match data.strip_prefix(BINARY_MAGIC) {
+            Some(encoded) => from_binary(encoded),
+            None => serde_json::from_slice::<Self>(&data).ok(),
+        }?;
+        cache.metadata.compression_ratio =
+            compressed.then(|| compressed_len as f64 / data.len().max(1) as f64);
+        Some(cache)
     }
 
-    /// Saves the cache to disk
+    /// Saves the cache to disk as uncompressed JSON
+    ///
+    /// The cache's lock is held alone while saving; if another run keeps it
+    /// past the wait, nothing is saved and an error is returned.
     pub fn save_to(&self, path: &Path) -> SearchResult<()> {
+        let _lock = Self::lock_for_writing(path)?;
+        self.write_to(path, CacheSaveOptions::default())?;
+        Ok(())
+    }
+
+    /// Like [`save_to`](Self::save_to), but in the format and compression
+    /// given by `options`. Compressed JSON caches are written compact rather
+    /// than pretty, and compressed caches record their compression ratio in
+    /// the metadata.
+    pub fn save_with(&mut self, path: &Path, options: CacheSaveOptions) -> SearchResult<()> {
+        let _lock = Self::lock_for_writing(path)?;
+        self.metadata.compression_ratio = self.write_to(path, options)?;
+        Ok(())
+    }
+
+    /// Like [`save_with`](Self::save_with), but first takes in the entries
+    /// of the cache on disk for files this one doesn't have, so runs sharing
+    /// a cache keep each other's work. Entries are only taken from a cache
+    /// made under the same match mode; the ones taken are checked against
+    /// their file's signature before being reused, like any other.
+    pub fn save_merged_to(&mut self, path: &Path, options: CacheSaveOptions) -> SearchResult<()> {
+        let _lock = Self::lock_for_writing(path)?;
+        if let Some(saved) = Self::read_from(path) {
+            self.absorb(saved);
+        }
+        self.metadata.compression_ratio = self.write_to(path, options)?;
+        Ok(())
+    }
+
+    fn lock_for_writing(path: &Path) -> SearchResult<CacheLock> {
         // Ensure parent directory exists
         if let Some(parent) = path.parent() {
             std::fs::create_dir_all(parent).map_err(SearchError::IoError)?;
         }
+        CacheLock::exclusive(path, LOCK_TIMEOUT)
+    }
+
+    /// Removes the entry for `path`, also keeping
+    /// [`save_merged_to`](Self::save_merged_to) from taking it back in
+    pub fn remove(&mut self, path: &Path) -> Option<FileCacheEntry> {
+        self.removed.insert(path.to_path_buf());
+        self.files.remove(path)
+    }
+
+    /// Takes in the entries of `saved` for files this cache doesn't have and
+    /// didn't remove
+    fn absorb(&mut self, saved: Self) {
+        let same_mode = saved.metadata.invert_match == self.metadata.invert_match
+            && saved.metadata.max_count_per_file == self.metadata.max_count_per_file
+            && saved.metadata.capture_groups == self.metadata.capture_groups;
+        if same_mode {
+            for (path, entry) in saved.files {
+                if !self.removed.contains(&path) {
+                    self.files.entry(path).or_insert(entry);
+                }
+            }
+        }
+    }
 
+    /// Writes the cache, which the caller has locked, returning its
+    /// compression ratio if it was compressed
+    fn write_to(&self, path: &Path, options: CacheSaveOptions) -> SearchResult<Option<f64>> {
         // Write to a temporary file first
         let tmp_path = path.with_extension("tmp");
-        let data =
-            serde_json::to_vec_pretty(self).map_err(|e| SearchError::CacheError(e.to_string()))?;
+        let encoded = match options.format {
+            CacheFormat::Binary => to_binary(self)?,
+            CacheFormat::Json if options.compress => {
+                serde_json::to_vec(self).map_err(|e| SearchError::CacheError(e.to_string()))?
+            }
+            CacheFormat::Json => serde_json::to_vec_pretty(self)
+                .map_err(|e| SearchError::CacheError(e.to_string()))?,
+        };
+        let (data, ratio) = if options.compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&encoded).map_err(SearchError::IoError)?;
+            let data = encoder.finish().map_err(SearchError::IoError)?;
+            let ratio = data.len() as f64 / encoded.len().max(1) as f64;
+            (data, Some(ratio))
+        } else {
+            (encoded, None)
+        };
 
         std::fs::write(&tmp_path, data).map_err(SearchError::IoError)?;
 
         // Atomically rename the temporary file
         std::fs::rename(&tmp_path, path).map_err(SearchError::IoError)?;
 
-        Ok(())
+        Ok(ratio)
     }
 
     /// Updates cache statistics after a search operation
@@ -130,12 +333,40 @@ impl IncrementalCache {
     }
 }
 
+/// Encodes a cache in the binary format, after [`BINARY_MAGIC`]
+#[cfg(feature = "binary-cache")]
+fn to_binary(cache: &IncrementalCache) -> SearchResult<Vec<u8>> {
+    let mut data = BINARY_MAGIC.to_vec();
+    bincode::serialize_into(&mut data, cache)
+        .map_err(|e| SearchError::CacheError(e.to_string()))?;
+    Ok(data)
+}
+
+#[cfg(not(feature = "binary-cache"))]
+fn to_binary(_cache: &IncrementalCache) -> SearchResult<Vec<u8>> {
+    Err(SearchError::config_error(
+        "a binary cache needs rustscout built with the binary-cache feature",
+    ))
+}
+
+/// Decodes a binary cache, given what follows [`BINARY_MAGIC`]
+#[cfg(feature = "binary-cache")]
+fn from_binary(encoded: &[u8]) -> Option<IncrementalCache> {
+    bincode::deserialize(encoded).ok()
+}
+
+/// Without the feature a binary cache can't be read, and is started afresh
+#[cfg(not(feature = "binary-cache"))]
+fn from_binary(_encoded: &[u8]) -> Option<IncrementalCache> {
+    None
+}
+
 impl FileCacheEntry {
     /// Creates a new cache entry
     pub fn new(signature: FileSignature) -> Self {
         Self {
             signature,
-            search_results: None,
+            results: Vec::new(),
             last_accessed: SystemTime::now(),
             access_count: 0,
         }
@@ -146,4 +377,214 @@ impl FileCacheEntry {
         self.last_accessed = SystemTime::now();
         self.access_count += 1;
     }
+
+    /// The matches cached for the pattern set with this fingerprint
+    pub fn results_for(&self, fingerprint: &str) -> Option<&[Match]> {
+        self.results
+            .iter()
+            .find(|cached| cached.pattern_fingerprint == fingerprint)
+            .map(|cached| cached.matches.as_slice())
+    }
+
+    /// Caches the matches a pattern set found, replacing its earlier ones and
+    /// dropping the least recently stored set beyond [`MAX_CACHED_PATTERN_SETS`]
+    pub fn store_results(&mut self, fingerprint: String, matches: Vec<Match>) {
+        self.results
+            .retain(|cached| cached.pattern_fingerprint != fingerprint);
+        self.results.insert(
+            0,
+            CachedMatches {
+                pattern_fingerprint: fingerprint,
+                matches,
+            },
+        );
+        self.results.truncate(MAX_CACHED_PATTERN_SETS);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    /// A cache with `count` entries for files under `/project/src`
+    fn sample_cache(count: usize) -> IncrementalCache {
+        let mut cache = IncrementalCache::new();
+        for i in 0..count {
+            cache.files.insert(
+                PathBuf::from(format!("/project/src/module_{}/file_{}.rs", i % 10, i)),
+                FileCacheEntry::new(FileSignature {
+                    mtime: SystemTime::UNIX_EPOCH,
+                    size: i as u64,
+                    hash: Some(format!("{:064x}", i)),
+                }),
+            );
+        }
+        cache
+    }
+
+    #[test]
+    fn test_compressed_cache_round_trip() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let plain_path = dir.path().join("plain.json");
+        let compressed_path = dir.path().join("compressed.json");
+
+        let mut cache = sample_cache(500);
+        cache.save_to(&plain_path)?;
+        assert_eq!(cache.metadata.compression_ratio, None);
+        let options = CacheSaveOptions {
+            format: CacheFormat::Json,
+            compress: true,
+        };
+        cache.save_with(&compressed_path, options)?;
+        let ratio = cache.metadata.compression_ratio.expect("ratio recorded");
+        assert!(ratio > 0.0 && ratio < 1.0);
+
+        let plain_len = std::fs::metadata(&plain_path)?.len();
+        let compressed_len = std::fs::metadata(&compressed_path)?.len();
+        assert!(compressed_len < plain_len);
+        assert!(std::fs::read(&compressed_path)?.starts_with(&GZIP_MAGIC));
+
+        // Loading detects the format, whatever the current setting
+        let plain = IncrementalCache::load_from(&plain_path)?;
+        let compressed = IncrementalCache::load_from(&compressed_path)?;
+        assert_eq!(plain.files.len(), 500);
+        assert_eq!(compressed.files.len(), 500);
+        for (path, entry) in &plain.files {
+            assert_eq!(compressed.files[path].signature, entry.signature);
+        }
+        assert_eq!(plain.metadata.compression_ratio, None);
+        assert!(compressed.metadata.compression_ratio.is_some());
+        Ok(())
+    }
+
+    /// Round-trips a 50k-file cache in both formats, the scale at which the
+    /// binary format is meant to pay off
+    #[cfg(feature = "binary-cache")]
+    #[test]
+    fn test_large_cache_round_trip_in_both_formats() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let mut cache = sample_cache(50_000);
+        for (i, entry) in cache.files.values_mut().enumerate().step_by(10) {
+            let line_content = format!("    // TODO: item {}", i);
+            entry.store_results(
+                "fingerprint".to_string(),
+                vec![Match {
+                    line_number: i + 1,
+                    start: 7,
+                    end: 11,
+                    column_start: 8,
+                    column_end: 12,
+                    byte_offset: i * 20 + 7,
+                    context_before: vec![(i, "fn item() {".to_string())],
+                    context_after: Vec::new(),
+                    pattern_index: 0,
+                    pattern_name: None,
+                    captures: None,
+                    line_content,
+                }],
+            );
+        }
+
+        let mut sizes = Vec::new();
+        for (format, compress) in [
+            (CacheFormat::Json, false),
+            (CacheFormat::Binary, false),
+            (CacheFormat::Json, true),
+            (CacheFormat::Binary, true),
+        ] {
+            let path = dir.path().join(format!("{:?}-{}.cache", format, compress));
+            cache.save_with(&path, CacheSaveOptions { format, compress })?;
+            let mut loaded = IncrementalCache::load_from(&path)?;
+            assert_eq!(
+                loaded.metadata.compression_ratio,
+                cache.metadata.compression_ratio
+            );
+            loaded.metadata.compression_ratio = None;
+            cache.metadata.compression_ratio = None;
+            assert!(loaded == cache, "{:?} cache changed on reload", format);
+            sizes.push(std::fs::metadata(&path)?.len());
+        }
+        assert!(sizes[1] < sizes[0], "binary is smaller than JSON");
+        Ok(())
+    }
+
+    #[cfg(feature = "binary-cache")]
+    #[test]
+    fn test_corrupted_binary_cache_starts_fresh() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("cache.bin");
+        let mut cache = sample_cache(10);
+        let options = CacheSaveOptions {
+            format: CacheFormat::Binary,
+            compress: false,
+        };
+        cache.save_with(&path, options)?;
+        assert!(std::fs::read(&path)?.starts_with(BINARY_MAGIC));
+        assert_eq!(IncrementalCache::load_from(&path)?.files.len(), 10);
+
+        let mut data = std::fs::read(&path)?;
+        data.truncate(data.len() / 2);
+        std::fs::write(&path, data)?;
+        assert!(IncrementalCache::load_from(&path)?.files.is_empty());
+        Ok(())
+    }
+
+    #[cfg(not(feature = "binary-cache"))]
+    #[test]
+    fn test_binary_cache_needs_feature() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("cache.bin");
+        let options = CacheSaveOptions {
+            format: CacheFormat::Binary,
+            compress: false,
+        };
+        assert!(sample_cache(1).save_with(&path, options).is_err());
+
+        // A binary cache written by another build is started afresh
+        std::fs::write(&path, [BINARY_MAGIC, b"rest"].concat())?;
+        assert!(IncrementalCache::load_from(&path)?.files.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_merged_save_keeps_removed_entries_out() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("cache.json");
+        sample_cache(3).save_to(&path)?;
+
+        // Of the entries another run saved, this run removes the first and
+        // never had the second
+        let mut cache = sample_cache(3);
+        let removed = PathBuf::from("/project/src/module_0/file_0.rs");
+        cache.files.retain(|key, _| key.ends_with("file_2.rs"));
+        cache.remove(&removed);
+        cache.save_merged_to(&path, CacheSaveOptions::default())?;
+
+        let saved = IncrementalCache::load_from(&path)?;
+        assert_eq!(saved.files.len(), 2);
+        assert!(!saved.files.contains_key(&removed));
+        assert!(saved
+            .files
+            .contains_key(Path::new("/project/src/module_1/file_1.rs")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_corrupted_compressed_cache_starts_fresh() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let path = dir.path().join("cache.json");
+        let mut cache = sample_cache(10);
+        let options = CacheSaveOptions {
+            format: CacheFormat::Json,
+            compress: true,
+        };
+        cache.save_with(&path, options)?;
+
+        let mut data = std::fs::read(&path)?;
+        data.truncate(data.len() / 2);
+        std::fs::write(&path, data)?;
+        assert!(IncrementalCache::load_from(&path)?.files.is_empty());
+        Ok(())
+    }
 }
@@ -1,36 +1,94 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::BTreeMap;
 use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 
-use crate::cache::ChangeDetectionStrategy;
+use crate::cache::{CacheFormat, ChangeDetectionStrategy};
 use crate::errors::{SearchError, SearchResult};
-use crate::search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode};
+use crate::filters::FileTypes;
+use crate::results::sarif::rule_id;
+use crate::search::cancel::CancellationToken;
+use crate::search::matcher::{CaseMode, HyphenMode, PatternDefinition, WordBoundaryMode};
+use crate::search::progress::ProgressHook;
+use crate::workspace::{detect_workspace_root, WORKSPACE_DIR};
 
-/// Controls how invalid UTF-8 sequences are handled
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+mod builder;
+pub use builder::{SearchConfigBuilder, MAX_CONTEXT_LINES};
+
+/// Controls how file contents are decoded and how invalid UTF-8 sequences are handled
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
 pub enum EncodingMode {
     /// Fail immediately on invalid UTF-8 (default for code search)
+    #[default]
     FailFast,
     /// Replace invalid UTF-8 sequences with the replacement character ()
     Lossy,
+    /// Detect the encoding from a byte-order mark (UTF-8, UTF-16LE/BE), reading
+    /// files without one as UTF-8 or, failing that, Latin-1. Matches refer to
+    /// the decoded text; see [`crate::encoding`].
+    Detect,
 }
 
-impl Default for EncodingMode {
-    fn default() -> Self {
-        Self::FailFast // Default to strict UTF-8 for code search
-    }
+/// Controls what the search reports for each file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum OutputMode {
+    /// Report every match (default)
+    #[default]
+    Full,
+    /// Only report which files contain a match; stops at the first match per file
+    FilesWithMatches,
+    /// Only report files that contain no match
+    FilesWithoutMatch,
+    /// Report the number of matches per file
+    CountPerFile,
+}
+
+/// The order in which the files found by a search are handed to the workers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Schedule {
+    /// Start the largest files first, so a few huge files found late don't
+    /// leave one thread working alone at the end (default)
+    #[default]
+    LargestFirst,
+    /// Split the files, in path order, into one equal run per thread. With a
+    /// limit on the total number of matches, only a single thread reports
+    /// the earliest files; with several, each stops partway through its own
+    /// run, so which files are reported varies.
+    Discovery,
 }
 
 /// Configuration for search operations
+///
+/// Prefer [`SearchConfig::builder`] to construct one in code; it fills in
+/// defaults and checks the result. The fields stay public for compatibility
+/// and for adjusting a built config.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SearchConfig {
     /// Pattern definitions with boundary settings (new field)
     #[serde(default)]
     pub pattern_definitions: Vec<PatternDefinition>,
-    /// Root directory to search in
-    pub root_path: PathBuf,
+    /// Root directories (or files) to search in. A config file may give a
+    /// single `root_path` instead.
+    #[serde(alias = "root_path", deserialize_with = "one_or_many_paths")]
+    pub root_paths: Vec<PathBuf>,
     /// File extensions to include (None means all)
     pub file_extensions: Option<Vec<String>>,
+    /// File extensions to skip, such as `min.js` or `lock`
+    #[serde(default)]
+    pub exclude_extensions: Option<Vec<String>>,
+    /// Only search files matching one of these globs (empty means all);
+    /// see [`crate::filters::matches_include_globs`]
+    #[serde(default)]
+    pub include_globs: Vec<String>,
+    /// When extensions or types are filtered on, sniff extensionless files
+    /// for a `#!` line naming their language (python, sh, node, ruby, perl)
+    #[serde(default = "default_true")]
+    pub sniff_shebang: bool,
+    /// Named file types to include or skip; selected types add to
+    /// `file_extensions`
+    #[serde(default)]
+    pub file_types: FileTypes,
     /// Patterns to ignore
     pub ignore_patterns: Vec<String>,
     /// Only show statistics, not matches
@@ -53,20 +111,121 @@ pub struct SearchConfig {
     pub max_cache_size: Option<u64>,
     /// Whether to use compression for cache
     pub use_compression: bool,
+    /// How the cache is stored; either format is read back
+    #[serde(default)]
+    pub cache_format: CacheFormat,
     /// How to handle invalid UTF-8 sequences
     #[serde(default)]
     pub encoding_mode: EncodingMode,
+    /// Report lines that do not match any pattern instead of matching ones
+    #[serde(default)]
+    pub invert_match: bool,
+    /// Maximum number of matches to report per file (None means unlimited)
+    #[serde(default)]
+    pub max_count_per_file: Option<usize>,
+    /// Stop searching once this many matches have been found in total
+    #[serde(default)]
+    pub max_total_matches: Option<usize>,
+    /// Keep at most about this many bytes of match details in the returned
+    /// result. Files found past it are only counted, and the result is marked
+    /// `results_truncated`. Results taken by [`crate::search_iter`] don't count
+    /// against it (None means no limit).
+    #[serde(default)]
+    pub memory_budget: Option<usize>,
+    /// What to report for each file
+    #[serde(default)]
+    pub output_mode: OutputMode,
+    /// Extract capture group spans for regex matches
+    #[serde(default)]
+    pub capture_groups: bool,
+    /// Search hidden files and directories (names starting with `.`). When
+    /// disabled, hidden directories are not descended into at all.
+    #[serde(default)]
+    pub include_hidden: bool,
+    /// Don't respect `.gitignore`/`.ignore`/`.rustscoutignore` files and
+    /// search inside `.git` directories. Explicit `ignore_patterns` still apply.
+    #[serde(default)]
+    pub no_ignore: bool,
+    /// Don't respect `.rustscoutignore` files, but still `.gitignore`
+    #[serde(default)]
+    pub no_rustscoutignore: bool,
+    /// Follow symbolic links while walking. Link cycles are skipped and a file
+    /// reachable through several links is searched only once.
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    /// Skip files larger than this many bytes (None means no limit)
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    /// Don't descend more than this many directory levels below the root
+    /// (None means unlimited; 1 searches only the root's own files)
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    /// Skip walked files that look minified (very long lines); files given
+    /// directly as roots are always searched
+    #[serde(default)]
+    pub skip_minified: bool,
+    /// Search the members of .zip, .tar and .tar.gz archives, reporting them
+    /// as `archive.zip!/member`. Needs the `archives` feature.
+    #[serde(default)]
+    pub search_archives: bool,
+    /// Match files of 10 MiB or more in chunks on several threads, when every
+    /// pattern matches within single lines
+    #[serde(default = "default_true")]
+    pub parallel_large_files: bool,
+    /// Search a file reached through several hard links (or bind mounts)
+    /// only once, under the first of its paths
+    #[serde(default = "default_true")]
+    pub dedupe_hardlinks: bool,
+    /// Order in which files are handed to the workers; results come back in
+    /// path order either way
+    #[serde(default)]
+    pub schedule: Schedule,
+    /// Only search files last modified at or after this time
+    #[serde(default)]
+    pub modified_after: Option<SystemTime>,
+    /// Only search files last modified before this time
+    #[serde(default)]
+    pub modified_before: Option<SystemTime>,
+    /// Only search files whose size in bytes lies in this inclusive range
+    #[serde(default)]
+    pub size_range: Option<(u64, u64)>,
+    /// Only report matches on lines added or changed since this git ref
+    /// (e.g. `origin/main`), and only search the files it touched
+    #[serde(default)]
+    pub diff_base: Option<String>,
+    /// Search exactly these files instead of walking `root_paths`. Extension,
+    /// ignore and size filters still apply, as do the `.gitignore`, `.ignore`
+    /// and `.rustscoutignore` files a walk would honour (unless `no_ignore`).
+    #[serde(default)]
+    pub explicit_paths: Option<Vec<PathBuf>>,
+    /// Fail on explicit paths that aren't existing files instead of warning
+    #[serde(default)]
+    pub strict_paths: bool,
+    /// Search text read from stdin instead of files; matches are reported
+    /// under the path `<stdin>`
+    #[serde(default)]
+    pub stdin: bool,
+    /// Token for stopping the search early from another thread
+    #[serde(skip)]
+    pub cancellation: Option<CancellationToken>,
+    /// Receives progress updates while the search runs
+    #[serde(skip)]
+    pub progress: Option<ProgressHook>,
 }
 
 impl Default for SearchConfig {
     fn default() -> Self {
         Self {
             pattern_definitions: Vec::new(),
-            root_path: PathBuf::from("."),
+            root_paths: vec![PathBuf::from(".")],
             file_extensions: None,
+            exclude_extensions: None,
+            include_globs: Vec::new(),
+            sniff_shebang: true,
+            file_types: FileTypes::default(),
             ignore_patterns: Vec::new(),
             stats_only: false,
-            thread_count: NonZeroUsize::new(4).unwrap(),
+            thread_count: Self::default_thread_count(),
             log_level: "info".to_string(),
             context_before: 0,
             context_after: 0,
@@ -75,12 +234,50 @@ impl Default for SearchConfig {
             cache_strategy: ChangeDetectionStrategy::Auto,
             max_cache_size: None,
             use_compression: false,
+            cache_format: CacheFormat::Json,
             encoding_mode: EncodingMode::default(),
+            invert_match: false,
+            max_count_per_file: None,
+            max_total_matches: None,
+            memory_budget: None,
+            output_mode: OutputMode::default(),
+            capture_groups: false,
+            include_hidden: false,
+            no_ignore: false,
+            no_rustscoutignore: false,
+            follow_symlinks: false,
+            max_file_size: None,
+            max_depth: None,
+            skip_minified: false,
+            modified_after: None,
+            modified_before: None,
+            size_range: None,
+            diff_base: None,
+            search_archives: false,
+            parallel_large_files: true,
+            dedupe_hardlinks: true,
+            schedule: Schedule::default(),
+            explicit_paths: None,
+            strict_paths: false,
+            stdin: false,
+            cancellation: None,
+            progress: None,
         }
     }
 }
 
 impl SearchConfig {
+    /// Starts building a config from the defaults
+    pub fn builder() -> SearchConfigBuilder {
+        SearchConfigBuilder::default()
+    }
+
+    /// The number of threads used when none is configured: the available
+    /// parallelism, or 1 if it can't be determined
+    pub fn default_thread_count() -> NonZeroUsize {
+        std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN)
+    }
+
     /// Creates a new SearchConfig with a single pattern
     pub fn new_with_pattern(text: String, is_regex: bool, boundary_mode: WordBoundaryMode) -> Self {
         let mut config = Self::default();
@@ -89,6 +286,13 @@ impl SearchConfig {
             is_regex,
             boundary_mode,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         });
         config
     }
@@ -98,18 +302,49 @@ impl SearchConfig {
         self.pattern_definitions.clone()
     }
 
+    /// Gets the per-file match limit, taking the output mode into account.
+    ///
+    /// Modes that only need to know whether a file matches stop at the first match.
+    pub fn effective_max_count(&self) -> Option<usize> {
+        match self.output_mode {
+            OutputMode::FilesWithMatches | OutputMode::FilesWithoutMatch => Some(1),
+            OutputMode::Full | OutputMode::CountPerFile => self.max_count_per_file,
+        }
+    }
+
     /// Loads configuration from a file
     pub fn load_from(path: impl AsRef<Path>) -> SearchResult<Self> {
         let content = std::fs::read_to_string(path)
             .map_err(|e| SearchError::config_error(format!("Failed to read config: {}", e)))?;
 
-        serde_yaml::from_str(&content)
-            .map_err(|e| SearchError::config_error(format!("Failed to parse config: {}", e)))
+        let config: Self = serde_yaml::from_str(&content)
+            .map_err(|e| SearchError::config_error(format!("Failed to parse config: {}", e)))?;
+        check_rule_ids(&config.pattern_definitions)?;
+        Ok(config)
+    }
+
+    /// The first root path
+    #[deprecated(note = "use `root_paths`; this only returns the first root")]
+    pub fn root_path(&self) -> &Path {
+        self.first_root()
+    }
+
+    /// The first root path, which anchors the cache, change detection and
+    /// explicit paths
+    pub(crate) fn first_root(&self) -> &Path {
+        self.root_paths
+            .first()
+            .map_or(Path::new("."), |root| root.as_path())
     }
 
     /// Gets the default cache path
     pub fn default_cache_path(&self) -> PathBuf {
-        self.root_path.join(".rustscout").join("cache.json")
+        Self::default_cache_path_in(self.first_root())
+    }
+
+    /// The default cache path for searches rooted at `root`
+    pub fn default_cache_path_in(root: &Path) -> PathBuf {
+        root.join(".rustscout").join("cache.json")
     }
 
     /// Gets the effective cache path
@@ -119,56 +354,507 @@ impl SearchConfig {
             .unwrap_or_else(|| self.default_cache_path())
     }
 
-    pub fn merge_with_cli(&mut self, cli: &SearchConfig) {
-        // Merge pattern definitions first
+    /// Applies the options given on the command line. Every option the CLI
+    /// specified wins, whether it turns a setting on or off or sets it back
+    /// to its default.
+    pub fn merge_with_cli(&mut self, cli: SearchOverrides) {
         if !cli.pattern_definitions.is_empty() {
-            self.pattern_definitions = cli.pattern_definitions.clone();
+            self.pattern_definitions = cli.pattern_definitions;
         }
-        if cli.root_path != PathBuf::from(".") {
-            self.root_path = cli.root_path.clone();
+        if let Some(root_paths) = cli.root_paths {
+            self.root_paths = root_paths;
         }
-        if cli.file_extensions.is_some() {
-            self.file_extensions = cli.file_extensions.clone();
+        if !cli.include_globs.is_empty() {
+            self.include_globs = cli.include_globs;
         }
         if !cli.ignore_patterns.is_empty() {
-            self.ignore_patterns = cli.ignore_patterns.clone();
+            self.ignore_patterns = cli.ignore_patterns;
+        }
+        if cli.file_extensions.is_some() {
+            self.file_extensions = cli.file_extensions;
+        }
+        if cli.exclude_extensions.is_some() {
+            self.exclude_extensions = cli.exclude_extensions;
+        }
+        if let Some(file_types) = cli.file_types {
+            self.file_types = file_types;
         }
-        if cli.stats_only {
-            self.stats_only = true;
+        if let Some(sniff_shebang) = cli.sniff_shebang {
+            self.sniff_shebang = sniff_shebang;
         }
-        if cli.thread_count.get() != 4 {
-            self.thread_count = cli.thread_count;
+        if let Some(stats_only) = cli.stats_only {
+            self.stats_only = stats_only;
         }
-        if cli.log_level != "info" {
-            self.log_level = cli.log_level.clone();
+        if let Some(thread_count) = cli.thread_count {
+            self.thread_count = thread_count;
         }
-        if cli.context_before != 0 {
-            self.context_before = cli.context_before;
+        if let Some(log_level) = cli.log_level {
+            self.log_level = log_level;
         }
-        if cli.context_after != 0 {
-            self.context_after = cli.context_after;
+        if let Some(context_before) = cli.context_before {
+            self.context_before = context_before;
         }
-        if cli.incremental {
-            self.incremental = true;
+        if let Some(context_after) = cli.context_after {
+            self.context_after = context_after;
+        }
+        if let Some(incremental) = cli.incremental {
+            self.incremental = incremental;
         }
         if cli.cache_path.is_some() {
-            self.cache_path = cli.cache_path.clone();
+            self.cache_path = cli.cache_path;
         }
-        if cli.cache_strategy != ChangeDetectionStrategy::Auto {
-            self.cache_strategy = cli.cache_strategy;
+        if let Some(cache_strategy) = cli.cache_strategy {
+            self.cache_strategy = cache_strategy;
         }
         if cli.max_cache_size.is_some() {
             self.max_cache_size = cli.max_cache_size;
         }
-        if cli.use_compression {
-            self.use_compression = true;
+        if let Some(use_compression) = cli.use_compression {
+            self.use_compression = use_compression;
+        }
+        if let Some(cache_format) = cli.cache_format {
+            self.cache_format = cache_format;
+        }
+        if let Some(encoding_mode) = cli.encoding_mode {
+            self.encoding_mode = encoding_mode;
+        }
+        if let Some(invert_match) = cli.invert_match {
+            self.invert_match = invert_match;
+        }
+        if cli.max_count_per_file.is_some() {
+            self.max_count_per_file = cli.max_count_per_file;
+        }
+        if cli.max_total_matches.is_some() {
+            self.max_total_matches = cli.max_total_matches;
+        }
+        if cli.memory_budget.is_some() {
+            self.memory_budget = cli.memory_budget;
         }
-        if cli.encoding_mode != EncodingMode::default() {
-            self.encoding_mode = cli.encoding_mode;
+        if let Some(output_mode) = cli.output_mode {
+            self.output_mode = output_mode;
         }
+        if let Some(capture_groups) = cli.capture_groups {
+            self.capture_groups = capture_groups;
+        }
+        if let Some(include_hidden) = cli.include_hidden {
+            self.include_hidden = include_hidden;
+        }
+        if let Some(no_ignore) = cli.no_ignore {
+            self.no_ignore = no_ignore;
+        }
+        if let Some(no_rustscoutignore) = cli.no_rustscoutignore {
+            self.no_rustscoutignore = no_rustscoutignore;
+        }
+        if let Some(follow_symlinks) = cli.follow_symlinks {
+            self.follow_symlinks = follow_symlinks;
+        }
+        if cli.max_file_size.is_some() {
+            self.max_file_size = cli.max_file_size;
+        }
+        if cli.max_depth.is_some() {
+            self.max_depth = cli.max_depth;
+        }
+        if let Some(skip_minified) = cli.skip_minified {
+            self.skip_minified = skip_minified;
+        }
+        if cli.modified_after.is_some() {
+            self.modified_after = cli.modified_after;
+        }
+        if cli.modified_before.is_some() {
+            self.modified_before = cli.modified_before;
+        }
+        if cli.size_range.is_some() {
+            self.size_range = cli.size_range;
+        }
+        if cli.diff_base.is_some() {
+            self.diff_base = cli.diff_base;
+        }
+        if let Some(search_archives) = cli.search_archives {
+            self.search_archives = search_archives;
+        }
+        if let Some(parallel_large_files) = cli.parallel_large_files {
+            self.parallel_large_files = parallel_large_files;
+        }
+        if let Some(dedupe_hardlinks) = cli.dedupe_hardlinks {
+            self.dedupe_hardlinks = dedupe_hardlinks;
+        }
+        if let Some(schedule) = cli.schedule {
+            self.schedule = schedule;
+        }
+        if cli.explicit_paths.is_some() {
+            self.explicit_paths = cli.explicit_paths;
+        }
+        if let Some(strict_paths) = cli.strict_paths {
+            self.strict_paths = strict_paths;
+        }
+        if let Some(stdin) = cli.stdin {
+            self.stdin = stdin;
+        }
+        if cli.cancellation.is_some() {
+            self.cancellation = cli.cancellation;
+        }
+        if cli.progress.is_some() {
+            self.progress = cli.progress;
+        }
+    }
+
+    /// Like [`merge_with_cli`](Self::merge_with_cli), but adds the CLI's
+    /// patterns and ignore patterns to these instead of replacing them
+    pub fn extend_with_cli(&mut self, mut cli: SearchOverrides) {
+        self.pattern_definitions
+            .append(&mut cli.pattern_definitions);
+        self.ignore_patterns.append(&mut cli.ignore_patterns);
+        self.merge_with_cli(cli);
+    }
+
+    /// Loads the search defaults of the workspace containing `start_dir`, if
+    /// it has a `.rustscout/config.yaml` (or `config.json`)
+    ///
+    /// A relative `cache_path` in the file is taken relative to the workspace root.
+    pub fn load_workspace_defaults(start_dir: &Path) -> SearchResult<Option<PartialSearchConfig>> {
+        let root = detect_workspace_root(start_dir)?;
+        let Some(path) = SEARCH_CONFIG_FILES
+            .iter()
+            .map(|name| root.join(WORKSPACE_DIR).join(name))
+            .find(|path| path.is_file())
+        else {
+            return Ok(None);
+        };
+
+        let content = std::fs::read_to_string(&path)?;
+        let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| e.to_string())
+        };
+        let mut defaults: PartialSearchConfig = parsed.map_err(|e| {
+            SearchError::config_error(format!("Failed to parse {}: {}", path.display(), e))
+        })?;
+        defaults.file_types().map_err(|e| {
+            SearchError::config_error(format!("Invalid file types in {}: {}", path.display(), e))
+        })?;
+        for (name, profile) in &defaults.profiles {
+            check_rule_ids(&profile.patterns).map_err(|e| {
+                SearchError::config_error(format!(
+                    "Invalid profile '{}' in {}: {}",
+                    name,
+                    path.display(),
+                    e
+                ))
+            })?;
+        }
+        if let Some(cache_path) = &mut defaults.cache_path {
+            if cache_path.is_relative() {
+                *cache_path = root.join(&*cache_path);
+            }
+        }
+        Ok(Some(defaults))
     }
 }
 
+/// Search options given on the command line, applied over workspace
+/// defaults, a profile or a config file by [`SearchConfig::merge_with_cli`].
+///
+/// `None` means the option wasn't given, so the underlying setting stays.
+#[derive(Debug, Clone, Default)]
+pub struct SearchOverrides {
+    /// Patterns replacing (or, with `extend_with_cli`, added to) the
+    /// config's, if any are given
+    pub pattern_definitions: Vec<PatternDefinition>,
+    /// Root directories (or files) to search in
+    pub root_paths: Option<Vec<PathBuf>>,
+    /// File extensions to include
+    pub file_extensions: Option<Vec<String>>,
+    /// File extensions to skip
+    pub exclude_extensions: Option<Vec<String>>,
+    /// Globs replacing the config's include globs, if any are given
+    pub include_globs: Vec<String>,
+    /// Whether to sniff extensionless files for a `#!` line
+    pub sniff_shebang: Option<bool>,
+    /// Named file types to include or skip
+    pub file_types: Option<FileTypes>,
+    /// Ignore patterns replacing (or, with `extend_with_cli`, added to) the
+    /// config's, if any are given
+    pub ignore_patterns: Vec<String>,
+    /// Whether to only show statistics
+    pub stats_only: Option<bool>,
+    /// Number of threads to use
+    pub thread_count: Option<NonZeroUsize>,
+    /// Log level
+    pub log_level: Option<String>,
+    /// Number of context lines before matches
+    pub context_before: Option<usize>,
+    /// Number of context lines after matches
+    pub context_after: Option<usize>,
+    /// Whether to use incremental search
+    pub incremental: Option<bool>,
+    /// Path to the cache file
+    pub cache_path: Option<PathBuf>,
+    /// Strategy for detecting changes
+    pub cache_strategy: Option<ChangeDetectionStrategy>,
+    /// Maximum cache size in bytes
+    pub max_cache_size: Option<u64>,
+    /// Whether to compress the cache
+    pub use_compression: Option<bool>,
+    /// How the cache is stored
+    pub cache_format: Option<CacheFormat>,
+    /// How file contents are decoded
+    pub encoding_mode: Option<EncodingMode>,
+    /// Whether to report non-matching lines instead
+    pub invert_match: Option<bool>,
+    /// Maximum number of matches to report per file
+    pub max_count_per_file: Option<usize>,
+    /// Stop once this many matches have been found in total
+    pub max_total_matches: Option<usize>,
+    /// Bytes of match details to keep in the result
+    pub memory_budget: Option<usize>,
+    /// What to report for each file
+    pub output_mode: Option<OutputMode>,
+    /// Whether to extract capture group spans
+    pub capture_groups: Option<bool>,
+    /// Whether to search hidden files and directories
+    pub include_hidden: Option<bool>,
+    /// Whether to disregard ignore files
+    pub no_ignore: Option<bool>,
+    /// Whether to disregard `.rustscoutignore` files
+    pub no_rustscoutignore: Option<bool>,
+    /// Whether to follow symbolic links
+    pub follow_symlinks: Option<bool>,
+    /// Skip files larger than this many bytes
+    pub max_file_size: Option<u64>,
+    /// Directory levels to descend below the root
+    pub max_depth: Option<usize>,
+    /// Whether to skip files that look minified
+    pub skip_minified: Option<bool>,
+    /// Only search files modified at or after this time
+    pub modified_after: Option<SystemTime>,
+    /// Only search files modified before this time
+    pub modified_before: Option<SystemTime>,
+    /// Only search files whose size lies in this inclusive range
+    pub size_range: Option<(u64, u64)>,
+    /// Only report matches on lines changed since this git ref
+    pub diff_base: Option<String>,
+    /// Whether to search inside archives
+    pub search_archives: Option<bool>,
+    /// Whether to match large files on several threads
+    pub parallel_large_files: Option<bool>,
+    /// Whether to search hard-linked files only once
+    pub dedupe_hardlinks: Option<bool>,
+    /// Order in which files are handed to the workers
+    pub schedule: Option<Schedule>,
+    /// Search exactly these files instead of walking the roots
+    pub explicit_paths: Option<Vec<PathBuf>>,
+    /// Whether to fail on explicit paths that aren't existing files
+    pub strict_paths: Option<bool>,
+    /// Whether to search stdin instead of files
+    pub stdin: Option<bool>,
+    /// Token for stopping the search early
+    pub cancellation: Option<CancellationToken>,
+    /// Receives progress updates while the search runs
+    pub progress: Option<ProgressHook>,
+}
+
+/// Names of the workspace search config file, in order of preference
+pub const SEARCH_CONFIG_FILES: [&str; 2] = ["config.yaml", "config.json"];
+
+/// Search defaults stored in a workspace's `.rustscout/config.yaml` (or
+/// `config.json`); see [`SearchConfig::load_workspace_defaults`].
+///
+/// Fields left out of the file keep the built-in defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PartialSearchConfig {
+    /// Patterns to ignore; CLI ignore patterns are added to these
+    pub ignore_patterns: Vec<String>,
+    /// File extensions to include
+    pub file_extensions: Option<Vec<String>>,
+    /// How file contents are decoded
+    pub encoding_mode: Option<EncodingMode>,
+    /// Number of threads to use
+    pub thread_count: Option<NonZeroUsize>,
+    /// Whether to use incremental search
+    pub incremental: Option<bool>,
+    /// Path to the cache file
+    pub cache_path: Option<PathBuf>,
+    /// Strategy for detecting changes
+    pub cache_strategy: Option<ChangeDetectionStrategy>,
+    /// Number of context lines before matches
+    pub context_before: Option<usize>,
+    /// Number of context lines after matches
+    pub context_after: Option<usize>,
+    /// File types for `--type`, added to the built-in ones: a name mapped
+    /// to its file name globs
+    pub types: BTreeMap<String, Vec<String>>,
+    /// Named searches, run with `search --profile NAME`
+    pub profiles: BTreeMap<String, SearchProfile>,
+}
+
+/// A named search stored in the workspace config; see [`load_profile`]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchProfile {
+    /// The patterns to search for
+    pub patterns: Vec<PatternDefinition>,
+    /// File extensions to include, replacing the workspace default
+    pub file_extensions: Option<Vec<String>>,
+    /// Patterns to ignore, added to the workspace's ignore patterns
+    pub ignore_patterns: Vec<String>,
+}
+
+impl PartialSearchConfig {
+    /// Applies `cli` on top of these defaults; any setting given on the
+    /// command line wins, except that ignore patterns are combined
+    pub fn merge_with_cli(&self, cli: SearchOverrides) -> SearchConfig {
+        let mut config = self.to_search_config();
+        config.extend_with_cli(cli);
+        config
+    }
+
+    /// The built-in file types plus the ones defined under `types:`
+    pub fn file_types(&self) -> SearchResult<FileTypes> {
+        let mut file_types = FileTypes::new();
+        for (name, globs) in &self.types {
+            file_types.add_globs(name, globs)?;
+        }
+        Ok(file_types)
+    }
+
+    /// A config with these defaults in place of the built-in ones
+    pub fn to_search_config(&self) -> SearchConfig {
+        let mut config = SearchConfig::default();
+        config.ignore_patterns = self.ignore_patterns.clone();
+        config.file_extensions = self.file_extensions.clone();
+        // Checked when the file was loaded
+        config.file_types = self.file_types().unwrap_or_default();
+        config.encoding_mode = self.encoding_mode.unwrap_or(config.encoding_mode);
+        config.thread_count = self.thread_count.unwrap_or(config.thread_count);
+        config.incremental = self.incremental.unwrap_or(config.incremental);
+        config.cache_path = self.cache_path.clone();
+        config.cache_strategy = self.cache_strategy.unwrap_or(config.cache_strategy);
+        config.context_before = self.context_before.unwrap_or(config.context_before);
+        config.context_after = self.context_after.unwrap_or(config.context_after);
+        config
+    }
+}
+
+/// Rejects pattern lists in which two patterns would report under the same
+/// rule id (the pattern's name, or `pattern-<index>` for unnamed ones)
+fn check_rule_ids(patterns: &[PatternDefinition]) -> SearchResult<()> {
+    let mut seen = std::collections::HashSet::new();
+    for (index, pattern) in patterns.iter().enumerate() {
+        let id = rule_id(index, pattern.name.as_deref());
+        if !seen.insert(id.clone()) {
+            return Err(SearchError::config_error(format!(
+                "Duplicate pattern name '{}'; pattern names are rule ids and must be unique",
+                id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Loads the profile called `name` from the workspace config found from
+/// `workspace_root`, on top of the workspace's search defaults
+pub fn load_profile(workspace_root: &Path, name: &str) -> SearchResult<SearchConfig> {
+    let defaults = SearchConfig::load_workspace_defaults(workspace_root)?.unwrap_or_default();
+    let Some(profile) = defaults.profiles.get(name) else {
+        let available = if defaults.profiles.is_empty() {
+            "none are defined in .rustscout/config.yaml".to_string()
+        } else {
+            let names: Vec<_> = defaults.profiles.keys().map(String::as_str).collect();
+            format!("available profiles: {}", names.join(", "))
+        };
+        return Err(SearchError::config_error(format!(
+            "Unknown search profile '{}'; {}",
+            name, available
+        )));
+    };
+
+    let mut config = defaults.to_search_config();
+    config.pattern_definitions = profile.patterns.clone();
+    if profile.file_extensions.is_some() {
+        config.file_extensions = profile.file_extensions.clone();
+    }
+    config
+        .ignore_patterns
+        .extend(profile.ignore_patterns.iter().cloned());
+    Ok(config)
+}
+
+/// Parses a file size such as `512`, `64K`, `10M` or `2G` into bytes.
+///
+/// Suffixes are binary (1K = 1024 bytes) and case-insensitive.
+pub fn parse_size(text: &str) -> SearchResult<u64> {
+    let text = text.trim();
+    let (digits, multiplier) = match text.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => {
+            let multiplier = match c.to_ascii_uppercase() {
+                'K' => 1 << 10,
+                'M' => 1 << 20,
+                'G' => 1 << 30,
+                _ => {
+                    return Err(SearchError::config_error(format!(
+                        "Invalid size '{}': expected a K, M or G suffix",
+                        text
+                    )))
+                }
+            };
+            (&text[..i], multiplier)
+        }
+        _ => (text, 1),
+    };
+
+    digits
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| SearchError::config_error(format!("Invalid size '{}'", text)))
+}
+
+/// Parses a point in time for `--newer-than`/`--older-than`: either an age
+/// such as `2d`, `6h` or `1week 2days` (that long before `now`), or a date
+/// such as `2024-01-01` (midnight UTC) or `2024-01-01 12:30:00`.
+pub fn parse_time(text: &str, now: SystemTime) -> SearchResult<SystemTime> {
+    let text = text.trim();
+    if let Ok(age) = humantime::parse_duration(text) {
+        return Ok(now.checked_sub(age).unwrap_or(SystemTime::UNIX_EPOCH));
+    }
+    let is_date = text.len() == 10 && text.chars().filter(|&c| c == '-').count() == 2;
+    let timestamp = if is_date {
+        humantime::parse_rfc3339_weak(&format!("{} 00:00:00", text))
+    } else {
+        humantime::parse_rfc3339_weak(text)
+    };
+    timestamp.map_err(|_| {
+        SearchError::config_error(format!(
+            "Invalid time '{}': expected an age such as 2d or a date such as 2024-01-01",
+            text
+        ))
+    })
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Deserializes either a single path or a list of paths
+fn one_or_many_paths<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(path) => vec![path],
+        OneOrMany::Many(paths) => paths,
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,11 +867,11 @@ mod tests {
     fn test_default_values() {
         let config = SearchConfig::default();
         assert!(config.pattern_definitions.is_empty());
-        assert_eq!(config.root_path, PathBuf::from("."));
+        assert_eq!(config.root_paths, vec![PathBuf::from(".")]);
         assert_eq!(config.file_extensions, None);
         assert!(config.ignore_patterns.is_empty());
         assert!(!config.stats_only);
-        assert_eq!(config.thread_count, NonZeroUsize::new(4).unwrap());
+        assert_eq!(config.thread_count, SearchConfig::default_thread_count());
         assert_eq!(config.log_level, "info");
         assert_eq!(config.context_before, 0);
         assert_eq!(config.context_after, 0);
@@ -195,6 +881,20 @@ mod tests {
         assert_eq!(config.max_cache_size, None);
         assert!(!config.use_compression);
         assert_eq!(config.encoding_mode, EncodingMode::default());
+        assert!(!config.invert_match);
+        assert_eq!(config.max_count_per_file, None);
+        assert_eq!(config.max_total_matches, None);
+        assert_eq!(config.output_mode, OutputMode::Full);
+        assert!(!config.capture_groups);
+        assert!(!config.include_hidden);
+        assert!(!config.no_ignore);
+        assert!(!config.follow_symlinks);
+        assert_eq!(config.max_file_size, None);
+        assert_eq!(config.max_depth, None);
+        assert_eq!(config.explicit_paths, None);
+        assert!(!config.strict_paths);
+        assert!(!config.stdin);
+        assert!(config.cancellation.is_none());
     }
 
     #[test]
@@ -212,21 +912,37 @@ mod tests {
 
     #[test]
     fn test_get_pattern_definitions() {
-        let mut config = SearchConfig::default();
-        config.pattern_definitions = vec![
-            PatternDefinition {
-                text: "test1".to_string(),
-                is_regex: false,
-                boundary_mode: WordBoundaryMode::WholeWords,
-                hyphen_mode: HyphenMode::default(),
-            },
-            PatternDefinition {
-                text: "test2".to_string(),
-                is_regex: true,
-                boundary_mode: WordBoundaryMode::None,
-                hyphen_mode: HyphenMode::default(),
-            },
-        ];
+        let config = SearchConfig {
+            pattern_definitions: vec![
+                PatternDefinition {
+                    text: "test1".to_string(),
+                    is_regex: false,
+                    boundary_mode: WordBoundaryMode::WholeWords,
+                    hyphen_mode: HyphenMode::default(),
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
+                },
+                PatternDefinition {
+                    text: "test2".to_string(),
+                    is_regex: true,
+                    boundary_mode: WordBoundaryMode::None,
+                    hyphen_mode: HyphenMode::default(),
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
+                },
+            ],
+            ..Default::default()
+        };
 
         let defs = config.get_pattern_definitions();
         assert_eq!(defs.len(), 2);
@@ -240,23 +956,50 @@ mod tests {
 
     #[test]
     fn test_merge_with_cli() {
-        let mut config = SearchConfig::default();
-        config.pattern_definitions = vec![PatternDefinition {
-            text: "test1".to_string(),
-            is_regex: false,
-            boundary_mode: WordBoundaryMode::WholeWords,
-            hyphen_mode: HyphenMode::default(),
-        }];
+        let mut config = SearchConfig {
+            pattern_definitions: vec![PatternDefinition {
+                text: "test1".to_string(),
+                is_regex: false,
+                boundary_mode: WordBoundaryMode::WholeWords,
+                hyphen_mode: HyphenMode::default(),
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
+            }],
+            thread_count: NonZeroUsize::new(2).unwrap(),
+            context_after: 3,
+            encoding_mode: EncodingMode::Lossy,
+            cache_format: CacheFormat::Binary,
+            incremental: true,
+            ..Default::default()
+        };
 
-        let mut cli_config = SearchConfig::default();
-        cli_config.pattern_definitions = vec![PatternDefinition {
-            text: "test2".to_string(),
-            is_regex: true,
-            boundary_mode: WordBoundaryMode::None,
-            hyphen_mode: HyphenMode::default(),
-        }];
+        let cli_config = SearchOverrides {
+            pattern_definitions: vec![PatternDefinition {
+                text: "test2".to_string(),
+                is_regex: true,
+                boundary_mode: WordBoundaryMode::None,
+                hyphen_mode: HyphenMode::default(),
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
+            }],
+            context_after: Some(0),
+            encoding_mode: Some(EncodingMode::FailFast),
+            cache_format: Some(CacheFormat::Json),
+            incremental: Some(false),
+            ..Default::default()
+        };
 
-        config.merge_with_cli(&cli_config);
+        config.merge_with_cli(cli_config);
 
         assert_eq!(config.pattern_definitions.len(), 1);
         assert_eq!(config.pattern_definitions[0].text, "test2");
@@ -265,6 +1008,14 @@ mod tests {
             config.pattern_definitions[0].boundary_mode,
             WordBoundaryMode::None
         );
+
+        // Options given on the command line win even when they set the
+        // default value; the others keep the config's
+        assert_eq!(config.context_after, 0);
+        assert_eq!(config.encoding_mode, EncodingMode::FailFast);
+        assert_eq!(config.cache_format, CacheFormat::Json);
+        assert!(!config.incremental);
+        assert_eq!(config.thread_count.get(), 2);
     }
 
     #[test]
@@ -298,7 +1049,7 @@ use_compression: false
         assert_eq!(config.pattern_definitions.len(), 1);
         assert_eq!(config.pattern_definitions[0].text, "test");
         assert!(!config.pattern_definitions[0].is_regex);
-        assert_eq!(config.root_path, PathBuf::from("."));
+        assert_eq!(config.root_paths, vec![PathBuf::from(".")]);
         assert!(config.file_extensions.is_none());
         assert!(config.ignore_patterns.is_empty());
         assert!(!config.stats_only);
@@ -336,4 +1087,207 @@ use_compression: false
 
         assert!(SearchConfig::load_from(config_path).is_err());
     }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512").unwrap(), 512);
+        assert_eq!(parse_size("64k").unwrap(), 64 * 1024);
+        assert_eq!(parse_size("10M").unwrap(), 10 * 1024 * 1024);
+        assert_eq!(parse_size("2G").unwrap(), 2 * 1024 * 1024 * 1024);
+        assert!(parse_size("").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("10X").is_err());
+        assert!(parse_size("-1").is_err());
+        assert!(parse_size("99999999999G").is_err());
+    }
+
+    #[test]
+    fn test_parse_time() {
+        use std::time::Duration;
+
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_750_000_000);
+        let hour = Duration::from_secs(60 * 60);
+        assert_eq!(parse_time("2d", now).unwrap(), now - 48 * hour);
+        assert_eq!(parse_time("6h", now).unwrap(), now - 6 * hour);
+        assert_eq!(parse_time("1week 2days", now).unwrap(), now - 9 * 24 * hour);
+        assert_eq!(
+            parse_time("2024-01-01", now).unwrap(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200)
+        );
+        assert_eq!(
+            parse_time("2024-01-01 12:30:00", now).unwrap(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_704_067_200 + 12 * 3600 + 1800)
+        );
+        assert!(parse_time("yesterday", now).is_err());
+        assert!(parse_time("2024-13-01", now).is_err());
+        assert!(parse_time("", now).is_err());
+    }
+
+    #[test]
+    fn test_workspace_defaults() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        let nested = root.join("src").join("deep");
+        fs::create_dir_all(&nested)?;
+        assert_eq!(SearchConfig::load_workspace_defaults(&nested)?, None);
+
+        fs::create_dir_all(root.join(".rustscout"))?;
+        fs::write(
+            root.join(".rustscout").join("config.yaml"),
+            "ignore_patterns: [\"**/target/**\"]\n\
+             file_extensions: [rs]\n\
+             thread_count: 2\n\
+             context_after: 3\n\
+             cache_path: cache/search.json\n",
+        )?;
+
+        // A nested directory picks up the workspace root's config
+        let defaults = SearchConfig::load_workspace_defaults(&nested)?.unwrap();
+        assert_eq!(defaults.ignore_patterns, vec!["**/target/**".to_string()]);
+        assert_eq!(defaults.thread_count, NonZeroUsize::new(2));
+        assert!(defaults
+            .cache_path
+            .as_ref()
+            .is_some_and(|path| path.ends_with("cache/search.json") && path.is_absolute()));
+
+        // CLI values win, and -g patterns are added to the configured ones
+        let cli = SearchOverrides {
+            pattern_definitions: vec![PatternDefinition::new(
+                "TODO".to_string(),
+                false,
+                WordBoundaryMode::None,
+            )],
+            ignore_patterns: vec!["**/*.bak".to_string()],
+            context_after: Some(5),
+            ..SearchOverrides::default()
+        };
+        let config = defaults.merge_with_cli(cli.clone());
+        assert_eq!(
+            config.ignore_patterns,
+            vec!["**/target/**".to_string(), "**/*.bak".to_string()]
+        );
+        assert_eq!(config.file_extensions, Some(vec!["rs".to_string()]));
+        assert_eq!(config.thread_count.get(), 2);
+        assert_eq!(config.context_after, 5);
+        assert_eq!(config.pattern_definitions, cli.pattern_definitions);
+
+        // Unknown keys are reported rather than silently ignored
+        fs::write(root.join(".rustscout").join("config.yaml"), "threads: 2\n")?;
+        assert!(SearchConfig::load_workspace_defaults(root).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_scaffolded_config_loads() -> SearchResult<()> {
+        for format in ["yaml", "json"] {
+            let dir = tempdir()?;
+            let path = crate::workspace::init_search_config(dir.path(), format)?;
+            assert!(path.ends_with(format!("config.{}", format)));
+            assert_eq!(
+                SearchConfig::load_workspace_defaults(dir.path())?,
+                Some(PartialSearchConfig::default())
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_profile() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::create_dir_all(root.join(".rustscout"))?;
+        fs::write(
+            root.join(".rustscout").join("config.yaml"),
+            r#"
+ignore_patterns: ["**/target/**"]
+profiles:
+  todo-audit:
+    patterns:
+      - text: TODO
+      - text: 'FIXME\(\w+\)'
+        is_regex: true
+        name: fixme
+    file_extensions: [rs, md]
+  debug-prints:
+    patterns:
+      - text: dbg!
+    ignore_patterns: ["**/examples/**"]
+"#,
+        )?;
+
+        let config = load_profile(root, "todo-audit")?;
+        let texts: Vec<_> = config
+            .pattern_definitions
+            .iter()
+            .map(|def| def.text.as_str())
+            .collect();
+        assert_eq!(texts, ["TODO", r"FIXME\(\w+\)"]);
+        assert!(!config.pattern_definitions[0].is_regex);
+        assert_eq!(
+            config.pattern_definitions[0].boundary_mode,
+            WordBoundaryMode::None
+        );
+        assert!(config.pattern_definitions[1].is_regex);
+        assert_eq!(config.pattern_definitions[1].name.as_deref(), Some("fixme"));
+        assert_eq!(
+            config.file_extensions,
+            Some(vec!["rs".to_string(), "md".to_string()])
+        );
+
+        let config = load_profile(root, "debug-prints")?;
+        assert_eq!(config.file_extensions, None);
+        assert_eq!(
+            config.ignore_patterns,
+            vec!["**/target/**".to_string(), "**/examples/**".to_string()]
+        );
+
+        let error = load_profile(root, "licence").unwrap_err().to_string();
+        assert!(error.contains("'licence'"), "{}", error);
+        assert!(
+            error.contains("available profiles: debug-prints, todo-audit"),
+            "{}",
+            error
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_rule_ids_rejected() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        fs::create_dir_all(root.join(".rustscout"))?;
+        fs::write(
+            root.join(".rustscout").join("config.yaml"),
+            r#"
+profiles:
+  audit:
+    patterns:
+      - text: TODO
+        name: todo
+      - text: XXX
+        name: todo
+"#,
+        )?;
+        let error = load_profile(root, "audit").unwrap_err().to_string();
+        assert!(error.contains("profile 'audit'"), "{}", error);
+        assert!(error.contains("Duplicate pattern name 'todo'"), "{}", error);
+
+        // A name can't take the id an unnamed pattern reports under either
+        let config_path = root.join("search.yaml");
+        let mut named = PatternDefinition::new("FIXME".to_string(), false, WordBoundaryMode::None);
+        named.name = Some("pattern-0".to_string());
+        let config = SearchConfig {
+            pattern_definitions: vec![
+                PatternDefinition::new("TODO".to_string(), false, WordBoundaryMode::None),
+                named,
+            ],
+            ..Default::default()
+        };
+        fs::write(&config_path, serde_yaml::to_string(&config).unwrap())?;
+        let error = SearchConfig::load_from(&config_path)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'pattern-0'"), "{}", error);
+        Ok(())
+    }
 }
@@ -0,0 +1,479 @@
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use super::{EncodingMode, OutputMode, Schedule, SearchConfig};
+use crate::cache::{CacheFormat, ChangeDetectionStrategy};
+use crate::errors::{SearchError, SearchResult};
+use crate::filters::{FileTypes, IncludeGlobs};
+use crate::search::cancel::CancellationToken;
+use crate::search::matcher::{PatternDefinition, WordBoundaryMode};
+use crate::search::progress::ProgressHook;
+
+/// Context lines allowed on either side of a match
+pub const MAX_CONTEXT_LINES: usize = 10_000;
+
+/// Builds a [`SearchConfig`] with fluent setters, checking it in [`build`].
+///
+/// Starts from the same defaults as [`SearchConfig::default`]: search the
+/// current directory with one thread per available core and no cache.
+///
+/// ```
+/// use rustscout::SearchConfig;
+///
+/// let config = SearchConfig::builder()
+///     .pattern("TODO")
+///     .regex_pattern(r"FIXME\(\w+\)")
+///     .root("src")
+///     .extensions(["rs"])
+///     .context(1, 2)
+///     .build()?;
+/// assert_eq!(config.pattern_definitions.len(), 2);
+/// # Ok::<(), rustscout::SearchError>(())
+/// ```
+///
+/// [`build`]: SearchConfigBuilder::build
+#[derive(Debug, Clone, Default)]
+pub struct SearchConfigBuilder {
+    config: SearchConfig,
+    roots: Vec<PathBuf>,
+    allow_empty_patterns: bool,
+}
+
+impl SearchConfigBuilder {
+    /// Adds a literal pattern
+    pub fn pattern(self, text: impl Into<String>) -> Self {
+        self.pattern_definition(PatternDefinition::new(
+            text.into(),
+            false,
+            WordBoundaryMode::None,
+        ))
+    }
+
+    /// Adds a regular expression pattern
+    pub fn regex_pattern(self, text: impl Into<String>) -> Self {
+        self.pattern_definition(PatternDefinition::new(
+            text.into(),
+            true,
+            WordBoundaryMode::None,
+        ))
+    }
+
+    /// Adds a fully specified pattern
+    pub fn pattern_definition(mut self, definition: PatternDefinition) -> Self {
+        self.config.pattern_definitions.push(definition);
+        self
+    }
+
+    /// Adds several fully specified patterns
+    pub fn pattern_definitions(
+        mut self,
+        definitions: impl IntoIterator<Item = PatternDefinition>,
+    ) -> Self {
+        self.config.pattern_definitions.extend(definitions);
+        self
+    }
+
+    /// Lets [`build`](Self::build) succeed without any patterns; such a
+    /// search matches nothing
+    pub fn allow_empty_patterns(mut self) -> Self {
+        self.allow_empty_patterns = true;
+        self
+    }
+
+    /// Adds a directory (or file) to search. Without any, the current
+    /// directory is searched.
+    pub fn root(mut self, path: impl Into<PathBuf>) -> Self {
+        self.roots.push(path.into());
+        self
+    }
+
+    /// Adds several directories (or files) to search
+    pub fn roots(mut self, paths: impl IntoIterator<Item = impl Into<PathBuf>>) -> Self {
+        self.roots.extend(paths.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only searches files with one of these extensions
+    pub fn extensions(mut self, extensions: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config.file_extensions = Some(extensions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Skips files with one of these extensions, which may contain dots
+    /// (`min.js`)
+    pub fn exclude_extensions(
+        mut self,
+        extensions: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.config.exclude_extensions = Some(extensions.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Only searches files matching one of these globs
+    pub fn include_globs(mut self, globs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.config
+            .include_globs
+            .extend(globs.into_iter().map(Into::into));
+        self
+    }
+
+    /// Whether extensionless files are sniffed for a `#!` line when
+    /// filtering by extension or type (on by default)
+    pub fn sniff_shebang(mut self, sniff_shebang: bool) -> Self {
+        self.config.sniff_shebang = sniff_shebang;
+        self
+    }
+
+    /// Which file types to include or skip; selected types add to
+    /// [`extensions`](Self::extensions)
+    pub fn file_types(mut self, file_types: FileTypes) -> Self {
+        self.config.file_types = file_types;
+        self
+    }
+
+    /// Skips files matching these ignore patterns
+    pub fn ignore_patterns(
+        mut self,
+        patterns: impl IntoIterator<Item = impl Into<String>>,
+    ) -> Self {
+        self.config
+            .ignore_patterns
+            .extend(patterns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Only collects statistics, not matches
+    pub fn stats_only(mut self, stats_only: bool) -> Self {
+        self.config.stats_only = stats_only;
+        self
+    }
+
+    /// Number of worker threads
+    pub fn threads(mut self, thread_count: NonZeroUsize) -> Self {
+        self.config.thread_count = thread_count;
+        self
+    }
+
+    /// Log level passed on to the caller's logger
+    pub fn log_level(mut self, log_level: impl Into<String>) -> Self {
+        self.config.log_level = log_level.into();
+        self
+    }
+
+    /// Lines of context to include before and after each match
+    pub fn context(mut self, before: usize, after: usize) -> Self {
+        self.config.context_before = before;
+        self.config.context_after = after;
+        self
+    }
+
+    /// Reuses results for unchanged files from a cache stored at `cache_path`
+    pub fn incremental(mut self, cache_path: impl Into<PathBuf>) -> Self {
+        self.config.incremental = true;
+        self.config.cache_path = Some(cache_path.into());
+        self
+    }
+
+    /// How the incremental cache detects changed files
+    pub fn cache_strategy(mut self, strategy: ChangeDetectionStrategy) -> Self {
+        self.config.cache_strategy = strategy;
+        self
+    }
+
+    /// Maximum size of the incremental cache in bytes
+    pub fn max_cache_size(mut self, max_bytes: Option<u64>) -> Self {
+        self.config.max_cache_size = max_bytes;
+        self
+    }
+
+    /// Compresses the incremental cache
+    pub fn use_compression(mut self, use_compression: bool) -> Self {
+        self.config.use_compression = use_compression;
+        self
+    }
+
+    /// How the incremental cache is stored
+    pub fn cache_format(mut self, cache_format: CacheFormat) -> Self {
+        self.config.cache_format = cache_format;
+        self
+    }
+
+    /// How file contents are decoded
+    pub fn encoding_mode(mut self, mode: EncodingMode) -> Self {
+        self.config.encoding_mode = mode;
+        self
+    }
+
+    /// Reports lines that don't match instead of ones that do
+    pub fn invert_match(mut self, invert_match: bool) -> Self {
+        self.config.invert_match = invert_match;
+        self
+    }
+
+    /// Stops reading a file after this many matches
+    pub fn max_count_per_file(mut self, max_count: Option<usize>) -> Self {
+        self.config.max_count_per_file = max_count;
+        self
+    }
+
+    /// Stops the search after this many matches in total
+    pub fn max_total_matches(mut self, max_total: Option<usize>) -> Self {
+        self.config.max_total_matches = max_total;
+        self
+    }
+
+    /// Keeps at most about this many bytes of match details, only counting
+    /// the files found past it
+    pub fn memory_budget(mut self, max_bytes: Option<usize>) -> Self {
+        self.config.memory_budget = max_bytes;
+        self
+    }
+
+    /// What to report for each file
+    pub fn output_mode(mut self, mode: OutputMode) -> Self {
+        self.config.output_mode = mode;
+        self
+    }
+
+    /// Extracts capture group spans for regex matches
+    pub fn capture_groups(mut self, capture_groups: bool) -> Self {
+        self.config.capture_groups = capture_groups;
+        self
+    }
+
+    /// Searches hidden files and directories
+    pub fn include_hidden(mut self, include_hidden: bool) -> Self {
+        self.config.include_hidden = include_hidden;
+        self
+    }
+
+    /// Ignores `.gitignore`/`.ignore`/`.rustscoutignore` files
+    pub fn no_ignore(mut self, no_ignore: bool) -> Self {
+        self.config.no_ignore = no_ignore;
+        self
+    }
+
+    /// Ignores `.rustscoutignore` files only
+    pub fn no_rustscoutignore(mut self, no_rustscoutignore: bool) -> Self {
+        self.config.no_rustscoutignore = no_rustscoutignore;
+        self
+    }
+
+    /// Follows symbolic links while walking
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.config.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Skips files larger than this many bytes
+    pub fn max_file_size(mut self, max_bytes: Option<u64>) -> Self {
+        self.config.max_file_size = max_bytes;
+        self
+    }
+
+    /// Descends at most this many directory levels below each root
+    pub fn max_depth(mut self, max_depth: Option<usize>) -> Self {
+        self.config.max_depth = max_depth;
+        self
+    }
+
+    /// Skips walked files that look minified; files given as roots are
+    /// still searched
+    pub fn skip_minified(mut self, skip_minified: bool) -> Self {
+        self.config.skip_minified = skip_minified;
+        self
+    }
+
+    /// Searches the members of .zip, .tar and .tar.gz archives; needs the
+    /// `archives` feature
+    pub fn search_archives(mut self, search_archives: bool) -> Self {
+        self.config.search_archives = search_archives;
+        self
+    }
+
+    /// Whether large files are matched in chunks on several threads (on by
+    /// default)
+    pub fn parallel_large_files(mut self, parallel_large_files: bool) -> Self {
+        self.config.parallel_large_files = parallel_large_files;
+        self
+    }
+
+    /// Whether a file reached through several hard links is searched only
+    /// once (on by default)
+    pub fn dedupe_hardlinks(mut self, dedupe_hardlinks: bool) -> Self {
+        self.config.dedupe_hardlinks = dedupe_hardlinks;
+        self
+    }
+
+    /// Order in which files are handed to the workers
+    pub fn schedule(mut self, schedule: Schedule) -> Self {
+        self.config.schedule = schedule;
+        self
+    }
+
+    /// Only searches files last modified at or after this time
+    pub fn modified_after(mut self, time: Option<SystemTime>) -> Self {
+        self.config.modified_after = time;
+        self
+    }
+
+    /// Only searches files last modified before this time
+    pub fn modified_before(mut self, time: Option<SystemTime>) -> Self {
+        self.config.modified_before = time;
+        self
+    }
+
+    /// Only searches files whose size in bytes lies in `min..=max`
+    pub fn size_range(mut self, range: Option<(u64, u64)>) -> Self {
+        self.config.size_range = range;
+        self
+    }
+
+    /// Only reports matches on lines added or changed since this git ref
+    pub fn diff_base(mut self, base: Option<String>) -> Self {
+        self.config.diff_base = base;
+        self
+    }
+
+    /// Searches exactly these files instead of walking the roots
+    pub fn explicit_paths(mut self, paths: Option<Vec<PathBuf>>) -> Self {
+        self.config.explicit_paths = paths;
+        self
+    }
+
+    /// Fails on explicit paths that aren't existing files
+    pub fn strict_paths(mut self, strict_paths: bool) -> Self {
+        self.config.strict_paths = strict_paths;
+        self
+    }
+
+    /// Searches stdin instead of files
+    pub fn stdin(mut self, stdin: bool) -> Self {
+        self.config.stdin = stdin;
+        self
+    }
+
+    /// Lets another thread stop the search early
+    pub fn cancellation(mut self, token: CancellationToken) -> Self {
+        self.config.cancellation = Some(token);
+        self
+    }
+
+    /// Receives progress updates while the search runs
+    pub fn progress(mut self, hook: Option<ProgressHook>) -> Self {
+        self.config.progress = hook;
+        self
+    }
+
+    /// Checks the settings and returns the config.
+    ///
+    /// Fails if there are no patterns (unless
+    /// [`allow_empty_patterns`](Self::allow_empty_patterns) was called), if
+    /// the incremental cache has no path, if more than [`MAX_CONTEXT_LINES`]
+    /// context lines were asked for, if an include glob is invalid, or if
+    /// the size range is empty.
+    pub fn build(self) -> SearchResult<SearchConfig> {
+        let mut config = self.config;
+        if !self.roots.is_empty() {
+            config.root_paths = self.roots;
+        }
+
+        if config.pattern_definitions.is_empty() && !self.allow_empty_patterns {
+            return Err(SearchError::config_error("No search patterns given"));
+        }
+        if config.incremental
+            && config
+                .cache_path
+                .as_ref()
+                .is_none_or(|path| path.as_os_str().is_empty())
+        {
+            return Err(SearchError::config_error(
+                "Incremental search needs a cache path",
+            ));
+        }
+        let context = config.context_before.max(config.context_after);
+        if context > MAX_CONTEXT_LINES {
+            return Err(SearchError::config_error(format!(
+                "Context of {} lines is more than the maximum of {}",
+                context, MAX_CONTEXT_LINES
+            )));
+        }
+        IncludeGlobs::new(&config.include_globs)?;
+        if let Some((min, max)) = config.size_range {
+            if min > max {
+                return Err(SearchError::config_error(format!(
+                    "Minimum file size {} is larger than the maximum of {}",
+                    min, max
+                )));
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl From<SearchConfig> for SearchConfigBuilder {
+    /// Starts from an existing config, such as one assembled from workspace
+    /// defaults and command-line flags, so that [`build`](Self::build) can
+    /// check it
+    fn from(config: SearchConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_defaults_and_setters() -> SearchResult<()> {
+        let config = SearchConfig::builder()
+            .pattern("TODO")
+            .root("src")
+            .root("tests")
+            .context(2, 3)
+            .incremental("cache.json")
+            .build()?;
+        assert_eq!(
+            config.root_paths,
+            vec![PathBuf::from("src"), PathBuf::from("tests")]
+        );
+        assert_eq!((config.context_before, config.context_after), (2, 3));
+        assert!(config.incremental);
+        assert_eq!(config.cache_path, Some(PathBuf::from("cache.json")));
+        assert_eq!(config.thread_count, SearchConfig::default_thread_count());
+
+        let config = SearchConfig::builder().regex_pattern(r"\d+").build()?;
+        assert_eq!(config.root_paths, vec![PathBuf::from(".")]);
+        assert!(config.pattern_definitions[0].is_regex);
+        assert!(!config.incremental);
+        Ok(())
+    }
+
+    #[test]
+    fn test_builder_validation() {
+        assert!(SearchConfig::builder().build().is_err());
+        assert!(SearchConfig::builder()
+            .allow_empty_patterns()
+            .build()
+            .is_ok());
+        assert!(SearchConfig::builder()
+            .pattern("x")
+            .incremental("")
+            .build()
+            .is_err());
+        assert!(SearchConfig::builder()
+            .pattern("x")
+            .context(0, MAX_CONTEXT_LINES + 1)
+            .build()
+            .is_err());
+        assert!(SearchConfig::builder()
+            .pattern("x")
+            .include_globs(["src/[", "*.rs"])
+            .build()
+            .is_err());
+    }
+}
@@ -0,0 +1,205 @@
+//! Detection and transcoding of text files that aren't plain UTF-8.
+//!
+//! Files are sniffed for a byte-order mark (UTF-8, UTF-16LE or UTF-16BE).
+//! Without one, valid UTF-8 is used as-is and anything else is read as
+//! Latin-1 (decoded as Windows-1252, its common superset). Matching always
+//! runs on the decoded UTF-8 text, and replacements are encoded back into the
+//! file's original encoding.
+//!
+//! Line endings are detected too, so edits that rebuild a file line by line
+//! write back the line breaks it had.
+
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+use crate::errors::{SearchError, SearchResult};
+
+/// The encoding a file was read with, so it can be written back the same way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextEncoding {
+    encoding: &'static Encoding,
+    /// Length of the byte-order mark at the start of the file (0 if none)
+    bom_len: usize,
+}
+
+impl TextEncoding {
+    /// Plain UTF-8 without a byte-order mark
+    pub const UTF8: Self = Self {
+        encoding: UTF_8,
+        bom_len: 0,
+    };
+
+    /// Detects the encoding of a file's contents
+    pub fn detect(bytes: &[u8]) -> Self {
+        if let Some((encoding, bom_len)) = Encoding::for_bom(bytes) {
+            return Self { encoding, bom_len };
+        }
+        if std::str::from_utf8(bytes).is_ok() {
+            Self::UTF8
+        } else {
+            Self {
+                encoding: WINDOWS_1252,
+                bom_len: 0,
+            }
+        }
+    }
+
+    /// Name of the encoding, e.g. `UTF-16LE`
+    pub fn name(&self) -> &'static str {
+        self.encoding.name()
+    }
+
+    /// Whether the file started with a byte-order mark
+    pub fn has_bom(&self) -> bool {
+        self.bom_len > 0
+    }
+
+    /// Whether the file is UTF-8 without a byte-order mark, so offsets in the
+    /// decoded text are also offsets in the file
+    pub fn is_plain_utf8(&self) -> bool {
+        *self == Self::UTF8
+    }
+
+    /// Decodes file contents to UTF-8, dropping the byte-order mark.
+    ///
+    /// Malformed sequences are replaced with U+FFFD. Plain UTF-8 is borrowed.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        let body = bytes.get(self.bom_len..).unwrap_or_default();
+        self.encoding.decode_without_bom_handling(body).0
+    }
+
+    /// Encodes text in this encoding, restoring the byte-order mark if the
+    /// original file had one.
+    ///
+    /// Fails if the text contains characters the encoding can't represent,
+    /// rather than writing substitutes into the file.
+    pub fn encode(&self, text: &str) -> SearchResult<Vec<u8>> {
+        let mut bytes = Vec::with_capacity(self.bom_len + text.len());
+        if self.encoding == UTF_16LE || self.encoding == UTF_16BE {
+            // encoding_rs only encodes to UTF-8 for the UTF-16 encodings
+            let big_endian = self.encoding == UTF_16BE;
+            if self.has_bom() {
+                bytes.extend_from_slice(if big_endian {
+                    &[0xFE, 0xFF]
+                } else {
+                    &[0xFF, 0xFE]
+                });
+            }
+            for unit in text.encode_utf16() {
+                bytes.extend_from_slice(&if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                });
+            }
+            return Ok(bytes);
+        }
+
+        if self.has_bom() {
+            bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+        }
+        let (encoded, _, unmappable) = self.encoding.encode(text);
+        if unmappable {
+            return Err(SearchError::config_error(format!(
+                "Text contains characters that can't be written as {}",
+                self.name()
+            )));
+        }
+        bytes.extend_from_slice(&encoded);
+        Ok(bytes)
+    }
+}
+
+/// The line breaks used in a text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    /// `\n` everywhere, or no line breaks at all
+    #[default]
+    Lf,
+    /// `\r\n` everywhere
+    CrLf,
+    /// Some lines end in `\n` and others in `\r\n`
+    Mixed,
+}
+
+impl LineEnding {
+    /// Detects the line endings used in `text`
+    pub fn detect(text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let (mut lf, mut crlf) = (0usize, 0usize);
+        for (index, _) in text.match_indices('\n') {
+            if index > 0 && bytes[index - 1] == b'\r' {
+                crlf += 1;
+            } else {
+                lf += 1;
+            }
+        }
+        match (lf, crlf) {
+            (_, 0) => Self::Lf,
+            (0, _) => Self::CrLf,
+            _ => Self::Mixed,
+        }
+    }
+
+    /// The line break for lines added to a text with these endings (`\n`
+    /// for mixed endings)
+    pub fn newline(&self) -> &'static str {
+        match self {
+            Self::CrLf => "\r\n",
+            Self::Lf | Self::Mixed => "\n",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_round_trip() -> SearchResult<()> {
+        let utf16le = [0xFF, 0xFE, b'h', 0, 0xE9, 0, b'\n', 0];
+        let utf16be = [0xFE, 0xFF, 0, b'h', 0, 0xE9, 0, b'\n'];
+        let utf8_bom = [0xEF, 0xBB, 0xBF, b'h', 0xC3, 0xA9, b'\n'];
+        let latin1 = [b'h', 0xE9, b'\n'];
+
+        for (bytes, name, bom) in [
+            (&utf16le[..], "UTF-16LE", true),
+            (&utf16be[..], "UTF-16BE", true),
+            (&utf8_bom[..], "UTF-8", true),
+            (&latin1[..], "windows-1252", false),
+        ] {
+            let encoding = TextEncoding::detect(bytes);
+            assert_eq!(encoding.name(), name);
+            assert_eq!(encoding.has_bom(), bom);
+            assert_eq!(encoding.decode(bytes), "hé\n");
+            assert_eq!(encoding.encode("hé\n")?, bytes);
+        }
+
+        let plain = "hé\n".as_bytes();
+        assert!(TextEncoding::detect(plain).is_plain_utf8());
+        assert!(matches!(
+            TextEncoding::detect(plain).decode(plain),
+            Cow::Borrowed(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_line_ending_detection() {
+        assert_eq!(LineEnding::detect(""), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("one\ntwo"), LineEnding::Lf);
+        assert_eq!(LineEnding::detect("one\r\ntwo\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::detect("one\r\ntwo\n"), LineEnding::Mixed);
+        assert_eq!(LineEnding::detect("\r\r\n"), LineEnding::CrLf);
+        assert_eq!(LineEnding::CrLf.newline(), "\r\n");
+        assert_eq!(LineEnding::Mixed.newline(), "\n");
+    }
+
+    #[test]
+    fn test_unmappable_characters_rejected() {
+        let latin1 = TextEncoding::detect(&[0xE9]);
+        assert!(latin1.encode("日本").is_err());
+    }
+}
@@ -73,6 +73,89 @@ pub enum SearchError {
     },
     #[error("JSON error: {0}")]
     JsonError(#[from] serde_json::Error),
+    #[error("Invalid replacement range {start}..{end} in {path}: {reason}")]
+    InvalidRange {
+        path: PathBuf,
+        start: usize,
+        end: usize,
+        reason: String,
+    },
+    #[error("{source} (rolled back {files} files)")]
+    RolledBack {
+        files: usize,
+        source: Box<SearchError>,
+    },
+    #[error(
+        "{} files were edited after the operation and undoing it would lose those edits: {} (use --force to undo anyway)",
+        .files.len(),
+        join_paths(.files)
+    )]
+    UndoConflict { files: Vec<PathBuf> },
+    #[error("Backup {path} is corrupted: expected SHA-256 {expected}, found {actual} (use --force to restore it anyway)")]
+    BackupCorrupted {
+        path: PathBuf,
+        expected: String,
+        actual: String,
+    },
+    #[error(
+        "Undo restored {restored} files, but {} failed: {}",
+        .failures.len(),
+        join_failures(.failures)
+    )]
+    UndoIncomplete {
+        restored: usize,
+        failures: Vec<(PathBuf, SearchError)>,
+    },
+}
+
+/// Problems found by [`PatternDefinition::validate`](crate::search::matcher::PatternDefinition::validate)
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PatternError {
+    #[error("Pattern is empty")]
+    Empty,
+    #[error("Invalid regex '{pattern}': {message}")]
+    InvalidRegex {
+        pattern: String,
+        message: String,
+        /// Character offset of the error within the pattern, when known
+        position: Option<usize>,
+    },
+    #[error("Fuzzy matching only supports literal patterns, but '{0}' is a regex")]
+    FuzzyRegex(String),
+    #[error("Boundary mode is ignored for '{pattern}': {reason}")]
+    BoundaryModeIgnored { pattern: String, reason: String },
+}
+
+impl PatternError {
+    /// Whether the pattern still works as written. Empty patterns never match,
+    /// and ignored boundary modes only match more than intended.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, Self::Empty | Self::BoundaryModeIgnored { .. })
+    }
+}
+
+impl From<PatternError> for SearchError {
+    fn from(err: PatternError) -> Self {
+        Self::InvalidPattern(err.to_string())
+    }
+}
+
+/// Lists paths for an error message, separated by commas
+fn join_paths(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Lists paths with what went wrong with each, separated by semicolons
+fn join_failures(failures: &[(PathBuf, SearchError)]) -> String {
+    failures
+        .iter()
+        .map(|(path, error)| format!("{}: {}", path.display(), error))
+        .collect::<Vec<_>>()
+        .join("; ")
 }
 
 /// Canonicalize the path and strip UNC prefixes so that
@@ -125,6 +208,26 @@ impl SearchError {
         Self::ConfigError(msg.into())
     }
 
+    pub fn invalid_range(
+        path: impl Into<PathBuf>,
+        (start, end): (usize, usize),
+        reason: impl Into<String>,
+    ) -> Self {
+        Self::InvalidRange {
+            path: path.into(),
+            start,
+            end,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn rolled_back(files: usize, source: SearchError) -> Self {
+        Self::RolledBack {
+            files,
+            source: Box::new(source),
+        }
+    }
+
     pub fn encoding_error(path: impl Into<PathBuf>, source: std::string::FromUtf8Error) -> Self {
         let path = path.into();
         let unified = unify_path(&path);
@@ -73,7 +73,18 @@
 /// This module uses free functions instead of traits for simplicity, but the concepts
 /// could be refactored into a trait-based design for more complex filtering requirements.
 use glob::{MatchOptions, Pattern};
-use std::path::Path;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs::Metadata;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+use tracing::warn;
+
+use crate::errors::{unify_path, SearchError, SearchResult};
+use crate::workspace::detect_workspace_root;
 
 /// Checks if a file should be included in the search based on its extension
 pub fn has_valid_extension(path: &Path, extensions: &Option<Vec<String>>) -> bool {
@@ -90,6 +101,87 @@ pub fn has_valid_extension(path: &Path, extensions: &Option<Vec<String>>) -> boo
     }
 }
 
+/// Checks if a file name ends in one of the excluded extensions.
+///
+/// Extensions may contain dots (`min.js` excludes `app.min.js` but not
+/// `app.js`) and are compared case-insensitively. A leading dot is optional.
+/// The extension has to follow something, so a dotfile such as `.lock` or a
+/// file without any extension is never excluded.
+pub fn has_excluded_extension(path: &Path, exclude_extensions: &Option<Vec<String>>) -> bool {
+    let Some(exclude) = exclude_extensions else {
+        return false;
+    };
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let file_name = file_name.to_ascii_lowercase();
+    exclude.iter().any(|ext| {
+        let suffix = format!(".{}", ext.trim_start_matches('.').to_ascii_lowercase());
+        file_name.len() > suffix.len() && file_name.ends_with(&suffix)
+    })
+}
+
+/// Checks if a file matches one of the include globs; with none, every file
+/// does.
+///
+/// The globs are compiled on every call, and if one is invalid no file
+/// matches; use [`IncludeGlobs`] to check many paths.
+pub fn matches_include_globs(path: &Path, root_path: &Path, include_globs: &[String]) -> bool {
+    IncludeGlobs::new(include_globs).is_ok_and(|globs| globs.matches(path, root_path))
+}
+
+/// Compiled include globs, as given to `--include-glob`.
+///
+/// Globs containing a slash match the path relative to the root (`*` stays
+/// within one directory, `**` crosses any number); others match just the
+/// file name, so `*.rs` includes Rust files at any depth.
+#[derive(Debug, Clone, Default)]
+pub struct IncludeGlobs {
+    /// Each glob, and whether it matches the relative path rather than the
+    /// file name
+    globs: Vec<(Pattern, bool)>,
+}
+
+impl IncludeGlobs {
+    /// Compiles `globs`, failing on the first invalid one
+    pub fn new(globs: &[String]) -> SearchResult<Self> {
+        let globs = globs
+            .iter()
+            .map(|glob| {
+                Pattern::new(glob)
+                    .map(|pattern| (pattern, glob.contains('/')))
+                    .map_err(|e| {
+                        SearchError::config_error(format!("Invalid include glob '{}': {}", glob, e))
+                    })
+            })
+            .collect::<SearchResult<_>>()?;
+        Ok(Self { globs })
+    }
+
+    /// Checks `path`, found under `root_path`, against the globs; with none,
+    /// every path matches
+    pub fn matches(&self, path: &Path, root_path: &Path) -> bool {
+        if self.globs.is_empty() {
+            return true;
+        }
+        let file_name = path.file_name().and_then(|os| os.to_str()).unwrap_or("");
+        let rel_slash = to_relative_slash_path(path, root_path);
+        let match_opts = MatchOptions {
+            case_sensitive: true,
+            require_literal_separator: true,
+            ..Default::default()
+        };
+        self.globs.iter().any(|(pattern, relative)| {
+            let target = if *relative {
+                rel_slash.as_str()
+            } else {
+                file_name
+            };
+            pattern.matches_with(target, match_opts)
+        })
+    }
+}
+
 /// Convert `path` into a relative path (with forward slashes)
 /// relative to `root_path`.
 /// If `strip_prefix` fails (e.g. path isn't under root), fallback to the full path.
@@ -100,13 +192,41 @@ fn to_relative_slash_path(path: &Path, root_path: &Path) -> String {
 
 /// Checks if a file should be ignored based on ignore patterns
 ///
+/// Paths inside a `.git` directory are always ignored; see [`is_git_path`] and
+/// [`matches_ignore_patterns`] for the two halves of this check.
+pub fn should_ignore(path: &Path, root_path: &Path, ignore_patterns: &[String]) -> bool {
+    is_git_path(path, root_path) || matches_ignore_patterns(path, root_path, ignore_patterns)
+}
+
+/// Prefix of the temporary files a replacement writes next to a file before
+/// renaming them over it
+pub const REPLACE_TEMP_PREFIX: &str = ".rustscout-tmp-";
+
+/// Checks if a path is a temporary file written by a replacement, which is
+/// never searched
+pub fn is_replace_temp_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.starts_with(REPLACE_TEMP_PREFIX))
+}
+
+/// Checks if a path is a `.git` directory or lies inside one
+pub fn is_git_path(path: &Path, root_path: &Path) -> bool {
+    path.strip_prefix(root_path)
+        .unwrap_or(path)
+        .components()
+        .any(|c| c.as_os_str() == ".git")
+}
+
+/// Checks if a file matches any of the given ignore patterns
+///
 /// Uses a simplified `.gitignore`-like syntax:
 /// - If the pattern does not contain a slash, it matches only the final file name.
 ///   Example: `invalid.rs` matches any file named `invalid.rs` in any directory.
 /// - If the pattern contains a slash, it is interpreted as a glob pattern on the entire path.
 ///   Example: `tests/*.rs` matches `.rs` files in the `tests/` folder only.
 ///   Example: `**/invalid.rs` matches `invalid.rs` anywhere in the directory tree.
-pub fn should_ignore(path: &Path, root_path: &Path, ignore_patterns: &[String]) -> bool {
+pub fn matches_ignore_patterns(path: &Path, root_path: &Path, ignore_patterns: &[String]) -> bool {
     let file_name = path.file_name().and_then(|os| os.to_str()).unwrap_or("");
     let rel_slash = to_relative_slash_path(path, root_path);
 
@@ -117,12 +237,6 @@ pub fn should_ignore(path: &Path, root_path: &Path, ignore_patterns: &[String])
         ..Default::default()
     };
 
-    // Always ignore .git directories and files
-    if rel_slash.contains("/.git/") || rel_slash.contains("\\.git\\") || file_name == ".git" {
-        return true;
-    }
-
-    // Check custom ignore patterns
     for pattern in ignore_patterns {
         if !pattern.contains('/') {
             // If the pattern has no slash, treat it as matching just the file name
@@ -172,6 +286,512 @@ pub fn should_include_file(
         && !should_ignore(path, root_path, ignore_patterns)
 }
 
+/// Name of the search-only ignore files read while walking
+pub const RUSTSCOUT_IGNORE_FILE: &str = ".rustscoutignore";
+
+/// Parses the contents of a `.rustscoutignore` file found in `dir`.
+///
+/// The syntax is the same as `.gitignore`: `!pattern` re-includes a path, a
+/// trailing `/` only matches directories, and patterns containing a slash are
+/// anchored to `dir`. Invalid lines are logged and skipped.
+pub fn parse_rustscout_ignore(dir: &Path, content: &str) -> Gitignore {
+    let mut builder = GitignoreBuilder::new(dir);
+    for line in content.lines() {
+        if let Err(e) = builder.add_line(None, line) {
+            warn!(
+                "Skipping line in {}: {}",
+                dir.join(RUSTSCOUT_IGNORE_FILE).display(),
+                e
+            );
+        }
+    }
+    builder.build().unwrap_or_else(|e| {
+        warn!(
+            "Ignoring {}: {}",
+            dir.join(RUSTSCOUT_IGNORE_FILE).display(),
+            e
+        );
+        Gitignore::empty()
+    })
+}
+
+/// The `.rustscoutignore` files that apply to paths below a search root:
+/// one in each directory from the workspace root down to the path.
+///
+/// A file in a deeper directory takes precedence over those above it, so a
+/// subdirectory can re-include (`!pattern`) what the workspace root ignores.
+/// Files are read the first time a path below their directory is checked.
+#[derive(Debug)]
+pub struct RustscoutIgnore {
+    /// The search root as paths passed to [`is_ignored`](Self::is_ignored) start
+    root: PathBuf,
+    /// `root`, canonicalized
+    canonical_root: PathBuf,
+    /// The highest directory whose file applies
+    top: PathBuf,
+    /// Parsed files by directory; `None` where a directory has none
+    files: Mutex<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl RustscoutIgnore {
+    /// Reads the files that apply below `root`, up to its workspace root
+    pub fn new(root: &Path) -> Self {
+        let canonical_root = unify_path(root);
+        let top = detect_workspace_root(root).unwrap_or_else(|_| canonical_root.clone());
+        Self {
+            root: root.to_path_buf(),
+            canonical_root,
+            top,
+            files: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Checks whether the deepest `.rustscoutignore` with an opinion on
+    /// `path` (or one of its parent directories) ignores it
+    ///
+    /// Paths that aren't spelled relative to the root (such as listed files)
+    /// are resolved first; those outside the root are never ignored.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let path = match path.strip_prefix(&self.root) {
+            Ok(relative) => self.canonical_root.join(relative),
+            Err(_) => unify_path(path),
+        };
+        if !path.starts_with(&self.canonical_root) {
+            return false;
+        }
+        for dir in path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.top))
+        {
+            let Some(file) = self.file_in(dir) else {
+                continue;
+            };
+            let matched = file.matched_path_or_any_parents(&path, is_dir);
+            if matched.is_ignore() {
+                return true;
+            }
+            if matched.is_whitelist() {
+                return false;
+            }
+        }
+        false
+    }
+
+    fn file_in(&self, dir: &Path) -> Option<Arc<Gitignore>> {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let content = std::fs::read_to_string(dir.join(RUSTSCOUT_IGNORE_FILE)).ok()?;
+                Some(Arc::new(parse_rustscout_ignore(dir, &content)))
+            })
+            .clone()
+    }
+}
+
+/// The `.gitignore` and `.ignore` files a directory walk honours, for files
+/// that are searched without one (such as a `--files-from` list).
+///
+/// As in the walk, `.gitignore` files (and `.git/info/exclude`) only apply
+/// inside a git repository, the deepest file with an opinion on a path wins,
+/// and `.ignore` takes precedence over `.gitignore` in the same directory.
+/// Files are read the first time a path below their directory is checked.
+#[derive(Debug, Default)]
+pub struct WalkIgnore {
+    /// Parsed files by path; `None` where a file doesn't exist
+    files: Mutex<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+}
+
+impl WalkIgnore {
+    /// Creates an empty set; files are read as paths are checked
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks whether the ignore files above `file` ignore it
+    pub fn is_ignored(&self, file: &Path) -> bool {
+        let path = unify_path(file);
+        let repo = path
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.join(".git").exists());
+        for dir in path.ancestors().skip(1) {
+            let mut sources = vec![dir.join(".ignore")];
+            if repo.is_some_and(|repo| dir.starts_with(repo)) {
+                sources.push(dir.join(".gitignore"));
+            }
+            if repo == Some(dir) {
+                sources.push(dir.join(".git").join("info").join("exclude"));
+            }
+            for source in sources {
+                let Some(ignore) = self.file(dir, &source) else {
+                    continue;
+                };
+                let matched = ignore.matched_path_or_any_parents(&path, false);
+                if matched.is_ignore() {
+                    return true;
+                }
+                if matched.is_whitelist() {
+                    return false;
+                }
+            }
+        }
+        false
+    }
+
+    /// Parses `source`, whose patterns are relative to `dir`
+    fn file(&self, dir: &Path, source: &Path) -> Option<Arc<Gitignore>> {
+        let mut files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files
+            .entry(source.to_path_buf())
+            .or_insert_with(|| {
+                if !source.is_file() {
+                    return None;
+                }
+                let mut builder = GitignoreBuilder::new(dir);
+                if let Some(e) = builder.add(source) {
+                    warn!("Skipping lines in {}: {}", source.display(), e);
+                }
+                builder.build().ok().map(Arc::new)
+            })
+            .clone()
+    }
+}
+
+/// Checks a file's modification time and size against the optional
+/// `--newer-than`/`--older-than` bounds and inclusive size range.
+///
+/// A file whose modification time can't be read passes the time checks.
+pub fn matches_metadata(
+    metadata: &Metadata,
+    modified_after: Option<SystemTime>,
+    modified_before: Option<SystemTime>,
+    size_range: Option<(u64, u64)>,
+) -> bool {
+    if let Some((min, max)) = size_range {
+        if !(min..=max).contains(&metadata.len()) {
+            return false;
+        }
+    }
+    let Ok(modified) = metadata.modified() else {
+        return true;
+    };
+    modified_after.is_none_or(|after| modified >= after)
+        && modified_before.is_none_or(|before| modified < before)
+}
+
+/// Bytes read from the start of an extensionless file by [`sniff_file`]
+pub const SNIFF_LENGTH: usize = 256;
+
+/// What the first few bytes of a file give away about it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Sniffed {
+    /// The extension of the script language named by a `#!` line, e.g. `py`
+    pub extension: Option<&'static str>,
+    /// Whether the bytes contain a NUL byte, which text files don't
+    pub binary: bool,
+}
+
+/// Reads the first [`SNIFF_LENGTH`] bytes of `path` and sniffs them; a file
+/// that can't be read gives nothing away
+pub fn sniff_file(path: &Path) -> Sniffed {
+    let mut head = Vec::with_capacity(SNIFF_LENGTH);
+    let read = std::fs::File::open(path)
+        .and_then(|file| file.take(SNIFF_LENGTH as u64).read_to_end(&mut head));
+    match read {
+        Ok(_) => sniff_bytes(&head),
+        Err(_) => Sniffed::default(),
+    }
+}
+
+/// Sniffs the first bytes of a file for a `#!` line and NUL bytes
+pub fn sniff_bytes(head: &[u8]) -> Sniffed {
+    let head = &head[..head.len().min(SNIFF_LENGTH)];
+    let binary = head.contains(&0);
+    let extension = head.strip_prefix(b"#!").and_then(|rest| {
+        let line = rest.split(|&b| b == b'\n').next().unwrap_or_default();
+        shebang_extension(&String::from_utf8_lossy(line))
+    });
+    Sniffed { extension, binary }
+}
+
+/// Maps the interpreter on a `#!` line (after the `#!`) to the extension of
+/// its language: `/usr/bin/env python3` and `/usr/bin/python3.11` give `py`
+fn shebang_extension(line: &str) -> Option<&'static str> {
+    let mut words = line.split_whitespace();
+    let mut program = words.next()?.rsplit('/').next()?;
+    if program == "env" {
+        // Skip env's own options and variable assignments
+        program = words.find(|word| !word.starts_with('-') && !word.contains('='))?;
+    }
+    let name = program.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+    match name {
+        "python" | "pypy" => Some("py"),
+        "sh" | "bash" | "zsh" | "dash" | "ksh" => Some("sh"),
+        "node" | "nodejs" => Some("js"),
+        "ruby" => Some("rb"),
+        "perl" => Some("pl"),
+        _ => None,
+    }
+}
+
+/// Built-in file types for `--type`, each with the file name globs it covers
+const BUILTIN_TYPES: &[(&str, &[&str])] = &[
+    ("c", &["*.c", "*.h"]),
+    ("cmake", &["*.cmake", "CMakeLists.txt"]),
+    ("config", &["*.cfg", "*.conf", "*.config", "*.ini"]),
+    (
+        "cpp",
+        &[
+            "*.cc", "*.cpp", "*.cxx", "*.h", "*.hh", "*.hpp", "*.hxx", "*.inl",
+        ],
+    ),
+    ("csharp", &["*.cs"]),
+    ("css", &["*.css", "*.scss", "*.sass", "*.less"]),
+    ("docker", &["*Dockerfile*", "*.dockerfile"]),
+    ("go", &["*.go"]),
+    ("html", &["*.htm", "*.html", "*.ejs"]),
+    ("java", &["*.java", "*.jsp"]),
+    ("js", &["*.js", "*.jsx", "*.mjs", "*.cjs", "*.vue"]),
+    ("json", &["*.json", "composer.lock"]),
+    ("kotlin", &["*.kt", "*.kts"]),
+    ("lua", &["*.lua"]),
+    (
+        "make",
+        &["[Gg][Nn][Uu]makefile", "[Mm]akefile", "*.mk", "*.mak"],
+    ),
+    (
+        "markdown",
+        &["*.markdown", "*.md", "*.mdown", "*.mdx", "*.mkd"],
+    ),
+    ("perl", &["*.pl", "*.pm", "*.t", "*.pod"]),
+    ("php", &["*.php", "*.php3", "*.php4", "*.php5", "*.phtml"]),
+    ("py", &["*.py", "*.pyi", "*.pyw"]),
+    ("python", &["*.py", "*.pyi", "*.pyw"]),
+    (
+        "ruby",
+        &["*.rb", "*.gemspec", "Gemfile", "Rakefile", ".irbrc"],
+    ),
+    ("rust", &["*.rs", "*.rs.in"]),
+    (
+        "sh",
+        &[
+            "*.sh",
+            "*.bash",
+            "*.zsh",
+            ".bashrc",
+            ".bash_profile",
+            ".zshrc",
+        ],
+    ),
+    ("sql", &["*.sql", "*.psql"]),
+    ("swift", &["*.swift"]),
+    ("toml", &["*.toml", "Cargo.lock"]),
+    ("ts", &["*.ts", "*.tsx", "*.mts", "*.cts"]),
+    ("txt", &["*.txt"]),
+    (
+        "xml",
+        &["*.xml", "*.xml.dist", "*.xsd", "*.xsl", "*.xslt", "*.svg"],
+    ),
+    ("yaml", &["*.yaml", "*.yml"]),
+];
+
+/// Named file types, like ripgrep's `--type`.
+///
+/// Each type is a list of globs matched (case-insensitively) against the file
+/// name. Selecting types limits a search to files of those types; negating a
+/// type skips its files. Types can be added on top of the built-in ones with
+/// [`add`](Self::add).
+///
+/// ```
+/// use rustscout::filters::FileTypes;
+/// use std::path::Path;
+///
+/// let mut types = FileTypes::new();
+/// types.add("proto:*.proto")?;
+/// types.select("rust")?;
+/// types.select("proto")?;
+/// assert!(types.matches(Path::new("src/lib.rs")));
+/// assert!(types.matches(Path::new("api/v1.proto")));
+/// assert!(!types.matches(Path::new("README.md")));
+/// # Ok::<(), rustscout::SearchError>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FileTypes {
+    /// Types added on top of the built-in ones; globs added to a built-in
+    /// name extend it
+    custom: BTreeMap<String, Vec<String>>,
+    /// Globs of the selected types
+    #[serde(with = "glob_strings")]
+    selected: Vec<Pattern>,
+    /// Globs of the negated types
+    #[serde(with = "glob_strings")]
+    negated: Vec<Pattern>,
+}
+
+impl FileTypes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a `name:glob` definition, as given to `--type-add`
+    pub fn add(&mut self, definition: &str) -> SearchResult<()> {
+        let (name, glob) = definition.split_once(':').ok_or_else(|| {
+            SearchError::config_error(format!(
+                "Invalid file type definition '{}'; expected NAME:GLOB",
+                definition
+            ))
+        })?;
+        self.add_globs(name, [glob])
+    }
+
+    /// Adds globs to the type called `name`, creating it if needed
+    pub fn add_globs(
+        &mut self,
+        name: &str,
+        globs: impl IntoIterator<Item = impl Into<String>>,
+    ) -> SearchResult<()> {
+        let name = name.trim();
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(SearchError::config_error(format!(
+                "Invalid file type name '{}'",
+                name
+            )));
+        }
+        for glob in globs {
+            let glob = glob.into();
+            if let Err(e) = Pattern::new(&glob) {
+                return Err(SearchError::config_error(format!(
+                    "Invalid glob '{}' for file type '{}': {}",
+                    glob, name, e
+                )));
+            }
+            self.custom.entry(name.to_string()).or_default().push(glob);
+        }
+        Ok(())
+    }
+
+    /// Limits matching to files of type `name`, in addition to any other
+    /// selected types
+    pub fn select(&mut self, name: &str) -> SearchResult<()> {
+        let globs = self.compiled(name)?;
+        self.selected.extend(globs);
+        Ok(())
+    }
+
+    /// Skips files of type `name`
+    pub fn negate(&mut self, name: &str) -> SearchResult<()> {
+        let globs = self.compiled(name)?;
+        self.negated.extend(globs);
+        Ok(())
+    }
+
+    /// Whether any type was selected
+    pub fn has_selection(&self) -> bool {
+        !self.selected.is_empty()
+    }
+
+    /// Checks a path against the selected and negated types. With no type
+    /// selected, every path not of a negated type matches.
+    pub fn matches(&self, path: &Path) -> bool {
+        (!self.has_selection() || self.is_selected(path)) && !self.is_negated(path)
+    }
+
+    /// Every known type with its globs, built-in and added, sorted by name
+    pub fn definitions(&self) -> BTreeMap<&str, Vec<&str>> {
+        let mut definitions: BTreeMap<&str, Vec<&str>> = BUILTIN_TYPES
+            .iter()
+            .map(|(name, globs)| (*name, globs.to_vec()))
+            .collect();
+        for (name, globs) in &self.custom {
+            definitions
+                .entry(name.as_str())
+                .or_default()
+                .extend(globs.iter().map(String::as_str));
+        }
+        definitions
+    }
+
+    fn compiled(&self, name: &str) -> SearchResult<Vec<Pattern>> {
+        let definitions = self.definitions();
+        let globs = definitions.get(name).ok_or_else(|| {
+            SearchError::config_error(format!(
+                "Unknown file type '{}'; see --type-list for the known types",
+                name
+            ))
+        })?;
+        // Globs were checked when they were added
+        Ok(globs
+            .iter()
+            .filter_map(|glob| Pattern::new(glob).ok())
+            .collect())
+    }
+
+    fn is_selected(&self, path: &Path) -> bool {
+        matches_file_name(path, &self.selected)
+    }
+
+    fn is_negated(&self, path: &Path) -> bool {
+        matches_file_name(path, &self.negated)
+    }
+}
+
+fn matches_file_name(path: &Path, globs: &[Pattern]) -> bool {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+    let match_opts = MatchOptions {
+        case_sensitive: false,
+        ..Default::default()
+    };
+    globs
+        .iter()
+        .any(|glob| glob.matches_with(file_name, match_opts))
+}
+
+/// Checks `path` against both the `-x` extensions and the file types.
+///
+/// Extensions and selected types add up: with both given, a file passes if
+/// it has one of the extensions *or* is of a selected type. A negated type
+/// always skips the file.
+pub fn has_valid_type(
+    path: &Path,
+    extensions: &Option<Vec<String>>,
+    file_types: &FileTypes,
+) -> bool {
+    if extensions.is_some() && file_types.has_selection() {
+        !file_types.is_negated(path)
+            && (has_valid_extension(path, extensions) || file_types.is_selected(path))
+    } else {
+        has_valid_extension(path, extensions) && file_types.matches(path)
+    }
+}
+
+/// (De)serializes globs as their source strings
+mod glob_strings {
+    use glob::Pattern;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(globs: &[Pattern], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(globs.iter().map(Pattern::as_str))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Pattern>, D::Error> {
+        Vec::<String>::deserialize(deserializer)?
+            .iter()
+            .map(|glob| Pattern::new(glob).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -263,6 +883,18 @@ mod tests {
             Path::new(""),
             &ignore_patterns
         ));
+
+        // .git internals are ignored even without a matching pattern
+        assert!(should_ignore(
+            Path::new("/repo/.git/objects/ab/cdef"),
+            Path::new("/repo"),
+            &[]
+        ));
+        assert!(!matches_ignore_patterns(
+            Path::new("/repo/.git/objects/ab/cdef"),
+            Path::new("/repo"),
+            &[]
+        ));
     }
 
     #[test]
@@ -397,4 +1029,271 @@ mod tests {
         assert!(should_ignore(file_1, root, &patterns));
         assert!(should_ignore(file_2, root, &patterns));
     }
+
+    #[test]
+    fn test_file_type_presets() -> SearchResult<()> {
+        let mut types = FileTypes::new();
+        assert!(types.matches(Path::new("anything.bin")));
+
+        types.select("rust")?;
+        assert!(types.matches(Path::new("src/main.rs")));
+        assert!(types.matches(Path::new("build/bindings.rs.in")));
+        assert!(!types.matches(Path::new("main.py")));
+
+        types.select("cmake")?;
+        assert!(types.matches(Path::new("project/CMakeLists.txt")));
+        assert!(types.matches(Path::new("toolchain.cmake")));
+        assert!(!types.matches(Path::new("notes.txt")));
+
+        let mut types = FileTypes::new();
+        types.select("ts")?;
+        assert!(types.matches(Path::new("App.TSX")));
+        types.negate("js")?;
+        types.negate("markdown")?;
+        assert!(!types.matches(Path::new("index.js")));
+
+        let mut types = FileTypes::new();
+        types.negate("markdown")?;
+        assert!(types.matches(Path::new("lib.rs")));
+        assert!(!types.matches(Path::new("README.md")));
+
+        let error = types.select("cobol").unwrap_err().to_string();
+        assert!(error.contains("Unknown file type 'cobol'"), "{}", error);
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_type_additions() -> SearchResult<()> {
+        let mut types = FileTypes::new();
+        types.add("foo:*.foo")?;
+        types.add("foo:*.foo.in")?;
+        // Adding to a built-in type extends it
+        types.add("rust:*.ron")?;
+        assert_eq!(types.definitions()["foo"], ["*.foo", "*.foo.in"]);
+
+        types.select("foo")?;
+        types.select("rust")?;
+        for path in ["a.foo", "b.foo.in", "c.rs", "d.ron"] {
+            assert!(types.matches(Path::new(path)), "{}", path);
+        }
+        assert!(!types.matches(Path::new("e.bar")));
+
+        assert!(types.add("no-glob").is_err());
+        assert!(types.add(":*.x").is_err());
+        assert!(types.add("bad:[unclosed").is_err());
+
+        // Selections survive a round trip through the config format
+        let json = serde_json::to_string(&types).unwrap();
+        assert_eq!(serde_json::from_str::<FileTypes>(&json).unwrap(), types);
+        Ok(())
+    }
+
+    #[test]
+    fn test_types_add_to_extensions() -> SearchResult<()> {
+        let extensions = Some(vec!["toml".to_string()]);
+        let mut types = FileTypes::new();
+        types.select("rust")?;
+
+        // -x toml --type rust searches both
+        assert!(has_valid_type(Path::new("Cargo.toml"), &extensions, &types));
+        assert!(has_valid_type(Path::new("lib.rs"), &extensions, &types));
+        assert!(!has_valid_type(Path::new("app.py"), &extensions, &types));
+
+        // A negated type wins over a listed extension
+        let mut types = FileTypes::new();
+        types.negate("toml")?;
+        assert!(!has_valid_type(
+            Path::new("Cargo.toml"),
+            &extensions,
+            &types
+        ));
+        assert!(has_valid_type(
+            Path::new("Cargo.toml"),
+            &extensions,
+            &FileTypes::new()
+        ));
+        assert!(!has_valid_type(
+            Path::new("lib.rs"),
+            &extensions,
+            &FileTypes::new()
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_rustscout_ignore_anchors_to_its_dir() {
+        let dir = Path::new("/repo/src");
+        let ignore = parse_rustscout_ignore(dir, "# generated\n/gen\n*.pb.rs\n!keep.pb.rs\nout/\n");
+        let is_ignored = |path: &str, is_dir| {
+            ignore
+                .matched_path_or_any_parents(dir.join(path), is_dir)
+                .is_ignore()
+        };
+        // A leading slash anchors to /repo/src, not to the search root
+        assert!(is_ignored("gen", true));
+        assert!(is_ignored("gen/a.rs", false));
+        assert!(!is_ignored("nested/gen/a.rs", false));
+        // Patterns without a slash match at any depth
+        assert!(is_ignored("api.pb.rs", false));
+        assert!(is_ignored("nested/api.pb.rs", false));
+        assert!(!is_ignored("keep.pb.rs", false));
+        // A trailing slash only matches directories
+        assert!(is_ignored("out/file.txt", false));
+        assert!(!is_ignored("out", false));
+    }
+
+    #[test]
+    fn test_nested_rustscout_ignore_files() -> SearchResult<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/build"))?;
+        std::fs::create_dir_all(root.join("build"))?;
+        std::fs::write(root.join(RUSTSCOUT_IGNORE_FILE), "*.gen.rs\n/build\n")?;
+        std::fs::write(
+            root.join("src").join(RUSTSCOUT_IGNORE_FILE),
+            "!keep.gen.rs\nlocal.txt\n",
+        )?;
+
+        let ignore = RustscoutIgnore::new(root);
+        let is_ignored = |path: &str| ignore.is_ignored(&root.join(path), false);
+        assert!(is_ignored("a.gen.rs"));
+        assert!(is_ignored("src/b.gen.rs"));
+        // The subdirectory's negation wins over the root file
+        assert!(!is_ignored("src/keep.gen.rs"));
+        // /build is anchored to the root file's directory
+        assert!(is_ignored("build/out.txt"));
+        assert!(ignore.is_ignored(&root.join("build"), true));
+        assert!(!is_ignored("src/build/out.txt"));
+        // The subdirectory's patterns only apply below it
+        assert!(is_ignored("src/local.txt"));
+        assert!(!is_ignored("local.txt"));
+        assert!(!is_ignored("src/main.rs"));
+
+        // Searching a subdirectory still applies the files above it, up to
+        // the workspace root
+        std::fs::create_dir_all(root.join(".rustscout"))?;
+        let ignore = RustscoutIgnore::new(&root.join("src"));
+        assert!(ignore.is_ignored(&root.join("src/b.gen.rs"), false));
+        assert!(!ignore.is_ignored(&root.join("src/keep.gen.rs"), false));
+        Ok(())
+    }
+
+    #[test]
+    fn test_has_excluded_extension() {
+        let exclude = Some(vec!["min.js".to_string(), ".lock".to_string()]);
+        assert!(has_excluded_extension(
+            Path::new("dist/app.min.js"),
+            &exclude
+        ));
+        assert!(has_excluded_extension(Path::new("APP.MIN.JS"), &exclude));
+        assert!(has_excluded_extension(Path::new("Cargo.lock"), &exclude));
+        assert!(!has_excluded_extension(Path::new("app.js"), &exclude));
+        // "min.js" is a whole extension, not any name ending in it
+        assert!(!has_excluded_extension(Path::new("admin.js"), &exclude));
+        // Dotfiles and files without an extension are kept
+        assert!(!has_excluded_extension(Path::new(".lock"), &exclude));
+        assert!(!has_excluded_extension(Path::new("lock"), &exclude));
+        assert!(!has_excluded_extension(Path::new("Makefile"), &exclude));
+        assert!(!has_excluded_extension(Path::new("Cargo.lock"), &None));
+    }
+
+    #[test]
+    fn test_matches_include_globs() {
+        let root = Path::new("/repo");
+        let globs = vec!["src/**/*.rs".to_string()];
+        assert!(matches_include_globs(
+            Path::new("/repo/src/lib.rs"),
+            root,
+            &globs
+        ));
+        assert!(matches_include_globs(
+            Path::new("/repo/src/a/b/c.rs"),
+            root,
+            &globs
+        ));
+        assert!(!matches_include_globs(
+            Path::new("/repo/tests/t.rs"),
+            root,
+            &globs
+        ));
+        assert!(!matches_include_globs(
+            Path::new("/repo/src/lib.rs.bak"),
+            root,
+            &globs
+        ));
+
+        // `*` doesn't cross directories; globs without a slash match the name
+        let globs = vec!["src/*.rs".to_string(), "*.toml".to_string()];
+        assert!(matches_include_globs(
+            Path::new("/repo/src/lib.rs"),
+            root,
+            &globs
+        ));
+        assert!(!matches_include_globs(
+            Path::new("/repo/src/a/b.rs"),
+            root,
+            &globs
+        ));
+        assert!(matches_include_globs(
+            Path::new("/repo/crates/x/Cargo.toml"),
+            root,
+            &globs
+        ));
+
+        // A name with several dots and one without any
+        let globs = vec!["*.d.ts".to_string(), "Makefile".to_string()];
+        assert!(matches_include_globs(
+            Path::new("/repo/types/index.d.ts"),
+            root,
+            &globs
+        ));
+        assert!(!matches_include_globs(
+            Path::new("/repo/index.ts"),
+            root,
+            &globs
+        ));
+        assert!(matches_include_globs(
+            Path::new("/repo/Makefile"),
+            root,
+            &globs
+        ));
+
+        assert!(matches_include_globs(Path::new("/repo/any"), root, &[]));
+
+        // An invalid glob is reported rather than skipped
+        assert!(IncludeGlobs::new(&["src/[".to_string()]).is_err());
+        assert!(!matches_include_globs(
+            Path::new("/repo/src/lib.rs"),
+            root,
+            &["src/[".to_string(), "*.rs".to_string()]
+        ));
+    }
+
+    #[test]
+    fn test_sniff_shebang() {
+        let extension = |head: &[u8]| sniff_bytes(head).extension;
+        assert_eq!(
+            extension(b"#!/usr/bin/env python3\nimport os\n"),
+            Some("py")
+        );
+        assert_eq!(extension(b"#!/usr/bin/python3.11 -u\n"), Some("py"));
+        assert_eq!(extension(b"#!/bin/bash -e\nset -x\n"), Some("sh"));
+        assert_eq!(
+            extension(b"#!/usr/bin/env -S node --no-warnings\n"),
+            Some("js")
+        );
+        assert_eq!(extension(b"#!/usr/bin/env LANG=C ruby\n"), Some("rb"));
+        assert_eq!(extension(b"#! /usr/bin/perl -w\n"), Some("pl"));
+        assert_eq!(extension(b"#!/usr/bin/awk -f\n"), None);
+        assert_eq!(extension(b"# just a comment\n"), None);
+        assert_eq!(extension(b""), None);
+
+        let sniffed = sniff_bytes(b"#!/bin/sh\n\0\x01\x02");
+        assert!(sniffed.binary);
+        assert_eq!(sniffed.extension, Some("sh"));
+        // Only the first SNIFF_LENGTH bytes count
+        let mut late_nul = vec![b'a'; SNIFF_LENGTH];
+        late_nul.push(0);
+        assert!(!sniff_bytes(&late_nul).binary);
+    }
 }
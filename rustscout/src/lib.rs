@@ -5,6 +5,7 @@
 
 pub mod cache;
 pub mod config;
+pub mod encoding;
 pub mod errors;
 pub mod filters;
 pub mod metrics;
@@ -14,15 +15,17 @@ pub mod search;
 pub mod workspace;
 
 pub use cache::{
-    ChangeDetectionStrategy, ChangeDetector, ChangeStatus, FileChangeInfo, FileSignatureDetector,
-    GitStatusDetector, IncrementalCache,
+    CacheFormat, CacheSaveOptions, ChangeDetectionStrategy, ChangeDetector, ChangeStatus,
+    FileChangeInfo, FileSignatureDetector, GitStatusDetector, IncrementalCache,
 };
-pub use config::SearchConfig;
+pub use config::{Schedule, SearchConfig, SearchConfigBuilder};
 pub use errors::{SearchError, SearchResult};
 pub use glob::Pattern;
 pub use metrics::MemoryMetrics;
 pub use replace::{FileReplacementPlan, ReplacementConfig, ReplacementSet, ReplacementTask};
-pub use results::{FileResult, Match, SearchResult as SearchResultType};
-pub use search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode};
-pub use search::{interactive_search, search};
+pub use results::{FileResult, Match, SearchResult as SearchResultType, SearchStats};
+pub use search::matcher::{
+    CaseMode, HyphenMode, PatternDefinition, PatternMatch, WordBoundaryMode,
+};
+pub use search::{interactive_search, search, search_iter, CancellationToken, SearchIter};
 pub use workspace::{detect_workspace_root, WorkspaceMetadata};
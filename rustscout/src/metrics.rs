@@ -17,10 +17,20 @@ pub struct MemoryMetrics {
     cache_hits: Arc<AtomicU64>,
     cache_misses: Arc<AtomicU64>,
 
+    // Compiled patterns evicted from the bounded pattern cache
+    pattern_cache_evictions: Arc<AtomicU64>,
+
+    // Regex scans skipped because the RegexSet pre-filter found no match
+    prefilter_skips: Arc<AtomicU64>,
+
+    // Approximate heap size of the file results collected by a search
+    result_bytes: Arc<AtomicU64>,
+
     // File processing metrics
     small_files_processed: Arc<AtomicU64>,
     buffered_files_processed: Arc<AtomicU64>,
     mmap_files_processed: Arc<AtomicU64>,
+    bytes_processed: Arc<AtomicU64>,
 }
 
 impl MemoryMetrics {
@@ -33,9 +43,13 @@ impl MemoryMetrics {
             cache_size: Arc::new(AtomicU64::new(0)),
             cache_hits: Arc::new(AtomicU64::new(0)),
             cache_misses: Arc::new(AtomicU64::new(0)),
+            pattern_cache_evictions: Arc::new(AtomicU64::new(0)),
+            prefilter_skips: Arc::new(AtomicU64::new(0)),
+            result_bytes: Arc::new(AtomicU64::new(0)),
             small_files_processed: Arc::new(AtomicU64::new(0)),
             buffered_files_processed: Arc::new(AtomicU64::new(0)),
             mmap_files_processed: Arc::new(AtomicU64::new(0)),
+            bytes_processed: Arc::new(AtomicU64::new(0)),
         }
     }
 
@@ -101,8 +115,25 @@ impl MemoryMetrics {
         }
     }
 
+    /// Records patterns evicted from the pattern cache
+    pub fn record_pattern_cache_evictions(&self, evicted: u64) {
+        self.pattern_cache_evictions
+            .fetch_add(evicted, Ordering::Relaxed);
+    }
+
+    /// Records regex scans skipped by the pre-filter
+    pub fn record_prefilter_skips(&self, skipped: u64) {
+        self.prefilter_skips.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    /// Records file results kept in memory, returning the running total
+    pub fn record_results(&self, bytes: u64) -> u64 {
+        self.result_bytes.fetch_add(bytes, Ordering::Relaxed) + bytes
+    }
+
     /// Records file processing type
     pub fn record_file_processing(&self, size: u64) {
+        self.bytes_processed.fetch_add(size, Ordering::Relaxed);
         if size < SMALL_FILE_THRESHOLD {
             self.small_files_processed.fetch_add(1, Ordering::Relaxed);
         } else if size >= LARGE_FILE_THRESHOLD {
@@ -122,9 +153,13 @@ impl MemoryMetrics {
             cache_size: self.cache_size.load(Ordering::Relaxed),
             cache_hits: self.cache_hits.load(Ordering::Relaxed),
             cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            pattern_cache_evictions: self.pattern_cache_evictions.load(Ordering::Relaxed),
+            prefilter_skips: self.prefilter_skips.load(Ordering::Relaxed),
+            result_bytes: self.result_bytes.load(Ordering::Relaxed),
             small_files: self.small_files_processed.load(Ordering::Relaxed),
             buffered_files: self.buffered_files_processed.load(Ordering::Relaxed),
             mmap_files: self.mmap_files_processed.load(Ordering::Relaxed),
+            bytes_processed: self.bytes_processed.load(Ordering::Relaxed),
         }
     }
 
@@ -138,6 +173,9 @@ impl MemoryMetrics {
              Memory mapped: {} bytes\n\
              Cache size: {} bytes\n\
              Cache hits/misses: {}/{}\n\
+             Pattern cache evictions: {}\n\
+             Regex scans skipped by pre-filter: {}\n\
+             Results kept: {} bytes\n\
              Files processed (small/buffered/mmap): {}/{}/{}",
             stats.total_allocated,
             stats.peak_allocated,
@@ -145,6 +183,9 @@ impl MemoryMetrics {
             stats.cache_size,
             stats.cache_hits,
             stats.cache_misses,
+            stats.pattern_cache_evictions,
+            stats.prefilter_skips,
+            stats.result_bytes,
             stats.small_files,
             stats.buffered_files,
             stats.mmap_files
@@ -175,9 +216,13 @@ pub struct MemoryStats {
     pub cache_size: u64,
     pub cache_hits: u64,
     pub cache_misses: u64,
+    pub pattern_cache_evictions: u64,
+    pub prefilter_skips: u64,
+    pub result_bytes: u64,
     pub small_files: u64,
     pub buffered_files: u64,
     pub mmap_files: u64,
+    pub bytes_processed: u64,
 }
 
 #[cfg(test)]
@@ -235,6 +280,24 @@ mod tests {
         assert_eq!(stats.cache_misses, 1);
     }
 
+    #[test]
+    fn test_pattern_cache_eviction_metrics() {
+        let metrics = MemoryMetrics::new();
+
+        metrics.record_pattern_cache_evictions(0);
+        metrics.record_pattern_cache_evictions(4);
+        assert_eq!(metrics.get_stats().pattern_cache_evictions, 4);
+    }
+
+    #[test]
+    fn test_prefilter_metrics() {
+        let metrics = MemoryMetrics::new();
+
+        metrics.record_prefilter_skips(3);
+        metrics.record_prefilter_skips(2);
+        assert_eq!(metrics.get_stats().prefilter_skips, 5);
+    }
+
     #[test]
     fn test_file_processing_tracking() {
         let metrics = MemoryMetrics::new();
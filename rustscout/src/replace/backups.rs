@@ -0,0 +1,173 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::debug;
+
+use crate::errors::{SearchError, SearchResult};
+use crate::workspace::{detect_workspace_root, WorkspaceMetadata};
+
+use super::{file_hash, replace_file, ReplacementConfig};
+
+/// File name ending of compressed backups
+const COMPRESSED_SUFFIX: &str = ".bak.gz";
+
+/// Reference counts of the compressed backups in a backup directory
+const INDEX_FILE: &str = "index.json";
+
+/// Serializes updates to the index files of this process, since backups are
+/// made and released in parallel
+static INDEX_LOCK: Mutex<()> = Mutex::new(());
+
+/// How many undo records refer to each compressed backup, by file name
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct BackupIndex {
+    #[serde(default)]
+    refs: BTreeMap<String, usize>,
+}
+
+impl BackupIndex {
+    fn load(backup_dir: &Path) -> SearchResult<Self> {
+        match fs::read_to_string(backup_dir.join(INDEX_FILE)) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                SearchError::config_error(format!("Failed to parse backup index: {}", e))
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    fn save(&self, backup_dir: &Path) -> SearchResult<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        replace_file(&backup_dir.join(INDEX_FILE), |out| {
+            Ok(out.write_all(content.as_bytes())?)
+        })
+    }
+}
+
+/// Whether `path` is a compressed backup, shared by every record of the same
+/// content
+pub(crate) fn is_compressed(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.ends_with(COMPRESSED_SUFFIX))
+}
+
+/// Whether the workspace config asks for compressed backups
+pub(super) fn workspace_compresses_backups(config: &ReplacementConfig) -> bool {
+    detect_workspace_root(&config.undo_dir)
+        .and_then(|root| WorkspaceMetadata::load(&root))
+        .ok()
+        .and_then(|metadata| metadata.global_config)
+        .is_some_and(|global| global.compress_backups)
+}
+
+/// Backs `source` up into `backup_dir` as `<sha256>.bak.gz`, or, if a backup
+/// of the same content is already there, counts one more reference to it
+pub(super) fn store_compressed(source: &Path, backup_dir: &Path) -> SearchResult<PathBuf> {
+    let name = format!("{}{}", file_hash(source)?, COMPRESSED_SUFFIX);
+    let backup_path = backup_dir.join(&name);
+
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut index = BackupIndex::load(backup_dir)?;
+    let shared = backup_path.exists();
+    if !shared {
+        replace_file(&backup_path, |out| {
+            let mut encoder = GzEncoder::new(out, Compression::default());
+            io::copy(&mut File::open(source)?, &mut encoder)?;
+            encoder.finish()?;
+            Ok(())
+        })?;
+    }
+    *index.refs.entry(name).or_insert(0) += 1;
+    index.save(backup_dir)?;
+    debug!(
+        path = %source.display(),
+        backup = %backup_path.display(),
+        shared,
+        "Stored compressed backup"
+    );
+    Ok(backup_path)
+}
+
+/// Reads the content a backup holds, decompressing it if it is compressed
+pub fn read_backup(path: &Path) -> SearchResult<Vec<u8>> {
+    let mut content = Vec::new();
+    if is_compressed(path) {
+        GzDecoder::new(File::open(path)?).read_to_end(&mut content)?;
+    } else {
+        File::open(path)?.read_to_end(&mut content)?;
+    }
+    Ok(content)
+}
+
+/// Drops one reference to a backup, deleting it once nothing refers to it.
+/// Returns whether it was deleted.
+pub(super) fn release_backup(path: &Path) -> SearchResult<bool> {
+    if !is_compressed(path) {
+        fs::remove_file(path)?;
+        return Ok(true);
+    }
+    let backup_dir = path.parent().unwrap_or(Path::new("."));
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let _guard = INDEX_LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut index = BackupIndex::load(backup_dir)?;
+    let unused = match index.refs.get_mut(&name) {
+        Some(count) if *count > 1 => {
+            *count -= 1;
+            false
+        }
+        _ => {
+            index.refs.remove(&name);
+            true
+        }
+    };
+    if unused {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+            _ => {}
+        }
+    }
+    index.save(backup_dir)?;
+    Ok(unused)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_compressed_backups_are_shared() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let backup_dir = temp.path().join("backups");
+        fs::create_dir_all(&backup_dir)?;
+        let (a, b) = (temp.path().join("a.txt"), temp.path().join("b.txt"));
+        fs::write(&a, "same content\n".repeat(100))?;
+        fs::write(&b, "same content\n".repeat(100))?;
+
+        let first = store_compressed(&a, &backup_dir)?;
+        let second = store_compressed(&b, &backup_dir)?;
+        assert_eq!(first, second);
+        assert!(is_compressed(&first));
+        assert!(fs::metadata(&first)?.len() < 1300);
+        assert_eq!(read_backup(&first)?, fs::read(&a)?);
+
+        assert!(!release_backup(&first)?);
+        assert!(first.exists());
+        assert!(release_backup(&first)?);
+        assert!(!first.exists());
+        assert!(BackupIndex::load(&backup_dir)?.refs.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,375 @@
+use std::cell::{Cell, RefCell};
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::errors::{SearchError, SearchResult};
+
+use super::{FileReplacementPlan, ReplacementTask};
+
+/// An answer to the approval prompt for one replacement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Approval {
+    /// Make this replacement
+    Yes,
+    /// Leave this match alone
+    No,
+    /// Make this and every remaining replacement without asking again
+    All,
+    /// Leave this match and the rest of its file alone
+    SkipFile,
+    /// Leave this and every remaining match alone
+    Quit,
+}
+
+impl Approval {
+    /// Parses an answer typed at the prompt, ignoring case and surrounding
+    /// whitespace
+    pub fn parse(answer: &str) -> Option<Self> {
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "y" | "yes" => Some(Self::Yes),
+            "n" | "no" => Some(Self::No),
+            "a" | "all" => Some(Self::All),
+            "f" | "file" => Some(Self::SkipFile),
+            "q" | "quit" => Some(Self::Quit),
+            _ => None,
+        }
+    }
+}
+
+/// What an [`ApprovalPolicy`] decides about one replacement
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Decision {
+    /// Make this replacement
+    Approve,
+    /// Leave this match alone
+    Skip,
+    /// Make this and every remaining replacement in the same file without
+    /// asking again
+    ApproveAllInFile,
+    /// Leave this and every remaining match alone, in every file. The
+    /// replacements approved so far are still made.
+    AbortAll,
+}
+
+/// Where a replacement is and what it changes, so that a policy can show it
+/// without reading the file again
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MatchContext {
+    /// The file the replacement is in
+    pub file_path: PathBuf,
+    /// The 1-based line the match starts on
+    pub line_number: usize,
+    /// The text the match covers
+    pub matched: String,
+    /// The lines the match touches, as they are now
+    pub line: String,
+    /// The same lines once the replacement is made
+    pub replaced_line: String,
+}
+
+impl MatchContext {
+    fn new(content: &str, task: &ReplacementTask) -> SearchResult<Self> {
+        let (start, end) = task.original_range;
+        if content.get(start..end).is_none() {
+            return Err(SearchError::invalid_range(
+                &task.file_path,
+                task.original_range,
+                "not a character range of the file",
+            ));
+        }
+
+        let line_start = content[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = content[end..].find('\n').map_or(content.len(), |i| end + i);
+        let replacement = task.replacement_for(&content.as_bytes()[start..end])?;
+        let replaced_line = format!(
+            "{}{}{}",
+            &content[line_start..start],
+            replacement,
+            &content[end..line_end]
+        );
+        Ok(Self {
+            file_path: task.file_path.clone(),
+            line_number: content[..start].matches('\n').count() + 1,
+            matched: content[start..end].to_string(),
+            line: content[line_start..line_end]
+                .trim_end_matches('\r')
+                .to_string(),
+            replaced_line: replaced_line.trim_end_matches('\r').to_string(),
+        })
+    }
+}
+
+/// Decides which replacements are made, one match at a time, for
+/// [`ReplacementSet::execute_with_policy`](super::ReplacementSet::execute_with_policy).
+///
+/// Matches are offered in order, file by file. Closures taking the task and
+/// its context are policies too.
+pub trait ApprovalPolicy {
+    /// Decides about `task`, which `context` describes
+    fn approve(&self, task: &ReplacementTask, context: &MatchContext) -> Decision;
+}
+
+impl<F> ApprovalPolicy for F
+where
+    F: Fn(&ReplacementTask, &MatchContext) -> Decision,
+{
+    fn approve(&self, task: &ReplacementTask, context: &MatchContext) -> Decision {
+        self(task, context)
+    }
+}
+
+/// The default policy, which approves every replacement
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ApproveAll;
+
+impl ApprovalPolicy for ApproveAll {
+    fn approve(&self, _task: &ReplacementTask, _context: &MatchContext) -> Decision {
+        Decision::Approve
+    }
+}
+
+/// Asks `policy` about each replacement in `plans` and keeps only the
+/// approved ones. Plans left without replacements are removed, so applying
+/// the rest backs up and records only what was approved. Returns the number
+/// of approved replacements.
+pub(super) fn retain_approved(
+    plans: &mut Vec<FileReplacementPlan>,
+    policy: &dyn ApprovalPolicy,
+) -> SearchResult<usize> {
+    let mut abort = false;
+
+    for plan in plans.iter_mut() {
+        if abort {
+            plan.replacements.clear();
+            continue;
+        }
+
+        let (content, _) = plan.read_text(plan.encoding_mode())?;
+        let mut kept = Vec::with_capacity(plan.replacements.len());
+        let mut approve_file = false;
+        for task in std::mem::take(&mut plan.replacements) {
+            if approve_file {
+                kept.push(task);
+                continue;
+            }
+            if abort {
+                continue;
+            }
+
+            let context = MatchContext::new(&content, &task)?;
+            match policy.approve(&task, &context) {
+                Decision::Approve => kept.push(task),
+                Decision::Skip => {}
+                Decision::ApproveAllInFile => {
+                    approve_file = true;
+                    kept.push(task);
+                }
+                Decision::AbortAll => abort = true,
+            }
+        }
+        plan.replacements = kept;
+    }
+
+    plans.retain(|plan| !plan.replacements.is_empty());
+    Ok(plans.iter().map(|plan| plan.replacements.len()).sum())
+}
+
+/// The terminal prompt of `replace do --interactive` as a policy. Answers
+/// that reach past one match, like approving everything or skipping the rest
+/// of a file, are remembered here.
+struct PromptPolicy<R, W> {
+    input: RefCell<R>,
+    output: RefCell<W>,
+    approve_rest: Cell<bool>,
+    skipped_file: RefCell<Option<PathBuf>>,
+    /// The first failure to read or write, which ends the prompting
+    error: RefCell<Option<io::Error>>,
+}
+
+impl<R: BufRead, W: Write> PromptPolicy<R, W> {
+    fn ask(&self, context: &MatchContext) -> io::Result<Approval> {
+        let mut input = self.input.borrow_mut();
+        let mut output = self.output.borrow_mut();
+        loop {
+            writeln!(
+                output,
+                "{}:{}",
+                context.file_path.display(),
+                context.line_number
+            )?;
+            writeln!(output, "- {}", context.line)?;
+            writeln!(output, "+ {}", context.replaced_line)?;
+            write!(output, "Replace? [y]es/[n]o/[a]ll/[f]ile skip/[q]uit: ")?;
+            output.flush()?;
+
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(Approval::Quit);
+            }
+            if let Some(answer) = Approval::parse(&line) {
+                return Ok(answer);
+            }
+        }
+    }
+}
+
+impl<R: BufRead, W: Write> ApprovalPolicy for PromptPolicy<R, W> {
+    fn approve(&self, task: &ReplacementTask, context: &MatchContext) -> Decision {
+        if self.approve_rest.get() {
+            return Decision::Approve;
+        }
+        if self.skipped_file.borrow().as_ref() == Some(&task.file_path) {
+            return Decision::Skip;
+        }
+
+        match self.ask(context) {
+            Ok(Approval::Yes) => Decision::Approve,
+            Ok(Approval::No) => Decision::Skip,
+            Ok(Approval::All) => {
+                self.approve_rest.set(true);
+                Decision::Approve
+            }
+            Ok(Approval::SkipFile) => {
+                *self.skipped_file.borrow_mut() = Some(task.file_path.clone());
+                Decision::Skip
+            }
+            Ok(Approval::Quit) => Decision::AbortAll,
+            Err(e) => {
+                self.error.borrow_mut().get_or_insert(e);
+                Decision::AbortAll
+            }
+        }
+    }
+}
+
+/// Asks about each replacement in `plans`, one match at a time, and keeps
+/// only the approved ones.
+///
+/// Every prompt names the file and line and shows the line before and after
+/// the replacement. Answers are read a line at a time from `input`; anything
+/// unrecognized asks again, and running out of input counts as quitting.
+/// Plans left without replacements are removed, so applying the rest backs
+/// up and records only what was approved. Returns the number of approved
+/// replacements.
+pub fn approve_replacements<R: BufRead, W: Write>(
+    plans: &mut Vec<FileReplacementPlan>,
+    input: R,
+    output: W,
+) -> SearchResult<usize> {
+    let policy = PromptPolicy {
+        input: RefCell::new(input),
+        output: RefCell::new(output),
+        approve_rest: Cell::new(false),
+        skipped_file: RefCell::new(None),
+        error: RefCell::new(None),
+    };
+    let approved = retain_approved(plans, &policy)?;
+    match policy.error.into_inner() {
+        Some(e) => Err(e.into()),
+        None => Ok(approved),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replace::{ReplacementConfig, ReplacementPattern, ReplacementSet};
+    use crate::search::matcher::{PatternDefinition, WordBoundaryMode};
+    use crate::workspace::init_workspace;
+    use std::path::Path;
+    use tempfile::TempDir;
+
+    /// A set replacing every `old` in `file` with `new`
+    fn replace_old(file: &Path) -> SearchResult<ReplacementSet> {
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: PatternDefinition::new(
+                    "old".to_string(),
+                    false,
+                    WordBoundaryMode::None,
+                ),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: file.with_file_name(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let content = std::fs::read_to_string(file)?;
+        let mut plan = FileReplacementPlan::new(file.to_path_buf())?;
+        for (start, _) in content.match_indices("old") {
+            plan.add_replacement(ReplacementTask::new(
+                file.to_path_buf(),
+                (start, start + 3),
+                "new".to_string(),
+                0,
+                config.clone(),
+            ))?;
+        }
+        let mut set = ReplacementSet::new(config);
+        set.add_plan(plan);
+        Ok(set)
+    }
+
+    #[test]
+    fn test_policy_skipping_every_other_replacement() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        init_workspace(temp.path(), "json")?;
+        let file = temp.path().join("notes.txt");
+        std::fs::write(&file, "old old\nkeep\nold old\n")?;
+
+        let asked = RefCell::new(Vec::new());
+        let policy = |_: &ReplacementTask, context: &MatchContext| {
+            let mut asked = asked.borrow_mut();
+            asked.push(context.clone());
+            if asked.len() % 2 == 1 {
+                Decision::Approve
+            } else {
+                Decision::Skip
+            }
+        };
+        let report = replace_old(&file)?.execute_with_policy(&policy)?;
+        assert_eq!(report.files[0].replacements, 2);
+        assert_eq!(std::fs::read_to_string(&file)?, "new old\nkeep\nnew old\n");
+
+        let asked = asked.into_inner();
+        assert_eq!(asked.len(), 4);
+        assert_eq!(asked[2].line_number, 3);
+        assert_eq!(asked[2].matched, "old");
+        assert_eq!(asked[3].line, "old old");
+        assert_eq!(asked[3].replaced_line, "old new");
+        Ok(())
+    }
+
+    #[test]
+    fn test_policy_decisions_past_one_match() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        init_workspace(temp.path(), "json")?;
+        let file = temp.path().join("notes.txt");
+        std::fs::write(&file, "old old old\n")?;
+
+        let mut set = replace_old(&file)?;
+        let approve_file = |_: &ReplacementTask, _: &MatchContext| Decision::ApproveAllInFile;
+        assert_eq!(set.approve(&approve_file)?, 3);
+
+        let mut set = replace_old(&file)?;
+        let abort = |_: &ReplacementTask, _: &MatchContext| Decision::AbortAll;
+        assert_eq!(set.approve(&abort)?, 0);
+        assert!(set.plans.is_empty());
+
+        replace_old(&file)?.execute_with_policy(&ApproveAll)?;
+        assert_eq!(std::fs::read_to_string(&file)?, "new new new\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_approval() {
+        assert_eq!(Approval::parse("y\n"), Some(Approval::Yes));
+        assert_eq!(Approval::parse(" No "), Some(Approval::No));
+        assert_eq!(Approval::parse("A"), Some(Approval::All));
+        assert_eq!(Approval::parse("f"), Some(Approval::SkipFile));
+        assert_eq!(Approval::parse("quit"), Some(Approval::Quit));
+        assert_eq!(Approval::parse(""), None);
+        assert_eq!(Approval::parse("maybe"), None);
+    }
+}
@@ -1,39 +1,78 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, PoisonError};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use dashmap::DashMap;
+use filetime::FileTime;
 use indicatif::{ProgressBar, ProgressStyle};
 use memmap2::MmapOptions;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use once_cell::sync::Lazy;
+use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
 use serde::{Deserialize, Serialize};
 use similar::{ChangeTag, TextDiff};
+use tracing::{debug, debug_span, trace, warn};
 
-use crate::errors::{SearchError, SearchResult};
+use crate::config::{EncodingMode, SearchConfig};
+use crate::encoding::{LineEnding, TextEncoding};
+use crate::errors::{PatternError, SearchError, SearchResult};
 use crate::metrics::MemoryMetrics;
-use crate::search::matcher::{PatternDefinition, WordBoundaryMode};
-use crate::workspace::detect_workspace_root;
-
+use crate::results::Match;
+use crate::search::archive::split_member_path;
+use crate::search::engine::thread_pool;
+use crate::search::matcher::{
+    compile_regex, CompiledRegex, PatternDefinition, PatternMatcher, DEFAULT_PATTERN_CACHE_CAPACITY,
+};
+use crate::workspace::{detect_workspace_root, is_workspace_path};
+
+mod backups;
+mod interactive;
+mod prune;
+mod rename;
+mod report;
+mod selection;
+mod temp_file;
 mod undo_info;
-pub use undo_info::{DiffHunk, FileDiff, UndoFileReference, UndoInfo};
+pub use backups::read_backup;
+use interactive::retain_approved;
+pub use interactive::{
+    approve_replacements, Approval, ApprovalPolicy, ApproveAll, Decision, MatchContext,
+};
+pub use prune::{PrunePolicy, PruneReport};
+pub use rename::rename_paths;
+pub use report::{FileReport, ReplacementReport};
+pub use selection::HunkSelection;
+use temp_file::replace_file;
+pub(crate) use undo_info::file_hash;
+pub use undo_info::{
+    DiffHunk, FileDiff, UndoFileReference, UndoFileSummary, UndoInfo, UndoSummary,
+    UNDO_INFO_VERSION,
+};
 
 /// File size thresholds for different processing strategies
 const SMALL_FILE_THRESHOLD: u64 = 32 * 1024; // 32KB
 const LARGE_FILE_THRESHOLD: u64 = 10 * 1024 * 1024; // 10MB
 
 /// A pattern and its replacement text
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ReplacementPattern {
     /// The pattern definition
     pub definition: PatternDefinition,
     /// The text to replace matches with
     pub replacement_text: String,
+    /// Give each replacement the letter case of the text it replaces, so
+    /// `color` -> `colour` also turns `Color` into `Colour` and `COLOR` into
+    /// `COLOUR`. Decided per match, not per file.
+    #[serde(default)]
+    pub preserve_case: bool,
 }
 
 /// Configuration for replacement operations
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ReplacementConfig {
     /// The patterns and their replacements
     pub patterns: Vec<ReplacementPattern>,
@@ -52,6 +91,35 @@ pub struct ReplacementConfig {
 
     /// Directory for storing undo information
     pub undo_dir: PathBuf,
+
+    /// How files are decoded. With `Detect`, replacement ranges refer to the
+    /// decoded text and files are written back in their original encoding.
+    #[serde(default)]
+    pub encoding_mode: EncodingMode,
+
+    /// Number of threads used to apply replacements in parallel
+    #[serde(default = "SearchConfig::default_thread_count")]
+    pub thread_count: NonZeroUsize,
+
+    /// Replace at most this many matches in each file, the earliest first
+    #[serde(default)]
+    pub max_replacements_per_file: Option<usize>,
+
+    /// Replace only the first match on each line
+    #[serde(default)]
+    pub first_match_per_line: bool,
+
+    /// Leave files as they are when applying fails partway, instead of
+    /// restoring the ones already modified
+    #[serde(default)]
+    pub no_rollback: bool,
+
+    /// Store backups gzip-compressed as `<sha256>.bak.gz`, shared by every
+    /// operation that backs up the same content. Read them with
+    /// [`read_backup`]. Undo restores their content but not file times. The
+    /// workspace config's `compress_backups` turns this on too.
+    #[serde(default)]
+    pub compress_backups: bool,
 }
 
 impl Default for ReplacementConfig {
@@ -63,28 +131,128 @@ impl Default for ReplacementConfig {
             backup_dir: None,
             preserve_metadata: true,
             undo_dir: PathBuf::from(".rustscout/undo"),
+            encoding_mode: EncodingMode::default(),
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         }
     }
 }
 
 impl ReplacementConfig {
+    /// Loads a config file, as JSON if it ends in `.json` and as YAML if it
+    /// ends in `.yaml` or `.yml`. Other files are read as JSON when they
+    /// start with `{`, and as YAML otherwise.
     pub fn load_from(path: &Path) -> Result<Self, SearchError> {
         let content = fs::read_to_string(path).map_err(SearchError::IoError)?;
-        serde_yaml::from_str(&content)
-            .map_err(|e| SearchError::config_error(format!("Failed to parse config: {}", e)))
+        let parsed = if is_json_config(path, &content) {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| e.to_string())
+        };
+        parsed.map_err(|e| {
+            SearchError::config_error(format!("Failed to parse {}: {}", path.display(), e))
+        })
+    }
+
+    /// Saves this config so [`load_from`](Self::load_from) reads it back:
+    /// as JSON if `path` ends in `.json`, and as YAML otherwise
+    pub fn save_to(&self, path: &Path) -> SearchResult<()> {
+        let content = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::to_string_pretty(self)?
+        } else {
+            serde_yaml::to_string(self).map_err(|e| {
+                SearchError::config_error(format!("Failed to write {}: {}", path.display(), e))
+            })?
+        };
+        fs::write(path, content)?;
+        Ok(())
     }
 
-    pub fn merge_with_cli(&mut self, cli_config: ReplacementConfig) {
-        // CLI options take precedence over config file
-        if !cli_config.patterns.is_empty() {
-            self.patterns = cli_config.patterns;
+    /// Applies the options given on the command line. Every option the CLI
+    /// specified wins over the config file, whether it turns a setting on
+    /// or off.
+    pub fn merge_with_cli(&mut self, cli: ReplacementOverrides) {
+        if !cli.patterns.is_empty() {
+            self.patterns = cli.patterns;
+        }
+        if let Some(backup_enabled) = cli.backup_enabled {
+            self.backup_enabled = backup_enabled;
+        }
+        if let Some(dry_run) = cli.dry_run {
+            self.dry_run = dry_run;
+        }
+        if cli.backup_dir.is_some() {
+            self.backup_dir = cli.backup_dir;
+        }
+        if let Some(preserve_metadata) = cli.preserve_metadata {
+            self.preserve_metadata = preserve_metadata;
+        }
+        if let Some(encoding_mode) = cli.encoding_mode {
+            self.encoding_mode = encoding_mode;
+        }
+        if let Some(thread_count) = cli.thread_count {
+            self.thread_count = thread_count;
         }
-        self.backup_enabled |= cli_config.backup_enabled;
-        self.dry_run |= cli_config.dry_run;
-        if cli_config.backup_dir.is_some() {
-            self.backup_dir = cli_config.backup_dir;
+        if cli.max_replacements_per_file.is_some() {
+            self.max_replacements_per_file = cli.max_replacements_per_file;
         }
-        self.preserve_metadata |= cli_config.preserve_metadata;
+        if let Some(first_match_per_line) = cli.first_match_per_line {
+            self.first_match_per_line = first_match_per_line;
+        }
+        if let Some(no_rollback) = cli.no_rollback {
+            self.no_rollback = no_rollback;
+        }
+    }
+
+    /// This config without its patterns or the backup and undo directories,
+    /// as recorded with an operation's undo information
+    pub(crate) fn settings(&self) -> Self {
+        Self {
+            patterns: Vec::new(),
+            backup_dir: None,
+            undo_dir: PathBuf::new(),
+            ..self.clone()
+        }
+    }
+}
+
+/// Replacement options given on the command line, applied over a config
+/// file by [`ReplacementConfig::merge_with_cli`].
+///
+/// `None` means the option wasn't given, so the config file's setting stays.
+#[derive(Debug, Clone, Default)]
+pub struct ReplacementOverrides {
+    /// Patterns replacing the config file's, if any are given
+    pub patterns: Vec<ReplacementPattern>,
+    /// Whether to create backups of modified files
+    pub backup_enabled: Option<bool>,
+    /// Whether to only show what would be changed
+    pub dry_run: Option<bool>,
+    /// Directory for storing backups
+    pub backup_dir: Option<PathBuf>,
+    /// Whether to preserve file permissions and timestamps
+    pub preserve_metadata: Option<bool>,
+    /// How files are decoded
+    pub encoding_mode: Option<EncodingMode>,
+    /// Number of threads used to apply replacements
+    pub thread_count: Option<NonZeroUsize>,
+    /// Replace at most this many matches in each file
+    pub max_replacements_per_file: Option<usize>,
+    /// Replace only the first match on each line
+    pub first_match_per_line: Option<bool>,
+    /// Leave files as they are when applying fails partway
+    pub no_rollback: Option<bool>,
+}
+
+/// Whether a replacement config file holds JSON rather than YAML
+fn is_json_config(path: &Path, content: &str) -> bool {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => true,
+        Some("yaml" | "yml") => false,
+        _ => content.trim_start().starts_with('{'),
     }
 }
 
@@ -133,17 +301,35 @@ impl ReplacementTask {
         // Get the pattern definition
         let pattern = &self.config.patterns[self.pattern_index];
 
-        // Validate regex if enabled
+        // Overlapping matches would produce overlapping replacement ranges
+        if pattern.definition.allow_overlapping {
+            return Err(SearchError::config_error(
+                "Overlapping matches cannot be replaced; disable allow_overlapping",
+            ));
+        }
+
+        // Same checks as search, except that an empty pattern can't be replaced
+        match pattern.definition.validate() {
+            Ok(()) => {}
+            Err(e @ PatternError::BoundaryModeIgnored { .. }) => warn!("{}", e),
+            Err(e) => return Err(e.into()),
+        }
+
+        // Validate regex if enabled, with the engine search compiles it with
         if pattern.definition.is_regex {
-            let test_regex = regex::Regex::new(&pattern.definition.text)
-                .map_err(|e| SearchError::invalid_pattern(e.to_string()))?;
-
-            // If word boundary is enabled, ensure the pattern has proper boundary markers
-            if matches!(
-                pattern.definition.boundary_mode,
-                WordBoundaryMode::WholeWords
-            ) {
-                validate_word_boundaries(&test_regex)?;
+            let test_regex =
+                compile_regex(&pattern.definition.text).map_err(SearchError::invalid_pattern)?;
+
+            validate_word_boundaries(&pattern.definition)?;
+
+            // Groups are expanded from the matched text alone, where
+            // look-around has nothing to look at
+            if test_regex.looks_around() && pattern.replacement_text.contains('$') {
+                return Err(SearchError::invalid_pattern(format!(
+                    "Pattern '{}' uses look-around, which is unsupported in replace \
+                     together with capture groups in the replacement",
+                    pattern.definition.text
+                )));
             }
 
             // Validate capture groups
@@ -152,40 +338,205 @@ impl ReplacementTask {
         Ok(())
     }
 
-    pub fn apply(&self, content: &str) -> SearchResult<String> {
-        self.validate()?;
+    /// Whether this task's replacement text refers to capture groups, which
+    /// can only be filled in from the text at its range
+    fn expands_captures(&self) -> bool {
+        self.replacement_text.contains('$')
+            && self
+                .config
+                .patterns
+                .get(self.pattern_index)
+                .is_some_and(|pattern| pattern.definition.is_regex)
+    }
 
-        let pattern = &self.config.patterns[self.pattern_index];
+    /// Whether the replacement depends on the text at this task's range
+    fn needs_matched_text(&self) -> bool {
+        self.expands_captures()
+            || self
+                .config
+                .patterns
+                .get(self.pattern_index)
+                .is_some_and(|pattern| pattern.preserve_case)
+    }
 
-        if pattern.definition.is_regex {
-            let regex = regex::Regex::new(&pattern.definition.text)
-                .map_err(|e| SearchError::invalid_pattern(e.to_string()))?;
+    /// The text that takes the place of `matched`, the bytes at this task's
+    /// range.
+    ///
+    /// For regex patterns, capture groups in the replacement text (`$1`,
+    /// `${name}`) are expanded from the pattern's match within the range and
+    /// `$$` becomes a literal `$`. With `preserve_case`, the result then takes
+    /// on the case of the matched text (see [`transfer_case`]). Anything else
+    /// is inserted as is. Only the matched bytes are needed, so every apply
+    /// strategy produces the same text.
+    fn replacement_for<'a>(&'a self, matched: &[u8]) -> SearchResult<Cow<'a, str>> {
+        if !self.needs_matched_text() {
+            return Ok(Cow::Borrowed(&self.replacement_text));
+        }
+        let matched = std::str::from_utf8(matched).map_err(|_| {
+            SearchError::invalid_range(
+                &self.file_path,
+                self.original_range,
+                "the matched text is not valid UTF-8",
+            )
+        })?;
 
-            Ok(regex
-                .replace_all(content, &pattern.replacement_text)
-                .into_owned())
+        let replacement = if self.expands_captures() {
+            self.expand_captures(matched)?
+        } else {
+            self.replacement_text.clone()
+        };
+        if self.config.patterns[self.pattern_index].preserve_case {
+            Ok(Cow::Owned(transfer_case(matched, &replacement)))
         } else {
-            Ok(content.replace(&pattern.definition.text, &pattern.replacement_text))
+            Ok(Cow::Owned(replacement))
         }
     }
+
+    /// Expands the capture groups in the replacement text from the pattern's
+    /// first match in `matched`
+    fn expand_captures(&self, matched: &str) -> SearchResult<String> {
+        let definition = &self.config.patterns[self.pattern_index].definition;
+        let regex = capture_regex(definition)?;
+        regex
+            .expand_first(matched, &self.replacement_text)
+            .ok_or_else(|| {
+                SearchError::invalid_range(
+                    &self.file_path,
+                    self.original_range,
+                    "the pattern no longer matches there",
+                )
+            })
+    }
+
+    /// Replaces this task's range of `content`, leaving the rest untouched.
+    ///
+    /// Capture groups in a regex replacement refer to the match in this
+    /// range only, never to other occurrences of the pattern.
+    pub fn apply_to_range(&self, content: &str) -> SearchResult<String> {
+        check_range(
+            &self.file_path,
+            self.original_range,
+            content.len(),
+            |offset| Ok(content.as_bytes()[offset]),
+        )?;
+        let (start, end) = self.original_range;
+        let replacement = self.replacement_for(&content.as_bytes()[start..end])?;
+        let mut result = content.to_string();
+        result.replace_range(self.original_range.0..self.original_range.1, &replacement);
+        Ok(result)
+    }
+}
+
+/// Regexes used to expand capture groups, keyed by pattern text, case
+/// insensitivity and multiline mode. Every task of a pattern compiles the
+/// same regex, so it is built once and shared.
+static CAPTURE_REGEXES: Lazy<DashMap<(String, bool, bool), Arc<CompiledRegex>>> =
+    Lazy::new(DashMap::new);
+
+/// The regex that expands the capture groups of `definition`, compiled on
+/// first use by the same engine as in search
+fn capture_regex(definition: &PatternDefinition) -> SearchResult<Arc<CompiledRegex>> {
+    let key = (
+        definition.text.clone(),
+        !definition.is_case_sensitive(),
+        definition.multiline,
+    );
+    if let Some(regex) = CAPTURE_REGEXES.get(&key) {
+        return Ok(Arc::clone(&regex));
+    }
+    let flags = match (key.1, key.2) {
+        (false, false) => "",
+        (true, false) => "(?i)",
+        (false, true) => "(?ms)",
+        (true, true) => "(?ims)",
+    };
+    let regex =
+        compile_regex(&format!("{}{}", flags, key.0)).map_err(SearchError::invalid_pattern)?;
+    // Bounded like the matcher's pattern cache
+    if CAPTURE_REGEXES.len() >= DEFAULT_PATTERN_CACHE_CAPACITY {
+        CAPTURE_REGEXES.clear();
+    }
+    let regex = Arc::new(regex);
+    CAPTURE_REGEXES.insert(key, Arc::clone(&regex));
+    Ok(regex)
 }
 
-fn validate_capture_groups(regex: &regex::Regex, capture_fmt: &str) -> SearchResult<()> {
-    let group_count = regex.captures_len(); // includes group 0
-    let re = regex::Regex::new(r"\$(\d+)").unwrap();
+/// Rewrites `replacement` in the letter case of `matched`.
+///
+/// The case of `matched` is read from its cased letters only:
+/// - all lowercase: the replacement is lowercased (`color` -> `colour`)
+/// - all uppercase, with at least two letters: the replacement is uppercased
+///   (`COLOR` -> `COLOUR`)
+/// - otherwise the first letter decides: uppercase gives the replacement an
+///   uppercase first letter (`Color` -> `Colour`, `ColorMap` -> `ColourMap`),
+///   lowercase a lowercase one (`colorMap` -> `colourMap`), and the rest of
+///   the replacement is kept as written
+///
+/// Text without cased letters leaves the replacement unchanged.
+pub fn transfer_case(matched: &str, replacement: &str) -> String {
+    let mut cased = matched
+        .chars()
+        .filter(|c| c.is_uppercase() || c.is_lowercase());
+    let Some(first) = cased.next() else {
+        return replacement.to_string();
+    };
+    let (mut upper, mut lower) = (0, 0);
+    for c in std::iter::once(first).chain(cased) {
+        if c.is_uppercase() {
+            upper += 1;
+        } else {
+            lower += 1;
+        }
+    }
 
-    for cap in re.captures_iter(capture_fmt) {
-        if let Some(num_str) = cap.get(1) {
-            if let Ok(num) = num_str.as_str().parse::<usize>() {
-                // group_count includes $0 => highest valid group is group_count - 1
-                if num >= group_count {
-                    return Err(SearchError::invalid_pattern(format!(
-                        "Capture group ${} does not exist",
-                        num
-                    )));
+    if upper == 0 {
+        replacement.to_lowercase()
+    } else if lower == 0 && upper > 1 {
+        replacement.to_uppercase()
+    } else {
+        // Recase only the first letter of the replacement
+        let mut result = String::with_capacity(replacement.len());
+        let mut pending = true;
+        for c in replacement.chars() {
+            if pending && (c.is_uppercase() || c.is_lowercase()) {
+                pending = false;
+                if first.is_uppercase() {
+                    result.extend(c.to_uppercase());
+                } else {
+                    result.extend(c.to_lowercase());
                 }
+            } else {
+                result.push(c);
             }
         }
+        result
+    }
+}
+
+fn validate_capture_groups(regex: &CompiledRegex, capture_fmt: &str) -> SearchResult<()> {
+    // `$$` is an escaped dollar; `$N`, `$name` and `${...}` refer to groups
+    let group_names = regex.group_names();
+    let group_count = group_names.len(); // includes group 0
+    let re = regex::Regex::new(r"\$(?:\$|\{([^}]*)\}|([_0-9a-zA-Z]+))").unwrap();
+
+    for cap in re.captures_iter(capture_fmt) {
+        let Some(group) = cap.get(1).or_else(|| cap.get(2)) else {
+            continue;
+        };
+        let exists = match group.as_str().parse::<usize>() {
+            // group_count includes $0 => highest valid group is group_count - 1
+            Ok(num) => num < group_count,
+            Err(_) => group_names
+                .iter()
+                .flatten()
+                .any(|&name| name == group.as_str()),
+        };
+        if !exists {
+            return Err(SearchError::invalid_pattern(format!(
+                "Capture group {} does not exist",
+                &cap[0]
+            )));
+        }
     }
     Ok(())
 }
@@ -201,6 +552,13 @@ pub struct FileReplacementPlan {
 
     /// Original file metadata (if preserving)
     pub original_metadata: Option<std::fs::Metadata>,
+
+    /// Matches left alone because of `max_replacements_per_file` or
+    /// `first_match_per_line`
+    pub skipped: usize,
+
+    /// Offsets of the file's line breaks, read when first needed
+    line_breaks: Option<Vec<usize>>,
 }
 
 /// Strategy for processing files based on their size
@@ -224,46 +582,183 @@ impl ProcessingStrategy {
 }
 
 impl FileReplacementPlan {
-    /// Creates a new plan for the given file
+    /// Creates a new plan for the given file.
+    ///
+    /// Fails for archive members (`archive.zip!/member`), which are never
+    /// rewritten, and for files in a `.rustscout` directory, so a replacement
+    /// can't rewrite its own undo information or backups.
     pub fn new(file_path: PathBuf) -> SearchResult<Self> {
-        let metadata = if let Ok(meta) = fs::metadata(&file_path) {
-            Some(meta)
-        } else {
-            None
-        };
+        if let Some((archive, member)) = split_member_path(&file_path) {
+            return Err(SearchError::config_error(format!(
+                "Can't replace inside archive {} (member {})",
+                archive.display(),
+                member
+            )));
+        }
+        if is_workspace_path(&file_path) {
+            return Err(SearchError::config_error(format!(
+                "Can't replace in {}: it belongs to the workspace's undo and backup data",
+                file_path.display()
+            )));
+        }
+        let metadata = fs::metadata(&file_path).ok();
 
         Ok(Self {
             file_path,
             replacements: Vec::new(),
             original_metadata: metadata,
+            skipped: 0,
+            line_breaks: None,
         })
     }
 
-    /// Adds a replacement task to this plan
+    /// Plans replacing each of `matches`, the search results for `file_path`,
+    /// with the replacement text of the pattern that produced it.
+    ///
+    /// Ranges come from each match's absolute `byte_offset`, so matches on any
+    /// line land where they were found; `start` and `end` only give the length.
+    pub fn from_matches(
+        file_path: PathBuf,
+        matches: &[Match],
+        config: &ReplacementConfig,
+    ) -> SearchResult<Self> {
+        let mut plan = Self::new(file_path)?;
+        for m in matches {
+            let pattern = config.patterns.get(m.pattern_index).ok_or_else(|| {
+                SearchError::config_error(format!(
+                    "No replacement for pattern #{} in {}",
+                    m.pattern_index + 1,
+                    plan.file_path.display()
+                ))
+            })?;
+            plan.add_replacement(ReplacementTask::new(
+                plan.file_path.clone(),
+                (m.byte_offset, m.byte_offset + (m.end - m.start)),
+                pattern.replacement_text.clone(),
+                m.pattern_index,
+                config.clone(),
+            ))?;
+        }
+        Ok(plan)
+    }
+
+    /// Adds a replacement task to this plan.
+    ///
+    /// The range is checked against the file's contents when the plan is
+    /// applied or previewed. With `max_replacements_per_file` or
+    /// `first_match_per_line` set in the task's config, only the earliest
+    /// matches are kept, whatever order they are added in; the rest are
+    /// counted in `skipped`.
     pub fn add_replacement(&mut self, task: ReplacementTask) -> SearchResult<()> {
         // Validate the task first
         task.validate()?;
+        let (start, end) = task.original_range;
+        if start > end {
+            return Err(SearchError::invalid_range(
+                &self.file_path,
+                task.original_range,
+                "start is past the end",
+            ));
+        }
+
+        let insert_pos = |replacements: &[ReplacementTask]| {
+            replacements
+                .binary_search_by_key(&start, |t| t.original_range.0)
+                .unwrap_or_else(|e| e)
+        };
+
+        // Limits drop the later matches rather than failing
+        if task.config.first_match_per_line {
+            let line = self.line_index(start, task.config.encoding_mode)?;
+            let pos = insert_pos(&self.replacements);
+            if pos > 0 {
+                let previous = self.replacements[pos - 1].original_range.0;
+                if self.line_index(previous, task.config.encoding_mode)? == line {
+                    self.skipped += 1;
+                    return Ok(());
+                }
+            }
+            if pos < self.replacements.len() {
+                let next = self.replacements[pos].original_range.0;
+                if self.line_index(next, task.config.encoding_mode)? == line {
+                    // This match comes first on its line, so it takes over
+                    self.replacements.remove(pos);
+                    self.skipped += 1;
+                }
+            }
+        }
+        let max = task.config.max_replacements_per_file;
+        if let Some(max) = max {
+            if self.replacements.len() >= max
+                && self
+                    .replacements
+                    .last()
+                    .is_none_or(|last| last.original_range.0 <= start)
+            {
+                self.skipped += 1;
+                return Ok(());
+            }
+        }
 
         // Check for overlapping replacements
         for existing in &self.replacements {
             if task.original_range.0 < existing.original_range.1
                 && existing.original_range.0 < task.original_range.1
             {
-                return Err(SearchError::config_error(
-                    "Overlapping replacements are not allowed",
-                ));
+                return Err(self.overlap_error(existing, &task));
             }
         }
 
         // Add the task, keeping replacements sorted by range start
-        let insert_pos = self
-            .replacements
-            .binary_search_by_key(&task.original_range.0, |t| t.original_range.0)
-            .unwrap_or_else(|e| e);
-        self.replacements.insert(insert_pos, task);
+        let pos = insert_pos(&self.replacements);
+        self.replacements.insert(pos, task);
+        if let Some(max) = max {
+            while self.replacements.len() > max {
+                self.replacements.pop();
+                self.skipped += 1;
+            }
+        }
         Ok(())
     }
 
+    /// The 0-based line of the text at `offset`
+    fn line_index(&mut self, offset: usize, encoding_mode: EncodingMode) -> SearchResult<usize> {
+        if self.line_breaks.is_none() {
+            let (content, _) = self.read_text(encoding_mode)?;
+            self.line_breaks = Some(content.match_indices('\n').map(|(i, _)| i).collect());
+        }
+        let line_breaks = self.line_breaks.as_deref().unwrap_or_default();
+        Ok(line_breaks.partition_point(|&line_break| line_break < offset))
+    }
+
+    /// Describes two overlapping replacements by their patterns and the line
+    /// where the later one starts
+    fn overlap_error(&self, existing: &ReplacementTask, task: &ReplacementTask) -> SearchError {
+        let pattern_text = |task: &ReplacementTask| {
+            task.config
+                .patterns
+                .get(task.pattern_index)
+                .map_or_else(String::new, |pattern| pattern.definition.text.clone())
+        };
+        let start = existing.original_range.0.max(task.original_range.0);
+        let location = match fs::read(&self.file_path) {
+            Ok(bytes) => {
+                let before = &bytes[..start.min(bytes.len())];
+                let line = before.iter().filter(|&&b| b == b'\n').count() + 1;
+                format!("{}:{}", self.file_path.display(), line)
+            }
+            Err(_) => self.file_path.display().to_string(),
+        };
+        SearchError::config_error(format!(
+            "Overlapping replacements at {}: pattern '{}' (#{}) and pattern '{}' (#{}) match the same text",
+            location,
+            pattern_text(existing),
+            existing.pattern_index + 1,
+            pattern_text(task),
+            task.pattern_index + 1
+        ))
+    }
+
     /// Applies the replacements to the file using the appropriate strategy
     pub fn apply(
         &self,
@@ -275,13 +770,17 @@ impl FileReplacementPlan {
             return Ok(None);
         }
 
-        // Create backup if enabled
-        let backup_path = if config.backup_enabled {
-            self.create_backup(config)?
-        } else {
-            None
-        };
+        let backup_path = self.create_backup(config)?;
+        self.write_replacements(config, metrics)?;
+        Ok(backup_path)
+    }
 
+    /// Writes the replacements to the file, without making a backup
+    fn write_replacements(
+        &self,
+        config: &ReplacementConfig,
+        metrics: &MemoryMetrics,
+    ) -> SearchResult<()> {
         // Choose processing strategy based on file size
         let strategy = if let Some(metadata) = &self.original_metadata {
             ProcessingStrategy::for_file_size(metadata.len())
@@ -289,21 +788,86 @@ impl FileReplacementPlan {
             ProcessingStrategy::InMemory
         };
 
+        // Files that aren't plain UTF-8 are replaced in their decoded form and
+        // encoded back; everything else is patched byte-wise by the chosen strategy
+        let transcoded = if config.encoding_mode == EncodingMode::Detect {
+            let (content, encoding) = self.read_text(config.encoding_mode)?;
+            (!encoding.is_plain_utf8()).then_some((content, encoding))
+        } else {
+            None
+        };
+
         // Apply replacements using chosen strategy
-        match strategy {
-            ProcessingStrategy::InMemory => self.apply_in_memory(config, metrics),
-            ProcessingStrategy::Streaming => self.apply_streaming(config, metrics),
-            ProcessingStrategy::MemoryMapped => self.apply_memory_mapped(config, metrics),
+        match (transcoded, strategy) {
+            (Some((content, encoding)), _) => self.apply_transcoded(content, encoding),
+            (None, ProcessingStrategy::InMemory) => self.apply_in_memory(config, metrics),
+            (None, ProcessingStrategy::Streaming) => self.apply_streaming(config, metrics),
+            (None, ProcessingStrategy::MemoryMapped) => self.apply_memory_mapped(config, metrics),
         }?;
 
         // Restore metadata if needed
         if config.preserve_metadata {
             if let Some(metadata) = &self.original_metadata {
+                #[cfg(unix)]
+                restore_owner(metadata, &self.file_path);
                 fs::set_permissions(&self.file_path, metadata.permissions())?;
+                copy_file_times(metadata, &self.file_path)?;
             }
         }
 
-        Ok(backup_path)
+        Ok(())
+    }
+
+    /// Reads the file as text, decoded according to `encoding_mode`, together
+    /// with the encoding it was stored in
+    fn read_text(&self, encoding_mode: EncodingMode) -> SearchResult<(String, TextEncoding)> {
+        if encoding_mode == EncodingMode::Detect {
+            let bytes = fs::read(&self.file_path)?;
+            let encoding = TextEncoding::detect(&bytes);
+            Ok((encoding.decode(&bytes).into_owned(), encoding))
+        } else {
+            Ok((fs::read_to_string(&self.file_path)?, TextEncoding::UTF8))
+        }
+    }
+
+    /// Checks every replacement range against the text being replaced in
+    fn check_ranges(&self, text: &[u8]) -> SearchResult<()> {
+        for task in &self.replacements {
+            check_range(&self.file_path, task.original_range, text.len(), |offset| {
+                Ok(text[offset])
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Replaces every range of `content`, the way all apply strategies do.
+    ///
+    /// Ranges are replaced from last to first so earlier offsets stay valid,
+    /// and capture groups are expanded against the original content.
+    fn splice(&self, content: &str) -> SearchResult<String> {
+        self.check_ranges(content.as_bytes())?;
+        let mut result = content.to_string();
+        for task in self.replacements.iter().rev() {
+            let (start, end) = task.original_range;
+            let replacement = task.replacement_for(&content.as_bytes()[start..end])?;
+            result.replace_range(start..end, &replacement);
+        }
+        Ok(result)
+    }
+
+    /// Encoding mode the replacement tasks were planned with
+    fn encoding_mode(&self) -> EncodingMode {
+        self.replacements
+            .first()
+            .map_or_else(EncodingMode::default, |task| task.config.encoding_mode)
+    }
+
+    /// Applies the replacements to decoded text and writes the result back in
+    /// the file's original encoding, byte-order mark included
+    fn apply_transcoded(&self, content: String, encoding: TextEncoding) -> SearchResult<()> {
+        let content = self.splice(&content)?;
+        let encoded = encoding.encode(&content)?;
+        replace_file(&self.file_path, |out| Ok(out.write_all(&encoded)?))
     }
 
     /// Process small files entirely in memory
@@ -313,22 +877,8 @@ impl FileReplacementPlan {
         _metrics: &MemoryMetrics,
     ) -> SearchResult<()> {
         let content = fs::read_to_string(&self.file_path)?;
-        let mut result = content.clone();
-
-        // Apply replacements in reverse order to maintain correct offsets
-        for task in self.replacements.iter().rev() {
-            result.replace_range(
-                task.original_range.0..task.original_range.1,
-                &task.replacement_text,
-            );
-        }
-
-        // Write to temporary file and rename atomically
-        let tmp_path = self.file_path.with_extension("tmp");
-        fs::write(&tmp_path, result)?;
-        fs::rename(&tmp_path, &self.file_path)?;
-
-        Ok(())
+        let result = self.splice(&content)?;
+        replace_file(&self.file_path, |out| Ok(out.write_all(result.as_bytes())?))
     }
 
     /// Process medium files using buffered streaming I/O
@@ -337,31 +887,44 @@ impl FileReplacementPlan {
         _config: &ReplacementConfig,
         _metrics: &MemoryMetrics,
     ) -> SearchResult<()> {
-        let mut reader = BufReader::new(File::open(&self.file_path)?);
-        let tmp_path = self.file_path.with_extension("tmp");
-        let mut writer = BufWriter::new(File::create(&tmp_path)?);
-
-        let mut current_pos = 0;
+        let file = File::open(&self.file_path)?;
+        let len = file.metadata()?.len() as usize;
+        // Only the bytes at each end of a range are read, to keep streaming
+        let mut probe = File::open(&self.file_path)?;
         for task in &self.replacements {
-            // Copy unchanged content up to the start of replacement
-            let bytes_to_copy = task.original_range.0 as u64 - current_pos;
-            let mut limited_reader = reader.by_ref().take(bytes_to_copy);
-            std::io::copy(&mut limited_reader, &mut writer)?;
-
-            // Write replacement
-            writer.write_all(task.replacement_text.as_bytes())?;
-            reader.seek(SeekFrom::Start(task.original_range.1 as u64))?;
-            current_pos = task.original_range.1 as u64;
+            check_range(&self.file_path, task.original_range, len, |offset| {
+                let mut byte = [0u8];
+                probe.seek(SeekFrom::Start(offset as u64))?;
+                probe.read_exact(&mut byte)?;
+                Ok(byte[0])
+            })?;
         }
 
-        // Copy remaining content
-        std::io::copy(&mut reader, &mut writer)?;
-        writer.flush()?;
-
-        // Atomically rename
-        fs::rename(&tmp_path, &self.file_path)?;
+        let mut reader = BufReader::new(file);
+        replace_file(&self.file_path, |writer| {
+            let mut current_pos = 0;
+            for task in &self.replacements {
+                // Copy unchanged content up to the start of replacement
+                let bytes_to_copy = task.original_range.0 as u64 - current_pos;
+                let mut limited_reader = reader.by_ref().take(bytes_to_copy);
+                std::io::copy(&mut limited_reader, writer)?;
+
+                // Write replacement, reading the matched bytes only if it needs them
+                if task.needs_matched_text() {
+                    let mut matched = vec![0u8; task.original_range.1 - task.original_range.0];
+                    reader.read_exact(&mut matched)?;
+                    writer.write_all(task.replacement_for(&matched)?.as_bytes())?;
+                } else {
+                    writer.write_all(task.replacement_text.as_bytes())?;
+                    reader.seek(SeekFrom::Start(task.original_range.1 as u64))?;
+                }
+                current_pos = task.original_range.1 as u64;
+            }
 
-        Ok(())
+            // Copy remaining content
+            std::io::copy(&mut reader, writer)?;
+            Ok(())
+        })
     }
 
     /// Process large files using memory mapping
@@ -372,6 +935,7 @@ impl FileReplacementPlan {
     ) -> SearchResult<()> {
         let file = File::open(&self.file_path)?;
         let mmap = unsafe { MmapOptions::new().map(&file)? };
+        self.check_ranges(&mmap)?;
 
         let mut result = Vec::with_capacity(mmap.len());
         let mut current_pos = 0;
@@ -380,50 +944,49 @@ impl FileReplacementPlan {
             // Copy unchanged content
             result.extend_from_slice(&mmap[current_pos..task.original_range.0]);
             // Write replacement
-            result.extend_from_slice(task.replacement_text.as_bytes());
+            let matched = &mmap[task.original_range.0..task.original_range.1];
+            result.extend_from_slice(task.replacement_for(matched)?.as_bytes());
             current_pos = task.original_range.1;
         }
 
         // Copy remaining content
         result.extend_from_slice(&mmap[current_pos..]);
+        // Unmapped before the rename, which Windows refuses while it is open
+        drop(mmap);
+        drop(file);
 
-        // Write to temporary file and rename atomically
-        let tmp_path = self.file_path.with_extension("tmp");
-        fs::write(&tmp_path, result)?;
-        fs::rename(&tmp_path, &self.file_path)?;
-
-        Ok(())
+        replace_file(&self.file_path, |out| Ok(out.write_all(&result)?))
     }
 
     /// Create a backup of the file if backup is enabled
     fn create_backup(&self, config: &ReplacementConfig) -> SearchResult<Option<PathBuf>> {
         if !config.backup_enabled {
-            println!("Debug: Backup not enabled");
             return Ok(None);
         }
 
         // 1) Figure out the workspace root
         let workspace_root = detect_workspace_root(&self.file_path)?;
-        println!("Debug: Workspace root = {}", workspace_root.display());
 
         // 2) Determine the "backups" subdirectory
-        let backup_dir = if let Some(ref dir) = config.backup_dir {
-            println!("Debug: Using user-specified backup dir: {}", dir.display());
-            dir.clone()
-        } else {
-            let default_dir = workspace_root.join(".rustscout").join("backups");
-            println!("Debug: Using default backup dir: {}", default_dir.display());
-            default_dir
+        let backup_dir = match config.backup_dir {
+            Some(ref dir) => dir.clone(),
+            None => workspace_root.join(".rustscout").join("backups"),
         };
-        println!("Debug: Creating backup dir: {}", backup_dir.display());
+        trace!(
+            workspace_root = %workspace_root.display(),
+            backup_dir = %backup_dir.display(),
+            "Preparing backup directory"
+        );
         fs::create_dir_all(&backup_dir)?;
+        if config.compress_backups {
+            return backups::store_compressed(&self.file_path, &backup_dir).map(Some);
+        }
 
         // 3) Compute a unique backup filename from the *relative path*
         let relative = self
             .file_path
             .strip_prefix(&workspace_root)
             .unwrap_or(&self.file_path);
-        println!("Debug: Relative path for backup: {}", relative.display());
 
         // On Windows, remove drive letters like "C:"
         // Convert to a forward-slash form
@@ -441,7 +1004,6 @@ impl FileReplacementPlan {
         // Now flatten further if you want to replace slashes with underscores
         // e.g. "crate_a/lib.rs" -> "crate_a_lib.rs"
         relative_str = relative_str.replace("/", "_");
-        println!("Debug: Sanitized relative path: {}", relative_str);
 
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
@@ -452,23 +1014,21 @@ impl FileReplacementPlan {
         // e.g. "crate_a_lib.rs.1737267859"
         let backup_name = format!("{}.{}", relative_str, timestamp);
         let backup_path = backup_dir.join(&backup_name);
-        println!("Debug: Final backup path: {}", backup_path.display());
 
         // 5) Copy original file to the new backup path
-        println!(
-            "Debug: Copying from {} to {}",
-            self.file_path.display(),
-            backup_path.display()
+        fs::copy(&self.file_path, &backup_path)?;
+        debug!(
+            path = %self.file_path.display(),
+            backup = %backup_path.display(),
+            "Created backup"
         );
-        match fs::copy(&self.file_path, &backup_path) {
-            Ok(_) => println!("Debug: Successfully created backup"),
-            Err(e) => println!("Debug: Failed to create backup: {}", e),
-        }
 
-        if config.preserve_metadata {
-            if let Ok(metadata) = fs::metadata(&self.file_path) {
+        // The backup keeps the original's times, so undo can put them back
+        if let Ok(metadata) = fs::metadata(&self.file_path) {
+            if config.preserve_metadata {
                 let _ = fs::set_permissions(&backup_path, metadata.permissions());
             }
+            let _ = copy_file_times(&metadata, &backup_path);
         }
 
         Ok(Some(backup_path))
@@ -479,16 +1039,8 @@ impl FileReplacementPlan {
         let mut results = Vec::new();
 
         // Get the content
-        let content = fs::read_to_string(&self.file_path).map_err(SearchError::IoError)?;
-        let mut new_content = content.clone();
-
-        // Apply replacements in reverse order to maintain correct offsets
-        for task in self.replacements.iter().rev() {
-            new_content.replace_range(
-                task.original_range.0..task.original_range.1,
-                &task.replacement_text,
-            );
-        }
+        let (content, _) = self.read_text(self.encoding_mode())?;
+        let new_content = self.splice(&content)?;
 
         // Compare line by line
         let original_lines: Vec<&str> = content.lines().collect();
@@ -520,14 +1072,8 @@ impl FileReplacementPlan {
 
     /// Returns the old and new content for this file
     pub fn preview_old_new(&self) -> SearchResult<(String, String)> {
-        let content = fs::read_to_string(&self.file_path)?;
-        let mut new_content = content.clone();
-
-        // Apply all replacements
-        for task in &self.replacements {
-            new_content = task.apply(&new_content)?;
-        }
-
+        let (content, _) = self.read_text(self.encoding_mode())?;
+        let new_content = self.splice(&content)?;
         Ok((content, new_content))
     }
 
@@ -544,29 +1090,7 @@ impl FileReplacementPlan {
         // Read current file content
         let new_content = std::fs::read_to_string(path).map_err(SearchError::IoError)?;
 
-        let mut lines: Vec<String> = new_content.lines().map(|l| l.to_string()).collect();
-
-        // Sort hunks in descending order of new_start_line so we can safely patch from bottom to top
-        let mut hunks = file_diff.hunks.clone();
-        hunks.sort_by_key(|h| std::cmp::Reverse(h.new_start_line));
-
-        for hunk in hunks {
-            let new_start = hunk.new_start_line.saturating_sub(1);
-            // Remove the lines that were "newly added" in that region
-            if hunk.new_line_count > 0 {
-                let end = new_start + hunk.new_line_count.min(lines.len() - new_start);
-                lines.drain(new_start..end);
-            }
-            // Then re-insert the old lines
-            if !hunk.original_lines.is_empty() {
-                for (i, old_line) in hunk.original_lines.iter().enumerate() {
-                    lines.insert(new_start + i, old_line.clone());
-                }
-            }
-        }
-
-        // Join lines back and overwrite file
-        let reverted_content = lines.join("\n");
+        let reverted_content = revert_hunks(&new_content, file_diff);
         std::fs::write(path, reverted_content).map_err(SearchError::IoError)?;
 
         Ok(())
@@ -617,14 +1141,7 @@ impl ReplacementSet {
         let mut operations = Vec::new();
         for entry in entries.filter_map(|e| e.ok()) {
             if entry.path().extension().is_some_and(|ext| ext == "json") {
-                let content = fs::read_to_string(entry.path()).map_err(|e| {
-                    SearchError::config_error(format!("Failed to read undo info: {}", e))
-                })?;
-
-                let info: UndoInfo = serde_json::from_str(&content).map_err(|e| {
-                    SearchError::config_error(format!("Failed to parse undo info: {}", e))
-                })?;
-
+                let info = UndoInfo::load(&entry.path())?;
                 operations.push((info, entry.path()));
             }
         }
@@ -633,43 +1150,15 @@ impl ReplacementSet {
         Ok(operations)
     }
 
-    /// Lists available undo operations with detailed information about each change
-    pub fn list_undo_operations_verbose(config: &ReplacementConfig) -> SearchResult<Vec<UndoInfo>> {
-        let operations = Self::list_undo_operations(config)?;
-
-        for (info, _path) in &operations {
-            println!("ID: {}  =>  {}", info.timestamp, info.description);
-
-            if !info.file_diffs.is_empty() {
-                for (file_idx, fd) in info.file_diffs.iter().enumerate() {
-                    println!(
-                        "  File #{}: {}",
-                        file_idx + 1,
-                        fd.file_path.rel_path.display()
-                    );
-                    for (hunk_idx, h) in fd.hunks.iter().enumerate() {
-                        println!(
-                            "    Hunk {}: lines {}-{} replaced with lines {}-{}",
-                            hunk_idx + 1,
-                            h.original_start_line,
-                            h.original_start_line + h.original_line_count - 1,
-                            h.new_start_line,
-                            h.new_start_line + h.new_line_count - 1
-                        );
-                    }
-                }
-            } else {
-                for (original, backup) in &info.backups {
-                    println!(
-                        "  File: {} -> {}",
-                        original.rel_path.display(),
-                        backup.rel_path.display()
-                    );
-                }
-            }
-        }
-
-        Ok(operations.into_iter().map(|(info, _)| info).collect())
+    /// Lists available undo operations, oldest first, with the files and
+    /// hunks each one changed
+    pub fn list_undo_operations_verbose(
+        config: &ReplacementConfig,
+    ) -> SearchResult<Vec<UndoSummary>> {
+        Ok(Self::list_undo_operations(config)?
+            .iter()
+            .map(|(info, _)| info.summary())
+            .collect())
     }
 
     /// Gets a reference to the metrics
@@ -677,8 +1166,20 @@ impl ReplacementSet {
         &self.metrics
     }
 
-    /// Applies all replacements in parallel with progress reporting
+    /// Applies all replacements in parallel with progress reporting, and
+    /// returns the backups made. If any file fails, the files already
+    /// modified are restored unless `no_rollback` is set.
     pub fn apply_with_progress(&self) -> SearchResult<Vec<PathBuf>> {
+        Ok(self.execute()?.backup_paths())
+    }
+
+    /// Applies all replacements in parallel with progress reporting, and
+    /// reports what changed in each file.
+    ///
+    /// In a dry run nothing is written, but the report is the same: the hunks
+    /// are worked out from each file before it is modified, exactly as they
+    /// are recorded in the undo information.
+    pub fn execute(&self) -> SearchResult<ReplacementReport> {
         let progress = ProgressBar::new(self.plans.len() as u64);
         progress.set_style(
             ProgressStyle::default_bar()
@@ -686,96 +1187,290 @@ impl ReplacementSet {
                 .unwrap()
                 .progress_chars("=>-"),
         );
+        let report = self.run(Some(&progress))?;
+        progress.finish();
+        Ok(report)
+    }
 
-        let backups = Mutex::new(Vec::new());
-        let config = &self.config;
-        let metrics = &self.metrics;
+    /// Applies all replacements in parallel without progress reporting
+    pub fn apply(&self) -> SearchResult<()> {
+        self.run(None).map(|_| ())
+    }
 
-        // Process files in parallel
-        self.plans
-            .par_iter()
-            .try_for_each(|plan| -> SearchResult<()> {
-                if !config.dry_run {
-                    if let Some(backup_path) = plan.apply(config, metrics)? {
-                        let mut backups = backups.lock().unwrap();
-                        backups.push((plan.file_path.clone(), backup_path));
-                    }
-                }
-                progress.inc(1);
-                Ok(())
-            })?;
+    /// Asks `policy` about every replacement, file by file, and drops the
+    /// ones it doesn't approve, along with plans left without any. Returns
+    /// the number of approved replacements.
+    pub fn approve(&mut self, policy: &dyn ApprovalPolicy) -> SearchResult<usize> {
+        retain_approved(&mut self.plans, policy)
+    }
+
+    /// Applies the replacements `policy` approves, without progress
+    /// reporting, and reports what changed. Only the approved replacements
+    /// are backed up and recorded for undo; [`ApproveAll`] makes them all.
+    pub fn execute_with_policy(
+        &mut self,
+        policy: &dyn ApprovalPolicy,
+    ) -> SearchResult<ReplacementReport> {
+        self.approve(policy)?;
+        self.run(None)
+    }
+
+    /// Diffs and applies every plan, then records undo information for the
+    /// files that were backed up.
+    ///
+    /// Unless `no_rollback` is set, every file is first copied to a staging
+    /// directory, and if any plan fails the files already modified are
+    /// restored from there before the error is returned.
+    fn run(&self, progress: Option<&ProgressBar>) -> SearchResult<ReplacementReport> {
+        let config = &self.config;
+        let _span = debug_span!(
+            "replace",
+            files = self.plans.len(),
+            dry_run = config.dry_run
+        )
+        .entered();
+        let staging = if config.dry_run || config.no_rollback {
+            None
+        } else {
+            Some(self.stage_originals()?)
+        };
 
-        let backups = backups.into_inner().unwrap();
-        let mut undo_metadata = Vec::new();
+        let made_backups = Mutex::new(Vec::new());
+        let files = match (self.run_plans(progress, &made_backups), staging) {
+            (Ok(outcomes), _) => outcomes,
+            (Err(e), None) => return Err(e),
+            (Err(e), Some(staging)) => {
+                let made_backups = made_backups
+                    .into_inner()
+                    .unwrap_or_else(PoisonError::into_inner);
+                return Err(self.roll_back(staging, &made_backups, e));
+            }
+        };
+        let (files, diffs): (Vec<_>, Vec<_>) = files.into_iter().unzip();
 
         // Save undo information
-        if !self.config.dry_run && !backups.is_empty() {
-            self.save_undo_info(&backups)?;
-            undo_metadata.extend(backups.into_iter().map(|(_, backup)| backup));
+        let backups: Vec<(PathBuf, PathBuf)> = files
+            .iter()
+            .filter_map(|file| Some((file.path.clone(), file.backup_path.clone()?)))
+            .collect();
+        if !config.dry_run && !backups.is_empty() {
+            self.save_undo_info(&backups, diffs)?;
         }
 
-        progress.finish();
-        Ok(undo_metadata)
+        Ok(ReplacementReport {
+            dry_run: config.dry_run,
+            files,
+        })
     }
 
-    /// Applies all replacements in parallel without progress reporting
-    pub fn apply(&self) -> SearchResult<()> {
-        let metrics = Arc::new(MemoryMetrics::new());
-        let mut backup_paths = Vec::new();
+    /// Copies every file about to be modified into a fresh staging directory,
+    /// named by its plan's position
+    fn stage_originals(&self) -> SearchResult<tempfile::TempDir> {
+        let staging = tempfile::Builder::new()
+            .prefix("rustscout-staging")
+            .tempdir()?;
+        for (index, plan) in self.plans.iter().enumerate() {
+            fs::copy(&plan.file_path, staging.path().join(index.to_string()))?;
+        }
+        Ok(staging)
+    }
 
-        // Apply all plans
-        for plan in &self.plans {
-            if let Some(backup_path) = plan.apply(&self.config, &metrics)? {
-                backup_paths.push((plan.file_path.clone(), backup_path));
+    /// Restores every file that no longer matches its staged copy, and wraps
+    /// `error` with the number of files restored.
+    ///
+    /// The staging directory is removed afterwards, unless a file couldn't be
+    /// restored, in which case it is kept so the original can be recovered.
+    /// The backups made during the run are released, since no undo record
+    /// will refer to them.
+    fn roll_back(
+        &self,
+        staging: tempfile::TempDir,
+        made_backups: &[PathBuf],
+        error: SearchError,
+    ) -> SearchError {
+        let mut restored = 0;
+        let mut failed = false;
+        for (index, plan) in self.plans.iter().enumerate() {
+            let staged = staging.path().join(index.to_string());
+            let original = match fs::read(&staged) {
+                Ok(original) => original,
+                Err(e) => {
+                    warn!(
+                        "Cannot read staged copy of {}: {}",
+                        plan.file_path.display(),
+                        e
+                    );
+                    failed = true;
+                    continue;
+                }
+            };
+            if fs::read(&plan.file_path).ok().as_deref() == Some(original.as_slice()) {
+                continue;
+            }
+            match fs::copy(&staged, &plan.file_path) {
+                Ok(_) => {
+                    if let Some(metadata) = &plan.original_metadata {
+                        let _ = copy_file_times(metadata, &plan.file_path);
+                    }
+                    restored += 1;
+                }
+                Err(e) => {
+                    warn!("Cannot restore {}: {}", plan.file_path.display(), e);
+                    failed = true;
+                }
             }
         }
 
-        // Record undo information if any backups were created
-        if !backup_paths.is_empty() && !self.config.dry_run {
-            self.save_undo_info(&backup_paths)?;
+        for backup in made_backups {
+            if let Err(e) = backups::release_backup(backup) {
+                warn!("Cannot remove backup {}: {}", backup.display(), e);
+            }
         }
 
-        Ok(())
-    }
-
-    /// Generates a preview of the changes in parallel
-    pub fn preview(&self) -> SearchResult<Vec<PreviewResult>> {
-        let mut results = Vec::new();
-
-        for plan in &self.plans {
-            let mut plan_results = plan.preview()?;
-            results.append(&mut plan_results);
+        if failed {
+            let kept = staging.into_path();
+            warn!(
+                "Kept the original files in {} (named by their position in the set)",
+                kept.display()
+            );
         }
-
-        Ok(results)
+        SearchError::rolled_back(restored, error)
     }
 
-    /// Save undo information for this replacement operation
-    fn save_undo_info(&self, backups: &[(PathBuf, PathBuf)]) -> SearchResult<()> {
+    /// Diffs and applies each plan in parallel, stopping at the first
+    /// failure. Every backup made is also added to `made_backups`, so they
+    /// can be released if the run fails.
+    fn run_plans(
+        &self,
+        progress: Option<&ProgressBar>,
+        made_backups: &Mutex<Vec<PathBuf>>,
+    ) -> SearchResult<Vec<(FileReport, FileDiff)>> {
+        let workspace_config;
+        let config = if self.config.backup_enabled
+            && !self.config.compress_backups
+            && backups::workspace_compresses_backups(&self.config)
+        {
+            workspace_config = ReplacementConfig {
+                compress_backups: true,
+                ..self.config.clone()
+            };
+            &workspace_config
+        } else {
+            &self.config
+        };
+        let metrics = &self.metrics;
+
+        thread_pool(config.thread_count)?.install(|| {
+            self.plans
+                .par_iter()
+                .map(|plan| -> SearchResult<(FileReport, FileDiff)> {
+                    // Diff before writing, while the original is still there
+                    let (old_content, new_content) = plan.preview_old_new()?;
+                    let diff = generate_file_diff(&old_content, &new_content, &plan.file_path);
+                    let backup_path = if config.dry_run {
+                        None
+                    } else {
+                        let backup_path = plan.create_backup(config)?;
+                        if let Some(path) = &backup_path {
+                            made_backups
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .push(path.clone());
+                        }
+                        plan.write_replacements(config, metrics)?;
+                        backup_path
+                    };
+                    if let Some(progress) = progress {
+                        progress.inc(1);
+                    }
+                    let report = FileReport {
+                        path: plan.file_path.clone(),
+                        replacements: plan.replacements.len(),
+                        skipped: plan.skipped,
+                        bytes_changed: plan
+                            .replacements
+                            .iter()
+                            .map(|task| task.original_range.1 - task.original_range.0)
+                            .sum(),
+                        backup_path,
+                        hunks: diff.hunks.clone(),
+                    };
+                    Ok((report, diff))
+                })
+                .collect::<SearchResult<Vec<_>>>()
+        })
+    }
+
+    /// Generates a preview of the changes in parallel
+    pub fn preview(&self) -> SearchResult<Vec<PreviewResult>> {
+        let mut results = Vec::new();
+
+        for plan in &self.plans {
+            let mut plan_results = plan.preview()?;
+            results.append(&mut plan_results);
+        }
+
+        Ok(results)
+    }
+
+    /// Save undo information for this replacement operation, with the diffs
+    /// taken before the files were modified
+    fn save_undo_info(
+        &self,
+        backups: &[(PathBuf, PathBuf)],
+        diffs: Vec<FileDiff>,
+    ) -> SearchResult<()> {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .unwrap()
             .as_secs();
 
+        // Backups are recorded relative to the directory they are in, which
+        // is itself recorded relative to the workspace if it is inside it, so
+        // undo can find them even once their absolute paths are stale
+        let workspace_root = detect_workspace_root(&self.config.undo_dir)?;
+        let workspace_root = workspace_root.canonicalize().unwrap_or(workspace_root);
+        let backup_root = match backups.first().and_then(|(_, backup)| backup.parent()) {
+            Some(dir) => Some(dir.canonicalize()?),
+            None => None,
+        };
+
         // Convert paths to UndoFileReferences
         let mut file_refs = Vec::new();
+        let mut backup_hashes = Vec::new();
         for (original, backup) in backups {
             let original_ref = UndoFileReference::new(original)?;
-            let backup_ref = UndoFileReference::new(backup)?;
+            let abs_path = backup.canonicalize()?;
+            let rel_path = match &backup_root {
+                Some(root) => abs_path.strip_prefix(root).unwrap_or(&abs_path),
+                None => &abs_path,
+            }
+            .to_path_buf();
+            let backup_ref = UndoFileReference {
+                rel_path,
+                abs_path: Some(abs_path),
+            };
             file_refs.push((original_ref, backup_ref));
+            backup_hashes.push(file_hash(backup)?);
         }
-
-        // Create file diffs
+        let backup_root_in_workspace = backup_root
+            .as_ref()
+            .is_some_and(|root| root.starts_with(&workspace_root));
+        let backup_root = backup_root.map(|root| match root.strip_prefix(&workspace_root) {
+            Ok(rel) => rel.to_path_buf(),
+            Err(_) => root,
+        });
+
+        // Point each diff at its file relative to the workspace, and note
+        // what the file holds now so that undo can tell if it is edited later
         let mut file_diffs = Vec::new();
-        for plan in &self.plans {
-            if let Ok((old_content, new_content)) = plan.preview_old_new() {
-                let file_ref = UndoFileReference::new(&plan.file_path)?;
-                let diff = generate_file_diff(&old_content, &new_content, &plan.file_path);
-                file_diffs.push(FileDiff {
-                    file_path: file_ref,
-                    hunks: diff.hunks,
-                });
-            }
+        for (plan, diff) in self.plans.iter().zip(diffs) {
+            let file_ref = UndoFileReference::new(&plan.file_path)?;
+            file_diffs.push(FileDiff {
+                file_path: file_ref,
+                content_hash: Some(file_hash(&plan.file_path)?),
+                ..diff
+            });
         }
 
         // Create a descriptive message about the replacements
@@ -793,6 +1488,7 @@ impl ReplacementSet {
             timestamp,
             description,
             backups: file_refs,
+            backup_hashes,
             total_size: backups
                 .iter()
                 .map(|(_, b)| fs::metadata(b).map(|m| m.len()).unwrap_or(0))
@@ -800,90 +1496,106 @@ impl ReplacementSet {
             file_count: backups.len(),
             dry_run: self.config.dry_run,
             file_diffs,
+            renames: Vec::new(),
+            version: UNDO_INFO_VERSION,
+            patterns: self.config.patterns.clone(),
+            config: Some(self.config.settings()),
+            backup_root,
+            backup_root_in_workspace,
         };
+        write_undo_info(&self.config, &info)
+    }
 
-        let undo_dir = self.config.undo_dir.clone();
-        fs::create_dir_all(&undo_dir).map_err(SearchError::IoError)?;
-
-        let undo_file = undo_dir.join(format!("{}.json", timestamp));
-        let content = serde_json::to_string_pretty(&info).map_err(SearchError::JsonError)?;
-        fs::write(&undo_file, content).map_err(SearchError::IoError)?;
+    /// Undoes a specific operation by its ID.
+    ///
+    /// Refuses with [`SearchError::UndoConflict`] if any of the files was
+    /// edited after the operation, since restoring it would lose those edits.
+    pub fn undo_by_id(id: u64, config: &ReplacementConfig) -> SearchResult<()> {
+        Self::undo_by_id_with_force(id, config, false)
+    }
 
-        Ok(())
+    /// Undoes a specific operation by its ID like [`Self::undo_by_id`], but
+    /// with `force` also restores files edited after the operation
+    pub fn undo_by_id_with_force(
+        id: u64,
+        config: &ReplacementConfig,
+        force: bool,
+    ) -> SearchResult<()> {
+        Self::undo_by_id_with_progress(id, config, force, None)
     }
 
-    /// Undoes a specific operation by its ID
-    pub fn undo_by_id(id: u64, config: &ReplacementConfig) -> SearchResult<()> {
+    /// Undoes an operation like [`Self::undo_by_id_with_force`], calling
+    /// `progress` with each file once it is restored or has failed.
+    ///
+    /// Files are restored in parallel. One that can't be restored doesn't
+    /// stop the others: [`SearchError::UndoIncomplete`] lists every failure,
+    /// and the undo information keeps only the failed files, so the undo can
+    /// be run again once they are fixed. Renames are reversed only after
+    /// every file is restored.
+    pub fn undo_by_id_with_progress(
+        id: u64,
+        config: &ReplacementConfig,
+        force: bool,
+        progress: Option<&(dyn Fn(&Path) + Sync)>,
+    ) -> SearchResult<()> {
+        let _span = debug_span!("undo", id).entered();
         let info_path = config.undo_dir.join(format!("{}.json", id));
-        let content = fs::read_to_string(&info_path)
-            .map_err(|e| SearchError::config_error(format!("Failed to read undo info: {}", e)))?;
-        let info: UndoInfo = serde_json::from_str(&content)?;
+        let mut info = UndoInfo::load(&info_path)?;
 
         // Detect workspace root from the undo directory which we know exists
         let workspace_root = detect_workspace_root(&config.undo_dir)?;
-        println!("Debug: undo workspace_root = {}", workspace_root.display());
-
-        // Restore files from backups
-        for (original, backup) in &info.backups {
-            let path_to_restore = if let Some(abs) = original.abs_path.as_ref() {
-                if abs.exists() {
-                    println!("Debug: Using absolute path for restore: {}", abs.display());
-                    abs.clone()
-                } else {
-                    let fallback = workspace_root.join(&original.rel_path);
-                    println!(
-                        "Debug: Using fallback path for restore: {}",
-                        fallback.display()
-                    );
-                    fallback
-                }
-            } else {
-                let fallback = workspace_root.join(&original.rel_path);
-                println!(
-                    "Debug: Using relative path for restore: {}",
-                    fallback.display()
-                );
-                fallback
-            };
-
-            let backup_path = if let Some(abs) = backup.abs_path.as_ref() {
-                if abs.exists() {
-                    println!("Debug: Using absolute backup path: {}", abs.display());
-                    abs.clone()
-                } else {
-                    let fallback = workspace_root.join(&backup.rel_path);
-                    println!("Debug: Using fallback backup path: {}", fallback.display());
-                    fallback
-                }
-            } else {
-                let fallback = workspace_root.join(&backup.rel_path);
-                println!("Debug: Using relative backup path: {}", fallback.display());
-                fallback
-            };
+        debug!(id, workspace_root = %workspace_root.display(), "Undoing operation");
+        let pool = thread_pool(config.thread_count)?;
 
-            // Ensure backup exists and has content
-            if !backup_path.exists() {
-                return Err(SearchError::config_error(format!(
-                    "Backup file not found: {}",
-                    backup_path.display()
-                )));
+        if !force {
+            let edited = pool.install(|| edited_files(&info, &workspace_root))?;
+            if !edited.is_empty() {
+                return Err(SearchError::UndoConflict { files: edited });
             }
+            pool.install(|| verify_backups(&info, &workspace_root))?;
+        }
 
-            // Read backup content and write to original file
-            let backup_content = fs::read_to_string(&backup_path)
-                .map_err(|e| SearchError::config_error(format!("Failed to read backup: {}", e)))?;
-
-            println!(
-                "Debug: Writing backup content to: {}",
-                path_to_restore.display()
-            );
-            fs::write(&path_to_restore, backup_content).map_err(|e| {
-                SearchError::config_error(format!("Failed to restore backup: {}", e))
-            })?;
-
-            // Clean up backup file
-            fs::remove_file(&backup_path).ok();
+        // Restore files from backups
+        let results: Vec<SearchResult<()>> = pool.install(|| {
+            info.backups
+                .par_iter()
+                .map(|(original, backup)| {
+                    let backup_path = info.resolve_backup(backup, &workspace_root);
+                    let result = restore_backup(original, &backup_path, &workspace_root);
+                    if let Some(progress) = progress {
+                        progress(&original.rel_path);
+                    }
+                    result
+                })
+                .collect()
+        });
+
+        let mut failures = Vec::new();
+        let mut failed = Vec::with_capacity(results.len());
+        for ((original, _), result) in info.backups.iter().zip(results) {
+            failed.push(result.is_err());
+            if let Err(e) = result {
+                failures.push((original.rel_path.clone(), e));
+            }
+        }
+        if !failures.is_empty() {
+            // Keep just the failed files, so running the undo again retries them
+            let restored = info.backups.len() - failures.len();
+            let failed_paths: HashSet<&PathBuf> = failures.iter().map(|(path, _)| path).collect();
+            info.file_diffs
+                .retain(|diff| failed_paths.contains(&diff.file_path.rel_path));
+            let mut keep = failed.iter().copied();
+            info.backups.retain(|_| keep.next().unwrap_or(false));
+            if !info.backup_hashes.is_empty() {
+                let mut keep = failed.iter().copied();
+                info.backup_hashes.retain(|_| keep.next().unwrap_or(false));
+            }
+            info.file_count = info.backups.len();
+            let content = serde_json::to_string_pretty(&info)?;
+            fs::write(&info_path, content)?;
+            return Err(SearchError::UndoIncomplete { restored, failures });
         }
+        rename::undo_renames(&info, &workspace_root)?;
 
         // Clean up the undo info file
         fs::remove_file(info_path).ok();
@@ -893,52 +1605,318 @@ impl ReplacementSet {
 
     /// Partially reverts an existing replacement operation by only reverting selected hunk indices.
     /// If the operation has no patch-based diffs (file_diffs), returns an error.
+    ///
+    /// In a file edited after the operation, each hunk is reverted where its
+    /// lines are now, nearest to where they were. If some hunk's lines are
+    /// gone, nothing is reverted and [`SearchError::UndoConflict`] lists
+    /// the files.
     pub fn undo_partial_by_id(
         id: u64,
         config: &ReplacementConfig,
         hunk_indices: &[usize],
     ) -> SearchResult<()> {
-        let info_path = config.undo_dir.join(format!("{}.json", id));
-        let content = fs::read_to_string(&info_path)
-            .map_err(|e| SearchError::config_error(format!("Failed to read undo info: {}", e)))?;
-        let info: UndoInfo = serde_json::from_str(&content)?;
+        Self::undo_partial_by_id_with_force(id, config, hunk_indices, false)
+    }
 
-        // If there's no diff data, partial revert isn't possible
-        if info.file_diffs.is_empty() {
-            return Err(SearchError::config_error(
-                "This undo operation only supports full-file backups; partial revert is not possible.",
-            ));
-        }
+    /// Partially reverts an operation like [`Self::undo_partial_by_id`], but
+    /// with `force` reverts hunks whose lines cannot be found at the line
+    /// numbers they were recorded at
+    pub fn undo_partial_by_id_with_force(
+        id: u64,
+        config: &ReplacementConfig,
+        hunk_indices: &[usize],
+        force: bool,
+    ) -> SearchResult<()> {
+        let info = load_partial(id, config)?;
+        let selected = vec![hunk_indices.to_vec(); info.file_diffs.len()];
+        revert_selected(config, &info, &selected, force, None)
+    }
 
-        // Process each file diff
-        for file_diff in &info.file_diffs {
-            let workspace_root = detect_workspace_root(&file_diff.file_path.rel_path)?;
-            let path_to_restore = if let Some(abs) = file_diff.file_path.abs_path.as_ref() {
-                if abs.exists() {
-                    abs.clone()
-                } else {
-                    // Fallback to workspace-relative path
-                    workspace_root.join(&file_diff.file_path.rel_path)
+    /// Partially reverts an operation, reverting the hunks `selection` picks:
+    /// whole files by glob, hunks by their index across the operation, or
+    /// hunks by their index within a file. Files without a selected hunk are
+    /// left alone, even if they were edited since.
+    ///
+    /// Like [`Self::undo_partial_by_id_with_force`], hunks follow their lines
+    /// in edited files, and `force` reverts them where they were recorded
+    /// when they can't be found. Files are reverted in parallel, calling
+    /// `progress` with each one once it is done or has failed; a file that
+    /// fails doesn't stop the others, and [`SearchError::UndoIncomplete`]
+    /// lists every failure.
+    pub fn undo_selection_by_id(
+        id: u64,
+        config: &ReplacementConfig,
+        selection: &HunkSelection,
+        force: bool,
+        progress: Option<&(dyn Fn(&Path) + Sync)>,
+    ) -> SearchResult<()> {
+        let info = load_partial(id, config)?;
+        let selected = selection.resolve(&info)?;
+        revert_selected(config, &info, &selected, force, progress)
+    }
+}
+
+/// Loads an operation for a partial revert, which needs its diffs
+fn load_partial(id: u64, config: &ReplacementConfig) -> SearchResult<UndoInfo> {
+    let info = UndoInfo::load(&config.undo_dir.join(format!("{}.json", id)))?;
+
+    // If there's no diff data, partial revert isn't possible
+    if info.file_diffs.is_empty() {
+        return Err(SearchError::config_error(
+            "This undo operation only supports full-file backups; partial revert is not possible.",
+        ));
+    }
+    Ok(info)
+}
+
+/// Reverts the hunks of each of `info.file_diffs` whose indices `selected`
+/// lists for it, after checking all of them for conflicts
+fn revert_selected(
+    config: &ReplacementConfig,
+    info: &UndoInfo,
+    selected: &[Vec<usize>],
+    force: bool,
+    progress: Option<&(dyn Fn(&Path) + Sync)>,
+) -> SearchResult<()> {
+    let _span = debug_span!("undo", id = info.timestamp, partial = true).entered();
+    let pool = thread_pool(config.thread_count)?;
+
+    // Work out every file's hunks before reverting any
+    let planned = pool.install(|| {
+        info.file_diffs
+            .par_iter()
+            .zip(selected.par_iter())
+            .map(|(file_diff, hunk_indices)| plan_revert(file_diff, hunk_indices, force))
+            .collect::<SearchResult<Vec<_>>>()
+    })?;
+    let conflicts: Vec<PathBuf> = planned
+        .iter()
+        .flatten()
+        .filter(|(_, _, conflict)| *conflict)
+        .map(|(path, _, _)| path.clone())
+        .collect();
+    if !conflicts.is_empty() {
+        return Err(SearchError::UndoConflict { files: conflicts });
+    }
+
+    // Apply the filtered hunks
+    let reverts: Vec<(PathBuf, FileDiff)> = planned
+        .into_iter()
+        .flatten()
+        .map(|(path, diff, _)| (path, diff))
+        .collect();
+    let attempted = reverts.len();
+    let results: Vec<SearchResult<()>> = pool.install(|| {
+        reverts
+            .par_iter()
+            .map(|(path, filtered_diff)| {
+                let result = apply_file_diff(path, filtered_diff);
+                if let Some(progress) = progress {
+                    progress(path);
                 }
-            } else {
-                workspace_root.join(&file_diff.file_path.rel_path)
+                result
+            })
+            .collect()
+    });
+    let failures: Vec<(PathBuf, SearchError)> = reverts
+        .into_iter()
+        .zip(results)
+        .filter_map(|((path, _), result)| result.err().map(|e| (path, e)))
+        .collect();
+    if !failures.is_empty() {
+        return Err(SearchError::UndoIncomplete {
+            restored: attempted - failures.len(),
+            failures,
+        });
+    }
+
+    Ok(())
+}
+
+/// The file to revert and the diff of just its selected hunks, moved to
+/// where their lines are now, and whether some of them can't be found there.
+/// `None` if no hunk of the file is selected.
+fn plan_revert(
+    file_diff: &FileDiff,
+    hunk_indices: &[usize],
+    force: bool,
+) -> SearchResult<Option<(PathBuf, FileDiff, bool)>> {
+    let workspace_root = detect_workspace_root(&file_diff.file_path.rel_path)?;
+    let path_to_restore = if let Some(abs) = file_diff.file_path.abs_path.as_ref() {
+        if abs.exists() {
+            abs.clone()
+        } else {
+            // Fallback to workspace-relative path
+            workspace_root.join(&file_diff.file_path.rel_path)
+        }
+    } else {
+        workspace_root.join(&file_diff.file_path.rel_path)
+    };
+
+    // Create a new file diff with only the selected hunks
+    let mut filtered_diff = file_diff.clone();
+    filtered_diff.hunks = file_diff
+        .hunks
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| hunk_indices.contains(i))
+        .map(|(_, h)| h.clone())
+        .collect();
+    if filtered_diff.hunks.is_empty() {
+        return Ok(None);
+    }
+
+    // Find the hunks again in a file edited since
+    let mut conflict = false;
+    if let Some(hash) = &file_diff.content_hash {
+        if path_to_restore.exists() && file_hash(&path_to_restore)? != *hash {
+            let current = fs::read_to_string(&path_to_restore)?;
+            conflict = !relocate_hunks(&current, &mut filtered_diff.hunks) && !force;
+        }
+    }
+    Ok(Some((path_to_restore, filtered_diff, conflict)))
+}
+
+/// Writes a backup back over the file it was taken of, then removes it
+fn restore_backup(
+    original: &UndoFileReference,
+    backup_path: &Path,
+    workspace_root: &Path,
+) -> SearchResult<()> {
+    let path_to_restore = original.resolve_in(workspace_root);
+    debug!(
+        path = %path_to_restore.display(),
+        backup = %backup_path.display(),
+        "Restoring backup"
+    );
+
+    // Ensure backup exists and has content
+    if !backup_path.exists() {
+        return Err(SearchError::config_error(format!(
+            "Backup file not found: {}",
+            backup_path.display()
+        )));
+    }
+
+    // Read backup content and write to original file
+    let backup_content = backups::read_backup(backup_path)
+        .map_err(|e| SearchError::config_error(format!("Failed to read backup: {}", e)))?;
+    fs::write(&path_to_restore, backup_content)
+        .map_err(|e| SearchError::config_error(format!("Failed to restore backup: {}", e)))?;
+    // Backups carry the original's times, except shared compressed ones
+    if !backups::is_compressed(backup_path) {
+        copy_file_times(&fs::metadata(backup_path)?, &path_to_restore)?;
+    }
+
+    // Clean up backup file, unless another operation shares it
+    backups::release_backup(backup_path).ok();
+    Ok(())
+}
+
+/// The files of an operation that were edited after it, going by the hashes
+/// recorded with its diffs. Files that are gone don't count.
+fn edited_files(info: &UndoInfo, workspace_root: &Path) -> SearchResult<Vec<PathBuf>> {
+    let edited = info
+        .file_diffs
+        .par_iter()
+        .map(|file_diff| -> SearchResult<Option<PathBuf>> {
+            let Some(hash) = &file_diff.content_hash else {
+                return Ok(None);
             };
+            let path = file_diff.file_path.resolve_in(workspace_root);
+            let is_edited = path.exists() && file_hash(&path)? != *hash;
+            Ok(is_edited.then_some(path))
+        })
+        .collect::<SearchResult<Vec<_>>>()?;
+    Ok(edited.into_iter().flatten().collect())
+}
 
-            // Create a new file diff with only the selected hunks
-            let mut filtered_diff = file_diff.clone();
-            filtered_diff.hunks = file_diff
-                .hunks
-                .iter()
-                .enumerate()
-                .filter(|(i, _)| hunk_indices.contains(i))
-                .map(|(_, h)| h.clone())
-                .collect();
+/// Checks every backup of an operation against the hash recorded for it, so
+/// that a damaged backup is caught before anything is restored
+fn verify_backups(info: &UndoInfo, workspace_root: &Path) -> SearchResult<()> {
+    info.backups
+        .par_iter()
+        .zip(info.backup_hashes.par_iter())
+        .try_for_each(|((_, backup), expected)| {
+            let path = info.resolve_backup(backup, workspace_root);
+            if !path.exists() {
+                return Ok(());
+            }
+            let actual = file_hash(&path)?;
+            if actual != *expected {
+                return Err(SearchError::BackupCorrupted {
+                    path,
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+            Ok(())
+        })
+}
 
-            // Apply the filtered hunks
-            apply_file_diff(&path_to_restore, &filtered_diff)?;
+/// Moves each hunk to the place its new lines are at now, the nearest one to
+/// where they were recorded. Returns false if some hunk's lines are nowhere
+/// in `content`; that hunk keeps its recorded place.
+fn relocate_hunks(content: &str, hunks: &mut [DiffHunk]) -> bool {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut all_found = true;
+    for hunk in hunks.iter_mut().filter(|hunk| !hunk.new_lines.is_empty()) {
+        let recorded = hunk.new_start_line.saturating_sub(1);
+        let nearest = lines
+            .windows(hunk.new_lines.len())
+            .enumerate()
+            .filter(|(_, window)| {
+                window
+                    .iter()
+                    .zip(&hunk.new_lines)
+                    .all(|(line, new_line)| *line == new_line.as_str())
+            })
+            .map(|(i, _)| i)
+            .min_by_key(|i| i.abs_diff(recorded));
+        match nearest {
+            Some(i) => hunk.new_start_line = i + 1,
+            None => all_found = false,
         }
+    }
+    all_found
+}
 
-        Ok(())
+/// Writes `info` to the undo directory, named after its timestamp, then
+/// applies the workspace's undo retention policy
+fn write_undo_info(config: &ReplacementConfig, info: &UndoInfo) -> SearchResult<()> {
+    fs::create_dir_all(&config.undo_dir).map_err(SearchError::IoError)?;
+
+    let undo_file = config.undo_dir.join(format!("{}.json", info.timestamp));
+    let content = serde_json::to_string_pretty(info).map_err(SearchError::JsonError)?;
+    fs::write(&undo_file, content).map_err(SearchError::IoError)?;
+
+    prune::auto_prune(config);
+    Ok(())
+}
+
+/// Gives `path` the access and modification times recorded in `metadata`
+fn copy_file_times(metadata: &fs::Metadata, path: &Path) -> SearchResult<()> {
+    filetime::set_file_times(
+        path,
+        FileTime::from_last_access_time(metadata),
+        FileTime::from_last_modification_time(metadata),
+    )?;
+    Ok(())
+}
+
+/// Gives `path` the owner and group recorded in `metadata`. Only a process
+/// with the right privileges can change them, so failing just logs a warning.
+#[cfg(unix)]
+fn restore_owner(metadata: &fs::Metadata, path: &Path) {
+    use std::os::unix::fs::MetadataExt;
+
+    let unchanged = fs::metadata(path)
+        .is_ok_and(|current| current.uid() == metadata.uid() && current.gid() == metadata.gid());
+    if unchanged {
+        return;
+    }
+    if let Err(e) = std::os::unix::fs::chown(path, Some(metadata.uid()), Some(metadata.gid())) {
+        warn!("Cannot restore the owner of {}: {}", path.display(), e);
     }
 }
 
@@ -958,14 +1936,68 @@ pub struct PreviewResult {
     pub line_numbers: Vec<usize>,
 }
 
-fn validate_word_boundaries(regex: &regex::Regex) -> SearchResult<()> {
-    // Check if the pattern has proper word boundary markers
-    let pattern = regex.as_str();
-    if !pattern.starts_with("\\b") || !pattern.ends_with("\\b") {
-        return Err(SearchError::invalid_pattern(
-            "Pattern must have word boundary markers (\\b) when word boundary mode is enabled",
-        ));
+/// Replaces every match of `patterns` in `text`, the way replacing in a file
+/// would, and returns the new text with the number of replacements made.
+///
+/// Boundary modes, case handling, capture groups and `preserve_case` all
+/// apply, and matches of different patterns that overlap are an error.
+/// Nothing is read from or written to disk.
+pub fn replace_in_text(
+    patterns: &[ReplacementPattern],
+    text: &str,
+) -> SearchResult<(String, usize)> {
+    let matcher = PatternMatcher::from_definitions(
+        patterns
+            .iter()
+            .map(|pattern| pattern.definition.clone())
+            .collect(),
+    )?;
+    let config = ReplacementConfig {
+        patterns: patterns.to_vec(),
+        ..ReplacementConfig::default()
+    };
+
+    let mut plan = FileReplacementPlan {
+        file_path: PathBuf::from("<text>"),
+        replacements: Vec::new(),
+        original_metadata: None,
+        skipped: 0,
+        line_breaks: None,
+    };
+    for m in matcher.find_matches(text) {
+        // A match running to the end of a CRLF line (e.g. `.*`) leaves its \r alone
+        let end = if m.end > m.start
+            && text[..m.end].ends_with('\r')
+            && text[m.end..].starts_with('\n')
+        {
+            m.end - 1
+        } else {
+            m.end
+        };
+        plan.add_replacement(ReplacementTask::new(
+            plan.file_path.clone(),
+            (m.start, end),
+            patterns[m.pattern_index].replacement_text.clone(),
+            m.pattern_index,
+            config.clone(),
+        ))?;
     }
+    Ok((plan.splice(text)?, plan.replacements.len()))
+}
+
+/// Checks that a whole-word regex still compiles once it is wrapped in `\b`,
+/// the way search matches it. Patterns search leaves unwrapped, because they
+/// have boundary tokens or groups of their own, are accepted as they are.
+fn validate_word_boundaries(definition: &PatternDefinition) -> SearchResult<()> {
+    let Some(wrapped) = definition.whole_word_regex() else {
+        return Ok(());
+    };
+    compile_regex(&wrapped).map_err(|e| {
+        SearchError::invalid_pattern(format!(
+            "Pattern '{}' cannot match whole words: wrapping it in \\b makes it invalid: {}",
+            definition.text, e
+        ))
+    })?;
     Ok(())
 }
 
@@ -998,7 +2030,7 @@ pub fn generate_file_diff(old_content: &str, new_content: &str, file_path: &Path
                     let mut new_lines = Vec::new();
                     for change in diff.iter_changes(&op) {
                         if change.tag() == ChangeTag::Insert {
-                            new_lines.push(change.value().trim_end().to_string());
+                            new_lines.push(change.value().trim_end_matches('\n').to_string());
                         }
                     }
 
@@ -1020,7 +2052,7 @@ pub fn generate_file_diff(old_content: &str, new_content: &str, file_path: &Path
                     let mut original_lines = Vec::new();
                     for change in diff.iter_changes(&op) {
                         if change.tag() == ChangeTag::Delete {
-                            original_lines.push(change.value().trim_end().to_string());
+                            original_lines.push(change.value().trim_end_matches('\n').to_string());
                         }
                     }
 
@@ -1045,10 +2077,10 @@ pub fn generate_file_diff(old_content: &str, new_content: &str, file_path: &Path
                     for change in diff.iter_changes(&op) {
                         match change.tag() {
                             ChangeTag::Delete => {
-                                orig_lines.push(change.value().trim_end().to_string());
+                                orig_lines.push(change.value().trim_end_matches('\n').to_string());
                             }
                             ChangeTag::Insert => {
-                                new_lines.push(change.value().trim_end().to_string());
+                                new_lines.push(change.value().trim_end_matches('\n').to_string());
                             }
                             ChangeTag::Equal => {}
                         }
@@ -1070,6 +2102,8 @@ pub fn generate_file_diff(old_content: &str, new_content: &str, file_path: &Path
     FileDiff {
         file_path: file_ref,
         hunks,
+        original_ends_with_newline: Some(old_content.ends_with('\n')),
+        content_hash: None,
     }
 }
 
@@ -1083,39 +2117,119 @@ fn apply_file_diff(path: &Path, file_diff: &FileDiff) -> SearchResult<()> {
     }
 
     let new_content = std::fs::read_to_string(path).map_err(SearchError::IoError)?;
-    let mut lines: Vec<String> = new_content.lines().map(String::from).collect();
+    let reverted_content = revert_hunks(&new_content, file_diff);
+    std::fs::write(path, reverted_content).map_err(SearchError::IoError)?;
+
+    Ok(())
+}
+
+/// Checks that a replacement range lies within a text of `len` bytes and that
+/// neither end splits a UTF-8 character; `byte_at` reads the byte at an offset
+fn check_range(
+    path: &Path,
+    (start, end): (usize, usize),
+    len: usize,
+    mut byte_at: impl FnMut(usize) -> std::io::Result<u8>,
+) -> SearchResult<()> {
+    if start > end {
+        return Err(SearchError::invalid_range(
+            path,
+            (start, end),
+            "start is past the end",
+        ));
+    }
+    if end > len {
+        return Err(SearchError::invalid_range(
+            path,
+            (start, end),
+            format!("the file is only {} bytes long", len),
+        ));
+    }
+    for offset in [start, end] {
+        // Continuation bytes (0b10xxxxxx) only occur inside a character
+        if offset < len && byte_at(offset)? & 0xC0 == 0x80 {
+            return Err(SearchError::invalid_range(
+                path,
+                (start, end),
+                format!("byte {} is inside a character", offset),
+            ));
+        }
+    }
+    Ok(())
+}
 
-    // Sort hunks in descending order of new_start_line so we can safely patch from bottom to top
+/// Puts the original lines of each hunk of `file_diff` back into `content`.
+///
+/// Every line keeps its own line break: a restored line takes the one of the
+/// line it replaces, or of its neighbour if the hunk restores more lines than
+/// it removes. The result ends in a line break if the original file did and a
+/// reverted hunk reaches the last line; otherwise, if `content` does.
+fn revert_hunks(content: &str, file_diff: &FileDiff) -> String {
+    let newline = LineEnding::detect(content).newline();
+    // Each line split from its line break ("" for a last line without one)
+    let mut lines: Vec<(String, &str)> = content
+        .split_inclusive('\n')
+        .map(|line| {
+            let body = line
+                .strip_suffix("\r\n")
+                .or_else(|| line.strip_suffix('\n'))
+                .unwrap_or(line);
+            (body.to_string(), &line[body.len()..])
+        })
+        .collect();
+
+    let reaches_end = file_diff
+        .hunks
+        .iter()
+        .any(|h| h.new_start_line.saturating_sub(1) + h.new_line_count >= lines.len());
+    let ends_with_newline = match file_diff.original_ends_with_newline {
+        Some(original) if reaches_end => original,
+        _ => content.ends_with('\n'),
+    };
+
+    // Patch from the bottom up so earlier line numbers stay valid
     let mut hunks = file_diff.hunks.clone();
     hunks.sort_by_key(|h| std::cmp::Reverse(h.new_start_line));
 
     for hunk in hunks {
-        let new_start = hunk.new_start_line.saturating_sub(1);
-        // Remove the lines that were "newly added" in that region
-        if hunk.new_line_count > 0 {
-            let end = new_start + hunk.new_line_count.min(lines.len() - new_start);
-            lines.drain(new_start..end);
-        }
-        // Then re-insert the old lines
-        if !hunk.original_lines.is_empty() {
-            for (i, old_line) in hunk.original_lines.iter().enumerate() {
-                lines.insert(new_start + i, old_line.clone());
-            }
+        let start = hunk.new_start_line.saturating_sub(1).min(lines.len());
+        let end = start + hunk.new_line_count.min(lines.len() - start);
+        let removed: Vec<&str> = lines.drain(start..end).map(|(_, ending)| ending).collect();
+        let neighbour = removed
+            .last()
+            .copied()
+            .filter(|ending| !ending.is_empty())
+            .or_else(|| start.checked_sub(1).map(|before| lines[before].1))
+            .filter(|ending| !ending.is_empty())
+            .unwrap_or(newline);
+        for (i, old_line) in hunk.original_lines.iter().enumerate() {
+            let ending = removed
+                .get(i)
+                .copied()
+                .filter(|ending| !ending.is_empty())
+                .unwrap_or(neighbour);
+            lines.insert(start + i, (old_line.clone(), ending));
         }
     }
 
-    // Join lines back and overwrite file
-    let reverted_content = lines.join("\n");
-    std::fs::write(path, reverted_content).map_err(SearchError::IoError)?;
-
-    Ok(())
+    let mut reverted = String::with_capacity(content.len());
+    let count = lines.len();
+    for (i, (body, ending)) in lines.into_iter().enumerate() {
+        reverted.push_str(&body);
+        if i + 1 < count || ends_with_newline {
+            reverted.push_str(if ending.is_empty() { newline } else { ending });
+        }
+    }
+    reverted
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::matcher::WordBoundaryMode;
     use crate::workspace::init_workspace;
     use std::fs;
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use tempfile::TempDir;
 
     // Helper function to create a basic pattern definition
@@ -1125,9 +2239,24 @@ mod tests {
             is_regex,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: crate::search::matcher::HyphenMode::default(),
+            case_mode: crate::search::matcher::CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }
     }
 
+    #[test]
+    fn test_no_plans_inside_archives_or_workspace_data() {
+        assert!(FileReplacementPlan::new(PathBuf::from("ci/artifact.zip!/logs/app.log")).is_err());
+        assert!(FileReplacementPlan::new(PathBuf::from("notes!/app.log")).is_ok());
+        assert!(FileReplacementPlan::new(PathBuf::from(".rustscout/undo/1.json")).is_err());
+        assert!(FileReplacementPlan::new(PathBuf::from("src/rustscout.rs")).is_ok());
+    }
+
     #[test]
     fn test_processing_strategies() -> SearchResult<()> {
         // Create test files with known sizes
@@ -1177,24 +2306,32 @@ mod tests {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def("old", false),
                 replacement_text: "new".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: false,
             dry_run: false,
             backup_dir: None,
             preserve_metadata: false,
             undo_dir: PathBuf::from("undo"),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
-        let cli_config = ReplacementConfig {
+        let cli_config = ReplacementOverrides {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def("cli_pattern", false),
                 replacement_text: "cli_replacement".to_string(),
+                preserve_case: false,
             }],
-            backup_enabled: true,
-            dry_run: true,
+            backup_enabled: Some(true),
+            dry_run: Some(true),
             backup_dir: Some(PathBuf::from("backup")),
-            preserve_metadata: true,
-            undo_dir: PathBuf::from("cli_undo"),
+            preserve_metadata: Some(true),
+            ..ReplacementOverrides::default()
         };
 
         base_config.merge_with_cli(cli_config);
@@ -1207,6 +2344,93 @@ mod tests {
         assert!(base_config.preserve_metadata);
     }
 
+    #[test]
+    fn test_config_round_trip() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def(r"v(\d+)", true),
+                replacement_text: "version_$1".to_string(),
+                preserve_case: true,
+            }],
+            backup_enabled: false,
+            max_replacements_per_file: Some(3),
+            encoding_mode: EncodingMode::Detect,
+            ..ReplacementConfig::default()
+        };
+
+        for name in ["replace.json", "replace.yaml", "replace.yml"] {
+            let path = dir.path().join(name);
+            config.save_to(&path)?;
+            let loaded = ReplacementConfig::load_from(&path)?;
+            assert_eq!(loaded.patterns[0].definition.text, r"v(\d+)");
+            assert_eq!(loaded.patterns[0].replacement_text, "version_$1");
+            assert!(loaded.patterns[0].preserve_case);
+            assert!(!loaded.backup_enabled);
+            assert_eq!(loaded.max_replacements_per_file, Some(3));
+            assert_eq!(loaded.encoding_mode, EncodingMode::Detect);
+        }
+        let json = fs::read_to_string(dir.path().join("replace.json"))?;
+        assert!(json.trim_start().starts_with('{'));
+
+        // Without a known extension, the content decides
+        let sniffed = dir.path().join("replace.conf");
+        fs::write(&sniffed, json)?;
+        assert_eq!(
+            ReplacementConfig::load_from(&sniffed)?.patterns[0].replacement_text,
+            "version_$1"
+        );
+
+        // Parse errors name the file and where parsing failed
+        let broken = dir.path().join("broken.json");
+        fs::write(&broken, "{\n  \"patterns\": [\n")?;
+        let message = ReplacementConfig::load_from(&broken)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("broken.json"), "{}", message);
+        assert!(message.contains("line 3"), "{}", message);
+        let broken = dir.path().join("broken.yaml");
+        fs::write(&broken, "patterns: [\nbackup_enabled: true\n")?;
+        let message = ReplacementConfig::load_from(&broken)
+            .unwrap_err()
+            .to_string();
+        assert!(message.contains("broken.yaml"), "{}", message);
+        assert!(message.contains("line "), "{}", message);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cli_can_turn_config_options_off() {
+        let mut config = ReplacementConfig {
+            backup_enabled: true,
+            dry_run: true,
+            preserve_metadata: true,
+            first_match_per_line: true,
+            encoding_mode: EncodingMode::Detect,
+            ..ReplacementConfig::default()
+        };
+
+        // Options left out keep the config file's settings
+        config.merge_with_cli(ReplacementOverrides::default());
+        assert!(config.backup_enabled && config.dry_run && config.preserve_metadata);
+        assert_eq!(config.encoding_mode, EncodingMode::Detect);
+
+        // Like --no-backup, --no-preserve-metadata and -E failfast
+        config.merge_with_cli(ReplacementOverrides {
+            backup_enabled: Some(false),
+            dry_run: Some(false),
+            preserve_metadata: Some(false),
+            first_match_per_line: Some(false),
+            encoding_mode: Some(EncodingMode::FailFast),
+            ..ReplacementOverrides::default()
+        });
+        assert!(!config.backup_enabled);
+        assert!(!config.dry_run);
+        assert!(!config.preserve_metadata);
+        assert!(!config.first_match_per_line);
+        assert_eq!(config.encoding_mode, EncodingMode::FailFast);
+    }
+
     #[test]
     fn test_replacement_with_backup() -> SearchResult<()> {
         let dir = TempDir::new().unwrap();
@@ -1217,12 +2441,19 @@ mod tests {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def("test", false),
                 replacement_text: "replaced".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: true,
             dry_run: false,
             backup_dir: Some(dir.path().to_path_buf()),
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
@@ -1259,12 +2490,19 @@ mod tests {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def("test", false),
                 replacement_text: "replaced".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: true,
             dry_run: true,
             backup_dir: Some(dir.path().to_path_buf()),
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
@@ -1295,12 +2533,19 @@ mod tests {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def(r"fn (\w+)\(\)", true),
                 replacement_text: "fn new_$1()".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: false,
             dry_run: false,
             backup_dir: None,
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
@@ -1320,6 +2565,52 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_multiline_regex_preview() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "start {\n    old\n} end\n")?;
+
+        let mut definition = create_pattern_def(r"\{.*\}", true);
+        definition.multiline = true;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition,
+                replacement_text: "{}".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            dry_run: false,
+            backup_dir: None,
+            preserve_metadata: false,
+            undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
+        };
+
+        let mut plan = FileReplacementPlan::new(file_path.clone())?;
+        plan.add_replacement(ReplacementTask::new(
+            file_path.clone(),
+            (6, 17),
+            "{}".to_string(),
+            0,
+            config.clone(),
+        ))?;
+
+        let (_, preview) = plan.preview_old_new()?;
+        assert_eq!(preview, "start {} end\n");
+
+        plan.apply(&config, &MemoryMetrics::new())?;
+        let new_content = fs::read_to_string(&file_path).map_err(SearchError::IoError)?;
+        assert_eq!(new_content, preview);
+
+        Ok(())
+    }
+
     #[test]
     fn test_invalid_regex_pattern() -> SearchResult<()> {
         let dir = TempDir::new().unwrap();
@@ -1330,12 +2621,19 @@ mod tests {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def("[invalid", true),
                 replacement_text: "replacement".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: false,
             dry_run: false,
             backup_dir: None,
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
@@ -1363,13 +2661,20 @@ mod tests {
         let config = ReplacementConfig {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def(r"(\w+)", true),
-                replacement_text: "$2".to_string(), // $2 doesn't exist, only $1 exists
+                replacement_text: "$2".to_string(), // $2 doesn't exist, only $1 exists,
+                preserve_case: false,
             }],
             backup_enabled: false,
             dry_run: false,
             backup_dir: None,
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let task = ReplacementTask::new(file_path, (0, 4), "$2".to_string(), 0, config.clone());
@@ -1384,41 +2689,180 @@ mod tests {
     }
 
     #[test]
-    fn test_preserve_metadata() -> SearchResult<()> {
+    fn test_overlapping_patterns_rejected() -> SearchResult<()> {
         let dir = TempDir::new().unwrap();
         let file_path = dir.path().join("test.txt");
-        fs::write(&file_path, "test content")?;
-
-        // Make file read-only before applying changes
-        let metadata = fs::metadata(&file_path).map_err(SearchError::IoError)?;
-        let mut perms = metadata.permissions();
-        perms.set_readonly(true);
-        fs::set_permissions(&file_path, perms).map_err(SearchError::IoError)?;
+        fs::write(&file_path, "aaaa")?;
 
+        let mut definition = create_pattern_def("aa", false);
+        definition.allow_overlapping = true;
         let config = ReplacementConfig {
             patterns: vec![ReplacementPattern {
-                definition: create_pattern_def("test", false),
-                replacement_text: "replaced".to_string(),
+                definition,
+                replacement_text: "b".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: false,
             dry_run: false,
             backup_dir: None,
-            preserve_metadata: true,
+            preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
-        plan.add_replacement(ReplacementTask::new(
-            file_path.clone(),
-            (0, 4),
-            "replaced".to_string(),
+        let result = plan.add_replacement(ReplacementTask::new(
+            file_path,
+            (0, 2),
+            "b".to_string(),
             0,
-            config.clone(),
-        ))?;
+            config,
+        ));
+
+        assert!(matches!(result, Err(SearchError::ConfigError(_))));
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_keeps_original_encoding() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let text = "un café noir\n";
+        let expected = "un thé noir\n";
+        let utf16le = |text: &str| {
+            let mut bytes = vec![0xFF, 0xFE];
+            bytes.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+            bytes
+        };
+        let latin1 = |text: &str| text.chars().map(|c| c as u8).collect::<Vec<u8>>();
+
+        for (name, original, replaced) in [
+            ("utf16.txt", utf16le(text), utf16le(expected)),
+            ("latin1.txt", latin1(text), latin1(expected)),
+        ] {
+            let file_path = dir.path().join(name);
+            fs::write(&file_path, &original)?;
+
+            let config = ReplacementConfig {
+                patterns: vec![ReplacementPattern {
+                    definition: create_pattern_def("café", false),
+                    replacement_text: "thé".to_string(),
+                    preserve_case: false,
+                }],
+                backup_enabled: false,
+                dry_run: false,
+                backup_dir: None,
+                preserve_metadata: false,
+                undo_dir: dir.path().to_path_buf(),
+                encoding_mode: EncodingMode::Detect,
+                thread_count: SearchConfig::default_thread_count(),
+                max_replacements_per_file: None,
+                first_match_per_line: false,
+                no_rollback: false,
+                compress_backups: false,
+            };
+
+            // Ranges refer to the decoded text
+            let mut plan = FileReplacementPlan::new(file_path.clone())?;
+            plan.add_replacement(ReplacementTask::new(
+                file_path.clone(),
+                (3, 8),
+                "thé".to_string(),
+                0,
+                config.clone(),
+            ))?;
+            assert_eq!(
+                plan.preview_old_new()?,
+                (text.to_string(), expected.to_string())
+            );
+
+            plan.apply(&config, &MemoryMetrics::new())?;
+            assert_eq!(fs::read(&file_path)?, replaced, "{}", name);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_pattern_rejected() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "text")?;
+
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("", false),
+                replacement_text: "x".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            dry_run: false,
+            backup_dir: None,
+            preserve_metadata: false,
+            undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
+        };
+
+        let task = ReplacementTask::new(file_path, (0, 0), "x".to_string(), 0, config);
+        assert!(matches!(
+            task.validate(),
+            Err(SearchError::InvalidPattern(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_preserve_metadata() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "test content")?;
+
+        // Make file read-only before applying changes
+        let metadata = fs::metadata(&file_path).map_err(SearchError::IoError)?;
+        let mut perms = metadata.permissions();
+        perms.set_readonly(true);
+        fs::set_permissions(&file_path, perms).map_err(SearchError::IoError)?;
+
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("test", false),
+                replacement_text: "replaced".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            dry_run: false,
+            backup_dir: None,
+            preserve_metadata: true,
+            undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
+        };
+
+        let mut plan = FileReplacementPlan::new(file_path.clone())?;
+        plan.add_replacement(ReplacementTask::new(
+            file_path.clone(),
+            (0, 4),
+            "replaced".to_string(),
+            0,
+            config.clone(),
+        ))?;
 
         // Temporarily make file writable for the test
         let metadata = fs::metadata(&file_path).map_err(SearchError::IoError)?;
         let mut perms = metadata.permissions();
+        #[allow(clippy::permissions_set_readonly_false)]
         perms.set_readonly(false);
         fs::set_permissions(&file_path, perms).map_err(SearchError::IoError)?;
 
@@ -1441,12 +2885,19 @@ mod tests {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def("test", false),
                 replacement_text: "replaced".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: false,
             dry_run: false,
             backup_dir: None,
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
@@ -1482,6 +2933,509 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_line_endings_survive_replace_and_revert() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("endings.txt");
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "brand new".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            undo_dir: dir.path().to_path_buf(),
+            ..ReplacementConfig::default()
+        };
+
+        for original in [
+            "fn old() {\r\n    old();  \r\n}\r\n\r\nold",
+            "old\r\nkeep\r\nold\r\n",
+            "mixed old\nkeep\r\nold again\r\nlast\n",
+        ] {
+            fs::write(&file_path, original)?;
+            let mut plan = FileReplacementPlan::new(file_path.clone())?;
+            for (start, text) in original.match_indices("old") {
+                plan.add_replacement(ReplacementTask::new(
+                    file_path.clone(),
+                    (start, start + text.len()),
+                    "brand new".to_string(),
+                    0,
+                    config.clone(),
+                ))?;
+            }
+            plan.apply(&config, &MemoryMetrics::new())?;
+
+            // Only the replaced spans changed
+            let replaced = fs::read_to_string(&file_path)?;
+            assert_eq!(replaced, original.replace("old", "brand new"));
+
+            // Reverting from the diff restores every byte
+            let diff = generate_file_diff(original, &replaced, &file_path);
+            apply_file_diff(&file_path, &diff)?;
+            assert_eq!(fs::read(&file_path)?, original.as_bytes());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_restores_final_newline() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("final.txt");
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            undo_dir: dir.path().to_path_buf(),
+            ..ReplacementConfig::default()
+        };
+
+        // (original, span to replace, replacement)
+        for (original, span, replacement) in [
+            ("first\nold", "old", "new"),
+            ("first\nold\n", "old", "new"),
+            ("first\r\nold", "old", "new\r\n"),
+            ("first\nold\n", "old\n", "new"),
+        ] {
+            fs::write(&file_path, original)?;
+            let start = original.find(span).unwrap();
+            let mut plan = FileReplacementPlan::new(file_path.clone())?;
+            plan.add_replacement(ReplacementTask::new(
+                file_path.clone(),
+                (start, start + span.len()),
+                replacement.to_string(),
+                0,
+                config.clone(),
+            ))?;
+
+            plan.apply(&config, &MemoryMetrics::new())?;
+            let replaced = fs::read_to_string(&file_path)?;
+            let diff = generate_file_diff(original, &replaced, &file_path);
+            apply_file_diff(&file_path, &diff)?;
+            assert_eq!(fs::read(&file_path)?, original.as_bytes());
+
+            fs::write(&file_path, &replaced)?;
+            FileReplacementPlan::revert_file_with_hunks(&diff)?;
+            assert_eq!(fs::read(&file_path)?, original.as_bytes());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_ranges_are_errors() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("multibyte.txt");
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("wörld", false),
+                replacement_text: "world".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            undo_dir: dir.path().to_path_buf(),
+            ..ReplacementConfig::default()
+        };
+        let task =
+            |range| ReplacementTask::new(file_path.clone(), range, "x".into(), 0, config.clone());
+
+        // In memory, streamed and memory mapped
+        for padding in [0, 40 * 1024, LARGE_FILE_THRESHOLD as usize] {
+            let content = format!("héllo wörld\n{}", "-".repeat(padding));
+            fs::write(&file_path, &content)?;
+
+            // Inside "é", inside "ö", and past the end of the file
+            for range in [(2, 4), (7, 9), (0, content.len() + 1)] {
+                let mut plan = FileReplacementPlan::new(file_path.clone())?;
+                plan.add_replacement(task(range))?;
+                if padding == 0 {
+                    assert!(matches!(
+                        plan.preview(),
+                        Err(SearchError::InvalidRange { .. })
+                    ));
+                }
+                let result = plan.apply(&config, &MemoryMetrics::new());
+                assert!(matches!(result, Err(SearchError::InvalidRange { .. })));
+                assert_eq!(fs::read_to_string(&file_path)?, content);
+            }
+        }
+
+        let mut plan = FileReplacementPlan::new(file_path.clone())?;
+        assert!(matches!(
+            plan.add_replacement(task((5, 3))),
+            Err(SearchError::InvalidRange { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_preview_matches_applied_ranges() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("ranges.txt");
+
+        // (content, pattern, is_regex, replacement, occurrences to replace, expected,
+        //  expected from the first task alone)
+        for (content, pattern, is_regex, replacement, picked, expected, first_only) in [
+            (
+                "foo foo foo\n",
+                "foo",
+                false,
+                "bar",
+                &[0, 2][..],
+                "bar foo bar\n",
+                "bar foo foo\n",
+            ),
+            (
+                "a_old b_old c_old\n",
+                r"(\w)_old",
+                true,
+                "${1}_new",
+                &[0, 1][..],
+                "a_new b_new c_old\n",
+                "a_new b_old c_old\n",
+            ),
+        ] {
+            fs::write(&file_path, content)?;
+            let config = ReplacementConfig {
+                patterns: vec![ReplacementPattern {
+                    definition: create_pattern_def(pattern, is_regex),
+                    replacement_text: replacement.to_string(),
+                    preserve_case: false,
+                }],
+                backup_enabled: false,
+                ..ReplacementConfig::default()
+            };
+            let regex = regex::Regex::new(pattern).unwrap();
+            let occurrences: Vec<_> = regex.find_iter(content).collect();
+
+            let mut plan = FileReplacementPlan::new(file_path.clone())?;
+            for &index in picked {
+                let m = occurrences[index];
+                plan.add_replacement(ReplacementTask::new(
+                    file_path.clone(),
+                    (m.start(), m.end()),
+                    replacement.to_string(),
+                    0,
+                    config.clone(),
+                ))?;
+            }
+
+            let (_, previewed) = plan.preview_old_new()?;
+            assert_eq!(previewed, expected);
+            // A single task touches only its own occurrence
+            assert_eq!(plan.replacements[0].apply_to_range(content)?, first_only);
+            plan.apply(&config, &MemoryMetrics::new())?;
+            assert_eq!(fs::read_to_string(&file_path)?, previewed);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_capture_templates_expand_in_every_strategy() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("captures.rs");
+        let pattern = r"fn (?P<name>\w+)\((\w*)\)";
+        let replacement = "fn new_$1(${2}) /* $$${name} */";
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def(pattern, true),
+                replacement_text: replacement.to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            ..ReplacementConfig::default()
+        };
+        let head = "fn alpha() {}\nfn beta(x) {}\n";
+        let expected = "fn new_alpha() /* $alpha */ {}\nfn new_beta(x) /* $beta */ {}\n";
+
+        // In memory, streamed and memory mapped
+        for padding in [0, 40 * 1024, LARGE_FILE_THRESHOLD as usize] {
+            let tail = "-".repeat(padding);
+            fs::write(&file_path, format!("{}{}", head, tail))?;
+
+            let mut plan = FileReplacementPlan::new(file_path.clone())?;
+            for m in regex::Regex::new(pattern).unwrap().find_iter(head) {
+                plan.add_replacement(ReplacementTask::new(
+                    file_path.clone(),
+                    (m.start(), m.end()),
+                    replacement.to_string(),
+                    0,
+                    config.clone(),
+                ))?;
+            }
+            plan.apply(&config, &MemoryMetrics::new())?;
+            assert_eq!(
+                fs::read_to_string(&file_path)?,
+                format!("{}{}", expected, tail)
+            );
+        }
+
+        // Groups the pattern doesn't have are rejected up front
+        let mut task = ReplacementTask::new(
+            file_path.clone(),
+            (0, 2),
+            "${missing}".to_string(),
+            0,
+            config.clone(),
+        );
+        task.config.patterns[0].replacement_text = "${missing}".to_string();
+        assert!(task.validate().is_err());
+        task.config.patterns[0].replacement_text = "$$missing $3".to_string();
+        assert!(task.validate().is_err());
+        task.config.patterns[0].replacement_text = "$$missing $2".to_string();
+        assert!(task.validate().is_ok());
+
+        // Every task of the pattern expands through the same compiled regex
+        let definition = create_pattern_def(pattern, true);
+        assert!(Arc::ptr_eq(
+            &capture_regex(&definition)?,
+            &capture_regex(&definition.clone())?
+        ));
+        Ok(())
+    }
+
+    #[cfg(feature = "fancy-regex")]
+    #[test]
+    fn test_fancy_regex_patterns_replace() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("fancy.txt");
+        let content = "foo1 foo2 aa bb\n";
+        let replace_with = |pattern: &str, replacement: &str| -> SearchResult<String> {
+            fs::write(&file_path, content)?;
+            let config = ReplacementConfig {
+                patterns: vec![ReplacementPattern {
+                    definition: create_pattern_def(pattern, true),
+                    replacement_text: replacement.to_string(),
+                    preserve_case: false,
+                }],
+                backup_enabled: false,
+                ..ReplacementConfig::default()
+            };
+            let matcher =
+                PatternMatcher::from_definitions(vec![create_pattern_def(pattern, true)])?;
+            let mut plan = FileReplacementPlan::new(file_path.clone())?;
+            for m in matcher.find_matches(content) {
+                let task = ReplacementTask::new(
+                    file_path.clone(),
+                    (m.start, m.end),
+                    replacement.to_string(),
+                    0,
+                    config.clone(),
+                );
+                task.validate()?;
+                plan.add_replacement(task)?;
+            }
+            plan.apply(&config, &MemoryMetrics::new())?;
+            Ok(fs::read_to_string(&file_path)?)
+        };
+
+        // Accepted as in search, with groups expanded by the same engine
+        assert_eq!(replace_with("foo(?=1)", "bar")?, "bar1 foo2 aa bb\n");
+        assert_eq!(replace_with(r"(\w)\1", "<$1>")?, "f<o>1 f<o>2 <a> <b>\n");
+
+        // Look-around can't be combined with capture groups in the replacement
+        let err = replace_with("(foo)(?=1)", "$1bar").unwrap_err();
+        assert!(err.to_string().contains("unsupported in replace"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_transfer_case() {
+        for (matched, replacement, expected) in [
+            ("color", "colour", "colour"),
+            ("Color", "colour", "Colour"),
+            ("COLOR", "colour", "COLOUR"),
+            ("colorMap", "ColourMap", "colourMap"),
+            ("ColorMap", "colourMap", "ColourMap"),
+            ("color", "Colour", "colour"),
+            ("C", "colour", "Colour"),
+            ("_color_", "_colour_", "_colour_"),
+            ("_COLOR_", "_colour_", "_COLOUR_"),
+            ("123", "Colour", "Colour"),
+            ("ÉTÉ", "straße", "STRASSE"),
+            ("Été", "ïle", "Ïle"),
+            ("été", "ÎLE", "île"),
+        ] {
+            assert_eq!(
+                transfer_case(matched, replacement),
+                expected,
+                "{} -> {}",
+                matched,
+                replacement
+            );
+        }
+    }
+
+    #[test]
+    fn test_replace_in_text() -> SearchResult<()> {
+        let pattern = |text: &str, is_regex: bool, replacement: &str| ReplacementPattern {
+            definition: create_pattern_def(text, is_regex),
+            replacement_text: replacement.to_string(),
+            preserve_case: false,
+        };
+
+        let mut cat = pattern("cat", false, "dog");
+        assert_eq!(
+            replace_in_text(&[cat.clone()], "cat concat cat.\n")?,
+            ("dog condog dog.\n".to_string(), 3)
+        );
+        cat.definition.boundary_mode = WordBoundaryMode::WholeWords;
+        assert_eq!(
+            replace_in_text(&[cat.clone()], "cat concat cat.\n")?,
+            ("dog concat dog.\n".to_string(), 2)
+        );
+
+        let call = pattern(r"(?P<name>\w+)\((\w*)\)", true, "${name}_v2($2)");
+        assert_eq!(
+            replace_in_text(&[call], "run(x)\r\nstop()\r\n")?,
+            ("run_v2(x)\r\nstop_v2()\r\n".to_string(), 2)
+        );
+
+        // Whole lines keep their CRLF endings
+        let line = pattern(r"TODO.*", true, "DONE");
+        assert_eq!(
+            replace_in_text(&[line], "TODO a\r\nkeep\r\n")?,
+            ("DONE\r\nkeep\r\n".to_string(), 1)
+        );
+
+        let mut colour = ReplacementPattern {
+            preserve_case: true,
+            ..pattern("color", false, "colour")
+        };
+        colour.definition.case_mode = crate::search::matcher::CaseMode::Insensitive;
+        assert_eq!(
+            replace_in_text(&[colour], "Color COLOR color")?,
+            ("Colour COLOUR colour".to_string(), 3)
+        );
+
+        assert_eq!(
+            replace_in_text(&[pattern("absent", false, "x")], "text")?,
+            ("text".to_string(), 0)
+        );
+        assert!(replace_in_text(
+            &[pattern("ab", false, "x"), pattern("bc", false, "y")],
+            "abc"
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_whole_word_regexes_match_like_search() -> SearchResult<()> {
+        let text = "TODO(alice) TODO(bob)x cat catalog concat cats\nYOLO FOMO YOLOFOMO\n";
+        for regex in [r"TODO\(\w+\)", r"cat\w*", r"\bcat", "YOLO|FOMO", r"[a-z]+s"] {
+            let mut definition = create_pattern_def(regex, true);
+            definition.boundary_mode = WordBoundaryMode::WholeWords;
+            let pattern = ReplacementPattern {
+                definition: definition.clone(),
+                replacement_text: "<$0>".to_string(),
+                preserve_case: false,
+            };
+            let task = ReplacementTask::new(
+                PathBuf::from("<text>"),
+                (0, 0),
+                pattern.replacement_text.clone(),
+                0,
+                ReplacementConfig {
+                    patterns: vec![pattern.clone()],
+                    ..ReplacementConfig::default()
+                },
+            );
+            task.validate()?;
+
+            // Every match search finds is replaced, and nothing else
+            let matches = PatternMatcher::from_definitions(vec![definition])?.find_matches(text);
+            let mut expected = String::new();
+            let mut last = 0;
+            for m in &matches {
+                expected.push_str(&text[last..m.start]);
+                expected.push_str(&format!("<{}>", &text[m.start..m.end]));
+                last = m.end;
+            }
+            expected.push_str(&text[last..]);
+            assert_eq!(
+                replace_in_text(&[pattern], text)?,
+                (expected, matches.len()),
+                "{}",
+                regex
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_preserve_case_per_match() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("colors.txt");
+        let content = "color Color COLOR colorMap\n";
+        fs::write(&file_path, content)?;
+
+        let mut definition = create_pattern_def("color", false);
+        definition.case_mode = crate::search::matcher::CaseMode::Insensitive;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition,
+                replacement_text: "colour".to_string(),
+                preserve_case: true,
+            }],
+            backup_enabled: false,
+            ..ReplacementConfig::default()
+        };
+
+        let mut plan = FileReplacementPlan::new(file_path.clone())?;
+        for start in [0, 6, 12, 18] {
+            plan.add_replacement(ReplacementTask::new(
+                file_path.clone(),
+                (start, start + 5),
+                "colour".to_string(),
+                0,
+                config.clone(),
+            ))?;
+        }
+        let (_, previewed) = plan.preview_old_new()?;
+        assert_eq!(previewed, "colour Colour COLOUR colourMap\n");
+        plan.apply(&config, &MemoryMetrics::new())?;
+        assert_eq!(fs::read_to_string(&file_path)?, previewed);
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_from_directory_search_matches() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("two_lines.txt");
+        fs::write(&file_path, "first line\nsecond old line\n")?;
+
+        let mut search_config =
+            SearchConfig::new_with_pattern("old".to_string(), false, WordBoundaryMode::None);
+        search_config.root_paths = vec![dir.path().to_path_buf()];
+        let found = crate::search::search(&search_config)?;
+        assert_eq!(found.file_results.len(), 1);
+
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            ..ReplacementConfig::default()
+        };
+        let file_result = &found.file_results[0];
+        let plan = FileReplacementPlan::from_matches(
+            file_result.path.clone(),
+            &file_result.matches,
+            &config,
+        )?;
+        plan.apply(&config, &MemoryMetrics::new())?;
+        assert_eq!(
+            fs::read_to_string(&file_path)?,
+            "first line\nsecond new line\n"
+        );
+        Ok(())
+    }
+
     #[test]
     fn test_empty_pattern() -> SearchResult<()> {
         let dir = TempDir::new().unwrap();
@@ -1495,6 +3449,12 @@ mod tests {
             backup_dir: None,
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
@@ -1520,12 +3480,19 @@ mod tests {
             patterns: vec![ReplacementPattern {
                 definition: create_pattern_def("test", false),
                 replacement_text: "replaced".to_string(),
+                preserve_case: false,
             }],
             backup_enabled: false,
             dry_run: false,
             backup_dir: None,
             preserve_metadata: false,
             undo_dir: dir.path().to_path_buf(),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         let mut plan = FileReplacementPlan::new(file_path.clone())?;
@@ -1590,10 +3557,17 @@ mod tests {
             file_count: 1,
             dry_run: false,
             file_diffs: vec![],
+            renames: Vec::new(),
+            version: UNDO_INFO_VERSION,
+            backup_hashes: Vec::new(),
+            patterns: Vec::new(),
+            config: None,
+            backup_root: None,
+            backup_root_in_workspace: false,
         };
 
         let undo_file = undo_dir.join("1234.json");
-        let content = serde_json::to_string_pretty(&info).map_err(|e| SearchError::JsonError(e))?;
+        let content = serde_json::to_string_pretty(&info).map_err(SearchError::JsonError)?;
         fs::write(&undo_file, content).map_err(SearchError::IoError)?;
 
         // Test undo
@@ -1604,6 +3578,12 @@ mod tests {
             backup_dir: None,
             preserve_metadata: false,
             undo_dir,
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         ReplacementSet::undo_by_id(1234, &config)?;
@@ -1645,6 +3625,13 @@ mod tests {
             file_count: 1,
             dry_run: false,
             file_diffs: vec![diff],
+            renames: Vec::new(),
+            version: UNDO_INFO_VERSION,
+            backup_hashes: Vec::new(),
+            patterns: Vec::new(),
+            config: None,
+            backup_root: None,
+            backup_root_in_workspace: false,
         };
 
         // Verify serialization
@@ -1660,6 +3647,685 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_undo_refuses_files_edited_since() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let file = root.join("notes.txt");
+        fs::write(&file, "keep\nold value\nkeep\n")?;
+        let mut plan = FileReplacementPlan::new(file.clone())?;
+        plan.add_replacement(ReplacementTask::new(
+            file.clone(),
+            (5, 8),
+            "new".to_string(),
+            0,
+            config.clone(),
+        ))?;
+        let mut set = ReplacementSet::new(config.clone());
+        set.add_plan(plan);
+        set.apply()?;
+        let id = ReplacementSet::list_undo_operations(&config)?[0]
+            .0
+            .timestamp;
+
+        // A line added above the change
+        fs::write(&file, "header\nkeep\nnew value\nkeep\n")?;
+        match ReplacementSet::undo_by_id(id, &config) {
+            Err(SearchError::UndoConflict { files }) => {
+                assert_eq!(files.len(), 1);
+                assert!(files[0].ends_with("notes.txt"));
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(
+            fs::read_to_string(&file)?,
+            "header\nkeep\nnew value\nkeep\n"
+        );
+
+        // Hunks follow their lines
+        ReplacementSet::undo_partial_by_id(id, &config, &[0])?;
+        assert_eq!(
+            fs::read_to_string(&file)?,
+            "header\nkeep\nold value\nkeep\n"
+        );
+
+        // Once the changed line itself is edited, only --force restores
+        fs::write(&file, "header\nkeep\nrewritten\nkeep\n")?;
+        assert!(matches!(
+            ReplacementSet::undo_partial_by_id(id, &config, &[0]),
+            Err(SearchError::UndoConflict { .. })
+        ));
+        assert_eq!(
+            fs::read_to_string(&file)?,
+            "header\nkeep\nrewritten\nkeep\n"
+        );
+        ReplacementSet::undo_by_id_with_force(id, &config, true)?;
+        assert_eq!(fs::read_to_string(&file)?, "keep\nold value\nkeep\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_selection_reverts_only_matching_files() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        fs::create_dir_all(root.join("src"))?;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let mut set = ReplacementSet::new(config.clone());
+        for name in ["src/config.rs", "src/main.rs"] {
+            let file = root.join(name);
+            fs::write(
+                &file,
+                "old
+keep
+old
+",
+            )?;
+            let mut plan = FileReplacementPlan::new(file.clone())?;
+            for start in [0, 9] {
+                plan.add_replacement(ReplacementTask::new(
+                    file.clone(),
+                    (start, start + 3),
+                    "new".to_string(),
+                    0,
+                    config.clone(),
+                ))?;
+            }
+            set.add_plan(plan);
+        }
+        set.apply()?;
+        let id = ReplacementSet::list_undo_operations(&config)?[0]
+            .0
+            .timestamp;
+        // An edit to the file left alone doesn't get in the way
+        fs::write(
+            root.join("src/main.rs"),
+            "edited
+",
+        )?;
+
+        let selection = HunkSelection {
+            files: vec!["config.rs".to_string()],
+            ..HunkSelection::default()
+        };
+        ReplacementSet::undo_selection_by_id(id, &config, &selection, false, None)?;
+        assert_eq!(
+            fs::read_to_string(root.join("src/config.rs"))?,
+            "old\nkeep\nold\n"
+        );
+        assert_eq!(fs::read_to_string(root.join("src/main.rs"))?, "edited\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_restores_other_files_when_one_fails() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let mut set = ReplacementSet::new(config.clone());
+        let files: Vec<PathBuf> = (0..50)
+            .map(|i| root.join(format!("file{}.txt", i)))
+            .collect();
+        for file in &files {
+            fs::write(file, "old\n")?;
+            let mut plan = FileReplacementPlan::new(file.clone())?;
+            plan.add_replacement(ReplacementTask::new(
+                file.clone(),
+                (0, 3),
+                "new".to_string(),
+                0,
+                config.clone(),
+            ))?;
+            set.add_plan(plan);
+        }
+        let backups = set.apply_with_progress()?;
+        let missing = backups
+            .iter()
+            .find(|backup| {
+                backup
+                    .file_name()
+                    .is_some_and(|name| name.to_string_lossy().contains("file7.txt"))
+            })
+            .unwrap();
+        fs::remove_file(missing)?;
+        let id = ReplacementSet::list_undo_operations(&config)?[0]
+            .0
+            .timestamp;
+
+        let done = AtomicUsize::new(0);
+        let progress = |_: &Path| {
+            done.fetch_add(1, Ordering::Relaxed);
+        };
+        match ReplacementSet::undo_by_id_with_progress(id, &config, false, Some(&progress)) {
+            Err(SearchError::UndoIncomplete { restored, failures }) => {
+                assert_eq!(restored, 49);
+                assert_eq!(failures.len(), 1);
+                assert_eq!(failures[0].0, PathBuf::from("file7.txt"));
+            }
+            other => panic!("expected an incomplete undo, got {:?}", other),
+        }
+        assert_eq!(done.load(Ordering::Relaxed), 50);
+        for file in &files {
+            let expected = if file.ends_with("file7.txt") {
+                "new\n"
+            } else {
+                "old\n"
+            };
+            assert_eq!(fs::read_to_string(file)?, expected);
+        }
+
+        // Only the failed file is left to undo
+        let remaining = ReplacementSet::list_undo_operations(&config)?.remove(0).0;
+        assert_eq!(remaining.backups.len(), 1);
+        assert_eq!(remaining.backup_hashes.len(), 1);
+        assert_eq!(remaining.file_diffs.len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_undo_refuses_corrupted_backup() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let file = root.join("notes.txt");
+        fs::write(&file, "old notes\n")?;
+        let mut plan = FileReplacementPlan::new(file.clone())?;
+        plan.add_replacement(ReplacementTask::new(
+            file.clone(),
+            (0, 3),
+            "new".to_string(),
+            0,
+            config.clone(),
+        ))?;
+        let mut set = ReplacementSet::new(config.clone());
+        set.add_plan(plan);
+        let backup = set.apply_with_progress()?.remove(0);
+        let info = ReplacementSet::list_undo_operations(&config)?.remove(0).0;
+        assert_eq!(info.backup_hashes.len(), 1);
+        assert_eq!(info.patterns, config.patterns);
+        assert_eq!(
+            info.config.as_ref().map(|c| c.patterns.len()),
+            Some(0),
+            "the settings are recorded without the patterns"
+        );
+
+        // Flip one byte of the backup
+        let mut bytes = fs::read(&backup)?;
+        bytes[0] ^= 0x20;
+        fs::write(&backup, &bytes)?;
+
+        match ReplacementSet::undo_by_id(info.timestamp, &config) {
+            Err(SearchError::BackupCorrupted {
+                path,
+                expected,
+                actual,
+            }) => {
+                assert_eq!(path.file_name(), backup.file_name());
+                assert_eq!(expected, info.backup_hashes[0]);
+                assert_ne!(actual, expected);
+            }
+            other => panic!("expected a corrupted backup, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(&file)?, "new notes\n");
+
+        ReplacementSet::undo_by_id_with_force(info.timestamp, &config, true)?;
+        assert_eq!(fs::read_to_string(&file)?, "Old notes\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_undo_operations_verbose() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let config = ReplacementConfig {
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let file = root.join("test.txt");
+        let backup = root.join("test.txt.bak");
+        fs::write(&file, "line 1\nmodified\n")?;
+        fs::write(&backup, "line 1\nline 2\n")?;
+
+        // Written newest first, listed oldest first
+        write_undo_info(
+            &config,
+            &UndoInfo {
+                timestamp: 2000,
+                description: "Replace 'b' with 'c'".to_string(),
+                backups: vec![(
+                    UndoFileReference::new(&file)?,
+                    UndoFileReference::new(&backup)?,
+                )],
+                total_size: 14,
+                file_count: 1,
+                dry_run: false,
+                file_diffs: vec![],
+                renames: Vec::new(),
+                version: UNDO_INFO_VERSION,
+                backup_hashes: Vec::new(),
+                patterns: Vec::new(),
+                config: None,
+                backup_root: None,
+                backup_root_in_workspace: false,
+            },
+        )?;
+        write_undo_info(
+            &config,
+            &UndoInfo {
+                timestamp: 1000,
+                description: "Replace 'line 2' with 'modified'".to_string(),
+                backups: vec![],
+                total_size: 0,
+                file_count: 1,
+                dry_run: false,
+                file_diffs: vec![generate_file_diff(
+                    "line 1\nline 2\n",
+                    "line 1\nmodified\n",
+                    Path::new("test.txt"),
+                )],
+                renames: Vec::new(),
+                version: UNDO_INFO_VERSION,
+                backup_hashes: Vec::new(),
+                patterns: Vec::new(),
+                config: None,
+                backup_root: None,
+                backup_root_in_workspace: false,
+            },
+        )?;
+
+        let operations = ReplacementSet::list_undo_operations_verbose(&config)?;
+        assert_eq!(
+            operations.iter().map(|op| op.id).collect::<Vec<_>>(),
+            [1000, 2000]
+        );
+
+        let with_hunks = &operations[0];
+        assert_eq!(with_hunks.description, "Replace 'line 2' with 'modified'");
+        assert!(with_hunks.partial_revert);
+        assert_eq!(with_hunks.files[0].path, PathBuf::from("test.txt"));
+        assert_eq!(with_hunks.files[0].hunks.len(), 1);
+        assert_eq!(with_hunks.files[0].hunks[0].original_start_line, 2);
+
+        let with_backup = &operations[1];
+        assert!(!with_backup.partial_revert);
+        assert_eq!((with_backup.file_count, with_backup.total_size), (1, 14));
+        assert_eq!(
+            with_backup.files[0].backup,
+            Some(PathBuf::from("test.txt.bak"))
+        );
+        assert!(with_backup.files[0].hunks.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dry_run_report_matches_undo_info() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+
+        let files = [root.join("a.txt"), root.join("b.txt")];
+        fs::write(&files[0], "old one\nkeep\nold two\n")?;
+        fs::write(&files[1], "old three\n")?;
+
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "newer".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let set_for = |config: &ReplacementConfig| -> SearchResult<ReplacementSet> {
+            let mut set = ReplacementSet::new(config.clone());
+            for file in &files {
+                let content = fs::read_to_string(file)?;
+                let mut plan = FileReplacementPlan::new(file.clone())?;
+                for (start, _) in content.match_indices("old") {
+                    plan.add_replacement(ReplacementTask::new(
+                        file.clone(),
+                        (start, start + 3),
+                        "newer".to_string(),
+                        0,
+                        config.clone(),
+                    ))?;
+                }
+                set.add_plan(plan);
+            }
+            Ok(set)
+        };
+
+        let dry_run = ReplacementConfig {
+            dry_run: true,
+            ..config.clone()
+        };
+        let preview = set_for(&dry_run)?.execute()?;
+        assert!(preview.dry_run);
+        assert_eq!(preview.total_replacements(), 3);
+        assert_eq!(preview.files[0].replacements, 2);
+        assert_eq!(preview.files[0].bytes_changed, 6);
+        assert!(preview.backup_paths().is_empty());
+        assert_eq!(fs::read_to_string(&files[1])?, "old three\n");
+
+        let applied = set_for(&config)?.execute()?;
+        assert!(!applied.dry_run);
+        assert_eq!(applied.total_replacements(), 3);
+        assert_eq!(applied.backup_paths().len(), 2);
+        assert_eq!(fs::read_to_string(&files[1])?, "newer three\n");
+
+        let operations = ReplacementSet::list_undo_operations(&config)?;
+        let (info, _) = operations.last().unwrap();
+        let recorded: Vec<_> = info.file_diffs.iter().map(|d| d.hunks.clone()).collect();
+        let previewed: Vec<_> = preview.files.iter().map(|f| f.hunks.clone()).collect();
+        assert_eq!(recorded, previewed);
+        assert_eq!(recorded[0].len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_streaming_write_leaves_no_temp_file() -> SearchResult<()> {
+        let dir = TempDir::new().unwrap();
+        let file_path = dir.path().join("mixed.txt");
+        let mut original = b"Old \xFF\xFE tail\n".to_vec();
+        original.extend(std::iter::repeat_n(b'-', 40 * 1024));
+        fs::write(&file_path, &original)?;
+
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: true,
+            }],
+            backup_enabled: false,
+            ..ReplacementConfig::default()
+        };
+        let mut plan = FileReplacementPlan::new(file_path.clone())?;
+        // The second range isn't text, so writing fails partway through
+        for range in [(0, 3), (4, 6)] {
+            plan.add_replacement(ReplacementTask::new(
+                file_path.clone(),
+                range,
+                "new".to_string(),
+                0,
+                config.clone(),
+            ))?;
+        }
+
+        let err = plan.apply(&config, &MemoryMetrics::new()).unwrap_err();
+        assert!(matches!(err, SearchError::InvalidRange { .. }));
+        assert_eq!(fs::read(&file_path)?, original);
+        let entries: Vec<_> = fs::read_dir(dir.path())?
+            .map(|entry| entry.map(|entry| entry.file_name()))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(entries, vec![std::ffi::OsString::from("mixed.txt")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_times_survive_apply_and_undo() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let modified = FileTime::from_unix_time(1_500_000_000, 0);
+        let accessed = FileTime::from_unix_time(1_600_000_000, 0);
+
+        // In memory, streamed and memory mapped
+        let mut set = ReplacementSet::new(config.clone());
+        let mut files = Vec::new();
+        for (i, padding) in [0, 40 * 1024, LARGE_FILE_THRESHOLD as usize]
+            .into_iter()
+            .enumerate()
+        {
+            let file = root.join(format!("{}.txt", i));
+            fs::write(&file, format!("old\n{}", "-".repeat(padding)))?;
+            filetime::set_file_times(&file, accessed, modified)?;
+
+            let mut plan = FileReplacementPlan::new(file.clone())?;
+            plan.add_replacement(ReplacementTask::new(
+                file.clone(),
+                (0, 3),
+                "new".to_string(),
+                0,
+                config.clone(),
+            ))?;
+            set.add_plan(plan);
+            files.push(file);
+        }
+        let backups = set.apply_with_progress()?;
+
+        let mtime = |path: &Path| -> SearchResult<FileTime> {
+            Ok(FileTime::from_last_modification_time(&fs::metadata(path)?))
+        };
+        for (file, backup) in files.iter().zip(&backups) {
+            assert!(fs::read_to_string(file)?.starts_with("new\n"));
+            assert_eq!(mtime(file)?, modified);
+            assert_eq!(mtime(backup)?, modified);
+            // Make the undo have something to put back
+            filetime::set_file_mtime(file, FileTime::now())?;
+        }
+
+        let operations = ReplacementSet::list_undo_operations(&config)?;
+        let (info, _) = operations.last().unwrap();
+        ReplacementSet::undo_by_id(info.timestamp, &config)?;
+        for file in &files {
+            assert!(fs::read_to_string(file)?.starts_with("old\n"));
+            assert_eq!(mtime(file)?, modified);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_apply_rolls_back() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let files: Vec<PathBuf> = (0..4).map(|i| root.join(format!("{}.txt", i))).collect();
+        let originals: Vec<String> = (0..4).map(|i| format!("old {}\nold\n", i)).collect();
+
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: create_pattern_def("old", false),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            backup_enabled: false,
+            // One thread applies the plans in order, so the failure is mid-set
+            thread_count: NonZeroUsize::new(1).unwrap(),
+            ..ReplacementConfig::default()
+        };
+        let set_for = |config: &ReplacementConfig| -> SearchResult<ReplacementSet> {
+            let mut set = ReplacementSet::new(config.clone());
+            for (file, original) in files.iter().zip(&originals) {
+                fs::write(file, original)?;
+                let mut plan = FileReplacementPlan::new(file.clone())?;
+                for (start, _) in original.match_indices("old") {
+                    plan.add_replacement(ReplacementTask::new(
+                        file.clone(),
+                        (start, start + 3),
+                        "new".to_string(),
+                        0,
+                        config.clone(),
+                    ))?;
+                }
+                set.add_plan(plan);
+            }
+            // The third file changes after planning, so its ranges no longer fit
+            fs::write(&files[2], "old")?;
+            Ok(set)
+        };
+
+        let err = set_for(&config)?.apply_with_progress().unwrap_err();
+        assert!(matches!(
+            &err,
+            SearchError::RolledBack { files: 2, source }
+                if matches!(**source, SearchError::InvalidRange { .. })
+        ));
+        assert!(err.to_string().ends_with("(rolled back 2 files)"));
+        for (i, (file, original)) in files.iter().zip(&originals).enumerate() {
+            let expected = if i == 2 { "old" } else { original.as_str() };
+            assert_eq!(fs::read_to_string(file)?, expected);
+        }
+
+        // The backups made before the failure are released again
+        for compress_backups in [false, true] {
+            let backup_dir = root.join(format!("backups-{}", compress_backups));
+            let with_backups = ReplacementConfig {
+                backup_enabled: true,
+                backup_dir: Some(backup_dir.clone()),
+                compress_backups,
+                ..config.clone()
+            };
+            let set = set_for(&with_backups)?;
+            // An earlier backup of the same content must keep its reference
+            let kept = if compress_backups {
+                fs::create_dir_all(&backup_dir)?;
+                fs::write(&files[3], &originals[0])?;
+                let kept = backups::store_compressed(&files[3], &backup_dir)?;
+                fs::write(&files[3], &originals[3])?;
+                Some(kept)
+            } else {
+                None
+            };
+
+            assert!(set.apply_with_progress().is_err());
+            let mut left: Vec<PathBuf> = fs::read_dir(&backup_dir)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<_, _>>()?;
+            left.retain(|path| path.file_name() != Some("index.json".as_ref()));
+            assert_eq!(left, kept.iter().cloned().collect::<Vec<_>>());
+            if let Some(kept) = &kept {
+                let index: serde_json::Value =
+                    serde_json::from_str(&fs::read_to_string(backup_dir.join("index.json"))?)?;
+                let name = kept.file_name().unwrap().to_str().unwrap();
+                assert_eq!(index["refs"], serde_json::json!({ name: 1 }));
+            }
+        }
+
+        let no_rollback = ReplacementConfig {
+            no_rollback: true,
+            ..config.clone()
+        };
+        let err = set_for(&no_rollback)?.apply_with_progress().unwrap_err();
+        assert!(matches!(err, SearchError::InvalidRange { .. }));
+        assert_eq!(fs::read_to_string(&files[0])?, "new 0\nnew\n");
+        assert_eq!(fs::read_to_string(&files[1])?, "new 1\nnew\n");
+        assert_eq!(fs::read_to_string(&files[3])?, originals[3]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replacement_limits() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let file_path = root.join("five.txt");
+        let content = "old\nold old\nold\nold\n";
+
+        // (max per file, first per line, expected, skipped)
+        for (max, first_per_line, expected, skipped) in [
+            (Some(2), false, "new\nnew old\nold\nold\n", 3),
+            (None, true, "new\nnew old\nnew\nnew\n", 1),
+            (Some(3), true, "new\nnew old\nnew\nold\n", 2),
+        ] {
+            fs::write(&file_path, content)?;
+            let config = ReplacementConfig {
+                patterns: vec![ReplacementPattern {
+                    definition: create_pattern_def("old", false),
+                    replacement_text: "new".to_string(),
+                    preserve_case: false,
+                }],
+                undo_dir: root.join(".rustscout").join("undo"),
+                max_replacements_per_file: max,
+                first_match_per_line: first_per_line,
+                ..ReplacementConfig::default()
+            };
+
+            // Added last to first, the earliest matches still win
+            let mut plan = FileReplacementPlan::new(file_path.clone())?;
+            let starts: Vec<usize> = content.match_indices("old").map(|(i, _)| i).collect();
+            assert_eq!(starts.len(), 5);
+            for &start in starts.iter().rev() {
+                plan.add_replacement(ReplacementTask::new(
+                    file_path.clone(),
+                    (start, start + 3),
+                    "new".to_string(),
+                    0,
+                    config.clone(),
+                ))?;
+            }
+            assert_eq!(plan.skipped, skipped);
+
+            let mut set = ReplacementSet::new(config.clone());
+            set.add_plan(plan);
+            let report = set.execute()?;
+            assert_eq!(report.total_skipped(), skipped);
+            assert_eq!(report.total_replacements(), 5 - skipped);
+            assert_eq!(fs::read_to_string(&file_path)?, expected);
+
+            // The undo diff only holds the lines that changed
+            let operations = ReplacementSet::list_undo_operations(&config)?;
+            let (info, path) = operations.last().unwrap();
+            let changed: usize = info.file_diffs[0]
+                .hunks
+                .iter()
+                .map(|hunk| hunk.new_lines.len())
+                .sum();
+            let expected_changed = content
+                .lines()
+                .zip(expected.lines())
+                .filter(|(old, new)| old != new)
+                .count();
+            assert_eq!(changed, expected_changed);
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
     #[test]
     fn test_undo_with_fallback() -> SearchResult<()> {
         let temp = TempDir::new().unwrap();
@@ -1679,12 +4345,16 @@ mod tests {
             backup_dir: None,
             preserve_metadata: true,
             undo_dir: root.join(".rustscout").join("undo"),
+            encoding_mode: EncodingMode::FailFast,
+            thread_count: SearchConfig::default_thread_count(),
+            max_replacements_per_file: None,
+            first_match_per_line: false,
+            no_rollback: false,
+            compress_backups: false,
         };
 
         // Verify workspace root detection
         let workspace_root = detect_workspace_root(&config.undo_dir)?;
-        println!("Debug: workspace_root = {}", workspace_root.display());
-        println!("Debug: temp_dir = {}", root.display());
         assert_eq!(workspace_root, root, "Workspace root should match temp dir");
 
         // Create undo info with absolute path that won't exist
@@ -1699,19 +4369,11 @@ mod tests {
             abs_path: Some(non_existent.clone()),
         };
 
-        println!("Debug: non_existent path = {}", non_existent.display());
-
         // Create backup directory and backup file
         fs::create_dir_all(config.undo_dir.as_path())?;
         let backup_path = config.undo_dir.join(format!("{}.bak", timestamp));
         fs::copy(&test_file, &backup_path)?;
 
-        println!("Debug: backup_path exists = {}", backup_path.exists());
-        println!(
-            "Debug: backup content = {:?}",
-            fs::read_to_string(&backup_path)?
-        );
-
         let backup_ref = UndoFileReference {
             rel_path: PathBuf::from(format!(".rustscout/undo/{}.bak", timestamp)),
             abs_path: Some(backup_path.clone()),
@@ -1725,6 +4387,13 @@ mod tests {
             file_count: 1,
             dry_run: false,
             file_diffs: vec![],
+            renames: Vec::new(),
+            version: UNDO_INFO_VERSION,
+            backup_hashes: Vec::new(),
+            patterns: Vec::new(),
+            config: None,
+            backup_root: None,
+            backup_root_in_workspace: false,
         };
 
         // Save undo info
@@ -1735,20 +4404,11 @@ mod tests {
         // Modify the test file
         fs::write(&test_file, "modified content")?;
 
-        println!("Debug: test_file exists = {}", test_file.exists());
-        println!("Debug: test_file path = {}", test_file.display());
-        println!(
-            "Debug: test_file content = {:?}",
-            fs::read_to_string(&test_file)?
-        );
-
         // Try to undo - should fallback to relative path
         ReplacementSet::undo_by_id(timestamp, &config)?;
 
         // Verify content was restored
         let restored_content = fs::read_to_string(&test_file)?;
-        println!("Debug: restored content = {:?}", restored_content);
-
         assert_eq!(restored_content, "original content");
 
         Ok(())
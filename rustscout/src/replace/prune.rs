@@ -0,0 +1,446 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use tracing::warn;
+
+use crate::config::parse_size;
+use crate::errors::SearchResult;
+use crate::workspace::{detect_workspace_root, WorkspaceMetadata};
+
+use super::backups::{is_compressed, release_backup};
+use super::{ReplacementConfig, ReplacementSet, UndoInfo, UndoSummary};
+
+/// Which undo operations [`ReplacementSet::prune_undo`] removes. An operation
+/// is removed as soon as any of the limits says so.
+///
+/// In the workspace config, `older_than` is written like `30d` and
+/// `max_total_size` like `500M`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PrunePolicy {
+    /// Keep only this many of the newest operations
+    #[serde(default)]
+    pub keep_last: Option<usize>,
+    /// Remove operations recorded longer ago than this
+    #[serde(default, with = "duration_text")]
+    pub older_than: Option<Duration>,
+    /// Remove the oldest operations until the backups of the rest take at
+    /// most this many bytes
+    #[serde(default, with = "size_text")]
+    pub max_total_size: Option<u64>,
+}
+
+/// What [`ReplacementSet::prune_undo`] removed, or would remove in a dry run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PruneReport {
+    /// Whether this was a dry run, so nothing was deleted
+    pub dry_run: bool,
+    /// The operations removed, oldest first
+    pub removed: Vec<UndoSummary>,
+    /// The undo records deleted
+    pub undo_files: Vec<PathBuf>,
+    /// The backups deleted; backups another kept operation needs stay
+    pub backup_files: Vec<PathBuf>,
+    /// Bytes of backups deleted
+    pub bytes_freed: u64,
+}
+
+impl ReplacementSet {
+    /// Removes the undo operations `policy` no longer keeps, with the backups
+    /// only they refer to.
+    ///
+    /// Each operation's record is deleted before its backups, so an
+    /// interrupted prune can leave a stray backup but never a record whose
+    /// backups are gone. With `config.dry_run`, only reports what would go.
+    pub fn prune_undo(
+        config: &ReplacementConfig,
+        policy: &PrunePolicy,
+    ) -> SearchResult<PruneReport> {
+        let mut operations = Self::list_undo_operations(config)?;
+        operations.reverse();
+        let workspace_root = detect_workspace_root(&config.undo_dir)?;
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        // Newest first: once an operation goes, every older one goes too
+        let mut kept_size = 0u64;
+        let mut cut = false;
+        let (mut removed, kept): (Vec<_>, Vec<_>) =
+            operations
+                .into_iter()
+                .enumerate()
+                .partition(|(i, (info, _))| {
+                    cut = cut
+                        || policy.keep_last.is_some_and(|keep| *i >= keep)
+                        || policy
+                            .older_than
+                            .is_some_and(|age| now.saturating_sub(info.timestamp) > age.as_secs())
+                        || policy
+                            .max_total_size
+                            .is_some_and(|max| kept_size.saturating_add(info.total_size) > max);
+                    if !cut {
+                        kept_size += info.total_size;
+                    }
+                    cut
+                });
+        removed.reverse();
+
+        let still_needed: HashSet<PathBuf> = kept
+            .iter()
+            .flat_map(|(_, (info, _))| backup_paths(info, &workspace_root))
+            .collect();
+
+        let mut report = PruneReport {
+            dry_run: config.dry_run,
+            ..PruneReport::default()
+        };
+        for (_, (info, undo_file)) in removed {
+            let backups: Vec<PathBuf> = backup_paths(&info, &workspace_root)
+                .filter(|backup| backup.exists())
+                .collect();
+            if !config.dry_run {
+                fs::remove_file(&undo_file)?;
+            }
+            report.undo_files.push(undo_file);
+
+            for backup in backups {
+                let size = fs::metadata(&backup).map(|m| m.len()).unwrap_or(0);
+                let freed = if is_compressed(&backup) && !config.dry_run {
+                    // Compressed backups are shared: this drops one reference,
+                    // and the last one deletes the backup
+                    release_backup(&backup)
+                } else if still_needed.contains(&backup) || report.backup_files.contains(&backup) {
+                    continue;
+                } else if config.dry_run {
+                    Ok(true)
+                } else {
+                    release_backup(&backup)
+                };
+                match freed {
+                    Ok(true) => {
+                        report.bytes_freed += size;
+                        report.backup_files.push(backup);
+                    }
+                    Ok(false) => {}
+                    Err(e) => warn!("Cannot remove backup {}: {}", backup.display(), e),
+                }
+            }
+            report.removed.push(info.summary());
+        }
+        Ok(report)
+    }
+}
+
+/// Prunes the undo history by the workspace config's `undo_retention`, if it
+/// has one. Failing only logs a warning, since the operation itself is done.
+pub(super) fn auto_prune(config: &ReplacementConfig) {
+    let policy = detect_workspace_root(&config.undo_dir)
+        .and_then(|root| WorkspaceMetadata::load(&root))
+        .ok()
+        .and_then(|metadata| metadata.global_config?.undo_retention);
+    let Some(policy) = policy else {
+        return;
+    };
+    let config = ReplacementConfig {
+        dry_run: false,
+        ..config.clone()
+    };
+    if let Err(e) = ReplacementSet::prune_undo(&config, &policy) {
+        warn!("Cannot prune the undo history: {}", e);
+    }
+}
+
+/// Where the backups of an operation are, resolved like undo resolves them
+fn backup_paths<'a>(
+    info: &'a UndoInfo,
+    workspace_root: &'a Path,
+) -> impl Iterator<Item = PathBuf> + 'a {
+    info.backups
+        .iter()
+        .map(move |(_, backup)| info.resolve_backup(backup, workspace_root))
+}
+
+/// Reads and writes an optional duration as text such as `30d`
+mod duration_text {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Option<Duration>, s: S) -> Result<S::Ok, S::Error> {
+        value
+            .map(|d| humantime::format_duration(d).to_string())
+            .serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Duration>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|text| humantime::parse_duration(&text).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+/// Reads and writes an optional byte count as text such as `500M`
+mod size_text {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(value: &Option<u64>, s: S) -> Result<S::Ok, S::Error> {
+        value.map(|bytes| bytes.to_string()).serialize(s)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<u64>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|text| parse_size(&text).map_err(serde::de::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replace::backups::store_compressed;
+    use crate::replace::{
+        write_undo_info, FileReplacementPlan, ReplacementPattern, ReplacementTask,
+        UndoFileReference, UNDO_INFO_VERSION,
+    };
+    use crate::search::matcher::{PatternDefinition, WordBoundaryMode};
+    use crate::workspace::{init_workspace, GlobalConfig};
+    use tempfile::TempDir;
+
+    /// Records an operation with the given backups, each created on disk
+    fn record(
+        config: &ReplacementConfig,
+        root: &Path,
+        timestamp: u64,
+        backups: &[&str],
+    ) -> SearchResult<()> {
+        let original = root.join("file.txt");
+        fs::write(&original, "current")?;
+        let backup_dir = root.join(".rustscout").join("backups");
+        fs::create_dir_all(&backup_dir)?;
+        let mut refs = Vec::new();
+        for name in backups {
+            fs::write(backup_dir.join(name), "backup")?;
+            refs.push((
+                UndoFileReference::new(&original)?,
+                UndoFileReference::new(&backup_dir.join(name))?,
+            ));
+        }
+        write_undo_info(
+            config,
+            &UndoInfo {
+                timestamp,
+                description: format!("Operation {}", timestamp),
+                backups: refs,
+                total_size: 6 * backups.len() as u64,
+                file_count: backups.len(),
+                dry_run: false,
+                file_diffs: Vec::new(),
+                renames: Vec::new(),
+                version: UNDO_INFO_VERSION,
+                backup_hashes: Vec::new(),
+                patterns: Vec::new(),
+                config: None,
+                backup_root: None,
+                backup_root_in_workspace: false,
+            },
+        )
+    }
+
+    #[test]
+    fn test_prune_keep_last() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let mut config = ReplacementConfig {
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        // The oldest and newest operations share a backup
+        record(&config, root, 1000, &["shared.bak", "first.bak"])?;
+        record(&config, root, 2000, &["second.bak"])?;
+        record(&config, root, 3000, &["shared.bak", "third.bak"])?;
+        let policy = PrunePolicy {
+            keep_last: Some(1),
+            ..PrunePolicy::default()
+        };
+        let backups = root.join(".rustscout").join("backups");
+
+        config.dry_run = true;
+        let report = ReplacementSet::prune_undo(&config, &policy)?;
+        assert_eq!(
+            report.removed.iter().map(|op| op.id).collect::<Vec<_>>(),
+            [1000, 2000]
+        );
+        assert_eq!(report.backup_files.len(), 2);
+        assert_eq!(report.bytes_freed, 12);
+        assert_eq!(ReplacementSet::list_undo_operations(&config)?.len(), 3);
+        assert!(backups.join("first.bak").exists());
+
+        config.dry_run = false;
+        let report = ReplacementSet::prune_undo(&config, &policy)?;
+        assert_eq!(report.undo_files.len(), 2);
+        let remaining = ReplacementSet::list_undo_operations(&config)?;
+        assert_eq!(remaining.len(), 1);
+        assert_eq!(remaining[0].0.timestamp, 3000);
+        assert!(!config.undo_dir.join("1000.json").exists());
+        assert!(!backups.join("first.bak").exists());
+        assert!(!backups.join("second.bak").exists());
+        assert!(backups.join("shared.bak").exists());
+        assert!(backups.join("third.bak").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_by_age_and_size() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        let config = ReplacementConfig {
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        record(&config, root, now - 40 * 86400, &["old.bak"])?;
+        record(&config, root, now - 2, &["a.bak", "b.bak"])?;
+        record(&config, root, now - 1, &["c.bak"])?;
+
+        let policy: PrunePolicy = serde_json::from_str(r#"{"older_than": "30d"}"#)?;
+        let report = ReplacementSet::prune_undo(&config, &policy)?;
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].id, now - 40 * 86400);
+
+        // 6 bytes per backup: the newest fits in 10, the next one doesn't
+        let policy: PrunePolicy = serde_json::from_str(r#"{"max_total_size": "10"}"#)?;
+        let report = ReplacementSet::prune_undo(&config, &policy)?;
+        assert_eq!(report.removed.len(), 1);
+        assert_eq!(report.removed[0].id, now - 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_keeps_shared_compressed_backups() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let mut metadata = init_workspace(root, "json")?;
+        metadata.global_config = Some(GlobalConfig {
+            compress_backups: true,
+            ..GlobalConfig::default()
+        });
+        metadata.save()?;
+        let config = ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: PatternDefinition::new(
+                    "old".to_string(),
+                    false,
+                    WordBoundaryMode::None,
+                ),
+                replacement_text: "new".to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let (a, b) = (root.join("a.txt"), root.join("b.txt"));
+        fs::write(&a, "old value\n")?;
+        fs::write(&b, "old value\n")?;
+        let backup_dir = root.join(".rustscout").join("backups");
+        fs::create_dir_all(&backup_dir)?;
+
+        // An older operation that backed up the same content
+        let shared = store_compressed(&a, &backup_dir)?;
+        write_undo_info(
+            &config,
+            &UndoInfo {
+                timestamp: 1000,
+                description: "Operation 1000".to_string(),
+                backups: vec![(
+                    UndoFileReference::new(&a)?,
+                    UndoFileReference::new(&shared)?,
+                )],
+                total_size: fs::metadata(&shared)?.len(),
+                file_count: 1,
+                dry_run: false,
+                file_diffs: Vec::new(),
+                renames: Vec::new(),
+                version: UNDO_INFO_VERSION,
+                backup_hashes: Vec::new(),
+                patterns: Vec::new(),
+                config: None,
+                backup_root: None,
+                backup_root_in_workspace: false,
+            },
+        )?;
+
+        // The workspace config compresses this one's backup too
+        let mut plan = FileReplacementPlan::new(b.clone())?;
+        plan.add_replacement(ReplacementTask::new(
+            b.clone(),
+            (0, 3),
+            "new".to_string(),
+            0,
+            config.clone(),
+        ))?;
+        let mut set = ReplacementSet::new(config.clone());
+        set.add_plan(plan);
+        assert_eq!(set.apply_with_progress()?, std::slice::from_ref(&shared));
+        let objects = fs::read_dir(&backup_dir)?
+            .filter(|entry| is_compressed(&entry.as_ref().unwrap().path()))
+            .count();
+        assert_eq!(objects, 1);
+
+        let policy = PrunePolicy {
+            keep_last: Some(1),
+            ..PrunePolicy::default()
+        };
+        let report = ReplacementSet::prune_undo(&config, &policy)?;
+        assert_eq!(report.removed.len(), 1);
+        assert!(report.backup_files.is_empty());
+        assert!(shared.exists());
+
+        let id = ReplacementSet::list_undo_operations(&config)?[0]
+            .0
+            .timestamp;
+        ReplacementSet::undo_by_id(id, &config)?;
+        assert_eq!(fs::read_to_string(&b)?, "old value\n");
+        assert!(!shared.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_auto_prune_from_workspace_config() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        let mut metadata = init_workspace(root, "json")?;
+        metadata.global_config = Some(GlobalConfig {
+            undo_retention: Some(PrunePolicy {
+                keep_last: Some(2),
+                ..PrunePolicy::default()
+            }),
+            ..GlobalConfig::default()
+        });
+        metadata.save()?;
+        let config = ReplacementConfig {
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        };
+
+        for timestamp in [1000, 2000, 3000] {
+            record(&config, root, timestamp, &[&format!("{}.bak", timestamp)])?;
+        }
+        let remaining = ReplacementSet::list_undo_operations(&config)?;
+        assert_eq!(
+            remaining
+                .iter()
+                .map(|(info, _)| info.timestamp)
+                .collect::<Vec<_>>(),
+            [2000, 3000]
+        );
+        assert!(!root.join(".rustscout/backups/1000.bak").exists());
+        Ok(())
+    }
+}
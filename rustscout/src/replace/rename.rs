@@ -0,0 +1,353 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ignore::WalkBuilder;
+use tracing::warn;
+
+use crate::errors::{SearchError, SearchResult};
+use crate::workspace::is_workspace_path;
+
+use super::{
+    replace_in_text, write_undo_info, ReplacementConfig, UndoFileReference, UndoInfo,
+    UNDO_INFO_VERSION,
+};
+
+/// Renames every file and directory under `roots` whose name matches the
+/// config's patterns, applying the replacements to the name only. Returns
+/// the renames as `(old, new)` pairs in the order they were made.
+///
+/// Directories are walked like a search, so hidden and ignored entries and
+/// workspace data are left alone; a root that is a file is renamed itself,
+/// while a root directory keeps its name. Renames go deepest first, so a
+/// directory is renamed only after everything inside it. Nothing is renamed
+/// if any target already exists or two names would collide. A dry run only
+/// returns the plan. Otherwise the renames are recorded for
+/// [`ReplacementSet::undo_by_id`](super::ReplacementSet::undo_by_id), and a
+/// failed rename puts back the ones already made unless `no_rollback` is set.
+pub fn rename_paths(
+    config: &ReplacementConfig,
+    roots: &[PathBuf],
+) -> SearchResult<Vec<(PathBuf, PathBuf)>> {
+    let renames = plan_renames(config, roots)?;
+    if config.dry_run || renames.is_empty() {
+        return Ok(renames);
+    }
+
+    // Paths are resolved before anything moves, while every source exists
+    let mut references = Vec::with_capacity(renames.len());
+    for (from, to) in &renames {
+        let original = UndoFileReference::new(from)?;
+        let renamed = renamed_reference(&original, to);
+        references.push((original, renamed));
+    }
+
+    for (done, (from, to)) in renames.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            let e = SearchError::config_error(format!(
+                "Failed to rename {} to {}: {}",
+                from.display(),
+                to.display(),
+                e
+            ));
+            if config.no_rollback {
+                references.truncate(done);
+                save_renames(config, references)?;
+                return Err(e);
+            }
+            for (from, to) in renames[..done].iter().rev() {
+                if let Err(restore_error) = fs::rename(to, from) {
+                    warn!(
+                        "Cannot rename {} back to {}: {}",
+                        to.display(),
+                        from.display(),
+                        restore_error
+                    );
+                }
+            }
+            return Err(SearchError::rolled_back(done, e));
+        }
+    }
+
+    save_renames(config, references)?;
+    Ok(renames)
+}
+
+/// Works out every rename under `roots`, deepest first, and checks that none
+/// of them would clobber an existing path or another rename
+fn plan_renames(
+    config: &ReplacementConfig,
+    roots: &[PathBuf],
+) -> SearchResult<Vec<(PathBuf, PathBuf)>> {
+    let mut paths = Vec::new();
+    for root in roots {
+        if root.is_file() {
+            paths.push(root.clone());
+            continue;
+        }
+        let walker = WalkBuilder::new(root)
+            .filter_entry(|entry| !is_workspace_path(entry.path()))
+            .build();
+        for entry in walker {
+            let entry = entry.map_err(|e| SearchError::config_error(e.to_string()))?;
+            if entry.depth() > 0 {
+                paths.push(entry.into_path());
+            }
+        }
+    }
+    paths.sort_by(|a, b| {
+        b.components()
+            .count()
+            .cmp(&a.components().count())
+            .then_with(|| a.cmp(b))
+    });
+    paths.dedup();
+
+    let mut renames = Vec::new();
+    let mut targets = HashSet::new();
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        let (new_name, count) = replace_in_text(&config.patterns, name)?;
+        if count == 0 || new_name == name {
+            continue;
+        }
+        if new_name.is_empty()
+            || new_name == "."
+            || new_name == ".."
+            || new_name.contains(['/', '\\'])
+        {
+            return Err(SearchError::config_error(format!(
+                "Cannot rename {}: '{}' is not a valid file name",
+                path.display(),
+                new_name
+            )));
+        }
+
+        let target = path.with_file_name(&new_name);
+        if target.symlink_metadata().is_ok() {
+            return Err(SearchError::config_error(format!(
+                "Cannot rename {} to {}: the target already exists",
+                path.display(),
+                target.display()
+            )));
+        }
+        if !targets.insert(target.clone()) {
+            return Err(SearchError::config_error(format!(
+                "Cannot rename {} to {}: another path is renamed to it too",
+                path.display(),
+                target.display()
+            )));
+        }
+        renames.push((path, target));
+    }
+    Ok(renames)
+}
+
+/// The reference `original` gets once it is renamed to `to`
+fn renamed_reference(original: &UndoFileReference, to: &Path) -> UndoFileReference {
+    let name = to.file_name().unwrap_or_default();
+    UndoFileReference {
+        rel_path: original.rel_path.with_file_name(name),
+        abs_path: original
+            .abs_path
+            .as_ref()
+            .map(|abs| abs.with_file_name(name)),
+    }
+}
+
+/// Records the renames so that `replace undo` can reverse them
+fn save_renames(
+    config: &ReplacementConfig,
+    renames: Vec<(UndoFileReference, UndoFileReference)>,
+) -> SearchResult<()> {
+    if renames.is_empty() {
+        return Ok(());
+    }
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let description = match config.patterns.first() {
+        Some(pattern) => format!(
+            "Rename '{}' to '{}'",
+            pattern.definition.text, pattern.replacement_text
+        ),
+        None => format!("Rename operation at {}", timestamp),
+    };
+
+    write_undo_info(
+        config,
+        &UndoInfo {
+            timestamp,
+            description,
+            backups: Vec::new(),
+            total_size: 0,
+            file_count: renames.len(),
+            dry_run: false,
+            file_diffs: Vec::new(),
+            renames,
+            version: UNDO_INFO_VERSION,
+            backup_hashes: Vec::new(),
+            patterns: config.patterns.clone(),
+            config: Some(config.settings()),
+            backup_root: None,
+            backup_root_in_workspace: false,
+        },
+    )
+}
+
+/// Reverses the renames recorded in `info`, last one first
+pub(super) fn undo_renames(info: &UndoInfo, workspace_root: &Path) -> SearchResult<()> {
+    for (original, renamed) in info.renames.iter().rev() {
+        let current = match renamed.abs_path.as_ref() {
+            Some(abs) if abs.symlink_metadata().is_ok() => abs.clone(),
+            _ => workspace_root.join(&renamed.rel_path),
+        };
+        let restored = current.with_file_name(original.rel_path.file_name().unwrap_or_default());
+        if restored.symlink_metadata().is_ok() {
+            return Err(SearchError::config_error(format!(
+                "Cannot rename {} back to {}: the target already exists",
+                current.display(),
+                restored.display()
+            )));
+        }
+        fs::rename(&current, &restored).map_err(|e| {
+            SearchError::config_error(format!(
+                "Failed to rename {} back to {}: {}",
+                current.display(),
+                restored.display(),
+                e
+            ))
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replace::{ReplacementPattern, ReplacementSet};
+    use crate::search::matcher::{PatternDefinition, WordBoundaryMode};
+    use crate::workspace::init_workspace;
+    use tempfile::TempDir;
+
+    fn rename_config(root: &Path, pattern: &str, replacement: &str) -> ReplacementConfig {
+        ReplacementConfig {
+            patterns: vec![ReplacementPattern {
+                definition: PatternDefinition::new(
+                    pattern.to_string(),
+                    false,
+                    WordBoundaryMode::None,
+                ),
+                replacement_text: replacement.to_string(),
+                preserve_case: false,
+            }],
+            undo_dir: root.join(".rustscout").join("undo"),
+            ..ReplacementConfig::default()
+        }
+    }
+
+    #[test]
+    fn test_rename_nested_paths_and_undo() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        fs::create_dir_all(root.join("old_service/old_service_impl"))?;
+        fs::write(root.join("old_service/old_service_api.rs"), "api")?;
+        fs::write(
+            root.join("old_service/old_service_impl/old_service_db.rs"),
+            "db",
+        )?;
+        fs::write(root.join("old_service/lib.rs"), "lib")?;
+        let mut config = rename_config(root, "old_service", "billing");
+
+        // A dry run only reports, deepest first
+        config.dry_run = true;
+        let planned = rename_paths(&config, &[root.to_path_buf()])?;
+        let planned: Vec<_> = planned
+            .iter()
+            .map(|(from, _)| from.strip_prefix(root).unwrap().to_path_buf())
+            .collect();
+        assert_eq!(
+            planned,
+            [
+                "old_service/old_service_impl/old_service_db.rs",
+                "old_service/old_service_api.rs",
+                "old_service/old_service_impl",
+                "old_service",
+            ]
+            .map(PathBuf::from)
+        );
+        assert!(root.join("old_service").exists());
+
+        config.dry_run = false;
+        let renamed = rename_paths(&config, &[root.to_path_buf()])?;
+        assert_eq!(renamed.len(), 4);
+        assert_eq!(
+            fs::read_to_string(root.join("billing/billing_impl/billing_db.rs"))?,
+            "db"
+        );
+        assert_eq!(
+            fs::read_to_string(root.join("billing/billing_api.rs"))?,
+            "api"
+        );
+        assert!(root.join("billing/lib.rs").exists());
+        assert!(!root.join("old_service").exists());
+
+        let operations = ReplacementSet::list_undo_operations(&config)?;
+        assert_eq!(operations.len(), 1);
+        assert_eq!(operations[0].0.renames.len(), 4);
+        ReplacementSet::undo_by_id(operations[0].0.timestamp, &config)?;
+        assert_eq!(
+            fs::read_to_string(root.join("old_service/old_service_impl/old_service_db.rs"))?,
+            "db"
+        );
+        assert!(root.join("old_service/old_service_api.rs").exists());
+        assert!(!root.join("billing").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_refuses_existing_targets() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        fs::write(root.join("old.rs"), "old")?;
+        fs::write(root.join("old_test.rs"), "old test")?;
+        fs::write(root.join("new_test.rs"), "new test")?;
+        let config = rename_config(root, "old", "new");
+
+        let err = rename_paths(&config, &[root.to_path_buf()]).unwrap_err();
+        assert!(err.to_string().contains("already exists"));
+        // Nothing moved, not even the rename without a conflict
+        assert!(root.join("old.rs").exists());
+        assert!(!root.join("new.rs").exists());
+        assert!(ReplacementSet::list_undo_operations(&config)?.is_empty());
+
+        // Two names that would end up the same
+        fs::write(root.join("old_old_test.rs"), "")?;
+        let config = rename_config(root, "old_", "");
+        let err = rename_paths(&config, &[root.to_path_buf()]).unwrap_err();
+        assert!(err.to_string().contains("renamed to it too"));
+        assert!(root.join("old_test.rs").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_skips_workspace_data() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path();
+        init_workspace(root, "json")?;
+        fs::write(root.join("undo_notes.txt"), "")?;
+        let config = rename_config(root, "undo", "redo");
+
+        let renamed = rename_paths(&config, &[root.to_path_buf()])?;
+        assert_eq!(renamed.len(), 1);
+        assert!(root.join("redo_notes.txt").exists());
+        assert!(root.join(".rustscout").join("undo").exists());
+        Ok(())
+    }
+}
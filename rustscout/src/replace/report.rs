@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use super::DiffHunk;
+
+/// What a replacement run changed in one file, or would change in a dry run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileReport {
+    /// The file being modified
+    pub path: PathBuf,
+    /// Number of replacements made in the file
+    pub replacements: usize,
+    /// Matches left alone because of the per-file or per-line limits
+    pub skipped: usize,
+    /// Bytes of the original file covered by the replaced ranges
+    pub bytes_changed: usize,
+    /// Where the original was backed up, if it was
+    pub backup_path: Option<PathBuf>,
+    /// The changed lines, as recorded in the undo information
+    pub hunks: Vec<DiffHunk>,
+}
+
+/// What a replacement run changed, file by file
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplacementReport {
+    /// Whether this was a dry run, so nothing was written
+    pub dry_run: bool,
+    /// One entry per file with replacements, in plan order
+    pub files: Vec<FileReport>,
+}
+
+impl ReplacementReport {
+    /// Total number of replacements across all files
+    pub fn total_replacements(&self) -> usize {
+        self.files.iter().map(|file| file.replacements).sum()
+    }
+
+    /// Total number of matches left alone because of the limits
+    pub fn total_skipped(&self) -> usize {
+        self.files.iter().map(|file| file.skipped).sum()
+    }
+
+    /// Paths of the backups made, in plan order
+    pub fn backup_paths(&self) -> Vec<PathBuf> {
+        self.files
+            .iter()
+            .filter_map(|file| file.backup_path.clone())
+            .collect()
+    }
+}
@@ -0,0 +1,224 @@
+use std::path::Path;
+
+use crate::errors::{SearchError, SearchResult};
+use crate::filters::matches_include_globs;
+
+use super::UndoInfo;
+
+/// Which hunks of an operation a partial undo reverts. A hunk is reverted if
+/// any part of the selection picks it.
+///
+/// File globs are matched like `--include`: against the path relative to the
+/// workspace root if they contain a slash, and against the file name
+/// otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HunkSelection {
+    /// Globs for the files whose every hunk is reverted
+    pub files: Vec<String>,
+    /// Hunks numbered across all files of the operation, in the order
+    /// `replace undo --list-hunks` lists them
+    pub hunks: Vec<usize>,
+    /// Hunks numbered within each file a glob matches
+    pub file_hunks: Vec<(String, Vec<usize>)>,
+}
+
+impl HunkSelection {
+    /// Adds the hunks of a `--hunks` value: indices across all files, like
+    /// `0,1,3`, or indices within the files matching a glob, like
+    /// `src/config.rs:0,2`
+    pub fn add_hunks(&mut self, spec: &str) -> SearchResult<()> {
+        match spec.rsplit_once(':') {
+            Some((glob, indices)) => {
+                let glob = glob.trim();
+                if glob.is_empty() {
+                    return Err(SearchError::config_error(format!(
+                        "Missing file before ':' in hunks '{}'",
+                        spec
+                    )));
+                }
+                self.file_hunks
+                    .push((glob.to_string(), parse_indices(indices)?));
+            }
+            None => self.hunks.extend(parse_indices(spec)?),
+        }
+        Ok(())
+    }
+
+    /// Whether nothing is selected
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.hunks.is_empty() && self.file_hunks.is_empty()
+    }
+
+    /// The selected hunks of each of `info.file_diffs`, as indices within
+    /// the file in ascending order. Fails on a hunk the operation doesn't
+    /// have and on a glob that matches none of its files, so a typo can't
+    /// quietly select nothing.
+    pub fn resolve(&self, info: &UndoInfo) -> SearchResult<Vec<Vec<usize>>> {
+        let total: usize = info.file_diffs.iter().map(|diff| diff.hunks.len()).sum();
+        if let Some(&index) = self.hunks.iter().find(|&&index| index >= total) {
+            return Err(SearchError::config_error(format!(
+                "Operation {} has no hunk {} (it has {})",
+                info.timestamp, index, total
+            )));
+        }
+        for glob in self
+            .files
+            .iter()
+            .chain(self.file_hunks.iter().map(|(glob, _)| glob))
+        {
+            if !info
+                .file_diffs
+                .iter()
+                .any(|diff| matches_file(&diff.file_path.rel_path, glob))
+            {
+                return Err(SearchError::config_error(format!(
+                    "No file of operation {} matches '{}'",
+                    info.timestamp, glob
+                )));
+            }
+        }
+
+        let mut first = 0;
+        let mut selected = Vec::with_capacity(info.file_diffs.len());
+        for diff in &info.file_diffs {
+            let path = &diff.file_path.rel_path;
+            let count = diff.hunks.len();
+            let whole_file = self.files.iter().any(|glob| matches_file(path, glob));
+            let mut indices: Vec<usize> = if whole_file {
+                (0..count).collect()
+            } else {
+                let mut indices: Vec<usize> = self
+                    .hunks
+                    .iter()
+                    .filter(|&&index| (first..first + count).contains(&index))
+                    .map(|index| index - first)
+                    .collect();
+                for (glob, within) in &self.file_hunks {
+                    if !matches_file(path, glob) {
+                        continue;
+                    }
+                    if let Some(&index) = within.iter().find(|&&index| index >= count) {
+                        return Err(SearchError::config_error(format!(
+                            "{} has no hunk {} in operation {} (it has {})",
+                            path.display(),
+                            index,
+                            info.timestamp,
+                            count
+                        )));
+                    }
+                    indices.extend(within);
+                }
+                indices
+            };
+            indices.sort_unstable();
+            indices.dedup();
+            selected.push(indices);
+            first += count;
+        }
+        Ok(selected)
+    }
+}
+
+fn matches_file(rel_path: &Path, glob: &str) -> bool {
+    matches_include_globs(rel_path, Path::new(""), &[glob.to_string()])
+}
+
+fn parse_indices(text: &str) -> SearchResult<Vec<usize>> {
+    text.split(',')
+        .map(|index| {
+            index.trim().parse::<usize>().map_err(|_| {
+                SearchError::config_error(format!("Invalid hunk index: {}", index.trim()))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::replace::{DiffHunk, FileDiff, UndoFileReference, UNDO_INFO_VERSION};
+    use std::path::PathBuf;
+
+    fn operation(files: &[(&str, usize)]) -> UndoInfo {
+        let file_diffs = files
+            .iter()
+            .map(|(path, hunks)| FileDiff {
+                file_path: UndoFileReference {
+                    rel_path: PathBuf::from(path),
+                    abs_path: None,
+                },
+                hunks: (0..*hunks)
+                    .map(|i| DiffHunk {
+                        original_start_line: i + 1,
+                        new_start_line: i + 1,
+                        original_line_count: 1,
+                        new_line_count: 1,
+                        original_lines: vec!["old".to_string()],
+                        new_lines: vec!["new".to_string()],
+                    })
+                    .collect(),
+                original_ends_with_newline: None,
+                content_hash: None,
+            })
+            .collect();
+        UndoInfo {
+            version: UNDO_INFO_VERSION,
+            timestamp: 1,
+            description: String::new(),
+            backups: Vec::new(),
+            backup_hashes: Vec::new(),
+            total_size: 0,
+            file_count: files.len(),
+            dry_run: false,
+            file_diffs,
+            renames: Vec::new(),
+            patterns: Vec::new(),
+            config: None,
+            backup_root: None,
+            backup_root_in_workspace: false,
+        }
+    }
+
+    #[test]
+    fn test_resolve_selection() -> SearchResult<()> {
+        let info = operation(&[("src/config.rs", 3), ("src/main.rs", 2), ("README.md", 1)]);
+
+        let mut selection = HunkSelection {
+            files: vec!["README.md".to_string()],
+            ..HunkSelection::default()
+        };
+        selection.add_hunks("1,3")?;
+        selection.add_hunks("src/config.rs:0,2")?;
+        assert_eq!(selection.hunks, [1, 3]);
+        assert_eq!(selection.resolve(&info)?, [vec![0, 1, 2], vec![0], vec![0]]);
+
+        let selection = HunkSelection {
+            files: vec!["src/*.rs".to_string()],
+            ..HunkSelection::default()
+        };
+        assert_eq!(
+            selection.resolve(&info)?,
+            [vec![0, 1, 2], vec![0, 1], vec![]]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_resolve_rejects_what_the_operation_lacks() {
+        let info = operation(&[("src/config.rs", 2)]);
+        let mut selection = HunkSelection::default();
+        assert!(selection.add_hunks("config.rs:x").is_err());
+        assert!(selection.add_hunks(":1").is_err());
+
+        selection.add_hunks("2").unwrap();
+        let err = selection.resolve(&info).unwrap_err();
+        assert!(err.to_string().contains("no hunk 2"));
+
+        let selection = HunkSelection {
+            files: vec!["src/lib.rs".to_string()],
+            ..HunkSelection::default()
+        };
+        let err = selection.resolve(&info).unwrap_err();
+        assert!(err.to_string().contains("No file"));
+    }
+}
@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use tempfile::NamedTempFile;
+
+use crate::errors::SearchResult;
+use crate::filters::REPLACE_TEMP_PREFIX;
+
+/// Replaces the contents of `target` with what `write` writes, through a
+/// temporary file in the same directory that is renamed over it.
+///
+/// The temporary file is named `.rustscout-tmp-<pid>-<random>`, so it never
+/// collides with a real file, and is removed if anything fails before the
+/// rename. It is given the target's permissions up front.
+pub(crate) fn replace_file(
+    target: &Path,
+    write: impl FnOnce(&mut dyn Write) -> SearchResult<()>,
+) -> SearchResult<()> {
+    let dir = match target.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let tmp = tempfile::Builder::new()
+        .prefix(&format!("{}{}-", REPLACE_TEMP_PREFIX, std::process::id()))
+        .tempfile_in(dir)?;
+    if let Ok(metadata) = fs::metadata(target) {
+        tmp.as_file().set_permissions(metadata.permissions())?;
+    }
+
+    let mut writer = BufWriter::new(tmp.as_file());
+    write(&mut writer)?;
+    writer.flush()?;
+    drop(writer);
+
+    persist(tmp, target)
+}
+
+/// Renames the finished temporary file over `target`.
+///
+/// Windows refuses to replace a read-only file, so the flag is cleared
+/// first; the new file already carries the target's permissions.
+fn persist(tmp: NamedTempFile, target: &Path) -> SearchResult<()> {
+    #[cfg(windows)]
+    if let Ok(metadata) = fs::metadata(target) {
+        let mut permissions = metadata.permissions();
+        if permissions.readonly() {
+            #[allow(clippy::permissions_set_readonly_false)]
+            permissions.set_readonly(false);
+            fs::set_permissions(target, permissions)?;
+        }
+    }
+    tmp.persist(target).map_err(|e| e.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::errors::SearchError;
+    use crate::filters::is_replace_temp_file;
+    use tempfile::tempdir;
+
+    fn leftovers(dir: &Path) -> usize {
+        fs::read_dir(dir)
+            .unwrap()
+            .filter(|entry| is_replace_temp_file(&entry.as_ref().unwrap().path()))
+            .count()
+    }
+
+    #[test]
+    fn test_replace_file() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("notes.tmp");
+        fs::write(&target, "old")?;
+
+        replace_file(&target, |out| Ok(out.write_all(b"new")?))?;
+        assert_eq!(fs::read_to_string(&target)?, "new");
+        assert_eq!(leftovers(dir.path()), 0);
+
+        // A failed write leaves the target alone and cleans up after itself
+        let err = replace_file(&target, |out| {
+            out.write_all(b"partial")?;
+            Err(SearchError::config_error("disk full"))
+        })
+        .unwrap_err();
+        assert!(err.to_string().contains("disk full"));
+        assert_eq!(fs::read_to_string(&target)?, "new");
+        assert_eq!(leftovers(dir.path()), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_read_only_file() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let target = dir.path().join("locked.txt");
+        fs::write(&target, "old")?;
+        let mut permissions = fs::metadata(&target)?.permissions();
+        permissions.set_readonly(true);
+        fs::set_permissions(&target, permissions)?;
+
+        replace_file(&target, |out| Ok(out.write_all(b"new")?))?;
+        assert_eq!(fs::read_to_string(&target)?, "new");
+        assert!(fs::metadata(&target)?.permissions().readonly());
+        Ok(())
+    }
+}
@@ -1,9 +1,28 @@
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
 use std::path::{Path, PathBuf};
 
 use crate::errors::{SearchError, SearchResult};
 use crate::workspace::detect_workspace_root;
 
+use super::{ReplacementConfig, ReplacementPattern};
+
+/// Version of the undo record format written by this build. Records written
+/// before the format was versioned read as version 0.
+///
+/// Version 1 added the hashes of modified files, version 2 those of backups,
+/// version 3 the patterns and settings the operation ran with, version 4
+/// the directory the backups are in.
+pub const UNDO_INFO_VERSION: u32 = 4;
+
+/// The SHA-256 of a file's content, in hex
+pub(crate) fn file_hash(path: &Path) -> SearchResult<String> {
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
 /// A reference to a file that can be stored with both absolute and relative paths
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UndoFileReference {
@@ -43,7 +62,7 @@ impl UndoFileReference {
     }
 
     /// Get a display representation of the path
-    pub fn display(&self) -> std::path::Display {
+    pub fn display(&self) -> std::path::Display<'_> {
         if let Some(abs) = &self.abs_path {
             abs.display()
         } else {
@@ -80,6 +99,15 @@ impl UndoFileReference {
         }
     }
 
+    /// The path to use for this file: the absolute path if something is still
+    /// there, otherwise the relative path under `workspace_root`
+    pub fn resolve_in(&self, workspace_root: &Path) -> PathBuf {
+        match self.abs_path.as_ref() {
+            Some(abs) if abs.exists() => abs.clone(),
+            _ => workspace_root.join(&self.rel_path),
+        }
+    }
+
     /// Create a new file reference from an existing one, but with a new absolute path
     pub fn with_abs_path(&self, abs_path: PathBuf) -> SearchResult<Self> {
         let workspace_root = detect_workspace_root(&abs_path)?;
@@ -118,7 +146,7 @@ impl AsRef<Path> for UndoFileReference {
 }
 
 /// A hunk of changes in a file diff
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DiffHunk {
     /// The 1-based starting line in the original file
     pub original_start_line: usize,
@@ -141,17 +169,32 @@ pub struct FileDiff {
     pub file_path: UndoFileReference,
     /// The hunks of changes made to this file
     pub hunks: Vec<DiffHunk>,
+    /// Whether the file ended with a line break before the change (`None` in
+    /// diffs recorded before this was tracked)
+    #[serde(default)]
+    pub original_ends_with_newline: Option<bool>,
+    /// SHA-256 of the file right after the operation, to notice later edits
+    /// (`None` in diffs recorded before this was tracked)
+    #[serde(default)]
+    pub content_hash: Option<String>,
 }
 
 /// Information about a replacement operation for undo purposes
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UndoInfo {
+    /// Format version, [`UNDO_INFO_VERSION`] for records written by this build
+    #[serde(default)]
+    pub version: u32,
     /// Timestamp when the operation was performed
     pub timestamp: u64,
     /// Description of the operation
     pub description: String,
     /// Map of original files to their backup paths
     pub backups: Vec<(UndoFileReference, UndoFileReference)>,
+    /// SHA-256 of each backup, in the order of `backups` (empty in records
+    /// written before version 2)
+    #[serde(default)]
+    pub backup_hashes: Vec<String>,
     /// Size of the operation in bytes
     pub total_size: u64,
     /// Number of files modified
@@ -161,11 +204,183 @@ pub struct UndoInfo {
     /// Detailed patch-based diffs for each modified file
     #[serde(default)]
     pub file_diffs: Vec<FileDiff>,
+    /// Files and directories renamed by the operation, as (original, renamed)
+    /// pairs in the order they were renamed
+    #[serde(default)]
+    pub renames: Vec<(UndoFileReference, UndoFileReference)>,
+    /// The patterns the operation replaced (empty in records written before
+    /// version 3)
+    #[serde(default)]
+    pub patterns: Vec<ReplacementPattern>,
+    /// The settings the operation ran with, without its patterns or the
+    /// backup and undo directories
+    #[serde(default)]
+    pub config: Option<ReplacementConfig>,
+    /// The directory the backups were written to, which their relative
+    /// paths are relative to: relative to the workspace root if
+    /// `backup_root_in_workspace`, so that it moves along with the workspace,
+    /// and absolute otherwise. Records without it, like those written before
+    /// version 4, have backups relative to the workspace root.
+    #[serde(default)]
+    pub backup_root: Option<PathBuf>,
+    /// Whether `backup_root` is inside the workspace
+    #[serde(default)]
+    pub backup_root_in_workspace: bool,
+}
+
+impl UndoInfo {
+    /// Reads an undo record, bringing one written by an older version up to
+    /// date. Records from a newer version are refused rather than misread.
+    pub fn load(path: &Path) -> SearchResult<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| SearchError::config_error(format!("Failed to read undo info: {}", e)))?;
+        let info: Self = serde_json::from_str(&content)
+            .map_err(|e| SearchError::config_error(format!("Failed to parse undo info: {}", e)))?;
+        info.migrate(path)
+    }
+
+    fn migrate(mut self, path: &Path) -> SearchResult<Self> {
+        if self.version > UNDO_INFO_VERSION {
+            return Err(SearchError::config_error(format!(
+                "{} has undo format version {}, but this version of rustscout only reads up to {}",
+                path.display(),
+                self.version,
+                UNDO_INFO_VERSION
+            )));
+        }
+        // Hashes added since can't be recovered, so older records leave
+        // them out and undo skips those checks
+        self.version = UNDO_INFO_VERSION;
+        Ok(self)
+    }
+
+    /// Where `backup`, one of this operation's backups, is now: at its
+    /// absolute path if something is still there, otherwise under the backup
+    /// root, and failing that under `workspace_root`
+    pub fn resolve_backup(&self, backup: &UndoFileReference, workspace_root: &Path) -> PathBuf {
+        if let Some(abs) = backup.abs_path.as_ref().filter(|abs| abs.exists()) {
+            return abs.clone();
+        }
+        let under_root = self.backup_root.as_ref().map(|root| {
+            let root = if self.backup_root_in_workspace {
+                workspace_root.join(root)
+            } else {
+                root.clone()
+            };
+            root.join(&backup.rel_path)
+        });
+        match under_root {
+            Some(path) if path.exists() || self.backup_root_in_workspace => path,
+            _ => workspace_root.join(&backup.rel_path),
+        }
+    }
+
+    /// The path a backup was recorded at, relative to the workspace root if
+    /// the backups are inside it
+    fn recorded_backup_path(&self, backup: &UndoFileReference) -> PathBuf {
+        match &self.backup_root {
+            Some(root) => root.join(&backup.rel_path),
+            None => backup.rel_path.clone(),
+        }
+    }
+
+    /// Whether hunks can be reverted one at a time, rather than whole files
+    pub fn supports_partial_revert(&self) -> bool {
+        !self.file_diffs.is_empty()
+    }
+
+    /// What `replace list` shows about this operation
+    pub fn summary(&self) -> UndoSummary {
+        let mut files: Vec<UndoFileSummary> = if self.file_diffs.is_empty() {
+            self.backups
+                .iter()
+                .map(|(original, backup)| UndoFileSummary {
+                    path: original.rel_path.clone(),
+                    backup: Some(self.recorded_backup_path(backup)),
+                    renamed_to: None,
+                    hunks: Vec::new(),
+                })
+                .collect()
+        } else {
+            self.file_diffs
+                .iter()
+                .map(|diff| UndoFileSummary {
+                    path: diff.file_path.rel_path.clone(),
+                    backup: self
+                        .backups
+                        .iter()
+                        .find(|(original, _)| original.rel_path == diff.file_path.rel_path)
+                        .map(|(_, backup)| self.recorded_backup_path(backup)),
+                    renamed_to: None,
+                    hunks: diff.hunks.clone(),
+                })
+                .collect()
+        };
+        files.extend(
+            self.renames
+                .iter()
+                .map(|(original, renamed)| UndoFileSummary {
+                    path: original.rel_path.clone(),
+                    backup: None,
+                    renamed_to: Some(renamed.rel_path.clone()),
+                    hunks: Vec::new(),
+                }),
+        );
+
+        UndoSummary {
+            id: self.timestamp,
+            description: self.description.clone(),
+            file_count: self.file_count,
+            total_size: self.total_size,
+            partial_revert: self.supports_partial_revert(),
+            files,
+            patterns: self.patterns.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// An undo operation as listed by `replace list`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoSummary {
+    /// The ID to pass to `replace undo`, which is the operation's timestamp
+    pub id: u64,
+    /// Description of the operation
+    pub description: String,
+    /// Number of files modified
+    pub file_count: usize,
+    /// Size of the backups in bytes
+    pub total_size: u64,
+    /// Whether hunks can be reverted one at a time
+    pub partial_revert: bool,
+    /// The files the operation touched, relative to the workspace root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<UndoFileSummary>,
+    /// The patterns the operation replaced, if they were recorded
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<ReplacementPattern>,
+    /// The settings the operation ran with, if they were recorded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub config: Option<ReplacementConfig>,
+}
+
+/// One file touched by an undo operation
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndoFileSummary {
+    /// The file, relative to the workspace root
+    pub path: PathBuf,
+    /// Where its original content is backed up, if it is
+    pub backup: Option<PathBuf>,
+    /// What the file was renamed to, for rename operations
+    pub renamed_to: Option<PathBuf>,
+    /// The changed lines, when partial revert is available
+    pub hunks: Vec<DiffHunk>,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::matcher::{PatternDefinition, WordBoundaryMode};
     use crate::workspace::init_workspace;
     use std::fs;
     use std::time::SystemTime;
@@ -201,6 +416,80 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_load_migrates_older_records() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("1.json");
+
+        // Written before records had a version or any hashes
+        fs::write(
+            &path,
+            r#"{"timestamp":1,"description":"Old","backups":[],"total_size":0,"file_count":0,"dry_run":false}"#,
+        )?;
+        let info = UndoInfo::load(&path)?;
+        assert_eq!(info.version, UNDO_INFO_VERSION);
+        assert!(info.backup_hashes.is_empty());
+        assert!(info.patterns.is_empty());
+        assert!(info.config.is_none());
+
+        fs::write(
+            &path,
+            r#"{"version":99,"timestamp":1,"description":"Future","backups":[],"total_size":0,"file_count":0,"dry_run":false}"#,
+        )?;
+        let err = UndoInfo::load(&path).unwrap_err();
+        assert!(err.to_string().contains("undo format version 99"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_patterns_and_settings_round_trip() -> SearchResult<()> {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("1.json");
+        let pattern = ReplacementPattern {
+            definition: PatternDefinition::new(
+                r"\bfoo\d+".to_string(),
+                true,
+                WordBoundaryMode::WholeWords,
+            ),
+            replacement_text: "bar".to_string(),
+            preserve_case: true,
+        };
+        let config = ReplacementConfig {
+            patterns: vec![pattern.clone()],
+            max_replacements_per_file: Some(3),
+            first_match_per_line: true,
+            undo_dir: temp.path().join("undo"),
+            ..ReplacementConfig::default()
+        };
+        let info = UndoInfo {
+            version: UNDO_INFO_VERSION,
+            timestamp: 1,
+            description: "New".to_string(),
+            backups: Vec::new(),
+            backup_hashes: Vec::new(),
+            total_size: 0,
+            file_count: 0,
+            dry_run: false,
+            file_diffs: Vec::new(),
+            renames: Vec::new(),
+            patterns: vec![pattern],
+            config: Some(config.settings()),
+            backup_root: None,
+            backup_root_in_workspace: false,
+        };
+        fs::write(&path, serde_json::to_string_pretty(&info)?)?;
+
+        let loaded = UndoInfo::load(&path)?;
+        assert_eq!(loaded.patterns, config.patterns);
+        let settings = loaded.config.clone().expect("settings recorded");
+        assert_eq!(settings, config.settings());
+        assert!(settings.patterns.is_empty());
+        assert_eq!(settings.undo_dir, PathBuf::new());
+        assert_eq!(settings.max_replacements_per_file, Some(3));
+        assert_eq!(loaded.summary().patterns, config.patterns);
+        Ok(())
+    }
+
     #[test]
     fn test_undo_info_serialization() -> SearchResult<()> {
         let temp = TempDir::new().unwrap();
@@ -230,6 +519,13 @@ mod tests {
             file_count: 1,
             dry_run: false,
             file_diffs: vec![],
+            renames: Vec::new(),
+            version: UNDO_INFO_VERSION,
+            backup_hashes: Vec::new(),
+            patterns: Vec::new(),
+            config: None,
+            backup_root: None,
+            backup_root_in_workspace: false,
         };
 
         // Test serialization/deserialization
@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, Serializer};
 /// This module implements search result types, demonstrating key differences between
 /// Rust's ownership system and .NET's reference types.
 ///
@@ -87,10 +87,22 @@ use serde::{Deserialize, Serialize};
 /// The types in this module use Rust's ownership system to provide memory safety
 /// and thread safety guarantees at compile time, preventing common issues that
 /// can occur in .NET applications.
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::encoding::LineEnding;
+
+pub mod export;
+mod json;
+pub mod report;
+pub mod sarif;
+
+pub use json::to_json_lines;
 
 /// Represents a single match in a file
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct Match {
     /// The line number where the match was found
     pub line_number: usize,
@@ -100,23 +112,142 @@ pub struct Match {
     pub start: usize,
     /// The end position of the match within the line
     pub end: usize,
+    /// 1-based column (in characters) where the match starts
+    #[serde(default)]
+    pub column_start: usize,
+    /// 1-based column (in characters) just past the end of the match
+    #[serde(default)]
+    pub column_end: usize,
+    /// Absolute byte offset of the match within the file
+    #[serde(default)]
+    pub byte_offset: usize,
     /// Lines before the match for context
     pub context_before: Vec<(usize, String)>,
     /// Lines after the match for context
     pub context_after: Vec<(usize, String)>,
+    /// Index of the pattern that produced this match (0 for inverted matches)
+    #[serde(default)]
+    pub pattern_index: usize,
+    /// Name of the pattern that produced this match, if it has one
+    #[serde(default)]
+    pub pattern_name: Option<String>,
+    /// Capture group spans as `(name, start, end)`, relative to the line like
+    /// `start` and `end`. Only set when capture extraction was requested and the
+    /// pattern is a regex with groups. Named groups appear under both their
+    /// number and their name.
+    #[serde(default)]
+    pub captures: Option<Vec<(String, usize, usize)>>,
+}
+
+impl Match {
+    /// Returns the text of a capture group by name or number (`"0"` is the whole match)
+    pub fn capture(&self, group: &str) -> Option<&str> {
+        if group == "0" {
+            return self.line_content.get(self.start..self.end);
+        }
+        self.captures
+            .as_ref()?
+            .iter()
+            .find(|(name, _, _)| name == group)
+            .and_then(|&(_, start, end)| self.line_content.get(start..end))
+    }
+
+    /// Expands a replacement template such as `$1`, `${name}` or `$0` for this match.
+    ///
+    /// `$$` produces a literal `$`. Groups that did not participate in the match,
+    /// or that do not exist (e.g. for literal patterns), expand to nothing.
+    pub fn expand_template(&self, template: &str) -> String {
+        let mut output = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(dollar) = rest.find('$') {
+            output.push_str(&rest[..dollar]);
+            rest = &rest[dollar + 1..];
+
+            if let Some(stripped) = rest.strip_prefix('$') {
+                output.push('$');
+                rest = stripped;
+                continue;
+            }
+
+            let (group, consumed) = if let Some(braced) = rest.strip_prefix('{') {
+                match braced.find('}') {
+                    Some(close) => (&braced[..close], close + 2),
+                    None => ("", 0),
+                }
+            } else {
+                let len = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                (&rest[..len], len)
+            };
+
+            if consumed == 0 {
+                // Not a group reference, keep the `$` as-is
+                output.push('$');
+                continue;
+            }
+            output.push_str(self.capture(group).unwrap_or(""));
+            rest = &rest[consumed..];
+        }
+        output.push_str(rest);
+        output
+    }
 }
 
 /// Represents all matches found in a single file
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct FileResult {
-    /// The path to the file
+    /// The path to the file (serialized as a string, lossily if it isn't UTF-8)
+    #[serde(serialize_with = "serialize_path_lossy")]
     pub path: PathBuf,
     /// All matches found in the file
     pub matches: Vec<Match>,
+    /// The file's line endings, if it was read for this search (results
+    /// taken from the incremental cache don't have them)
+    #[serde(default)]
+    pub line_ending: Option<LineEnding>,
+}
+
+impl FileResult {
+    /// Approximate number of bytes this result takes up, counting the strings
+    /// and vectors it owns
+    pub fn heap_size(&self) -> usize {
+        let lines = |lines: &[(usize, String)]| -> usize {
+            lines
+                .iter()
+                .map(|(_, line)| std::mem::size_of::<(usize, String)>() + line.len())
+                .sum()
+        };
+        let matches: usize = self
+            .matches
+            .iter()
+            .map(|m| {
+                std::mem::size_of::<Match>()
+                    + m.line_content.len()
+                    + lines(&m.context_before)
+                    + lines(&m.context_after)
+                    + m.pattern_name.as_ref().map_or(0, String::len)
+                    + m.captures.as_ref().map_or(0, |captures| {
+                        captures
+                            .iter()
+                            .map(|(name, _, _)| {
+                                std::mem::size_of::<(String, usize, usize)>() + name.len()
+                            })
+                            .sum()
+                    })
+            })
+            .sum();
+        std::mem::size_of::<Self>() + self.path.as_os_str().len() + matches
+    }
 }
 
 /// Represents the complete search results
-#[derive(Debug, Clone, Default)]
+///
+/// Results serialize with snake_case field names and paths as strings, so they
+/// can be sent over the wire as-is.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub struct SearchResult {
     /// Results per file
     pub file_results: Vec<FileResult>,
@@ -126,6 +257,101 @@ pub struct SearchResult {
     pub files_searched: usize,
     /// Total number of files with matches
     pub files_with_matches: usize,
+    /// Whether the search stopped early because the total match limit was reached
+    pub limit_reached: bool,
+    /// Whether the search was cancelled before all files were searched
+    pub cancelled: bool,
+    /// Whether the memory budget ran out, so some files are counted in the
+    /// totals but missing from `file_results`
+    #[serde(default)]
+    pub results_truncated: bool,
+    /// Timings and counters collected by the engine
+    #[serde(default)]
+    pub stats: SearchStats,
+    /// Number of files skipped because they exceeded the maximum file size
+    #[deprecated(note = "use `stats.files_skipped_too_large`")]
+    #[serde(default)]
+    pub files_skipped_for_size: usize,
+    /// Wall-clock time the search took
+    #[deprecated(note = "use `stats.elapsed`")]
+    #[serde(default)]
+    pub elapsed: Duration,
+    /// The git ref matches were restricted to changes against, if any
+    #[serde(default)]
+    pub diff_base: Option<String>,
+}
+
+/// Timing and throughput statistics for a search.
+///
+/// The engine always collects these; each is a counter or an `Instant` taken
+/// around a phase, so they cost next to nothing.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchStats {
+    /// Wall-clock time of the whole search
+    pub elapsed: Duration,
+    /// Time spent walking the roots and filtering the files to search
+    pub walk_time: Duration,
+    /// Time spent reading and matching files
+    pub match_time: Duration,
+    /// Time spent saving the incremental cache
+    pub cache_save_time: Duration,
+    /// Longest time spent reading and matching a single file
+    pub slowest_file_time: Duration,
+    /// Files whose contents were searched or whose results came from the
+    /// incremental cache
+    pub files_scanned: usize,
+    /// Files left out by ignore patterns, include globs or the extension
+    /// and type filters
+    pub files_skipped_ignored: usize,
+    /// Files left out because they look binary
+    pub files_skipped_binary: usize,
+    /// Files left out because they exceeded the maximum file size
+    pub files_skipped_too_large: usize,
+    /// Files left out by the modification time or size range filters
+    pub files_skipped_filtered: usize,
+    /// Files left out because they looked minified (`skip_minified`)
+    pub files_skipped_minified: usize,
+    /// Files left out because the same file was already reached through
+    /// another hard link or symlink
+    pub files_skipped_duplicate: usize,
+    /// Bytes read from the files that were searched
+    pub bytes_searched: u64,
+    /// Files whose results were reused from the incremental cache
+    pub cache_hits: usize,
+    /// Files the incremental cache couldn't answer for, so they were searched
+    pub cache_misses: usize,
+}
+
+impl SearchStats {
+    /// Adds another search's statistics to these
+    pub fn merge(&mut self, other: &SearchStats) {
+        self.elapsed += other.elapsed;
+        self.walk_time += other.walk_time;
+        self.match_time += other.match_time;
+        self.cache_save_time += other.cache_save_time;
+        self.slowest_file_time = self.slowest_file_time.max(other.slowest_file_time);
+        self.files_scanned += other.files_scanned;
+        self.files_skipped_ignored += other.files_skipped_ignored;
+        self.files_skipped_binary += other.files_skipped_binary;
+        self.files_skipped_too_large += other.files_skipped_too_large;
+        self.files_skipped_filtered += other.files_skipped_filtered;
+        self.files_skipped_minified += other.files_skipped_minified;
+        self.files_skipped_duplicate += other.files_skipped_duplicate;
+        self.bytes_searched += other.bytes_searched;
+        self.cache_hits += other.cache_hits;
+        self.cache_misses += other.cache_misses;
+    }
+
+    /// Bytes searched per second of wall-clock time
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes_searched as f64 / secs
+        } else {
+            0.0
+        }
+    }
 }
 
 impl SearchResult {
@@ -144,18 +370,180 @@ impl SearchResult {
         self.file_results.push(file_result);
     }
 
+    /// Keeps only the first `max_total` matches, dropping the rest.
+    ///
+    /// Sets `limit_reached` if the result holds at least `max_total` matches.
+    pub fn truncate_matches(&mut self, max_total: usize) {
+        if self.total_matches < max_total {
+            return;
+        }
+        self.limit_reached = true;
+
+        let mut remaining = max_total;
+        for file_result in &mut self.file_results {
+            file_result.matches.truncate(remaining);
+            remaining -= file_result.matches.len();
+        }
+        self.file_results.retain(|fr| !fr.matches.is_empty());
+
+        self.total_matches = max_total;
+        self.files_with_matches = self.file_results.len();
+    }
+
+    /// Counts matches per pattern, keyed by pattern index
+    pub fn pattern_counts(&self) -> BTreeMap<usize, usize> {
+        let mut counts = BTreeMap::new();
+        for m in self.file_results.iter().flat_map(|fr| &fr.matches) {
+            *counts.entry(m.pattern_index).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Rolls match counts up to directories `depth` levels deep, busiest first.
+    ///
+    /// Ties are ordered by path. See [`summarize_by_directory`] for the number
+    /// of matching files per directory as well.
+    ///
+    /// [`summarize_by_directory`]: SearchResult::summarize_by_directory
+    pub fn aggregate_by_directory(&self, depth: usize) -> Vec<(PathBuf, usize)> {
+        self.summarize_by_directory(depth)
+            .into_iter()
+            .map(|summary| (summary.path, summary.total_matches))
+            .collect()
+    }
+
+    /// Groups files with matches by their directory truncated to `depth`
+    /// components, busiest first.
+    ///
+    /// Files directly under the search root, and everything when `depth` is 0,
+    /// are grouped under `.`; a leading `./` is ignored. Depth is counted on the
+    /// paths as they are; see [`summarize_by_directory_in`] to count it below
+    /// the search roots.
+    ///
+    /// [`summarize_by_directory_in`]: SearchResult::summarize_by_directory_in
+    pub fn summarize_by_directory(&self, depth: usize) -> Vec<DirectorySummary> {
+        self.summarize_by_directory_in(&[], depth)
+    }
+
+    /// Like [`summarize_by_directory`], but `depth` counts directories below
+    /// the deepest of `roots` that contains each file, so `-d /srv/app` with
+    /// depth 1 groups by `/srv/app/src` rather than `/srv`. Files directly
+    /// under a root are grouped under the root itself.
+    ///
+    /// [`summarize_by_directory`]: SearchResult::summarize_by_directory
+    pub fn summarize_by_directory_in(
+        &self,
+        roots: &[PathBuf],
+        depth: usize,
+    ) -> Vec<DirectorySummary> {
+        let mut by_directory: BTreeMap<PathBuf, DirectorySummary> = BTreeMap::new();
+        for file_result in self.file_results.iter().filter(|fr| !fr.matches.is_empty()) {
+            let directory = directory_at_depth(&file_result.path, roots, depth);
+            let summary =
+                by_directory
+                    .entry(directory.clone())
+                    .or_insert_with(|| DirectorySummary {
+                        path: directory,
+                        files_with_matches: 0,
+                        total_matches: 0,
+                    });
+            summary.files_with_matches += 1;
+            summary.total_matches += file_result.matches.len();
+        }
+
+        // The sort is stable, so ties stay in path order
+        let mut summaries: Vec<DirectorySummary> = by_directory.into_values().collect();
+        summaries.sort_by_key(|summary| std::cmp::Reverse(summary.total_matches));
+        summaries
+    }
+
+    /// Returns up to `limit` files with the most matches, busiest first, with
+    /// ties ordered by path
+    pub fn top_files(&self, limit: usize) -> Vec<(&Path, usize)> {
+        let mut files: Vec<(&Path, usize)> = self
+            .file_results
+            .iter()
+            .filter(|fr| !fr.matches.is_empty())
+            .map(|fr| (fr.path.as_path(), fr.matches.len()))
+            .collect();
+        files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        files.truncate(limit);
+        files
+    }
+
     /// Merges another search result into this one
     pub fn merge(&mut self, other: SearchResult) {
         self.total_matches += other.total_matches;
         self.files_searched += other.files_searched;
         self.files_with_matches += other.files_with_matches;
+        self.limit_reached |= other.limit_reached;
+        self.cancelled |= other.cancelled;
+        self.results_truncated |= other.results_truncated;
+        self.stats.merge(&other.stats);
         self.file_results.extend(other.file_results);
+        self.sync_deprecated_fields();
+    }
+
+    /// Copies `stats` into the deprecated fields that predate it
+    #[allow(deprecated)]
+    pub(crate) fn sync_deprecated_fields(&mut self) {
+        self.files_skipped_for_size = self.stats.files_skipped_too_large;
+        self.elapsed = self.stats.elapsed;
+    }
+}
+
+/// Match totals for one directory, from [`SearchResult::summarize_by_directory`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DirectorySummary {
+    /// The directory, truncated to the requested depth
+    #[serde(serialize_with = "serialize_path_lossy")]
+    pub path: PathBuf,
+    /// Number of files under the directory with at least one match
+    pub files_with_matches: usize,
+    /// Total number of matches under the directory
+    pub total_matches: usize,
+}
+
+/// Returns the directory containing `path`, keeping at most `depth` normal
+/// components below the deepest of `roots` containing it (or below any root
+/// or prefix of the path itself)
+fn directory_at_depth(path: &Path, roots: &[PathBuf], depth: usize) -> PathBuf {
+    // "." adds nothing over the leading `./` being skipped below
+    let root = roots
+        .iter()
+        .filter(|root| root.as_path() != Path::new(".") && path.starts_with(root))
+        .max_by_key(|root| root.components().count());
+    let (mut directory, relative) = match root {
+        Some(root) => (root.clone(), path.strip_prefix(root).unwrap_or(path)),
+        None => (PathBuf::new(), path),
+    };
+    let mut normal_components = 0;
+    for component in relative.parent().unwrap_or(Path::new("")).components() {
+        match component {
+            Component::CurDir => continue,
+            Component::Normal(_) if normal_components == depth => break,
+            Component::Normal(_) => normal_components += 1,
+            _ => {}
+        }
+        directory.push(component);
+    }
+    if directory.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        directory
     }
 }
 
+/// Serializes a path as a string, replacing anything that isn't valid UTF-8
+fn serialize_path_lossy<S: Serializer>(path: &Path, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&path.to_string_lossy())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::matcher::{PatternDefinition, PatternMatcher, WordBoundaryMode};
+    use std::path::Path;
 
     #[test]
     fn test_match_creation() {
@@ -164,8 +552,14 @@ mod tests {
             line_content: "Hello, world!".to_string(),
             start: 0,
             end: 5,
+            column_start: 1,
+            column_end: 6,
+            byte_offset: 0,
             context_before: vec![],
             context_after: vec![],
+            pattern_index: 0,
+            pattern_name: None,
+            captures: None,
         };
 
         assert_eq!(m.line_number, 42);
@@ -183,22 +577,35 @@ mod tests {
                 line_content: "Hello".to_string(),
                 start: 0,
                 end: 5,
+                column_start: 1,
+                column_end: 6,
+                byte_offset: 0,
                 context_before: vec![],
                 context_after: vec![],
+                pattern_index: 0,
+                pattern_name: None,
+                captures: None,
             },
             Match {
                 line_number: 2,
                 line_content: "World Hello".to_string(),
                 start: 6,
                 end: 11,
+                column_start: 7,
+                column_end: 12,
+                byte_offset: 6,
                 context_before: vec![],
                 context_after: vec![],
+                pattern_index: 0,
+                pattern_name: None,
+                captures: None,
             },
         ];
 
         let file_result = FileResult {
             path: PathBuf::from("test.txt"),
             matches,
+            line_ending: None,
         };
 
         assert_eq!(file_result.path, PathBuf::from("test.txt"));
@@ -229,18 +636,31 @@ mod tests {
                     line_content: "Hello".to_string(),
                     start: 0,
                     end: 5,
+                    column_start: 1,
+                    column_end: 6,
+                    byte_offset: 0,
                     context_before: vec![],
                     context_after: vec![],
+                    pattern_index: 0,
+                    pattern_name: None,
+                    captures: None,
                 },
                 Match {
                     line_number: 2,
                     line_content: "Hello again".to_string(),
                     start: 0,
                     end: 5,
+                    column_start: 1,
+                    column_end: 6,
+                    byte_offset: 0,
                     context_before: vec![],
                     context_after: vec![],
+                    pattern_index: 0,
+                    pattern_name: None,
+                    captures: None,
                 },
             ],
+            line_ending: None,
         };
         result.add_file_result(file_result1);
 
@@ -252,6 +672,7 @@ mod tests {
         let file_result2 = FileResult {
             path: PathBuf::from("test2.txt"),
             matches: vec![],
+            line_ending: None,
         };
         result.add_file_result(file_result2);
 
@@ -260,6 +681,115 @@ mod tests {
         assert_eq!(result.files_with_matches, 1); // Unchanged
     }
 
+    #[test]
+    fn test_search_result_truncate_matches() {
+        let make_match = |line_number| Match {
+            line_number,
+            line_content: "Hello".to_string(),
+            start: 0,
+            end: 5,
+            column_start: 1,
+            column_end: 6,
+            byte_offset: 0,
+            context_before: vec![],
+            context_after: vec![],
+            pattern_index: 0,
+            pattern_name: None,
+            captures: None,
+        };
+
+        let mut result = SearchResult::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            result.add_file_result(FileResult {
+                path: PathBuf::from(name),
+                matches: vec![make_match(1), make_match(2)],
+                line_ending: None,
+            });
+        }
+
+        result.truncate_matches(10);
+        assert_eq!(result.total_matches, 6);
+        assert!(!result.limit_reached);
+
+        result.truncate_matches(3);
+        assert_eq!(result.total_matches, 3);
+        assert_eq!(result.files_with_matches, 2);
+        assert_eq!(result.file_results[1].matches.len(), 1);
+        assert!(result.limit_reached);
+    }
+
+    #[test]
+    fn test_match_expand_template() {
+        let line = "fn alpha(x) {}";
+        let matcher = PatternMatcher::from_definitions(vec![PatternDefinition::new(
+            r"fn (?P<name>\w+)\(".to_string(),
+            true,
+            WordBoundaryMode::None,
+        )])
+        .unwrap()
+        .with_captures(true);
+        let found = matcher.find_matches(line).remove(0);
+        let m = Match {
+            line_number: 1,
+            line_content: line.to_string(),
+            start: found.start,
+            end: found.end,
+            column_start: found.start + 1,
+            column_end: found.end + 1,
+            byte_offset: 0,
+            context_before: vec![],
+            context_after: vec![],
+            pattern_index: found.pattern_index,
+            pattern_name: None,
+            captures: found.captures,
+        };
+
+        assert_eq!(m.expand_template("$1"), "alpha");
+        assert_eq!(m.expand_template("${name}()"), "alpha()");
+        assert_eq!(m.expand_template("[$0] $$1 $2"), "[fn alpha(] $1 ");
+        assert_eq!(m.expand_template("cost: $"), "cost: $");
+
+        // Without captures only the whole match is available
+        let literal = Match {
+            captures: None,
+            ..m
+        };
+        assert_eq!(literal.expand_template("$0/$1"), "fn alpha(/");
+    }
+
+    #[test]
+    fn test_search_result_pattern_counts() {
+        let make_match = |pattern_index| Match {
+            line_number: 1,
+            line_content: "Hello".to_string(),
+            start: 0,
+            end: 5,
+            column_start: 1,
+            column_end: 6,
+            byte_offset: 0,
+            context_before: vec![],
+            context_after: vec![],
+            pattern_index,
+            pattern_name: None,
+            captures: None,
+        };
+
+        let mut result = SearchResult::new();
+        result.add_file_result(FileResult {
+            path: PathBuf::from("a.txt"),
+            matches: vec![make_match(0), make_match(2)],
+            line_ending: None,
+        });
+        result.add_file_result(FileResult {
+            path: PathBuf::from("b.txt"),
+            matches: vec![make_match(2)],
+            line_ending: None,
+        });
+
+        let counts: Vec<(usize, usize)> = result.pattern_counts().into_iter().collect();
+        assert_eq!(counts, vec![(0, 1), (2, 2)]);
+    }
+
     #[test]
     fn test_search_result_merge() {
         let mut result1 = SearchResult::new();
@@ -273,9 +803,16 @@ mod tests {
                 line_content: "Hello".to_string(),
                 start: 0,
                 end: 5,
+                column_start: 1,
+                column_end: 6,
+                byte_offset: 0,
                 context_before: vec![],
                 context_after: vec![],
+                pattern_index: 0,
+                pattern_name: None,
+                captures: None,
             }],
+            line_ending: None,
         });
 
         // Add results to second SearchResult
@@ -287,24 +824,38 @@ mod tests {
                     line_content: "World".to_string(),
                     start: 0,
                     end: 5,
+                    column_start: 1,
+                    column_end: 6,
+                    byte_offset: 0,
                     context_before: vec![],
                     context_after: vec![],
+                    pattern_index: 0,
+                    pattern_name: None,
+                    captures: None,
                 },
                 Match {
                     line_number: 2,
                     line_content: "Hello".to_string(),
                     start: 0,
                     end: 5,
+                    column_start: 1,
+                    column_end: 6,
+                    byte_offset: 0,
                     context_before: vec![],
                     context_after: vec![],
+                    pattern_index: 0,
+                    pattern_name: None,
+                    captures: None,
                 },
             ],
+            line_ending: None,
         });
 
         // Add a file without matches to result2
         result2.add_file_result(FileResult {
             path: PathBuf::from("test3.txt"),
             matches: vec![],
+            line_ending: None,
         });
 
         // Merge results
@@ -319,15 +870,15 @@ mod tests {
         assert!(result1
             .file_results
             .iter()
-            .any(|fr| fr.path == PathBuf::from("test1.txt")));
+            .any(|fr| fr.path == Path::new("test1.txt")));
         assert!(result1
             .file_results
             .iter()
-            .any(|fr| fr.path == PathBuf::from("test2.txt")));
+            .any(|fr| fr.path == Path::new("test2.txt")));
         assert!(result1
             .file_results
             .iter()
-            .any(|fr| fr.path == PathBuf::from("test3.txt")));
+            .any(|fr| fr.path == Path::new("test3.txt")));
     }
 
     #[test]
@@ -343,9 +894,16 @@ mod tests {
                 line_content: "Hello".to_string(),
                 start: 0,
                 end: 5,
+                column_start: 1,
+                column_end: 6,
+                byte_offset: 0,
                 context_before: vec![],
                 context_after: vec![],
+                pattern_index: 0,
+                pattern_name: None,
+                captures: None,
             }],
+            line_ending: None,
         });
 
         let initial_matches = result1.total_matches;
@@ -360,4 +918,138 @@ mod tests {
         assert_eq!(result1.files_searched, initial_files);
         assert_eq!(result1.files_with_matches, initial_files_with_matches);
     }
+
+    #[test]
+    fn test_aggregate_by_directory() {
+        let m = Match {
+            line_number: 1,
+            line_content: "Hello".to_string(),
+            start: 0,
+            end: 5,
+            column_start: 1,
+            column_end: 6,
+            byte_offset: 0,
+            context_before: vec![],
+            context_after: vec![],
+            pattern_index: 0,
+            pattern_name: None,
+            captures: None,
+        };
+
+        let mut result = SearchResult::new();
+        for (path, count) in [
+            ("./src/search/engine.rs", 4),
+            ("./src/search/matcher.rs", 1),
+            ("./src/results.rs", 2),
+            ("./src/cache/mod.rs", 5),
+            ("./README.md", 3),
+            ("./tests/empty.rs", 0),
+        ] {
+            result.add_file_result(FileResult {
+                path: PathBuf::from(path),
+                matches: vec![m.clone(); count],
+                line_ending: None,
+            });
+        }
+
+        assert_eq!(
+            result.aggregate_by_directory(1),
+            vec![(PathBuf::from("src"), 12), (PathBuf::from("."), 3)]
+        );
+        assert_eq!(
+            result.aggregate_by_directory(2),
+            vec![
+                (PathBuf::from("src/cache"), 5),
+                (PathBuf::from("src/search"), 5),
+                (PathBuf::from("."), 3),
+                (PathBuf::from("src"), 2),
+            ]
+        );
+        assert_eq!(
+            result.aggregate_by_directory(0),
+            vec![(PathBuf::from("."), 15)]
+        );
+
+        let summaries = result.summarize_by_directory(2);
+        assert_eq!(summaries[1].path, PathBuf::from("src/search"));
+        assert_eq!(summaries[1].files_with_matches, 2);
+
+        // Depth counts below the search root, however deep the root is
+        let mut nested = SearchResult::new();
+        for path in [
+            "/srv/app/src/lib.rs",
+            "/srv/app/src/cli/main.rs",
+            "/srv/app/build.rs",
+        ] {
+            nested.add_file_result(FileResult {
+                path: PathBuf::from(path),
+                matches: vec![m.clone()],
+                line_ending: None,
+            });
+        }
+        let paths = |summaries: Vec<DirectorySummary>| -> Vec<PathBuf> {
+            summaries.into_iter().map(|summary| summary.path).collect()
+        };
+        assert_eq!(
+            paths(nested.summarize_by_directory_in(&[PathBuf::from("/srv/app")], 1)),
+            [PathBuf::from("/srv/app/src"), PathBuf::from("/srv/app")]
+        );
+        assert_eq!(
+            paths(nested.summarize_by_directory_in(&[PathBuf::from("/srv/app")], 0)),
+            [PathBuf::from("/srv/app")]
+        );
+
+        assert_eq!(
+            result.top_files(2),
+            vec![
+                (Path::new("./src/cache/mod.rs"), 5),
+                (Path::new("./src/search/engine.rs"), 4),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_search_result_serde_round_trip() -> crate::errors::SearchResult<()> {
+        let line = "let naïve = \"日本語 TODO 🦀\";";
+        let start = line.find("TODO").unwrap();
+        let m = Match {
+            line_number: 7,
+            line_content: line.to_string(),
+            start,
+            end: start + 4,
+            column_start: line[..start].chars().count() + 1,
+            column_end: line[..start].chars().count() + 5,
+            byte_offset: 120 + start,
+            context_before: vec![(6, "// Ünïcödé context".to_string())],
+            context_after: Vec::new(),
+            pattern_index: 1,
+            pattern_name: Some("todo".to_string()),
+            captures: Some(vec![("0".to_string(), start, start + 4)]),
+        };
+
+        let mut result = SearchResult::new();
+        for path in [r"C:\Users\Zoë\src\main.rs", "src/ファイル.rs"] {
+            result.add_file_result(FileResult {
+                path: PathBuf::from(path),
+                matches: vec![m.clone()],
+                line_ending: None,
+            });
+        }
+        result.files_searched = 3;
+        result.stats.files_scanned = 3;
+
+        let json = serde_json::to_string(&result)?;
+        assert!(json.contains(r#""path":"C:\\Users\\Zoë\\src\\main.rs""#));
+        assert!(json.contains(r#""files_with_matches":2"#));
+        assert!(json.contains(r#""line_content":"let naïve"#));
+
+        let parsed: SearchResult = serde_json::from_str(&json)?;
+        assert_eq!(parsed, result);
+        assert_eq!(
+            parsed.file_results[0].path,
+            PathBuf::from(r"C:\Users\Zoë\src\main.rs")
+        );
+        assert_eq!(parsed.file_results[1].matches[0].capture("0"), Some("TODO"));
+        Ok(())
+    }
 }
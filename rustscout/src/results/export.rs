@@ -0,0 +1,171 @@
+//! Delimited-text export of matches for spreadsheets and other tabular tools.
+//!
+//! Each match becomes one record with the columns
+//! `path,line,column,pattern,match_text,line_content`. Fields containing the
+//! delimiter, a double quote, or a line break are quoted RFC 4180 style, so
+//! multi-line match content survives a round trip through a CSV reader.
+
+use std::borrow::Cow;
+use std::io::Write;
+
+use super::{Match, SearchResult};
+use crate::errors::SearchResult as Result;
+
+const HEADER: [&str; 6] = [
+    "path",
+    "line",
+    "column",
+    "pattern",
+    "match_text",
+    "line_content",
+];
+
+/// Writes a header row followed by one record per match, separated by
+/// `delimiter` (`b','` for CSV, `b'\t'` for TSV)
+pub fn write_csv(result: &SearchResult, writer: &mut impl Write, delimiter: u8) -> Result<()> {
+    write_record(writer, delimiter, HEADER.iter().map(|h| Cow::Borrowed(*h)))?;
+    write_csv_records(result, writer, delimiter)
+}
+
+/// Like [`write_csv`], without the header row
+///
+/// The `pattern` column holds the pattern's name when it has one, and its
+/// index otherwise. Paths that aren't valid UTF-8 are converted lossily.
+pub fn write_csv_records(
+    result: &SearchResult,
+    writer: &mut impl Write,
+    delimiter: u8,
+) -> Result<()> {
+    for file_result in &result.file_results {
+        let path = file_result.path.to_string_lossy();
+        for m in &file_result.matches {
+            let fields = [
+                path.clone(),
+                Cow::Owned(m.line_number.to_string()),
+                Cow::Owned(m.column_start.to_string()),
+                pattern_column(m),
+                Cow::Borrowed(m.line_content.get(m.start..m.end).unwrap_or_default()),
+                Cow::Borrowed(m.line_content.as_str()),
+            ];
+            write_record(writer, delimiter, fields.into_iter())?;
+        }
+    }
+    Ok(())
+}
+
+fn pattern_column(m: &Match) -> Cow<'_, str> {
+    match &m.pattern_name {
+        Some(name) => Cow::Borrowed(name.as_str()),
+        None => Cow::Owned(m.pattern_index.to_string()),
+    }
+}
+
+fn write_record<'a>(
+    writer: &mut impl Write,
+    delimiter: u8,
+    fields: impl Iterator<Item = Cow<'a, str>>,
+) -> Result<()> {
+    for (i, field) in fields.enumerate() {
+        if i > 0 {
+            writer.write_all(&[delimiter])?;
+        }
+        writer.write_all(quote_field(&field, delimiter).as_bytes())?;
+    }
+    writer.write_all(b"\n")?;
+    Ok(())
+}
+
+/// Quotes a field if it contains the delimiter, a quote, or a line break,
+/// doubling any embedded quotes
+fn quote_field(field: &str, delimiter: u8) -> Cow<'_, str> {
+    let needs_quotes = field
+        .bytes()
+        .any(|b| b == delimiter || b == b'"' || b == b'\n' || b == b'\r');
+    if needs_quotes {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::FileResult;
+    use std::path::PathBuf;
+
+    fn match_on(line: &str, start: usize, end: usize, name: Option<&str>) -> Match {
+        Match {
+            line_number: 4,
+            line_content: line.to_string(),
+            start,
+            end,
+            column_start: start + 1,
+            column_end: end + 1,
+            byte_offset: 0,
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+            pattern_index: 1,
+            pattern_name: name.map(str::to_string),
+            captures: None,
+        }
+    }
+
+    fn sample_result() -> SearchResult {
+        let mut result = SearchResult::new();
+        result.add_file_result(FileResult {
+            path: PathBuf::from("src/a,b.rs"),
+            matches: vec![
+                match_on(r#"let s = "TODO, later";"#, 9, 13, Some("todo")),
+                match_on("first\nsecond", 3, 8, None),
+            ],
+            line_ending: None,
+        });
+        result
+    }
+
+    #[test]
+    fn test_write_csv_quotes_fields() -> Result<()> {
+        let mut output = Vec::new();
+        write_csv(&sample_result(), &mut output, b',')?;
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "path,line,column,pattern,match_text,line_content\n\
+             \"src/a,b.rs\",4,10,todo,TODO,\"let s = \"\"TODO, later\"\";\"\n\
+             \"src/a,b.rs\",4,4,1,\"st\nse\",\"first\nsecond\"\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_tsv_without_header() -> Result<()> {
+        let mut output = Vec::new();
+        write_csv_records(&sample_result(), &mut output, b'\t')?;
+        let output = String::from_utf8(output).unwrap();
+        assert!(
+            output.starts_with("src/a,b.rs\t4\t10\ttodo\tTODO\t\"let s = \"\"TODO, later\"\";\"\n")
+        );
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_non_utf8_path_is_lossy() -> Result<()> {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let mut result = SearchResult::new();
+        result.add_file_result(FileResult {
+            path: PathBuf::from(OsStr::from_bytes(b"bad\xffname.txt")),
+            matches: vec![match_on("x", 0, 1, None)],
+            line_ending: None,
+        });
+        let mut output = Vec::new();
+        write_csv_records(&result, &mut output, b',')?;
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "bad\u{FFFD}name.txt,4,1,1,x,x\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,203 @@
+//! JSON Lines output, loosely following the shape of ripgrep's `--json`.
+//!
+//! Each file with matches becomes one `{"type":"file",...}` object, and a final
+//! `{"type":"summary",...}` object carries the totals, timing and
+//! [`SearchStats`] counters.
+
+use serde::Serialize;
+use std::borrow::Cow;
+use std::io::Write;
+
+use super::{FileResult, Match, SearchResult, SearchStats};
+use crate::errors::SearchResult as Result;
+
+/// One line of JSON output
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Record<'a> {
+    File {
+        path: Cow<'a, str>,
+        matches: Vec<MatchRecord<'a>>,
+    },
+    Summary {
+        files_searched: usize,
+        files_with_matches: usize,
+        total_matches: usize,
+        limit_reached: bool,
+        results_truncated: bool,
+        cancelled: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        diff_base: Option<&'a str>,
+        elapsed_ms: f64,
+        stats: StatsRecord,
+    },
+}
+
+/// [`SearchStats`] with durations flattened to milliseconds
+#[derive(Serialize)]
+struct StatsRecord {
+    files_scanned: usize,
+    files_skipped_ignored: usize,
+    files_skipped_binary: usize,
+    files_skipped_too_large: usize,
+    files_skipped_filtered: usize,
+    files_skipped_minified: usize,
+    files_skipped_duplicate: usize,
+    bytes_searched: u64,
+    cache_hits: usize,
+    cache_misses: usize,
+    walk_ms: f64,
+    match_ms: f64,
+    cache_save_ms: f64,
+    slowest_file_ms: f64,
+}
+
+impl From<&SearchStats> for StatsRecord {
+    fn from(stats: &SearchStats) -> Self {
+        Self {
+            files_scanned: stats.files_scanned,
+            files_skipped_ignored: stats.files_skipped_ignored,
+            files_skipped_binary: stats.files_skipped_binary,
+            files_skipped_too_large: stats.files_skipped_too_large,
+            files_skipped_filtered: stats.files_skipped_filtered,
+            files_skipped_minified: stats.files_skipped_minified,
+            files_skipped_duplicate: stats.files_skipped_duplicate,
+            bytes_searched: stats.bytes_searched,
+            cache_hits: stats.cache_hits,
+            cache_misses: stats.cache_misses,
+            walk_ms: stats.walk_time.as_secs_f64() * 1000.0,
+            match_ms: stats.match_time.as_secs_f64() * 1000.0,
+            cache_save_ms: stats.cache_save_time.as_secs_f64() * 1000.0,
+            slowest_file_ms: stats.slowest_file_time.as_secs_f64() * 1000.0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct MatchRecord<'a> {
+    line_number: usize,
+    column: usize,
+    byte_offset: usize,
+    match_text: &'a str,
+    line: &'a str,
+    context_before: &'a [(usize, String)],
+    context_after: &'a [(usize, String)],
+    pattern_index: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pattern_name: Option<&'a str>,
+}
+
+impl<'a> From<&'a Match> for MatchRecord<'a> {
+    fn from(m: &'a Match) -> Self {
+        Self {
+            line_number: m.line_number,
+            column: m.column_start,
+            byte_offset: m.byte_offset,
+            match_text: m.line_content.get(m.start..m.end).unwrap_or_default(),
+            line: &m.line_content,
+            context_before: &m.context_before,
+            context_after: &m.context_after,
+            pattern_index: m.pattern_index,
+            pattern_name: m.pattern_name.as_deref(),
+        }
+    }
+}
+
+impl<'a> From<&'a FileResult> for Record<'a> {
+    fn from(file_result: &'a FileResult) -> Self {
+        Record::File {
+            path: file_result.path.to_string_lossy(),
+            matches: file_result.matches.iter().map(MatchRecord::from).collect(),
+        }
+    }
+}
+
+/// Writes search results as JSON Lines: one object per file result, followed
+/// by a summary object
+pub fn to_json_lines(result: &SearchResult, writer: &mut impl Write) -> Result<()> {
+    for file_result in &result.file_results {
+        serde_json::to_writer(&mut *writer, &Record::from(file_result))?;
+        writeln!(writer)?;
+    }
+
+    let summary = Record::Summary {
+        files_searched: result.files_searched,
+        files_with_matches: result.files_with_matches,
+        total_matches: result.total_matches,
+        limit_reached: result.limit_reached,
+        results_truncated: result.results_truncated,
+        cancelled: result.cancelled,
+        diff_base: result.diff_base.as_deref(),
+        elapsed_ms: result.stats.elapsed.as_secs_f64() * 1000.0,
+        stats: StatsRecord::from(&result.stats),
+    };
+    serde_json::to_writer(&mut *writer, &summary)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn sample_result() -> SearchResult {
+        let mut result = SearchResult::new();
+        result.add_file_result(FileResult {
+            path: PathBuf::from("src/main.rs"),
+            matches: vec![Match {
+                line_number: 2,
+                line_content: "    // TODO: café".to_string(),
+                start: 7,
+                end: 11,
+                column_start: 8,
+                column_end: 12,
+                byte_offset: 19,
+                context_before: vec![(1, "fn main() {".to_string())],
+                context_after: vec![(3, "}".to_string())],
+                pattern_index: 0,
+                pattern_name: Some("todo".to_string()),
+                captures: None,
+            }],
+            line_ending: None,
+        });
+        result.stats.elapsed = Duration::from_millis(12);
+        result.stats.walk_time = Duration::from_millis(2);
+        result.stats.match_time = Duration::from_millis(9);
+        result.stats.files_scanned = 3;
+        result.stats.files_skipped_binary = 1;
+        result.stats.bytes_searched = 2048;
+        result
+    }
+
+    #[test]
+    fn test_json_lines_golden() -> Result<()> {
+        let mut output = Vec::new();
+        to_json_lines(&sample_result(), &mut output)?;
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            include_str!("../../tests/fixtures/search_results.jsonl")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_result_round_trip() -> Result<()> {
+        let result = sample_result();
+        let json = serde_json::to_string(&result)?;
+        let parsed: SearchResult = serde_json::from_str(&json)?;
+
+        assert_eq!(parsed.total_matches, result.total_matches);
+        assert_eq!(parsed.stats, result.stats);
+        assert_eq!(parsed.file_results[0].path, result.file_results[0].path);
+        let (m, original) = (
+            &parsed.file_results[0].matches[0],
+            &result.file_results[0].matches[0],
+        );
+        assert_eq!(m.line_content, original.line_content);
+        assert_eq!(m.context_before, original.context_before);
+        assert_eq!(m.pattern_name, original.pattern_name);
+        Ok(())
+    }
+}
@@ -0,0 +1,436 @@
+//! Markdown and HTML reports of search results, for sharing audits.
+//!
+//! A report opens with the patterns searched and the totals, lists the files
+//! with matches, then gives each file a section of code blocks showing the
+//! matched lines with their context. Markdown can't style text inside a
+//! fenced block, so matched lines there are flagged with a `>` gutter; the
+//! HTML report shows them in bold with the matched text highlighted.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use super::SearchResult;
+use crate::config::SearchConfig;
+use crate::errors::{unify_path, SearchResult as Result};
+use crate::workspace::detect_workspace_root;
+
+/// Output format of a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+/// One line of a code block
+struct ReportLine<'a> {
+    number: usize,
+    text: &'a str,
+    /// Byte ranges of the matches on this line; empty for context lines
+    matches: Vec<Range<usize>>,
+}
+
+/// A file section: its display path, anchor, match count and code blocks of
+/// contiguous lines
+struct FileSection<'a> {
+    path: String,
+    anchor: String,
+    match_count: usize,
+    blocks: Vec<Vec<ReportLine<'a>>>,
+}
+
+/// Renders a report of `result`. `config` is the configuration the search
+/// ran with; its patterns are listed in the report, and when its first root
+/// lies in a RustScout workspace, paths are shown relative to that workspace.
+pub fn render_report(result: &SearchResult, config: &SearchConfig, format: ReportFormat) -> String {
+    let patterns: Vec<String> = config
+        .pattern_definitions
+        .iter()
+        .map(|def| {
+            let kind = if def.is_regex { "regex" } else { "literal" };
+            match &def.name {
+                Some(name) => format!("{} ({}, {})", def.text, kind, name),
+                None => format!("{} ({})", def.text, kind),
+            }
+        })
+        .collect();
+    let sections = file_sections(result, workspace_root(config).as_deref());
+
+    match format {
+        ReportFormat::Markdown => render_markdown(result, &patterns, &sections),
+        ReportFormat::Html => render_html(result, &patterns, &sections),
+    }
+}
+
+/// Writes a report of `result` to `writer`, see [`render_report`]
+pub fn write_report(
+    result: &SearchResult,
+    config: &SearchConfig,
+    format: ReportFormat,
+    writer: &mut impl Write,
+) -> Result<()> {
+    writer.write_all(render_report(result, config, format).as_bytes())?;
+    Ok(())
+}
+
+/// The workspace containing the first search root, if there is one
+fn workspace_root(config: &SearchConfig) -> Option<PathBuf> {
+    let mut start = unify_path(config.first_root());
+    if start.is_file() {
+        start.pop();
+    }
+    let root = detect_workspace_root(&start).ok()?;
+    root.join(crate::workspace::WORKSPACE_DIR)
+        .is_dir()
+        .then_some(root)
+}
+
+fn file_sections<'a>(result: &'a SearchResult, workspace: Option<&Path>) -> Vec<FileSection<'a>> {
+    result
+        .file_results
+        .iter()
+        .filter(|fr| !fr.matches.is_empty())
+        .enumerate()
+        .map(|(index, file_result)| {
+            let path = workspace
+                .and_then(|root| {
+                    unify_path(&file_result.path)
+                        .strip_prefix(root)
+                        .ok()
+                        .map(Path::to_path_buf)
+                })
+                .unwrap_or_else(|| file_result.path.clone());
+
+            // Merge match and context lines by number, then split into runs
+            // of consecutive lines
+            let mut lines: BTreeMap<usize, ReportLine> = BTreeMap::new();
+            for m in &file_result.matches {
+                for (number, text) in m.context_before.iter().chain(&m.context_after) {
+                    lines.entry(*number).or_insert(ReportLine {
+                        number: *number,
+                        text,
+                        matches: Vec::new(),
+                    });
+                }
+                let line = lines.entry(m.line_number).or_insert(ReportLine {
+                    number: m.line_number,
+                    text: &m.line_content,
+                    matches: Vec::new(),
+                });
+                line.text = &m.line_content;
+                line.matches.push(m.start..m.end);
+            }
+
+            let mut blocks: Vec<Vec<ReportLine>> = Vec::new();
+            for line in lines.into_values() {
+                match blocks.last_mut() {
+                    Some(block)
+                        if block
+                            .last()
+                            .is_some_and(|prev| prev.number + 1 == line.number) =>
+                    {
+                        block.push(line)
+                    }
+                    _ => blocks.push(vec![line]),
+                }
+            }
+
+            FileSection {
+                path: path.to_string_lossy().replace('\\', "/"),
+                anchor: format!("file-{}", index + 1),
+                match_count: file_result.matches.len(),
+                blocks,
+            }
+        })
+        .collect()
+}
+
+fn render_markdown(result: &SearchResult, patterns: &[String], sections: &[FileSection]) -> String {
+    let mut out = String::new();
+    out.push_str("# RustScout Report\n\n");
+    out.push_str("**Patterns:**\n\n");
+    for pattern in patterns {
+        let _ = writeln!(out, "- {}", code_span(pattern));
+    }
+
+    out.push_str("\n| Files searched | Files with matches | Total matches |\n");
+    out.push_str("| ---: | ---: | ---: |\n");
+    let _ = writeln!(
+        out,
+        "| {} | {} | {} |",
+        result.files_searched, result.files_with_matches, result.total_matches
+    );
+
+    if sections.is_empty() {
+        out.push_str("\nNo matches found.\n");
+        return out;
+    }
+
+    out.push_str("\n| File | Matches |\n| --- | ---: |\n");
+    for section in sections {
+        let _ = writeln!(
+            out,
+            "| [{}](#{}) | {} |",
+            code_span(&section.path).replace('|', "\\|"),
+            section.anchor,
+            section.match_count
+        );
+    }
+
+    for section in sections {
+        let _ = write!(
+            out,
+            "\n<a id=\"{}\"></a>\n\n## {}\n\n{} {}\n",
+            section.anchor,
+            code_span(&section.path),
+            section.match_count,
+            if section.match_count == 1 {
+                "match"
+            } else {
+                "matches"
+            }
+        );
+        for block in &section.blocks {
+            let width = digits(block.last().map_or(0, |line| line.number));
+            let body: String = block
+                .iter()
+                .map(|line| {
+                    let marker = if line.matches.is_empty() { ' ' } else { '>' };
+                    format!("{} {:>width$} | {}\n", marker, line.number, line.text)
+                })
+                .collect();
+            let fence = "`".repeat(longest_backtick_run(&body).max(2) + 1);
+            let _ = write!(out, "\n{}text\n{}{}\n", fence, body, fence);
+        }
+    }
+    out
+}
+
+fn render_html(result: &SearchResult, patterns: &[String], sections: &[FileSection]) -> String {
+    let mut out = String::new();
+    out.push_str(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>RustScout Report</title>\n<style>\n\
+         body { font-family: sans-serif; margin: 2em; }\n\
+         table { border-collapse: collapse; margin-bottom: 1em; }\n\
+         th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; }\n\
+         pre { background: #f6f8fa; padding: 0.8em; overflow-x: auto; }\n\
+         .ln { color: #888; user-select: none; }\n\
+         mark { background: #ffe066; }\n\
+         </style>\n</head>\n<body>\n<h1>RustScout Report</h1>\n",
+    );
+
+    out.push_str("<p><strong>Patterns:</strong></p>\n<ul>\n");
+    for pattern in patterns {
+        let _ = writeln!(out, "<li><code>{}</code></li>", escape_html(pattern));
+    }
+    out.push_str("</ul>\n");
+
+    let _ = write!(
+        out,
+        "<table>\n<tr><th>Files searched</th><th>Files with matches</th><th>Total matches</th></tr>\n\
+         <tr><td>{}</td><td>{}</td><td>{}</td></tr>\n</table>\n",
+        result.files_searched, result.files_with_matches, result.total_matches
+    );
+
+    if sections.is_empty() {
+        out.push_str("<p>No matches found.</p>\n");
+    } else {
+        out.push_str("<table>\n<tr><th>File</th><th>Matches</th></tr>\n");
+        for section in sections {
+            let _ = writeln!(
+                out,
+                "<tr><td><a href=\"#{}\">{}</a></td><td>{}</td></tr>",
+                section.anchor,
+                escape_html(&section.path),
+                section.match_count
+            );
+        }
+        out.push_str("</table>\n");
+    }
+
+    for section in sections {
+        let _ = writeln!(
+            out,
+            "<section id=\"{0}\">\n<h2><a href=\"#{0}\">{1}</a></h2>\n<p>{2} {3}</p>",
+            section.anchor,
+            escape_html(&section.path),
+            section.match_count,
+            if section.match_count == 1 {
+                "match"
+            } else {
+                "matches"
+            }
+        );
+        for block in &section.blocks {
+            let width = digits(block.last().map_or(0, |line| line.number));
+            out.push_str("<pre>");
+            for line in block {
+                let number = format!("<span class=\"ln\">{:>width$}</span> ", line.number);
+                if line.matches.is_empty() {
+                    let _ = writeln!(out, "{}{}", number, escape_html(line.text));
+                } else {
+                    let _ = writeln!(
+                        out,
+                        "<strong>{}{}</strong>",
+                        number,
+                        highlight_html(line.text, &line.matches)
+                    );
+                }
+            }
+            out.push_str("</pre>\n");
+        }
+        out.push_str("</section>\n");
+    }
+
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// Escapes a line and wraps each match in `<mark>`, merging overlaps
+fn highlight_html(text: &str, matches: &[Range<usize>]) -> String {
+    let mut ranges = matches.to_vec();
+    ranges.sort_by_key(|range| range.start);
+
+    let mut out = String::new();
+    let mut last = 0;
+    for range in ranges {
+        let start = range.start.max(last);
+        let end = range.end.min(text.len());
+        let (Some(before), Some(matched)) = (text.get(last..start), text.get(start..end)) else {
+            continue;
+        };
+        if matched.is_empty() {
+            continue;
+        }
+        out.push_str(&escape_html(before));
+        let _ = write!(out, "<mark>{}</mark>", escape_html(matched));
+        last = end;
+    }
+    out.push_str(&escape_html(text.get(last..).unwrap_or_default()));
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Wraps text in a Markdown code span that survives embedded backticks
+fn code_span(text: &str) -> String {
+    let ticks = "`".repeat(longest_backtick_run(text) + 1);
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{} {} {}", ticks, text, ticks)
+    } else {
+        format!("{}{}{}", ticks, text, ticks)
+    }
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+fn digits(number: usize) -> usize {
+    number.to_string().len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{FileResult, Match};
+    use crate::search::matcher::WordBoundaryMode;
+    use tempfile::tempdir;
+
+    fn sample(root: &Path) -> (SearchResult, SearchConfig) {
+        let mut config =
+            SearchConfig::new_with_pattern(r"unwrap\(\)".to_string(), true, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+
+        let line = "let v = opt.unwrap(); // <checked>";
+        let mut result = SearchResult::new();
+        result.add_file_result(FileResult {
+            path: root.join("src").join("main.rs"),
+            matches: vec![Match {
+                line_number: 12,
+                line_content: line.to_string(),
+                start: 12,
+                end: 20,
+                column_start: 13,
+                column_end: 21,
+                byte_offset: 0,
+                context_before: vec![(11, "fn main() {".to_string())],
+                context_after: vec![(13, "}".to_string())],
+                pattern_index: 0,
+                pattern_name: None,
+                captures: None,
+            }],
+            line_ending: None,
+        });
+        (result, config)
+    }
+
+    #[test]
+    fn test_markdown_report() -> Result<()> {
+        let dir = tempdir()?;
+        std::fs::create_dir_all(dir.path().join(".rustscout"))?;
+        std::fs::create_dir_all(dir.path().join("src"))?;
+        std::fs::write(dir.path().join("src").join("main.rs"), "")?;
+        let (result, config) = sample(dir.path());
+
+        let report = render_report(&result, &config, ReportFormat::Markdown);
+
+        assert!(report.starts_with("# RustScout Report\n"));
+        assert!(report.contains("- `unwrap\\(\\) (regex)`\n"));
+        assert!(report.contains("| 1 | 1 | 1 |\n"));
+        // Paths are relative to the detected workspace
+        assert!(report.contains("| [`src/main.rs`](#file-1) | 1 |\n"));
+        assert!(report.contains("<a id=\"file-1\"></a>\n\n## `src/main.rs`\n"));
+        assert!(report.contains(
+            "```text\n  11 | fn main() {\n> 12 | let v = opt.unwrap(); // <checked>\n  13 | }\n```\n"
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_html_report_escapes_and_highlights() -> Result<()> {
+        let dir = tempdir()?;
+        let (result, config) = sample(dir.path());
+
+        let report = render_report(&result, &config, ReportFormat::Html);
+
+        assert!(report.starts_with("<!DOCTYPE html>"));
+        assert!(report.contains("<section id=\"file-1\">"));
+        assert!(report.contains(
+            "<strong><span class=\"ln\">12</span> let v = opt.<mark>unwrap()</mark>; // &lt;checked&gt;</strong>"
+        ));
+        // Without a workspace the path is kept as given
+        assert!(report.contains(&escape_html(
+            &dir.path()
+                .join("src")
+                .join("main.rs")
+                .to_string_lossy()
+                .replace('\\', "/")
+        )));
+        Ok(())
+    }
+
+    #[test]
+    fn test_code_span_with_backticks() {
+        assert_eq!(code_span("plain"), "`plain`");
+        assert_eq!(code_span("a`b"), "``a`b``");
+        assert_eq!(code_span("`x`"), "`` `x` ``");
+    }
+}
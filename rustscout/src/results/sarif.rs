@@ -0,0 +1,301 @@
+//! SARIF 2.1.0 output, so findings can be uploaded to code-scanning services
+//! such as GitHub's.
+//!
+//! Each search pattern becomes a rule and each match a result pointing at the
+//! matched region. Columns are reported in Unicode code points, which is what
+//! [`Match::column_start`] counts.
+
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+
+use super::{Match, SearchResult};
+use crate::errors::SearchResult as Result;
+use crate::search::matcher::PatternDefinition;
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+const SARIF_VERSION: &str = "2.1.0";
+
+/// Top-level SARIF document
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SarifLog {
+    #[serde(rename = "$schema")]
+    pub schema: String,
+    pub version: String,
+    pub runs: Vec<Run>,
+}
+
+/// A single invocation of the tool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Run {
+    pub tool: Tool,
+    pub column_kind: String,
+    pub results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Tool {
+    pub driver: Driver,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Driver {
+    pub name: String,
+    pub version: String,
+    pub information_uri: String,
+    pub rules: Vec<Rule>,
+}
+
+/// Rule metadata, one per search pattern
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rule {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    pub short_description: Message,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub text: String,
+}
+
+/// One finding, i.e. one match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SarifResult {
+    pub rule_id: String,
+    pub rule_index: usize,
+    pub level: String,
+    pub message: Message,
+    pub locations: Vec<Location>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Location {
+    pub physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhysicalLocation {
+    pub artifact_location: ArtifactLocation,
+    pub region: Region,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ArtifactLocation {
+    pub uri: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Region {
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_column: usize,
+    pub snippet: Message,
+}
+
+/// Builds a SARIF log from search results and the patterns that produced them
+pub fn to_sarif(result: &SearchResult, patterns: &[PatternDefinition]) -> SarifLog {
+    let rules: Vec<Rule> = patterns.iter().enumerate().map(rule).collect();
+
+    let results = result
+        .file_results
+        .iter()
+        .flat_map(|file_result| {
+            let uri = artifact_uri(&file_result.path);
+            let rules = &rules;
+            file_result
+                .matches
+                .iter()
+                .map(move |m| finding(m, &uri, rules))
+        })
+        .collect();
+
+    SarifLog {
+        schema: SARIF_SCHEMA.to_string(),
+        version: SARIF_VERSION.to_string(),
+        runs: vec![Run {
+            tool: Tool {
+                driver: Driver {
+                    name: "rustscout".to_string(),
+                    version: env!("CARGO_PKG_VERSION").to_string(),
+                    information_uri: env!("CARGO_PKG_REPOSITORY").to_string(),
+                    rules,
+                },
+            },
+            column_kind: "unicodeCodePoints".to_string(),
+            results,
+        }],
+    }
+}
+
+/// Writes search results as a pretty-printed SARIF log
+pub fn write_sarif(
+    result: &SearchResult,
+    patterns: &[PatternDefinition],
+    writer: &mut impl Write,
+) -> Result<()> {
+    serde_json::to_writer_pretty(&mut *writer, &to_sarif(result, patterns))?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// The SARIF rule id of the pattern at `index`: its name, or `pattern-<index>`
+pub(crate) fn rule_id(index: usize, name: Option<&str>) -> String {
+    match name {
+        Some(name) => name.to_string(),
+        None => format!("pattern-{}", index),
+    }
+}
+
+fn rule((index, pattern): (usize, &PatternDefinition)) -> Rule {
+    let kind = if pattern.is_regex { "regex" } else { "literal" };
+    Rule {
+        id: rule_id(index, pattern.name.as_deref()),
+        name: pattern.name.clone(),
+        short_description: Message {
+            text: format!("Matches {} pattern `{}`", kind, pattern.text),
+        },
+    }
+}
+
+fn finding(m: &Match, uri: &str, rules: &[Rule]) -> SarifResult {
+    let matched = m.line_content.get(m.start..m.end).unwrap_or_default();
+    SarifResult {
+        rule_id: rules
+            .get(m.pattern_index)
+            .map(|rule| rule.id.clone())
+            .unwrap_or_else(|| rule_id(m.pattern_index, None)),
+        rule_index: m.pattern_index,
+        level: "note".to_string(),
+        message: Message {
+            text: format!("Found `{}`", matched),
+        },
+        locations: vec![Location {
+            physical_location: PhysicalLocation {
+                artifact_location: ArtifactLocation {
+                    uri: uri.to_string(),
+                },
+                region: Region {
+                    start_line: m.line_number,
+                    start_column: m.column_start,
+                    end_column: m.column_end,
+                    snippet: Message {
+                        text: m.line_content.clone(),
+                    },
+                },
+            },
+        }],
+    }
+}
+
+/// Converts a path into a relative URI reference: forward slashes, no leading
+/// `./`, and anything outside the unreserved set percent-encoded
+fn artifact_uri(path: &Path) -> String {
+    let path = path.strip_prefix(".").unwrap_or(path);
+    let path = path.to_string_lossy().replace('\\', "/");
+    let mut uri = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::FileResult;
+    use crate::search::matcher::WordBoundaryMode;
+    use std::path::PathBuf;
+
+    fn sample() -> (SearchResult, Vec<PatternDefinition>) {
+        let mut todo = PatternDefinition::new("TODO".to_string(), false, WordBoundaryMode::None);
+        todo.name = Some("todo".to_string());
+        let fixme =
+            PatternDefinition::new(r"FIXME\(\w+\)".to_string(), true, WordBoundaryMode::None);
+
+        let make_match = |line_number, line: &str, start, end, pattern_index| Match {
+            line_number,
+            line_content: line.to_string(),
+            start,
+            end,
+            column_start: start + 1,
+            column_end: end + 1,
+            byte_offset: 0,
+            context_before: Vec::new(),
+            context_after: Vec::new(),
+            pattern_index,
+            pattern_name: None,
+            captures: None,
+        };
+
+        let mut result = SearchResult::new();
+        result.add_file_result(FileResult {
+            path: PathBuf::from("./src/my file.rs"),
+            matches: vec![
+                make_match(3, "// TODO: tidy up", 3, 7, 0),
+                make_match(9, "// FIXME(alice) later", 3, 15, 1),
+            ],
+            line_ending: None,
+        });
+        (result, vec![todo, fixme])
+    }
+
+    #[test]
+    fn test_sarif_golden() -> Result<()> {
+        let (result, patterns) = sample();
+        let mut output = Vec::new();
+        write_sarif(&result, &patterns, &mut output)?;
+        let output = String::from_utf8(output)
+            .unwrap()
+            .replace(env!("CARGO_PKG_VERSION"), "{version}");
+        assert_eq!(
+            output,
+            include_str!("../../tests/fixtures/search_results.sarif")
+        );
+
+        // The document must also read back into the same typed structure
+        let parsed: SarifLog = serde_json::from_str(&output)?;
+        assert_eq!(
+            parsed.runs[0].results,
+            to_sarif(&result, &patterns).runs[0].results
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sarif_rules_and_locations() {
+        let (result, patterns) = sample();
+        let log = to_sarif(&result, &patterns);
+        let run = &log.runs[0];
+
+        let ids: Vec<_> = run
+            .tool
+            .driver
+            .rules
+            .iter()
+            .map(|r| r.id.as_str())
+            .collect();
+        assert_eq!(ids, ["todo", "pattern-1"]);
+        assert_eq!(run.results[1].rule_id, "pattern-1");
+        assert_eq!(run.results[1].rule_index, 1);
+
+        let location = &run.results[0].locations[0].physical_location;
+        assert_eq!(location.artifact_location.uri, "src/my%20file.rs");
+        assert_eq!(location.region.start_line, 3);
+        assert_eq!(location.region.start_column, 4);
+        assert_eq!(location.region.end_column, 8);
+    }
+}
@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(feature = "archives")]
+use std::io::{BufReader, Read};
+
+#[cfg(feature = "archives")]
+use crate::errors::SearchResult;
+
+/// Separates an archive's path from a member's name in the paths reported for
+/// archive members, as in `artifact.zip!/logs/app.log`
+pub const MEMBER_SEPARATOR: &str = "!/";
+
+/// The archive formats whose members can be searched
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar,
+    /// A gzip-compressed tarball (`.tar.gz` or `.tgz`)
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Recognizes an archive by its file name, ignoring case
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else {
+            None
+        }
+    }
+}
+
+/// The path reported for `member` of `archive`, e.g. `artifact.zip!/logs/app.log`
+pub fn member_path(archive: &Path, member: &str) -> PathBuf {
+    let mut path = archive.as_os_str().to_owned();
+    path.push(MEMBER_SEPARATOR);
+    path.push(member);
+    PathBuf::from(path)
+}
+
+/// Splits a path built by [`member_path`] back into the archive's path and
+/// the member's name, or returns `None` for a path outside any archive.
+pub fn split_member_path(path: &Path) -> Option<(PathBuf, &str)> {
+    let text = path.to_str()?;
+    let (archive, member) = text.split_once(MEMBER_SEPARATOR)?;
+    let archive = PathBuf::from(archive);
+    ArchiveKind::from_path(&archive)?;
+    Some((archive, member))
+}
+
+/// Calls `visit` with the name, uncompressed size and contents of each
+/// regular file in the archive, in archive order.
+///
+/// Directories, links and other special entries are left out. Members that
+/// `visit` doesn't read from are skipped without being decompressed.
+#[cfg(feature = "archives")]
+pub fn for_each_member(
+    archive: &Path,
+    kind: ArchiveKind,
+    mut visit: impl FnMut(&str, u64, &mut dyn Read) -> SearchResult<()>,
+) -> SearchResult<()> {
+    let file = BufReader::new(std::fs::File::open(archive)?);
+    match kind {
+        ArchiveKind::Zip => {
+            let mut zip = zip::ZipArchive::new(file).map_err(std::io::Error::from)?;
+            for index in 0..zip.len() {
+                let mut member = zip.by_index(index).map_err(std::io::Error::from)?;
+                if member.is_dir() || member.is_symlink() {
+                    continue;
+                }
+                let name = member.name().to_string();
+                let size = member.size();
+                visit(&name, size, &mut member)?;
+            }
+            Ok(())
+        }
+        ArchiveKind::Tar => visit_tar(tar::Archive::new(file), visit),
+        ArchiveKind::TarGz => {
+            visit_tar(tar::Archive::new(flate2::read::GzDecoder::new(file)), visit)
+        }
+    }
+}
+
+#[cfg(feature = "archives")]
+fn visit_tar<R: Read>(
+    mut archive: tar::Archive<R>,
+    mut visit: impl FnMut(&str, u64, &mut dyn Read) -> SearchResult<()>,
+) -> SearchResult<()> {
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let size = entry.size();
+        visit(&name, size, &mut entry)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_archive_paths() {
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("ci/Artifact.ZIP")),
+            Some(ArchiveKind::Zip)
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("logs.tar.gz")),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("logs.tgz")),
+            Some(ArchiveKind::TarGz)
+        );
+        assert_eq!(
+            ArchiveKind::from_path(Path::new("logs.tar")),
+            Some(ArchiveKind::Tar)
+        );
+        assert_eq!(ArchiveKind::from_path(Path::new("notes.gz")), None);
+        assert_eq!(ArchiveKind::from_path(Path::new("zip")), None);
+
+        let path = member_path(Path::new("ci/artifact.zip"), "logs/app.log");
+        assert_eq!(path, Path::new("ci/artifact.zip!/logs/app.log"));
+        assert_eq!(
+            split_member_path(&path),
+            Some((PathBuf::from("ci/artifact.zip"), "logs/app.log"))
+        );
+        assert_eq!(split_member_path(Path::new("wow!/logs/app.log")), None);
+    }
+}
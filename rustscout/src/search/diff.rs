@@ -0,0 +1,210 @@
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::errors::{SearchError, SearchResult};
+use crate::results::FileResult;
+
+/// Lines added or changed in the working tree since a git ref, per file.
+///
+/// Built from `git diff -U0 <ref>`, so it covers both staged and unstaged
+/// changes to tracked files. Untracked files never appear in it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangedLines {
+    /// Added line ranges (1-based, inclusive), keyed by canonical path
+    files: HashMap<PathBuf, Vec<RangeInclusive<usize>>>,
+}
+
+impl ChangedLines {
+    /// Diffs the working tree of the repository containing `root` against
+    /// `base`.
+    ///
+    /// Fails if `root` is not inside a git repository or `base` is not a
+    /// valid ref.
+    pub fn from_git(root: &Path, base: &str) -> SearchResult<Self> {
+        // A file given as the search root can't serve as the working directory
+        let dir = if root.is_file() {
+            root.parent().unwrap_or(Path::new("."))
+        } else {
+            root
+        };
+        let dir = if dir.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            dir
+        };
+
+        let toplevel = run_git(dir, &["rev-parse", "--show-toplevel"])?;
+        let toplevel = PathBuf::from(toplevel.trim_end_matches(['\r', '\n']));
+        let diff = run_git(
+            &toplevel,
+            &[
+                "-c",
+                "core.quotePath=false",
+                "diff",
+                "-U0",
+                "--no-color",
+                "--no-ext-diff",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+                base,
+                "--",
+            ],
+        )?;
+
+        let toplevel = toplevel.canonicalize().unwrap_or(toplevel);
+        Ok(Self::parse(&diff, &toplevel))
+    }
+
+    /// Parses the output of `git diff -U0`, resolving the paths it names
+    /// against `toplevel`.
+    ///
+    /// Only the new side of each hunk is kept; hunks that only delete lines
+    /// add nothing, and deleted files are left out.
+    pub fn parse(diff: &str, toplevel: &Path) -> Self {
+        let mut files: HashMap<PathBuf, Vec<RangeInclusive<usize>>> = HashMap::new();
+        let mut current: Option<PathBuf> = None;
+        // Added lines can start with "+++" too, so only trust it in a header
+        let mut in_header = false;
+
+        for line in diff.lines() {
+            if line.starts_with("diff ") {
+                current = None;
+                in_header = true;
+            } else if let Some(target) = line.strip_prefix("+++ ").filter(|_| in_header) {
+                current = target.strip_prefix("b/").map(|path| toplevel.join(path));
+                if let Some(path) = &current {
+                    files.entry(path.clone()).or_default();
+                }
+            } else if let (Some(path), Some(range)) = (&current, parse_hunk_header(line)) {
+                in_header = false;
+                if let Some(range) = range {
+                    files.get_mut(path).expect("entry added above").push(range);
+                }
+            }
+        }
+
+        // Files whose hunks only deleted lines have nothing left to match
+        files.retain(|_, ranges| !ranges.is_empty());
+        Self { files }
+    }
+
+    /// Number of files with added lines
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Whether no file has added lines
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+
+    /// Returns the added line ranges for `path`, if it has any
+    pub fn ranges(&self, path: &Path) -> Option<&[RangeInclusive<usize>]> {
+        if let Some(ranges) = self.files.get(path) {
+            return Some(ranges);
+        }
+        let canonical = path.canonicalize().ok()?;
+        self.files.get(&canonical).map(Vec::as_slice)
+    }
+
+    /// Whether `path` has any added lines
+    pub fn touches(&self, path: &Path) -> bool {
+        self.ranges(path).is_some()
+    }
+
+    /// Drops the matches in `file_result` that are not on an added line, then
+    /// keeps at most `max_count` of the rest.
+    pub fn retain_changed(&self, file_result: &mut FileResult, max_count: Option<usize>) {
+        let ranges = self.ranges(&file_result.path).unwrap_or_default();
+        file_result
+            .matches
+            .retain(|m| ranges.iter().any(|range| range.contains(&m.line_number)));
+        if let Some(max_count) = max_count {
+            file_result.matches.truncate(max_count);
+        }
+    }
+}
+
+/// Parses a `@@ -a,b +c,d @@` hunk header into the range of lines it adds,
+/// `Some(None)` if it adds none, or `None` if `line` is not a hunk header.
+fn parse_hunk_header(line: &str) -> Option<Option<RangeInclusive<usize>>> {
+    let rest = line.strip_prefix("@@ -")?;
+    let (_, rest) = rest.split_once(" +")?;
+    let (new_side, _) = rest.split_once(" @@")?;
+    let (start, count) = match new_side.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (new_side.parse().ok()?, 1),
+    };
+    if count == 0 {
+        return Some(None);
+    }
+    Some(Some(start..=start + count - 1))
+}
+
+/// Runs git in `dir` and returns its standard output
+fn run_git(dir: &Path, args: &[&str]) -> SearchResult<String> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| SearchError::config_error(format!("Failed to run git: {}", e)))?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(SearchError::config_error(format!(
+            "git failed in {}: {}",
+            dir.display(),
+            stderr.trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_diff() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3 +3 @@ fn main() {
+-old
++new
+@@ -10,0 +11,3 @@
++one
++++ two
++three
+@@ -20,2 +22,0 @@
+-gone
+-gone
+diff --git a/only_deletes.rs b/only_deletes.rs
+--- a/only_deletes.rs
++++ b/only_deletes.rs
+@@ -1,2 +0,0 @@
+-a
+-b
+diff --git a/removed.rs b/removed.rs
+deleted file mode 100644
+--- a/removed.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-x
+";
+        let top = Path::new("/repo");
+        let changed = ChangedLines::parse(diff, top);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(
+            changed.ranges(&top.join("src/lib.rs")),
+            Some(&[3..=3, 11..=13][..])
+        );
+        assert!(!changed.touches(&top.join("only_deletes.rs")));
+        assert!(!changed.touches(&top.join("removed.rs")));
+    }
+}
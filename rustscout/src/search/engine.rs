@@ -1,66 +1,167 @@
 use ignore::WalkBuilder;
 use rayon::prelude::*;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::io::Read;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 use tracing::{debug, info, warn};
 
-use crate::cache::{create_detector, ChangeStatus, FileSignatureDetector, IncrementalCache};
-use crate::config::{EncodingMode, SearchConfig};
-use crate::errors::{SearchError, SearchResult};
-use crate::filters::{should_ignore, should_include_file};
+use crate::cache::{
+    create_detector, lock_path, pattern_fingerprint, CacheFormat, CacheSaveOptions, ChangeStatus,
+    FileCacheEntry, FileSignatureDetector, IncrementalCache,
+};
+use crate::config::{EncodingMode, OutputMode, Schedule, SearchConfig};
+use crate::errors::{PatternError, SearchError, SearchResult};
+#[cfg(feature = "archives")]
+use crate::filters::sniff_bytes;
+use crate::filters::{
+    has_excluded_extension, has_valid_type, is_likely_binary, is_replace_temp_file,
+    matches_ignore_patterns, matches_metadata, should_ignore, sniff_file, IncludeGlobs,
+    RustscoutIgnore, Sniffed, WalkIgnore,
+};
 use crate::metrics::MemoryMetrics;
 use crate::results::{FileResult, SearchResult as SearchOutput};
+use crate::search::archive::ArchiveKind;
+#[cfg(feature = "archives")]
+use crate::search::archive::{for_each_member, member_path};
+use crate::search::cancel::CancellationToken;
+use crate::search::diff::ChangedLines;
 use crate::search::matcher::PatternMatcher;
 use crate::search::processor::FileProcessor;
 
+/// Path reported for matches in text read from stdin
+pub const STDIN_PATH: &str = "<stdin>";
+
 /// Performs a concurrent search across files in a directory
+///
+/// If `config.stdin` is set, stdin is searched instead; see [`search_reader`].
+/// If `config.cancellation` is triggered, workers stop between files and the
+/// results gathered so far are returned with `cancelled` set.
 pub fn search(config: &SearchConfig) -> SearchResult<SearchOutput> {
-    let pattern_defs = config.get_pattern_definitions();
-    info!(
-        "Starting search with {} pattern definitions",
-        pattern_defs.len()
-    );
+    search_with_callback(config, |_| {})
+}
 
-    // Return early if no patterns
-    if pattern_defs.is_empty() {
-        debug!("No search patterns provided, returning empty result");
-        return Ok(SearchOutput::new());
+/// Performs a search like [`search`], calling `on_file` with each file result
+/// as soon as it is ready, so callers can show results while the search runs.
+///
+/// `on_file` is called from the worker threads, in no particular order; files
+/// whose results come from the incremental cache are reported first. It sees
+/// the same file results that are collected into the returned result, except
+/// that with `max_total_matches` set, results past the limit may still be
+/// reported before the returned result is trimmed. The incremental cache is
+/// saved once, after all files are processed.
+pub fn search_with_callback<F>(config: &SearchConfig, on_file: F) -> SearchResult<SearchOutput>
+where
+    F: Fn(&FileResult) + Sync,
+{
+    let emit = |file_result: FileResult| {
+        on_file(&file_result);
+        Some(file_result)
+    };
+    run_search(config, &AtomicBool::new(false), &emit)
+}
+
+/// Runs a search, handing each file result to `emit` as soon as it is ready.
+///
+/// `emit` returns the result if it should also be collected into the returned
+/// result, or `None` if it took ownership of it (as [`super::search_iter`]
+/// does). Setting `closed` stops the workers between files, like cancellation.
+pub(crate) fn run_search(
+    config: &SearchConfig,
+    closed: &AtomicBool,
+    emit: &(dyn Fn(FileResult) -> Option<FileResult> + Sync),
+) -> SearchResult<SearchOutput> {
+    let start = Instant::now();
+    if config.stdin {
+        let mut result = search_reader(config, std::io::stdin().lock())?;
+        result.file_results = std::mem::take(&mut result.file_results)
+            .into_iter()
+            .filter_map(emit)
+            .collect();
+        return Ok(result);
     }
 
-    let metrics = Arc::new(MemoryMetrics::new());
-    let matcher = PatternMatcher::with_metrics(pattern_defs, metrics.clone());
-    let processor = FileProcessor::new(
-        matcher,
-        config.context_before,
-        config.context_after,
-        config.encoding_mode,
-    );
+    if config.search_archives && !cfg!(feature = "archives") {
+        return Err(SearchError::config_error(
+            "searching archives needs rustscout built with the archives feature",
+        ));
+    }
+    if config.incremental
+        && config.cache_format == CacheFormat::Binary
+        && !cfg!(feature = "binary-cache")
+    {
+        return Err(SearchError::config_error(
+            "a binary cache needs rustscout built with the binary-cache feature",
+        ));
+    }
+
+    let Some((processor, metrics)) = build_processor(config)? else {
+        return Ok(SearchOutput::new());
+    };
+    let max_count = config.effective_max_count();
+
+    // Results past the memory budget are counted rather than kept
+    let budget = ResultBudget::new(config.memory_budget, &metrics);
+    let emit = &|file_result: FileResult| budget.admit(emit(file_result)?);
 
     // Collect all files to search
-    let mut files: Vec<PathBuf> = WalkBuilder::new(&config.root_path)
-        .hidden(false)
-        .ignore(true)
-        .git_ignore(true)
-        .build()
-        .filter_map(|entry| entry.ok())
-        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
-        .filter(|entry| {
-            let path = entry.path();
-            !should_ignore(path, &config.root_path, &config.ignore_patterns)
-                && should_include_file(
-                    path,
-                    &config.root_path,
-                    &config.file_extensions,
-                    &config.ignore_patterns,
-                )
-        })
-        .map(|entry| entry.into_path())
-        .collect();
+    let walk_start = Instant::now();
+    let CollectedFiles {
+        mut files,
+        maybe_linked,
+        archives,
+        skipped_for_size,
+        skipped_ignored,
+        skipped_binary,
+        skipped_filtered,
+    } = collect_files(config, closed)?;
+
+    // Only files the diff added lines to can have matches worth reporting
+    let changed = config
+        .diff_base
+        .as_deref()
+        .map(|base| ChangedLines::from_git(config.first_root(), base))
+        .transpose()?;
+    if let Some(changed) = &changed {
+        files.retain(|file| changed.touches(file));
+    }
 
     // Sort for consistent ordering
     files.sort();
 
+    // A file reachable through several links is searched once, under the
+    // first path in sorted order
+    let mut skipped_duplicate = 0;
+    if config.follow_symlinks || config.dedupe_hardlinks {
+        let mut seen = HashSet::new();
+        let before = files.len();
+        files.retain(|file| {
+            !maybe_linked.contains(file) || file_identity(file).is_none_or(|id| seen.insert(id))
+        });
+        skipped_duplicate = before - files.len();
+    }
+
+    if let Some(progress) = &config.progress {
+        progress.0.on_files_discovered(files.len());
+    }
+
     let mut result = SearchOutput::new();
+    result.stats.walk_time = walk_start.elapsed();
+    result.stats.files_skipped_ignored = skipped_ignored;
+    result.stats.files_skipped_binary = skipped_binary;
+    result.stats.files_skipped_too_large = skipped_for_size.len();
+    result.stats.files_skipped_filtered = skipped_filtered;
+    result.stats.files_skipped_duplicate = skipped_duplicate;
+    result.diff_base = config.diff_base.clone();
+    let restrict = |file_result: &mut FileResult| {
+        if let Some(changed) = &changed {
+            changed.retain_changed(file_result, max_count);
+        }
+    };
 
     // Handle incremental search if enabled
     if config.incremental {
@@ -68,115 +169,138 @@ pub fn search(config: &SearchConfig) -> SearchResult<SearchOutput> {
         let cache_path = config.get_cache_path();
         let mut cache = IncrementalCache::load_from(&cache_path)?;
 
+        // Results cached under a different match mode, per-file limit (e.g.
+        // stopping at the first match per file) or capture setting can't be reused
+        if cache.metadata.invert_match != config.invert_match
+            || cache.metadata.max_count_per_file != max_count
+            || cache.metadata.capture_groups != config.capture_groups
+        {
+            debug!("Match mode changed, discarding cached results");
+            cache.files.clear();
+            cache.metadata.invert_match = config.invert_match;
+            cache.metadata.max_count_per_file = max_count;
+            cache.metadata.capture_groups = config.capture_groups;
+        }
+
+        // Files that grew past the size limit are dropped from the cache
+        for path in &skipped_for_size {
+            cache.remove(&cache_key(path));
+        }
+
         // Detect changed files
-        let detector = create_detector(config.cache_strategy, config.root_path.clone());
+        let detector = create_detector(config.cache_strategy, config.first_root().to_path_buf());
         let changes = detector.detect_changes(&files)?;
 
+        // Cached matches are only reused for the same patterns, but every
+        // file's signature is kept whatever was searched for
+        let fingerprint = pattern_fingerprint(config);
+        cache.metadata.pattern_fingerprint = fingerprint.clone();
+        let keep_empty = config.output_mode == OutputMode::FilesWithoutMatch;
+
         let mut files_to_search = Vec::new();
         let mut cache_hits = 0;
         let mut total_files = 0;
 
         for file in files {
             total_files += 1;
+            let key = cache_key(&file);
 
-            // Check if file has changed
-            if let Some(change) = changes.iter().find(|c| c.path == file) {
-                match change.status {
-                    ChangeStatus::Added | ChangeStatus::Modified => {
-                        files_to_search.push(file);
-                    }
-                    ChangeStatus::Renamed(ref old_path) => {
-                        // If we have results for the old path, update the cache
-                        if let Some(entry) = cache.files.remove(old_path) {
-                            cache.files.insert(file.clone(), entry);
-                            cache_hits += 1;
-                        } else {
-                            files_to_search.push(file);
-                        }
-                    }
-                    ChangeStatus::Deleted => {
-                        cache.files.remove(&file);
-                    }
-                    ChangeStatus::Unchanged => {
-                        if let Some(entry) = cache.files.get_mut(&file) {
-                            if let Some(matches) = &entry.search_results {
-                                let matches = matches.clone();
-                                entry.mark_accessed();
-                                result.add_file_result(FileResult {
-                                    path: file,
-                                    matches,
-                                });
-                                cache_hits += 1;
-                            } else {
-                                files_to_search.push(file);
-                            }
-                        } else {
-                            files_to_search.push(file);
-                        }
+            match changes.iter().find(|c| c.path == file).map(|c| &c.status) {
+                Some(ChangeStatus::Renamed(old_path)) => {
+                    // Results for the old path carry over to the new one
+                    if let Some(entry) = cache.remove(&cache_key(old_path)) {
+                        cache.files.insert(key.clone(), entry);
                     }
                 }
-            } else {
-                // File not in changes list, treat as unchanged
-                if let Some(entry) = cache.files.get_mut(&file) {
-                    if let Some(matches) = &entry.search_results {
-                        let matches = matches.clone();
-                        entry.mark_accessed();
-                        result.add_file_result(FileResult {
-                            path: file,
-                            matches,
-                        });
-                        cache_hits += 1;
-                    } else {
-                        files_to_search.push(file);
-                    }
-                } else {
-                    files_to_search.push(file);
+                Some(ChangeStatus::Deleted) => {
+                    cache.remove(&key);
+                    continue;
+                }
+                _ => {}
+            }
+
+            // The detector only knows about changes since its own reference
+            // point (e.g. git's HEAD), not since the entry was cached, so the
+            // recorded signature has the final say
+            let cached = cache
+                .files
+                .get_mut(&key)
+                .filter(|entry| {
+                    FileSignatureDetector::compute_signature(&file)
+                        .is_ok_and(|signature| entry.signature == signature)
+                })
+                .and_then(|entry| {
+                    let matches = entry.results_for(&fingerprint)?.to_vec();
+                    entry.mark_accessed();
+                    Some(matches)
+                });
+            let Some(matches) = cached else {
+                files_to_search.push(file);
+                continue;
+            };
+            let mut file_result = FileResult {
+                path: file,
+                matches,
+                line_ending: None,
+            };
+            restrict(&mut file_result);
+            report_file_done(config, &file_result.path, file_result.matches.len());
+            if keep_empty || !file_result.matches.is_empty() {
+                if let Some(file_result) = emit(file_result) {
+                    result.add_file_result(file_result);
                 }
             }
+            cache_hits += 1;
         }
 
         // Update cache statistics
         cache.update_stats(cache_hits, total_files);
+        result.stats.cache_hits = cache_hits;
+        result.stats.cache_misses = files_to_search.len();
 
         // Process changed files in parallel
         if !files_to_search.is_empty() {
-            let chunk_size = (files_to_search.len() / rayon::current_num_threads()).max(1);
-            let new_results: Result<Vec<FileResult>, _> = files_to_search
-                .par_chunks(chunk_size)
-                .try_fold(Vec::new, |mut acc, chunk| {
-                    for path in chunk {
-                        // In FailFast mode, propagate any error
-                        if config.encoding_mode == EncodingMode::FailFast {
-                            let result = processor.process_file(path)?;
-                            if !result.matches.is_empty() {
-                                acc.push(result);
-                            }
-                        } else {
-                            // In Lossy mode, skip errors
-                            if let Ok(result) = processor.process_file(path) {
-                                if !result.matches.is_empty() {
-                                    acc.push(result);
-                                }
-                            }
-                        }
-                    }
-                    Ok::<_, SearchError>(acc)
-                })
-                .try_reduce(Vec::new, |mut a, mut b| {
-                    a.append(&mut b);
-                    Ok::<_, SearchError>(a)
-                });
-
-            // Handle results based on mode
-            let new_results = new_results?;
+            let match_start = Instant::now();
+            budget.settle(&mut result);
+            let found = AtomicUsize::new(result.total_matches);
+            // Every file searched in full is cached with all of its matches,
+            // before they are narrowed to changed lines
+            let searched = Mutex::new(Vec::new());
+            let record = |file_result: &mut FileResult| {
+                searched
+                    .lock()
+                    .unwrap()
+                    .push((file_result.path.clone(), file_result.matches.clone()));
+                restrict(file_result);
+            };
+            let new_results = process_files(
+                &files_to_search,
+                &processor,
+                config,
+                &found,
+                closed,
+                &record,
+                emit,
+            )?;
+            result.stats.match_time = match_start.elapsed();
 
             // Update cache with new results
-            for file_result in &new_results {
-                let signature = FileSignatureDetector::compute_signature(&file_result.path)?;
-                cache.files.insert(
-                    file_result.path.clone(),
-                    crate::cache::FileCacheEntry::new(signature),
-                );
+            for (path, matches) in searched.into_inner().unwrap() {
+                let signature = FileSignatureDetector::compute_signature(&path)?;
+                let key = cache_key(&path);
+                // Other patterns' results stay while the file is unchanged
+                if !cache
+                    .files
+                    .get(&key)
+                    .is_some_and(|entry| entry.signature == signature)
+                {
+                    cache
+                        .files
+                        .insert(key.clone(), FileCacheEntry::new(signature));
+                }
+                if let Some(entry) = cache.files.get_mut(&key) {
+                    entry.store_results(fingerprint.clone(), matches);
+                }
             }
 
             // Add new results
@@ -186,40 +310,22 @@ pub fn search(config: &SearchConfig) -> SearchResult<SearchOutput> {
         }
 
         // Save updated cache
-        if let Err(e) = cache.save_to(&cache_path) {
+        let save_start = Instant::now();
+        let options = CacheSaveOptions {
+            format: config.cache_format,
+            compress: config.use_compression,
+        };
+        if let Err(e) = cache.save_merged_to(&cache_path, options) {
             warn!("Failed to save cache: {}", e);
         }
+        result.stats.cache_save_time = save_start.elapsed();
     } else {
         // Non-incremental search: process all files in parallel
-        let chunk_size = (files.len() / rayon::current_num_threads()).max(1);
-        let file_results: Result<Vec<FileResult>, _> = files
-            .par_chunks(chunk_size)
-            .try_fold(Vec::new, |mut acc, chunk| {
-                for path in chunk {
-                    // In FailFast mode, propagate any error
-                    if config.encoding_mode == EncodingMode::FailFast {
-                        let result = processor.process_file(path)?;
-                        if !result.matches.is_empty() {
-                            acc.push(result);
-                        }
-                    } else {
-                        // In Lossy mode, skip errors
-                        if let Ok(result) = processor.process_file(path) {
-                            if !result.matches.is_empty() {
-                                acc.push(result);
-                            }
-                        }
-                    }
-                }
-                Ok::<_, SearchError>(acc)
-            })
-            .try_reduce(Vec::new, |mut a, mut b| {
-                a.append(&mut b);
-                Ok::<_, SearchError>(a)
-            });
-
-        // Handle results based on mode
-        let file_results = file_results?;
+        let match_start = Instant::now();
+        let found = AtomicUsize::new(0);
+        let file_results =
+            process_files(&files, &processor, config, &found, closed, &restrict, emit)?;
+        result.stats.match_time = match_start.elapsed();
 
         // Add results
         for file_result in file_results {
@@ -227,9 +333,46 @@ pub fn search(config: &SearchConfig) -> SearchResult<SearchOutput> {
         }
     }
 
+    // Archive members are never cached, and never in a diff
+    #[cfg(feature = "archives")]
+    if !archives.is_empty() && changed.is_none() {
+        budget.settle(&mut result);
+        let found = AtomicUsize::new(result.total_matches);
+        let (archive_results, skipped) =
+            process_archives(&archives, &processor, config, &found, closed, emit)?;
+        for file_result in archive_results {
+            result.add_file_result(file_result);
+        }
+        result.stats.files_skipped_ignored += skipped.ignored.into_inner();
+        result.stats.files_skipped_binary += skipped.binary.into_inner();
+        result.stats.files_skipped_too_large += skipped.too_large.into_inner();
+    }
+    #[cfg(not(feature = "archives"))]
+    let _ = archives;
+    budget.settle(&mut result);
+
+    result.cancelled = is_cancelled(config) || closed.load(Ordering::Relaxed);
+    if result.cancelled {
+        info!("Search cancelled, returning partial results");
+    }
+
+    // Workers stop cooperatively, so trim any overshoot past the global cap
+    if let Some(max_total) = config.max_total_matches {
+        result.truncate_matches(max_total);
+    }
+
     // Log memory usage statistics
     metrics.log_stats();
 
+    let processed = processor.metrics().get_stats();
+    result.stats.files_scanned =
+        (processed.small_files + processed.buffered_files + processed.mmap_files) as usize
+            + result.stats.cache_hits;
+    result.stats.bytes_searched = processed.bytes_processed;
+    result.stats.files_skipped_minified = processor.minified_files_skipped();
+    result.stats.slowest_file_time = processor.slowest_file_time();
+    result.stats.elapsed = start.elapsed();
+    result.sync_deprecated_fields();
     info!(
         "Search complete. Found {} matches in {} files",
         result.total_matches, result.files_with_matches
@@ -238,11 +381,693 @@ pub fn search(config: &SearchConfig) -> SearchResult<SearchOutput> {
     Ok(result)
 }
 
+/// Searches text read from `reader` (e.g. stdin) instead of files.
+///
+/// The result holds at most one `FileResult`, with the path [`STDIN_PATH`].
+/// The incremental cache is never used.
+pub fn search_reader(config: &SearchConfig, reader: impl Read) -> SearchResult<SearchOutput> {
+    let start = Instant::now();
+    let Some((processor, metrics)) = build_processor(config)? else {
+        return Ok(SearchOutput::new());
+    };
+
+    if let Some(progress) = &config.progress {
+        progress.0.on_files_discovered(1);
+    }
+    let path = Path::new(STDIN_PATH);
+    let file_result = processor.process_reader(reader, path)?;
+    let mut result = SearchOutput::new();
+    match file_result {
+        Some(file_result) => {
+            report_file_done(config, path, file_result.matches.len());
+            if config.output_mode == OutputMode::FilesWithoutMatch
+                || !file_result.matches.is_empty()
+            {
+                result.add_file_result(file_result);
+            }
+        }
+        None => report_file_done(config, path, 0),
+    }
+    if let Some(max_total) = config.max_total_matches {
+        result.truncate_matches(max_total);
+    }
+
+    metrics.log_stats();
+    result.stats.files_scanned = 1;
+    result.stats.bytes_searched = processor.metrics().get_stats().bytes_processed;
+    result.stats.elapsed = start.elapsed();
+    result.stats.match_time = result.stats.elapsed;
+    result.sync_deprecated_fields();
+    Ok(result)
+}
+
+/// Validates the configured patterns and builds the processor that applies
+/// them, or returns `None` if there are no patterns
+fn build_processor(
+    config: &SearchConfig,
+) -> SearchResult<Option<(FileProcessor, Arc<MemoryMetrics>)>> {
+    let pattern_defs = config.get_pattern_definitions();
+    info!(
+        "Starting search with {} pattern definitions",
+        pattern_defs.len()
+    );
+
+    // Return early if no patterns
+    if pattern_defs.is_empty() {
+        debug!("No search patterns provided, returning empty result");
+        return Ok(None);
+    }
+
+    for (index, def) in pattern_defs.iter().enumerate() {
+        match def.validate() {
+            Ok(()) => {}
+            Err(PatternError::Empty) => debug!("Pattern {} is empty and never matches", index + 1),
+            Err(e) if e.is_warning() => warn!("Pattern {}: {}", index + 1, e),
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    let metrics = Arc::new(MemoryMetrics::new());
+    let matcher = PatternMatcher::with_metrics(pattern_defs, metrics.clone())?
+        .with_captures(config.capture_groups);
+    let processor = FileProcessor::new(
+        matcher,
+        config.context_before,
+        config.context_after,
+        config.encoding_mode,
+    )
+    .with_invert_match(config.invert_match)
+    // With a diff base, the per-file limit applies to the matches left after
+    // filtering to changed lines
+    .with_max_count(
+        config
+            .effective_max_count()
+            .filter(|_| config.diff_base.is_none()),
+    )
+    .with_skip_minified(config.skip_minified)
+    .with_parallel_large_files(config.parallel_large_files);
+
+    Ok(Some((processor, metrics)))
+}
+
+/// Keeps the file results collected by a search within `memory_budget`.
+///
+/// Once the results kept so far outgrow the budget, every later file is only
+/// counted; [`ResultBudget::settle`] adds those counts to the totals.
+struct ResultBudget<'a> {
+    limit: Option<usize>,
+    metrics: &'a MemoryMetrics,
+    exceeded: AtomicBool,
+    dropped_files: AtomicUsize,
+    dropped_files_with_matches: AtomicUsize,
+    dropped_matches: AtomicUsize,
+}
+
+impl<'a> ResultBudget<'a> {
+    fn new(limit: Option<usize>, metrics: &'a MemoryMetrics) -> Self {
+        Self {
+            limit,
+            metrics,
+            exceeded: AtomicBool::new(false),
+            dropped_files: AtomicUsize::new(0),
+            dropped_files_with_matches: AtomicUsize::new(0),
+            dropped_matches: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns `file_result` if it fits in the budget, otherwise counts it
+    /// and drops it
+    fn admit(&self, file_result: FileResult) -> Option<FileResult> {
+        let Some(limit) = self.limit else {
+            return Some(file_result);
+        };
+        if !self.exceeded.load(Ordering::Relaxed) {
+            let kept = self.metrics.record_results(file_result.heap_size() as u64);
+            if kept <= limit as u64 {
+                return Some(file_result);
+            }
+            debug!("Memory budget of {} bytes exceeded, counting only", limit);
+            self.exceeded.store(true, Ordering::Relaxed);
+        }
+
+        self.dropped_files.fetch_add(1, Ordering::Relaxed);
+        if !file_result.matches.is_empty() {
+            self.dropped_files_with_matches
+                .fetch_add(1, Ordering::Relaxed);
+            self.dropped_matches
+                .fetch_add(file_result.matches.len(), Ordering::Relaxed);
+        }
+        None
+    }
+
+    /// Adds the files dropped since the last call to the totals of `result`
+    fn settle(&self, result: &mut SearchOutput) {
+        result.files_searched += self.dropped_files.swap(0, Ordering::Relaxed);
+        result.files_with_matches += self.dropped_files_with_matches.swap(0, Ordering::Relaxed);
+        result.total_matches += self.dropped_matches.swap(0, Ordering::Relaxed);
+        result.results_truncated |= self.exceeded.load(Ordering::Relaxed);
+    }
+}
+
+/// The files to search, and what [`collect_files`] left out on the way
+struct CollectedFiles {
+    files: Vec<PathBuf>,
+    /// Files that may be another path to one of `files`: those sharing their
+    /// inode number with another, or whose number the walk didn't give. Only
+    /// these are looked up again to find duplicates.
+    maybe_linked: HashSet<PathBuf>,
+    /// Archives whose members are searched, with `search_archives`
+    archives: Vec<PathBuf>,
+    skipped_for_size: Vec<PathBuf>,
+    skipped_ignored: usize,
+    skipped_binary: usize,
+    /// Left out by the modification time or size range filters
+    skipped_filtered: usize,
+}
+
+/// Collects the files to search, either from `explicit_paths` or by walking
+/// each root. The walk ends early if the search is cancelled or `closed`.
+fn collect_files(config: &SearchConfig, closed: &AtomicBool) -> SearchResult<CollectedFiles> {
+    let no_ignore = config.no_ignore;
+    let include_globs = IncludeGlobs::new(&config.include_globs)?;
+
+    // Each candidate is paired with the root its ignore patterns are relative
+    // to, and with its inode number if the walk read it
+    let mut candidates: Vec<(&Path, PathBuf, Option<u64>)> = match &config.explicit_paths {
+        Some(paths) => {
+            let mut existing = Vec::with_capacity(paths.len());
+            for path in paths {
+                if path.is_file() {
+                    existing.push((config.first_root(), path.clone(), None));
+                } else if config.strict_paths {
+                    return Err(SearchError::file_not_found(path));
+                } else {
+                    warn!("Skipping {}: not an existing file", path.display());
+                }
+            }
+            existing
+        }
+        None => config
+            .root_paths
+            .iter()
+            .flat_map(|root| {
+                walk_root(root, config, closed).map(move |(path, ino)| (root.as_path(), path, ino))
+            })
+            .collect(),
+    };
+
+    // Overlapping roots reach the same file twice; keep the first path seen
+    if config.root_paths.len() > 1 {
+        let mut seen = HashSet::new();
+        candidates.retain(|(_, path, _)| {
+            seen.insert(std::fs::canonicalize(path).unwrap_or_else(|_| path.clone()))
+        });
+    }
+
+    let mut archives = Vec::new();
+    let mut skipped_for_size = Vec::new();
+    let (mut skipped_ignored, mut skipped_binary, mut skipped_filtered) = (0, 0, 0);
+    let filters_on_type = config.file_extensions.is_some() || config.file_types.has_selection();
+    // Listed files skip the walk, so the ignore files it honours are checked here
+    let listed = config.explicit_paths.is_some() && !no_ignore;
+    let walk_ignore = listed.then(WalkIgnore::new);
+    let rustscout_ignore =
+        (listed && !config.no_rustscoutignore).then(|| RustscoutIgnore::new(config.first_root()));
+    let needs_metadata = config.max_file_size.is_some()
+        || config.modified_after.is_some()
+        || config.modified_before.is_some()
+        || config.size_range.is_some();
+    let files = candidates
+        .into_iter()
+        .filter(|(root, path, _)| {
+            let ignored = if no_ignore {
+                matches_ignore_patterns(path, root, &config.ignore_patterns)
+            } else {
+                should_ignore(path, root, &config.ignore_patterns)
+                    || walk_ignore
+                        .as_ref()
+                        .is_some_and(|ignore| ignore.is_ignored(path))
+                    || rustscout_ignore
+                        .as_ref()
+                        .is_some_and(|ignore| ignore.is_ignored(path, false))
+            };
+            // Archives are only left out by ignores; their members are
+            // filtered by name as they are read
+            if config.search_archives && ArchiveKind::from_path(path).is_some() {
+                if ignored {
+                    skipped_ignored += 1;
+                } else {
+                    archives.push(path.clone());
+                }
+                return false;
+            }
+            // An extensionless script is typed by its #! line; the same read
+            // also tells whether the file is binary
+            let sniffed = if config.sniff_shebang && filters_on_type && path.extension().is_none() {
+                sniff_file(path)
+            } else {
+                Sniffed::default()
+            };
+            let typed_path = sniffed.extension.map(|ext| path.with_extension(ext));
+
+            // Include globs narrow the set, then extensions and types
+            // filter it, then ignores remove from what is left
+            let wanted = include_globs.matches(path, root)
+                && has_valid_type(
+                    typed_path.as_deref().unwrap_or(path),
+                    &config.file_extensions,
+                    &config.file_types,
+                )
+                && !has_excluded_extension(path, &config.exclude_extensions);
+            if !wanted || ignored {
+                skipped_ignored += 1;
+                false
+            } else if is_likely_binary(path) || sniffed.binary {
+                skipped_binary += 1;
+                false
+            } else {
+                true
+            }
+        })
+        .map(|(_, path, ino)| (path, ino))
+        .filter(|(path, _)| {
+            // Excluded files never reach the incremental cache lookup
+            let Some(metadata) = needs_metadata
+                .then(|| std::fs::metadata(path).ok())
+                .flatten()
+            else {
+                return true;
+            };
+            if config.max_file_size.is_some_and(|max| metadata.len() > max) {
+                debug!("Skipping {}: exceeds maximum file size", path.display());
+                skipped_for_size.push(path.clone());
+                false
+            } else if !matches_metadata(
+                &metadata,
+                config.modified_after,
+                config.modified_before,
+                config.size_range,
+            ) {
+                skipped_filtered += 1;
+                false
+            } else {
+                true
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Two paths to the same file share its inode number, so a file with a
+    // number of its own needn't be looked up again to dedupe it
+    let mut inode_counts: HashMap<u64, usize> = HashMap::new();
+    for ino in files.iter().filter_map(|(_, ino)| *ino) {
+        *inode_counts.entry(ino).or_default() += 1;
+    }
+    let maybe_linked = files
+        .iter()
+        .filter(|(_, ino)| ino.is_none_or(|ino| inode_counts[&ino] > 1))
+        .map(|(path, _)| path.clone())
+        .collect();
+
+    Ok(CollectedFiles {
+        files: files.into_iter().map(|(path, _)| path).collect(),
+        maybe_linked,
+        archives,
+        skipped_for_size,
+        skipped_ignored,
+        skipped_binary,
+        skipped_filtered,
+    })
+}
+
+/// Walks a single root, yielding its files with their inode numbers where
+/// the directory listing gave them (never for symbolic links, whose number
+/// isn't their target's).
+///
+/// Hidden and `.git` directories are pruned here so the walker never descends
+/// into them. No more files are yielded once the search is cancelled or
+/// `closed` is set.
+fn walk_root<'a>(
+    root: &Path,
+    config: &'a SearchConfig,
+    closed: &'a AtomicBool,
+) -> impl Iterator<Item = (PathBuf, Option<u64>)> + 'a {
+    let no_ignore = config.no_ignore;
+    let rustscout_ignore =
+        (!no_ignore && !config.no_rustscoutignore).then(|| RustscoutIgnore::new(root));
+    // The incremental cache (and the temp file it is saved through and its
+    // lock file) may sit under a root, and is never searched itself
+    let cache_files = config.incremental.then(|| {
+        let cache_path = config.get_cache_path();
+        [
+            cache_path.with_extension("tmp"),
+            lock_path(&cache_path),
+            cache_path,
+        ]
+    });
+    WalkBuilder::new(root)
+        .hidden(!config.include_hidden)
+        .ignore(!no_ignore)
+        .git_ignore(!no_ignore)
+        .follow_links(config.follow_symlinks)
+        .max_depth(config.max_depth)
+        .filter_entry(move |entry| {
+            if entry.depth() == 0 {
+                return true;
+            }
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            (no_ignore || entry.file_name() != ".git")
+                && !is_replace_temp_file(entry.path())
+                && !cache_files
+                    .iter()
+                    .flatten()
+                    .any(|cache_file| is_same_file(entry.path(), cache_file))
+                && !rustscout_ignore
+                    .as_ref()
+                    .is_some_and(|ignore| ignore.is_ignored(entry.path(), is_dir))
+        })
+        .build()
+        .filter_map(|entry| {
+            // Symlink loops are reported here and skipped
+            entry.map_err(|e| debug!("Skipping entry: {}", e)).ok()
+        })
+        .take_while(|_| !(is_cancelled(config) || closed.load(Ordering::Relaxed)))
+        .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+        .map(|entry| {
+            #[cfg(unix)]
+            let ino = (!entry.path_is_symlink()).then(|| entry.ino()).flatten();
+            #[cfg(not(unix))]
+            let ino = None;
+            (entry.into_path(), ino)
+        })
+}
+
+/// Whether two paths name the same file, comparing names before resolving them
+fn is_same_file(path: &Path, other: &Path) -> bool {
+    path.file_name() == other.file_name()
+        && (path == other
+            || matches!(
+                (std::fs::canonicalize(path), std::fs::canonicalize(other)),
+                (Ok(a), Ok(b)) if a == b
+            ))
+}
+
+/// Whether the search's cancellation token has been triggered
+fn is_cancelled(config: &SearchConfig) -> bool {
+    config
+        .cancellation
+        .as_ref()
+        .is_some_and(CancellationToken::is_cancelled)
+}
+
+/// Cache entries are keyed by absolute path, so runs with different or
+/// overlapping roots share them
+fn cache_key(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Processes files in parallel, returning the results for files with matches
+/// (or for every file, in `FilesWithoutMatch` mode).
+///
+/// `found` tracks the number of matches seen so far across all workers; once it
+/// reaches `max_total_matches`, workers skip their remaining files. `restrict`
+/// trims each result (e.g. to the lines changed in a diff) before it is counted,
+/// and each kept result is passed to `emit` as soon as its file is done.
+///
+/// Files are handed out as `config.schedule` says; the returned results are
+/// in the order of `files` either way.
+fn process_files(
+    files: &[PathBuf],
+    processor: &FileProcessor,
+    config: &SearchConfig,
+    found: &AtomicUsize,
+    closed: &AtomicBool,
+    restrict: &(dyn Fn(&mut FileResult) + Sync),
+    emit: &(dyn Fn(FileResult) -> Option<FileResult> + Sync),
+) -> SearchResult<Vec<FileResult>> {
+    // Once the total limit is reached or the search is cancelled, the
+    // processor is stopped: workers take no new files, and large files being
+    // matched in chunks are abandoned
+    let limit_reached = |found: usize| config.max_total_matches.is_some_and(|max| found >= max);
+    if limit_reached(found.load(Ordering::Relaxed)) {
+        processor.stop();
+    }
+    let stopped = || {
+        if is_cancelled(config) || closed.load(Ordering::Relaxed) {
+            processor.stop();
+        }
+        processor.is_stopped()
+    };
+
+    let keep_empty = config.output_mode == OutputMode::FilesWithoutMatch;
+    // Files given directly as roots are searched even if they look minified
+    let named_files: HashSet<&Path> = config
+        .root_paths
+        .iter()
+        .filter(|root| root.is_file())
+        .map(PathBuf::as_path)
+        .collect();
+
+    // Searches one file, returning its result if it is to be collected
+    let search_file = |path: &Path| -> SearchResult<Option<FileResult>> {
+        // In FailFast mode, propagate any error; otherwise skip files
+        // that can't be read
+        let result = if named_files.contains(path) {
+            processor.process_path(path, false)
+        } else {
+            processor.process_walked_file(path)
+        };
+        let mut result = match result {
+            Ok(Some(result)) => result,
+            Err(e) if config.encoding_mode == EncodingMode::FailFast => return Err(e),
+            // Skipped as minified, abandoned, or unreadable. Abandoned files
+            // aren't emitted, so the incremental cache never records them.
+            Ok(None) | Err(_) => {
+                report_file_done(config, path, 0);
+                return Ok(None);
+            }
+        };
+        restrict(&mut result);
+        report_file_done(config, path, result.matches.len());
+        if keep_empty || !result.matches.is_empty() {
+            let count = result.matches.len();
+            if limit_reached(found.fetch_add(count, Ordering::Relaxed) + count) {
+                processor.stop();
+            }
+            Ok(emit(result))
+        } else {
+            Ok(None)
+        }
+    };
+
+    let pool = thread_pool(config.thread_count)?;
+    match config.schedule {
+        Schedule::Discovery => {
+            let chunk_size = (files.len() / config.thread_count.get()).max(1);
+            pool.install(|| {
+                files
+                    .par_chunks(chunk_size)
+                    .try_fold(Vec::new, |mut acc, chunk| {
+                        for path in chunk {
+                            if stopped() {
+                                break;
+                            }
+                            acc.extend(search_file(path)?);
+                        }
+                        Ok::<_, SearchError>(acc)
+                    })
+                    .try_reduce(Vec::new, |mut a, mut b| {
+                        a.append(&mut b);
+                        Ok::<_, SearchError>(a)
+                    })
+            })
+        }
+        Schedule::LargestFirst => {
+            let mut order: Vec<usize> = (0..files.len()).collect();
+            order.sort_by_cached_key(|&index| {
+                Reverse(std::fs::metadata(&files[index]).map_or(0, |m| m.len()))
+            });
+
+            // Each worker takes the largest file no one has started on yet
+            let next = AtomicUsize::new(0);
+            let per_worker = pool.broadcast(|_| {
+                let mut acc = Vec::new();
+                while !stopped() {
+                    let Some(&index) = order.get(next.fetch_add(1, Ordering::Relaxed)) else {
+                        break;
+                    };
+                    if let Some(result) = search_file(&files[index])? {
+                        acc.push((index, result));
+                    }
+                }
+                Ok::<_, SearchError>(acc)
+            });
+
+            let mut results = Vec::new();
+            for acc in per_worker {
+                results.extend(acc?);
+            }
+            results.sort_unstable_by_key(|&(index, _)| index);
+            Ok(results.into_iter().map(|(_, result)| result).collect())
+        }
+    }
+}
+
+/// Archive members left out of a search, by reason
+#[cfg(feature = "archives")]
+#[derive(Default)]
+struct SkippedMembers {
+    ignored: AtomicUsize,
+    binary: AtomicUsize,
+    too_large: AtomicUsize,
+}
+
+/// Searches the members of each archive, reporting them under paths like
+/// `artifact.zip!/logs/app.log`.
+///
+/// Members are filtered by name like walked files, and those larger than
+/// `max_file_size` are skipped. Archives are searched in parallel, the members
+/// of each one in order; `found` and `emit` work as in [`process_files`].
+#[cfg(feature = "archives")]
+fn process_archives(
+    archives: &[PathBuf],
+    processor: &FileProcessor,
+    config: &SearchConfig,
+    found: &AtomicUsize,
+    closed: &AtomicBool,
+    emit: &(dyn Fn(FileResult) -> Option<FileResult> + Sync),
+) -> SearchResult<(Vec<FileResult>, SkippedMembers)> {
+    let stopped = || {
+        config
+            .max_total_matches
+            .is_some_and(|max| found.load(Ordering::Relaxed) >= max)
+            || is_cancelled(config)
+            || closed.load(Ordering::Relaxed)
+            || processor.is_stopped()
+    };
+    let keep_empty = config.output_mode == OutputMode::FilesWithoutMatch;
+    let skipped = SkippedMembers::default();
+    // Member names are already relative to the archive
+    let no_root = Path::new("");
+    let include_globs = IncludeGlobs::new(&config.include_globs)?;
+
+    let results = thread_pool(config.thread_count)?.install(|| {
+        archives
+            .par_iter()
+            .map(|archive| {
+                let Some(kind) = ArchiveKind::from_path(archive) else {
+                    return Ok(Vec::new());
+                };
+                let mut results = Vec::new();
+                let searched = for_each_member(archive, kind, |name, size, reader| {
+                    if stopped() {
+                        return Ok(());
+                    }
+                    let name_path = Path::new(name);
+                    let wanted = include_globs.matches(name_path, no_root)
+                        && has_valid_type(name_path, &config.file_extensions, &config.file_types)
+                        && !has_excluded_extension(name_path, &config.exclude_extensions)
+                        && !matches_ignore_patterns(name_path, no_root, &config.ignore_patterns);
+                    if !wanted {
+                        skipped.ignored.fetch_add(1, Ordering::Relaxed);
+                        return Ok(());
+                    }
+                    if is_likely_binary(name_path) {
+                        skipped.binary.fetch_add(1, Ordering::Relaxed);
+                        return Ok(());
+                    }
+                    if config.max_file_size.is_some_and(|max| size > max) {
+                        debug!(
+                            "Skipping {}!/{}: exceeds maximum file size",
+                            archive.display(),
+                            name
+                        );
+                        skipped.too_large.fetch_add(1, Ordering::Relaxed);
+                        return Ok(());
+                    }
+
+                    let mut bytes = Vec::new();
+                    reader.read_to_end(&mut bytes)?;
+                    if sniff_bytes(&bytes).binary {
+                        skipped.binary.fetch_add(1, Ordering::Relaxed);
+                        return Ok(());
+                    }
+                    let path = member_path(archive, name);
+                    let result = match processor.process_reader(&bytes[..], &path) {
+                        Ok(Some(result)) => result,
+                        Err(e) if config.encoding_mode == EncodingMode::FailFast => return Err(e),
+                        // Abandoned members aren't reported, even as having no match
+                        Ok(None) | Err(_) => {
+                            report_file_done(config, &path, 0);
+                            return Ok(());
+                        }
+                    };
+                    report_file_done(config, &path, result.matches.len());
+                    if keep_empty || !result.matches.is_empty() {
+                        found.fetch_add(result.matches.len(), Ordering::Relaxed);
+                        results.extend(emit(result));
+                    }
+                    Ok(())
+                });
+                match searched {
+                    Ok(()) => Ok(results),
+                    Err(e) if config.encoding_mode == EncodingMode::FailFast => Err(e),
+                    // A corrupt or unreadable archive is skipped like an
+                    // unreadable file, keeping what was found before the error
+                    Err(e) => {
+                        debug!("Skipping rest of {}: {}", archive.display(), e);
+                        Ok(results)
+                    }
+                }
+            })
+            .try_reduce(Vec::new, |mut a, mut b| {
+                a.append(&mut b);
+                Ok::<_, SearchError>(a)
+            })
+    })?;
+    Ok((results, skipped))
+}
+
+/// Builds a thread pool with `thread_count` workers for one search or
+/// replacement run
+pub(crate) fn thread_pool(thread_count: NonZeroUsize) -> SearchResult<rayon::ThreadPool> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(thread_count.get())
+        .build()
+        .map_err(|e| {
+            SearchError::config_error(format!(
+                "Failed to start {} worker threads: {}",
+                thread_count, e
+            ))
+        })
+}
+
+/// Tells the progress hook, if any, that a file has been searched
+fn report_file_done(config: &SearchConfig, path: &Path, matches: usize) {
+    if let Some(progress) = &config.progress {
+        progress.0.on_file_done(path, matches);
+    }
+}
+
+/// Identifies a file independently of the path it was reached through
+#[cfg(unix)]
+fn file_identity(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|m| (m.dev(), m.ino()))
+}
+
+/// Identifies a file independently of the path it was reached through
+#[cfg(not(unix))]
+fn file_identity(path: &Path) -> Option<PathBuf> {
+    std::fs::canonicalize(path).ok()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::search::matcher::WordBoundaryMode;
+    use crate::search::progress::{ProgressHook, SearchProgress};
     use crate::ChangeDetectionStrategy;
+    use std::time::{Duration, SystemTime};
     use tempfile::tempdir;
 
     #[test]
@@ -256,7 +1081,7 @@ mod tests {
             true,
             WordBoundaryMode::None,
         );
-        config.root_path = dir.path().to_path_buf();
+        config.root_paths = vec![dir.path().to_path_buf()];
 
         let result = search(&config)?;
         assert_eq!(result.files_with_matches, 1);
@@ -266,27 +1091,179 @@ mod tests {
     }
 
     #[test]
-    fn test_incremental_search() -> SearchResult<()> {
+    fn test_fuzzy_search() -> SearchResult<()> {
         let dir = tempdir()?;
-        let file_path = dir.path().join("test.txt");
-        std::fs::write(&file_path, "pattern_1\npattern_2\n")?;
+        std::fs::write(dir.path().join("test.txt"), "recieve\nseperate\nseparate\n")?;
 
-        let cache_path = dir.path().join("cache.json");
-        let mut config = SearchConfig::new_with_pattern(
-            "pattern_\\d+".to_string(),
-            true,
-            WordBoundaryMode::None,
-        );
-        config.root_path = file_path.parent().unwrap().to_path_buf();
-        config.incremental = true;
-        config.cache_path = Some(cache_path.clone());
-        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+        let mut config =
+            SearchConfig::new_with_pattern("separate".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.pattern_definitions[0].fuzzy_distance = Some(1);
 
-        // First search should create cache
         let result = search(&config)?;
-        assert_eq!(result.files_with_matches, 1);
         assert_eq!(result.total_matches, 2);
-        assert!(cache_path.exists());
+
+        // Fuzzy matching is literal-only
+        config.pattern_definitions[0].is_regex = true;
+        assert!(matches!(
+            search(&config),
+            Err(SearchError::InvalidPattern(_))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_hidden_files_and_git_internals() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join(".github/workflows"))?;
+        std::fs::create_dir_all(root.join(".git/refs"))?;
+        std::fs::write(root.join("visible.txt"), "needle\n")?;
+        std::fs::write(root.join(".env"), "needle\n")?;
+        std::fs::write(root.join(".github/workflows/ci.yml"), "needle\n")?;
+        std::fs::write(root.join(".git/config"), "needle\n")?;
+        std::fs::write(root.join(".git/refs/main"), "needle\n")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        let searched = |config: &SearchConfig| -> SearchResult<Vec<PathBuf>> {
+            let result = search(config)?;
+            Ok(result
+                .file_results
+                .into_iter()
+                .map(|fr| fr.path.strip_prefix(root).unwrap().to_path_buf())
+                .collect())
+        };
+
+        assert_eq!(searched(&config)?, vec![PathBuf::from("visible.txt")]);
+
+        config.include_hidden = true;
+        assert_eq!(
+            searched(&config)?,
+            vec![
+                PathBuf::from(".env"),
+                PathBuf::from(".github/workflows/ci.yml"),
+                PathBuf::from("visible.txt"),
+            ]
+        );
+
+        // .git is only searched when ignores are disabled as well
+        config.no_ignore = true;
+        assert_eq!(searched(&config)?.len(), 5);
+
+        // A replacement's temporary files never are
+        std::fs::write(root.join(".rustscout-tmp-1-a1b2c3"), "needle\n")?;
+        assert_eq!(searched(&config)?.len(), 5);
+
+        config.include_hidden = false;
+        assert_eq!(searched(&config)?, vec![PathBuf::from("visible.txt")]);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_symlinks() -> SearchResult<()> {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempdir()?;
+        let root = dir.path();
+        // Diamond: left/ and right/ both link to shared/
+        std::fs::create_dir_all(root.join("shared"))?;
+        std::fs::write(root.join("shared/file.txt"), "needle\n")?;
+        std::fs::create_dir_all(root.join("top"))?;
+        symlink(root.join("shared"), root.join("top/left"))?;
+        symlink(root.join("shared"), root.join("top/right"))?;
+        // Loop: shared/loop points back at its parent
+        symlink(root.join("shared"), root.join("shared/loop"))?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.join("top")];
+        assert_eq!(search(&config)?.total_matches, 0);
+
+        config.follow_symlinks = true;
+        let result = search(&config)?;
+        assert_eq!(result.total_matches, 1);
+        assert_eq!(result.file_results[0].path, root.join("top/left/file.txt"));
+
+        // A symlinked file passed as the root is searched without --follow
+        symlink(root.join("shared/file.txt"), root.join("link.txt"))?;
+        config.root_paths = vec![root.join("link.txt")];
+        config.follow_symlinks = false;
+        assert_eq!(search(&config)?.total_matches, 1);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_hard_links_are_searched_once() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(root.join("a.txt"), "needle\n")?;
+        std::fs::hard_link(root.join("a.txt"), root.join("b.txt"))?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        let result = search(&config)?;
+        assert_eq!(result.file_results.len(), 1);
+        assert_eq!(result.file_results[0].path, root.join("a.txt"));
+        assert_eq!(result.stats.files_skipped_duplicate, 1);
+
+        config.dedupe_hardlinks = false;
+        let result = search(&config)?;
+        assert_eq!(result.file_results.len(), 2);
+        assert_eq!(result.stats.files_skipped_duplicate, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_regex_is_reported() -> SearchResult<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("test.txt"), "[invalid\n")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("[invalid".to_string(), true, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+
+        match search(&config) {
+            Err(SearchError::InvalidPattern(message)) => assert!(message.contains("[invalid")),
+            other => panic!(
+                "expected an invalid pattern error, got {:?}",
+                other.map(|_| ())
+            ),
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_search() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let file_path = dir.path().join("test.txt");
+        std::fs::write(&file_path, "pattern_1\npattern_2\n")?;
+
+        let cache_path = dir.path().join("cache.json");
+        let mut config = SearchConfig::new_with_pattern(
+            "pattern_\\d+".to_string(),
+            true,
+            WordBoundaryMode::None,
+        );
+        config.root_paths = vec![file_path.parent().unwrap().to_path_buf()];
+        config.incremental = true;
+        config.cache_path = Some(cache_path.clone());
+        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+
+        // First search should create cache
+        let result = search(&config)?;
+        assert_eq!(result.files_with_matches, 1);
+        assert_eq!(result.total_matches, 2);
+        assert!(cache_path.exists());
 
         // Second search should use cache
         let result = search(&config)?;
@@ -302,6 +1279,811 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_search_stats() -> SearchResult<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "TODO one\nnothing\n")?;
+        std::fs::write(dir.path().join("b.txt"), "nothing here\n")?;
+        std::fs::write(dir.path().join("image.png"), "TODO in a binary\n")?;
+        std::fs::write(dir.path().join("skip.log"), "TODO ignored\n")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.ignore_patterns = vec!["**/*.log".to_string()];
+
+        let result = search(&config)?;
+        let stats = &result.stats;
+        assert_eq!(result.files_with_matches, 1);
+        assert_eq!(stats.files_scanned, 2);
+        assert!(stats.files_scanned >= result.files_with_matches);
+        assert_eq!(stats.files_skipped_binary, 1);
+        assert_eq!(stats.files_skipped_ignored, 1);
+        assert!(stats.bytes_searched > 0);
+        assert!(stats.elapsed >= stats.walk_time + stats.match_time);
+        assert_eq!(stats.cache_hits + stats.cache_misses, 0);
+        // The fields that predate `stats` still report the same values
+        #[allow(deprecated)]
+        {
+            assert_eq!(result.elapsed, stats.elapsed);
+            assert_eq!(result.files_skipped_for_size, stats.files_skipped_too_large);
+        }
+
+        // Incremental runs account for every candidate file as a hit or miss
+        config.incremental = true;
+        config.cache_path = Some(dir.path().join("cache.json"));
+        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+        let first = search(&config)?;
+        assert_eq!(first.stats.cache_hits, 0);
+        assert!(first.stats.cache_misses >= 2);
+        let second = search(&config)?;
+        assert!(second.stats.cache_hits + second.stats.cache_misses >= 2);
+        assert!(second.stats.files_scanned >= second.files_with_matches);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_incremental_cache_per_pattern_set() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(root.join("a.txt"), "TODO one\nFIXME two\n")?;
+        std::fs::write(root.join("b.txt"), "TODO three\n")?;
+
+        let cache_path = root.join("cache.json");
+        let config_for = |pattern: &str| {
+            let mut config =
+                SearchConfig::new_with_pattern(pattern.to_string(), false, WordBoundaryMode::None);
+            config.root_paths = vec![root.to_path_buf()];
+            config.incremental = true;
+            config.cache_path = Some(cache_path.clone());
+            config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+            config
+        };
+        let todo = config_for("TODO");
+        let fixme = config_for("FIXME");
+
+        let first = search(&todo)?;
+        assert_eq!(first.total_matches, 2);
+        assert_eq!(first.stats.cache_misses, 2);
+
+        // Another pattern never gets the first one's matches
+        let other = search(&fixme)?;
+        assert_eq!(other.total_matches, 1);
+        assert_eq!(other.file_results[0].matches[0].line_content, "FIXME two");
+        assert_eq!(other.stats.cache_hits, 0);
+
+        // Both pattern sets are kept, so the first is reused again
+        let again = search(&todo)?;
+        assert_eq!(again.total_matches, 2);
+        assert_eq!((again.stats.cache_hits, again.stats.cache_misses), (2, 0));
+        let cache = IncrementalCache::load_from(&cache_path)?;
+        assert_eq!(
+            cache.metadata.pattern_fingerprint,
+            pattern_fingerprint(&todo)
+        );
+        assert_eq!(cache.files[&root.join("a.txt")].results.len(), 2);
+
+        // A changed file is searched again and loses the other set's results
+        std::fs::write(root.join("b.txt"), "TODO three\nTODO four\n")?;
+        let changed = search(&todo)?;
+        assert_eq!(changed.total_matches, 3);
+        assert_eq!(
+            (changed.stats.cache_hits, changed.stats.cache_misses),
+            (1, 1)
+        );
+        assert_eq!(search(&fixme)?.stats.cache_hits, 1);
+
+        // Minified-file and decoding settings are part of the fingerprint
+        let mut skipping_minified = config_for("TODO");
+        skipping_minified.skip_minified = true;
+        assert_ne!(
+            pattern_fingerprint(&skipping_minified),
+            pattern_fingerprint(&todo)
+        );
+        let mut lossy = config_for("TODO");
+        lossy.encoding_mode = EncodingMode::Lossy;
+        assert_ne!(pattern_fingerprint(&lossy), pattern_fingerprint(&todo));
+
+        // So are context settings
+        let mut with_context = config_for("TODO");
+        with_context.context_after = 1;
+        assert_ne!(
+            pattern_fingerprint(&with_context),
+            pattern_fingerprint(&todo)
+        );
+        let result = search(&with_context)?;
+        assert_eq!(result.stats.cache_hits, 0);
+        assert_eq!(result.file_results.len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_concurrent_searches_share_cache() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        for name in ["left", "right", "shared"] {
+            std::fs::create_dir_all(root.join(name))?;
+            for i in 0..20 {
+                std::fs::write(root.join(name).join(format!("{}.txt", i)), "needle\n")?;
+            }
+        }
+        let cache_path = root.join("cache.json");
+        let config_for = |side: &str| {
+            let mut config =
+                SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+            config.root_paths = vec![root.join(side), root.join("shared")];
+            config.incremental = true;
+            config.cache_path = Some(cache_path.clone());
+            config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+            config
+        };
+
+        for _ in 0..5 {
+            let _ = std::fs::remove_file(&cache_path);
+            let (left, right) = std::thread::scope(|scope| {
+                let left = scope.spawn(|| search(&config_for("left")));
+                let right = scope.spawn(|| search(&config_for("right")));
+                (left.join().unwrap(), right.join().unwrap())
+            });
+            assert_eq!(left?.total_matches, 40);
+            assert_eq!(right?.total_matches, 40);
+
+            // The cache on disk is whole and has both runs' files
+            let saved: serde_json::Value = serde_json::from_slice(&std::fs::read(&cache_path)?)
+                .expect("the cache is valid JSON");
+            let files = saved["files"].as_object().unwrap();
+            assert_eq!(files.len(), 60);
+            for name in ["left", "right", "shared"] {
+                let key = root.join(name).join("0.txt");
+                assert!(files.contains_key(key.to_str().unwrap()));
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_file_size_and_depth() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("a/b"))?;
+        std::fs::write(root.join("small.txt"), "needle\n")?;
+        std::fs::write(root.join("big.log"), "needle\n".repeat(100))?;
+        std::fs::write(root.join("a/one.txt"), "needle\n")?;
+        std::fs::write(root.join("a/b/two.txt"), "needle\n")?;
+
+        let cache_path = root.join("cache.json");
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        config.max_file_size = Some(64);
+        config.incremental = true;
+        config.cache_path = Some(cache_path.clone());
+        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+
+        let result = search(&config)?;
+        assert_eq!(result.files_with_matches, 3);
+        assert_eq!(result.stats.files_skipped_too_large, 1);
+        let cache = IncrementalCache::load_from(&cache_path)?;
+        assert!(!cache.files.contains_key(&root.join("big.log")));
+
+        config.incremental = false;
+        config.max_depth = Some(2);
+        let result = search(&config)?;
+        assert_eq!(result.files_with_matches, 2);
+
+        config.max_depth = Some(1);
+        let result = search(&config)?;
+        assert_eq!(result.files_with_matches, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_schedules_find_the_same_results() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..40 {
+            // Sizes vary so largest-first hands files out in another order
+            let contents = format!("needle {}\n", i).repeat(1 + (i * 37) % 200);
+            std::fs::write(dir.path().join(format!("file{:02}.txt", i)), contents)?;
+        }
+        std::fs::write(dir.path().join("empty.txt"), "")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.thread_count = NonZeroUsize::new(4).unwrap();
+
+        config.schedule = Schedule::Discovery;
+        let discovery = search(&config)?;
+        config.schedule = Schedule::LargestFirst;
+        let largest_first = search(&config)?;
+
+        assert_eq!(discovery.file_results.len(), 40);
+        assert_eq!(discovery.file_results, largest_first.file_results);
+        assert_eq!(discovery.total_matches, largest_first.total_matches);
+        assert!(largest_first.stats.slowest_file_time > std::time::Duration::ZERO);
+        Ok(())
+    }
+
+    #[test]
+    fn test_discovery_with_total_limit() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..200 {
+            std::fs::write(dir.path().join(format!("f{:03}.txt", i)), "needle\n")?;
+        }
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.schedule = Schedule::Discovery;
+        config.max_total_matches = Some(10);
+        let names = |result: &SearchOutput| -> Vec<String> {
+            result
+                .file_results
+                .iter()
+                .map(|file| {
+                    file.path
+                        .file_name()
+                        .unwrap()
+                        .to_string_lossy()
+                        .into_owned()
+                })
+                .collect()
+        };
+
+        // One thread takes the files in path order
+        config.thread_count = NonZeroUsize::new(1).unwrap();
+        let result = search(&config)?;
+        let first: Vec<String> = (0..10).map(|i| format!("f{:03}.txt", i)).collect();
+        assert_eq!(names(&result), first);
+
+        // Several threads each stop partway through their own run of files,
+        // so only the count and the order are fixed
+        config.thread_count = NonZeroUsize::new(4).unwrap();
+        let result = search(&config)?;
+        assert_eq!(result.total_matches, 10);
+        let names = names(&result);
+        assert_eq!(names.len(), 10);
+        assert!(names.windows(2).all(|pair| pair[0] < pair[1]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_match_stops_all_workers() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..3000 {
+            std::fs::write(dir.path().join(format!("file{:04}.txt", i)), "needle\n")?;
+        }
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.thread_count = NonZeroUsize::new(4).unwrap();
+        // What --quiet asks for
+        config.max_total_matches = Some(1);
+        for schedule in [Schedule::LargestFirst, Schedule::Discovery] {
+            config.schedule = schedule;
+            let result = search(&config)?;
+            assert_eq!(result.total_matches, 1);
+            assert!(result.limit_reached);
+            assert!(
+                result.stats.files_scanned < 100,
+                "{} files scanned",
+                result.stats.files_scanned
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_memory_budget_keeps_totals() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..50 {
+            std::fs::write(
+                dir.path().join(format!("file{:02}.txt", i)),
+                "needle\n".repeat(20),
+            )?;
+        }
+        std::fs::write(dir.path().join("empty.txt"), "")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        let full = search(&config)?;
+        assert!(!full.results_truncated);
+
+        config.memory_budget = Some(5 * full.file_results[0].heap_size());
+        let result = search(&config)?;
+        assert!(result.results_truncated);
+        assert!(!result.file_results.is_empty() && result.file_results.len() <= 5);
+        assert_eq!(result.total_matches, 1000);
+        assert_eq!(result.files_with_matches, 50);
+        assert_eq!(result.files_searched, full.files_searched);
+
+        // Streamed results are never held, so nothing is dropped
+        let streamed = crate::search_iter(&config).count();
+        assert_eq!(streamed, 50);
+        Ok(())
+    }
+
+    #[test]
+    fn test_modified_time_and_size_filters() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        let now = SystemTime::now();
+        let day = Duration::from_secs(24 * 60 * 60);
+        std::fs::write(root.join("new.txt"), "needle\n")?;
+        std::fs::write(root.join("big.txt"), "needle\n".repeat(100))?;
+        std::fs::write(root.join("old.txt"), "needle\n")?;
+        std::fs::File::options()
+            .write(true)
+            .open(root.join("old.txt"))?
+            .set_modified(now - 10 * day)?;
+
+        let cache_path = root.join("cache.json");
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        config.incremental = true;
+        config.cache_path = Some(cache_path.clone());
+        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+        // Fill the cache with every file first
+        assert_eq!(search(&config)?.files_with_matches, 3);
+
+        // Files cached by the earlier run are still filtered out
+        config.modified_after = Some(now - 2 * day);
+        let result = search(&config)?;
+        let mut paths: Vec<_> = result.file_results.iter().map(|fr| &fr.path).collect();
+        paths.sort();
+        assert_eq!(paths, [&root.join("big.txt"), &root.join("new.txt")]);
+        assert_eq!(result.stats.files_skipped_filtered, 1);
+
+        config.modified_after = None;
+        config.modified_before = Some(now - 2 * day);
+        let result = search(&config)?;
+        assert_eq!(result.file_results.len(), 1);
+        assert_eq!(result.file_results[0].path, root.join("old.txt"));
+
+        config.modified_before = None;
+        config.size_range = Some((100, u64::MAX));
+        let result = search(&config)?;
+        assert_eq!(result.file_results.len(), 1);
+        assert_eq!(result.file_results[0].path, root.join("big.txt"));
+        assert_eq!(result.stats.files_skipped_filtered, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rustscout_ignore_files() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/generated"))?;
+        std::fs::write(root.join(".rustscoutignore"), "generated/\n*.snap\n")?;
+        std::fs::write(root.join("src/.rustscoutignore"), "!keep.snap\n")?;
+        for path in [
+            "src/lib.rs",
+            "src/generated/api.rs",
+            "a.snap",
+            "src/keep.snap",
+        ] {
+            std::fs::write(root.join(path), "needle\n")?;
+        }
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        let searched = |config: &SearchConfig| -> SearchResult<Vec<PathBuf>> {
+            let mut paths: Vec<_> = search(config)?
+                .file_results
+                .into_iter()
+                .map(|fr| fr.path.strip_prefix(root).unwrap().to_path_buf())
+                .collect();
+            paths.sort();
+            Ok(paths)
+        };
+        assert_eq!(
+            searched(&config)?,
+            [PathBuf::from("src/keep.snap"), PathBuf::from("src/lib.rs")]
+        );
+
+        // Explicit ignore patterns win over a re-included file
+        config.ignore_patterns = vec!["**/keep.snap".to_string()];
+        assert_eq!(searched(&config)?, [PathBuf::from("src/lib.rs")]);
+
+        config.ignore_patterns.clear();
+        config.no_rustscoutignore = true;
+        assert_eq!(searched(&config)?.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn test_include_globs_and_excluded_extensions() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/web"))?;
+        for path in [
+            "src/lib.rs",
+            "src/web/app.js",
+            "src/web/app.min.js",
+            "src/Makefile",
+            "build.rs",
+            "Cargo.lock",
+        ] {
+            std::fs::write(root.join(path), "needle\n")?;
+        }
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        let searched = |config: &SearchConfig| -> SearchResult<Vec<String>> {
+            let mut paths: Vec<_> = search(config)?
+                .file_results
+                .into_iter()
+                .map(|fr| {
+                    let path = fr.path.strip_prefix(root).unwrap().to_string_lossy();
+                    path.replace('\\', "/")
+                })
+                .collect();
+            paths.sort();
+            Ok(paths)
+        };
+
+        config.exclude_extensions = Some(vec!["min.js".to_string(), "lock".to_string()]);
+        assert_eq!(
+            searched(&config)?,
+            ["build.rs", "src/Makefile", "src/lib.rs", "src/web/app.js"]
+        );
+
+        // Include globs narrow first, then extensions and ignores apply
+        config.include_globs = vec!["src/**".to_string()];
+        config.file_extensions = Some(vec!["js".to_string(), "rs".to_string()]);
+        config.ignore_patterns = vec!["**/lib.rs".to_string()];
+        let result = search(&config)?;
+        assert_eq!(searched(&config)?, ["src/web/app.js"]);
+        assert_eq!(result.stats.files_skipped_ignored, 5);
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_minified() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        // Two files of the same size: one long line, and 80-byte lines
+        let minified = format!("needle;{}", "a=b+c;".repeat(20_000));
+        let normal = format!("needle{}\n", " ".repeat(73)).repeat(minified.len() / 80);
+        std::fs::write(root.join("app.min.js"), &minified)?;
+        std::fs::write(root.join("app.js"), &normal)?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        assert_eq!(search(&config)?.files_with_matches, 2);
+
+        config.skip_minified = true;
+        let result = search(&config)?;
+        assert_eq!(result.files_with_matches, 1);
+        assert_eq!(result.file_results[0].path, root.join("app.js"));
+        assert_eq!(result.stats.files_skipped_minified, 1);
+
+        // A file given as a root is always searched
+        config.root_paths = vec![root.join("app.min.js")];
+        let result = search(&config)?;
+        assert_eq!(result.files_with_matches, 1);
+        assert_eq!(result.stats.files_skipped_minified, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_shebang_scripts_match_type_filters() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(root.join("build"), "#!/usr/bin/env python3\n# needle\n")?;
+        std::fs::write(root.join("deploy"), "#!/bin/bash\n# needle\n")?;
+        std::fs::write(root.join("notes"), "needle\n")?;
+        std::fs::write(root.join("blob"), b"#!/bin/sh\n\0needle\n")?;
+        std::fs::write(root.join("lib.py"), "# needle\n")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        let searched = |config: &SearchConfig| -> SearchResult<Vec<PathBuf>> {
+            let mut paths: Vec<_> = search(config)?
+                .file_results
+                .into_iter()
+                .map(|fr| fr.path.strip_prefix(root).unwrap().to_path_buf())
+                .collect();
+            paths.sort();
+            Ok(paths)
+        };
+
+        config.file_extensions = Some(vec!["py".to_string()]);
+        assert_eq!(
+            searched(&config)?,
+            [PathBuf::from("build"), PathBuf::from("lib.py")]
+        );
+
+        config.file_extensions = None;
+        config.file_types.select("sh")?;
+        let result = search(&config)?;
+        assert_eq!(result.file_results.len(), 1);
+        assert_eq!(result.file_results[0].path, root.join("deploy"));
+        // The NUL byte seen while sniffing marks the file as binary
+        assert_eq!(result.stats.files_skipped_binary, 1);
+
+        config.sniff_shebang = false;
+        assert!(searched(&config)?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_explicit_paths() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::write(root.join("listed.rs"), "needle\n")?;
+        std::fs::write(root.join("listed.md"), "needle\n")?;
+        std::fs::write(root.join("unlisted.rs"), "needle\n")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        config.file_extensions = Some(vec!["rs".to_string()]);
+        config.explicit_paths = Some(vec![
+            root.join("listed.rs"),
+            root.join("listed.md"),
+            root.join("missing.rs"),
+        ]);
+
+        // Missing paths are skipped, and filters still apply to listed ones
+        let result = search(&config)?;
+        assert_eq!(result.files_searched, 1);
+        assert_eq!(result.file_results[0].path, root.join("listed.rs"));
+
+        config.strict_paths = true;
+        assert!(matches!(
+            search(&config),
+            Err(SearchError::FileNotFound(path)) if path == root.join("missing.rs")
+        ));
+        config.strict_paths = false;
+
+        // Listed files still honour the ignore files a walk would
+        std::fs::create_dir_all(root.join(".git"))?;
+        std::fs::create_dir_all(root.join("gen"))?;
+        std::fs::write(root.join(".gitignore"), "gen/\n")?;
+        std::fs::write(root.join(".rustscoutignore"), "vendored.rs\n")?;
+        std::fs::write(root.join("gen/out.rs"), "needle\n")?;
+        std::fs::write(root.join("vendored.rs"), "needle\n")?;
+        config.explicit_paths = Some(vec![
+            root.join("listed.rs"),
+            root.join("gen/out.rs"),
+            root.join("vendored.rs"),
+        ]);
+        let result = search(&config)?;
+        assert_eq!(result.files_searched, 1);
+        assert_eq!(result.file_results[0].path, root.join("listed.rs"));
+
+        config.no_ignore = true;
+        assert_eq!(search(&config)?.files_searched, 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiple_root_paths() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("src/nested"))?;
+        std::fs::create_dir_all(root.join("docs"))?;
+        std::fs::create_dir_all(root.join("other"))?;
+        std::fs::write(root.join("src/lib.rs"), "needle\n")?;
+        std::fs::write(root.join("src/nested/mod.rs"), "needle\n")?;
+        std::fs::write(root.join("docs/guide.md"), "needle\n")?;
+        std::fs::write(root.join("other/skip.txt"), "needle\n")?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        // src/nested overlaps src, so its file must only be counted once
+        config.root_paths = vec![root.join("src"), root.join("docs"), root.join("src/nested")];
+        config.incremental = true;
+        config.cache_path = Some(root.join("cache.json"));
+        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+
+        let result = search(&config)?;
+        assert_eq!(result.files_searched, 3);
+        assert_eq!(result.total_matches, 3);
+
+        // A later run with a single overlapping root shares the cache entries
+        let cache = IncrementalCache::load_from(&root.join("cache.json"))?;
+        assert!(cache.files.contains_key(&root.join("src/nested/mod.rs")));
+        config.root_paths = vec![root.join("src/nested")];
+        assert_eq!(search(&config)?.total_matches, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_reader() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let cache_path = dir.path().join("cache.json");
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.stdin = true;
+        config.context_before = 1;
+        config.incremental = true;
+        config.cache_path = Some(cache_path.clone());
+
+        let result = search_reader(&config, &b"hay\nneedle\nhay\n"[..])?;
+        assert_eq!(result.total_matches, 1);
+        let file_result = &result.file_results[0];
+        assert_eq!(file_result.path, PathBuf::from(STDIN_PATH));
+        assert_eq!(file_result.matches[0].line_number, 2);
+        assert_eq!(
+            file_result.matches[0].context_before,
+            vec![(1, "hay".to_string())]
+        );
+        // The incremental cache is bypassed
+        assert!(!cache_path.exists());
+
+        // Encoding modes apply as they do to files
+        let invalid = b"needle \xFF\n";
+        assert!(matches!(
+            search_reader(&config, &invalid[..]),
+            Err(SearchError::EncodingError { .. })
+        ));
+        config.encoding_mode = EncodingMode::Lossy;
+        assert_eq!(search_reader(&config, &invalid[..])?.total_matches, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_with_callback() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..20 {
+            let content = "needle\n".repeat(i % 3) + "hay\n";
+            std::fs::write(dir.path().join(format!("file_{}.txt", i)), content)?;
+        }
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+
+        let calls = AtomicUsize::new(0);
+        let matches = AtomicUsize::new(0);
+        let streamed = search_with_callback(&config, |file_result| {
+            calls.fetch_add(1, Ordering::Relaxed);
+            matches.fetch_add(file_result.matches.len(), Ordering::Relaxed);
+        })?;
+        let batch = search(&config)?;
+
+        assert_eq!(calls.into_inner(), batch.files_with_matches);
+        assert_eq!(matches.into_inner(), batch.total_matches);
+        assert_eq!(streamed.total_matches, batch.total_matches);
+        assert_eq!(streamed.files_with_matches, batch.files_with_matches);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_thread_count_sizes_pool() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..8 {
+            std::fs::write(dir.path().join(format!("file_{}.txt", i)), "needle\n")?;
+        }
+
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+
+        for threads in [1, 3] {
+            config.thread_count = NonZeroUsize::new(threads).unwrap();
+            let pool_sizes = std::sync::Mutex::new(HashSet::new());
+            search_with_callback(&config, |_| {
+                pool_sizes
+                    .lock()
+                    .unwrap()
+                    .insert(rayon::current_num_threads());
+            })?;
+            assert_eq!(pool_sizes.into_inner().unwrap(), HashSet::from([threads]));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cancellation() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..500 {
+            let sub = dir.path().join(format!("dir_{}", i % 10));
+            std::fs::create_dir_all(&sub)?;
+            std::fs::write(sub.join(format!("file_{}.txt", i)), "needle\n")?;
+        }
+
+        let token = CancellationToken::new();
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.cancellation = Some(token.clone());
+
+        // Cancel from another thread once the first file is done, and wait for
+        // it so the rest of the search runs with the token set
+        let (found_tx, found_rx) = std::sync::mpsc::channel::<()>();
+        let (cancelled_tx, cancelled_rx) = std::sync::mpsc::channel::<()>();
+        let canceller = std::thread::spawn(move || {
+            found_rx.recv().unwrap();
+            token.cancel();
+            cancelled_tx.send(()).unwrap();
+        });
+        let found_tx = std::sync::Mutex::new(Some(found_tx));
+        let cancelled_rx = std::sync::Mutex::new(cancelled_rx);
+
+        let start = std::time::Instant::now();
+        let result = search_with_callback(&config, |_| {
+            if let Some(tx) = found_tx.lock().unwrap().take() {
+                tx.send(()).unwrap();
+                cancelled_rx.lock().unwrap().recv().unwrap();
+            }
+        })?;
+        canceller.join().unwrap();
+
+        assert!(result.cancelled);
+        assert!(result.files_with_matches >= 1);
+        assert!(result.files_with_matches < 500);
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        config.cancellation = Some(CancellationToken::new());
+        let result = search(&config)?;
+        assert!(!result.cancelled);
+        assert_eq!(result.files_with_matches, 500);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_progress_hook() -> SearchResult<()> {
+        #[derive(Default)]
+        struct Counter {
+            discovered: AtomicUsize,
+            done: AtomicUsize,
+            matches: AtomicUsize,
+        }
+
+        impl SearchProgress for Counter {
+            fn on_files_discovered(&self, count: usize) {
+                self.discovered.fetch_add(count, Ordering::Relaxed);
+            }
+
+            fn on_file_done(&self, _path: &Path, matches: usize) {
+                self.done.fetch_add(1, Ordering::Relaxed);
+                self.matches.fetch_add(matches, Ordering::Relaxed);
+            }
+        }
+
+        let dir = tempdir()?;
+        for i in 0..10 {
+            let content = if i % 2 == 0 { "needle\n" } else { "hay\n" };
+            std::fs::write(dir.path().join(format!("file_{}.txt", i)), content)?;
+        }
+
+        let counter = Arc::new(Counter::default());
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.progress = Some(ProgressHook(counter.clone()));
+
+        let result = search(&config)?;
+        assert_eq!(counter.discovered.load(Ordering::Relaxed), 10);
+        assert_eq!(counter.done.load(Ordering::Relaxed), 10);
+        assert_eq!(
+            counter.matches.load(Ordering::Relaxed),
+            result.total_matches
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_word_boundary_search() -> SearchResult<()> {
         let dir = tempdir()?;
@@ -311,7 +2093,7 @@ mod tests {
         // Search with word boundaries
         let mut config =
             SearchConfig::new_with_pattern("test".to_string(), false, WordBoundaryMode::WholeWords);
-        config.root_path = file_path.parent().unwrap().to_path_buf();
+        config.root_paths = vec![file_path.parent().unwrap().to_path_buf()];
 
         let result = search(&config)?;
         assert_eq!(
@@ -322,7 +2104,7 @@ mod tests {
         // Search without word boundaries
         let mut config =
             SearchConfig::new_with_pattern("test".to_string(), false, WordBoundaryMode::None);
-        config.root_path = file_path.parent().unwrap().to_path_buf();
+        config.root_paths = vec![file_path.parent().unwrap().to_path_buf()];
 
         let result = search(&config)?;
         assert_eq!(
@@ -332,4 +2114,185 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_incremental_cache_after_commit_with_git_status() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let cache_dir = tempdir()?;
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            // git isn't installed
+            return Ok(());
+        }
+        std::fs::write(root.join("notes.txt"), "TODO one\n")?;
+        assert!(git(&["add", "."]));
+        assert!(git(&["commit", "-q", "-m", "initial"]));
+
+        let mut config =
+            SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        config.incremental = true;
+        config.cache_path = Some(cache_dir.path().join("cache.json"));
+        config.cache_strategy = ChangeDetectionStrategy::GitStatus;
+        assert_eq!(search(&config)?.total_matches, 1);
+        assert_eq!(search(&config)?.stats.cache_hits, 1);
+
+        // Committed, the change no longer shows in git status
+        std::fs::write(root.join("notes.txt"), "TODO one\nTODO two\n")?;
+        assert!(git(&["commit", "-q", "-am", "more"]));
+        let result = search(&config)?;
+        assert_eq!(result.total_matches, 2);
+        assert_eq!(result.stats.cache_hits, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_base_reports_only_changed_lines() -> SearchResult<()> {
+        let dir = tempdir()?;
+        let root = dir.path();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git")
+                .arg("-C")
+                .arg(root)
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .is_ok_and(|output| output.status.success())
+        };
+        if !git(&["init", "-q"]) {
+            // git isn't installed
+            return Ok(());
+        }
+        std::fs::write(root.join("changed.txt"), "TODO one\nkeep\nTODO two\n")?;
+        std::fs::write(root.join("untouched.txt"), "TODO three\n")?;
+        assert!(git(&["add", "."]));
+        assert!(git(&["commit", "-q", "-m", "initial"]));
+
+        std::fs::write(
+            root.join("changed.txt"),
+            "TODO one\nTODO added\nTODO two edited\n",
+        )?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        config.diff_base = Some("HEAD".to_string());
+        let result = search(&config)?;
+        assert_eq!(result.diff_base.as_deref(), Some("HEAD"));
+        assert_eq!(result.file_results.len(), 1);
+        assert_eq!(result.file_results[0].path, root.join("changed.txt"));
+        let lines: Vec<_> = result.file_results[0]
+            .matches
+            .iter()
+            .map(|m| m.line_number)
+            .collect();
+        assert_eq!(lines, [2, 3]);
+
+        // The per-file limit counts only matches on changed lines
+        config.max_count_per_file = Some(1);
+        let result = search(&config)?;
+        assert_eq!(result.file_results[0].matches[0].line_number, 2);
+
+        config.diff_base = Some("no-such-ref".to_string());
+        assert!(search(&config).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "archives")]
+    #[test]
+    fn test_search_archives() -> SearchResult<()> {
+        use std::io::Write;
+        use zip::write::SimpleFileOptions;
+
+        let dir = tempdir()?;
+        let root = dir.path();
+        let archive = root.join("artifact.zip");
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&archive)?);
+        for (name, contents) in [
+            ("logs/app.log", "started\nERROR disk full\n"),
+            ("logs/other.log", "all good\n"),
+            ("notes.md", "ERROR in docs\n"),
+        ] {
+            zip.start_file(name, SimpleFileOptions::default())
+                .map_err(std::io::Error::from)?;
+            zip.write_all(contents.as_bytes())?;
+        }
+        zip.finish().map_err(std::io::Error::from)?;
+
+        let mut config =
+            SearchConfig::new_with_pattern("ERROR".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![root.to_path_buf()];
+        // Without the option, the archive is skipped as binary
+        assert_eq!(search(&config)?.total_matches, 0);
+
+        config.search_archives = true;
+        config.file_extensions = Some(vec!["log".to_string()]);
+        let result = search(&config)?;
+        assert_eq!(result.file_results.len(), 1);
+        assert_eq!(
+            result.file_results[0].path,
+            member_path(&archive, "logs/app.log")
+        );
+        assert_eq!(result.file_results[0].matches[0].line_number, 2);
+        assert_eq!(result.stats.files_skipped_ignored, 1);
+
+        config.max_file_size = Some(10);
+        let result = search(&config)?;
+        assert_eq!(result.total_matches, 0);
+        assert_eq!(result.stats.files_skipped_too_large, 1);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "archives"))]
+    #[test]
+    fn test_search_archives_needs_feature() {
+        let mut config =
+            SearchConfig::new_with_pattern("ERROR".to_string(), false, WordBoundaryMode::None);
+        config.search_archives = true;
+        assert!(search(&config).is_err());
+    }
+
+    #[cfg(feature = "binary-cache")]
+    #[test]
+    fn test_incremental_search_with_binary_cache() -> SearchResult<()> {
+        let dir = tempdir()?;
+        std::fs::write(dir.path().join("a.txt"), "needle\n")?;
+        let mut config =
+            SearchConfig::new_with_pattern("needle".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.path().to_path_buf()];
+        config.incremental = true;
+        config.cache_path = Some(dir.path().join("cache.bin"));
+        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+        config.cache_format = CacheFormat::Binary;
+
+        assert_eq!(search(&config)?.total_matches, 1);
+        let second = search(&config)?;
+        assert_eq!(second.total_matches, 1);
+        assert_eq!(second.stats.cache_hits, 1);
+
+        // Switching back to JSON keeps the cache
+        config.cache_format = CacheFormat::Json;
+        assert_eq!(search(&config)?.stats.cache_hits, 1);
+        Ok(())
+    }
+
+    #[cfg(not(feature = "binary-cache"))]
+    #[test]
+    fn test_binary_cache_needs_feature() {
+        let mut config =
+            SearchConfig::new_with_pattern("ERROR".to_string(), false, WordBoundaryMode::None);
+        config.incremental = true;
+        config.cache_format = CacheFormat::Binary;
+        assert!(search(&config).is_err());
+    }
 }
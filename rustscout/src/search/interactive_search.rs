@@ -12,9 +12,11 @@ use crossterm::{
 use crate::{
     cache::ChangeDetectionStrategy,
     config::{EncodingMode, SearchConfig},
-    replace::{UndoFileReference, UndoInfo},
+    replace::{file_hash, UndoFileReference, UndoInfo, UNDO_INFO_VERSION},
     results::Match as ScoutMatch,
-    search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode},
+    search::matcher::{
+        per_pattern_values, CaseMode, HyphenMode, PatternDefinition, WordBoundaryMode,
+    },
     search::search,
     workspace::detect_workspace_root,
     SearchError,
@@ -44,10 +46,16 @@ fn short_path(path: &Path, workspace_root: &Path, verbose: bool) -> String {
 pub struct InteractiveSearchArgs {
     pub patterns: Vec<String>,
     pub legacy_patterns: Vec<String>,
+    pub pattern_file: Option<PathBuf>,
     pub is_regex: Vec<bool>,
-    pub boundary_mode: String,
+    /// Boundary mode per pattern (`strict`, `partial` or `none`); patterns
+    /// without one inherit the last mode given
+    pub boundary_mode: Vec<String>,
     pub word_boundary: bool,
-    pub hyphen_mode: String,
+    /// Hyphen mode per pattern (`boundary` or `joining`), inherited like `boundary_mode`
+    pub hyphen_mode: Vec<String>,
+    pub ignore_case: bool,
+    pub smart_case: bool,
     pub root: PathBuf,
     pub extensions: Option<String>,
     pub ignore: Vec<String>,
@@ -148,8 +156,7 @@ impl EditSession {
             let undo_dir = PathBuf::from(".rustscout").join("undo");
             let json_path = undo_dir.join(format!("{}.json", info.timestamp));
 
-            let data = serde_json::to_string_pretty(&info)
-                .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+            let data = serde_json::to_string_pretty(&info).map_err(io::Error::other)?;
 
             fs::write(&json_path, data)?;
         }
@@ -189,15 +196,11 @@ impl EditSession {
             match self.mode {
                 EditMode::View => {
                     match self.read_view_action()? {
-                        EditAction::MovePrev => {
-                            if self.current_line > 0 {
-                                self.current_line -= 1;
-                            }
+                        EditAction::MovePrev if self.current_line > 0 => {
+                            self.current_line -= 1;
                         }
-                        EditAction::MoveNext => {
-                            if self.current_line < self.lines.len() - 1 {
-                                self.current_line += 1;
-                            }
+                        EditAction::MoveNext if self.current_line < self.lines.len() - 1 => {
+                            self.current_line += 1;
                         }
                         EditAction::StartEdit => {
                             self.mode = EditMode::LineEdit;
@@ -401,6 +404,13 @@ impl EditSession {
                     file_count: 1,
                     dry_run: false,
                     file_diffs: Vec::new(),
+                    renames: Vec::new(),
+                    version: UNDO_INFO_VERSION,
+                    backup_hashes: vec![file_hash(&backup_file)?],
+                    patterns: Vec::new(),
+                    config: None,
+                    backup_root: None,
+                    backup_root_in_workspace: false,
                 });
             }
 
@@ -557,57 +567,97 @@ pub fn convert_args_to_config(
     args: &InteractiveSearchArgs,
     verbosity: &str,
 ) -> Result<SearchConfig, SearchError> {
-    let pattern_defs = args
+    let is_regex = args.is_regex.first().copied().unwrap_or(false);
+    // The pattern file counts as one more pattern for per-pattern modes
+    let pattern_count = args.patterns.len() + usize::from(args.pattern_file.is_some());
+    let boundary_modes = if args.word_boundary {
+        vec![WordBoundaryMode::WholeWords; pattern_count]
+    } else {
+        let modes = args
+            .boundary_mode
+            .iter()
+            .map(|mode| mode.parse())
+            .collect::<Result<Vec<WordBoundaryMode>, SearchError>>()?;
+        per_pattern_values(
+            &modes,
+            pattern_count,
+            WordBoundaryMode::None,
+            "--boundary-mode",
+        )?
+    };
+    let modes = args
+        .hyphen_mode
+        .iter()
+        .map(|mode| mode.parse())
+        .collect::<Result<Vec<HyphenMode>, SearchError>>()?;
+    let hyphen_modes = per_pattern_values(
+        &modes,
+        pattern_count,
+        HyphenMode::default(),
+        "--hyphen-mode",
+    )?;
+    let case_mode = CaseMode::from_flags(args.ignore_case, args.smart_case);
+
+    let mut pattern_defs: Vec<PatternDefinition> = args
         .patterns
         .iter()
-        .map(|p| PatternDefinition {
+        .enumerate()
+        .map(|(i, p)| PatternDefinition {
             text: p.clone(),
-            is_regex: args.is_regex.first().copied().unwrap_or(false),
-            boundary_mode: if args.word_boundary {
-                WordBoundaryMode::WholeWords
-            } else {
-                match args.boundary_mode.as_str() {
-                    "strict" => WordBoundaryMode::WholeWords,
-                    "partial" => WordBoundaryMode::Partial,
-                    _ => WordBoundaryMode::None,
-                }
-            },
-            hyphen_mode: match args.hyphen_mode.as_str() {
-                "boundary" => HyphenMode::Boundary,
-                _ => HyphenMode::Joining,
-            },
+            is_regex,
+            boundary_mode: boundary_modes[i],
+            hyphen_mode: hyphen_modes[i],
+            case_mode,
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         })
         .collect();
 
-    Ok(SearchConfig {
-        pattern_definitions: pattern_defs,
-        root_path: args.root.clone(),
-        file_extensions: args
-            .extensions
-            .as_ref()
-            .map(|e| e.split(',').map(String::from).collect()),
-        ignore_patterns: args.ignore.clone(),
-        stats_only: false,
-        thread_count: args
-            .threads
-            .unwrap_or_else(|| NonZeroUsize::new(4).unwrap()),
-        log_level: verbosity.to_string(),
-        context_before: args.context_before,
-        context_after: args.context_after,
-        incremental: args.incremental,
-        cache_path: args.cache_path.clone(),
-        cache_strategy: match args.cache_strategy.as_str() {
+    // Append patterns loaded from a pattern file
+    if let Some(pattern_file) = &args.pattern_file {
+        let boundary_mode = boundary_modes[pattern_count - 1];
+        for mut def in PatternDefinition::load_from_file(pattern_file, is_regex, boundary_mode)? {
+            def.hyphen_mode = hyphen_modes[pattern_count - 1];
+            def.case_mode = case_mode;
+            pattern_defs.push(def);
+        }
+    }
+
+    let mut builder = SearchConfig::builder()
+        .pattern_definitions(pattern_defs)
+        .root(&args.root)
+        .ignore_patterns(&args.ignore)
+        .threads(
+            args.threads
+                .unwrap_or_else(SearchConfig::default_thread_count),
+        )
+        .log_level(verbosity)
+        .context(args.context_before, args.context_after)
+        .cache_strategy(match args.cache_strategy.as_str() {
             "git" => ChangeDetectionStrategy::GitStatus,
             "signature" => ChangeDetectionStrategy::FileSignature,
             _ => ChangeDetectionStrategy::Auto,
-        },
-        max_cache_size: None,
-        use_compression: false,
-        encoding_mode: match args.encoding.as_str() {
+        })
+        .encoding_mode(match args.encoding.as_str() {
             "lossy" => EncodingMode::Lossy,
+            "detect" => EncodingMode::Detect,
             _ => EncodingMode::FailFast,
-        },
-    })
+        });
+    if let Some(extensions) = &args.extensions {
+        builder = builder.extensions(extensions.split(','));
+    }
+    if args.incremental {
+        builder = builder.incremental(
+            args.cache_path
+                .clone()
+                .unwrap_or_else(|| SearchConfig::default_cache_path_in(&args.root)),
+        );
+    }
+    builder.build()
 }
 
 /// Main interactive loop for processing matches
@@ -1120,4 +1170,52 @@ mod tests {
             PromptAction::Unknown
         );
     }
+
+    #[test]
+    fn test_per_pattern_boundary_modes() {
+        let mut args = InteractiveSearchArgs {
+            patterns: vec!["foo".to_string(), "bar".to_string(), "baz".to_string()],
+            legacy_patterns: vec![],
+            pattern_file: None,
+            is_regex: vec![],
+            boundary_mode: vec!["strict".to_string(), "partial".to_string()],
+            word_boundary: false,
+            hyphen_mode: vec!["boundary".to_string()],
+            ignore_case: false,
+            smart_case: false,
+            root: PathBuf::from("."),
+            extensions: None,
+            ignore: vec![],
+            context_before: 0,
+            context_after: 0,
+            threads: None,
+            incremental: false,
+            cache_path: None,
+            cache_strategy: "auto".to_string(),
+            encoding: "failfast".to_string(),
+            no_color: true,
+        };
+
+        let config = convert_args_to_config(&args, "warn").unwrap();
+        let modes: Vec<_> = config
+            .pattern_definitions
+            .iter()
+            .map(|def| (def.boundary_mode, def.hyphen_mode))
+            .collect();
+        // "baz" inherits the last boundary mode, all patterns the single hyphen mode
+        assert_eq!(
+            modes,
+            vec![
+                (WordBoundaryMode::WholeWords, HyphenMode::Boundary),
+                (WordBoundaryMode::Partial, HyphenMode::Boundary),
+                (WordBoundaryMode::Partial, HyphenMode::Boundary),
+            ]
+        );
+
+        args.boundary_mode = vec!["none".to_string(); 4];
+        assert!(matches!(
+            convert_args_to_config(&args, "warn"),
+            Err(SearchError::ConfigError(_))
+        ));
+    }
 }
@@ -0,0 +1,203 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+use crate::config::SearchConfig;
+use crate::errors::{SearchError, SearchResult};
+use crate::results::{FileResult, SearchStats};
+use crate::search::engine::run_search;
+
+/// What the search thread sends to the iterator
+enum Message {
+    File(FileResult),
+    Done(SearchStats),
+    Failed(SearchError),
+}
+
+/// Searches like [`super::search`], but yields each file result as it is
+/// found instead of collecting them all.
+///
+/// Results arrive in no particular order over a bounded channel, so workers
+/// block rather than race far ahead of a slow consumer. Dropping the iterator
+/// early stops the search between files; either way the incremental cache is
+/// saved before the iterator finishes dropping. A failed search yields its
+/// error as the last item.
+pub fn search_iter(config: &SearchConfig) -> SearchIter {
+    let (sender, receiver) = sync_channel(config.thread_count.get() * 2);
+    let closed = Arc::new(AtomicBool::new(false));
+
+    let worker = {
+        let config = config.clone();
+        let closed = Arc::clone(&closed);
+        std::thread::spawn(move || {
+            let emit = |file_result: FileResult| {
+                if sender.send(Message::File(file_result)).is_err() {
+                    // The iterator was dropped, nobody is listening anymore
+                    closed.store(true, Ordering::Relaxed);
+                }
+                None
+            };
+            let message = match run_search(&config, &closed, &emit) {
+                Ok(result) => Message::Done(result.stats),
+                Err(e) => Message::Failed(e),
+            };
+            let _ = sender.send(message);
+        })
+    };
+
+    SearchIter {
+        receiver: Some(receiver),
+        worker: Some(worker),
+        closed,
+        remaining: config.max_total_matches,
+        stats: None,
+    }
+}
+
+/// Iterator over file results returned by [`search_iter`]
+pub struct SearchIter {
+    receiver: Option<Receiver<Message>>,
+    worker: Option<JoinHandle<()>>,
+    closed: Arc<AtomicBool>,
+    /// Matches left before `max_total_matches` is reached
+    remaining: Option<usize>,
+    stats: Option<SearchStats>,
+}
+
+impl SearchIter {
+    /// Statistics for the finished search, once the iterator is exhausted
+    pub fn stats(&self) -> Option<&SearchStats> {
+        self.stats.as_ref()
+    }
+
+    /// Stops receiving and waits for the search thread to wind down
+    fn finish(&mut self) {
+        self.closed.store(true, Ordering::Relaxed);
+        // Dropping the receiver unblocks any worker waiting to send
+        self.receiver = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+impl Iterator for SearchIter {
+    type Item = SearchResult<FileResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let message = self.receiver.as_ref()?.recv().ok();
+        match message {
+            Some(Message::File(mut file_result)) => {
+                // Workers stop cooperatively, so trim any overshoot here
+                if let Some(remaining) = &mut self.remaining {
+                    if *remaining == 0 {
+                        self.finish();
+                        return None;
+                    }
+                    file_result.matches.truncate(*remaining);
+                    *remaining -= file_result.matches.len();
+                }
+                Some(Ok(file_result))
+            }
+            Some(Message::Done(stats)) => {
+                self.stats = Some(stats);
+                self.finish();
+                None
+            }
+            Some(Message::Failed(e)) => {
+                self.finish();
+                Some(Err(e))
+            }
+            None => {
+                self.finish();
+                None
+            }
+        }
+    }
+}
+
+impl Drop for SearchIter {
+    fn drop(&mut self) {
+        self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::matcher::WordBoundaryMode;
+    use crate::ChangeDetectionStrategy;
+    use tempfile::tempdir;
+
+    fn config_for(dir: &std::path::Path) -> SearchConfig {
+        let mut config =
+            SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
+        config.root_paths = vec![dir.to_path_buf()];
+        config
+    }
+
+    #[test]
+    fn test_search_iter_yields_every_file() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..20 {
+            std::fs::write(dir.path().join(format!("file{}.txt", i)), "TODO\nTODO\n")?;
+        }
+
+        let mut iter = search_iter(&config_for(dir.path()));
+        let mut paths = Vec::new();
+        for file_result in iter.by_ref() {
+            let file_result = file_result?;
+            assert_eq!(file_result.matches.len(), 2);
+            paths.push(file_result.path);
+        }
+        paths.sort();
+        paths.dedup();
+        assert_eq!(paths.len(), 20);
+        assert_eq!(iter.stats().map(|s| s.files_scanned), Some(20));
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_iter_stops_early_without_deadlock() -> SearchResult<()> {
+        let dir = tempdir()?;
+        // Far more files than the channel holds, so workers block on sending
+        for i in 0..500 {
+            std::fs::write(dir.path().join(format!("file{}.txt", i)), "TODO\n")?;
+        }
+        let mut config = config_for(dir.path());
+        config.incremental = true;
+        config.cache_path = Some(dir.path().join("cache.json"));
+        config.cache_strategy = ChangeDetectionStrategy::FileSignature;
+
+        let taken = search_iter(&config)
+            .take(3)
+            .collect::<SearchResult<Vec<_>>>()?;
+        assert_eq!(taken.len(), 3);
+        // Dropping the iterator saved the cache before returning
+        assert!(dir.path().join("cache.json").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_search_iter_max_total_and_errors() -> SearchResult<()> {
+        let dir = tempdir()?;
+        for i in 0..10 {
+            std::fs::write(dir.path().join(format!("file{}.txt", i)), "TODO TODO\n")?;
+        }
+        let mut config = config_for(dir.path());
+        config.max_total_matches = Some(5);
+        let total: usize = search_iter(&config)
+            .map(|file_result| file_result.map(|fr| fr.matches.len()))
+            .sum::<SearchResult<usize>>()?;
+        assert_eq!(total, 5);
+
+        let mut config = config_for(dir.path());
+        config.pattern_definitions[0].text = "(unclosed".to_string();
+        config.pattern_definitions[0].is_regex = true;
+        let mut iter = search_iter(&config);
+        assert!(matches!(iter.next(), Some(Err(_))));
+        assert!(iter.next().is_none());
+        Ok(())
+    }
+}
@@ -1,18 +1,32 @@
+use aho_corasick::AhoCorasick;
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
-use regex::Regex;
+use regex::{Regex, RegexSet};
 use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
+use tracing::trace;
 use unicode_categories::UnicodeCategories;
 
+use crate::errors::{PatternError, SearchError, SearchResult};
 use crate::metrics::MemoryMetrics;
 
 const SIMPLE_PATTERN_THRESHOLD: usize = 32;
+/// Default number of compiled patterns kept in the global pattern cache
+pub const DEFAULT_PATTERN_CACHE_CAPACITY: usize = 1024;
+/// Minimum number of compatible literal patterns worth combining into one automaton
+const MULTI_LITERAL_THRESHOLD: usize = 2;
+/// Minimum number of regex patterns before a `RegexSet` pre-filter pays for its
+/// extra pass over the text
+const PREFILTER_THRESHOLD: usize = 4;
 
 /// Defines how word boundaries are interpreted for a pattern.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum WordBoundaryMode {
     /// No boundary checking (existing behavior).
+    #[default]
     None,
     /// Uses word boundary checks (\b or equivalent) with strict token separation.
     WholeWords,
@@ -22,6 +36,23 @@ pub enum WordBoundaryMode {
     Partial,
 }
 
+impl std::str::FromStr for WordBoundaryMode {
+    type Err = SearchError;
+
+    /// Parses the CLI spelling: `strict`, `partial` or `none`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "strict" => Ok(WordBoundaryMode::WholeWords),
+            "partial" => Ok(WordBoundaryMode::Partial),
+            "none" => Ok(WordBoundaryMode::None),
+            _ => Err(SearchError::config_error(format!(
+                "Invalid boundary mode '{}'. Valid values are: strict, partial, none",
+                s
+            ))),
+        }
+    }
+}
+
 /// Defines how hyphens are handled in word boundaries.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum HyphenMode {
@@ -32,17 +63,124 @@ pub enum HyphenMode {
     Joining,
 }
 
+impl std::str::FromStr for HyphenMode {
+    type Err = SearchError;
+
+    /// Parses the CLI spelling: `boundary` or `joining`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "boundary" => Ok(HyphenMode::Boundary),
+            "joining" => Ok(HyphenMode::Joining),
+            _ => Err(SearchError::config_error(format!(
+                "Invalid hyphen mode '{}'. Valid values are: boundary, joining",
+                s
+            ))),
+        }
+    }
+}
+
+/// Resolves repeatable per-pattern flag values (such as `-b` or `-y`) into one
+/// value per pattern.
+///
+/// The n-th value applies to the n-th pattern; patterns without a value of
+/// their own inherit the last one given, or `default` if none was. Giving more
+/// values than there are patterns is an error, as it usually means a flag was
+/// attached to the wrong pattern.
+pub fn per_pattern_values<T: Copy>(
+    values: &[T],
+    pattern_count: usize,
+    default: T,
+    flag: &str,
+) -> SearchResult<Vec<T>> {
+    if values.len() > pattern_count {
+        return Err(SearchError::config_error(format!(
+            "{} was given {} times but only {} pattern(s) were specified",
+            flag,
+            values.len(),
+            pattern_count
+        )));
+    }
+    let last = values.last().copied().unwrap_or(default);
+    Ok((0..pattern_count)
+        .map(|i| values.get(i).copied().unwrap_or(last))
+        .collect())
+}
+
+/// Defines how letter case is compared when matching a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
+pub enum CaseMode {
+    /// Exact case comparison (existing behavior)
+    #[default]
+    Sensitive,
+    /// Ignore case for all comparisons
+    Insensitive,
+    /// Case-insensitive unless the pattern contains an uppercase character
+    Smart,
+}
+
+impl CaseMode {
+    /// Resolves the --ignore-case and --smart-case flags; an explicit ignore-case wins
+    pub fn from_flags(ignore_case: bool, smart_case: bool) -> Self {
+        if ignore_case {
+            CaseMode::Insensitive
+        } else if smart_case {
+            CaseMode::Smart
+        } else {
+            CaseMode::Sensitive
+        }
+    }
+}
+
 /// A single pattern definition with boundary rules.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PatternDefinition {
     /// The pattern text (literal string or regex).
     pub text: String,
     /// Indicates if this pattern should be treated as a regex.
+    #[serde(default)]
     pub is_regex: bool,
     /// The boundary mode for this pattern.
+    #[serde(default)]
     pub boundary_mode: WordBoundaryMode,
     /// How to handle hyphens in word boundaries
+    #[serde(default)]
     pub hyphen_mode: HyphenMode,
+    /// How letter case is compared
+    #[serde(default)]
+    pub case_mode: CaseMode,
+    /// Allow regex matches to span line boundaries.
+    ///
+    /// When set, `.` also matches newlines and `^`/`$` match at the start and
+    /// end of every line.
+    #[serde(default)]
+    pub multiline: bool,
+    /// Only match lines that consist entirely of the pattern.
+    ///
+    /// Regex patterns are wrapped in `^(?:...)$`; an end the pattern already
+    /// anchors itself is left alone. Lines may end in `\n` or `\r\n`.
+    #[serde(default)]
+    pub match_whole_line: bool,
+    /// With `match_whole_line`, ignore leading and trailing spaces and tabs on
+    /// the line. The reported match then excludes that whitespace.
+    #[serde(default)]
+    pub trim_whole_line: bool,
+    /// Report every occurrence, including ones that overlap a previous match
+    /// of the same pattern (e.g. `aa` matches `aaaa` three times).
+    ///
+    /// Overlapping matches can't be replaced, so the replace pipeline rejects it.
+    #[serde(default)]
+    pub allow_overlapping: bool,
+    /// Match approximately, allowing up to this many single-character edits
+    /// (substitutions, insertions or deletions) per match.
+    ///
+    /// Only literal patterns can be fuzzy; matches never span lines and take
+    /// precedence over `match_whole_line`. The search engine rejects fuzzy
+    /// regex patterns.
+    #[serde(default)]
+    pub fuzzy_distance: Option<u8>,
+    /// Optional label reported with matches produced by this pattern
+    #[serde(default)]
+    pub name: Option<String>,
 }
 
 impl PatternDefinition {
@@ -53,12 +191,413 @@ impl PatternDefinition {
             is_regex,
             boundary_mode,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
+        }
+    }
+
+    /// Resolves the case mode to whether matching should be case-sensitive.
+    ///
+    /// In `Smart` mode the pattern is case-sensitive only if it contains an
+    /// uppercase character. For regex patterns, escape sequences (`\W`, `\p{Lu}`),
+    /// POSIX classes (`[[:upper:]]`) and group names are not considered.
+    pub fn is_case_sensitive(&self) -> bool {
+        match self.case_mode {
+            CaseMode::Sensitive => true,
+            CaseMode::Insensitive => false,
+            CaseMode::Smart => has_literal_uppercase(&self.text, self.is_regex),
+        }
+    }
+
+    /// Checks the pattern for problems up front.
+    ///
+    /// Invalid regex syntax and fuzzy regexes are errors. Empty patterns and
+    /// boundary modes the matcher would ignore are reported as well, as errors
+    /// for which [`PatternError::is_warning`] is true.
+    pub fn validate(&self) -> Result<(), PatternError> {
+        if self.is_regex {
+            if self.fuzzy_distance.is_some() {
+                return Err(PatternError::FuzzyRegex(self.text.clone()));
+            }
+            if let Err(message) = compile_regex(&self.text) {
+                return Err(invalid_regex(&self.text, message));
+            }
+        }
+        if self.text.is_empty() {
+            return Err(PatternError::Empty);
+        }
+        if let Some(reason) = self.ignored_boundary_reason() {
+            return Err(PatternError::BoundaryModeIgnored {
+                pattern: self.text.clone(),
+                reason: reason.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The regex a whole-word regex pattern is matched with, if the matcher
+    /// wraps it in `\b`: only when it has no boundary tokens of its own and
+    /// no alternation or groups whose meaning the wrapping would change
+    pub(crate) fn whole_word_regex(&self) -> Option<String> {
+        let wraps = self.is_regex
+            && self.boundary_mode == WordBoundaryMode::WholeWords
+            && !PatternMatcher::contains_boundary_tokens(&self.text)
+            && !PatternMatcher::has_grouping(&self.text);
+        wraps.then(|| format!(r"\b(?:{})\b", self.text))
+    }
+
+    /// Explains why the matcher won't apply this pattern's boundary mode, if it won't
+    fn ignored_boundary_reason(&self) -> Option<&'static str> {
+        if self.boundary_mode == WordBoundaryMode::None {
+            return None;
+        }
+        if self.match_whole_line && self.fuzzy_distance.is_none() {
+            return Some("whole-line matching already anchors the pattern to the line");
+        }
+        if self.is_regex
+            && self.boundary_mode == WordBoundaryMode::WholeWords
+            && !PatternMatcher::contains_boundary_tokens(&self.text)
+            && PatternMatcher::has_grouping(&self.text)
+        {
+            return Some(
+                "whole-word boundaries aren't added to regexes with alternation or groups; add \\b to the pattern instead",
+            );
+        }
+        None
+    }
+
+    /// Loads pattern definitions from a file with one pattern per line.
+    ///
+    /// Surrounding whitespace is trimmed, and blank lines and lines starting
+    /// with `#` are skipped. When `is_regex` is
+    /// set, every pattern is compiled up front and the first invalid one is
+    /// reported together with its line number.
+    pub fn load_from_file(
+        path: &Path,
+        is_regex: bool,
+        boundary_mode: WordBoundaryMode,
+    ) -> SearchResult<Vec<PatternDefinition>> {
+        let content = std::fs::read_to_string(path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::NotFound => SearchError::file_not_found(path),
+            std::io::ErrorKind::PermissionDenied => SearchError::permission_denied(path),
+            _ => SearchError::IoError(e),
+        })?;
+
+        let mut patterns = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let text = line.trim();
+            if text.is_empty() || text.starts_with('#') {
+                continue;
+            }
+
+            if is_regex {
+                compile_regex(text).map_err(|e| {
+                    SearchError::invalid_pattern(format!("{}:{}: {}", path.display(), index + 1, e))
+                })?;
+            }
+
+            patterns.push(Self::new(text.to_string(), is_regex, boundary_mode));
+        }
+
+        Ok(patterns)
+    }
+}
+
+/// A regex compiled by the `regex` crate, or by `fancy_regex` for patterns
+/// that need look-around or backreferences
+pub(crate) enum CompiledRegex {
+    Standard(Regex),
+    #[cfg(feature = "fancy-regex")]
+    Fancy(fancy_regex::Regex),
+}
+
+impl CompiledRegex {
+    /// The name of each capture group, `None` for unnamed ones, starting
+    /// with group 0
+    pub(crate) fn group_names(&self) -> Vec<Option<&str>> {
+        match self {
+            CompiledRegex::Standard(regex) => regex.capture_names().collect(),
+            #[cfg(feature = "fancy-regex")]
+            CompiledRegex::Fancy(regex) => regex.capture_names().collect(),
+        }
+    }
+
+    /// Whether the regex has look-around, so that whether it matches depends
+    /// on the text around the match as well. Only `fancy_regex` supports it.
+    pub(crate) fn looks_around(&self) -> bool {
+        match self {
+            CompiledRegex::Standard(_) => false,
+            #[cfg(feature = "fancy-regex")]
+            CompiledRegex::Fancy(regex) => ["(?=", "(?!", "(?<=", "(?<!"]
+                .iter()
+                .any(|opener| regex.as_str().contains(opener)),
+        }
+    }
+
+    /// Expands `template` (`$1`, `${name}`, `$$`) from the first match in
+    /// `text`, or returns `None` if there is none
+    pub(crate) fn expand_first(&self, text: &str, template: &str) -> Option<String> {
+        let mut expanded = String::new();
+        match self {
+            CompiledRegex::Standard(regex) => regex.captures(text)?.expand(template, &mut expanded),
+            // A match error (e.g. backtrack limit) counts as no match, as in search
+            #[cfg(feature = "fancy-regex")]
+            CompiledRegex::Fancy(regex) => {
+                regex.captures(text).ok()??.expand(template, &mut expanded)
+            }
         }
+        Some(expanded)
     }
 }
 
-static PATTERN_CACHE: Lazy<DashMap<(String, bool, WordBoundaryMode, HyphenMode), MatchStrategy>> =
-    Lazy::new(DashMap::new);
+/// Whether a regex only matches within a single line: it can't match a
+/// newline and doesn't depend on where the whole text starts or ends
+fn regex_is_line_local(pattern: &str) -> bool {
+    use regex_syntax::hir::{Class, Hir, HirKind, Look};
+
+    fn walk(hir: &Hir) -> bool {
+        match hir.kind() {
+            HirKind::Empty => true,
+            HirKind::Literal(literal) => !literal.0.contains(&b'\n'),
+            HirKind::Class(Class::Unicode(class)) => !class
+                .ranges()
+                .iter()
+                .any(|range| range.start() <= '\n' && '\n' <= range.end()),
+            HirKind::Class(Class::Bytes(class)) => !class
+                .ranges()
+                .iter()
+                .any(|range| range.start() <= b'\n' && b'\n' <= range.end()),
+            HirKind::Look(look) => !matches!(look, Look::Start | Look::End),
+            HirKind::Repetition(repetition) => walk(&repetition.sub),
+            HirKind::Capture(capture) => walk(&capture.sub),
+            HirKind::Concat(subs) | HirKind::Alternation(subs) => subs.iter().all(walk),
+        }
+    }
+
+    regex_syntax::parse(pattern).is_ok_and(|hir| walk(&hir))
+}
+
+/// Checks whether a `regex` error comes from syntax only `fancy_regex` supports
+fn needs_fancy_regex(error: &regex::Error) -> bool {
+    let message = error.to_string();
+    message.contains("look-around") || message.contains("backreferences")
+}
+
+/// Compiles a regex, falling back to `fancy_regex` for look-around and
+/// backreferences when the `fancy-regex` feature is enabled.
+///
+/// Errors are returned as a message describing the problem with the pattern.
+pub(crate) fn compile_regex(pattern: &str) -> Result<CompiledRegex, String> {
+    match Regex::new(pattern) {
+        Ok(regex) => Ok(CompiledRegex::Standard(regex)),
+        #[cfg(feature = "fancy-regex")]
+        Err(e) if needs_fancy_regex(&e) => fancy_regex::Regex::new(pattern)
+            .map(CompiledRegex::Fancy)
+            .map_err(|e| e.to_string()),
+        #[cfg(not(feature = "fancy-regex"))]
+        Err(e) if needs_fancy_regex(&e) => Err(
+            "look-around and backreferences are not supported by the regex engine; \
+             enable the fancy-regex feature for look-around support"
+                .to_string(),
+        ),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Builds an `InvalidRegex` error, locating the problem with `regex_syntax`
+/// when it is a syntax error. `message` is the error from [`compile_regex`].
+fn invalid_regex(pattern: &str, message: String) -> PatternError {
+    use regex_syntax::ast::ErrorKind;
+
+    let located = match regex_syntax::Parser::new().parse(pattern) {
+        Err(regex_syntax::Error::Parse(e)) => {
+            // Keep the fancy-regex hint for look-around and backreferences
+            let unsupported = matches!(
+                e.kind(),
+                ErrorKind::UnsupportedLookAround | ErrorKind::UnsupportedBackreference
+            );
+            Some((
+                (!unsupported).then(|| e.kind().to_string()),
+                e.span().start.offset,
+            ))
+        }
+        Err(regex_syntax::Error::Translate(e)) => {
+            Some((Some(e.kind().to_string()), e.span().start.offset))
+        }
+        _ => None,
+    };
+    match located {
+        Some((kind, offset)) => PatternError::InvalidRegex {
+            pattern: pattern.to_string(),
+            message: kind.unwrap_or(message),
+            position: Some(pattern[..offset].chars().count()),
+        },
+        None => PatternError::InvalidRegex {
+            pattern: pattern.to_string(),
+            message,
+            position: None,
+        },
+    }
+}
+
+/// Checks whether the literal characters of a pattern contain an uppercase letter
+fn has_literal_uppercase(text: &str, is_regex: bool) -> bool {
+    if !is_regex {
+        return text.chars().any(char::is_uppercase);
+    }
+
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                // Skip the escaped character and any braced argument (\p{Lu}, \x{41})
+                if let Some(escaped) = chars.next() {
+                    if matches!(escaped, 'p' | 'P' | 'x' | 'u' | 'U') && chars.peek() == Some(&'{')
+                    {
+                        for inner in chars.by_ref() {
+                            if inner == '}' {
+                                break;
+                            }
+                        }
+                    } else if matches!(escaped, 'p' | 'P') {
+                        // Single-letter class such as \pL
+                        chars.next();
+                    }
+                }
+            }
+            '[' if chars.peek() == Some(&':') => {
+                // POSIX class such as [:upper:]
+                for inner in chars.by_ref() {
+                    if inner == ']' {
+                        break;
+                    }
+                }
+            }
+            '(' if chars.peek() == Some(&'?') => {
+                chars.next();
+                // Skip group names in (?P<Name>...) and (?<Name>...)
+                if chars.peek() == Some(&'P') {
+                    chars.next();
+                }
+                if chars.peek() == Some(&'<') {
+                    for inner in chars.by_ref() {
+                        if inner == '>' {
+                            break;
+                        }
+                    }
+                } else {
+                    // Skip inline flags such as (?U) or (?i-s:...)
+                    while chars
+                        .peek()
+                        .is_some_and(|f| matches!(f, 'i' | 'm' | 's' | 'u' | 'x' | 'U' | 'R' | '-'))
+                    {
+                        chars.next();
+                    }
+                }
+            }
+            c if c.is_uppercase() => return true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Pattern text, is_regex, boundary mode, hyphen mode, case-sensitive,
+/// multiline, whole-line, trimmed whole-line, overlapping and fuzzy distance
+type PatternCacheKey = (
+    String,
+    bool,
+    WordBoundaryMode,
+    HyphenMode,
+    bool,
+    bool,
+    bool,
+    bool,
+    bool,
+    Option<u8>,
+);
+
+/// Literals sharing boundary, hyphen and overlap settings, as `(pattern_index, text)` pairs
+type LiteralGroup<'a> = ((WordBoundaryMode, HyphenMode, bool), Vec<(usize, &'a str)>);
+
+/// Compiled strategies shared by all matchers, bounded to `capacity` entries.
+///
+/// Each entry remembers when it was last used; once the cache grows past its
+/// capacity the least recently used entries are evicted.
+struct PatternCache {
+    entries: DashMap<PatternCacheKey, (MatchStrategy, u64)>,
+    clock: AtomicU64,
+    capacity: AtomicUsize,
+}
+
+impl PatternCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            entries: DashMap::new(),
+            clock: AtomicU64::new(0),
+            capacity: AtomicUsize::new(capacity),
+        }
+    }
+
+    fn tick(&self) -> u64 {
+        self.clock.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Looks up a strategy, marking it as recently used
+    fn get(&self, key: &PatternCacheKey) -> Option<MatchStrategy> {
+        let mut entry = self.entries.get_mut(key)?;
+        entry.1 = self.tick();
+        Some(entry.0.clone())
+    }
+
+    /// Inserts a strategy and returns how many entries were evicted to make room
+    fn insert(&self, key: PatternCacheKey, strategy: MatchStrategy) -> usize {
+        self.entries.insert(key, (strategy, self.tick()));
+        self.evict_to(self.capacity.load(Ordering::Relaxed))
+    }
+
+    fn set_capacity(&self, capacity: usize) -> usize {
+        self.capacity.store(capacity, Ordering::Relaxed);
+        self.evict_to(capacity)
+    }
+
+    /// Evicts least recently used entries until at most `capacity` remain
+    fn evict_to(&self, capacity: usize) -> usize {
+        let mut evicted = 0;
+        while self.entries.len() > capacity {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|entry| entry.value().1)
+                .map(|entry| entry.key().clone());
+            match oldest {
+                Some(key) => {
+                    if self.entries.remove(&key).is_some() {
+                        evicted += 1;
+                    }
+                }
+                None => break,
+            }
+        }
+        evicted
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn clear(&self) {
+        self.entries.clear();
+    }
+}
+
+static PATTERN_CACHE: Lazy<PatternCache> =
+    Lazy::new(|| PatternCache::new(DEFAULT_PATTERN_CACHE_CAPACITY));
 
 /// Strategy for pattern matching
 #[derive(Debug, Clone)]
@@ -68,31 +607,121 @@ pub enum MatchStrategy {
         pattern: String,
         boundary_mode: WordBoundaryMode,
         hyphen_mode: HyphenMode,
+        overlapping: bool,
     },
     /// Regex-based match with optional word boundary checks.
     Regex {
         regex: Arc<Regex>,
         boundary_mode: WordBoundaryMode,
         hyphen_mode: HyphenMode,
+        /// Strip surrounding spaces and tabs from matches (trimmed whole-line patterns)
+        trim_match: bool,
+        overlapping: bool,
     },
+    /// Several simple literals sharing boundary settings, matched in a single
+    /// pass. Automaton pattern `i` belongs to pattern definition `pattern_indices[i]`.
+    MultiLiteral {
+        automaton: Arc<AhoCorasick>,
+        pattern_indices: Vec<usize>,
+        boundary_mode: WordBoundaryMode,
+        hyphen_mode: HyphenMode,
+        overlapping: bool,
+    },
+    /// Regex using look-around or backreferences, compiled with `fancy_regex`.
+    /// Behaves like `Regex`, but is slower and never pre-filtered.
+    #[cfg(feature = "fancy-regex")]
+    FancyRegex {
+        regex: Arc<fancy_regex::Regex>,
+        boundary_mode: WordBoundaryMode,
+        hyphen_mode: HyphenMode,
+        trim_match: bool,
+        overlapping: bool,
+    },
+    /// Approximate literal match within an edit distance budget, scanned line
+    /// by line.
+    Fuzzy {
+        pattern: String,
+        max_distance: u8,
+        case_sensitive: bool,
+        boundary_mode: WordBoundaryMode,
+        hyphen_mode: HyphenMode,
+    },
+}
+
+impl MatchStrategy {
+    /// Whether every match of this strategy lies within a single line; see
+    /// [`PatternMatcher::matches_within_lines`]
+    fn is_line_local(&self) -> bool {
+        match self {
+            MatchStrategy::Simple { pattern, .. } | MatchStrategy::Fuzzy { pattern, .. } => {
+                !pattern.contains('\n')
+            }
+            MatchStrategy::Regex { regex, .. } => regex_is_line_local(regex.as_str()),
+            // Literals are only combined after this is checked
+            MatchStrategy::MultiLiteral { .. } => false,
+            // Look-around can see across lines
+            #[cfg(feature = "fancy-regex")]
+            MatchStrategy::FancyRegex { .. } => false,
+        }
+    }
+}
+
+/// A single match found by a [`PatternMatcher`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatternMatch {
+    /// Byte offset where the match starts
+    pub start: usize,
+    /// Byte offset just past the end of the match
+    pub end: usize,
+    /// Index of the pattern definition that produced the match
+    pub pattern_index: usize,
+    /// Capture group spans as `(name, start, end)` byte offsets, when capture
+    /// extraction is enabled and the pattern is a regex with groups.
+    ///
+    /// Every group is listed under its number; named groups are listed again
+    /// under their name. Groups that did not participate in the match are omitted.
+    pub captures: Option<Vec<(String, usize, usize)>>,
 }
 
 /// Handles pattern matching operations
 #[derive(Debug, Clone)]
 pub struct PatternMatcher {
-    strategies: Vec<MatchStrategy>,
+    /// Strategies paired with the index of the pattern definition they came from
+    strategies: Vec<(usize, MatchStrategy)>,
+    /// Pattern names, indexed by pattern index
+    names: Vec<Option<String>>,
+    /// Whether to extract capture group spans for regex patterns
+    capture_groups: bool,
+    /// Pre-filter over every `Regex` strategy, in strategy order. Regexes the
+    /// set doesn't report for a text are never run over it.
+    prefilter: Option<Arc<RegexSet>>,
+    /// Whether every match lies within one line; see [`Self::matches_within_lines`]
+    line_local: bool,
     metrics: Arc<MemoryMetrics>,
 }
 
 impl PatternMatcher {
-    /// Clears the pattern cache - used for testing
-    #[cfg(test)]
+    /// Removes all compiled patterns from the global pattern cache
     pub fn clear_cache() {
         PATTERN_CACHE.clear();
     }
 
+    /// Sets how many compiled patterns the global pattern cache keeps
+    /// (default [`DEFAULT_PATTERN_CACHE_CAPACITY`]).
+    ///
+    /// Shrinking the capacity evicts the least recently used patterns right
+    /// away. A capacity of 0 disables caching.
+    pub fn set_cache_capacity(capacity: usize) {
+        PATTERN_CACHE.set_capacity(capacity);
+    }
+
+    /// Number of compiled patterns currently held in the global pattern cache
+    pub fn cache_len() -> usize {
+        PATTERN_CACHE.len()
+    }
+
     /// Creates a new PatternMatcher for the given patterns (legacy constructor)
-    pub fn new(patterns: Vec<String>) -> Self {
+    pub fn new(patterns: Vec<String>) -> SearchResult<Self> {
         let pattern_defs = patterns
             .into_iter()
             .map(|text| PatternDefinition {
@@ -100,13 +729,20 @@ impl PatternMatcher {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::default(),
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             })
             .collect();
         Self::from_definitions(pattern_defs)
     }
 
     /// Creates a new PatternMatcher from pattern definitions
-    pub fn from_definitions(patterns: Vec<PatternDefinition>) -> Self {
+    pub fn from_definitions(patterns: Vec<PatternDefinition>) -> SearchResult<Self> {
         Self::with_metrics(patterns, Arc::new(MemoryMetrics::new()))
     }
 
@@ -120,79 +756,286 @@ impl PatternMatcher {
             || pattern.contains(r"\>")
     }
 
-    /// Creates a new PatternMatcher with the specified metrics
-    pub fn with_metrics(patterns: Vec<PatternDefinition>, metrics: Arc<MemoryMetrics>) -> Self {
+    /// Checks if a regex pattern contains alternation or groups, which
+    /// wrapping it in word boundaries would change the meaning of
+    fn has_grouping(pattern: &str) -> bool {
+        pattern.contains('|') || pattern.contains('(')
+    }
+
+    /// Checks which ends of a regex pattern are already anchored, as `(start, end)`
+    fn line_anchors(pattern: &str) -> (bool, bool) {
+        (
+            pattern.starts_with('^') || pattern.starts_with(r"\A"),
+            (pattern.ends_with('$') && !pattern.ends_with(r"\$")) || pattern.ends_with(r"\z"),
+        )
+    }
+
+    /// Creates a new PatternMatcher with the specified metrics.
+    ///
+    /// Fails with [`SearchError::InvalidPattern`] if a regex pattern doesn't compile.
+    pub fn with_metrics(
+        patterns: Vec<PatternDefinition>,
+        metrics: Arc<MemoryMetrics>,
+    ) -> SearchResult<Self> {
         let mut strategies = Vec::with_capacity(patterns.len());
+        let mut names = Vec::with_capacity(patterns.len());
 
-        for pattern in patterns {
+        for (pattern_index, pattern) in patterns.into_iter().enumerate() {
+            names.push(pattern.name.clone());
             if pattern.text.is_empty() {
                 continue;
             }
 
+            let case_sensitive = pattern.is_case_sensitive();
             let cache_key = (
                 pattern.text.clone(),
                 pattern.is_regex,
                 pattern.boundary_mode,
                 pattern.hyphen_mode,
+                case_sensitive,
+                pattern.multiline,
+                pattern.match_whole_line,
+                pattern.trim_whole_line,
+                pattern.allow_overlapping,
+                pattern.fuzzy_distance,
             );
-            let strategy = if let Some(entry) = PATTERN_CACHE.get(&cache_key) {
+            let strategy = if let Some(strategy) = PATTERN_CACHE.get(&cache_key) {
                 metrics.record_cache_operation(pattern.text.len() as i64, true);
-                entry.clone()
+                strategy
             } else {
-                let strategy = if !pattern.is_regex && Self::is_simple_pattern(&pattern.text) {
+                let trim_match = pattern.match_whole_line && pattern.trim_whole_line;
+                let strategy = if let Some(max_distance) =
+                    pattern.fuzzy_distance.filter(|_| !pattern.is_regex)
+                {
+                    MatchStrategy::Fuzzy {
+                        pattern: pattern.text.clone(),
+                        max_distance,
+                        case_sensitive,
+                        boundary_mode: pattern.boundary_mode,
+                        hyphen_mode: pattern.hyphen_mode,
+                    }
+                } else if !pattern.is_regex
+                    && case_sensitive
+                    && !pattern.match_whole_line
+                    && Self::is_simple_pattern(&pattern.text)
+                {
                     MatchStrategy::Simple {
                         pattern: pattern.text.clone(),
                         boundary_mode: pattern.boundary_mode,
                         hyphen_mode: pattern.hyphen_mode,
+                        overlapping: pattern.allow_overlapping,
                     }
                 } else {
-                    let regex_pattern = if pattern.is_regex {
-                        // Special handling for café test case
-                        if pattern.text.starts_with("café\\s+\\w+") {
-                            r"(?u)café(?:\s+|\d*)\w+".to_string()
+                    let regex_pattern = if pattern.match_whole_line {
+                        // The line anchors subsume any word boundary handling
+                        let body = if pattern.is_regex {
+                            pattern.text.clone()
                         } else {
-                            // Only add word boundaries if:
-                            // 1. WholeWords mode is requested
-                            // 2. Pattern doesn't already have boundary tokens
-                            // 3. Pattern doesn't contain alternation or groups that would be affected
-                            let needs_boundaries = pattern.boundary_mode == WordBoundaryMode::WholeWords
-                                && !Self::contains_boundary_tokens(&pattern.text)
-                                && !pattern.text.contains('|') // No alternation
-                                && !pattern.text.contains("(?:") // No non-capturing groups
-                                && !pattern.text.contains('('); // No capturing groups
-
-                            if needs_boundaries {
-                                format!(r"(?u)\b(?:{})\b", pattern.text)
-                            } else {
-                                format!(r"(?u){}", pattern.text)
-                            }
-                        }
+                            regex::escape(&pattern.text)
+                        };
+                        // Only add the anchors a regex doesn't already have
+                        let (anchored_start, anchored_end) = if pattern.is_regex {
+                            Self::line_anchors(&pattern.text)
+                        } else {
+                            (false, false)
+                        };
+                        let padding = if pattern.trim_whole_line {
+                            r"[ \t]*"
+                        } else {
+                            ""
+                        };
+                        let start = if anchored_start {
+                            String::new()
+                        } else {
+                            format!("^{}", padding)
+                        };
+                        let end = if anchored_end {
+                            String::new()
+                        } else {
+                            format!("{}$", padding)
+                        };
+                        format!(r"(?u){}(?:{}){}", start, body, end)
+                    } else if pattern.is_regex {
+                        // `(?u)` keeps \b, \w and friends Unicode-aware, so accented
+                        // and non-Latin words get the same boundaries as ASCII ones
+                        let body = pattern
+                            .whole_word_regex()
+                            .unwrap_or_else(|| pattern.text.clone());
+                        format!(r"(?u){}", body)
                     } else {
+                        // Literals that take the regex path are matched as written,
+                        // whatever the case mode
+                        let text = regex::escape(&pattern.text);
                         match pattern.boundary_mode {
-                            WordBoundaryMode::WholeWords => format!(r"(?u)\b{}\b", pattern.text),
+                            WordBoundaryMode::WholeWords => format!(r"(?u)\b{}\b", text),
                             WordBoundaryMode::Partial | WordBoundaryMode::None => {
-                                format!(r"(?u){}", pattern.text)
+                                format!(r"(?u){}", text)
                             }
                         }
                     };
-                    MatchStrategy::Regex {
-                        regex: Arc::new(Regex::new(&regex_pattern).expect("Invalid regex pattern")),
-                        boundary_mode: pattern.boundary_mode,
-                        hyphen_mode: pattern.hyphen_mode,
+                    let mut flags = String::new();
+                    if !case_sensitive {
+                        flags.push('i');
+                    }
+                    if pattern.multiline && pattern.is_regex {
+                        flags.push_str("ms");
+                    }
+                    if pattern.match_whole_line {
+                        // ^/$ match at every line, and $ also before \r\n
+                        if !flags.contains('m') {
+                            flags.push('m');
+                        }
+                        flags.push('R');
+                    }
+                    let regex_pattern = if flags.is_empty() {
+                        regex_pattern
+                    } else {
+                        format!("(?{}){}", flags, regex_pattern)
+                    };
+                    let compiled = compile_regex(&regex_pattern).map_err(|e| {
+                        SearchError::invalid_pattern(format!("'{}': {}", pattern.text, e))
+                    })?;
+                    match compiled {
+                        CompiledRegex::Standard(regex) => MatchStrategy::Regex {
+                            regex: Arc::new(regex),
+                            boundary_mode: pattern.boundary_mode,
+                            hyphen_mode: pattern.hyphen_mode,
+                            trim_match,
+                            overlapping: pattern.allow_overlapping,
+                        },
+                        #[cfg(feature = "fancy-regex")]
+                        CompiledRegex::Fancy(regex) => MatchStrategy::FancyRegex {
+                            regex: Arc::new(regex),
+                            boundary_mode: pattern.boundary_mode,
+                            hyphen_mode: pattern.hyphen_mode,
+                            trim_match,
+                            overlapping: pattern.allow_overlapping,
+                        },
                     }
                 };
 
                 metrics.record_cache_operation(pattern.text.len() as i64, false);
-                PATTERN_CACHE.insert(cache_key, strategy.clone());
+                let evicted = PATTERN_CACHE.insert(cache_key, strategy.clone());
+                metrics.record_pattern_cache_evictions(evicted as u64);
                 strategy
             };
-            strategies.push(strategy);
+            strategies.push((pattern_index, strategy));
         }
 
-        Self {
+        let line_local = strategies
+            .iter()
+            .all(|(_, strategy)| strategy.is_line_local());
+        let strategies = Self::combine_literals(strategies);
+        let prefilter = Self::build_prefilter(&strategies);
+        Ok(Self {
             strategies,
+            names,
+            capture_groups: false,
+            prefilter,
+            line_local,
             metrics,
+        })
+    }
+
+    /// Whether every pattern only matches within a single line and never
+    /// looks at where the text starts or ends.
+    ///
+    /// If so, matching each line separately (or any run of whole lines) finds
+    /// the same matches as matching the whole text. Multiline regexes, regexes
+    /// that can match a newline (`\s`, `[^x]`), `\A`/`\z` or non-multiline
+    /// `^`/`$`, and look-around don't qualify.
+    pub fn matches_within_lines(&self) -> bool {
+        self.line_local
+    }
+
+    /// Builds a `RegexSet` over all regex strategies when there are at least
+    /// `PREFILTER_THRESHOLD` of them.
+    ///
+    /// The set is only a cheap existence check; spans still come from the
+    /// individual regexes, so with only a few patterns the extra scan costs more
+    /// than the regexes it lets us skip. Returns `None` if the set can't be built
+    /// (e.g. it exceeds the size limit), in which case every regex simply runs.
+    fn build_prefilter(strategies: &[(usize, MatchStrategy)]) -> Option<Arc<RegexSet>> {
+        let patterns: Vec<&str> = strategies
+            .iter()
+            .filter_map(|(_, strategy)| match strategy {
+                MatchStrategy::Regex { regex, .. } => Some(regex.as_str()),
+                _ => None,
+            })
+            .collect();
+        if patterns.len() < PREFILTER_THRESHOLD {
+            return None;
         }
+        RegexSet::new(patterns).ok().map(Arc::new)
+    }
+
+    /// Replaces groups of simple literals that share boundary, hyphen and overlap settings
+    /// with a single Aho-Corasick strategy, so many literals cost one scan.
+    ///
+    /// The combined strategies are built per matcher and never cached;
+    /// `PATTERN_CACHE` keeps holding one strategy per pattern definition.
+    fn combine_literals(strategies: Vec<(usize, MatchStrategy)>) -> Vec<(usize, MatchStrategy)> {
+        let mut groups: Vec<LiteralGroup> = Vec::new();
+        for (pattern_index, strategy) in &strategies {
+            if let MatchStrategy::Simple {
+                pattern,
+                boundary_mode,
+                hyphen_mode,
+                overlapping,
+            } = strategy
+            {
+                let key = (*boundary_mode, *hyphen_mode, *overlapping);
+                match groups.iter_mut().find(|(group_key, _)| *group_key == key) {
+                    Some((_, members)) => members.push((*pattern_index, pattern)),
+                    None => groups.push((key, vec![(*pattern_index, pattern)])),
+                }
+            }
+        }
+
+        let mut combined = Vec::new();
+        for ((boundary_mode, hyphen_mode, overlapping), members) in groups {
+            if members.len() < MULTI_LITERAL_THRESHOLD {
+                continue;
+            }
+            let (pattern_indices, patterns): (Vec<usize>, Vec<&str>) = members.into_iter().unzip();
+            // If the automaton can't be built, the literals keep their own strategies
+            if let Ok(automaton) = AhoCorasick::new(&patterns) {
+                combined.push((
+                    pattern_indices[0],
+                    MatchStrategy::MultiLiteral {
+                        automaton: Arc::new(automaton),
+                        pattern_indices,
+                        boundary_mode,
+                        hyphen_mode,
+                        overlapping,
+                    },
+                ));
+            }
+        }
+        if combined.is_empty() {
+            return strategies;
+        }
+
+        let mut result: Vec<(usize, MatchStrategy)> = strategies
+            .into_iter()
+            .filter(|(pattern_index, _)| {
+                !combined.iter().any(|(_, strategy)| {
+                    matches!(strategy, MatchStrategy::MultiLiteral { pattern_indices, .. }
+                        if pattern_indices.contains(pattern_index))
+                })
+            })
+            .collect();
+        result.extend(combined);
+        result
+    }
+
+    /// Enables extraction of capture group spans for regex patterns.
+    ///
+    /// Literal patterns never report captures, and matching without captures
+    /// keeps using the faster `find_iter` path.
+    pub fn with_captures(mut self, capture_groups: bool) -> Self {
+        self.capture_groups = capture_groups;
+        self
     }
 
     /// Gets the current memory metrics
@@ -200,6 +1043,11 @@ impl PatternMatcher {
         &self.metrics
     }
 
+    /// Gets the name of the pattern at `pattern_index`, if it has one
+    pub fn pattern_name(&self, pattern_index: usize) -> Option<&str> {
+        self.names.get(pattern_index)?.as_deref()
+    }
+
     /// Determines if a pattern can use simple string matching
     fn is_simple_pattern(pattern: &str) -> bool {
         pattern.len() < SIMPLE_PATTERN_THRESHOLD
@@ -473,83 +1321,447 @@ impl PatternMatcher {
         has_pattern_before && has_pattern_after
     }
 
+    /// Collects the spans of all participating capture groups (excluding group 0),
+    /// keyed by number and, for named groups, by name as well
+    fn capture_spans(regex: &Regex, caps: &regex::Captures) -> Vec<(String, usize, usize)> {
+        Self::group_spans(regex.capture_names(), |index| {
+            caps.get(index).map(|group| (group.start(), group.end()))
+        })
+    }
+
+    /// Keys the span of each participating group (excluding group 0), given
+    /// the regex's group names in order and a lookup of each group's span
+    fn group_spans<'n>(
+        names: impl Iterator<Item = Option<&'n str>>,
+        span: impl Fn(usize) -> Option<(usize, usize)>,
+    ) -> Vec<(String, usize, usize)> {
+        let mut spans = Vec::new();
+        for (index, name) in names.enumerate().skip(1) {
+            let Some((start, end)) = span(index) else {
+                continue;
+            };
+            spans.push((index.to_string(), start, end));
+            if let Some(name) = name {
+                spans.push((name.to_string(), start, end));
+            }
+        }
+        spans
+    }
+
+    /// Checks the word boundaries of a regex match.
+    ///
+    /// For WholeWords mode, boundaries are handled in the pattern itself;
+    /// for Partial mode, we need to post-filter the matches.
+    fn regex_boundary_ok(
+        text: &str,
+        start: usize,
+        end: usize,
+        boundary_mode: WordBoundaryMode,
+        hyphen_mode: HyphenMode,
+    ) -> bool {
+        boundary_mode != WordBoundaryMode::Partial
+            || Self::is_word_boundary(
+                text,
+                start,
+                end,
+                &text[start..end],
+                hyphen_mode,
+                boundary_mode,
+            )
+    }
+
+    /// Span reported for a regex match. Trimmed whole-line matches report the
+    /// line without its indentation and trailing spaces or tabs.
+    fn regex_span(text: &str, start: usize, end: usize, trim_match: bool) -> (usize, usize) {
+        if !trim_match {
+            return (start, end);
+        }
+        let is_blank = |c: char| c == ' ' || c == '\t';
+        let matched = &text[start..end];
+        let trimmed = matched.trim_start_matches(is_blank);
+        let start = start + (matched.len() - trimmed.len());
+        (start, start + trimmed.trim_end_matches(is_blank).len())
+    }
+
+    /// Byte length of the character starting at `index` (1 at the end of the text)
+    fn char_len_at(text: &str, index: usize) -> usize {
+        text[index..].chars().next().map_or(1, char::len_utf8)
+    }
+
+    /// Finds every occurrence of `pattern`, advancing one character past each
+    /// match start so that occurrences may overlap
+    fn overlapping_indices<'t>(
+        text: &'t str,
+        pattern: &'t str,
+    ) -> impl Iterator<Item = (usize, usize)> + 't {
+        let mut from = 0;
+        std::iter::from_fn(move || {
+            let start = from + text.get(from..)?.find(pattern)?;
+            from = start + Self::char_len_at(text, start);
+            Some((start, start + pattern.len()))
+        })
+    }
+
+    /// Finds approximate occurrences of `pattern` within each line of `text`,
+    /// as byte spans in order of position.
+    ///
+    /// Uses Sellers' edit distance scan: every line position ending an
+    /// alignment with at most `max_distance` edits is a candidate. Candidates
+    /// are then taken best first (fewest edits, then longest, then earliest),
+    /// skipping any that overlap one already taken.
+    fn fuzzy_spans(
+        text: &str,
+        pattern: &str,
+        max_distance: usize,
+        case_sensitive: bool,
+    ) -> Vec<(usize, usize)> {
+        let fold = |c: char| -> char {
+            if case_sensitive {
+                c
+            } else {
+                c.to_lowercase().next().unwrap_or(c)
+            }
+        };
+        let pattern: Vec<char> = pattern.chars().map(fold).collect();
+        let m = pattern.len();
+        let mut spans = Vec::new();
+        let mut line_start = 0;
+
+        for line in text.split('\n') {
+            // Byte offset of every char in the line, plus the line end
+            let offsets: Vec<usize> = line
+                .char_indices()
+                .map(|(i, _)| line_start + i)
+                .chain(std::iter::once(line_start + line.len()))
+                .collect();
+
+            // cost[i] / start[i]: cheapest alignment of pattern[..i] ending at
+            // the current line position, and the char index it starts at
+            let mut cost: Vec<usize> = (0..=m).collect();
+            let mut start = vec![0; m + 1];
+            let mut candidates = Vec::new();
+            for (j, c) in line.chars().map(fold).enumerate() {
+                let (mut diag_cost, mut diag_start) = (cost[0], start[0]);
+                cost[0] = 0;
+                start[0] = j + 1;
+                for i in 1..=m {
+                    let substitute = (diag_cost + usize::from(pattern[i - 1] != c), diag_start);
+                    let skip_pattern = (cost[i - 1] + 1, start[i - 1]);
+                    let skip_text = (cost[i] + 1, start[i]);
+                    diag_cost = cost[i];
+                    diag_start = start[i];
+                    // Ties go to the earliest start, i.e. the longest alignment
+                    let best = substitute.min(skip_pattern).min(skip_text);
+                    cost[i] = best.0;
+                    start[i] = best.1;
+                }
+                if cost[m] <= max_distance && start[m] <= j {
+                    candidates.push((cost[m], start[m], j + 1));
+                }
+            }
+
+            candidates.sort_by_key(|&(cost, start, end)| (cost, Reverse(end - start), start));
+            let mut taken: Vec<(usize, usize)> = Vec::new();
+            for (_, start, end) in candidates {
+                if taken.iter().all(|&(s, e)| end <= s || start >= e) {
+                    taken.push((start, end));
+                }
+            }
+            taken.sort_unstable();
+            spans.extend(
+                taken
+                    .into_iter()
+                    .map(|(start, end)| (offsets[start], offsets[end])),
+            );
+
+            line_start += line.len() + 1;
+        }
+        spans
+    }
+
     /// Finds all matches in the given text
-    pub fn find_matches(&self, text: &str) -> Vec<(usize, usize)> {
+    pub fn find_matches(&self, text: &str) -> Vec<PatternMatch> {
+        self.find_matches_limited(text, None)
+    }
+
+    /// Finds at most `limit` matches in the given text, in order of position.
+    ///
+    /// Each pattern stops scanning once it has produced `limit` matches, so
+    /// callers that only need the first few hits don't pay for the rest.
+    pub fn find_matches_limited(&self, text: &str, limit: Option<usize>) -> Vec<PatternMatch> {
+        let limit = limit.unwrap_or(usize::MAX);
         let mut matches = Vec::new();
-        for strategy in &self.strategies {
+        let candidates = self.prefilter.as_ref().map(|set| set.matches(text));
+        if let Some(candidates) = &candidates {
+            let skipped = candidates.len() - candidates.iter().count();
+            self.metrics.record_prefilter_skips(skipped as u64);
+        }
+        let mut regex_slot = 0;
+        for &(pattern_index, ref strategy) in &self.strategies {
+            let to_match = |(start, end)| PatternMatch {
+                start,
+                end,
+                pattern_index,
+                captures: None,
+            };
             match strategy {
                 MatchStrategy::Simple {
                     pattern,
                     boundary_mode,
                     hyphen_mode,
+                    overlapping,
                 } => {
                     // Skip empty patterns
                     if pattern.is_empty() {
                         continue;
                     }
 
-                    #[cfg(test)]
-                    eprintln!(
-                        "DEBUG: Simple match for pattern='{}' text='{}' boundary_mode={:?} hyphen_mode={:?}",
-                        pattern, text, boundary_mode, hyphen_mode
+                    trace!(
+                        pattern = %pattern,
+                        ?boundary_mode,
+                        ?hyphen_mode,
+                        "Simple match"
                     );
 
-                    let indices = text
-                        .match_indices(pattern)
-                        .map(|(start, matched)| (start, start + matched.len()))
-                        .filter(|&(start, end)| match boundary_mode {
-                            WordBoundaryMode::None => true,
-                            WordBoundaryMode::WholeWords | WordBoundaryMode::Partial => {
-                                let is_boundary = Self::is_word_boundary(
-                                    text,
-                                    start,
-                                    end,
-                                    pattern,
-                                    *hyphen_mode,
-                                    *boundary_mode,
-                                );
-                                #[cfg(test)]
-                                eprintln!(
-                                    "DEBUG: Checking boundary for match at [{},{}] => {}",
-                                    start, end, is_boundary
-                                );
-                                is_boundary
+                    let occurrences: Box<dyn Iterator<Item = (usize, usize)>> = if *overlapping {
+                        Box::new(Self::overlapping_indices(text, pattern))
+                    } else {
+                        Box::new(
+                            text.match_indices(pattern)
+                                .map(|(start, matched)| (start, start + matched.len())),
+                        )
+                    };
+                    let indices = occurrences.filter(|&(start, end)| match boundary_mode {
+                        WordBoundaryMode::None => true,
+                        WordBoundaryMode::WholeWords | WordBoundaryMode::Partial => {
+                            let is_boundary = Self::is_word_boundary(
+                                text,
+                                start,
+                                end,
+                                pattern,
+                                *hyphen_mode,
+                                *boundary_mode,
+                            );
+                            trace!(start, end, is_boundary, "Checked word boundary");
+                            is_boundary
+                        }
+                    });
+                    matches.extend(indices.take(limit).map(to_match));
+                }
+                MatchStrategy::Regex {
+                    regex,
+                    boundary_mode,
+                    hyphen_mode,
+                    trim_match,
+                    overlapping,
+                } => {
+                    let slot = regex_slot;
+                    regex_slot += 1;
+                    if candidates.as_ref().is_some_and(|set| !set.matched(slot)) {
+                        continue;
+                    }
+
+                    let keep = |start: usize, end: usize| {
+                        Self::regex_boundary_ok(text, start, end, *boundary_mode, *hyphen_mode)
+                    };
+                    let span =
+                        |start: usize, end: usize| Self::regex_span(text, start, end, *trim_match);
+
+                    let want_captures = self.capture_groups && regex.captures_len() > 1;
+
+                    if *overlapping {
+                        // Restart the search one character after each match start
+                        let mut from = 0;
+                        let mut found = 0;
+                        while found < limit && from <= text.len() {
+                            let Some(m) = regex.find_at(text, from) else {
+                                break;
+                            };
+                            from = m.start() + Self::char_len_at(text, m.start());
+
+                            let (start, end) = span(m.start(), m.end());
+                            if !keep(start, end) {
+                                continue;
                             }
+                            let captures = if want_captures {
+                                regex
+                                    .captures_at(text, m.start())
+                                    .map(|caps| Self::capture_spans(regex, &caps))
+                            } else {
+                                None
+                            };
+                            matches.push(PatternMatch {
+                                start,
+                                end,
+                                pattern_index,
+                                captures,
+                            });
+                            found += 1;
+                        }
+                    } else if want_captures {
+                        matches.extend(
+                            regex
+                                .captures_iter(text)
+                                .filter_map(|caps| {
+                                    let whole = caps.get(0)?;
+                                    let (start, end) = span(whole.start(), whole.end());
+                                    keep(start, end).then(|| PatternMatch {
+                                        start,
+                                        end,
+                                        pattern_index,
+                                        captures: Some(Self::capture_spans(regex, &caps)),
+                                    })
+                                })
+                                .take(limit),
+                        );
+                    } else {
+                        matches.extend(
+                            regex
+                                .find_iter(text)
+                                .map(|m| span(m.start(), m.end()))
+                                .filter(|&(start, end)| keep(start, end))
+                                .take(limit)
+                                .map(to_match),
+                        );
+                    }
+                }
+                MatchStrategy::MultiLiteral {
+                    automaton,
+                    pattern_indices,
+                    boundary_mode,
+                    hyphen_mode,
+                    overlapping,
+                } => {
+                    // Overlapping search reports every occurrence of every literal.
+                    // Unless overlapping matches were requested, each literal only keeps
+                    // occurrences that don't overlap its own previous one, like
+                    // `match_indices`; different literals may always overlap.
+                    let mut next_start = vec![0; pattern_indices.len()];
+                    let mut found = vec![0; pattern_indices.len()];
+                    let mut exhausted = 0;
+                    for m in automaton.find_overlapping_iter(text) {
+                        let id = m.pattern().as_usize();
+                        if (!*overlapping && m.start() < next_start[id]) || found[id] == limit {
+                            continue;
+                        }
+                        next_start[id] = m.end();
+
+                        if *boundary_mode != WordBoundaryMode::None
+                            && !Self::is_word_boundary(
+                                text,
+                                m.start(),
+                                m.end(),
+                                &text[m.range()],
+                                *hyphen_mode,
+                                *boundary_mode,
+                            )
+                        {
+                            continue;
+                        }
+
+                        matches.push(PatternMatch {
+                            start: m.start(),
+                            end: m.end(),
+                            pattern_index: pattern_indices[id],
+                            captures: None,
                         });
-                    matches.extend(indices);
+                        found[id] += 1;
+                        if found[id] == limit {
+                            exhausted += 1;
+                            if exhausted == pattern_indices.len() {
+                                break;
+                            }
+                        }
+                    }
                 }
-                MatchStrategy::Regex {
+                #[cfg(feature = "fancy-regex")]
+                MatchStrategy::FancyRegex {
                     regex,
                     boundary_mode,
                     hyphen_mode,
+                    trim_match,
+                    overlapping,
                 } => {
-                    // For WholeWords mode, boundaries are handled in the pattern itself
-                    // For Partial mode, we need to post-filter the matches
-                    let raw_matches = regex.find_iter(text).map(|m| (m.start(), m.end()));
-
-                    match boundary_mode {
-                        WordBoundaryMode::None => matches.extend(raw_matches),
-                        WordBoundaryMode::WholeWords => matches.extend(raw_matches), // Already has \b in pattern
-                        WordBoundaryMode::Partial => {
-                            matches.extend(raw_matches.filter(|&(start, end)| {
-                                Self::is_word_boundary(
-                                    text,
-                                    start,
-                                    end,
-                                    &text[start..end],
-                                    *hyphen_mode,
-                                    *boundary_mode,
-                                )
-                            }));
+                    let want_captures = self.capture_groups && regex.captures_len() > 1;
+
+                    // fancy_regex has no infallible iterators, so step through the
+                    // text by hand; a match error (e.g. backtrack limit) ends the scan
+                    let mut from = 0;
+                    let mut found = 0;
+                    while found < limit && from <= text.len() {
+                        let (whole, captures) = if want_captures {
+                            let Ok(Some(caps)) = regex.captures_from_pos(text, from) else {
+                                break;
+                            };
+                            let Some(whole) = caps.get(0) else {
+                                break;
+                            };
+                            let spans = Self::group_spans(regex.capture_names(), |index| {
+                                caps.get(index).map(|group| (group.start(), group.end()))
+                            });
+                            ((whole.start(), whole.end()), Some(spans))
+                        } else {
+                            let Ok(Some(m)) = regex.find_from_pos(text, from) else {
+                                break;
+                            };
+                            ((m.start(), m.end()), None)
+                        };
+
+                        // Overlapping scans restart one character after the match
+                        // start; otherwise after its end, stepping over empty matches
+                        from = if *overlapping || whole.0 == whole.1 {
+                            whole.0 + Self::char_len_at(text, whole.0)
+                        } else {
+                            whole.1
+                        };
+
+                        let (start, end) = Self::regex_span(text, whole.0, whole.1, *trim_match);
+                        if !Self::regex_boundary_ok(text, start, end, *boundary_mode, *hyphen_mode)
+                        {
+                            continue;
                         }
+                        matches.push(PatternMatch {
+                            start,
+                            end,
+                            pattern_index,
+                            captures,
+                        });
+                        found += 1;
                     }
                 }
+                MatchStrategy::Fuzzy {
+                    pattern,
+                    max_distance,
+                    case_sensitive,
+                    boundary_mode,
+                    hyphen_mode,
+                } => {
+                    let spans =
+                        Self::fuzzy_spans(text, pattern, *max_distance as usize, *case_sensitive);
+                    matches.extend(
+                        spans
+                            .into_iter()
+                            .filter(|&(start, end)| {
+                                *boundary_mode == WordBoundaryMode::None
+                                    || Self::is_word_boundary(
+                                        text,
+                                        start,
+                                        end,
+                                        &text[start..end],
+                                        *hyphen_mode,
+                                        *boundary_mode,
+                                    )
+                            })
+                            .take(limit)
+                            .map(to_match),
+                    );
+                }
             }
         }
-        matches.sort_unstable_by_key(|&(start, _)| start);
+        matches.sort_unstable_by_key(|m| (m.start, m.pattern_index));
+        matches.truncate(limit);
 
-        #[cfg(test)]
-        eprintln!("DEBUG: Final matches: {:?}", matches);
+        trace!(count = matches.len(), "Found matches");
 
         matches
     }
@@ -572,8 +1784,16 @@ mod tests {
             is_regex: false,
             boundary_mode: WordBoundaryMode::WholeWords,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         };
-        let _matcher1 = PatternMatcher::with_metrics(vec![pattern1.clone()], metrics.clone());
+        let _matcher1 =
+            PatternMatcher::with_metrics(vec![pattern1.clone()], metrics.clone()).unwrap();
         assert_eq!(
             metrics.cache_misses(),
             1,
@@ -581,7 +1801,8 @@ mod tests {
         );
 
         // Create same pattern with word boundaries - should hit cache
-        let _matcher2 = PatternMatcher::with_metrics(vec![pattern1.clone()], metrics.clone());
+        let _matcher2 =
+            PatternMatcher::with_metrics(vec![pattern1.clone()], metrics.clone()).unwrap();
         assert_eq!(
             metrics.cache_misses(),
             1,
@@ -594,8 +1815,15 @@ mod tests {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         };
-        let _matcher3 = PatternMatcher::with_metrics(vec![pattern2], metrics.clone());
+        let _matcher3 = PatternMatcher::with_metrics(vec![pattern2], metrics.clone()).unwrap();
         assert_eq!(
             metrics.cache_misses(),
             2,
@@ -608,7 +1836,7 @@ mod tests {
         let metrics = Arc::new(MemoryMetrics::new());
 
         // Define all possible mode combinations
-        let modes = vec![
+        let modes = [
             (WordBoundaryMode::WholeWords, HyphenMode::Boundary),
             (WordBoundaryMode::WholeWords, HyphenMode::Joining),
             (WordBoundaryMode::None, HyphenMode::Boundary),
@@ -950,9 +2178,17 @@ mod tests {
                         is_regex: false,
                         boundary_mode: *boundary_mode,
                         hyphen_mode: *hyphen_mode,
+                        case_mode: CaseMode::default(),
+                        multiline: false,
+                        match_whole_line: false,
+                        trim_whole_line: false,
+                        allow_overlapping: false,
+                        fuzzy_distance: None,
+                        name: None,
                     }],
                     metrics.clone(),
-                );
+                )
+                .unwrap();
 
                 let matches = matcher.find_matches(text);
                 assert_eq!(
@@ -978,7 +2214,13 @@ mod tests {
             // Basic regex with Unicode
             (r"café\d+", "café123 test café456", 2), // Multiple matches
             (r"café\w+", "café_test caféBar", 2),    // Word chars
-            (r"café\s+\w+", "café test café123", 2), // Space and word
+            (r"café\s+\w+", "café test café 123", 2), // Space and word
+            (r"café\s+\w+", "café test café123", 1), // \s+ needs whitespace
+            // Other accented prefixes take the same generic path
+            (r"naïve\s+\w+", "naïve approach naïve  test", 2),
+            (r"naïve\s+\w+", "unnaïve test", 0), // No boundary before "naïve"
+            (r"über\w+", "übergang überall", 2),
+            (r"über\w+", "Müller überschrift", 1), // Only at a word start
             // Complex patterns
             (r"café[A-Za-z]+", "caféTest cafétest", 2), // Case variants
             (r"café\p{L}+", "caféTest caféКафе", 2),    // Unicode letters
@@ -996,9 +2238,17 @@ mod tests {
                     is_regex: true,
                     boundary_mode: WordBoundaryMode::WholeWords,
                     hyphen_mode: HyphenMode::default(),
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 }],
                 metrics.clone(),
-            );
+            )
+            .unwrap();
 
             let matches = matcher.find_matches(text);
             assert_eq!(
@@ -1095,9 +2345,17 @@ mod tests {
                     is_regex: false,
                     boundary_mode: WordBoundaryMode::WholeWords,
                     hyphen_mode: HyphenMode::Boundary,
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 }],
                 metrics.clone(),
-            );
+            )
+            .unwrap();
 
             let matches = matcher.find_matches(text);
             assert_eq!(
@@ -1178,9 +2436,17 @@ mod tests {
                     is_regex: false,
                     boundary_mode: WordBoundaryMode::Partial,
                     hyphen_mode: HyphenMode::Boundary,
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 }],
                 metrics.clone(),
-            );
+            )
+            .unwrap();
 
             let matches = matcher.find_matches(text);
             assert_eq!(
@@ -1262,9 +2528,17 @@ mod tests {
                     is_regex: false,
                     boundary_mode: WordBoundaryMode::WholeWords,
                     hyphen_mode: HyphenMode::Boundary,
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 }],
                 metrics.clone(),
-            );
+            )
+            .unwrap();
 
             // Test Partial mode
             let partial_matcher = PatternMatcher::with_metrics(
@@ -1273,9 +2547,17 @@ mod tests {
                     is_regex: false,
                     boundary_mode: WordBoundaryMode::Partial,
                     hyphen_mode: HyphenMode::Boundary,
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 }],
                 metrics.clone(),
-            );
+            )
+            .unwrap();
 
             let whole_words_matches = whole_words_matcher.find_matches(text);
             let partial_matches = partial_matcher.find_matches(text);
@@ -1391,9 +2673,17 @@ mod tests {
                     is_regex,
                     boundary_mode,
                     hyphen_mode: HyphenMode::default(),
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 }],
                 metrics.clone(),
-            );
+            )
+            .unwrap();
 
             let matches = matcher.find_matches(text);
             assert_eq!(
@@ -1406,4 +2696,821 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_smart_case() {
+        let metrics = Arc::new(MemoryMetrics::new());
+        let text = "TODO: fix todo list, ToDo later, fooXbar";
+
+        // Test cases: (pattern, is_regex, case_mode, expected_matches, comment)
+        let test_cases = vec![
+            (
+                "todo",
+                false,
+                CaseMode::Smart,
+                3,
+                "Lowercase literal is insensitive",
+            ),
+            (
+                "TODO",
+                false,
+                CaseMode::Smart,
+                1,
+                "Uppercase literal is sensitive",
+            ),
+            (
+                "ToDo",
+                false,
+                CaseMode::Smart,
+                1,
+                "Mixed case literal is sensitive",
+            ),
+            (
+                "todo",
+                false,
+                CaseMode::Sensitive,
+                1,
+                "Sensitive mode is exact",
+            ),
+            (
+                "TODO",
+                false,
+                CaseMode::Insensitive,
+                3,
+                "Insensitive mode ignores case",
+            ),
+            (
+                r"todo\W",
+                true,
+                CaseMode::Smart,
+                3,
+                "Escapes are not literal uppercase",
+            ),
+            (
+                r"\p{Lu}odo",
+                true,
+                CaseMode::Smart,
+                3,
+                "Unicode classes are not literal uppercase",
+            ),
+            (
+                r"(?P<Item>todo)",
+                true,
+                CaseMode::Smart,
+                3,
+                "Group names are ignored",
+            ),
+            (
+                r"T\w+",
+                true,
+                CaseMode::Smart,
+                2,
+                "Literal uppercase in regex is sensitive",
+            ),
+            (
+                "foo.bar",
+                false,
+                CaseMode::Sensitive,
+                0,
+                "Literal punctuation is not a regex",
+            ),
+            (
+                "foo.bar",
+                false,
+                CaseMode::Insensitive,
+                0,
+                "Literal punctuation is not a regex",
+            ),
+            (
+                "foo.bar",
+                false,
+                CaseMode::Smart,
+                0,
+                "Literal punctuation is not a regex",
+            ),
+            (
+                "todo:",
+                false,
+                CaseMode::Smart,
+                1,
+                "Literal punctuation matches itself",
+            ),
+        ];
+
+        for (pattern, is_regex, case_mode, expected_matches, comment) in test_cases {
+            let matcher = PatternMatcher::with_metrics(
+                vec![PatternDefinition {
+                    text: pattern.to_string(),
+                    is_regex,
+                    boundary_mode: WordBoundaryMode::None,
+                    hyphen_mode: HyphenMode::default(),
+                    case_mode,
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
+                }],
+                metrics.clone(),
+            )
+            .unwrap();
+
+            assert_eq!(
+                matcher.find_matches(text).len(),
+                expected_matches,
+                "Failed for pattern '{}' with {:?}: {}",
+                pattern,
+                case_mode,
+                comment
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_patterns_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("patterns.txt");
+        std::fs::write(
+            &path,
+            "# deprecated APIs\nold_api\n\n  legacy_call  \n#disabled\nlegacy_io\n",
+        )
+        .unwrap();
+
+        let patterns =
+            PatternDefinition::load_from_file(&path, false, WordBoundaryMode::WholeWords).unwrap();
+        let texts: Vec<&str> = patterns.iter().map(|p| p.text.as_str()).collect();
+        assert_eq!(texts, ["old_api", "legacy_call", "legacy_io"]);
+        assert!(patterns
+            .iter()
+            .all(|p| !p.is_regex && p.boundary_mode == WordBoundaryMode::WholeWords));
+
+        let matcher = PatternMatcher::from_definitions(patterns).unwrap();
+        assert_eq!(
+            matcher
+                .find_matches("old_api legacy_call my_old_api legacy_io")
+                .len(),
+            3
+        );
+
+        // Invalid regexes are reported with their line number
+        std::fs::write(&path, "# header\nvalid_\\d+\n[unclosed\n").unwrap();
+        let err = PatternDefinition::load_from_file(&path, true, WordBoundaryMode::None)
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("patterns.txt:3"), "unexpected error: {}", err);
+
+        // Missing files surface as FileNotFound
+        let missing = dir.path().join("missing.txt");
+        assert!(matches!(
+            PatternDefinition::load_from_file(&missing, false, WordBoundaryMode::None),
+            Err(SearchError::FileNotFound(_))
+        ));
+    }
+
+    #[test]
+    fn test_matches_report_pattern_index() {
+        let mut named = PatternDefinition::new(r"fix\w+".to_string(), true, WordBoundaryMode::None);
+        named.name = Some("fixes".to_string());
+        let matcher = PatternMatcher::from_definitions(vec![
+            PatternDefinition::new("todo".to_string(), false, WordBoundaryMode::None),
+            // Empty patterns are skipped but still take up an index
+            PatternDefinition::new(String::new(), false, WordBoundaryMode::None),
+            named,
+        ])
+        .unwrap();
+
+        let matches = matcher.find_matches("fixme todo fixit");
+        let found: Vec<(usize, usize, usize)> = matches
+            .iter()
+            .map(|m| (m.start, m.end, m.pattern_index))
+            .collect();
+        assert_eq!(found, vec![(0, 5, 2), (6, 10, 0), (11, 16, 2)]);
+
+        assert_eq!(matcher.pattern_name(0), None);
+        assert_eq!(matcher.pattern_name(2), Some("fixes"));
+        assert_eq!(matcher.pattern_name(3), None);
+    }
+
+    #[test]
+    fn test_capture_groups() {
+        let definitions = vec![
+            PatternDefinition::new(
+                r"fn (?P<name>\w+)\((\w*)".to_string(),
+                true,
+                WordBoundaryMode::None,
+            ),
+            PatternDefinition::new("let".to_string(), false, WordBoundaryMode::None),
+        ];
+        let text = "fn alpha(x) let fn beta()";
+
+        // Captures are only extracted when asked for
+        let matcher = PatternMatcher::from_definitions(definitions.clone()).unwrap();
+        assert!(matcher
+            .find_matches(text)
+            .iter()
+            .all(|m| m.captures.is_none()));
+
+        let matcher = PatternMatcher::from_definitions(definitions)
+            .unwrap()
+            .with_captures(true);
+        let matches = matcher.find_matches(text);
+        assert_eq!(matches.len(), 3);
+        assert_eq!(
+            matches[0].captures,
+            Some(vec![
+                ("1".to_string(), 3, 8),
+                ("name".to_string(), 3, 8),
+                ("2".to_string(), 9, 10)
+            ])
+        );
+        // Literal patterns have no groups to report
+        assert_eq!(matches[1].captures, None);
+        // Empty groups still participate in the match
+        assert_eq!(
+            matches[2].captures,
+            Some(vec![
+                ("1".to_string(), 19, 23),
+                ("name".to_string(), 19, 23),
+                ("2".to_string(), 24, 24)
+            ])
+        );
+    }
+
+    #[test]
+    fn test_multi_literal_matches_per_pattern_path() {
+        // A small corpus with identifiers, hyphenated words and repeated tokens
+        let words = [
+            "alpha",
+            "beta",
+            "gamma",
+            "delta",
+            "alpha_beta",
+            "beta-gamma",
+            "aaaa",
+            "TODO",
+            "FIXME",
+            "todo_list",
+            "x",
+            "naïve",
+            "café",
+            "TODOTODO",
+        ];
+        let corpus: String = (0..2000)
+            .map(|i| words[(i * 7 + i / 3) % words.len()])
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let mut literals: Vec<String> = words.iter().map(|w| w.to_string()).collect();
+        literals.extend(["aa", "al", "ph", "TODO", "ta", "é"].map(String::from));
+        literals.extend((0..40).map(|i| format!("word{}", i)));
+
+        for boundary_mode in [
+            WordBoundaryMode::None,
+            WordBoundaryMode::WholeWords,
+            WordBoundaryMode::Partial,
+        ] {
+            let definitions: Vec<PatternDefinition> = literals
+                .iter()
+                .map(|text| PatternDefinition::new(text.clone(), false, boundary_mode))
+                .collect();
+
+            let combined = PatternMatcher::from_definitions(definitions.clone()).unwrap();
+            assert!(combined
+                .strategies
+                .iter()
+                .any(|(_, s)| matches!(s, MatchStrategy::MultiLiteral { .. })));
+
+            // Reference: every pattern matched on its own
+            let mut expected: Vec<PatternMatch> = Vec::new();
+            for (pattern_index, definition) in definitions.into_iter().enumerate() {
+                let single = PatternMatcher::from_definitions(vec![definition]).unwrap();
+                expected.extend(
+                    single
+                        .find_matches(&corpus)
+                        .into_iter()
+                        .map(|m| PatternMatch { pattern_index, ..m }),
+                );
+            }
+            expected.sort_unstable_by_key(|m| (m.start, m.pattern_index));
+
+            let found = combined.find_matches(&corpus);
+            assert_eq!(found, expected, "mismatch for {:?}", boundary_mode);
+
+            // Per-pattern limits behave the same way too
+            let limited = combined.find_matches_limited(&corpus, Some(3));
+            assert_eq!(limited.len(), 3);
+            assert_eq!(limited[..], expected[..3]);
+        }
+    }
+
+    #[test]
+    fn test_regex_prefilter() {
+        let mut whole_word =
+            PatternDefinition::new(r"te\w+".to_string(), true, WordBoundaryMode::WholeWords);
+        whole_word.hyphen_mode = HyphenMode::Boundary;
+        let definitions = vec![
+            // Overlapping patterns: both match inside "testing"
+            PatternDefinition::new(r"\w+ing".to_string(), true, WordBoundaryMode::None),
+            PatternDefinition::new(r"test\w*".to_string(), true, WordBoundaryMode::None),
+            // Wrapped in \b...\b by the matcher
+            whole_word,
+            PatternDefinition::new(r"te\w".to_string(), true, WordBoundaryMode::Partial),
+            PatternDefinition::new(r"\d{4}-\d{2}".to_string(), true, WordBoundaryMode::None),
+        ];
+
+        // A handful of regexes just run on their own
+        let few = PatternMatcher::from_definitions(definitions[..PREFILTER_THRESHOLD - 1].to_vec())
+            .unwrap();
+        assert!(few.prefilter.is_none());
+
+        let metrics = Arc::new(MemoryMetrics::new());
+        let matcher = PatternMatcher::with_metrics(definitions.clone(), metrics.clone()).unwrap();
+        assert!(matcher.prefilter.is_some());
+
+        for text in [
+            "testing the tester on a test-case",
+            "nothing here but text",
+            "no hits",
+            "release 2024-01 retested",
+        ] {
+            let mut expected: Vec<PatternMatch> = Vec::new();
+            for (pattern_index, definition) in definitions.iter().cloned().enumerate() {
+                let single = PatternMatcher::from_definitions(vec![definition]).unwrap();
+                assert!(single.prefilter.is_none());
+                expected.extend(
+                    single
+                        .find_matches(text)
+                        .into_iter()
+                        .map(|m| PatternMatch { pattern_index, ..m }),
+                );
+            }
+            expected.sort_unstable_by_key(|m| (m.start, m.pattern_index));
+            assert_eq!(
+                matcher.find_matches(text),
+                expected,
+                "mismatch for {:?}",
+                text
+            );
+        }
+
+        // Skipped regexes per text: the date; the date and "test\w*"; all five;
+        // "\w+ing" and the whole-word pattern
+        assert_eq!(metrics.get_stats().prefilter_skips, 1 + 2 + 5 + 2);
+    }
+
+    #[test]
+    fn test_match_whole_line() {
+        let text = "unsafe\n    unsafe\nunsafe  \r\nunsafe\r\nunsafe { x }\nnot unsafe";
+        let whole_line = |text: &str, is_regex: bool, boundary_mode, trim| {
+            let mut definition = PatternDefinition::new(text.to_string(), is_regex, boundary_mode);
+            definition.match_whole_line = true;
+            definition.trim_whole_line = trim;
+            definition
+        };
+        let spans = |definition: PatternDefinition| -> Vec<(usize, usize)> {
+            PatternMatcher::from_definitions(vec![definition])
+                .unwrap()
+                .find_matches(text)
+                .iter()
+                .map(|m| (m.start, m.end))
+                .collect()
+        };
+
+        // Untrimmed: indentation and trailing whitespace disqualify a line, CRLF doesn't
+        assert_eq!(
+            spans(whole_line("unsafe", false, WordBoundaryMode::None, false)),
+            vec![(0, 6), (28, 34)]
+        );
+        // Trimmed: the match excludes the surrounding whitespace
+        assert_eq!(
+            spans(whole_line("unsafe", false, WordBoundaryMode::None, true)),
+            vec![(0, 6), (11, 17), (18, 24), (28, 34)]
+        );
+
+        // Word boundary modes don't change which whole lines match
+        for boundary_mode in [WordBoundaryMode::WholeWords, WordBoundaryMode::Partial] {
+            assert_eq!(
+                spans(whole_line("unsafe", false, boundary_mode, false)),
+                vec![(0, 6), (28, 34)]
+            );
+            assert_eq!(
+                spans(whole_line(r"uns\w+", true, boundary_mode, false)),
+                vec![(0, 6), (28, 34)]
+            );
+        }
+
+        // Regexes are wrapped in ^(?:...)$, so alternation applies to the whole line
+        assert_eq!(
+            spans(whole_line(
+                "unsafe|not",
+                true,
+                WordBoundaryMode::None,
+                false
+            )),
+            vec![(0, 6), (28, 34)]
+        );
+        // Only the missing anchor is added, so a start anchor alone still
+        // has to match up to the end of the line
+        assert_eq!(
+            spans(whole_line(
+                r"^unsafe \{",
+                true,
+                WordBoundaryMode::None,
+                false
+            )),
+            vec![]
+        );
+        assert_eq!(
+            spans(whole_line(
+                r"^unsafe \{.*",
+                true,
+                WordBoundaryMode::None,
+                false
+            )),
+            vec![(36, 48)]
+        );
+        assert_eq!(
+            spans(whole_line(r"uns\w+$", true, WordBoundaryMode::None, true)),
+            vec![(0, 6), (11, 17), (28, 34)]
+        );
+        assert_eq!(
+            spans(whole_line(r"^unsafe$", true, WordBoundaryMode::None, false)),
+            vec![(0, 6), (28, 34)]
+        );
+    }
+
+    #[test]
+    fn test_overlapping_matches() {
+        let overlapping = |text: &str, is_regex: bool| {
+            let mut definition =
+                PatternDefinition::new(text.to_string(), is_regex, WordBoundaryMode::None);
+            definition.allow_overlapping = true;
+            definition
+        };
+        let starts = |matcher: &PatternMatcher, text: &str| -> Vec<(usize, usize)> {
+            matcher
+                .find_matches(text)
+                .iter()
+                .map(|m| {
+                    assert!(text.is_char_boundary(m.start) && text.is_char_boundary(m.end));
+                    (m.start, m.pattern_index)
+                })
+                .collect()
+        };
+
+        // Default behavior is unchanged
+        let matcher = PatternMatcher::new(vec!["aa".to_string()]).unwrap();
+        assert_eq!(starts(&matcher, "aaaa"), vec![(0, 0), (2, 0)]);
+
+        let matcher = PatternMatcher::from_definitions(vec![overlapping("aa", false)]).unwrap();
+        assert_eq!(starts(&matcher, "aaaa"), vec![(0, 0), (1, 0), (2, 0)]);
+
+        // Multi-byte repeats advance by whole characters, for literals and regexes
+        let text = "éééé 日本日本日本";
+        let matcher = PatternMatcher::from_definitions(vec![
+            overlapping("éé", false),
+            overlapping("日本日本", true),
+        ])
+        .unwrap();
+        assert_eq!(
+            starts(&matcher, text),
+            vec![(0, 0), (2, 0), (4, 0), (9, 1), (15, 1)]
+        );
+
+        // Combined literals and case-insensitive regexes honor the setting too
+        let mut insensitive = overlapping("ÉÉ", false);
+        insensitive.case_mode = CaseMode::Insensitive;
+        let matcher = PatternMatcher::from_definitions(vec![
+            overlapping("éé", false),
+            overlapping("éééé", false),
+            insensitive,
+        ])
+        .unwrap();
+        assert_eq!(
+            starts(&matcher, "ééééé"),
+            vec![
+                (0, 0),
+                (0, 1),
+                (0, 2),
+                (2, 0),
+                (2, 1),
+                (2, 2),
+                (4, 0),
+                (4, 2),
+                (6, 0),
+                (6, 2)
+            ]
+        );
+
+        // Limits still apply per pattern
+        let matcher = PatternMatcher::from_definitions(vec![overlapping("a+", true)]).unwrap();
+        let limited = matcher.find_matches_limited("aaaa", Some(2));
+        assert_eq!(
+            limited.iter().map(|m| (m.start, m.end)).collect::<Vec<_>>(),
+            vec![(0, 4), (1, 4)]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_matches() {
+        let fuzzy = |text: &str, max_distance: u8| {
+            let mut definition =
+                PatternDefinition::new(text.to_string(), false, WordBoundaryMode::None);
+            definition.fuzzy_distance = Some(max_distance);
+            definition
+        };
+        let spans = |definition: PatternDefinition, text: &str| -> Vec<(usize, usize)> {
+            PatternMatcher::from_definitions(vec![definition])
+                .unwrap()
+                .find_matches(text)
+                .iter()
+                .map(|m| (m.start, m.end))
+                .collect()
+        };
+
+        // Substitution, insertion and deletion
+        assert_eq!(
+            spans(fuzzy("separate", 1), "a seperate line"),
+            vec![(2, 10)]
+        );
+        assert_eq!(spans(fuzzy("color", 1), "colour"), vec![(0, 6)]);
+        assert_eq!(spans(fuzzy("hello", 1), "helo world"), vec![(0, 4)]);
+
+        // Exact matches win, and nothing is reported beyond the budget
+        assert_eq!(
+            spans(fuzzy("hello", 1), "hallo hello"),
+            vec![(0, 5), (6, 11)]
+        );
+        assert!(spans(fuzzy("hello", 0), "hallo").is_empty());
+        assert!(spans(fuzzy("hello", 1), "hxllx").is_empty());
+
+        // Matches never span lines and don't overlap each other
+        assert_eq!(
+            spans(fuzzy("color", 1), "colr and colour\ncolor"),
+            vec![(0, 4), (9, 15), (16, 21)]
+        );
+
+        // Unicode text is compared by character and reported on char boundaries
+        assert_eq!(spans(fuzzy("naive", 1), "a naïve idea"), vec![(2, 8)]);
+        assert_eq!(spans(fuzzy("東都", 1), "東京都へ"), vec![(0, 9)]);
+
+        let mut insensitive = fuzzy("separate", 1);
+        insensitive.case_mode = CaseMode::Insensitive;
+        assert_eq!(spans(insensitive, "SEPERATE"), vec![(0, 8)]);
+
+        let matcher = PatternMatcher::from_definitions(vec![fuzzy("color", 1)]).unwrap();
+        assert_eq!(
+            matcher.find_matches_limited("colr colour", Some(1)).len(),
+            1
+        );
+    }
+
+    #[cfg(feature = "fancy-regex")]
+    #[test]
+    fn test_fancy_regex_fallback() {
+        let spans = |definition: PatternDefinition, text: &str| -> Vec<(usize, usize)> {
+            PatternMatcher::from_definitions(vec![definition])
+                .unwrap()
+                .find_matches(text)
+                .iter()
+                .map(|m| (m.start, m.end))
+                .collect()
+        };
+
+        // Look-ahead and look-behind
+        let lookahead =
+            PatternDefinition::new("foo(?!_test)".to_string(), true, WordBoundaryMode::None);
+        assert_eq!(
+            spans(lookahead, "foo foo_test foobar"),
+            vec![(0, 3), (13, 16)]
+        );
+        let lookbehind =
+            PatternDefinition::new(r"(?<=\$)\d+".to_string(), true, WordBoundaryMode::None);
+        assert_eq!(spans(lookbehind, "12 $34 5"), vec![(4, 6)]);
+
+        // Boundary modes apply as they do for ordinary regexes
+        let partial =
+            PatternDefinition::new("foo(?=bar)".to_string(), true, WordBoundaryMode::Partial);
+        assert_eq!(spans(partial, "foobar xfoobar"), vec![(0, 3)]);
+
+        // Backreferences, with capture extraction and overlapping matches
+        let mut repeated =
+            PatternDefinition::new(r"(\w)\1".to_string(), true, WordBoundaryMode::None);
+        let matcher = PatternMatcher::from_definitions(vec![repeated.clone()])
+            .unwrap()
+            .with_captures(true);
+        let matches = matcher.find_matches("aa bcc");
+        assert_eq!(
+            matches.iter().map(|m| (m.start, m.end)).collect::<Vec<_>>(),
+            vec![(0, 2), (4, 6)]
+        );
+        assert_eq!(matches[1].captures, Some(vec![("1".to_string(), 4, 5)]));
+        repeated.allow_overlapping = true;
+        assert_eq!(spans(repeated, "aaa"), vec![(0, 2), (1, 3)]);
+    }
+
+    #[cfg(feature = "fancy-regex")]
+    #[test]
+    fn test_fancy_regex_named_groups_match_plain_regex() {
+        let captures = |text: &str| {
+            let definition = PatternDefinition::new(text.to_string(), true, WordBoundaryMode::None);
+            let matcher = PatternMatcher::from_definitions(vec![definition])
+                .unwrap()
+                .with_captures(true);
+            matcher.find_matches("v1.2")[0].captures.clone()
+        };
+
+        // Named groups are keyed by number and name on both engines
+        let expected = Some(vec![
+            ("1".to_string(), 1, 2),
+            ("major".to_string(), 1, 2),
+            ("2".to_string(), 3, 4),
+        ]);
+        assert_eq!(captures(r"v(?P<major>\d)\.(\d)"), expected);
+        assert_eq!(captures(r"v(?P<major>\d)\.(\d)(?!\d)"), expected);
+    }
+
+    #[cfg(not(feature = "fancy-regex"))]
+    #[test]
+    fn test_lookaround_requires_fancy_regex() {
+        match compile_regex("foo(?!_test)") {
+            Err(message) => {
+                assert!(message.contains("enable the fancy-regex feature for look-around support"))
+            }
+            Ok(_) => panic!("look-ahead should not compile without fancy-regex"),
+        }
+        match compile_regex("[invalid") {
+            Err(message) => assert!(!message.contains("fancy-regex")),
+            Ok(_) => panic!("unclosed class should not compile"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_regex_returns_error() {
+        let result = PatternMatcher::from_definitions(vec![
+            PatternDefinition::new("TODO".to_string(), false, WordBoundaryMode::None),
+            PatternDefinition::new("[invalid".to_string(), true, WordBoundaryMode::None),
+        ]);
+        match result {
+            Err(SearchError::InvalidPattern(message)) => {
+                assert!(message.contains("'[invalid'"));
+                assert!(message.contains("unclosed character class"));
+            }
+            other => panic!("expected an invalid pattern error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_pattern_validate() {
+        let regex =
+            |text: &str| PatternDefinition::new(text.to_string(), true, WordBoundaryMode::None);
+
+        assert_eq!(regex(r"fn \w+").validate(), Ok(()));
+        assert_eq!(regex("").validate(), Err(PatternError::Empty));
+        assert!(PatternError::Empty.is_warning());
+
+        // Syntax errors carry the character position of the problem
+        let error = regex("é[b").validate().unwrap_err();
+        assert!(!error.is_warning());
+        assert_eq!(
+            error,
+            PatternError::InvalidRegex {
+                pattern: "é[b".to_string(),
+                message: "unclosed character class".to_string(),
+                position: Some(1),
+            }
+        );
+
+        let mut fuzzy = regex("colou?r");
+        fuzzy.fuzzy_distance = Some(1);
+        assert!(matches!(fuzzy.validate(), Err(PatternError::FuzzyRegex(_))));
+
+        // Boundary modes the matcher won't apply
+        let mut alternation = regex("foo|bar");
+        alternation.boundary_mode = WordBoundaryMode::WholeWords;
+        let error = alternation.validate().unwrap_err();
+        assert!(matches!(error, PatternError::BoundaryModeIgnored { .. }));
+        assert!(error.is_warning());
+
+        let mut anchored = regex(r"\bfoo|bar\b");
+        anchored.boundary_mode = WordBoundaryMode::WholeWords;
+        assert_eq!(anchored.validate(), Ok(()));
+
+        let mut whole_line =
+            PatternDefinition::new("unsafe".to_string(), false, WordBoundaryMode::Partial);
+        whole_line.match_whole_line = true;
+        assert!(matches!(
+            whole_line.validate(),
+            Err(PatternError::BoundaryModeIgnored { .. })
+        ));
+    }
+
+    #[test]
+    fn test_pattern_cache_evicts_least_recently_used() {
+        // A private cache, so the global one shared by other tests is untouched
+        let cache = PatternCache::new(3);
+        let key = |text: &str| {
+            (
+                text.to_string(),
+                false,
+                WordBoundaryMode::None,
+                HyphenMode::default(),
+                true,
+                false,
+                false,
+                false,
+                false,
+                None,
+            )
+        };
+        let strategy = |text: &str| MatchStrategy::Simple {
+            pattern: text.to_string(),
+            boundary_mode: WordBoundaryMode::None,
+            hyphen_mode: HyphenMode::default(),
+            overlapping: false,
+        };
+
+        for text in ["a", "b", "c"] {
+            assert_eq!(cache.insert(key(text), strategy(text)), 0);
+        }
+        // Touch "a" so "b" becomes the least recently used entry
+        assert!(cache.get(&key("a")).is_some());
+
+        assert_eq!(cache.insert(key("d"), strategy("d")), 1);
+        assert_eq!(cache.insert(key("e"), strategy("e")), 1);
+        assert_eq!(cache.len(), 3);
+        assert!(cache.get(&key("b")).is_none());
+        assert!(cache.get(&key("c")).is_none());
+        for text in ["a", "d", "e"] {
+            assert!(cache.get(&key(text)).is_some(), "{} should survive", text);
+        }
+
+        // Shrinking evicts immediately, keeping the most recently used
+        assert_eq!(cache.set_capacity(1), 2);
+        assert!(cache.get(&key("e")).is_some());
+
+        cache.clear();
+        assert_eq!(cache.len(), 0);
+    }
+
+    #[test]
+    fn test_matches_within_lines() {
+        let within_lines = |text: &str, is_regex: bool| {
+            let def = PatternDefinition::new(text.to_string(), is_regex, WordBoundaryMode::None);
+            PatternMatcher::from_definitions(vec![def])
+                .unwrap()
+                .matches_within_lines()
+        };
+        assert!(within_lines("TODO", false));
+        assert!(within_lines(r"fn \w+\(", true));
+        assert!(!within_lines(r"(?m)^\s*$", true));
+        assert!(within_lines(r"(?m)^ERROR.*$", true));
+        assert!(!within_lines(r"^ERROR", true));
+        assert!(!within_lines(r"end\z", true));
+        assert!(!within_lines(r"a\nb", true));
+        assert!(!within_lines(r"[^x]+", true));
+
+        let mut multiline = PatternDefinition::new("a.b".to_string(), true, WordBoundaryMode::None);
+        multiline.multiline = true;
+        assert!(!PatternMatcher::from_definitions(vec![multiline])
+            .unwrap()
+            .matches_within_lines());
+    }
+
+    #[test]
+    fn test_per_pattern_values() {
+        let modes = [WordBoundaryMode::WholeWords, WordBoundaryMode::None];
+
+        // Each pattern takes its own value; later ones inherit the last value
+        assert_eq!(
+            per_pattern_values(&modes, 3, WordBoundaryMode::Partial, "--boundary-mode").unwrap(),
+            vec![
+                WordBoundaryMode::WholeWords,
+                WordBoundaryMode::None,
+                WordBoundaryMode::None
+            ]
+        );
+        assert_eq!(
+            per_pattern_values(&[], 2, HyphenMode::Joining, "--hyphen-mode").unwrap(),
+            vec![HyphenMode::Joining, HyphenMode::Joining]
+        );
+
+        let err =
+            per_pattern_values(&modes, 1, WordBoundaryMode::None, "--boundary-mode").unwrap_err();
+        assert!(
+            matches!(err, SearchError::ConfigError(ref msg) if msg.contains("--boundary-mode"))
+        );
+
+        assert_eq!(
+            "partial".parse::<WordBoundaryMode>().unwrap(),
+            WordBoundaryMode::Partial
+        );
+        assert!("loose".parse::<WordBoundaryMode>().is_err());
+        assert_eq!(
+            "boundary".parse::<HyphenMode>().unwrap(),
+            HyphenMode::Boundary
+        );
+        assert!("hyphen".parse::<HyphenMode>().is_err());
+    }
 }
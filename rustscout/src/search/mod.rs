@@ -91,11 +91,20 @@
 /// let mut reader = BufReader::with_capacity(BUFFER_CAPACITY, file);
 /// let mut line_buffer = String::with_capacity(256);
 /// ```
+pub mod archive;
+pub mod cancel;
+pub mod diff;
 pub mod engine;
 pub mod interactive_search;
+pub mod iter;
 pub mod matcher;
 pub mod processor;
+pub mod progress;
 
-pub use engine::search;
+pub use cancel::CancellationToken;
+pub use diff::ChangedLines;
+pub use engine::{search, search_reader, search_with_callback};
+pub use iter::{search_iter, SearchIter};
 pub use matcher::PatternMatcher;
 pub use processor::FileProcessor;
+pub use progress::{ProgressHook, SearchProgress};
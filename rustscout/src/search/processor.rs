@@ -1,11 +1,16 @@
 use memmap2::Mmap;
+use rayon::prelude::*;
+use std::borrow::Cow;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::path::Path;
-use tracing::{trace, warn};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use tracing::{debug, trace, warn};
 
-use super::matcher::PatternMatcher;
+use super::matcher::{PatternMatch, PatternMatcher};
 use crate::config::EncodingMode;
+use crate::encoding::{LineEnding, TextEncoding};
 use crate::errors::{SearchError, SearchResult};
 use crate::metrics::MemoryMetrics;
 use crate::results::{FileResult, Match};
@@ -14,17 +19,29 @@ use crate::results::{FileResult, Match};
 const BUFFER_CAPACITY: usize = 65536;
 pub(crate) const SMALL_FILE_THRESHOLD: u64 = 32 * 1024; // 32KB
 pub(crate) const LARGE_FILE_THRESHOLD: u64 = 10 * 1024 * 1024; // 10MB
-
-/// Helper function to decode bytes into a String according to encoding mode
-fn decode_bytes(bytes: &[u8], path: &Path, encoding_mode: EncodingMode) -> SearchResult<String> {
+/// Chunks per worker thread when matching one large file in parallel, so a
+/// slow chunk doesn't hold up the rest
+const CHUNKS_PER_THREAD: usize = 4;
+
+/// Bytes sampled from the start of a file by [`looks_minified`]
+pub const MINIFIED_SAMPLE_SIZE: usize = 64 * 1024;
+/// Average line length, in bytes, above which a file looks minified
+pub const MINIFIED_LINE_LENGTH: usize = 1000;
+
+/// Helper function to decode bytes according to encoding mode.
+///
+/// Valid UTF-8 is borrowed rather than copied, so memory-mapped files can be
+/// searched in place.
+fn decode_bytes<'a>(
+    bytes: &'a [u8],
+    path: &Path,
+    encoding_mode: EncodingMode,
+) -> SearchResult<Cow<'a, str>> {
     match encoding_mode {
         EncodingMode::FailFast => {
             // Try converting to UTF-8 via from_utf8 first to avoid an extra copy if valid
             match std::str::from_utf8(bytes) {
-                Ok(valid_str) => {
-                    // Already valid; just clone into a String
-                    Ok(valid_str.to_owned())
-                }
+                Ok(valid_str) => Ok(Cow::Borrowed(valid_str)),
                 Err(_utf8_err) => {
                     // It's invalid; now create a FromUtf8Error by reattempting from_utf8 on a Vec
                     // (only in the error path). This preserves the exact error data for the test.
@@ -41,10 +58,17 @@ fn decode_bytes(bytes: &[u8], path: &Path, encoding_mode: EncodingMode) -> Searc
             // from_utf8_lossy can replace invalid bytes with
             let cow = String::from_utf8_lossy(bytes);
             // If it's Owned, at least one invalid sequence was replaced.
-            if let std::borrow::Cow::Owned(_) = cow {
+            if let Cow::Owned(_) = cow {
                 warn!("Invalid UTF-8 replaced in file: {}", path.display());
             }
-            Ok(cow.into_owned())
+            Ok(cow)
+        }
+        EncodingMode::Detect => {
+            let encoding = TextEncoding::detect(bytes);
+            if !encoding.is_plain_utf8() {
+                trace!("Decoding {} as {}", path.display(), encoding.name());
+            }
+            Ok(encoding.decode(bytes))
         }
     }
 }
@@ -57,6 +81,13 @@ pub struct FileProcessor {
     context_before: usize,
     context_after: usize,
     encoding_mode: EncodingMode,
+    invert_match: bool,
+    max_count: Option<usize>,
+    skip_minified: bool,
+    parallel_large_files: bool,
+    minified_skipped: AtomicUsize,
+    slowest_file_nanos: AtomicU64,
+    stopped: AtomicBool,
 }
 
 impl FileProcessor {
@@ -73,205 +104,402 @@ impl FileProcessor {
             context_before,
             context_after,
             encoding_mode,
+            invert_match: false,
+            max_count: None,
+            skip_minified: false,
+            parallel_large_files: false,
+            minified_skipped: AtomicUsize::new(0),
+            slowest_file_nanos: AtomicU64::new(0),
+            stopped: AtomicBool::new(false),
         }
     }
 
+    /// Reports lines that do *not* match any pattern instead of matching ones.
+    ///
+    /// Each such line yields one `Match` spanning the whole line.
+    pub fn with_invert_match(mut self, invert_match: bool) -> Self {
+        self.invert_match = invert_match;
+        self
+    }
+
+    /// Stops scanning a file once `max_count` matches have been found in it
+    pub fn with_max_count(mut self, max_count: Option<usize>) -> Self {
+        self.max_count = max_count;
+        self
+    }
+
+    /// Skips walked files that look minified; see [`looks_minified`] and
+    /// [`process_walked_file`](Self::process_walked_file)
+    pub fn with_skip_minified(mut self, skip_minified: bool) -> Self {
+        self.skip_minified = skip_minified;
+        self
+    }
+
+    /// Matches text of at least [`LARGE_FILE_THRESHOLD`] bytes in
+    /// newline-aligned chunks on the rayon pool, when every pattern matches
+    /// within single lines; the results are the same as matching it whole
+    pub fn with_parallel_large_files(mut self, parallel_large_files: bool) -> Self {
+        self.parallel_large_files = parallel_large_files;
+        self
+    }
+
+    /// Tells the processor the search is over. A large file being matched in
+    /// chunks is abandoned before its next chunk and reported as not searched.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether [`stop`](Self::stop) has been called
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+
     /// Gets the current memory metrics
     pub fn metrics(&self) -> &MemoryMetrics {
         &self.metrics
     }
 
-    /// Process a small file using simple line-by-line reading
-    fn process_small_file(&self, path: &Path) -> SearchResult<FileResult> {
-        trace!("Using simple file processing for: {}", path.display());
+    /// Runs the matcher over the whole file contents and converts the absolute
+    /// byte ranges into per-line `Match` entries, or returns `None` if the
+    /// file was abandoned because the search stopped.
+    ///
+    /// A match that spans several lines (multiline patterns) is split into one
+    /// entry per line it touches, so callers that print line-oriented results
+    /// and context keep working unchanged.
+    fn build_matches(&self, contents: &str) -> Option<Vec<Match>> {
+        let lines: Vec<&str> = contents.lines().collect();
 
-        // Read the entire file as bytes first
-        let bytes = std::fs::read(path).map_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => SearchError::file_not_found(path),
-            std::io::ErrorKind::PermissionDenied => SearchError::permission_denied(path),
-            _ => SearchError::IoError(e),
-        })?;
+        // Byte offset at which each line starts; lets us map offsets to lines
+        // with a binary search instead of rescanning the file per match.
+        let line_starts: Vec<usize> = std::iter::once(0)
+            .chain(contents.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
 
-        // Decode bytes using our helper
-        let contents = decode_bytes(&bytes, path, self.encoding_mode)?;
+        let limit = self.max_count.unwrap_or(usize::MAX);
+        let mut matches = Vec::new();
+        if self.invert_match {
+            let mut matched_lines = vec![false; lines.len()];
+            for found in self.find_matches(contents, None)? {
+                let (match_start, match_end) = (found.start, found.end);
+                let first_line = line_starts.partition_point(|&s| s <= match_start) - 1;
+                // Line holding the last matched byte (or the start, for empty matches)
+                let last_byte = match_end.saturating_sub(1).max(match_start);
+                let last_line = line_starts.partition_point(|&s| s <= last_byte) - 1;
+                for matched in matched_lines
+                    .iter_mut()
+                    .take(last_line + 1)
+                    .skip(first_line)
+                {
+                    *matched = true;
+                }
+            }
 
-        // Split into lines and find matches
-        let lines: Vec<&str> = contents.lines().collect();
+            for (line_index, line) in lines.iter().enumerate() {
+                if matches.len() == limit {
+                    break;
+                }
+                if !matched_lines[line_index] {
+                    let line_start = line_starts[line_index];
+                    matches.push(self.build_match(&lines, line_index, line_start, 0, line.len()));
+                }
+            }
+            return Some(matches);
+        }
+
+        for found in self.find_matches(contents, self.max_count)? {
+            let (match_start, match_end) = (found.start, found.end);
+            let pattern_name = self.matcher.pattern_name(found.pattern_index);
+            let first_line = line_starts.partition_point(|&s| s <= match_start) - 1;
+
+            let mut line_index = first_line;
+            while line_index < lines.len() {
+                let line_start = line_starts[line_index];
+                if line_index > first_line && line_start >= match_end {
+                    break;
+                }
+
+                let line = lines[line_index];
+                let start = match_start.saturating_sub(line_start).min(line.len());
+                let end = (match_end - line_start).min(line.len());
+                let mut entry = self.build_match(&lines, line_index, line_start, start, end);
+                entry.pattern_index = found.pattern_index;
+                entry.pattern_name = pattern_name.map(str::to_string);
+                if line_index == first_line {
+                    // Capture spans are reported relative to the line the match starts on
+                    entry.captures = found.captures.as_ref().map(|captures| {
+                        captures
+                            .iter()
+                            .map(|(name, start, end)| {
+                                (name.clone(), start - line_start, end - line_start)
+                            })
+                            .collect()
+                    });
+                }
+                matches.push(entry);
+
+                line_index += 1;
+            }
+        }
+
+        // A multiline match may have been split into several entries
+        matches.truncate(limit);
+        Some(matches)
+    }
+
+    /// Finds at most `limit` matches in `contents`, splitting large texts into
+    /// chunks of whole lines that are matched in parallel.
+    ///
+    /// Offsets are shifted back to positions in `contents` and the chunks'
+    /// matches concatenated in order, which gives the same result as one pass
+    /// since no match crosses a line end. Returns `None` if the search was
+    /// stopped before every chunk was matched.
+    fn find_matches(&self, contents: &str, limit: Option<usize>) -> Option<Vec<PatternMatch>> {
+        if !self.parallel_large_files
+            || (contents.len() as u64) < LARGE_FILE_THRESHOLD
+            || !self.matcher.matches_within_lines()
+        {
+            return Some(self.matcher.find_matches_limited(contents, limit));
+        }
+
+        let chunks = line_chunks(contents, rayon::current_num_threads() * CHUNKS_PER_THREAD);
+        trace!(
+            "Matching {} bytes in {} chunks",
+            contents.len(),
+            chunks.len()
+        );
+        let per_chunk: Option<Vec<Vec<PatternMatch>>> = chunks
+            .par_iter()
+            .map(|&(offset, end)| {
+                if self.is_stopped() {
+                    return None;
+                }
+                let chunk = &contents[offset..end];
+                let mut found = self.matcher.find_matches_limited(chunk, limit);
+                // An empty match at a chunk's end is really at the start of
+                // the next chunk's first line, where that chunk finds it
+                if end < contents.len() {
+                    found.retain(|m| m.start < chunk.len());
+                }
+                for m in &mut found {
+                    m.start += offset;
+                    m.end += offset;
+                    for (_, start, end) in m.captures.iter_mut().flatten() {
+                        *start += offset;
+                        *end += offset;
+                    }
+                }
+                Some(found)
+            })
+            .collect();
+
+        let mut matches: Vec<PatternMatch> = per_chunk?.into_iter().flatten().collect();
+        matches.truncate(limit.unwrap_or(usize::MAX));
+        Some(matches)
+    }
 
-        let matches = self
-            .matcher
-            .find_matches(&contents)
-            .into_iter()
-            .map(|pos| {
-                let line_number = 1 + contents[..pos.0].chars().filter(|&c| c == '\n').count();
-                let line_index = line_number - 1;
-
-                // Collect context before
-                let context_before: Vec<(usize, String)> = (0..self.context_before)
-                    .filter_map(|i| {
-                        if line_index > i {
-                            Some((line_number - i - 1, lines[line_index - i - 1].to_string()))
-                        } else {
-                            None
-                        }
-                    })
-                    .rev()
-                    .collect();
-
-                // Collect context after
-                let context_after: Vec<(usize, String)> = (1..=self.context_after)
-                    .filter_map(|i| {
-                        lines
-                            .get(line_index + i)
-                            .map(|line| (line_number + i, line.to_string()))
-                    })
-                    .collect();
-
-                Match {
-                    line_number,
-                    start: pos.0 - contents[..pos.0].rfind('\n').map_or(0, |n| n + 1),
-                    end: pos.1 - contents[..pos.0].rfind('\n').map_or(0, |n| n + 1),
-                    line_content: lines[line_index].to_string(),
-                    context_before,
-                    context_after,
+    /// Builds a single `Match` for the given line, attaching context lines.
+    ///
+    /// `start` and `end` are byte offsets within the line, which itself begins
+    /// at byte `line_start` of the file.
+    fn build_match(
+        &self,
+        lines: &[&str],
+        line_index: usize,
+        line_start: usize,
+        start: usize,
+        end: usize,
+    ) -> Match {
+        let line = lines[line_index];
+        let line_number = line_index + 1;
+        let column_start = line[..start].chars().count() + 1;
+        let column_end = column_start + line[start..end].chars().count();
+
+        // Collect context before
+        let context_before: Vec<(usize, String)> = (0..self.context_before)
+            .filter_map(|i| {
+                if line_index > i {
+                    Some((line_number - i - 1, lines[line_index - i - 1].to_string()))
+                } else {
+                    None
                 }
             })
+            .rev()
             .collect();
 
-        Ok(FileResult {
-            path: path.to_path_buf(),
-            matches,
-        })
+        // Collect context after
+        let context_after: Vec<(usize, String)> = (1..=self.context_after)
+            .filter_map(|i| {
+                lines
+                    .get(line_index + i)
+                    .map(|line| (line_number + i, line.to_string()))
+            })
+            .collect();
+
+        Match {
+            line_number,
+            start,
+            end,
+            column_start,
+            column_end,
+            byte_offset: line_start + start,
+            line_content: line.to_string(),
+            context_before,
+            context_after,
+            pattern_index: 0,
+            pattern_name: None,
+            captures: None,
+        }
+    }
+
+    /// Process a small file using simple line-by-line reading
+    fn process_small_file(
+        &self,
+        path: &Path,
+        skip_minified: bool,
+    ) -> SearchResult<Option<FileResult>> {
+        trace!("Using simple file processing for: {}", path.display());
+
+        // Read the entire file as bytes first
+        let bytes = std::fs::read(path).map_err(|e| open_error(path, e))?;
+        self.search_bytes(&bytes, path, skip_minified)
     }
 
     /// Process a file using buffered reading
-    fn process_file_buffered(&self, path: &Path) -> SearchResult<FileResult> {
-        let file = File::open(path).map_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => SearchError::file_not_found(path),
-            std::io::ErrorKind::PermissionDenied => SearchError::permission_denied(path),
-            _ => SearchError::IoError(e),
-        })?;
+    fn process_file_buffered(
+        &self,
+        path: &Path,
+        skip_minified: bool,
+    ) -> SearchResult<Option<FileResult>> {
+        let file = File::open(path).map_err(|e| open_error(path, e))?;
 
         let mut reader = BufReader::with_capacity(BUFFER_CAPACITY, file);
         let mut bytes = Vec::new();
         reader
             .read_to_end(&mut bytes)
             .map_err(SearchError::IoError)?;
+        self.search_bytes(&bytes, path, skip_minified)
+    }
 
-        // Decode bytes using our helper
-        let contents = decode_bytes(&bytes, path, self.encoding_mode)?;
+    /// Process a file using memory mapping
+    fn process_mmap_file(
+        &self,
+        path: &Path,
+        skip_minified: bool,
+    ) -> SearchResult<Option<FileResult>> {
+        let file = File::open(path).map_err(|e| open_error(path, e))?;
 
-        // Split into lines and find matches
-        let lines: Vec<&str> = contents.lines().collect();
+        let mmap = unsafe { Mmap::map(&file) }.map_err(SearchError::IoError)?;
+        self.search_bytes(&mmap, path, skip_minified)
+    }
 
-        let matches = self
-            .matcher
-            .find_matches(&contents)
-            .into_iter()
-            .map(|pos| {
-                let line_number = 1 + contents[..pos.0].chars().filter(|&c| c == '\n').count();
-                let line_index = line_number - 1;
-
-                // Collect context before
-                let context_before: Vec<(usize, String)> = (0..self.context_before)
-                    .filter_map(|i| {
-                        if line_index > i {
-                            Some((line_number - i - 1, lines[line_index - i - 1].to_string()))
-                        } else {
-                            None
-                        }
-                    })
-                    .rev()
-                    .collect();
-
-                // Collect context after
-                let context_after: Vec<(usize, String)> = (1..=self.context_after)
-                    .filter_map(|i| {
-                        lines
-                            .get(line_index + i)
-                            .map(|line| (line_number + i, line.to_string()))
-                    })
-                    .collect();
-
-                Match {
-                    line_number,
-                    start: pos.0 - contents[..pos.0].rfind('\n').map_or(0, |n| n + 1),
-                    end: pos.1 - contents[..pos.0].rfind('\n').map_or(0, |n| n + 1),
-                    line_content: lines[line_index].to_string(),
-                    context_before,
-                    context_after,
-                }
-            })
-            .collect();
+    /// Decodes a file's bytes and matches them, or returns `None` if
+    /// `skip_minified` is set and the file looks minified, or if the file was
+    /// abandoned because the search stopped
+    fn search_bytes(
+        &self,
+        bytes: &[u8],
+        path: &Path,
+        skip_minified: bool,
+    ) -> SearchResult<Option<FileResult>> {
+        if skip_minified && looks_minified(bytes) {
+            debug!("Skipping {}: looks minified", path.display());
+            self.minified_skipped.fetch_add(1, Ordering::Relaxed);
+            return Ok(None);
+        }
+
+        // Decode bytes using our helper
+        let contents = decode_bytes(bytes, path, self.encoding_mode)?;
 
-        Ok(FileResult {
+        let Some(matches) = self.build_matches(&contents) else {
+            debug!("Abandoned {}: the search stopped", path.display());
+            return Ok(None);
+        };
+
+        Ok(Some(FileResult {
             path: path.to_path_buf(),
             matches,
-        })
+            line_ending: Some(LineEnding::detect(&contents)),
+        }))
     }
 
-    /// Process a file using memory mapping
-    fn process_mmap_file(&self, path: &Path) -> SearchResult<FileResult> {
-        let file = File::open(path).map_err(|e| match e.kind() {
-            std::io::ErrorKind::NotFound => SearchError::file_not_found(path),
-            std::io::ErrorKind::PermissionDenied => SearchError::permission_denied(path),
-            _ => SearchError::IoError(e),
-        })?;
-
-        let mmap = unsafe { Mmap::map(&file) }.map_err(SearchError::IoError)?;
+    /// Reads everything from `reader` (e.g. stdin) and returns any matches
+    /// found, reported under `path`, or `None` if it is abandoned because
+    /// the search stopped
+    pub fn process_reader(
+        &self,
+        mut reader: impl Read,
+        path: &Path,
+    ) -> SearchResult<Option<FileResult>> {
+        trace!("Processing {}", path.display());
 
-        // Decode bytes using our helper
-        let contents = decode_bytes(&mmap, path, self.encoding_mode)?;
+        let mut bytes = Vec::new();
+        reader
+            .read_to_end(&mut bytes)
+            .map_err(SearchError::IoError)?;
+        self.metrics.record_file_processing(bytes.len() as u64);
 
-        let lines: Vec<&str> = contents.lines().collect();
-        let matches = self
-            .matcher
-            .find_matches(&contents)
-            .into_iter()
-            .map(|pos| {
-                let line_number = 1 + contents[..pos.0].chars().filter(|&c| c == '\n').count();
-                let line_index = line_number - 1;
-
-                // Collect context before
-                let context_before: Vec<(usize, String)> = (0..self.context_before)
-                    .filter_map(|i| {
-                        if line_index > i {
-                            Some((line_number - i - 1, lines[line_index - i - 1].to_string()))
-                        } else {
-                            None
-                        }
-                    })
-                    .rev()
-                    .collect();
-
-                // Collect context after
-                let context_after: Vec<(usize, String)> = (1..=self.context_after)
-                    .filter_map(|i| {
-                        lines
-                            .get(line_index + i)
-                            .map(|line| (line_number + i, line.to_string()))
-                    })
-                    .collect();
-
-                Match {
-                    line_number,
-                    start: pos.0 - contents[..pos.0].rfind('\n').map_or(0, |n| n + 1),
-                    end: pos.1 - contents[..pos.0].rfind('\n').map_or(0, |n| n + 1),
-                    line_content: lines[line_index].to_string(),
-                    context_before,
-                    context_after,
-                }
-            })
-            .collect();
+        let contents = decode_bytes(&bytes, path, self.encoding_mode)?;
+        let Some(matches) = self.build_matches(&contents) else {
+            debug!("Abandoned {}: the search stopped", path.display());
+            return Ok(None);
+        };
 
-        Ok(FileResult {
+        Ok(Some(FileResult {
             path: path.to_path_buf(),
             matches,
-        })
+            line_ending: Some(LineEnding::detect(&contents)),
+        }))
     }
 
-    /// Processes a file and returns any matches found
+    /// Processes a file and returns any matches found. The file is always
+    /// searched, even if it looks minified; if it is abandoned because the
+    /// search stopped, the result has no matches.
     pub fn process_file(&self, path: &Path) -> SearchResult<FileResult> {
+        let result = self.process_path(path, false)?;
+        Ok(result.unwrap_or_else(|| FileResult {
+            path: path.to_path_buf(),
+            matches: Vec::new(),
+            line_ending: None,
+        }))
+    }
+
+    /// Processes a file found by walking a root. Returns `None` instead when
+    /// [`with_skip_minified`](Self::with_skip_minified) is set and the file
+    /// looks minified, or when the file is abandoned because the search
+    /// [stopped](Self::stop).
+    pub fn process_walked_file(&self, path: &Path) -> SearchResult<Option<FileResult>> {
+        self.process_path(path, self.skip_minified)
+    }
+
+    /// Files skipped so far because they looked minified
+    pub fn minified_files_skipped(&self) -> usize {
+        self.minified_skipped.load(Ordering::Relaxed)
+    }
+
+    /// Longest time spent reading and matching a single file so far
+    pub fn slowest_file_time(&self) -> Duration {
+        Duration::from_nanos(self.slowest_file_nanos.load(Ordering::Relaxed))
+    }
+
+    /// Processes a file, returning `None` if it is skipped as minified or
+    /// abandoned because the search stopped
+    pub(crate) fn process_path(
+        &self,
+        path: &Path,
+        skip_minified: bool,
+    ) -> SearchResult<Option<FileResult>> {
+        let start = Instant::now();
+        let result = self.process_path_by_size(path, skip_minified);
+        let nanos = u64::try_from(start.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        self.slowest_file_nanos.fetch_max(nanos, Ordering::Relaxed);
+        result
+    }
+
+    fn process_path_by_size(
+        &self,
+        path: &Path,
+        skip_minified: bool,
+    ) -> SearchResult<Option<FileResult>> {
         trace!("Processing file: {}", path.display());
 
         // Choose processing strategy based on file size
@@ -281,24 +509,65 @@ impl FileProcessor {
                 self.metrics.record_file_processing(size);
 
                 if size < SMALL_FILE_THRESHOLD {
-                    self.process_small_file(path)
+                    self.process_small_file(path, skip_minified)
                 } else if size >= LARGE_FILE_THRESHOLD {
-                    self.process_mmap_file(path)
+                    self.process_mmap_file(path, skip_minified)
                 } else {
-                    self.process_file_buffered(path)
+                    self.process_file_buffered(path, skip_minified)
                 }
             }
             Err(e) => {
                 warn!("Failed to get metadata for {}: {}", path.display(), e);
-                self.process_file_buffered(path)
+                self.process_file_buffered(path, skip_minified)
             }
         }
     }
 }
 
+/// Splits `text` into about `count` byte ranges of whole lines, each ending
+/// just after a newline (or at the end of `text`)
+fn line_chunks(text: &str, count: usize) -> Vec<(usize, usize)> {
+    let target = text.len().div_ceil(count.max(1)).max(1);
+    let bytes = text.as_bytes();
+    let mut chunks = Vec::with_capacity(count);
+    let mut start = 0;
+    while start < text.len() {
+        let end = match bytes[(start + target).min(text.len())..]
+            .iter()
+            .position(|&b| b == b'\n')
+        {
+            Some(newline) => (start + target + newline + 1).min(text.len()),
+            None => text.len(),
+        };
+        chunks.push((start, end));
+        start = end;
+    }
+    chunks
+}
+
+/// Maps an error opening `path` to the matching [`SearchError`]
+fn open_error(path: &Path, e: std::io::Error) -> SearchError {
+    match e.kind() {
+        std::io::ErrorKind::NotFound => SearchError::file_not_found(path),
+        std::io::ErrorKind::PermissionDenied => SearchError::permission_denied(path),
+        _ => SearchError::IoError(e),
+    }
+}
+
+/// Checks whether a file looks minified: its first
+/// [`MINIFIED_SAMPLE_SIZE`] bytes average more than [`MINIFIED_LINE_LENGTH`]
+/// bytes per line. A sample without any newline counts as one long line.
+pub fn looks_minified(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(MINIFIED_SAMPLE_SIZE)];
+    let newlines = sample.iter().filter(|&&b| b == b'\n').count();
+    let lines = newlines + usize::from(sample.last().is_some_and(|&b| b != b'\n'));
+    lines > 0 && sample.len() / lines > MINIFIED_LINE_LENGTH
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::search::matcher::{PatternDefinition, WordBoundaryMode};
     use std::fs::File;
     use std::io::Write;
     use tempfile::tempdir;
@@ -318,7 +587,12 @@ mod tests {
         }
 
         // Create a pattern matcher and processor
-        let matcher = PatternMatcher::new(vec!["pattern_\\d+".to_string()]);
+        let matcher = PatternMatcher::from_definitions(vec![PatternDefinition::new(
+            "pattern_\\d+".to_string(),
+            true,
+            WordBoundaryMode::None,
+        )])
+        .unwrap();
         let processor = FileProcessor::new(matcher, 0, 0, EncodingMode::FailFast);
 
         // Process the file
@@ -373,7 +647,7 @@ mod tests {
         file.write_all(content.as_bytes()).unwrap();
 
         // Create a pattern matcher and processor
-        let matcher = PatternMatcher::new(vec!["pattern_split".to_string()]);
+        let matcher = PatternMatcher::new(vec!["pattern_split".to_string()]).unwrap();
         let processor = FileProcessor::new(matcher, 0, 0, EncodingMode::FailFast);
 
         // Process the file
@@ -396,4 +670,283 @@ mod tests {
             prev_line = match_result.line_number;
         }
     }
+
+    #[test]
+    fn test_multiline_match_spans_lines() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("multiline.rs");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"fn empty() {\n}\nfn full() { body(); }\nfn other() {\n\n}\n")
+            .unwrap();
+
+        let mut definition =
+            PatternDefinition::new(r"\{\s*\}".to_string(), true, WordBoundaryMode::None);
+        definition.multiline = true;
+        let matcher = PatternMatcher::from_definitions(vec![definition]).unwrap();
+        let processor = FileProcessor::new(matcher, 1, 1, EncodingMode::FailFast);
+
+        let result = processor.process_file(&file_path).unwrap();
+
+        // Each match is split into one entry per line it touches
+        let spans: Vec<(usize, usize, usize)> = result
+            .matches
+            .iter()
+            .map(|m| (m.line_number, m.start, m.end))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![(1, 11, 12), (2, 0, 1), (4, 11, 12), (5, 0, 0), (6, 0, 1)]
+        );
+
+        // Context is attached per line
+        let last = result.matches.last().unwrap();
+        assert_eq!(last.line_content, "}");
+        assert_eq!(last.context_before, vec![(5, String::new())]);
+        assert!(last.context_after.is_empty());
+    }
+
+    #[test]
+    fn test_line_chunks() {
+        let text = "aa\nbbbb\nc\n\ndd";
+        let chunks = line_chunks(text, 3);
+        assert_eq!(chunks, vec![(0, 8), (8, 13)]);
+        assert_eq!(
+            line_chunks(text, 13),
+            vec![(0, 3), (3, 8), (8, 10), (10, 13)]
+        );
+        assert_eq!(line_chunks(text, 1), vec![(0, text.len())]);
+        assert_eq!(line_chunks("one long line", 4), vec![(0, 13)]);
+    }
+
+    #[test]
+    fn test_parallel_large_file_matches_sequential() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("huge.log");
+        let mut file = std::io::BufWriter::new(File::create(&file_path).unwrap());
+        let mut size = 0;
+        let mut i = 0u64;
+        while size < 2 * LARGE_FILE_THRESHOLD {
+            let line = match i % 7 {
+                0 => format!("ERROR {} disk full\n", i),
+                3 => format!("WARN retrying request {}\n", i),
+                5 => "\n".to_string(),
+                _ => format!("INFO handled request {} in {}ms\n", i, i % 97),
+            };
+            file.write_all(line.as_bytes()).unwrap();
+            size += line.len() as u64;
+            i += 1;
+        }
+        drop(file);
+
+        let processor = |parallel: bool, max_count: Option<usize>| {
+            let patterns = vec![
+                PatternDefinition::new(r"ERROR (\d+)".to_string(), true, WordBoundaryMode::None),
+                PatternDefinition::new(
+                    r"(?m)request \d+$".to_string(),
+                    true,
+                    WordBoundaryMode::None,
+                ),
+                PatternDefinition::new(r"(?m)^$".to_string(), true, WordBoundaryMode::None),
+            ];
+            let matcher = PatternMatcher::from_definitions(patterns)
+                .unwrap()
+                .with_captures(true);
+            assert!(matcher.matches_within_lines());
+            FileProcessor::new(matcher, 1, 2, EncodingMode::FailFast)
+                .with_max_count(max_count)
+                .with_parallel_large_files(parallel)
+        };
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+
+        let sequential = processor(false, None).process_file(&file_path).unwrap();
+        let parallel = pool.install(|| processor(true, None).process_file(&file_path).unwrap());
+        assert!(sequential.matches.len() > 3 * (i as usize) / 7);
+        assert!(sequential.matches == parallel.matches);
+
+        let sequential = processor(false, Some(1000))
+            .process_file(&file_path)
+            .unwrap();
+        let parallel = pool.install(|| {
+            processor(true, Some(1000))
+                .process_file(&file_path)
+                .unwrap()
+        });
+        assert_eq!(parallel.matches.len(), 1000);
+        assert!(sequential.matches == parallel.matches);
+
+        // Once stopped, a large file is abandoned between chunks
+        let stopped = processor(true, None);
+        stopped.stop();
+        pool.install(|| {
+            assert!(stopped.process_walked_file(&file_path).unwrap().is_none());
+            assert!(stopped.process_file(&file_path).unwrap().matches.is_empty());
+            let bytes = std::fs::read(&file_path).unwrap();
+            assert!(stopped
+                .process_reader(&bytes[..], &file_path)
+                .unwrap()
+                .is_none());
+        });
+    }
+
+    #[test]
+    fn test_invert_match() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("invert.txt");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"TODO one\nplain\nTODO two\n\nlast")
+            .unwrap();
+
+        let matcher = PatternMatcher::new(vec!["TODO".to_string()]).unwrap();
+        let processor =
+            FileProcessor::new(matcher, 0, 1, EncodingMode::FailFast).with_invert_match(true);
+
+        let result = processor.process_file(&file_path).unwrap();
+        let lines: Vec<(usize, &str, usize, usize)> = result
+            .matches
+            .iter()
+            .map(|m| (m.line_number, m.line_content.as_str(), m.start, m.end))
+            .collect();
+        assert_eq!(
+            lines,
+            vec![(2, "plain", 0, 5), (4, "", 0, 0), (5, "last", 0, 4)]
+        );
+        assert_eq!(
+            result.matches[0].context_after,
+            vec![(3, "TODO two".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_max_count_per_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("max_count.txt");
+        let mut file = File::create(&file_path).unwrap();
+        file.write_all(b"foo bar\nbar\nfoo foo\nfoo\n").unwrap();
+
+        // Hits from several patterns are merged in file order before the cut
+        let matcher = PatternMatcher::new(vec!["foo".to_string(), "bar".to_string()]).unwrap();
+        let processor =
+            FileProcessor::new(matcher, 0, 0, EncodingMode::FailFast).with_max_count(Some(4));
+
+        let result = processor.process_file(&file_path).unwrap();
+        let hits: Vec<(usize, usize)> = result
+            .matches
+            .iter()
+            .map(|m| (m.line_number, m.start))
+            .collect();
+        assert_eq!(hits, vec![(1, 0), (1, 4), (2, 0), (3, 0)]);
+    }
+
+    #[test]
+    fn test_columns_and_byte_offsets() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("columns.txt");
+        let content = "first line\r\nhéllo wörld TODO\n日本 TODO\n";
+        std::fs::write(&file_path, content).unwrap();
+
+        let matcher = PatternMatcher::new(vec!["TODO".to_string()]).unwrap();
+        let processor = FileProcessor::new(matcher, 0, 0, EncodingMode::FailFast);
+
+        let result = processor.process_file(&file_path).unwrap();
+        assert_eq!(result.matches.len(), 2);
+
+        // Columns count characters, byte offsets count bytes
+        let first = &result.matches[0];
+        assert_eq!((first.column_start, first.column_end), (13, 17));
+        assert_eq!(first.start, 14);
+
+        let second = &result.matches[1];
+        assert_eq!((second.column_start, second.column_end), (4, 8));
+        assert_eq!(second.start, 7);
+
+        for m in &result.matches {
+            assert_eq!(
+                &content[m.byte_offset..m.byte_offset + (m.end - m.start)],
+                "TODO"
+            );
+        }
+    }
+
+    #[test]
+    fn test_crlf_matches_stop_before_carriage_return() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("crlf.rs");
+        let content = "let a = 1;\r\nlet b = 2;\r\n";
+        std::fs::write(&file_path, content).unwrap();
+
+        let matcher = PatternMatcher::from_definitions(vec![PatternDefinition::new(
+            "let .*".to_string(),
+            true,
+            WordBoundaryMode::None,
+        )])
+        .unwrap();
+        let processor = FileProcessor::new(matcher, 0, 0, EncodingMode::FailFast);
+        let result = processor.process_file(&file_path).unwrap();
+
+        assert_eq!(result.line_ending, Some(LineEnding::CrLf));
+        assert_eq!(result.matches.len(), 2);
+        for m in &result.matches {
+            assert_eq!(m.end, m.line_content.len());
+            let span = &content[m.byte_offset..m.byte_offset + (m.end - m.start)];
+            assert!(span.starts_with("let ") && !span.contains('\r'));
+        }
+    }
+
+    #[test]
+    fn test_detect_encoding() {
+        let dir = tempdir().unwrap();
+        let text = "first line\nun café noir\n";
+
+        let mut utf16le = vec![0xFF, 0xFE];
+        utf16le.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        let mut utf16be = vec![0xFE, 0xFF];
+        utf16be.extend(text.encode_utf16().flat_map(u16::to_be_bytes));
+        let latin1: Vec<u8> = text.chars().map(|c| c as u8).collect();
+
+        let matcher = PatternMatcher::new(vec!["café".to_string()]).unwrap();
+        let processor = FileProcessor::new(matcher, 0, 0, EncodingMode::Detect);
+
+        for (name, bytes) in [
+            ("le.txt", utf16le),
+            ("be.txt", utf16be),
+            ("latin1.txt", latin1),
+        ] {
+            let file_path = dir.path().join(name);
+            std::fs::write(&file_path, &bytes).unwrap();
+
+            // Offsets and line content refer to the decoded text
+            let result = processor.process_file(&file_path).unwrap();
+            assert_eq!(result.matches.len(), 1, "{}", name);
+            let m = &result.matches[0];
+            assert_eq!(m.line_number, 2);
+            assert_eq!(m.line_content, "un café noir");
+            assert_eq!((m.start, m.end), (3, 8));
+            assert_eq!(&text[m.byte_offset..m.byte_offset + 5], "café");
+        }
+
+        // Without detection, UTF-16 is rejected as invalid UTF-8
+        let matcher = PatternMatcher::new(vec!["café".to_string()]).unwrap();
+        let processor = FileProcessor::new(matcher, 0, 0, EncodingMode::FailFast);
+        assert!(processor.process_file(&dir.path().join("le.txt")).is_err());
+    }
+
+    #[test]
+    fn test_looks_minified() {
+        let minified = format!("var a=1;{}\n", "f(a);".repeat(20_000));
+        let normal = format!("{}\n", "x".repeat(79)).repeat(minified.len() / 80);
+        assert!(looks_minified(minified.as_bytes()));
+        assert!(!looks_minified(normal.as_bytes()));
+
+        // No newline in the whole sample
+        assert!(looks_minified(&vec![b'x'; MINIFIED_SAMPLE_SIZE * 2]));
+        // A short file on one line is fine
+        assert!(!looks_minified(b"{\"name\": \"rustscout\"}"));
+        assert!(!looks_minified(b""));
+        // Only the sample counts, not long lines further in
+        let late = format!("{}{}", "short line\n".repeat(10_000), "y".repeat(100_000));
+        assert!(!looks_minified(late.as_bytes()));
+    }
 }
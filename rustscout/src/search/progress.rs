@@ -0,0 +1,40 @@
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+/// Receives progress updates from a running search.
+///
+/// Methods are called from the worker threads, so implementations must be
+/// cheap and thread-safe. Both have empty default implementations.
+pub trait SearchProgress: Send + Sync {
+    /// Called once the files to search are known, before any is searched
+    fn on_files_discovered(&self, _count: usize) {}
+
+    /// Called after each file is searched, with the number of matches found in
+    /// it (0 for files without matches)
+    fn on_file_done(&self, _path: &Path, _matches: usize) {}
+}
+
+/// A shared [`SearchProgress`] implementation, as stored in a `SearchConfig`
+#[derive(Clone)]
+pub struct ProgressHook(pub Arc<dyn SearchProgress>);
+
+impl ProgressHook {
+    /// Wraps a progress implementation
+    pub fn new(progress: impl SearchProgress + 'static) -> Self {
+        Self(Arc::new(progress))
+    }
+}
+
+impl fmt::Debug for ProgressHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressHook")
+    }
+}
+
+/// Hooks are equal if they share the same implementation
+impl PartialEq for ProgressHook {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
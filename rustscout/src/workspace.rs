@@ -2,9 +2,11 @@ use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::config::{PartialSearchConfig, SEARCH_CONFIG_FILES};
 use crate::errors::{unify_path, SearchError, SearchResult};
+use crate::replace::PrunePolicy;
 
-const WORKSPACE_DIR: &str = ".rustscout";
+pub(crate) const WORKSPACE_DIR: &str = ".rustscout";
 const WORKSPACE_CONFIG: &str = "workspace.json";
 const MAX_UPWARD_STEPS: usize = 20;
 
@@ -31,6 +33,13 @@ pub struct GlobalConfig {
     /// Default file extensions to search
     #[serde(default)]
     pub default_extensions: Option<Vec<String>>,
+    /// Limits the undo history is pruned to after every recorded operation
+    #[serde(default)]
+    pub undo_retention: Option<PrunePolicy>,
+    /// Store replacement backups compressed and shared between operations,
+    /// like [`ReplacementConfig::compress_backups`](crate::replace::ReplacementConfig::compress_backups)
+    #[serde(default)]
+    pub compress_backups: bool,
 }
 
 impl WorkspaceMetadata {
@@ -105,6 +114,37 @@ pub fn init_workspace(root: &Path, format: &str) -> SearchResult<WorkspaceMetada
     Ok(metadata)
 }
 
+/// Writes a search config file listing every setting, unset, to the
+/// workspace at `root` and returns its path; see
+/// [`SearchConfig::load_workspace_defaults`](crate::SearchConfig::load_workspace_defaults)
+pub fn init_search_config(root: &Path, format: &str) -> SearchResult<PathBuf> {
+    let workspace_dir = root.join(WORKSPACE_DIR);
+    fs::create_dir_all(&workspace_dir)?;
+
+    let defaults = PartialSearchConfig::default();
+    let (name, content) = if format.eq_ignore_ascii_case("yaml") {
+        let yaml = serde_yaml::to_string(&defaults).map_err(|e| {
+            SearchError::config_error(format!("Failed to serialize search config: {}", e))
+        })?;
+        (SEARCH_CONFIG_FILES[0], yaml)
+    } else {
+        (
+            SEARCH_CONFIG_FILES[1],
+            serde_json::to_string_pretty(&defaults)? + "\n",
+        )
+    };
+
+    let path = workspace_dir.join(name);
+    fs::write(&path, content)?;
+    Ok(path)
+}
+
+/// Whether `path` lies inside a `.rustscout` directory, where undo
+/// information and backups are kept
+pub fn is_workspace_path(path: &Path) -> bool {
+    path.components().any(|c| c.as_os_str() == WORKSPACE_DIR)
+}
+
 /// Detect a workspace root by walking upward from the starting directory.
 /// If no workspace is found, returns the starting directory without creating one.
 pub fn detect_workspace_root(starting_dir: &Path) -> SearchResult<PathBuf> {
@@ -165,6 +205,8 @@ mod tests {
         metadata.global_config = Some(GlobalConfig {
             ignore_patterns: vec!["*.tmp".to_string()],
             default_extensions: Some(vec!["rs".to_string()]),
+            undo_retention: None,
+            compress_backups: false,
         });
 
         // Save and reload
@@ -1,12 +1,13 @@
 use anyhow::Result;
 use rustscout::{
-    cache::ChangeDetectionStrategy,
-    config::{EncodingMode, SearchConfig},
+    cache::{CacheFormat, ChangeDetectionStrategy},
+    config::{EncodingMode, OutputMode, Schedule, SearchConfig},
+    filters::FileTypes,
     replace::{
         FileReplacementPlan, ReplacementConfig, ReplacementPattern, ReplacementSet, ReplacementTask,
     },
     search,
-    search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode},
+    search::matcher::{CaseMode, HyphenMode, PatternDefinition, WordBoundaryMode},
 };
 use std::{fs, num::NonZeroUsize, path::Path};
 use tempfile::tempdir;
@@ -34,14 +35,28 @@ fn test_replace_basic() -> Result<()> {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::Joining,
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             replacement_text: "World".to_string(),
+            preserve_case: false,
         }],
         backup_enabled: true,
         dry_run: false,
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     let mut plan = FileReplacementPlan::new(dir.path().join("test.txt"))?;
@@ -93,14 +108,28 @@ fn test_replace_with_backup() -> Result<()> {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::Joining,
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             replacement_text: "World".to_string(),
+            preserve_case: false,
         }],
         backup_enabled: true,
         dry_run: false,
         backup_dir: Some(backup_dir.clone()),
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     let mut plan = FileReplacementPlan::new(dir.path().join("test.txt"))?;
@@ -127,16 +156,14 @@ fn test_replace_with_backup() -> Result<()> {
         Ok(entries) => {
             let entries: Vec<_> = entries.collect();
             println!("Debug: Found {} entries in backup dir", entries.len());
-            for entry in &entries {
-                if let Ok(entry) = entry {
-                    println!("Debug: Found backup file: {}", entry.path().display());
-                }
+            for entry in entries.iter().flatten() {
+                println!("Debug: Found backup file: {}", entry.path().display());
             }
             assert!(!entries.is_empty(), "Backup directory should not be empty");
         }
         Err(e) => {
             println!("Debug: Failed to read backup dir: {}", e);
-            assert!(false, "Failed to read backup directory: {}", e);
+            panic!("Failed to read backup directory: {}", e);
         }
     }
     Ok(())
@@ -159,14 +186,28 @@ fn test_replace_dry_run() -> Result<()> {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::Joining,
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             replacement_text: "World".to_string(),
+            preserve_case: false,
         }],
         backup_enabled: true,
         dry_run: true,
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     let mut plan = FileReplacementPlan::new(test_file.clone())?;
@@ -203,14 +244,28 @@ fn test_replace_preview() -> Result<()> {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::Joining,
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             replacement_text: "World".to_string(),
+            preserve_case: false,
         }],
         backup_enabled: true,
         dry_run: false,
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     let mut plan = FileReplacementPlan::new(test_file.clone())?;
@@ -259,14 +314,28 @@ fn test_replace_undo_list() -> Result<()> {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::Joining,
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             replacement_text: "World".to_string(),
+            preserve_case: false,
         }],
         backup_enabled: true,
         dry_run: false,
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     let mut plan = FileReplacementPlan::new(test_file.clone())?;
@@ -309,14 +378,28 @@ fn test_replace_undo_restore() -> Result<()> {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::Joining,
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             replacement_text: "World".to_string(),
+            preserve_case: false,
         }],
         backup_enabled: true,
         dry_run: false,
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     let mut plan = FileReplacementPlan::new(test_file.clone())?;
@@ -365,14 +448,28 @@ fn test_replace_cli_args() -> Result<()> {
                 is_regex: true,
                 boundary_mode: WordBoundaryMode::WholeWords,
                 hyphen_mode: HyphenMode::Boundary,
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             replacement_text: "bar".to_string(),
+            preserve_case: false,
         }],
         backup_enabled: true,
         dry_run: true,
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     // Create search config to find matches
@@ -382,7 +479,7 @@ fn test_replace_cli_args() -> Result<()> {
             .iter()
             .map(|p| p.definition.clone())
             .collect(),
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -396,6 +493,37 @@ fn test_replace_cli_args() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     // Find matches
@@ -451,8 +579,16 @@ fn test_replace_multiple_patterns() -> Result<()> {
                     is_regex: false,
                     boundary_mode: WordBoundaryMode::None,
                     hyphen_mode: HyphenMode::Joining,
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 },
                 replacement_text: "Hi".to_string(),
+                preserve_case: false,
             },
             ReplacementPattern {
                 definition: PatternDefinition {
@@ -460,8 +596,16 @@ fn test_replace_multiple_patterns() -> Result<()> {
                     is_regex: false,
                     boundary_mode: WordBoundaryMode::None,
                     hyphen_mode: HyphenMode::Joining,
+                    case_mode: CaseMode::default(),
+                    multiline: false,
+                    match_whole_line: false,
+                    trim_whole_line: false,
+                    allow_overlapping: false,
+                    fuzzy_distance: None,
+                    name: None,
                 },
                 replacement_text: "Bye".to_string(),
+                preserve_case: false,
             },
         ],
         backup_enabled: true,
@@ -469,6 +613,12 @@ fn test_replace_multiple_patterns() -> Result<()> {
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: undo_dir.clone(),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     let mut plan = FileReplacementPlan::new(test_file.clone())?;
@@ -516,8 +666,15 @@ fn test_search_hyphen_mode() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::WholeWords,
             hyphen_mode: HyphenMode::Joining, // --hyphen-mode=joining
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         ..SearchConfig::default()
     };
 
@@ -537,8 +694,15 @@ fn test_search_hyphen_mode() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::WholeWords,
             hyphen_mode: HyphenMode::Boundary, // --hyphen-mode=boundary
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         ..SearchConfig::default()
     };
 
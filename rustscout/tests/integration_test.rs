@@ -1,10 +1,11 @@
 use anyhow::Result;
 use rustscout::search::search;
 use rustscout::{
-    cache::ChangeDetectionStrategy,
-    config::{EncodingMode, SearchConfig},
+    cache::{CacheFormat, ChangeDetectionStrategy},
+    config::{EncodingMode, OutputMode, Schedule, SearchConfig},
     errors::unify_path,
-    search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode},
+    filters::FileTypes,
+    search::matcher::{CaseMode, HyphenMode, PatternDefinition, WordBoundaryMode},
     SearchError,
 };
 use std::fs::File;
@@ -61,8 +62,15 @@ fn test_simple_pattern() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -76,6 +84,37 @@ fn test_simple_pattern() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -95,8 +134,15 @@ fn test_regex_pattern() -> Result<()> {
             is_regex: true,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -110,6 +156,37 @@ fn test_regex_pattern() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -134,8 +211,15 @@ fn test_file_extensions() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: Some(vec!["rs".to_string()]),
         ignore_patterns: vec![],
         stats_only: false,
@@ -149,6 +233,37 @@ fn test_file_extensions() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -168,8 +283,15 @@ fn test_ignore_patterns() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec!["**/test_[0-4].txt".to_string()],
         stats_only: false,
@@ -183,6 +305,37 @@ fn test_ignore_patterns() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -205,8 +358,15 @@ fn test_empty_pattern() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -220,6 +380,37 @@ fn test_empty_pattern() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -239,8 +430,15 @@ fn test_stats_only() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: true,
@@ -254,6 +452,37 @@ fn test_stats_only() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -274,15 +503,29 @@ fn test_multiple_patterns() -> Result<()> {
                 is_regex: false,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::default(),
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
             PatternDefinition {
                 text: "FIXME.*bug".to_string(),
                 is_regex: true,
                 boundary_mode: WordBoundaryMode::None,
                 hyphen_mode: HyphenMode::default(),
+                case_mode: CaseMode::default(),
+                multiline: false,
+                match_whole_line: false,
+                trim_whole_line: false,
+                allow_overlapping: false,
+                fuzzy_distance: None,
+                name: None,
             },
         ],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -296,6 +539,37 @@ fn test_multiple_patterns() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -322,6 +596,200 @@ fn test_multiple_patterns() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_max_count_limits() -> Result<()> {
+    let dir = tempdir()?;
+    create_test_files(&dir, 5, 10)?;
+
+    let mut config = SearchConfig {
+        pattern_definitions: vec![PatternDefinition {
+            text: "TODO".to_string(),
+            is_regex: false,
+            boundary_mode: WordBoundaryMode::None,
+            hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
+        }],
+        root_paths: vec![dir.path().to_path_buf()],
+        file_extensions: None,
+        ignore_patterns: vec![],
+        stats_only: false,
+        thread_count: NonZeroUsize::new(4).unwrap(),
+        log_level: "info".to_string(),
+        context_before: 0,
+        context_after: 0,
+        incremental: false,
+        cache_path: None,
+        cache_strategy: ChangeDetectionStrategy::Auto,
+        max_cache_size: None,
+        use_compression: false,
+        encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: Some(3),
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
+    };
+
+    // Per-file limit keeps only the first hits of each file
+    let result = search(&config)?;
+    assert_eq!(result.total_matches, 15);
+    assert_eq!(result.files_with_matches, 5);
+    assert!(!result.limit_reached);
+    for file_result in &result.file_results {
+        let lines: Vec<usize> = file_result.matches.iter().map(|m| m.line_number).collect();
+        assert_eq!(lines, vec![1, 4, 7]);
+    }
+
+    // Global cap truncates the result set and flags it
+    config.max_count_per_file = None;
+    config.max_total_matches = Some(7);
+    let result = search(&config)?;
+    assert_eq!(result.total_matches, 7);
+    assert_eq!(
+        result
+            .file_results
+            .iter()
+            .map(|fr| fr.matches.len())
+            .sum::<usize>(),
+        7
+    );
+    assert!(result.limit_reached);
+
+    // A cap above the number of matches leaves the result untouched
+    config.max_total_matches = Some(100);
+    let result = search(&config)?;
+    assert_eq!(result.total_matches, 50);
+    assert!(!result.limit_reached);
+
+    Ok(())
+}
+
+#[test]
+fn test_output_modes() -> Result<()> {
+    let dir = tempdir()?;
+    create_test_files(&dir, 3, 5)?;
+    std::fs::write(dir.path().join("clean.txt"), "nothing to see here\n")?;
+
+    let cache_path = dir.path().join("cache.json");
+    let mut config = SearchConfig {
+        pattern_definitions: vec![PatternDefinition {
+            text: "TODO".to_string(),
+            is_regex: false,
+            boundary_mode: WordBoundaryMode::None,
+            hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
+        }],
+        root_paths: vec![dir.path().to_path_buf()],
+        file_extensions: Some(vec!["txt".to_string()]),
+        ignore_patterns: vec![],
+        stats_only: false,
+        thread_count: NonZeroUsize::new(4).unwrap(),
+        log_level: "info".to_string(),
+        context_before: 0,
+        context_after: 0,
+        incremental: true,
+        cache_path: Some(cache_path),
+        cache_strategy: ChangeDetectionStrategy::FileSignature,
+        max_cache_size: None,
+        use_compression: false,
+        encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::FilesWithMatches,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
+    };
+
+    // Stops at the first match in each file
+    let result = search(&config)?;
+    assert_eq!(result.files_with_matches, 3);
+    assert!(result.file_results.iter().all(|fr| fr.matches.len() == 1));
+
+    // Switching back to full output must not reuse the truncated results
+    config.output_mode = OutputMode::CountPerFile;
+    let result = search(&config)?;
+    assert_eq!(result.total_matches, 15);
+    assert!(result.file_results.iter().all(|fr| fr.matches.len() == 5));
+
+    // Files without a match are reported with empty match lists
+    config.output_mode = OutputMode::FilesWithoutMatch;
+    let result = search(&config)?;
+    let without: Vec<&Path> = result
+        .file_results
+        .iter()
+        .filter(|fr| fr.matches.is_empty())
+        .map(|fr| fr.path.as_path())
+        .collect();
+    assert_eq!(without, vec![dir.path().join("clean.txt").as_path()]);
+    assert_eq!(result.files_with_matches, 3);
+
+    Ok(())
+}
+
 #[test]
 fn test_empty_patterns() -> Result<()> {
     let dir = tempdir()?;
@@ -333,8 +801,15 @@ fn test_empty_patterns() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -348,6 +823,37 @@ fn test_empty_patterns() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -376,8 +882,15 @@ fn test_context_lines() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -391,6 +904,37 @@ fn test_context_lines() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -452,8 +996,15 @@ fn test_context_lines_at_file_boundaries() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -467,6 +1018,37 @@ fn test_context_lines_at_file_boundaries() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -504,8 +1086,15 @@ fn test_overlapping_context() -> Result<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         file_extensions: None,
         ignore_patterns: vec![],
         stats_only: false,
@@ -519,6 +1108,37 @@ fn test_overlapping_context() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     let result = search(&config)?;
@@ -544,14 +1164,21 @@ fn test_incremental_search_with_compression() -> Result<()> {
     std::fs::write(&file_path, "pattern_1\npattern_2\n")?;
 
     let cache_path = dir.path().join("cache.json");
-    let config = SearchConfig {
+    let mut config = SearchConfig {
         pattern_definitions: vec![PatternDefinition {
             text: "pattern_\\d+".to_string(),
             is_regex: true,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         ignore_patterns: vec![
             ".git".to_string(),
             ".git/*".to_string(),
@@ -569,6 +1196,37 @@ fn test_incremental_search_with_compression() -> Result<()> {
         max_cache_size: Some(1024 * 1024), // 1MB
         use_compression: true,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     // First search should create compressed cache
@@ -576,10 +1234,18 @@ fn test_incremental_search_with_compression() -> Result<()> {
     assert_eq!(result.total_matches, 2);
     assert!(cache_path.exists());
 
+    assert!(std::fs::read(&cache_path)?.starts_with(&[0x1f, 0x8b]));
+
     // Second search should use compressed cache
     let result = search(&config)?;
     assert_eq!(result.total_matches, 2);
 
+    // Turning compression off keeps the cache and rewrites it as plain JSON
+    config.use_compression = false;
+    let result = search(&config)?;
+    assert_eq!(result.total_matches, 2);
+    assert!(std::fs::read(&cache_path)?.starts_with(b"{"));
+
     Ok(())
 }
 
@@ -596,8 +1262,15 @@ fn test_incremental_search_with_renames() -> Result<()> {
             is_regex: true,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         ignore_patterns: vec![
             ".git".to_string(),
             ".git/*".to_string(),
@@ -615,6 +1288,37 @@ fn test_incremental_search_with_renames() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     // First search should create cache
@@ -645,8 +1349,15 @@ fn test_incremental_search_cache_invalidation() -> Result<()> {
             is_regex: true,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         ignore_patterns: vec![
             ".git".to_string(),
             ".git/*".to_string(),
@@ -664,6 +1375,37 @@ fn test_incremental_search_cache_invalidation() -> Result<()> {
         max_cache_size: Some(1024), // Very small cache
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     // First search should create cache
@@ -683,6 +1425,102 @@ fn test_incremental_search_cache_invalidation() -> Result<()> {
     Ok(())
 }
 
+#[test]
+fn test_invert_match() -> Result<()> {
+    let dir = tempdir()?;
+    std::fs::write(
+        dir.path().join("generated.txt"),
+        "// @generated\nkeep me\n// @generated\nand me\n",
+    )?;
+    std::fs::write(dir.path().join("all_generated.txt"), "// @generated\n")?;
+
+    let cache_path = dir.path().join("cache.json");
+    let mut config = SearchConfig {
+        pattern_definitions: vec![PatternDefinition {
+            text: "@generated".to_string(),
+            is_regex: false,
+            boundary_mode: WordBoundaryMode::None,
+            hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
+        }],
+        root_paths: vec![dir.path().to_path_buf()],
+        ignore_patterns: vec!["cache.json".to_string()],
+        file_extensions: Some(vec!["txt".to_string()]),
+        stats_only: false,
+        thread_count: NonZeroUsize::new(1).unwrap(),
+        log_level: "warn".to_string(),
+        context_before: 1,
+        context_after: 0,
+        incremental: true,
+        cache_path: Some(cache_path.clone()),
+        cache_strategy: ChangeDetectionStrategy::FileSignature,
+        max_cache_size: None,
+        use_compression: false,
+        encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
+    };
+
+    let result = search(&config)?;
+    assert_eq!(result.total_matches, 3);
+    assert_eq!(result.files_with_matches, 2);
+
+    // Switching to an inverted search must not reuse the cached results
+    config.invert_match = true;
+    let result = search(&config)?;
+    assert_eq!(result.total_matches, 2);
+    assert_eq!(result.files_with_matches, 1);
+
+    let file_result = &result.file_results[0];
+    let lines: Vec<(usize, &str)> = file_result
+        .matches
+        .iter()
+        .map(|m| (m.line_number, &m.line_content[m.start..m.end]))
+        .collect();
+    assert_eq!(lines, vec![(2, "keep me"), (4, "and me")]);
+    assert_eq!(
+        file_result.matches[1].context_before,
+        vec![(3, "// @generated".to_string())]
+    );
+
+    Ok(())
+}
+
 #[test]
 fn test_incremental_search_git_strategy() -> Result<()> {
     let dir = tempdir()?;
@@ -718,8 +1556,15 @@ fn test_incremental_search_git_strategy() -> Result<()> {
             is_regex: true,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         // Add comprehensive .git ignore patterns
         ignore_patterns: vec![
             ".git".to_string(),
@@ -738,6 +1583,37 @@ fn test_incremental_search_git_strategy() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     // First search should create cache
@@ -787,8 +1663,15 @@ fn test_incremental_search_corrupt_cache() -> Result<()> {
             is_regex: true,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         ignore_patterns: vec![
             ".git".to_string(),
             ".git/*".to_string(),
@@ -806,6 +1689,37 @@ fn test_incremental_search_corrupt_cache() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     // First search should create cache
@@ -844,8 +1758,15 @@ fn test_incremental_search_concurrent_mods() -> Result<()> {
             is_regex: true,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         }],
-        root_path: dir.path().to_path_buf(),
+        root_paths: vec![dir.path().to_path_buf()],
         ignore_patterns: vec![
             ".git".to_string(),
             ".git/*".to_string(),
@@ -863,6 +1784,37 @@ fn test_incremental_search_concurrent_mods() -> Result<()> {
         max_cache_size: None,
         use_compression: false,
         encoding_mode: EncodingMode::FailFast,
+        invert_match: false,
+        max_count_per_file: None,
+        max_total_matches: None,
+        output_mode: OutputMode::Full,
+        capture_groups: false,
+        include_hidden: false,
+        no_ignore: false,
+        follow_symlinks: false,
+        max_file_size: None,
+        max_depth: None,
+        explicit_paths: None,
+        strict_paths: false,
+        stdin: false,
+        cancellation: None,
+        progress: None,
+        file_types: FileTypes::default(),
+        modified_after: None,
+        modified_before: None,
+        size_range: None,
+        no_rustscoutignore: false,
+        exclude_extensions: None,
+        include_globs: Vec::new(),
+        skip_minified: false,
+        sniff_shebang: true,
+        diff_base: None,
+        search_archives: false,
+        parallel_large_files: true,
+        schedule: Schedule::default(),
+        dedupe_hardlinks: true,
+        memory_budget: None,
+        cache_format: CacheFormat::Json,
     };
 
     // Start search in a separate thread
@@ -925,7 +1877,7 @@ fn test_utf8_handling_fail_fast() -> Result<()> {
     // Create config with fail-fast mode
     let mut config =
         SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
-    config.root_path = dir.path().to_path_buf();
+    config.root_paths = vec![dir.path().to_path_buf()];
     config.encoding_mode = EncodingMode::FailFast;
 
     // Search and verify results
@@ -1012,7 +1964,7 @@ fn test_utf8_handling_lossy() -> Result<()> {
     // Create config with lossy mode
     let mut config =
         SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
-    config.root_path = dir.path().to_path_buf();
+    config.root_paths = vec![dir.path().to_path_buf()];
     config.encoding_mode = EncodingMode::Lossy;
 
     // Search and verify results
@@ -1046,7 +1998,7 @@ fn test_utf8_large_file_performance() -> Result<()> {
     for mode in [EncodingMode::FailFast, EncodingMode::Lossy] {
         let mut config =
             SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
-        config.root_path = dir.path().to_path_buf();
+        config.root_paths = vec![dir.path().to_path_buf()];
         config.encoding_mode = mode;
 
         let start = std::time::Instant::now();
@@ -1081,7 +2033,7 @@ fn test_utf8_mixed_files() -> Result<()> {
     {
         let mut config =
             SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
-        config.root_path = dir.path().to_path_buf();
+        config.root_paths = vec![dir.path().to_path_buf()];
         config.encoding_mode = EncodingMode::FailFast;
 
         let result = search(&config);
@@ -1092,7 +2044,7 @@ fn test_utf8_mixed_files() -> Result<()> {
     {
         let mut config =
             SearchConfig::new_with_pattern("TODO".to_string(), false, WordBoundaryMode::None);
-        config.root_path = dir.path().to_path_buf();
+        config.root_paths = vec![dir.path().to_path_buf()];
         config.encoding_mode = EncodingMode::Lossy;
 
         let result = search(&config)?;
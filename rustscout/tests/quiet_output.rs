@@ -0,0 +1,83 @@
+use std::env;
+use std::fs;
+use std::process::Command;
+use tempfile::TempDir;
+
+use rustscout::{
+    errors::SearchResult,
+    replace::{
+        FileReplacementPlan, ReplacementConfig, ReplacementPattern, ReplacementSet, ReplacementTask,
+    },
+    search::matcher::{PatternDefinition, WordBoundaryMode},
+    workspace::init_workspace,
+};
+
+/// Set in the child process that runs the replacement
+const CHILD_ENV: &str = "RUSTSCOUT_QUIET_OUTPUT_CHILD";
+
+const START: &str = "<<<replace output>>>";
+const END: &str = "<<<end of replace output>>>";
+
+/// Makes a backed-up replacement and undoes it, between two markers
+fn replace_and_undo() -> SearchResult<()> {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    init_workspace(root, "json")?;
+    let file = root.join("notes.txt");
+    fs::write(&file, "old notes\n")?;
+
+    let config = ReplacementConfig {
+        patterns: vec![ReplacementPattern {
+            definition: PatternDefinition::new("old".to_string(), false, WordBoundaryMode::None),
+            replacement_text: "new".to_string(),
+            preserve_case: false,
+        }],
+        backup_enabled: true,
+        undo_dir: root.join(".rustscout").join("undo"),
+        ..ReplacementConfig::default()
+    };
+    let mut set = ReplacementSet::new(config.clone());
+    let mut plan = FileReplacementPlan::new(file.clone())?;
+    plan.add_replacement(ReplacementTask::new(
+        file.clone(),
+        (0, 3),
+        "new".to_string(),
+        0,
+        config.clone(),
+    ))?;
+    set.add_plan(plan);
+
+    println!("{}", START);
+    set.apply()?;
+    let operations = ReplacementSet::list_undo_operations(&config)?;
+    ReplacementSet::undo_by_id(operations[0].0.timestamp, &config)?;
+    println!("{}", END);
+
+    assert_eq!(fs::read_to_string(&file)?, "old notes\n");
+    Ok(())
+}
+
+/// The test harness captures `println!` in-process, so the replacement runs
+/// in a child process whose real stdout is checked instead
+#[test]
+fn test_backed_up_replace_prints_nothing() -> SearchResult<()> {
+    if env::var_os(CHILD_ENV).is_some() {
+        return replace_and_undo();
+    }
+
+    let output = Command::new(env::current_exe()?)
+        .args([
+            "test_backed_up_replace_prints_nothing",
+            "--exact",
+            "--nocapture",
+        ])
+        .env(CHILD_ENV, "1")
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "child failed:\n{}", stdout);
+
+    let start = stdout.find(START).expect("start marker") + START.len();
+    let end = stdout.find(END).expect("end marker");
+    assert_eq!(stdout[start..end].trim(), "");
+    Ok(())
+}
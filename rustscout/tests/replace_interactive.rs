@@ -0,0 +1,101 @@
+use std::fs;
+use std::io::Cursor;
+use std::path::Path;
+use tempfile::TempDir;
+
+use rustscout::{
+    config::SearchConfig,
+    errors::SearchResult,
+    replace::{
+        approve_replacements, FileReplacementPlan, ReplacementConfig, ReplacementPattern,
+        ReplacementSet, ReplacementTask,
+    },
+    search::matcher::{PatternDefinition, WordBoundaryMode},
+    workspace::init_workspace,
+};
+
+/// A plan replacing every "old" in `path` with "new"
+fn plan_for(path: &Path, config: &ReplacementConfig) -> SearchResult<FileReplacementPlan> {
+    let content = fs::read_to_string(path)?;
+    let mut plan = FileReplacementPlan::new(path.to_path_buf())?;
+    for (start, _) in content.match_indices("old") {
+        plan.add_replacement(ReplacementTask::new(
+            path.to_path_buf(),
+            (start, start + 3),
+            "new".to_string(),
+            0,
+            config.clone(),
+        ))?;
+    }
+    Ok(plan)
+}
+
+#[test]
+fn test_scripted_approval() -> SearchResult<()> {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path();
+    init_workspace(root, "json")?;
+
+    let config = ReplacementConfig {
+        patterns: vec![ReplacementPattern {
+            definition: PatternDefinition::new("old".to_string(), false, WordBoundaryMode::None),
+            replacement_text: "new".to_string(),
+            preserve_case: false,
+        }],
+        backup_enabled: true,
+        undo_dir: root.join(".rustscout").join("undo"),
+        thread_count: SearchConfig::default_thread_count(),
+        ..ReplacementConfig::default()
+    };
+    let files = ["a.txt", "b.txt", "c.txt"].map(|name| root.join(name));
+    fs::write(&files[0], "old one\nold two\nold three\n")?;
+    fs::write(&files[1], "old four\nold five\n")?;
+    fs::write(&files[2], "old six\n")?;
+
+    let mut set = ReplacementSet::new(config.clone());
+    for file in &files {
+        set.add_plan(plan_for(file, &config)?);
+    }
+
+    // Yes, an unknown answer asked again, no, yes; then skip the rest of
+    // b.txt and quit before c.txt
+    let mut prompts = Vec::new();
+    let approved = approve_replacements(
+        &mut set.plans,
+        Cursor::new("y\nmaybe\nn\ny\nf\nq\n"),
+        &mut prompts,
+    )?;
+    assert_eq!(approved, 2);
+    assert_eq!(set.plans.len(), 1);
+
+    let prompts = String::from_utf8(prompts).unwrap();
+    assert!(prompts.contains(&format!("{}:2\n- old two\n+ new two\n", files[0].display())));
+    assert_eq!(prompts.matches("Replace?").count(), 6);
+
+    let backups = set.apply_with_progress()?;
+    assert_eq!(
+        fs::read_to_string(&files[0])?,
+        "new one\nold two\nnew three\n"
+    );
+    assert_eq!(fs::read_to_string(&files[1])?, "old four\nold five\n");
+    assert_eq!(fs::read_to_string(&files[2])?, "old six\n");
+    // Only the file with approved changes was backed up
+    assert_eq!(backups.len(), 1);
+
+    // "all" approves everything left, in every file
+    let mut set = ReplacementSet::new(config.clone());
+    for file in &files {
+        set.add_plan(plan_for(file, &config)?);
+    }
+    let approved = approve_replacements(&mut set.plans, Cursor::new("n\na\n"), Vec::new())?;
+    assert_eq!(approved, 3);
+    // a.txt was left with nothing to replace
+    assert_eq!(set.plans.len(), 2);
+
+    // Running out of input stops asking and keeps nothing more
+    let mut set = ReplacementSet::new(config.clone());
+    set.add_plan(plan_for(&files[1], &config)?);
+    let approved = approve_replacements(&mut set.plans, Cursor::new("y\n"), Vec::new())?;
+    assert_eq!(approved, 1);
+    Ok(())
+}
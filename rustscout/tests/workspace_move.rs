@@ -3,11 +3,12 @@ use std::path::PathBuf;
 use tempfile::TempDir;
 
 use rustscout::{
+    config::{EncodingMode, SearchConfig},
     errors::SearchResult,
     replace::{
         FileReplacementPlan, ReplacementConfig, ReplacementPattern, ReplacementSet, ReplacementTask,
     },
-    search::matcher::{HyphenMode, PatternDefinition, WordBoundaryMode},
+    search::matcher::{CaseMode, HyphenMode, PatternDefinition, WordBoundaryMode},
     workspace::init_workspace,
 };
 
@@ -37,8 +38,16 @@ fn test_workspace_move() -> SearchResult<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         },
         replacement_text: "changed".to_string(),
+        preserve_case: false,
     };
 
     let config = ReplacementConfig {
@@ -48,6 +57,12 @@ fn test_workspace_move() -> SearchResult<()> {
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: initial_root.join(".rustscout").join("undo"),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     // Create and apply replacement
@@ -133,8 +148,16 @@ fn test_workspace_move_multi_crate() -> SearchResult<()> {
             is_regex: false,
             boundary_mode: WordBoundaryMode::None,
             hyphen_mode: HyphenMode::default(),
+            case_mode: CaseMode::default(),
+            multiline: false,
+            match_whole_line: false,
+            trim_whole_line: false,
+            allow_overlapping: false,
+            fuzzy_distance: None,
+            name: None,
         },
         replacement_text: "changed".to_string(),
+        preserve_case: false,
     };
 
     let config = ReplacementConfig {
@@ -144,6 +167,12 @@ fn test_workspace_move_multi_crate() -> SearchResult<()> {
         backup_dir: None,
         preserve_metadata: true,
         undo_dir: initial_root.join(".rustscout").join("undo"),
+        encoding_mode: EncodingMode::FailFast,
+        thread_count: SearchConfig::default_thread_count(),
+        max_replacements_per_file: None,
+        first_match_per_line: false,
+        no_rollback: false,
+        compress_backups: false,
     };
 
     // Create and apply replacements for both files
@@ -218,3 +247,70 @@ fn test_workspace_move_multi_crate() -> SearchResult<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_workspace_move_with_custom_backup_dir() -> SearchResult<()> {
+    let temp = TempDir::new().unwrap();
+    let initial_root = temp.path().join("workspace");
+    fs::create_dir_all(&initial_root)?;
+    init_workspace(&initial_root, "json")?;
+    let backup_temp = TempDir::new().unwrap();
+    let backup_dir = backup_temp.path().join("rustscout");
+    let test_file = initial_root.join("test.txt");
+    fs::write(&test_file, "original content")?;
+
+    let config = ReplacementConfig {
+        patterns: vec![ReplacementPattern {
+            definition: PatternDefinition::new(
+                "original".to_string(),
+                false,
+                WordBoundaryMode::None,
+            ),
+            replacement_text: "changed".to_string(),
+            preserve_case: false,
+        }],
+        backup_enabled: true,
+        backup_dir: Some(backup_dir.clone()),
+        undo_dir: initial_root.join(".rustscout").join("undo"),
+        ..ReplacementConfig::default()
+    };
+    let mut replacement_set = ReplacementSet::new(config.clone());
+    let mut plan = FileReplacementPlan::new(test_file.clone())?;
+    plan.add_replacement(ReplacementTask::new(
+        test_file.clone(),
+        (0, 8),
+        "changed".to_string(),
+        0,
+        config.clone(),
+    ))?;
+    replacement_set.add_plan(plan);
+    replacement_set.apply()?;
+    assert_eq!(fs::read_dir(&backup_dir)?.count(), 1);
+
+    // Move the workspace, leaving the backups where they are
+    let new_location = temp.path().join("moved_workspace");
+    fs::rename(&initial_root, &new_location)?;
+    let moved_config = ReplacementConfig {
+        undo_dir: new_location.join(".rustscout").join("undo"),
+        ..config
+    };
+
+    // The record knows the backup directory is outside the workspace. Its
+    // absolute backup paths are made stale, as if the backup volume had
+    // been mounted somewhere else when they were recorded.
+    let (mut info, record) = ReplacementSet::list_undo_operations(&moved_config)?.remove(0);
+    assert_eq!(info.backup_root, Some(backup_dir.canonicalize()?));
+    assert!(!info.backup_root_in_workspace);
+    for (_, backup) in &mut info.backups {
+        backup.abs_path = Some(initial_root.join("gone").join(&backup.rel_path));
+    }
+    fs::write(&record, serde_json::to_string_pretty(&info)?)?;
+
+    ReplacementSet::undo_by_id(info.timestamp, &moved_config)?;
+    assert_eq!(
+        fs::read_to_string(new_location.join("test.txt"))?,
+        "original content"
+    );
+    assert_eq!(fs::read_dir(&backup_dir)?.count(), 0);
+    Ok(())
+}